@@ -11,15 +11,22 @@ pub mod staking;
 use {
     crate::api::{
         query_server::query_api, submission_server::submission_api::SubmissionApi,
+        unix_socket::UnixSocket,
     },
     config::abci::{global_cfg::CFG, ABCIConfig},
     futures::executor::ThreadPool,
     lazy_static::lazy_static,
-    ledger::converter::{LOWLEVEL_DATA_MAX, LOWLEVEL_DATA_MIN},
+    ledger::{
+        converter::{LOWLEVEL_DATA_MAX, LOWLEVEL_DATA_MIN},
+        store::{api_cache, audit, snapshot, LedgerState},
+    },
+    parking_lot::RwLock,
     ruc::*,
     std::{
-        env, fs, mem,
+        env, fs, io, mem,
         net::SocketAddr,
+        path::Path,
+        process,
         sync::{
             atomic::{AtomicBool, Ordering},
             Arc,
@@ -38,6 +45,98 @@ lazy_static! {
     pub static ref IS_EXITING: AtomicBool = AtomicBool::new(false);
 }
 
+/// Serve the query api from a local ledger following a primary node,
+/// neither consensus nor the submission api is run
+fn run_query_replica(basedir: &str, config: &ABCIConfig, primary: String) -> Result<()> {
+    let mut ledger = LedgerState::load_or_init(basedir).c(d!())?;
+    if let Some(path) = CFG.api_cache_snapshot.as_ref() {
+        info_omit!(api_cache::load_snapshot(&mut ledger, Path::new(path)));
+    }
+    let ledger = Arc::new(RwLock::new(ledger));
+    query_api::replica::IS_REPLICA.store(true, Ordering::Release);
+
+    query_api::service::start_query_server(
+        Arc::clone(&ledger),
+        &[
+            (&config.abci_host, config.query_port),
+            (&config.abci_host, config.ledger_port),
+        ],
+        &query_api::QueryApiConfig::from_env().c(d!())?,
+    )
+    .c(d!())?
+    .write()
+    .update();
+
+    let hdr = Arc::clone(&ledger);
+    thread::spawn(move || info_omit!(api_cache::recover_lost_data(&hdr)));
+    query_api::replica::start_sync(ledger, primary);
+
+    loop {
+        thread::park();
+    }
+}
+
+/// Cross-check the api cache against the ledger, the node must be stopped,
+/// returns whether the cache is consistent once done
+fn verify_api_cache(basedir: &str, repair: bool) -> Result<bool> {
+    let mut ledger = LedgerState::load_or_init(basedir).c(d!())?;
+    let report = api_cache::verify_api_cache(&mut ledger, repair).c(d!())?;
+    println!("{}", serde_json::to_string_pretty(&report).c(d!())?);
+    Ok(report.is_consistent() || repair)
+}
+
+/// Write a snapshot of the ledger state into `dir`, the node must be stopped
+fn take_ledger_snapshot(basedir: &str, dir: &str) -> Result<()> {
+    let mut ledger = LedgerState::load_or_init(basedir).c(d!())?;
+    let s = ledger.take_snapshot(basedir, None).c(d!())?;
+    let path = snapshot::write_archive(&s, dir).c(d!())?;
+    println!("height {}: {}", s.height, path.display());
+    Ok(())
+}
+
+/// Restore the ledger state of a fresh node from a snapshot archive
+fn restore_ledger_snapshot(basedir: &str, archive: &str) -> Result<()> {
+    let s = snapshot::read_archive(Path::new(archive)).c(d!())?;
+    let ledger = LedgerState::restore_snapshot(basedir, None, s).c(d!())?;
+    println!("restored at height {}", ledger.get_tendermint_height());
+    Ok(())
+}
+
+/// Replay a block export stream on top of the ledger, the node must be stopped,
+/// returns whether every state commitment matched the exported one
+fn replay_blocks(basedir: &str, path: &str) -> Result<bool> {
+    let mut ledger = LedgerState::load_or_init(basedir).c(d!())?;
+    let file = fs::File::open(path).c(d!(path.to_owned()))?;
+    let report = ledger
+        .replay_blocks(io::BufReader::new(file), |sid| {
+            if 0 == sid % 1000 {
+                println!("replayed block {}", sid);
+            }
+        })
+        .c(d!())?;
+    println!("{}", serde_json::to_string_pretty(&report).c(d!())?);
+    Ok(report.divergence.is_none())
+}
+
+/// Audit the replay protection of the committed txns, or of the ones of a block
+/// export stream if `path` is given, returns whether no anomaly was found
+fn audit_replay(basedir: &str, path: Option<&str>) -> Result<bool> {
+    let on_block = |sid: usize| {
+        if 0 == sid % 1000 {
+            println!("audited block {}", sid);
+        }
+    };
+    let report = if let Some(path) = path {
+        let file = fs::File::open(path).c(d!(path.to_owned()))?;
+        audit::audit_replay_stream(io::BufReader::new(file), on_block).c(d!())?
+    } else {
+        let ledger = LedgerState::load_or_init(basedir).c(d!())?;
+        ledger.audit_replay(on_block).c(d!())?
+    };
+    println!("{}", serde_json::to_string_pretty(&report).c(d!())?);
+    Ok(report.anomalies.is_empty())
+}
+
 /// Starting findorad
 pub fn run() -> Result<()> {
     let basedir = {
@@ -52,10 +151,46 @@ pub fn run() -> Result<()> {
 
     LOWLEVEL_DATA_MIN.swap(CFG.checkpoint.lowlevel_data_min as i64, Ordering::Relaxed);
     LOWLEVEL_DATA_MAX.swap(CFG.checkpoint.lowlevel_data_max as i64, Ordering::Relaxed);
-    if CFG.enable_query_service {
+    // fail early on a bad index list, it is parsed lazily by the api cache
+    api_cache::ApiCacheIndexes::from_cfg().c(d!())?;
+
+    // a validator may keep some api cache indexes without the query service
+    if CFG.enable_query_service
+        || CFG.query_replica_of.is_some()
+        || CFG.api_cache_verify
+        || CFG.api_cache_indexes.is_some()
+    {
         env::set_var("FINDORAD_KEEP_HIST", "1");
     }
 
+    if CFG.api_cache_verify {
+        // nothing else is started, exit once done
+        let ok = verify_api_cache(&CFG.ledger_dir, CFG.api_cache_repair).c(d!())?;
+        process::exit(alt!(ok, 0, 1));
+    }
+
+    if let Some(dir) = CFG.ledger_snapshot_dir.as_ref() {
+        take_ledger_snapshot(&CFG.ledger_dir, dir).c(d!())?;
+        process::exit(0);
+    }
+    if let Some(archive) = CFG.ledger_snapshot_restore.as_ref() {
+        restore_ledger_snapshot(&CFG.ledger_dir, archive).c(d!())?;
+        process::exit(0);
+    }
+    if let Some(path) = CFG.ledger_replay.as_ref() {
+        let ok = replay_blocks(&CFG.ledger_dir, path).c(d!())?;
+        process::exit(alt!(ok, 0, 1));
+    }
+    if CFG.ledger_audit_replay || CFG.ledger_audit_replay_stream.is_some() {
+        let path = CFG.ledger_audit_replay_stream.as_deref();
+        let ok = audit_replay(&CFG.ledger_dir, path).c(d!())?;
+        process::exit(alt!(ok, 0, 1));
+    }
+
+    if let Some(primary) = CFG.query_replica_of.clone() {
+        return run_query_replica(&CFG.ledger_dir, &config, primary);
+    }
+
     let app = server::ABCISubmissionServer::new(
         basedir,
         format!("{}:{}", config.tendermint_host, config.tendermint_port),
@@ -66,23 +201,41 @@ pub fn run() -> Result<()> {
         let submission_service_hdr = Arc::clone(&app.la);
 
         let query_service_hdr = submission_service_hdr.read().borrowable_ledger_state();
+        if let Some(path) = CFG.api_cache_snapshot.as_ref() {
+            // a non-empty cache is kept, so the flag is harmless on restarts
+            info_omit!(api_cache::load_snapshot(
+                &mut query_service_hdr.write(),
+                Path::new(path)
+            ));
+        }
         pnk!(query_api::service::start_query_server(
             Arc::clone(&query_service_hdr),
             &[
                 (&config.abci_host, config.query_port),
                 (&config.abci_host, config.ledger_port)
             ],
+            &pnk!(query_api::QueryApiConfig::from_env()),
         ))
         .write()
         .update();
 
+        // catch up in the background, instead of on the first commit
+        thread::spawn(move || {
+            info_omit!(api_cache::recover_lost_data(&query_service_hdr))
+        });
+
         let submission_host = config.abci_host.clone();
         let submission_port = config.submission_port;
+        let submission_socket = UnixSocket::new(
+            CFG.submission_unix_socket.as_deref(),
+            CFG.submission_unix_socket_mode,
+        );
         thread::spawn(move || {
             pnk!(SubmissionApi::create(
                 submission_service_hdr,
                 &submission_host,
                 submission_port,
+                submission_socket.as_ref(),
             ));
         });
     }
@@ -650,6 +650,9 @@ pub fn commit(s: &mut ABCISubmissionServer, req: &RequestCommit) -> ResponseComm
     // will change `struct LedgerStatus`
     let td_height = TENDERMINT_BLOCK_HEIGHT.load(Ordering::Relaxed);
     state.set_tendermint_height(td_height as u64);
+    if let Some(header) = REQ_BEGIN_BLOCK.lock().header.as_ref() {
+        state.set_tendermint_time(header.get_time().get_seconds());
+    }
 
     // cache last block for QueryServer
     pnk!(api_cache::update_api_cache(&mut state));
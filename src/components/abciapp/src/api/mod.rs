@@ -7,3 +7,6 @@ pub mod query_server;
 
 /// Provide services for operating transactions
 pub mod submission_server;
+
+/// Serve the apis over Unix domain sockets
+pub mod unix_socket;
@@ -0,0 +1,104 @@
+//!
+//! Governance proposals of the ledger, from `ApiCache::governance_proposals`,
+//! so that their history can be shown without replaying the chain.
+//!
+
+use {
+    super::{
+        server::QueryServer, state_etag, with_etag, ApiVersion, Cached, PageQueryParams,
+        PagedJson,
+    },
+    actix_web::{error, web, HttpRequest},
+    globutils::wallet,
+    ledger::{
+        data_model::TxnSID,
+        staking::{ops::governance::ByzantineKind, BlockHeight},
+        store::api_cache::GovernanceProposal,
+    },
+    parking_lot::RwLock,
+    schemars::JsonSchema,
+    serde::{Deserialize, Serialize},
+    std::sync::Arc,
+};
+
+/// A governance proposal, with its keys in base64
+#[allow(missing_docs)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct ProposalInfo {
+    pub id: u64,
+    pub txn_sid: TxnSID,
+    pub txn_hash: String,
+    pub height: BlockHeight,
+    /// the co-signers of the proposal
+    pub proposers: Vec<String>,
+    #[schemars(with = "serde_json::Value")]
+    pub kind: ByzantineKind,
+    /// the penalized validator
+    pub byzantine_id: String,
+    pub custom_percent: Option<[u64; 2]>,
+    /// the custom percent, or the one of the rule of `kind`
+    pub penalty_percent: Option<[u64; 2]>,
+}
+
+impl From<GovernanceProposal> for ProposalInfo {
+    fn from(p: GovernanceProposal) -> Self {
+        ProposalInfo {
+            id: p.id,
+            txn_sid: p.txn_sid,
+            txn_hash: p.txn_hash,
+            height: p.height,
+            proposers: p
+                .proposers
+                .iter()
+                .map(wallet::public_key_to_base64)
+                .collect(),
+            kind: p.payload.kind().clone(),
+            byzantine_id: wallet::public_key_to_base64(p.payload.byzantine_id()),
+            custom_percent: p.payload.custom_percent(),
+            penalty_percent: p.payload.penalty_percent(),
+        }
+    }
+}
+
+/// Returns the governance proposals, by id
+pub async fn get_governance_proposals(
+    req: HttpRequest,
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    web::Query(paging): web::Query<PageQueryParams>,
+    version: web::Data<ApiVersion>,
+) -> actix_web::Result<Cached<PagedJson<ProposalInfo, Vec<ProposalInfo>>>> {
+    let server = data.read();
+    with_etag(&req, state_etag(&server), || {
+        let proposals = server
+            .ledger_cloned
+            .api_cache
+            .as_ref()
+            .map(|api| {
+                api.governance_proposals
+                    .iter()
+                    .map(|(_, p)| ProposalInfo::from(p))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        paging.respond(*version, proposals, |proposals| proposals)
+    })
+}
+
+/// Returns a governance proposal by id
+pub async fn get_governance_proposal(
+    req: HttpRequest,
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    id: web::Path<u64>,
+) -> actix_web::Result<Cached<web::Json<ProposalInfo>>> {
+    let id = id.into_inner();
+    let server = data.read();
+    with_etag(&req, state_etag(&server), || {
+        server
+            .ledger_cloned
+            .api_cache
+            .as_ref()
+            .and_then(|api| api.governance_proposals.get(&id))
+            .map(|p| web::Json(ProposalInfo::from(p)))
+            .ok_or_else(|| error::ErrorNotFound("No governance proposal of this id."))
+    })
+}
@@ -0,0 +1,386 @@
+//!
+//! GraphQL view of the query server cache,
+//! transactions, txos, assets and addresses with nested resolvers
+//!
+
+use {
+    super::{operation_detail, server::QueryServer},
+    actix_web::web,
+    async_graphql::{Context, EmptyMutation, EmptySubscription, Object, Result, Schema},
+    async_graphql_actix_web::{Request, Response},
+    globutils::wallet,
+    ledger::data_model::{
+        AssetTypeCode, DefineAsset, IssuerPublicKey, TxOutput, TxnSID, TxoSID,
+        XfrAddress,
+    },
+    ledger::store::api_cache::get_related_addresses,
+    parking_lot::RwLock,
+    std::sync::Arc,
+};
+
+/// Max number of items of a list field
+const MAX_LIST_LEN: usize = 1000;
+
+/// Max nesting of the fields of a query, the resolvers being cyclic
+const MAX_DEPTH: usize = 8;
+
+/// Max count of the fields of a query
+const MAX_COMPLEXITY: usize = 500;
+
+/// Schema served at `/graphql`
+pub type QuerySchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+/// Build the schema over a query server
+pub fn schema(server: Arc<RwLock<QueryServer>>) -> QuerySchema {
+    Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .data(server)
+        .limit_depth(MAX_DEPTH)
+        .limit_complexity(MAX_COMPLEXITY)
+        .finish()
+}
+
+/// Execute a GraphQL request
+pub async fn graphql(schema: web::Data<QuerySchema>, req: Request) -> Response {
+    schema.execute(req.into_inner()).await.into()
+}
+
+fn server<'a>(ctx: &Context<'a>) -> &'a Arc<RwLock<QueryServer>> {
+    ctx.data_unchecked::<Arc<RwLock<QueryServer>>>()
+}
+
+fn parse_address(address: &str) -> Result<XfrAddress> {
+    wallet::public_key_from_base64(address)
+        .or_else(|_| wallet::public_key_from_bech32(address))
+        .map(|key| XfrAddress { key })
+        .map_err(|e| e.to_string().into())
+}
+
+#[allow(missing_docs)]
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// A transaction by sid or hash
+    async fn transaction(
+        &self,
+        ctx: &Context<'_>,
+        sid: Option<u64>,
+        hash: Option<String>,
+    ) -> Option<Transaction> {
+        let server = server(ctx).read();
+        let sid = match (sid, hash) {
+            (Some(sid), _) => TxnSID(sid as usize),
+            (None, Some(hash)) => server.get_transaction_sid(hash.to_uppercase())?,
+            (None, None) => return None,
+        };
+        server.get_transaction_hash(sid).map(|_| Transaction(sid))
+    }
+
+    /// A txo by sid
+    async fn txo(&self, ctx: &Context<'_>, sid: u64) -> Option<Txo> {
+        server(ctx)
+            .read()
+            .get_authenticated_txnid(TxoSID(sid))
+            .map(|_| Txo(TxoSID(sid)))
+    }
+
+    /// An asset by its base64 code
+    async fn asset(&self, ctx: &Context<'_>, code: String) -> Result<Option<Asset>> {
+        let code = AssetTypeCode::new_from_base64(&code).map_err(|e| e.to_string())?;
+        Ok(server(ctx)
+            .read()
+            .ledger_cloned
+            .get_asset_type(&code)
+            .map(|_| Asset(code)))
+    }
+
+    /// An address in base64 or bech32 form
+    async fn address(&self, address: String) -> Result<Address> {
+        parse_address(&address).map(Address)
+    }
+}
+
+/// A finalized transaction
+pub struct Transaction(TxnSID);
+
+#[Object]
+impl Transaction {
+    async fn sid(&self) -> u64 {
+        self.0 .0 as u64
+    }
+
+    async fn hash(&self, ctx: &Context<'_>) -> Option<String> {
+        server(ctx).read().get_transaction_hash(self.0)
+    }
+
+    /// Ledger block containing the transaction
+    async fn block(&self, ctx: &Context<'_>) -> Option<u64> {
+        server(ctx)
+            .read()
+            .ledger_cloned
+            .tx_to_block_location
+            .get(&self.0)
+            .map(|[block_idx, _]| block_idx as u64)
+    }
+
+    /// Tendermint height of the block containing the transaction
+    async fn height(&self, ctx: &Context<'_>) -> Option<u64> {
+        server(ctx).read().get_transaction_height(self.0)
+    }
+
+    /// Unix time of the block containing the transaction
+    async fn time(&self, ctx: &Context<'_>) -> Option<i64> {
+        let server = server(ctx).read();
+        server
+            .get_transaction_height(self.0)
+            .and_then(|h| server.get_block_time(h))
+    }
+
+    /// Names of the operations, in order
+    async fn operations(&self, ctx: &Context<'_>) -> Vec<String> {
+        server(ctx)
+            .read()
+            .ledger_cloned
+            .get_transaction_light(self.0)
+            .map(|ftx| {
+                ftx.txn
+                    .body
+                    .operations
+                    .iter()
+                    .map(|op| operation_detail(op).op_type)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Txos created by the transaction
+    async fn txos(&self, ctx: &Context<'_>) -> Vec<Txo> {
+        server(ctx)
+            .read()
+            .ledger_cloned
+            .get_transaction_light(self.0)
+            .map(|ftx| ftx.txo_ids.into_iter().map(Txo).collect())
+            .unwrap_or_default()
+    }
+
+    /// Addresses involved in the transaction
+    async fn addresses(&self, ctx: &Context<'_>) -> Vec<Address> {
+        server(ctx)
+            .read()
+            .ledger_cloned
+            .get_transaction_light(self.0)
+            .map(|ftx| {
+                get_related_addresses(&ftx.txn, |_| {})
+                    .into_iter()
+                    .map(Address)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// A transaction output, spent or not
+pub struct Txo(TxoSID);
+
+impl Txo {
+    /// Output of the creating transaction
+    fn output(&self, server: &QueryServer) -> Option<TxOutput> {
+        let (txn_sid, _) = server.get_authenticated_txnid(self.0)?;
+        let ftx = server.ledger_cloned.get_transaction_light(txn_sid).ok()?;
+        let idx = ftx.txo_ids.iter().position(|sid| *sid == self.0)?;
+        ftx.txn.get_outputs_ref(false).into_iter().nth(idx)
+    }
+}
+
+#[Object]
+impl Txo {
+    async fn sid(&self) -> u64 {
+        self.0 .0
+    }
+
+    async fn owner(&self, ctx: &Context<'_>) -> Option<Address> {
+        server(ctx).read().get_address_of_sid(self.0).map(Address)
+    }
+
+    /// Amount, null if confidential
+    async fn amount(&self, ctx: &Context<'_>) -> Option<u64> {
+        self.output(&server(ctx).read())
+            .and_then(|o| o.record.amount.get_amount())
+    }
+
+    /// Asset of the txo, null if the type is confidential
+    async fn asset(&self, ctx: &Context<'_>) -> Option<Asset> {
+        self.output(&server(ctx).read())
+            .and_then(|o| o.record.asset_type.get_asset_type())
+            .map(|val| Asset(AssetTypeCode { val }))
+    }
+
+    async fn spent(&self, ctx: &Context<'_>) -> bool {
+        server(ctx)
+            .read()
+            .ledger_cloned
+            .get_utxo_light(self.0)
+            .is_none()
+    }
+
+    /// Transaction spending the txo, null if unspent
+    async fn spent_by(&self, ctx: &Context<'_>) -> Option<Transaction> {
+        server(ctx)
+            .read()
+            .get_spent_utxo(self.0)
+            .map(|(sid, _)| Transaction(sid))
+    }
+
+    /// Transaction creating the txo
+    async fn transaction(&self, ctx: &Context<'_>) -> Option<Transaction> {
+        server(ctx)
+            .read()
+            .get_authenticated_txnid(self.0)
+            .map(|(sid, _)| Transaction(sid))
+    }
+}
+
+/// A defined asset
+pub struct Asset(AssetTypeCode);
+
+#[Object]
+impl Asset {
+    async fn code(&self) -> String {
+        self.0.to_base64()
+    }
+
+    async fn issuer(&self, ctx: &Context<'_>) -> Option<Address> {
+        server(ctx)
+            .read()
+            .ledger_cloned
+            .get_asset_type(&self.0)
+            .map(|a| {
+                Address(XfrAddress {
+                    key: a.properties.issuer.key,
+                })
+            })
+    }
+
+    async fn memo(&self, ctx: &Context<'_>) -> Option<String> {
+        server(ctx)
+            .read()
+            .ledger_cloned
+            .get_asset_type(&self.0)
+            .map(|a| a.properties.memo.0)
+    }
+
+    async fn decimals(&self, ctx: &Context<'_>) -> Option<u8> {
+        server(ctx)
+            .read()
+            .ledger_cloned
+            .get_asset_type(&self.0)
+            .map(|a| a.properties.asset_rules.decimals)
+    }
+
+    async fn max_units(&self, ctx: &Context<'_>) -> Option<u64> {
+        server(ctx)
+            .read()
+            .ledger_cloned
+            .get_asset_type(&self.0)
+            .and_then(|a| a.properties.asset_rules.max_units)
+    }
+
+    async fn transferable(&self, ctx: &Context<'_>) -> Option<bool> {
+        server(ctx)
+            .read()
+            .ledger_cloned
+            .get_asset_type(&self.0)
+            .map(|a| a.properties.asset_rules.transferable)
+    }
+}
+
+/// An account address
+pub struct Address(XfrAddress);
+
+#[Object]
+impl Address {
+    /// Base64 form of the address
+    async fn address(&self) -> String {
+        wallet::public_key_to_base64(&self.0.key)
+    }
+
+    async fn bech32(&self) -> String {
+        wallet::public_key_to_bech32(&self.0.key)
+    }
+
+    /// Unspent txos owned by the address
+    async fn utxos(&self, ctx: &Context<'_>) -> Vec<Txo> {
+        server(ctx)
+            .read()
+            .ledger_cloned
+            .get_owned_utxos(&self.0.key)
+            .map(|utxos| utxos.into_keys().take(MAX_LIST_LEN).map(Txo).collect())
+            .unwrap_or_default()
+    }
+
+    /// Related transactions, newest first
+    async fn transactions(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default = 0)] offset: usize,
+        #[graphql(default = 20)] limit: usize,
+    ) -> Vec<Transaction> {
+        let mut sids = server(ctx)
+            .read()
+            .get_related_transactions(&self.0)
+            .unwrap_or_default()
+            .into_iter()
+            .collect::<Vec<_>>();
+        sids.sort_unstable_by(|a, b| b.cmp(a));
+        sids.into_iter()
+            .skip(offset)
+            .take(limit.min(MAX_LIST_LEN))
+            .map(Transaction)
+            .collect()
+    }
+
+    /// Assets defined by the address
+    async fn created_assets(&self, ctx: &Context<'_>) -> Vec<Asset> {
+        server(ctx)
+            .read()
+            .get_created_assets(&IssuerPublicKey { key: self.0.key })
+            .unwrap_or_default()
+            .into_iter()
+            .map(|d: DefineAsset| Asset(d.body.asset.code))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*, futures::executor::block_on, ledger::store::LedgerState,
+        rand_chacha::ChaChaRng, rand_core::SeedableRng, zei::XfrKeyPair,
+    };
+
+    #[test]
+    fn query_limits() {
+        let ledger = Arc::new(RwLock::new(LedgerState::tmp_ledger()));
+        let schema = schema(Arc::new(RwLock::new(QueryServer::new(ledger))));
+        let kp = XfrKeyPair::generate(&mut ChaChaRng::from_entropy());
+        let address = wallet::public_key_to_base64(kp.get_pk_ref());
+
+        let query = |fields: &str| {
+            format!("{{ address(address: \"{address}\") {{ {fields} }} }}")
+        };
+        assert!(block_on(schema.execute(query("address"))).is_ok());
+
+        // address -> utxos -> transaction -> addresses -> utxos -> ...
+        let mut fields = "address".to_owned();
+        for _ in 0..MAX_DEPTH {
+            fields = format!("utxos {{ transaction {{ addresses {{ {fields} }} }} }}");
+        }
+        assert!(block_on(schema.execute(query(&fields))).is_err());
+
+        let fields = (0..MAX_COMPLEXITY)
+            .map(|i| format!("a{i}: address"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        assert!(block_on(schema.execute(query(&fields))).is_err());
+    }
+}
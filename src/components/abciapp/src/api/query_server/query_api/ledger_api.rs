@@ -3,8 +3,8 @@
 //!
 
 use {
-    super::server::QueryServer,
-    actix_web::{error, web},
+    super::{replica, server::QueryServer, with_etag, Cached},
+    actix_web::{error, web, HttpRequest},
     config::abci::global_cfg::CFG,
     finutils::api::{
         DelegationInfo, DelegatorInfo, DelegatorList, NetworkRoute, Validator,
@@ -14,8 +14,8 @@ use {
     ledger::{
         data_model::{
             ABARData, ATxoSID, AssetType, AssetTypeCode, AssetTypePrefix,
-            AuthenticatedUtxo, StateCommitmentData, TxnSID, TxoSID, UnAuthenticatedUtxo,
-            Utxo,
+            AuthenticatedUtxo, AuthenticatedUtxoStatus, StateCommitmentData, TxnSID,
+            TxoSID, UnAuthenticatedUtxo, Utxo,
         },
         staking::{
             DelegationRwdDetail, DelegationState, Staking, TendermintAddr,
@@ -57,6 +57,20 @@ pub async fn query_utxo(
     }
 }
 
+/// query the status of a utxo at a past state commitment version,
+/// along with the utxo map proving it against that version
+pub async fn query_utxo_status_at_version(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    info: web::Path<(u64, u64)>,
+) -> actix_web::Result<web::Json<AuthenticatedUtxoStatus>> {
+    let (txo_sid, version) = info.into_inner();
+    let qs = data.read();
+    qs.ledger_cloned
+        .get_utxo_status_at_version(TxoSID(txo_sid), version)
+        .map(web::Json)
+        .map_err(|e| error::ErrorNotFound(e.to_string()))
+}
+
 /// query utxo according to `TxoSID` return UnAuthenticated Utxo
 pub async fn query_utxo_light(
     data: web::Data<Arc<RwLock<QueryServer>>>,
@@ -196,15 +210,20 @@ pub async fn query_txn(
 
 /// query tx according to `TxnSID`, lighter and faster version
 pub async fn query_txn_light(
+    req: HttpRequest,
     data: web::Data<Arc<RwLock<QueryServer>>>,
     info: web::Path<String>,
-) -> actix_web::Result<String> {
+) -> actix_web::Result<Cached<String>> {
     let qs = data.read();
     let ledger = &qs.ledger_cloned;
     if let Ok(txn_sid) = info.parse::<usize>() {
         if let Ok(mut txn) = ruc::info!(ledger.get_transaction_light(TxnSID(txn_sid))) {
-            txn.set_txo_id();
-            Ok(serde_json::to_string(&txn)?)
+            // committed txns never change
+            let etag = txn.txn.hash_tm().hex().to_uppercase();
+            with_etag(&req, etag, || {
+                txn.set_txo_id();
+                Ok(serde_json::to_string(&txn)?)
+            })
         } else {
             Err(actix_web::error::ErrorNotFound(
                 "Specified transaction does not exist.",
@@ -245,6 +264,7 @@ pub async fn query_global_state_version(
 pub async fn query_validators(
     data: web::Data<Arc<RwLock<QueryServer>>>,
 ) -> actix_web::Result<web::Json<ValidatorList>> {
+    replica::check_staking()?;
     let qs = data.read();
     let ledger = &qs.ledger_cloned;
     let staking = ledger.get_staking();
@@ -296,6 +316,7 @@ pub async fn get_delegation_reward(
     data: web::Data<Arc<RwLock<QueryServer>>>,
     web::Query(info): web::Query<DelegationRwdQueryParams>,
 ) -> actix_web::Result<web::Json<Vec<DelegationRwdDetail>>> {
+    replica::check_staking()?;
     // Convert from base64 representation
     let key: XfrPublicKey = globutils::wallet::public_key_from_base64(&info.address)
         .c(d!())
@@ -346,6 +367,7 @@ pub async fn get_validator_delegation_history(
     data: web::Data<Arc<RwLock<QueryServer>>>,
     web::Query(info): web::Query<ValidatorDelegationQueryParams>,
 ) -> actix_web::Result<web::Json<Vec<ValidatorDelegation>>> {
+    replica::check_staking()?;
     let qs = data.read();
     let ledger = &qs.ledger_cloned;
     let staking = ledger.get_staking();
@@ -475,6 +497,7 @@ pub async fn get_delegators_with_params(
     data: web::Data<Arc<RwLock<QueryServer>>>,
     web::Query(info): web::Query<DelegatorQueryParams>,
 ) -> actix_web::Result<web::Json<DelegatorList>> {
+    replica::check_staking()?;
     let qs = data.read();
     let ledger = &qs.ledger_cloned;
     let staking = ledger.get_staking();
@@ -511,6 +534,7 @@ pub async fn query_delegator_list(
     data: web::Data<Arc<RwLock<QueryServer>>>,
     addr: web::Path<TendermintAddr>,
 ) -> actix_web::Result<web::Json<DelegatorList>> {
+    replica::check_staking()?;
     let qs = data.read();
     let ledger = &qs.ledger_cloned;
     let staking = ledger.get_staking();
@@ -534,6 +558,7 @@ pub async fn query_validator_detail(
     data: web::Data<Arc<RwLock<QueryServer>>>,
     addr: web::Path<TendermintAddr>,
 ) -> actix_web::Result<web::Json<ValidatorDetail>> {
+    replica::check_staking()?;
     let qs = data.read();
     let ledger = &qs.ledger_cloned;
     let staking = ledger.get_staking();
@@ -613,6 +638,7 @@ pub async fn query_delegation_info(
     data: web::Data<Arc<RwLock<QueryServer>>>,
     address: web::Path<String>,
 ) -> actix_web::Result<web::Json<DelegationInfo>> {
+    replica::check_staking()?;
     let pk = globutils::wallet::public_key_from_base64(address.as_str())
         .c(d!())
         .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
@@ -735,6 +761,7 @@ pub enum ApiRoutes {
     UtxoSid,
     UtxoSidLight,
     UtxoSidList,
+    UtxoStatusAtVersion,
     AssetIssuanceNum,
     AssetToken,
     GetDerivedAssetCode,
@@ -756,6 +783,7 @@ impl NetworkRoute for ApiRoutes {
             ApiRoutes::UtxoSid => "utxo_sid",
             ApiRoutes::UtxoSidLight => "utxo_sid_light",
             ApiRoutes::UtxoSidList => "utxo_sid_list",
+            ApiRoutes::UtxoStatusAtVersion => "utxo_status_at_version",
             ApiRoutes::AssetIssuanceNum => "asset_issuance_num",
             ApiRoutes::AssetToken => "asset_token",
             ApiRoutes::GetDerivedAssetCode => "get_derived_asset_code",
@@ -5,41 +5,117 @@
 // pub it for doc
 pub mod ledger_api;
 
+pub mod governance;
+pub mod graphql;
+pub mod openapi;
+pub mod private_records;
+pub mod replica;
+pub mod rewards;
 pub mod server;
 pub mod service;
+pub mod tracer;
+pub mod webhook;
 
 use {
+    crate::api::unix_socket::UnixSocket,
     actix_cors::Cors,
-    actix_web::{error, middleware, web, App, HttpServer},
+    actix_web::{
+        dev::Server,
+        error,
+        http::header::{self, EntityTag, IfNoneMatch},
+        middleware, web, App, Either, HttpMessage, HttpRequest, HttpResponse,
+        HttpServer, Responder,
+    },
     config::abci::{global_cfg::CFG, CheckPointConfig},
     finutils::api::NetworkRoute,
     globutils::wallet,
     ledger::{
         data_model::{
-            b64dec, ATxoSID, AssetTypeCode, DefineAsset, IssuerPublicKey, Transaction,
-            TxOutput, TxnIDHash, TxnSID, TxoSID, XfrAddress, BLACK_HOLE_PUBKEY,
+            b64dec, ATxoSID, AssetTypeCode, DefineAsset, IssuerPublicKey, Operation,
+            Transaction, TxOutput, TxnIDHash, TxnSID, TxoSID, XfrAddress,
+            BLACK_HOLE_PUBKEY,
         },
         staking::{
-            ops::mint_fra::MintEntry, FF_PK_EXTRA_120_0000, FRA, FRA_TOTAL_AMOUNT,
+            ops::mint_fra::{MintEntry, MintKind},
+            BlockHeight, FF_PK_EXTRA_120_0000, FRA, FRA_TOTAL_AMOUNT,
+        },
+        store::{
+            api_cache::{
+                self, get_related_addresses, AddressFilter, AddressStats, PruneStats,
+                PruningPolicy,
+            },
+            stats::{BlockStats, BlockStatsSummary, FeeSuggestion},
+            supply::AssetSupply,
+            validators::{UptimePeriod, ValidatorSet},
         },
     },
     ledger_api::*,
     parking_lot::RwLock,
     ruc::*,
+    rustls::{
+        internal::pemfile::{certs, pkcs8_private_keys},
+        NoClientAuth, ServerConfig,
+    },
+    schemars::JsonSchema,
     serde::{Deserialize, Serialize},
     server::QueryServer,
     std::{
-        collections::{BTreeMap, HashMap, HashSet},
+        collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+        env,
+        fs::{self, File},
+        io::BufReader,
+        path::Path,
         sync::Arc,
     },
+    strum::{EnumIter, IntoEnumIterator},
     tracing::info,
     zei::{
         noah_algebra::serialization::NoahFromToBytes,
         noah_api::anon_xfr::structs::{AxfrOwnerMemo, Commitment, MTLeafInfo},
-        OwnerMemo, XfrPublicKey,
+        BlindAssetRecord, OwnerMemo, XfrPublicKey,
     },
 };
 
+/// A response that is replaced by `304 Not Modified`
+/// when the client already holds the same version
+pub type Cached<R> = Either<HttpResponse, actix_web::dev::CustomResponder<R>>;
+
+/// Tag the response of `build` with `etag`, or skip building it
+/// if the `If-None-Match` header of the request matches.
+///
+/// The tag must change whenever the response may change, e.g. a txn hash
+/// for committed txns or the ledger state commitment for cached indexes.
+pub(crate) fn with_etag<R: Responder>(
+    req: &HttpRequest,
+    etag: String,
+    build: impl FnOnce() -> actix_web::Result<R>,
+) -> actix_web::Result<Cached<R>> {
+    let etag = EntityTag::strong(etag);
+
+    let matched = match req.get_header::<IfNoneMatch>() {
+        Some(IfNoneMatch::Any) => true,
+        Some(IfNoneMatch::Items(tags)) => tags.iter().any(|t| t.weak_eq(&etag)),
+        None => false,
+    };
+
+    if matched {
+        return Ok(Either::A(
+            HttpResponse::NotModified()
+                .header(header::ETAG, etag.to_string())
+                .finish(),
+        ));
+    }
+
+    build().map(|r| Either::B(r.with_header(header::ETAG, etag.to_string())))
+}
+
+/// ETag of the responses derived from the current ledger state
+#[inline(always)]
+fn state_etag(server: &QueryServer) -> String {
+    let (commitment, block_count) = server.get_state_commitment_from_api_cache();
+    format!("{}-{}", commitment.hex(), block_count)
+}
+
 /// Returns the git commit hash and commit date of this build
 #[allow(clippy::unnecessary_wraps)]
 pub async fn version() -> actix_web::Result<String> {
@@ -95,6 +171,28 @@ pub async fn get_owner_memo_batch(
     Ok(web::Json(resp))
 }
 
+/// Returns the owner memos of all utxos currently owned by a given address,
+/// utxos without a memo are skipped
+pub async fn get_owner_memos_by_address(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    owner: web::Path<String>,
+) -> actix_web::Result<web::Json<Vec<(TxoSID, OwnerMemo)>>> {
+    let qs = data.read();
+    let ledger = &qs.ledger_cloned;
+
+    let pk = wallet::public_key_from_base64(owner.as_str())
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
+    let memos = ledger
+        .get_owned_utxos(&pk)
+        .map_err(actix_web::error::ErrorServiceUnavailable)?
+        .into_iter()
+        .filter_map(|(sid, (_, memo))| memo.map(|m| (sid, m)))
+        .collect();
+
+    Ok(web::Json(memos))
+}
+
 /// Returns the owner memo required to decrypt the asset record stored at given index, if it exists.
 #[allow(clippy::unnecessary_wraps)]
 async fn get_abar_memo(
@@ -133,25 +231,254 @@ async fn get_abar_commitment(
     Ok(web::Json(server.get_abar_commitment(ATxoSID(*info))))
 }
 
-/// Returns an array of the utxo sids currently spendable by a given address
+/// Returns an array of the utxo sids currently spendable by a given address,
+/// paged and sorted by sid if `page` or `per_page` is given
 pub async fn get_owned_utxos(
     data: web::Data<Arc<RwLock<QueryServer>>>,
     owner: web::Path<String>,
-) -> actix_web::Result<web::Json<HashSet<TxoSID>>> {
+    web::Query(paging): web::Query<PageQueryParams>,
+    version: web::Data<ApiVersion>,
+) -> actix_web::Result<PagedJson<TxoSID, HashSet<TxoSID>>> {
     let qs = data.read();
     let ledger = &qs.ledger_cloned;
 
     let pk = wallet::public_key_from_base64(owner.as_str())
         .map_err(actix_web::error::ErrorServiceUnavailable)?;
 
-    let utxos = ledger
+    let mut utxos = ledger
         .get_owned_utxos(&pk)
         .map_err(actix_web::error::ErrorServiceUnavailable)?
         .keys()
         .copied()
-        .collect();
+        .collect::<Vec<_>>();
+    utxos.sort();
+
+    paging.respond(*version, utxos, |utxos| utxos.into_iter().collect())
+}
+
+/// Balances of an address aggregated from its owned utxos
+#[allow(missing_docs)]
+#[derive(Debug, Default, Deserialize, Serialize, JsonSchema)]
+pub struct AddressBalances {
+    /// base64 asset code => total nonconfidential amount
+    pub nonconfidential: BTreeMap<String, u64>,
+    pub nonconfidential_utxo_count: u64,
+    /// utxos whose amount or asset type is confidential
+    pub confidential_utxo_count: u64,
+}
+
+/// Returns the per-asset nonconfidential balances of a given address,
+/// along with the number of confidential utxos it owns
+pub async fn get_balances(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    owner: web::Path<String>,
+) -> actix_web::Result<web::Json<AddressBalances>> {
+    let qs = data.read();
+    let ledger = &qs.ledger_cloned;
+
+    let pk = wallet::public_key_from_base64(owner.as_str())
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
+    let mut res = AddressBalances::default();
+    ledger
+        .get_owned_utxos(&pk)
+        .map_err(actix_web::error::ErrorServiceUnavailable)?
+        .values()
+        .for_each(|(utxo, _)| {
+            let record = &utxo.0.record;
+            if let (Some(asset_type), Some(amount)) = (
+                record.asset_type.get_asset_type(),
+                record.amount.get_amount(),
+            ) {
+                let total = res
+                    .nonconfidential
+                    .entry(AssetTypeCode { val: asset_type }.to_base64())
+                    .or_insert(0);
+                *total = total.saturating_add(amount);
+                res.nonconfidential_utxo_count += 1;
+            } else {
+                res.confidential_utxo_count += 1;
+            }
+        });
+
+    Ok(web::Json(res))
+}
+
+/// A holder of an asset
+#[allow(missing_docs)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct AssetHolder {
+    pub address: String,
+    /// nonconfidential amount held
+    pub amount: u64,
+}
+
+/// Returns the holders of a nonconfidential asset, richest first,
+/// `total_count` of a page is the holder count
+pub async fn get_asset_holders(
+    req: HttpRequest,
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    info: web::Path<String>,
+    web::Query(paging): web::Query<PageQueryParams>,
+    version: web::Data<ApiVersion>,
+) -> actix_web::Result<Cached<PagedJson<AssetHolder, Vec<AssetHolder>>>> {
+    let code = AssetTypeCode::new_from_base64(&info)
+        .c(d!())
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+    let server = data.read();
+
+    with_etag(&req, state_etag(&server), || {
+        let holders = server.get_asset_holders(&code).ok_or_else(|| {
+            error::ErrorNotFound("No nonconfidential holder of the asset.")
+        })?;
+
+        let mut res = Vec::with_capacity(holders.len());
+        for holder in holders {
+            let amount = server
+                .ledger_cloned
+                .get_owned_utxos(&holder.key)
+                .map_err(error::ErrorServiceUnavailable)?
+                .values()
+                .map(|(utxo, _)| &utxo.0.record)
+                .filter(|r| r.asset_type.get_asset_type() == Some(code.val))
+                .filter_map(|r| r.amount.get_amount())
+                .fold(0u64, |acc, n| acc.saturating_add(n));
+            res.push(AssetHolder {
+                address: wallet::public_key_to_base64(&holder.key),
+                amount,
+            });
+        }
+        res.sort_unstable_by(|a, b| {
+            b.amount
+                .cmp(&a.amount)
+                .then_with(|| a.address.cmp(&b.address))
+        });
+
+        paging.respond(*version, res, |res| res)
+    })
+}
 
-    Ok(web::Json(utxos))
+/// Returns the first-seen height, txn count and per-asset nonconfidential
+/// amounts received and sent by a given address
+pub async fn get_address_stats(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    owner: web::Path<String>,
+) -> actix_web::Result<web::Json<AddressStats>> {
+    let key = wallet::public_key_from_base64(owner.as_str())
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
+    data.read()
+        .get_address_stats(&XfrAddress { key })
+        .map(web::Json)
+        .ok_or_else(|| error::ErrorNotFound("No transaction of the address."))
+}
+
+/// Provenance of a spent txo
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct SpentUtxo {
+    /// sid of the spending txn
+    pub txn_sid: TxnSID,
+    /// hash of the spending txn
+    pub txn_hash: Option<String>,
+    /// height of the block containing the spending txn
+    pub height: BlockHeight,
+}
+
+/// Returns the txn spending a given txo, 404 if the txo is unspent
+pub async fn get_spent_utxo(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    info: web::Path<u64>,
+) -> actix_web::Result<web::Json<SpentUtxo>> {
+    let server = data.read();
+    server
+        .get_spent_utxo(TxoSID(*info))
+        .map(|(txn_sid, height)| {
+            web::Json(SpentUtxo {
+                txn_sid,
+                txn_hash: server.get_transaction_hash(txn_sid),
+                height,
+            })
+        })
+        .ok_or_else(|| error::ErrorNotFound("Txo is not spent or does not exist."))
+}
+
+/// Max number of hits of each kind returned by `search`
+const MAX_SEARCH_HITS: usize = 20;
+
+/// A typed match of the search box
+#[allow(missing_docs)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SearchHit {
+    Txn { sid: TxnSID, hash: String },
+    Asset { code: String },
+    Txo { sid: TxoSID, address: String },
+    Address { address: String, txn_count: usize },
+}
+
+#[allow(missing_docs)]
+#[derive(Debug, Deserialize)]
+pub struct SearchQueryParams {
+    q: String,
+}
+
+/// Search txns by hash prefix or sid, assets by code prefix,
+/// txos by sid and addresses by their base64 or bech32 form
+pub async fn search(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    web::Query(info): web::Query<SearchQueryParams>,
+) -> actix_web::Result<web::Json<Vec<SearchHit>>> {
+    let q = info.q.trim();
+    if q.is_empty() {
+        return Err(error::ErrorBadRequest("Missing q"));
+    }
+
+    let server = data.read();
+    let mut hits = vec![];
+
+    if let Ok(n) = q.parse::<u64>() {
+        if let Some(hash) = server.get_transaction_hash(TxnSID(n as usize)) {
+            hits.push(SearchHit::Txn {
+                sid: TxnSID(n as usize),
+                hash,
+            });
+        }
+        if let Some(address) = server.get_address_of_sid(TxoSID(n)) {
+            hits.push(SearchHit::Txo {
+                sid: TxoSID(n),
+                address: wallet::public_key_to_base64(&address.key),
+            });
+        }
+    }
+
+    if let Ok(key) =
+        wallet::public_key_from_base64(q).or_else(|_| wallet::public_key_from_bech32(q))
+    {
+        let address = XfrAddress { key };
+        if let Some(txn_count) = server.get_related_transaction_count(&address) {
+            hits.push(SearchHit::Address {
+                address: wallet::public_key_to_base64(&key),
+                txn_count,
+            });
+        }
+    }
+
+    server
+        .search_txn_hashes(&q.to_uppercase(), MAX_SEARCH_HITS)
+        .into_iter()
+        .filter_map(|hash| {
+            server
+                .get_transaction_sid(hash.clone())
+                .map(|sid| SearchHit::Txn { sid, hash })
+        })
+        .for_each(|hit| hits.push(hit));
+
+    server
+        .search_asset_codes(q, MAX_SEARCH_HITS)
+        .into_iter()
+        .for_each(|code| hits.push(SearchHit::Asset { code }));
+
+    Ok(web::Json(hits))
 }
 
 /// Returns the ATxo Sid currently spendable by a given commitment
@@ -203,6 +530,7 @@ async fn get_max_atxo_sid_at_height(
 
 /// Define interface type
 #[allow(missing_docs)]
+#[derive(EnumIter)]
 pub enum QueryServerRoutes {
     GetAddress,
     GetOwnerMemo,
@@ -225,6 +553,31 @@ pub enum QueryServerRoutes {
     GetTransactionHash,
     GetTransactionSid,
     GetCommits,
+    GetBlocks,
+    GetTxnDetail,
+    GetBalances,
+    Search,
+    GetOwnerMemosByAddress,
+    GetTracingChallenge,
+    GetTracedTransfers,
+    RegisterWebhook,
+    RemoveWebhook,
+    GetAddressStats,
+    GetSpentUtxo,
+    GetTxnsByOpType,
+    GetAssetHolders,
+    GetCoinbaseEntries,
+    GetAddressFilters,
+    GetPrivateRecords,
+    GetBlockStats,
+    GetBlockStatsSummary,
+    GetValidators,
+    GetValidatorUptime,
+    GetDelegationRewards,
+    GetGovernanceProposals,
+    GetGovernanceProposal,
+    GetAssetSupply,
+    GetFeeSuggestion,
 }
 
 impl NetworkRoute for QueryServerRoutes {
@@ -251,6 +604,31 @@ impl NetworkRoute for QueryServerRoutes {
             QueryServerRoutes::GetTransactionHash => "get_transaction_hash",
             QueryServerRoutes::GetTransactionSid => "get_transaction_sid",
             QueryServerRoutes::GetCommits => "get_commits",
+            QueryServerRoutes::GetBlocks => "blocks",
+            QueryServerRoutes::GetTxnDetail => "tx",
+            QueryServerRoutes::GetBalances => "balances",
+            QueryServerRoutes::Search => "search",
+            QueryServerRoutes::GetOwnerMemosByAddress => "owner_memos_by_address",
+            QueryServerRoutes::GetTracingChallenge => "tracing_challenge",
+            QueryServerRoutes::GetTracedTransfers => "traced_transfers",
+            QueryServerRoutes::RegisterWebhook => "webhooks",
+            QueryServerRoutes::RemoveWebhook => "webhooks",
+            QueryServerRoutes::GetAddressStats => "address_stats",
+            QueryServerRoutes::GetSpentUtxo => "spent_utxo",
+            QueryServerRoutes::GetTxnsByOpType => "txns_by_op_type",
+            QueryServerRoutes::GetAssetHolders => "asset_holders",
+            QueryServerRoutes::GetCoinbaseEntries => "coinbase_entries",
+            QueryServerRoutes::GetAddressFilters => "address_filters",
+            QueryServerRoutes::GetPrivateRecords => "private_records",
+            QueryServerRoutes::GetBlockStats => "block_stats",
+            QueryServerRoutes::GetBlockStatsSummary => "block_stats_summary",
+            QueryServerRoutes::GetValidators => "validators",
+            QueryServerRoutes::GetValidatorUptime => "validator_uptime",
+            QueryServerRoutes::GetDelegationRewards => "delegation_rewards",
+            QueryServerRoutes::GetGovernanceProposals => "governance_proposals",
+            QueryServerRoutes::GetGovernanceProposal => "governance_proposal",
+            QueryServerRoutes::GetAssetSupply => "circulating_supply",
+            QueryServerRoutes::GetFeeSuggestion => "fee_suggestion",
         };
         "/".to_owned() + endpoint
     }
@@ -276,9 +654,12 @@ pub async fn get_created_assets(
 /// Returns the list of records issued by a public key
 #[allow(clippy::type_complexity)]
 pub async fn get_issued_records(
+    req: HttpRequest,
     data: web::Data<Arc<RwLock<QueryServer>>>,
     info: web::Path<String>,
-) -> actix_web::Result<web::Json<Vec<(TxOutput, Option<OwnerMemo>)>>> {
+    web::Query(paging): web::Query<PageQueryParams>,
+    version: web::Data<ApiVersion>,
+) -> actix_web::Result<Cached<PagedJson<IssuedRecord, Vec<IssuedRecord>>>> {
     // Convert from base64 representation
     let key: XfrPublicKey = XfrPublicKey::noah_from_bytes(
         &b64dec(&*info)
@@ -287,28 +668,33 @@ pub async fn get_issued_records(
     )
     .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
     let server = data.read();
-    let records = server.get_issued_records(&IssuerPublicKey { key });
-    Ok(web::Json(records.unwrap_or_default()))
+    with_etag(&req, state_etag(&server), || {
+        let records = server.get_issued_records(&IssuerPublicKey { key });
+        paging.respond(*version, records.unwrap_or_default(), |records| records)
+    })
 }
 
 /// Returns the list of records issued by a token code
 #[allow(clippy::type_complexity)]
 pub async fn get_issued_records_by_code(
+    req: HttpRequest,
     data: web::Data<Arc<RwLock<QueryServer>>>,
     info: web::Path<String>,
-) -> actix_web::Result<web::Json<Vec<(TxOutput, Option<OwnerMemo>)>>> {
+    web::Query(paging): web::Query<PageQueryParams>,
+    version: web::Data<ApiVersion>,
+) -> actix_web::Result<Cached<PagedJson<IssuedRecord, Vec<IssuedRecord>>>> {
     let server = data.read();
 
     match AssetTypeCode::new_from_base64(&info).c(d!()) {
-        Ok(token_code) => {
+        Ok(token_code) => with_etag(&req, state_etag(&server), || {
             if let Some(records) = server.get_issued_records_by_code(&token_code) {
-                Ok(web::Json(records))
+                paging.respond(*version, records, |records| records)
             } else {
                 Err(actix_web::error::ErrorNotFound(
                     "Specified asset definition does not currently exist.",
                 ))
             }
-        }
+        }),
         Err(e) => Err(actix_web::error::ErrorBadRequest(e.to_string())),
     }
 }
@@ -364,6 +750,497 @@ pub async fn get_commits(
     Ok(web::Json(server.get_commits()))
 }
 
+/// Max number of blocks returned by one `blocks` query
+const MAX_BLOCK_RANGE: usize = 100;
+
+/// A finalized transaction with its sid and hash
+#[allow(missing_docs)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct BlockTxnBody {
+    pub txn_sid: TxnSID,
+    pub txn_hash: String,
+    #[schemars(with = "serde_json::Value")]
+    pub txn: Transaction,
+}
+
+/// Transactions grouped by the ledger block containing them
+#[allow(missing_docs)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct BlockTxns {
+    /// index of the block in the ledger, empty blocks are not counted
+    pub block_sid: usize,
+    /// tendermint height of the block
+    #[serde(default)]
+    pub height: Option<BlockHeight>,
+    /// unix time of the block, in seconds
+    #[serde(default)]
+    pub time: Option<i64>,
+    pub txns: Vec<BlockTxnBody>,
+}
+
+/// Returns the transactions of the ledger blocks between start and end,
+/// include start and end, limit 100.
+pub async fn get_blocks(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    info: web::Path<(usize, usize)>,
+) -> actix_web::Result<web::Json<Vec<BlockTxns>>> {
+    let (start, end) = info.into_inner();
+    if end < start || end - start >= MAX_BLOCK_RANGE {
+        return Err(error::ErrorBadRequest(format!("Limit {MAX_BLOCK_RANGE}")));
+    }
+
+    let server = data.read();
+    let blocks = server
+        .get_blocks(start, end)
+        .into_iter()
+        .map(|(block_sid, txns)| {
+            let height = txns
+                .first()
+                .and_then(|ftx| server.get_transaction_height(ftx.tx_id));
+            BlockTxns {
+                block_sid,
+                height,
+                time: height.and_then(|h| server.get_block_time(h)),
+                txns: txns
+                    .into_iter()
+                    .map(|ftx| BlockTxnBody {
+                        txn_sid: ftx.tx_id,
+                        txn_hash: ftx.txn.hash_tm().hex().to_uppercase(),
+                        txn: ftx.txn,
+                    })
+                    .collect(),
+            }
+        })
+        .collect();
+
+    Ok(web::Json(blocks))
+}
+
+/// Address filter of a ledger block
+#[allow(missing_docs)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct BlockAddressFilter {
+    pub block_sid: usize,
+    #[schemars(with = "serde_json::Value")]
+    pub filter: AddressFilter,
+}
+
+/// Returns the address filters of the ledger blocks between start and end,
+/// include start and end, limit 100. Light clients match them against
+/// their own addresses, then fetch the matching blocks only.
+pub async fn get_address_filters(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    info: web::Path<(usize, usize)>,
+) -> actix_web::Result<web::Json<Vec<BlockAddressFilter>>> {
+    let (start, end) = info.into_inner();
+    if end < start || end - start >= MAX_BLOCK_RANGE {
+        return Err(error::ErrorBadRequest(format!("Limit {MAX_BLOCK_RANGE}")));
+    }
+
+    let filters = data
+        .read()
+        .get_address_filters(start, end)
+        .into_iter()
+        .map(|(block_sid, filter)| BlockAddressFilter { block_sid, filter })
+        .collect();
+
+    Ok(web::Json(filters))
+}
+
+/// Fee and utilization stats of a ledger block
+#[allow(missing_docs)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct BlockStatsEntry {
+    pub block_sid: usize,
+    pub stats: BlockStats,
+}
+
+/// Returns the stats of the ledger blocks between start and end,
+/// include start and end, limit 100.
+pub async fn get_block_stats(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    info: web::Path<(usize, usize)>,
+) -> actix_web::Result<web::Json<Vec<BlockStatsEntry>>> {
+    let (start, end) = info.into_inner();
+    if end < start || end - start >= MAX_BLOCK_RANGE {
+        return Err(error::ErrorBadRequest(format!("Limit {MAX_BLOCK_RANGE}")));
+    }
+
+    let stats = data
+        .read()
+        .ledger_cloned
+        .get_block_stats(start, end)
+        .into_iter()
+        .map(|(block_sid, stats)| BlockStatsEntry { block_sid, stats })
+        .collect();
+
+    Ok(web::Json(stats))
+}
+
+/// Blocks summed up by `block_stats_summary`, `fee_suggestion`
+/// and `/metrics` by default
+const STATS_WINDOW: usize = 100;
+
+/// Max blocks summed up by one `block_stats_summary` or `fee_suggestion` query
+const MAX_STATS_WINDOW: usize = 100_000;
+
+#[allow(missing_docs)]
+#[derive(Debug, Deserialize)]
+pub struct StatsWindowParams {
+    /// number of most recent blocks, default to `STATS_WINDOW`
+    blocks: Option<usize>,
+}
+
+/// Returns the sums of the stats of the most recent blocks
+pub async fn get_block_stats_summary(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    web::Query(params): web::Query<StatsWindowParams>,
+) -> actix_web::Result<web::Json<BlockStatsSummary>> {
+    let n = params.blocks.unwrap_or(STATS_WINDOW);
+    if n > MAX_STATS_WINDOW {
+        return Err(error::ErrorBadRequest(format!("Limit {MAX_STATS_WINDOW}")));
+    }
+    Ok(web::Json(
+        data.read().ledger_cloned.get_recent_block_stats(n),
+    ))
+}
+
+/// Returns the percentile fee levels of the txns of the most recent blocks,
+/// for the wallets to fill in the fee of a txn
+pub async fn get_fee_suggestion(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    web::Query(params): web::Query<StatsWindowParams>,
+) -> actix_web::Result<web::Json<FeeSuggestion>> {
+    let n = params.blocks.unwrap_or(STATS_WINDOW);
+    if n > MAX_STATS_WINDOW {
+        return Err(error::ErrorBadRequest(format!("Limit {MAX_STATS_WINDOW}")));
+    }
+    Ok(web::Json(data.read().ledger_cloned.get_fee_suggestion(n)))
+}
+
+/// Returns the current validators, with their voting power, commission,
+/// delegated amounts and signed block count, by decreasing voting power
+pub async fn get_validators(
+    req: HttpRequest,
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+) -> actix_web::Result<Cached<web::Json<ValidatorSet>>> {
+    replica::check_staking()?;
+    let server = data.read();
+    with_etag(&req, state_etag(&server), || {
+        Ok(web::Json(server.ledger_cloned.get_validator_set()))
+    })
+}
+
+/// Heights covered by `validator_uptime` by default
+const UPTIME_WINDOW: BlockHeight = 10_000;
+
+/// Max heights covered by one `validator_uptime` query
+const MAX_UPTIME_WINDOW: BlockHeight = 1_000_000;
+
+#[allow(missing_docs)]
+#[derive(Debug, Deserialize)]
+pub struct UptimeParams {
+    /// default to `UPTIME_WINDOW` before `to_height`
+    from_height: Option<BlockHeight>,
+    /// default to the current height
+    to_height: Option<BlockHeight>,
+}
+
+/// Uptime of a validator over a height range
+#[allow(missing_docs)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct ValidatorUptime {
+    pub addr: String,
+    /// sums of the periods
+    pub signed: u64,
+    pub blocks: u64,
+    /// sampled every `UPTIME_SAMPLE_ITV` blocks, the periods out of
+    /// the validator set or before the samples were introduced are missing
+    pub periods: Vec<UptimePeriod>,
+}
+
+/// Returns the blocks signed by a validator of the current set
+/// over a height range, period by period
+pub async fn get_validator_uptime(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    address: web::Path<String>,
+    web::Query(params): web::Query<UptimeParams>,
+) -> actix_web::Result<web::Json<ValidatorUptime>> {
+    replica::check_staking()?;
+    let server = data.read();
+    let ledger = &server.ledger_cloned;
+    let addr = address.trim().to_uppercase();
+    let id = ledger
+        .get_staking()
+        .validator_td_addr_to_app_pk(&addr)
+        .map_err(|_| error::ErrorNotFound("Not a current validator."))?;
+
+    let to_height = params
+        .to_height
+        .unwrap_or_else(|| ledger.get_staking().cur_height());
+    let from_height = params
+        .from_height
+        .unwrap_or_else(|| to_height.saturating_sub(UPTIME_WINDOW));
+    if to_height < from_height || to_height - from_height > MAX_UPTIME_WINDOW {
+        return Err(error::ErrorBadRequest(format!("Limit {MAX_UPTIME_WINDOW}")));
+    }
+
+    let periods = ledger.get_validator_uptime(&id, from_height, to_height);
+    Ok(web::Json(ValidatorUptime {
+        addr,
+        signed: periods.iter().map(|p| p.signed).sum(),
+        blocks: periods.iter().map(|p| p.blocks).sum(),
+        periods,
+    }))
+}
+
+/// Chain utilization gauges in the Prometheus text format,
+/// of the last block and of the last `STATS_WINDOW` blocks
+pub async fn metrics(data: web::Data<Arc<RwLock<QueryServer>>>) -> HttpResponse {
+    let server = data.read();
+    let ledger = &server.ledger_cloned;
+    let block_count = ledger.get_block_count();
+    let last = ledger.get_recent_block_stats(1);
+    let recent = ledger.get_recent_block_stats(STATS_WINDOW);
+
+    let mut body = String::new();
+    let mut gauge = |name: &str, help: &str, samples: Vec<(String, u64)>| {
+        body += &format!("# HELP {name} {help}\n# TYPE {name} gauge\n");
+        for (labels, v) in samples {
+            body += &format!("{name}{labels} {v}\n");
+        }
+    };
+
+    gauge(
+        "findora_ledger_blocks",
+        "Number of ledger blocks",
+        vec![(String::new(), block_count as u64)],
+    );
+    gauge(
+        "findora_query_server_commits",
+        "Block commit count of the query server",
+        vec![(String::new(), server.get_commits())],
+    );
+    for (name, help, of_last, of_recent) in [
+        ("txns", "Txns", last.txn_count, recent.txn_count),
+        (
+            "fees",
+            "FRA fees, in the smallest unit,",
+            last.fees,
+            recent.fees,
+        ),
+        (
+            "bytes",
+            "Bytes of the JSON encoded txns",
+            last.bytes,
+            recent.bytes,
+        ),
+    ] {
+        gauge(
+            &format!("findora_last_block_{name}"),
+            &format!("{help} of the last block"),
+            vec![(String::new(), of_last)],
+        );
+        gauge(
+            &format!("findora_recent_blocks_{name}"),
+            &format!("{help} of the last {STATS_WINDOW} blocks"),
+            vec![(String::new(), of_recent)],
+        );
+    }
+    gauge(
+        "findora_recent_blocks_operations",
+        &format!("Operations of the last {STATS_WINDOW} blocks, by kind"),
+        recent
+            .op_counts
+            .iter()
+            .map(|(kind, n)| (format!("{{kind=\"{kind}\"}}"), *n))
+            .collect(),
+    );
+
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(body)
+}
+
+/// A nonconfidential amount moved to an address
+#[allow(missing_docs)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct AmountDetail {
+    pub address: String,
+    pub asset_code: String,
+    pub amount: u64,
+}
+
+/// Decoded view of one operation
+#[allow(missing_docs)]
+#[derive(Debug, Default, Deserialize, Serialize, JsonSchema)]
+pub struct OperationDetail {
+    pub op_type: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub asset_codes: Vec<String>,
+    /// confidential amounts are not listed
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub amounts: Vec<AmountDetail>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_memo: Option<String>,
+}
+
+/// Decoded view of a transaction
+#[allow(missing_docs)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct TxnDetail {
+    pub txn_sid: TxnSID,
+    pub txn_hash: String,
+    /// index of the containing block in the ledger
+    pub block_sid: Option<usize>,
+    /// tendermint height of the containing block,
+    /// missing for txns indexed before heights were recorded
+    pub height: Option<BlockHeight>,
+    /// unix time of the containing block, in seconds
+    pub time: Option<i64>,
+    pub txo_sids: Vec<TxoSID>,
+    pub addresses: Vec<String>,
+    pub operations: Vec<OperationDetail>,
+}
+
+pub(crate) fn operation_detail(op: &Operation) -> OperationDetail {
+    let code_b64 = |code: &AssetTypeCode| code.to_base64();
+    let amount_of = |r: &BlindAssetRecord| {
+        r.asset_type
+            .get_asset_type()
+            .zip(r.amount.get_amount())
+            .map(|(asset_type, amount)| AmountDetail {
+                address: wallet::public_key_to_base64(&r.public_key),
+                asset_code: AssetTypeCode { val: asset_type }.to_base64(),
+                amount,
+            })
+    };
+
+    let (op_type, mut detail) = match op {
+        Operation::TransferAsset(i) => (
+            "TransferAsset",
+            OperationDetail {
+                amounts: i
+                    .body
+                    .transfer
+                    .outputs
+                    .iter()
+                    .filter_map(amount_of)
+                    .collect(),
+                ..Default::default()
+            },
+        ),
+        Operation::IssueAsset(i) => (
+            "IssueAsset",
+            OperationDetail {
+                asset_codes: vec![code_b64(&i.body.code)],
+                amounts: i
+                    .body
+                    .records
+                    .iter()
+                    .filter_map(|r| amount_of(&r.0.record))
+                    .collect(),
+                ..Default::default()
+            },
+        ),
+        Operation::DefineAsset(i) => (
+            "DefineAsset",
+            OperationDetail {
+                asset_codes: vec![code_b64(&i.body.asset.code)],
+                new_memo: Some(i.body.asset.memo.0.clone()),
+                ..Default::default()
+            },
+        ),
+        Operation::UpdateMemo(i) => (
+            "UpdateMemo",
+            OperationDetail {
+                asset_codes: vec![code_b64(&i.body.asset_type)],
+                new_memo: Some(i.body.new_memo.0.clone()),
+                ..Default::default()
+            },
+        ),
+        Operation::UpdateStaker(_) => ("UpdateStaker", OperationDetail::default()),
+        Operation::Delegation(_) => ("Delegation", OperationDetail::default()),
+        Operation::UnDelegation(_) => ("UnDelegation", OperationDetail::default()),
+        Operation::Claim(_) => ("Claim", OperationDetail::default()),
+        Operation::UpdateValidator(_) => ("UpdateValidator", OperationDetail::default()),
+        Operation::Governance(_) => ("Governance", OperationDetail::default()),
+        Operation::FraDistribution(_) => ("FraDistribution", OperationDetail::default()),
+        Operation::MintFra(_) => ("MintFra", OperationDetail::default()),
+        Operation::ConvertAccount(_) => ("ConvertAccount", OperationDetail::default()),
+        Operation::ReplaceStaker(_) => ("ReplaceStaker", OperationDetail::default()),
+        Operation::BarToAbar(_) => ("BarToAbar", OperationDetail::default()),
+        Operation::AbarToBar(_) => ("AbarToBar", OperationDetail::default()),
+        Operation::TransferAnonAsset(_) => {
+            ("TransferAnonAsset", OperationDetail::default())
+        }
+    };
+
+    detail.op_type = op_type.to_owned();
+    for a in detail.amounts.iter() {
+        if !detail.asset_codes.contains(&a.asset_code) {
+            detail.asset_codes.push(a.asset_code.clone());
+        }
+    }
+    detail
+}
+
+/// Returns a decoded view of a transaction, queried by its hash or sid
+pub async fn get_txn_detail(
+    req: HttpRequest,
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    info: web::Path<String>,
+) -> actix_web::Result<Cached<web::Json<TxnDetail>>> {
+    let server = data.read();
+
+    let txn_sid = if info.len() == 64 {
+        server.get_transaction_sid(info.to_uppercase())
+    } else {
+        info.parse::<usize>().ok().map(TxnSID)
+    }
+    .ok_or_else(|| {
+        error::ErrorNotFound(
+            "No transaction found. Please retry with correct hash or sid.",
+        )
+    })?;
+
+    let ftx = server
+        .ledger_cloned
+        .get_transaction_light(txn_sid)
+        .map_err(|e| error::ErrorNotFound(e.to_string()))?;
+
+    let mut addresses = get_related_addresses(&ftx.txn, |_| {})
+        .into_iter()
+        .map(|a| wallet::public_key_to_base64(&a.key))
+        .collect::<Vec<_>>();
+    addresses.sort();
+
+    let txn_hash = ftx.txn.hash_tm().hex().to_uppercase();
+    with_etag(&req, txn_hash.clone(), || {
+        let height = server.get_transaction_height(txn_sid);
+        Ok(web::Json(TxnDetail {
+            txn_sid,
+            txn_hash,
+            block_sid: server
+                .ledger_cloned
+                .tx_to_block_location
+                .get(&txn_sid)
+                .map(|[block_idx, _]| block_idx),
+            height,
+            time: height.and_then(|h| server.get_block_time(h)),
+            txo_sids: ftx.txo_ids,
+            addresses,
+            operations: ftx
+                .txn
+                .body
+                .operations
+                .iter()
+                .map(operation_detail)
+                .collect(),
+        }))
+    })
+}
+
 #[allow(missing_docs)]
 #[derive(Debug, Deserialize)]
 pub struct WalletQueryParams {
@@ -381,11 +1258,141 @@ enum OrderOption {
     Asc,
 }
 
+/// An issued record together with the memo needed to open it
+pub type IssuedRecord = (TxOutput, Option<OwnerMemo>);
+
+/// Version of the response shapes, selected by the `/v1` or `/v2` prefix,
+/// unprefixed routes are served as `/v1`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ApiVersion {
+    /// legacy shapes, deprecated
+    V1,
+    /// lists are always paged
+    V2,
+}
+
+/// Default page size when only `page` is given
+const DEFAULT_PER_PAGE: usize = 20;
+
+/// Upper bound of `per_page`
+const MAX_PER_PAGE: usize = 1000;
+
+/// Optional paging parameters of the list endpoints,
+/// the full list is returned as before if both are missing
+#[derive(Debug, Deserialize)]
+pub struct PageQueryParams {
+    /// starts from 1
+    page: Option<usize>,
+    per_page: Option<usize>,
+}
+
+/// One page of a list response
 #[allow(missing_docs)]
-#[derive(Debug, Deserialize, Serialize)]
-struct CoinbaseTxnBody {
-    height: u64,
-    data: MintEntry,
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct Paged<T> {
+    pub total_count: u64,
+    pub page: usize,
+    pub per_page: usize,
+    /// `None` if this is the last page
+    pub next_page: Option<usize>,
+    pub items: Vec<T>,
+}
+
+/// Optional block filters of the related transaction queries
+#[derive(Debug, Deserialize)]
+pub struct HeightFilterParams {
+    from_height: Option<u64>,
+    to_height: Option<u64>,
+    /// unix time in seconds
+    since: Option<i64>,
+}
+
+impl HeightFilterParams {
+    /// Keep the txns inside the requested range, txns without
+    /// a cached height are dropped once any filter is given
+    fn retain(&self, server: &QueryServer, txns: &mut Vec<TxnSID>) {
+        if self.from_height.is_none() && self.to_height.is_none() && self.since.is_none()
+        {
+            return;
+        }
+
+        txns.retain(|sid| {
+            server.get_transaction_height(*sid).map_or(false, |h| {
+                self.from_height.map_or(true, |from| from <= h)
+                    && self.to_height.map_or(true, |to| h <= to)
+                    && self.since.map_or(true, |since| {
+                        server.get_block_time(h).map_or(false, |t| since <= t)
+                    })
+            })
+        });
+    }
+}
+
+/// Either the legacy full list or a page of it
+pub type PagedJson<T, L> = Either<web::Json<L>, web::Json<Paged<T>>>;
+
+impl PageQueryParams {
+    #[inline(always)]
+    fn is_empty(&self) -> bool {
+        self.page.is_none() && self.per_page.is_none()
+    }
+
+    /// Cut a page out of the sorted `items`
+    fn paginate<T>(&self, items: Vec<T>) -> actix_web::Result<Paged<T>> {
+        let page = self.page.unwrap_or(1);
+        let per_page = self.per_page.unwrap_or(DEFAULT_PER_PAGE);
+        if page == 0 {
+            return Err(error::ErrorBadRequest("page starts from 1"));
+        }
+        if per_page == 0 || per_page > MAX_PER_PAGE {
+            return Err(error::ErrorBadRequest(format!(
+                "per_page should be in [1, {MAX_PER_PAGE}]"
+            )));
+        }
+
+        let total_count = items.len();
+
+        let start = (page - 1)
+            .checked_mul(per_page)
+            .c(d!())
+            .map_err(error::ErrorBadRequest)?;
+        let next_page = alt!(
+            start.saturating_add(per_page) < total_count,
+            Some(page + 1),
+            None
+        );
+
+        Ok(Paged {
+            total_count: total_count as u64,
+            page,
+            per_page,
+            next_page,
+            items: items.into_iter().skip(start).take(per_page).collect(),
+        })
+    }
+
+    /// Respond with a page, or under `/v1` with the full list
+    /// converted by `legacy` if paging is not requested
+    fn respond<T, L>(
+        &self,
+        version: ApiVersion,
+        items: Vec<T>,
+        legacy: impl FnOnce(Vec<T>) -> L,
+    ) -> actix_web::Result<PagedJson<T, L>> {
+        if version == ApiVersion::V1 && self.is_empty() {
+            Ok(Either::A(web::Json(legacy(items))))
+        } else {
+            self.paginate(items).map(|p| Either::B(web::Json(p)))
+        }
+    }
+}
+
+#[allow(missing_docs)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct CoinbaseTxnBody {
+    pub height: u64,
+    #[schemars(with = "serde_json::Value")]
+    pub data: MintEntry,
 }
 
 #[allow(missing_docs)]
@@ -400,6 +1407,7 @@ pub async fn get_coinbase_oper_list(
     data: web::Data<Arc<RwLock<QueryServer>>>,
     web::Query(info): web::Query<WalletQueryParams>,
 ) -> actix_web::Result<web::Json<CoinbaseOperInfo>> {
+    replica::check_staking()?;
     // Convert from base64 representation
     let key: XfrPublicKey = wallet::public_key_from_base64(&info.address)
         .c(d!())
@@ -446,7 +1454,76 @@ pub async fn get_coinbase_oper_list(
     }))
 }
 
-/// Returns the list of claim transations of a given ledger address
+/// Optional height range of the coinbase history, inclusive
+#[derive(Debug, Deserialize)]
+pub struct CoinbaseRangeParams {
+    from_height: Option<u64>,
+    to_height: Option<u64>,
+}
+
+/// Coinbase entries of an address in a height range, with the totals
+/// of the whole range
+#[allow(missing_docs)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct CoinbaseHistory {
+    pub total_amount: u64,
+    pub claim_amount: u64,
+    pub unstake_amount: u64,
+    pub other_amount: u64,
+    #[serde(flatten)]
+    pub entries: Paged<CoinbaseTxnBody>,
+}
+
+/// Returns the coinbase entries of a given address by height range,
+/// along with their totals, for delegators to reconcile their rewards
+pub async fn get_coinbase_entries(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    info: web::Path<String>,
+    web::Query(range): web::Query<CoinbaseRangeParams>,
+    web::Query(paging): web::Query<PageQueryParams>,
+) -> actix_web::Result<web::Json<CoinbaseHistory>> {
+    replica::check_staking()?;
+    let key = wallet::public_key_from_base64(info.as_str())
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+    let from = range.from_height.unwrap_or(0);
+    let to = range.to_height.unwrap_or(BlockHeight::MAX);
+    if from > to {
+        return Err(error::ErrorBadRequest("from_height is above to_height."));
+    }
+
+    let entries =
+        data.read()
+            .get_coinbase_entries_in_range(&XfrAddress { key }, from, to);
+
+    let (mut claim_amount, mut unstake_amount, mut other_amount) = (0u64, 0u64, 0u64);
+    for (_, entry) in entries.iter() {
+        let total = match entry.kind {
+            MintKind::Claim => &mut claim_amount,
+            MintKind::UnStake => &mut unstake_amount,
+            MintKind::Other => &mut other_amount,
+        };
+        *total = total.saturating_add(entry.amount);
+    }
+
+    let entries = paging.paginate(
+        entries
+            .into_iter()
+            .map(|(height, data)| CoinbaseTxnBody { height, data })
+            .collect(),
+    )?;
+
+    Ok(web::Json(CoinbaseHistory {
+        total_amount: claim_amount
+            .saturating_add(unstake_amount)
+            .saturating_add(other_amount),
+        claim_amount,
+        unstake_amount,
+        other_amount,
+        entries,
+    }))
+}
+
+/// Returns the list of claim transations of a given ledger address
 pub async fn get_claim_txns(
     data: web::Data<Arc<RwLock<QueryServer>>>,
     web::Query(info): web::Query<WalletQueryParams>,
@@ -488,7 +1565,10 @@ pub async fn get_claim_txns(
 pub async fn get_related_txns(
     data: web::Data<Arc<RwLock<QueryServer>>>,
     info: web::Path<String>,
-) -> actix_web::Result<web::Json<HashSet<TxnSID>>> {
+    web::Query(paging): web::Query<PageQueryParams>,
+    version: web::Data<ApiVersion>,
+    web::Query(filter): web::Query<HeightFilterParams>,
+) -> actix_web::Result<PagedJson<TxnSID, HashSet<TxnSID>>> {
     // Convert from base64 representation
     let key: XfrPublicKey = XfrPublicKey::noah_from_bytes(
         &b64dec(&*info)
@@ -498,19 +1578,31 @@ pub async fn get_related_txns(
     .c(d!())
     .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
     let server = data.read();
-    let records = server.get_related_transactions(&XfrAddress { key });
-    Ok(web::Json(records.unwrap_or_default()))
+    let mut records = server
+        .get_related_transactions(&XfrAddress { key })
+        .unwrap_or_default()
+        .into_iter()
+        .collect::<Vec<_>>();
+    filter.retain(&server, &mut records);
+    records.sort();
+    paging.respond(*version, records, |records| records.into_iter().collect())
 }
 
 /// Returns the list of transfer transations associated with a given asset
 pub async fn get_related_xfrs(
     data: web::Data<Arc<RwLock<QueryServer>>>,
     info: web::Path<String>,
-) -> actix_web::Result<web::Json<HashSet<TxnSID>>> {
+    web::Query(paging): web::Query<PageQueryParams>,
+    version: web::Data<ApiVersion>,
+    web::Query(filter): web::Query<HeightFilterParams>,
+) -> actix_web::Result<PagedJson<TxnSID, HashSet<TxnSID>>> {
     let server = data.read();
     if let Ok(token_code) = AssetTypeCode::new_from_base64(&info) {
         if let Some(records) = server.get_related_transfers(&token_code) {
-            Ok(web::Json(records))
+            let mut records = records.into_iter().collect::<Vec<_>>();
+            filter.retain(&server, &mut records);
+            records.sort();
+            paging.respond(*version, records, |records| records.into_iter().collect())
         } else {
             Err(actix_web::error::ErrorNotFound(
                 "Specified asset definition does not currently exist.",
@@ -523,12 +1615,36 @@ pub async fn get_related_xfrs(
     }
 }
 
+/// Returns the txns containing an operation of a given kind, such as
+/// `Delegation`, see `Operation::KINDS`
+pub async fn get_txns_by_op_type(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    info: web::Path<String>,
+    web::Query(paging): web::Query<PageQueryParams>,
+    version: web::Data<ApiVersion>,
+    web::Query(filter): web::Query<HeightFilterParams>,
+) -> actix_web::Result<PagedJson<TxnSID, Vec<TxnSID>>> {
+    if !Operation::KINDS.contains(&info.as_str()) {
+        return Err(error::ErrorBadRequest(format!(
+            "Unknown operation type, should be one of: {}",
+            Operation::KINDS.join(", ")
+        )));
+    }
+
+    let server = data.read();
+    let mut records = server.get_txns_by_op_type(&info).unwrap_or_default();
+    filter.retain(&server, &mut records);
+    records.sort();
+    paging.respond(*version, records, |records| records)
+}
+
 #[allow(missing_docs)]
 #[allow(clippy::unnecessary_wraps)]
 
 pub async fn get_circulating_supply(
     data: web::Data<Arc<RwLock<QueryServer>>>,
 ) -> actix_web::Result<web::Json<BTreeMap<&'static str, f64>>, actix_web::error::Error> {
+    replica::check_staking()?;
     let l = data.read();
     let fra = FRA as f64;
 
@@ -553,6 +1669,7 @@ pub async fn get_circulating_supply(
 pub async fn get_total_supply(
     data: web::Data<Arc<RwLock<QueryServer>>>,
 ) -> actix_web::Result<web::Json<BTreeMap<&'static str, f64>>, actix_web::error::Error> {
+    replica::check_staking()?;
     let l = data.read();
     let burn_pubkey = XfrPublicKey::from_noah(&BLACK_HOLE_PUBKEY);
     let extra_pubkey = XfrPublicKey::from_noah(&FF_PK_EXTRA_120_0000);
@@ -584,6 +1701,31 @@ pub async fn get_total_supply(
     Ok(web::Json(res))
 }
 
+/// Returns the issued, burned and circulating nonconfidential amounts
+/// of an asset type
+pub async fn get_asset_supply(
+    req: HttpRequest,
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    info: web::Path<String>,
+) -> actix_web::Result<Cached<web::Json<AssetSupply>>> {
+    replica::check_staking()?;
+    let code = AssetTypeCode::new_from_base64(&info)
+        .c(d!())
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+    let server = data.read();
+    with_etag(&req, state_etag(&server), || {
+        server
+            .ledger_cloned
+            .get_asset_supply(&code)
+            .map(web::Json)
+            .ok_or_else(|| {
+                error::ErrorNotFound(
+                    "Specified asset definition does not currently exist.",
+                )
+            })
+    })
+}
+
 #[inline(always)]
 #[allow(missing_docs)]
 pub async fn get_checkpoint(
@@ -591,217 +1733,670 @@ pub async fn get_checkpoint(
     Ok(web::Json(CFG.checkpoint.clone()))
 }
 
+/// Methods of the routes, along with the GET and POST of the ones
+/// out of `QueryServerRoutes`, e.g. `/graphql`
+fn cors_methods() -> BTreeSet<String> {
+    QueryServerRoutes::iter()
+        .map(|r| r.method().to_uppercase())
+        .chain(["GET".to_owned(), "POST".to_owned()])
+        .collect()
+}
+
+/// Options of the query api http server
+#[derive(Clone, Debug)]
+pub struct QueryApiConfig {
+    /// allowed CORS origins, any origin is allowed if empty
+    pub allowed_origins: Vec<String>,
+    /// (cert, key) paths in PEM format, serve plain http if `None`
+    pub tls: Option<(String, String)>,
+    /// number of workers, default to the number of cpus
+    pub workers: Option<usize>,
+    /// max size of a request body, in bytes
+    pub max_payload_size: usize,
+    /// keep-alive timeout, in seconds
+    pub keep_alive: usize,
+    /// time to drain in-flight requests on shutdown, in seconds
+    pub shutdown_timeout: u64,
+    /// bearer token of the `/admin` routes, they are disabled if `None`
+    pub admin_token: Option<String>,
+    /// HTTP-date after which `/v1` may be removed, sent in `Sunset`
+    pub v1_sunset: Option<String>,
+    /// socket file to serve on along with the TCP addresses, in plain http,
+    /// see `--query-unix-socket`
+    pub unix_socket: Option<UnixSocket>,
+    /// whether the webhooks may target the hosts of the local network
+    pub webhook_allow_private: bool,
+}
+
+impl Default for QueryApiConfig {
+    fn default() -> Self {
+        QueryApiConfig {
+            allowed_origins: vec![],
+            tls: None,
+            workers: None,
+            max_payload_size: 256 * 1024,
+            keep_alive: 5,
+            shutdown_timeout: 30,
+            admin_token: None,
+            v1_sunset: None,
+            unix_socket: None,
+            webhook_allow_private: false,
+        }
+    }
+}
+
+impl QueryApiConfig {
+    /// Load options from env, and the unix socket from `--query-unix-socket`,
+    /// missing ones are set to default
+    pub fn from_env() -> Result<Self> {
+        let mut cfg = Self::default();
+
+        if let Ok(origins) = env::var("QUERY_API_ALLOWED_ORIGINS") {
+            cfg.allowed_origins = origins
+                .split(',')
+                .map(|o| o.trim().to_owned())
+                .filter(|o| !o.is_empty())
+                .collect();
+        }
+
+        match (
+            env::var("QUERY_API_TLS_CERT"),
+            env::var("QUERY_API_TLS_KEY"),
+        ) {
+            (Ok(cert), Ok(key)) => cfg.tls = Some((cert, key)),
+            (Err(_), Err(_)) => {}
+            _ => {
+                return Err(eg!(
+                    "QUERY_API_TLS_CERT and QUERY_API_TLS_KEY must be set together"
+                ));
+            }
+        }
+
+        if let Ok(n) = env::var("QUERY_API_WORKERS") {
+            cfg.workers = Some(n.parse::<usize>().c(d!())?);
+        }
+        if let Ok(n) = env::var("QUERY_API_MAX_PAYLOAD_SIZE") {
+            cfg.max_payload_size = n.parse::<usize>().c(d!())?;
+        }
+        if let Ok(n) = env::var("QUERY_API_KEEP_ALIVE") {
+            cfg.keep_alive = n.parse::<usize>().c(d!())?;
+        }
+        if let Ok(n) = env::var("QUERY_API_SHUTDOWN_TIMEOUT") {
+            cfg.shutdown_timeout = n.parse::<u64>().c(d!())?;
+        }
+        cfg.admin_token = env::var("QUERY_API_ADMIN_TOKEN")
+            .ok()
+            .filter(|t| !t.is_empty());
+        cfg.v1_sunset = env::var("QUERY_API_V1_SUNSET").ok();
+        cfg.unix_socket = UnixSocket::new(
+            CFG.query_unix_socket.as_deref(),
+            CFG.query_unix_socket_mode,
+        );
+        cfg.webhook_allow_private = env::var("QUERY_API_WEBHOOK_ALLOW_PRIVATE")
+            .map(|v| "true" == v || "1" == v)
+            .unwrap_or(false);
+
+        Ok(cfg)
+    }
+
+    fn check_admin(&self, req: &HttpRequest) -> actix_web::Result<()> {
+        let token = self
+            .admin_token
+            .as_ref()
+            .ok_or_else(|| error::ErrorNotFound("Admin routes are disabled."))?;
+        let bearer = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "));
+        if bearer == Some(token.as_str()) {
+            Ok(())
+        } else {
+            Err(error::ErrorUnauthorized("Invalid admin token."))
+        }
+    }
+
+    /// Headers marking the `/v1` responses as deprecated
+    fn deprecation(&self) -> middleware::DefaultHeaders {
+        let headers = middleware::DefaultHeaders::new()
+            .header("Deprecation", "true")
+            .header(header::LINK, "</v2>; rel=\"successor-version\"");
+        match self.v1_sunset.as_ref() {
+            Some(date) => headers.header("Sunset", date.as_str()),
+            None => headers,
+        }
+    }
+
+    fn cors(&self) -> Cors {
+        if self.allowed_origins.is_empty() {
+            return Cors::permissive().supports_credentials();
+        }
+
+        let methods = cors_methods();
+        self.allowed_origins
+            .iter()
+            .fold(Cors::default(), |cors, o| cors.allowed_origin(o))
+            .allowed_methods(methods.iter().map(String::as_str))
+            .allow_any_header()
+            .supports_credentials()
+    }
+
+    fn rustls_config(&self) -> Result<Option<ServerConfig>> {
+        let (cert, key) = if let Some(tls) = self.tls.as_ref() {
+            tls
+        } else {
+            return Ok(None);
+        };
+
+        let certs = certs(&mut BufReader::new(File::open(cert).c(d!(cert))?))
+            .map_err(|_| eg!("invalid TLS cert: {}", cert))?;
+        let mut keys =
+            pkcs8_private_keys(&mut BufReader::new(File::open(key).c(d!(key))?))
+                .map_err(|_| eg!("invalid TLS key: {}", key))?;
+        if keys.is_empty() {
+            return Err(eg!("no PKCS8 private key found in {}", key));
+        }
+
+        let mut cfg = ServerConfig::new(NoClientAuth::new());
+        cfg.set_single_cert(certs, keys.remove(0)).c(d!())?;
+        Ok(Some(cfg))
+    }
+}
+
+/// Liveness probe, the process is able to serve http
+#[allow(clippy::unnecessary_wraps)]
+pub async fn healthz() -> actix_web::Result<String> {
+    Ok("ok".into())
+}
+
+/// Readiness probe, the query cache has caught up with the ledger
+pub async fn readyz(data: web::Data<Arc<RwLock<QueryServer>>>) -> HttpResponse {
+    let server = data.read();
+    if server.is_ready() {
+        HttpResponse::Ok().body(format!("ready at {}", server.get_commits()))
+    } else {
+        let progress = *api_cache::LOST_DATA_PROGRESS.read();
+        if replica::sync_stopped() {
+            HttpResponse::ServiceUnavailable().body("not ready, replica sync stopped")
+        } else if !server.is_caught_up() {
+            HttpResponse::ServiceUnavailable().body(format!(
+                "not ready, catching up, last block {}s ago",
+                server.last_block_age()
+            ))
+        } else if progress.is_done() {
+            HttpResponse::ServiceUnavailable().body("not ready")
+        } else {
+            HttpResponse::ServiceUnavailable().body(format!(
+                "not ready, api cache recovery at {:.2}%",
+                progress.ratio() * 100.0
+            ))
+        }
+    }
+}
+
+/// Dump the api cache into a snapshot file under `--api-cache-snapshot-dir`,
+/// returns the path of the file
+pub async fn export_api_cache_snapshot(
+    req: HttpRequest,
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    cfg: web::Data<QueryApiConfig>,
+) -> actix_web::Result<web::Json<String>> {
+    cfg.check_admin(&req)?;
+
+    let server = Arc::clone(data.get_ref());
+    web::block(move || {
+        let server = server.read();
+        let dir = Path::new(&CFG.api_cache_snapshot_dir);
+        let path = dir.join(format!(
+            "api_cache-{}.snap",
+            server.ledger_cloned.get_block_count()
+        ));
+        fs::create_dir_all(dir)
+            .c(d!())
+            .and_then(|_| api_cache::dump_snapshot(&server.ledger_cloned, &path))
+            .map(|_| path.to_string_lossy().into_owned())
+            .map_err(|e| e.to_string())
+    })
+    .await
+    .map(web::Json)
+    .map_err(error::ErrorInternalServerError)
+}
+
+/// Enforce a retention policy on the api cache, the configured one if no
+/// policy is given, returns the number of dropped entries.
+/// Blocks are not committed while the cache is being pruned.
+pub async fn prune_api_cache(
+    req: HttpRequest,
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    cfg: web::Data<QueryApiConfig>,
+    policy: Option<web::Json<PruningPolicy>>,
+) -> actix_web::Result<web::Json<PruneStats>> {
+    cfg.check_admin(&req)?;
+
+    let policy = policy.map_or_else(PruningPolicy::from_cfg, |p| p.into_inner());
+    let server = Arc::clone(data.get_ref());
+    web::block(move || {
+        let ledger = Arc::clone(&server.read().ledger);
+        let stats = api_cache::prune_api_cache(&mut ledger.write(), &policy)
+            .map_err(|e| e.to_string())?;
+        info!("api cache pruned: {:?}", stats);
+        server.write().update();
+        Ok::<_, String>(stats)
+    })
+    .await
+    .map(web::Json)
+    .map_err(error::ErrorInternalServerError)
+}
+
+/// Routes served under each api version
+fn api_routes(cfg: &mut web::ServiceConfig) {
+    cfg.route(&QueryServerRoutes::Search.route(), web::get().to(search))
+        .service(
+            web::resource("get_total_supply").route(web::get().to(get_total_supply)),
+        )
+        .service(
+            web::resource("circulating_supply")
+                .route(web::get().to(get_circulating_supply)),
+        )
+        .route(
+            &QueryServerRoutes::GetAddress.with_arg_template("txo_sid"),
+            web::get().to(get_address),
+        )
+        .route(
+            &QueryServerRoutes::GetOwnedUtxos.with_arg_template("address"),
+            web::get().to(get_owned_utxos),
+        )
+        .route(
+            &QueryServerRoutes::GetOwnedAbars.with_arg_template("commitment"),
+            web::get().to(get_owned_abar),
+        )
+        .route(
+            &QueryServerRoutes::GetOwnerMemo.with_arg_template("txo_sid"),
+            web::get().to(get_owner_memo),
+        )
+        .route(
+            &QueryServerRoutes::GetOwnerMemosByAddress.with_arg_template("address"),
+            web::get().to(get_owner_memos_by_address),
+        )
+        .route(
+            &QueryServerRoutes::GetOwnerMemoBatch.with_arg_template("txo_sid_list"),
+            web::get().to(get_owner_memo_batch),
+        )
+        .route(
+            &QueryServerRoutes::GetAbarCommitment.with_arg_template("atxo_sid"),
+            web::get().to(get_abar_commitment),
+        )
+        .route(
+            &QueryServerRoutes::GetAbarMemo.with_arg_template("atxo_sid"),
+            web::get().to(get_abar_memo),
+        )
+        .route(
+            &QueryServerRoutes::GetAbarMemos.route(),
+            web::get().to(get_abar_memos),
+        )
+        .route(
+            &QueryServerRoutes::GetAbarProof.with_arg_template("atxo_sid"),
+            web::get().to(get_abar_proof),
+        )
+        .route(
+            &QueryServerRoutes::CheckNullifierHash.with_arg_template("null_hash"),
+            web::get().to(check_nullifier_hash),
+        )
+        .route(
+            &QueryServerRoutes::GetMaxATxoSid.route(),
+            web::get().to(get_max_atxo_sid),
+        )
+        .route(
+            &QueryServerRoutes::GetMaxATxoSidAtHeight.with_arg_template("height"),
+            web::get().to(get_max_atxo_sid_at_height),
+        )
+        .route(
+            &QueryServerRoutes::GetRelatedTxns.with_arg_template("address"),
+            web::get().to(get_related_txns),
+        )
+        .service(web::resource("claim_history").route(web::get().to(get_claim_txns)))
+        .service(
+            web::resource("coinbase_history")
+                .route(web::get().to(get_coinbase_oper_list)),
+        )
+        .route(
+            &QueryServerRoutes::GetRelatedXfrs.with_arg_template("asset_token"),
+            web::get().to(get_related_xfrs),
+        )
+        .route(
+            &QueryServerRoutes::GetCreatedAssets.with_arg_template("address"),
+            web::get().to(get_created_assets),
+        )
+        .route(
+            &QueryServerRoutes::GetIssuedRecords.with_arg_template("address"),
+            web::get().to(get_issued_records),
+        )
+        .route(
+            &QueryServerRoutes::GetIssuedRecordsByCode.with_arg_template("asset_token"),
+            web::get().to(get_issued_records_by_code),
+        )
+        .route(
+            &QueryServerRoutes::GetAuthencatedTxnIDHash.with_arg_template("txo_sid"),
+            web::get().to(get_authenticated_txnid_hash),
+        )
+        .route(
+            &QueryServerRoutes::GetTransactionHash.with_arg_template("txn_sid"),
+            web::get().to(get_transaction_hash),
+        )
+        .route(
+            &QueryServerRoutes::GetTransactionSid.with_arg_template("txn_hash"),
+            web::get().to(get_transaction_sid),
+        )
+        .route(
+            &QueryServerRoutes::GetCommits.route(),
+            web::get().to(get_commits),
+        )
+        .route(
+            &(QueryServerRoutes::GetBlocks.with_arg_template("start") + "/{end}"),
+            web::get().to(get_blocks),
+        )
+        .route(
+            &(QueryServerRoutes::GetAddressFilters.with_arg_template("start")
+                + "/{end}"),
+            web::get().to(get_address_filters),
+        )
+        .route(
+            &(QueryServerRoutes::GetBlockStats.with_arg_template("start") + "/{end}"),
+            web::get().to(get_block_stats),
+        )
+        .route(
+            &QueryServerRoutes::GetBlockStatsSummary.route(),
+            web::get().to(get_block_stats_summary),
+        )
+        .route(
+            &QueryServerRoutes::GetFeeSuggestion.route(),
+            web::get().to(get_fee_suggestion),
+        )
+        .route(
+            &QueryServerRoutes::GetValidators.route(),
+            web::get().to(get_validators),
+        )
+        .route(
+            &QueryServerRoutes::GetValidatorUptime.with_arg_template("address"),
+            web::get().to(get_validator_uptime),
+        )
+        .route(
+            &QueryServerRoutes::GetDelegationRewards.with_arg_template("address"),
+            web::get().to(rewards::get_delegation_rewards),
+        )
+        .route(
+            &QueryServerRoutes::GetGovernanceProposals.route(),
+            web::get().to(governance::get_governance_proposals),
+        )
+        .route(
+            &QueryServerRoutes::GetGovernanceProposal.with_arg_template("id"),
+            web::get().to(governance::get_governance_proposal),
+        )
+        .route(
+            &QueryServerRoutes::GetAssetSupply.with_arg_template("code"),
+            web::get().to(get_asset_supply),
+        )
+        .route(
+            &QueryServerRoutes::GetTxnDetail.with_arg_template("hash_or_sid"),
+            web::get().to(get_txn_detail),
+        )
+        .route(
+            &QueryServerRoutes::GetBalances.with_arg_template("address"),
+            web::get().to(get_balances),
+        )
+        .route(
+            &QueryServerRoutes::GetAddressStats.with_arg_template("address"),
+            web::get().to(get_address_stats),
+        )
+        .route(
+            &QueryServerRoutes::GetSpentUtxo.with_arg_template("txo_sid"),
+            web::get().to(get_spent_utxo),
+        )
+        .route(
+            &QueryServerRoutes::GetTxnsByOpType.with_arg_template("op_type"),
+            web::get().to(get_txns_by_op_type),
+        )
+        .route(
+            &QueryServerRoutes::GetAssetHolders.with_arg_template("asset_token"),
+            web::get().to(get_asset_holders),
+        )
+        .route(
+            &QueryServerRoutes::GetCoinbaseEntries.with_arg_template("address"),
+            web::get().to(get_coinbase_entries),
+        )
+        .route(
+            &QueryServerRoutes::GetTracingChallenge.with_arg_template("asset_token"),
+            web::post().to(tracer::new_tracing_challenge),
+        )
+        .route(
+            &QueryServerRoutes::GetTracedTransfers.with_arg_template("asset_token"),
+            web::post().to(tracer::get_traced_transfers),
+        )
+        .route(
+            &QueryServerRoutes::RegisterWebhook.route(),
+            web::post().to(webhook::register_webhook),
+        )
+        .route(
+            &QueryServerRoutes::RemoveWebhook.with_arg_template("id"),
+            web::delete().to(webhook::remove_webhook),
+        )
+        .route(
+            &QueryServerRoutes::GetPrivateRecords.with_arg_template("address"),
+            web::get().to(private_records::get_private_records),
+        )
+        .route(
+            &ApiRoutes::UtxoSid.with_arg_template("sid"),
+            web::get().to(query_utxo),
+        )
+        .route(
+            &ApiRoutes::UtxoSidLight.with_arg_template("sid"),
+            web::get().to(query_utxo_light),
+        )
+        .route(
+            &ApiRoutes::UtxoSidList.with_arg_template("sid_list"),
+            web::get().to(query_utxos),
+        )
+        .route(
+            &(ApiRoutes::UtxoStatusAtVersion.with_arg_template("sid") + "/{version}"),
+            web::get().to(query_utxo_status_at_version),
+        )
+        .route(
+            &ApiRoutes::AssetIssuanceNum.with_arg_template("code"),
+            web::get().to(query_asset_issuance_num),
+        )
+        .route(
+            &ApiRoutes::AssetToken.with_arg_template("code"),
+            web::get().to(query_asset),
+        )
+        .route(
+            &ApiRoutes::GetDerivedAssetCode.with_arg_template("code"),
+            web::get().to(get_derived_asset_code),
+        )
+        .route(
+            &ApiRoutes::GlobalState.route(),
+            web::get().to(query_global_state),
+        )
+        .route(
+            &ApiRoutes::TxnSid.with_arg_template("sid"),
+            web::get().to(query_txn),
+        )
+        .route(
+            &ApiRoutes::TxnSidLight.with_arg_template("sid"),
+            web::get().to(query_txn_light),
+        )
+        .route(
+            &ApiRoutes::GlobalStateVersion.with_arg_template("version"),
+            web::get().to(query_global_state_version),
+        )
+        .route(
+            &ApiRoutes::OwnedUtxos.with_arg_template("owner"),
+            web::get().to(query_owned_utxos),
+        )
+        .route(
+            &ApiRoutes::OwnedAbars.with_arg_template("owner"),
+            web::get().to(query_owned_abar),
+        )
+        .route(
+            &ApiRoutes::ValidatorList.route(),
+            web::get().to(query_validators),
+        )
+        .route(
+            &ApiRoutes::DelegationInfo.with_arg_template("XfrPublicKey"),
+            web::get().to(query_delegation_info),
+        )
+        .route(
+            &ApiRoutes::DelegatorList.with_arg_template("NodeAddress"),
+            web::get().to(query_delegator_list),
+        )
+        .service(
+            web::resource("/delegator_list")
+                .route(web::get().to(get_delegators_with_params)),
+        )
+        .service(
+            web::resource("/delegation_rewards")
+                .route(web::get().to(get_delegation_reward)),
+        )
+        .service(
+            web::resource("/validator_delegation")
+                .route(web::get().to(get_validator_delegation_history)),
+        )
+        .route(
+            &ApiRoutes::ValidatorDetail.with_arg_template("NodeAddress"),
+            web::get().to(query_validator_detail),
+        )
+        .service(
+            web::resource("/display_checkpoint").route(web::get().to(get_checkpoint)),
+        );
+}
+
 /// Structures exposed to the outside world
-pub struct QueryApi;
+pub struct QueryApi {
+    hdr: Server,
+}
 
 impl QueryApi {
     pub(crate) fn create(
         server: Arc<RwLock<QueryServer>>,
         addrs: &[(&str, u16)],
+        cfg: &QueryApiConfig,
     ) -> Result<QueryApi> {
         let _ = actix_rt::System::new("findora API");
 
+        let tls = cfg.rustls_config().c(d!())?;
+        let app_cfg = cfg.clone();
+        let schema = graphql::schema(Arc::clone(&server));
+
         let mut hdr = HttpServer::new(move || {
             App::new()
                 .wrap(middleware::Logger::default())
-                .wrap(Cors::permissive().supports_credentials())
+                .wrap(middleware::Compress::default())
+                .wrap(app_cfg.cors())
+                .app_data(web::PayloadConfig::new(app_cfg.max_payload_size))
+                .app_data(web::JsonConfig::default().limit(app_cfg.max_payload_size))
                 .data(Arc::clone(&server))
+                .data(schema.clone())
+                .data(app_cfg.clone())
                 .route("/ping", web::get().to(ping))
+                .route("/healthz", web::get().to(healthz))
+                .route("/readyz", web::get().to(readyz))
                 .route("/version", web::get().to(version))
-                .service(
-                    web::resource("get_total_supply")
-                        .route(web::get().to(get_total_supply)),
-                )
-                .service(
-                    web::resource("circulating_supply")
-                        .route(web::get().to(get_circulating_supply)),
-                )
-                .route(
-                    &QueryServerRoutes::GetAddress.with_arg_template("txo_sid"),
-                    web::get().to(get_address),
-                )
+                .route("/metrics", web::get().to(metrics))
+                .route("/openapi.json", web::get().to(openapi::get_openapi))
                 .route(
-                    &QueryServerRoutes::GetOwnedUtxos.with_arg_template("address"),
-                    web::get().to(get_owned_utxos),
-                )
-                .route(
-                    &QueryServerRoutes::GetOwnedAbars.with_arg_template("commitment"),
-                    web::get().to(get_owned_abar),
-                )
-                .route(
-                    &QueryServerRoutes::GetOwnerMemo.with_arg_template("txo_sid"),
-                    web::get().to(get_owner_memo),
-                )
-                .route(
-                    &QueryServerRoutes::GetOwnerMemoBatch
-                        .with_arg_template("txo_sid_list"),
-                    web::get().to(get_owner_memo_batch),
-                )
-                .route(
-                    &QueryServerRoutes::GetAbarCommitment.with_arg_template("atxo_sid"),
-                    web::get().to(get_abar_commitment),
-                )
-                .route(
-                    &QueryServerRoutes::GetAbarMemo.with_arg_template("atxo_sid"),
-                    web::get().to(get_abar_memo),
-                )
-                .route(
-                    &QueryServerRoutes::GetAbarMemos.route(),
-                    web::get().to(get_abar_memos),
-                )
-                .route(
-                    &QueryServerRoutes::GetAbarProof.with_arg_template("atxo_sid"),
-                    web::get().to(get_abar_proof),
-                )
-                .route(
-                    &QueryServerRoutes::CheckNullifierHash
-                        .with_arg_template("null_hash"),
-                    web::get().to(check_nullifier_hash),
-                )
-                .route(
-                    &QueryServerRoutes::GetMaxATxoSid.route(),
-                    web::get().to(get_max_atxo_sid),
-                )
-                .route(
-                    &QueryServerRoutes::GetMaxATxoSidAtHeight
-                        .with_arg_template("height"),
-                    web::get().to(get_max_atxo_sid_at_height),
-                )
-                .route(
-                    &QueryServerRoutes::GetRelatedTxns.with_arg_template("address"),
-                    web::get().to(get_related_txns),
+                    "/admin/api_cache_snapshot",
+                    web::post().to(export_api_cache_snapshot),
                 )
+                .route("/admin/api_cache_prune", web::post().to(prune_api_cache))
+                .route("/graphql", web::post().to(graphql::graphql))
+                .service(web::scope("/v2").data(ApiVersion::V2).configure(api_routes))
                 .service(
-                    web::resource("claim_history").route(web::get().to(get_claim_txns)),
+                    web::scope("/v1")
+                        .data(ApiVersion::V1)
+                        .wrap(app_cfg.deprecation())
+                        .configure(api_routes),
                 )
                 .service(
-                    web::resource("coinbase_history")
-                        .route(web::get().to(get_coinbase_oper_list)),
-                )
-                .route(
-                    &QueryServerRoutes::GetRelatedXfrs.with_arg_template("asset_token"),
-                    web::get().to(get_related_xfrs),
-                )
-                .route(
-                    &QueryServerRoutes::GetCreatedAssets.with_arg_template("address"),
-                    web::get().to(get_created_assets),
-                )
-                .route(
-                    &QueryServerRoutes::GetIssuedRecords.with_arg_template("address"),
-                    web::get().to(get_issued_records),
-                )
-                .route(
-                    &QueryServerRoutes::GetIssuedRecordsByCode
-                        .with_arg_template("asset_token"),
-                    web::get().to(get_issued_records_by_code),
-                )
-                .route(
-                    &QueryServerRoutes::GetAuthencatedTxnIDHash
-                        .with_arg_template("txo_sid"),
-                    web::get().to(get_authenticated_txnid_hash),
-                )
-                .route(
-                    &QueryServerRoutes::GetTransactionHash.with_arg_template("txn_sid"),
-                    web::get().to(get_transaction_hash),
-                )
-                .route(
-                    &QueryServerRoutes::GetTransactionSid.with_arg_template("txn_hash"),
-                    web::get().to(get_transaction_sid),
-                )
-                .route(
-                    &QueryServerRoutes::GetCommits.route(),
-                    web::get().to(get_commits),
-                )
-                .route(
-                    &ApiRoutes::UtxoSid.with_arg_template("sid"),
-                    web::get().to(query_utxo),
-                )
-                .route(
-                    &ApiRoutes::UtxoSidLight.with_arg_template("sid"),
-                    web::get().to(query_utxo_light),
-                )
-                .route(
-                    &ApiRoutes::UtxoSidList.with_arg_template("sid_list"),
-                    web::get().to(query_utxos),
-                )
-                .route(
-                    &ApiRoutes::AssetIssuanceNum.with_arg_template("code"),
-                    web::get().to(query_asset_issuance_num),
-                )
-                .route(
-                    &ApiRoutes::AssetToken.with_arg_template("code"),
-                    web::get().to(query_asset),
-                )
-                .route(
-                    &ApiRoutes::GetDerivedAssetCode.with_arg_template("code"),
-                    web::get().to(get_derived_asset_code),
-                )
-                .route(
-                    &ApiRoutes::GlobalState.route(),
-                    web::get().to(query_global_state),
-                )
-                .route(
-                    &ApiRoutes::TxnSid.with_arg_template("sid"),
-                    web::get().to(query_txn),
-                )
-                .route(
-                    &ApiRoutes::TxnSidLight.with_arg_template("sid"),
-                    web::get().to(query_txn_light),
-                )
-                .route(
-                    &ApiRoutes::GlobalStateVersion.with_arg_template("version"),
-                    web::get().to(query_global_state_version),
-                )
-                .route(
-                    &ApiRoutes::OwnedUtxos.with_arg_template("owner"),
-                    web::get().to(query_owned_utxos),
-                )
-                .route(
-                    &ApiRoutes::OwnedAbars.with_arg_template("owner"),
-                    web::get().to(query_owned_abar),
-                )
-                .route(
-                    &ApiRoutes::ValidatorList.route(),
-                    web::get().to(query_validators),
-                )
-                .route(
-                    &ApiRoutes::DelegationInfo.with_arg_template("XfrPublicKey"),
-                    web::get().to(query_delegation_info),
-                )
-                .route(
-                    &ApiRoutes::DelegatorList.with_arg_template("NodeAddress"),
-                    web::get().to(query_delegator_list),
-                )
-                .service(
-                    web::resource("/delegator_list")
-                        .route(web::get().to(get_delegators_with_params)),
-                )
-                .service(
-                    web::resource("/delegation_rewards")
-                        .route(web::get().to(get_delegation_reward)),
-                )
-                .service(
-                    web::resource("/validator_delegation")
-                        .route(web::get().to(get_validator_delegation_history)),
-                )
-                .route(
-                    &ApiRoutes::ValidatorDetail.with_arg_template("NodeAddress"),
-                    web::get().to(query_validator_detail),
-                )
-                .service(
-                    web::resource("/display_checkpoint")
-                        .route(web::get().to(get_checkpoint)),
+                    web::scope("")
+                        .data(ApiVersion::V1)
+                        .wrap(app_cfg.deprecation())
+                        .configure(api_routes),
                 )
         });
 
+        hdr = hdr
+            .keep_alive(cfg.keep_alive)
+            .shutdown_timeout(cfg.shutdown_timeout);
+        if let Some(n) = cfg.workers {
+            hdr = hdr.workers(n);
+        }
+
         for (host, port) in addrs.iter() {
-            hdr = hdr.bind(&format!("{host}:{port}")).c(d!())?
+            let addr = format!("{host}:{port}");
+            hdr = if let Some(tls) = tls.clone() {
+                hdr.bind_rustls(&addr, tls).c(d!())?
+            } else {
+                hdr.bind(&addr).c(d!())?
+            };
+        }
+        if let Some(sock) = cfg.unix_socket.as_ref() {
+            hdr = hdr
+                .listen_uds(sock.bind().c(d!())?)
+                .c(d!(sock.path.clone()))?;
         }
 
-        hdr.run();
+        let hdr = hdr.run();
 
         info!("Query server started");
 
-        Ok(QueryApi)
+        Ok(QueryApi { hdr })
+    }
+
+    /// Stop accepting new connections and wait for
+    /// in-flight requests to finish, up to `shutdown_timeout`
+    pub fn shutdown(&self) {
+        futures::executor::block_on(self.hdr.stop(true));
+        info!("Query server stopped");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, actix_web::http::StatusCode};
+
+    #[test]
+    fn paginate() {
+        let paging = |page, per_page| PageQueryParams { page, per_page };
+        let items = (0..45).collect::<Vec<_>>();
+
+        let p = paging(None, None).paginate(items.clone()).unwrap();
+        assert_eq!(
+            (p.page, p.per_page, p.next_page),
+            (1, DEFAULT_PER_PAGE, Some(2))
+        );
+        assert_eq!(p.items, (0..20).collect::<Vec<_>>());
+
+        let p = paging(Some(3), None).paginate(items.clone()).unwrap();
+        assert_eq!((p.total_count, p.next_page), (45, None));
+        assert_eq!(p.items, (40..45).collect::<Vec<_>>());
+        assert!(paging(Some(4), None)
+            .paginate(items.clone())
+            .unwrap()
+            .items
+            .is_empty());
+
+        for (page, per_page) in [(Some(0), None), (None, Some(0)), (None, Some(1001))] {
+            let e = paging(page, per_page).paginate(items.clone()).unwrap_err();
+            assert_eq!(e.as_response_error().status_code(), StatusCode::BAD_REQUEST);
+        }
+    }
+
+    #[test]
+    fn cors_methods_cover_routes() {
+        let methods = cors_methods();
+        for m in ["GET", "POST", "DELETE"] {
+            assert!(methods.contains(m), "{m}");
+        }
+        assert!(QueryServerRoutes::iter()
+            .all(|r| methods.contains(&r.method().to_uppercase())));
     }
 }
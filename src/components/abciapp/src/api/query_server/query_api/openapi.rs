@@ -0,0 +1,532 @@
+//!
+//! OpenAPI document of the query server routes
+//!
+
+use {
+    super::{
+        governance::ProposalInfo,
+        rewards::DelegationRewards,
+        tracer::{TracedTransfer, TracingChallenge, TracingQuery},
+        webhook::{Webhook, WebhookId},
+        AddressBalances, AssetHolder, BlockAddressFilter, BlockStatsEntry, BlockTxns,
+        CoinbaseHistory, Paged, QueryServerRoutes, SearchHit, SpentUtxo, TxnDetail,
+        ValidatorUptime,
+    },
+    actix_web::web,
+    finutils::api::NetworkRoute,
+    ledger::{
+        data_model::{ATxoSID, TxnIDHash, TxnSID, TxoSID},
+        store::{
+            api_cache::AddressStats,
+            stats::{BlockStatsSummary, FeeSuggestion},
+            supply::AssetSupply,
+            validators::ValidatorSet,
+        },
+    },
+    schemars::{
+        gen::{SchemaGenerator, SchemaSettings},
+        schema::Schema,
+        JsonSchema,
+    },
+    serde_json::{json, Map, Value},
+    strum::IntoEnumIterator,
+};
+
+/// Zei types, e.g. memos and records, have no schema and are left free-form
+type Opaque = Value;
+
+/// Body of the paged routes, a plain list on `/v1` without paging arguments
+#[allow(dead_code)]
+#[derive(JsonSchema)]
+#[schemars(untagged)]
+enum PagedBody<T> {
+    List(Vec<T>),
+    Page(Paged<T>),
+}
+
+const PAGE_PARAMS: &[(&str, &str)] = &[("page", "integer"), ("per_page", "integer")];
+
+const HEIGHT_FILTER_PARAMS: &[(&str, &str)] = &[
+    ("page", "integer"),
+    ("per_page", "integer"),
+    ("from_height", "integer"),
+    ("to_height", "integer"),
+    ("since", "integer"),
+];
+
+impl QueryServerRoutes {
+    /// (name, type) of the path arguments
+    fn path_args(&self) -> &'static [(&'static str, &'static str)] {
+        match *self {
+            QueryServerRoutes::GetAddress
+            | QueryServerRoutes::GetOwnerMemo
+            | QueryServerRoutes::GetSpentUtxo
+            | QueryServerRoutes::GetAuthencatedTxnIDHash => &[("txo_sid", "integer")],
+            QueryServerRoutes::GetOwnerMemoBatch => &[("txo_sid_list", "string")],
+            QueryServerRoutes::GetOwnedUtxos
+            | QueryServerRoutes::GetCreatedAssets
+            | QueryServerRoutes::GetIssuedRecords
+            | QueryServerRoutes::GetRelatedTxns
+            | QueryServerRoutes::GetBalances
+            | QueryServerRoutes::GetAddressStats
+            | QueryServerRoutes::GetCoinbaseEntries
+            | QueryServerRoutes::GetPrivateRecords
+            | QueryServerRoutes::GetDelegationRewards
+            | QueryServerRoutes::GetOwnerMemosByAddress => &[("address", "string")],
+            QueryServerRoutes::GetOwnedAbars => &[("commitment", "string")],
+            QueryServerRoutes::GetAbarCommitment
+            | QueryServerRoutes::GetAbarMemo
+            | QueryServerRoutes::GetAbarProof => &[("atxo_sid", "integer")],
+            QueryServerRoutes::CheckNullifierHash => &[("null_hash", "string")],
+            QueryServerRoutes::GetMaxATxoSidAtHeight => &[("height", "integer")],
+            QueryServerRoutes::GetIssuedRecordsByCode
+            | QueryServerRoutes::GetRelatedXfrs
+            | QueryServerRoutes::GetTracingChallenge
+            | QueryServerRoutes::GetTracedTransfers
+            | QueryServerRoutes::GetAssetHolders => &[("asset_token", "string")],
+            QueryServerRoutes::GetAssetSupply => &[("code", "string")],
+            QueryServerRoutes::GetTransactionHash => &[("txn_sid", "integer")],
+            QueryServerRoutes::GetTransactionSid => &[("txn_hash", "string")],
+            QueryServerRoutes::GetBlocks
+            | QueryServerRoutes::GetAddressFilters
+            | QueryServerRoutes::GetBlockStats => {
+                &[("start", "integer"), ("end", "integer")]
+            }
+            QueryServerRoutes::GetTxnDetail => &[("hash_or_sid", "string")],
+            QueryServerRoutes::RemoveWebhook
+            | QueryServerRoutes::GetGovernanceProposal => &[("id", "integer")],
+            QueryServerRoutes::GetTxnsByOpType => &[("op_type", "string")],
+            QueryServerRoutes::GetValidatorUptime => &[("address", "string")],
+            QueryServerRoutes::GetAbarMemos
+            | QueryServerRoutes::GetMaxATxoSid
+            | QueryServerRoutes::GetCommits
+            | QueryServerRoutes::Search
+            | QueryServerRoutes::RegisterWebhook
+            | QueryServerRoutes::GetBlockStatsSummary
+            | QueryServerRoutes::GetFeeSuggestion
+            | QueryServerRoutes::GetValidators
+            | QueryServerRoutes::GetGovernanceProposals => &[],
+        }
+    }
+
+    /// (name, type) of the query arguments
+    fn query_args(&self) -> &'static [(&'static str, &'static str)] {
+        match *self {
+            QueryServerRoutes::GetAbarMemos => {
+                &[("start", "integer"), ("end", "integer")]
+            }
+            QueryServerRoutes::GetOwnedUtxos
+            | QueryServerRoutes::GetIssuedRecords
+            | QueryServerRoutes::GetIssuedRecordsByCode
+            | QueryServerRoutes::GetAssetHolders
+            | QueryServerRoutes::GetPrivateRecords
+            | QueryServerRoutes::GetGovernanceProposals => PAGE_PARAMS,
+            QueryServerRoutes::GetRelatedTxns
+            | QueryServerRoutes::GetRelatedXfrs
+            | QueryServerRoutes::GetTxnsByOpType => HEIGHT_FILTER_PARAMS,
+            QueryServerRoutes::Search => &[("q", "string")],
+            QueryServerRoutes::GetBlockStatsSummary
+            | QueryServerRoutes::GetFeeSuggestion => &[("blocks", "integer")],
+            QueryServerRoutes::GetValidatorUptime => {
+                &[("from_height", "integer"), ("to_height", "integer")]
+            }
+            QueryServerRoutes::GetDelegationRewards => &[
+                ("epoch_size", "integer"),
+                ("from_height", "integer"),
+                ("to_height", "integer"),
+                ("format", "string"),
+            ],
+            QueryServerRoutes::GetCoinbaseEntries => &[
+                ("page", "integer"),
+                ("per_page", "integer"),
+                ("from_height", "integer"),
+                ("to_height", "integer"),
+            ],
+            _ => &[],
+        }
+    }
+
+    /// Whether a query argument must be given
+    fn query_arg_required(&self, name: &str) -> bool {
+        matches!(
+            (self, name),
+            (QueryServerRoutes::GetAbarMemos, "start" | "end")
+                | (QueryServerRoutes::Search, "q")
+        )
+    }
+
+    /// One-line description of the route
+    fn summary(&self) -> &'static str {
+        match *self {
+            QueryServerRoutes::GetAddress => "Owner address of a txo",
+            QueryServerRoutes::GetOwnerMemo => "Owner memo of a txo",
+            QueryServerRoutes::GetOwnerMemoBatch => {
+                "Owner memos of a ',' separated list of txos"
+            }
+            QueryServerRoutes::GetOwnedUtxos => "Utxo sids spendable by an address",
+            QueryServerRoutes::GetOwnerMemosByAddress => {
+                "Owner memos of the utxos owned by an address"
+            }
+            QueryServerRoutes::GetOwnedAbars => "ATxo sid of an abar commitment",
+            QueryServerRoutes::GetAbarCommitment => "Commitment of an abar",
+            QueryServerRoutes::GetAbarMemo => "Owner memo of an abar",
+            QueryServerRoutes::GetAbarMemos => "Owner memos of abars in [start, end]",
+            QueryServerRoutes::GetAbarProof => "Merkle proof of an abar",
+            QueryServerRoutes::CheckNullifierHash => "Whether a nullifier is spent",
+            QueryServerRoutes::GetMaxATxoSid => "Max ATxo sid",
+            QueryServerRoutes::GetMaxATxoSidAtHeight => "Max ATxo sid at a height",
+            QueryServerRoutes::GetCreatedAssets => "Assets defined by an issuer",
+            QueryServerRoutes::GetIssuedRecords => "Records issued by an issuer",
+            QueryServerRoutes::GetIssuedRecordsByCode => "Records issued of an asset",
+            QueryServerRoutes::GetRelatedTxns => "Txns related to an address",
+            QueryServerRoutes::GetRelatedXfrs => "Transfers of a nonconfidential asset",
+            QueryServerRoutes::GetTxnsByOpType => "Txns containing an operation of a kind",
+            QueryServerRoutes::GetCoinbaseEntries => {
+                "Coinbase entries of an address in a height range, with their totals"
+            }
+            QueryServerRoutes::GetAssetHolders => {
+                "Holders of a nonconfidential asset and their amounts, richest first"
+            }
+            QueryServerRoutes::GetAuthencatedTxnIDHash => {
+                "Sid and hash of the txn creating a txo"
+            }
+            QueryServerRoutes::GetTransactionHash => "Hash of a txn",
+            QueryServerRoutes::GetTransactionSid => "Sid of a txn",
+            QueryServerRoutes::GetCommits => "Block commit count of the server",
+            QueryServerRoutes::GetBlocks => "Txns of the ledger blocks in [start, end]",
+            QueryServerRoutes::GetAddressFilters => {
+                "Bloom filters of the addresses involved in the ledger blocks in [start, end]"
+            }
+            QueryServerRoutes::GetTxnDetail => "Decoded view of a txn",
+            QueryServerRoutes::GetBalances => "Per-asset balances of an address",
+            QueryServerRoutes::GetAddressStats => {
+                "First-seen height, txn count and amounts sent and received by an address"
+            }
+            QueryServerRoutes::GetSpentUtxo => "Sid, hash and height of the txn spending a txo",
+            QueryServerRoutes::Search => {
+                "Search txns, assets, txos and addresses by hash, code, sid or key"
+            }
+            QueryServerRoutes::GetTracingChallenge => {
+                "Nonces encrypted to the tracer keys of an asset, to prove holding one"
+            }
+            QueryServerRoutes::GetTracedTransfers => {
+                "Transfers of the blocks in [start, end] traced by the policy of a challenge"
+            }
+            QueryServerRoutes::RegisterWebhook => {
+                "Register a webhook of new block events, admin token required"
+            }
+            QueryServerRoutes::RemoveWebhook => "Remove a webhook, admin token required",
+            QueryServerRoutes::GetBlockStats => {
+                "Txn count, fees, bytes and operation mix of the ledger blocks in [start, end]"
+            }
+            QueryServerRoutes::GetBlockStatsSummary => {
+                "Sums of the block stats of the most recent blocks, 100 by default"
+            }
+            QueryServerRoutes::GetPrivateRecords => {
+                "Unspent confidential records of an address with their owner memos, to be opened by its owner"
+            }
+            QueryServerRoutes::GetValidators => {
+                "Voting power, commission, delegations and signed blocks of the validators"
+            }
+            QueryServerRoutes::GetValidatorUptime => {
+                "Blocks signed by a validator over a height range, 10000 by default"
+            }
+            QueryServerRoutes::GetDelegationRewards => {
+                "Rewards claimed by a delegator by epochs, with a trailing APY, in JSON or CSV"
+            }
+            QueryServerRoutes::GetGovernanceProposals => {
+                "Governance proposals penalizing byzantine validators, by id"
+            }
+            QueryServerRoutes::GetGovernanceProposal => {
+                "Proposers, target and penalty of a governance proposal"
+            }
+            QueryServerRoutes::GetAssetSupply => {
+                "Issued, burned and circulating nonconfidential amounts of an asset"
+            }
+            QueryServerRoutes::GetFeeSuggestion => {
+                "Low, medium and high fee levels paid over the most recent blocks, 100 by default"
+            }
+        }
+    }
+
+    /// Schema of the 200 response
+    fn response_schema(&self, gen: &mut SchemaGenerator) -> Schema {
+        match *self {
+            QueryServerRoutes::GetAddress | QueryServerRoutes::GetTransactionHash => {
+                gen.subschema_for::<String>()
+            }
+            QueryServerRoutes::GetOwnerMemo
+            | QueryServerRoutes::GetAbarCommitment
+            | QueryServerRoutes::GetAbarMemo
+            | QueryServerRoutes::GetAbarProof => gen.subschema_for::<Option<Opaque>>(),
+            QueryServerRoutes::GetOwnerMemoBatch => {
+                gen.subschema_for::<Vec<Option<Opaque>>>()
+            }
+            QueryServerRoutes::GetOwnedUtxos => gen.subschema_for::<PagedBody<TxoSID>>(),
+            QueryServerRoutes::GetOwnerMemosByAddress => {
+                gen.subschema_for::<Vec<(TxoSID, Opaque)>>()
+            }
+            QueryServerRoutes::GetOwnedAbars => gen.subschema_for::<Option<ATxoSID>>(),
+            QueryServerRoutes::GetAbarMemos => gen.subschema_for::<Vec<(u64, Opaque)>>(),
+            QueryServerRoutes::CheckNullifierHash => gen.subschema_for::<Option<bool>>(),
+            QueryServerRoutes::GetMaxATxoSid
+            | QueryServerRoutes::GetMaxATxoSidAtHeight => {
+                gen.subschema_for::<Option<usize>>()
+            }
+            QueryServerRoutes::GetCreatedAssets => gen.subschema_for::<Vec<Opaque>>(),
+            QueryServerRoutes::GetIssuedRecords
+            | QueryServerRoutes::GetIssuedRecordsByCode => {
+                gen.subschema_for::<PagedBody<(Opaque, Option<Opaque>)>>()
+            }
+            QueryServerRoutes::GetRelatedTxns
+            | QueryServerRoutes::GetRelatedXfrs
+            | QueryServerRoutes::GetTxnsByOpType => {
+                gen.subschema_for::<PagedBody<TxnSID>>()
+            }
+            QueryServerRoutes::GetAuthencatedTxnIDHash => {
+                gen.subschema_for::<TxnIDHash>()
+            }
+            QueryServerRoutes::GetTransactionSid => gen.subschema_for::<usize>(),
+            QueryServerRoutes::GetCommits => gen.subschema_for::<u64>(),
+            QueryServerRoutes::GetBlocks => gen.subschema_for::<Vec<BlockTxns>>(),
+            QueryServerRoutes::GetTxnDetail => gen.subschema_for::<TxnDetail>(),
+            QueryServerRoutes::GetBalances => gen.subschema_for::<AddressBalances>(),
+            QueryServerRoutes::Search => gen.subschema_for::<Vec<SearchHit>>(),
+            QueryServerRoutes::GetTracingChallenge => {
+                gen.subschema_for::<TracingChallenge>()
+            }
+            QueryServerRoutes::GetTracedTransfers => {
+                gen.subschema_for::<Vec<TracedTransfer>>()
+            }
+            QueryServerRoutes::RegisterWebhook => gen.subschema_for::<WebhookId>(),
+            QueryServerRoutes::RemoveWebhook => gen.subschema_for::<bool>(),
+            QueryServerRoutes::GetAddressStats => gen.subschema_for::<AddressStats>(),
+            QueryServerRoutes::GetSpentUtxo => gen.subschema_for::<SpentUtxo>(),
+            QueryServerRoutes::GetAssetHolders => {
+                gen.subschema_for::<PagedBody<AssetHolder>>()
+            }
+            QueryServerRoutes::GetCoinbaseEntries => {
+                gen.subschema_for::<CoinbaseHistory>()
+            }
+            QueryServerRoutes::GetAddressFilters => {
+                gen.subschema_for::<Vec<BlockAddressFilter>>()
+            }
+            QueryServerRoutes::GetPrivateRecords => {
+                gen.subschema_for::<PagedBody<Opaque>>()
+            }
+            QueryServerRoutes::GetBlockStats => {
+                gen.subschema_for::<Vec<BlockStatsEntry>>()
+            }
+            QueryServerRoutes::GetBlockStatsSummary => {
+                gen.subschema_for::<BlockStatsSummary>()
+            }
+            QueryServerRoutes::GetValidators => gen.subschema_for::<ValidatorSet>(),
+            QueryServerRoutes::GetValidatorUptime => {
+                gen.subschema_for::<ValidatorUptime>()
+            }
+            QueryServerRoutes::GetDelegationRewards => {
+                gen.subschema_for::<DelegationRewards>()
+            }
+            QueryServerRoutes::GetGovernanceProposals => {
+                gen.subschema_for::<PagedBody<ProposalInfo>>()
+            }
+            QueryServerRoutes::GetGovernanceProposal => {
+                gen.subschema_for::<ProposalInfo>()
+            }
+            QueryServerRoutes::GetAssetSupply => gen.subschema_for::<AssetSupply>(),
+            QueryServerRoutes::GetFeeSuggestion => gen.subschema_for::<FeeSuggestion>(),
+        }
+    }
+
+    /// Schema of the JSON body, if any
+    fn request_schema(&self, gen: &mut SchemaGenerator) -> Option<Schema> {
+        match *self {
+            QueryServerRoutes::RegisterWebhook => Some(gen.subschema_for::<Webhook>()),
+            QueryServerRoutes::GetTracedTransfers => {
+                Some(gen.subschema_for::<TracingQuery>())
+            }
+            _ => None,
+        }
+    }
+
+    /// Media type of the 200 response
+    fn content_type(&self) -> &'static str {
+        match *self {
+            QueryServerRoutes::GetAddress => "text/plain",
+            _ => "application/json",
+        }
+    }
+
+    /// HTTP method of the route
+    pub(super) fn method(&self) -> &'static str {
+        match *self {
+            QueryServerRoutes::GetTracingChallenge
+            | QueryServerRoutes::GetTracedTransfers
+            | QueryServerRoutes::RegisterWebhook => "post",
+            QueryServerRoutes::RemoveWebhook => "delete",
+            _ => "get",
+        }
+    }
+
+    /// Path of the route in OpenAPI template form
+    fn openapi_path(&self) -> String {
+        self.path_args()
+            .iter()
+            .fold(self.route(), |p, (name, _)| format!("{p}/{{{name}}}"))
+    }
+}
+
+fn parameter(name: &str, typ: &str, location: &str, required: bool) -> Value {
+    json!({
+        "name": name,
+        "in": location,
+        "required": required,
+        "schema": { "type": typ },
+    })
+}
+
+/// Build the OpenAPI 3 document of `QueryServerRoutes`
+pub fn spec() -> Value {
+    let mut gen = SchemaSettings::openapi3().into_generator();
+    let mut paths = Map::new();
+    for r in QueryServerRoutes::iter() {
+        let parameters = r
+            .path_args()
+            .iter()
+            .map(|(name, typ)| parameter(name, typ, "path", true))
+            .chain(r.query_args().iter().map(|(name, typ)| {
+                parameter(name, typ, "query", r.query_arg_required(name))
+            }))
+            .collect::<Vec<_>>();
+        let mut op = json!({
+            "summary": r.summary(),
+            "parameters": parameters,
+            "responses": {
+                "200": {
+                    "description": "OK",
+                    "content": {
+                        r.content_type(): { "schema": r.response_schema(&mut gen) },
+                    },
+                },
+                "400": { "description": "Invalid argument" },
+                "404": { "description": "Not found" },
+            },
+        });
+        if let Some(schema) = r.request_schema(&mut gen) {
+            op["requestBody"] = json!({
+                "required": true,
+                "content": { "application/json": { "schema": schema } },
+            });
+        }
+
+        // several methods may share a path
+        if let Value::Object(ops) = paths
+            .entry(r.openapi_path())
+            .or_insert_with(|| Value::Object(Map::new()))
+        {
+            ops.insert(r.method().to_owned(), op);
+        }
+    }
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "Findora query server",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "servers": [
+            { "url": "/v2" },
+            { "url": "/v1", "description": "Deprecated, lists are not paged by default" },
+        ],
+        "paths": paths,
+        "components": { "schemas": gen.take_definitions() },
+    })
+}
+
+/// Returns the OpenAPI document of the query server
+#[allow(clippy::unnecessary_wraps)]
+pub async fn get_openapi() -> actix_web::Result<web::Json<Value>> {
+    Ok(web::Json(spec()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `$ref`s of a document
+    fn refs<'a>(v: &'a Value, out: &mut Vec<&'a str>) {
+        match v {
+            Value::Object(m) => m.iter().for_each(|(k, v)| match v {
+                Value::String(r) if k == "$ref" => out.push(r),
+                _ => refs(v, out),
+            }),
+            Value::Array(a) => a.iter().for_each(|v| refs(v, out)),
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn spec_covers_every_route() {
+        let spec = spec();
+
+        let mut ops = 0;
+        for r in QueryServerRoutes::iter() {
+            let op = &spec["paths"][r.openapi_path()][r.method()];
+            assert!(op.is_object(), "{} is missing", r.route());
+            let schema = &op["responses"]["200"]["content"][r.content_type()]["schema"];
+            assert!(!schema.is_null(), "{} has no response schema", r.route());
+
+            let params = op["parameters"].as_array().unwrap();
+            for (name, _) in r.path_args() {
+                assert!(
+                    params.iter().any(|p| p["name"] == *name
+                        && p["in"] == "path"
+                        && p["required"] == true),
+                    "{} misses {}",
+                    r.route(),
+                    name
+                );
+            }
+            ops += 1;
+        }
+        let documented = spec["paths"]
+            .as_object()
+            .unwrap()
+            .values()
+            .map(|p| p.as_object().unwrap().len())
+            .sum::<usize>();
+        assert_eq!(ops, documented);
+
+        let schemas = spec["components"]["schemas"].as_object().unwrap();
+        let mut all = vec![];
+        refs(&spec, &mut all);
+        for r in all {
+            let name = r.strip_prefix("#/components/schemas/").unwrap();
+            assert!(schemas.contains_key(name), "{r} is not defined");
+        }
+    }
+
+    #[test]
+    fn spec_required_args() {
+        let spec = spec();
+
+        let path = QueryServerRoutes::GetAbarMemos.openapi_path();
+        let memos = &spec["paths"][path]["get"]["parameters"];
+        for p in memos.as_array().unwrap() {
+            assert_eq!(p["required"], true);
+        }
+        let path = QueryServerRoutes::GetAssetHolders.openapi_path();
+        let holders = &spec["paths"][path]["get"]["parameters"];
+        assert!(holders
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|p| p["in"] == "query")
+            .all(|p| p["required"] == false));
+
+        let traced = &spec["components"]["schemas"]["TracingQuery"]["required"];
+        for field in ["challenge", "nonce", "start", "end"] {
+            assert!(traced.as_array().unwrap().contains(&json!(field)));
+        }
+    }
+}
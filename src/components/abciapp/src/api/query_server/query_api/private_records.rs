@@ -0,0 +1,110 @@
+//!
+//! Confidential records of an address, for its owner to decrypt locally.
+//!
+//! The query server never receives any key: the owner memos can only be
+//! opened with the secret key of the address, there is no view-only key
+//! in the key scheme of the ledger, so the balances are computed by the
+//! client, see `PrivateRecord::open`.
+//!
+
+use {
+    super::{server::QueryServer, ApiVersion, PageQueryParams, PagedJson},
+    actix_web::{error, web},
+    finutils::api::PrivateRecord,
+    globutils::wallet,
+    ledger::data_model::TxoSID,
+    parking_lot::RwLock,
+    std::sync::Arc,
+    zei::BlindAssetRecord,
+};
+
+/// Whether the amount or the asset type of a record is confidential
+fn is_confidential(record: &BlindAssetRecord) -> bool {
+    record.asset_type.get_asset_type().is_none() || record.amount.get_amount().is_none()
+}
+
+/// Returns the unspent confidential records of an address along with
+/// their owner memos, sorted by sid
+pub async fn get_private_records(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    address: web::Path<String>,
+    web::Query(paging): web::Query<PageQueryParams>,
+    version: web::Data<ApiVersion>,
+) -> actix_web::Result<PagedJson<PrivateRecord, Vec<PrivateRecord>>> {
+    let key = wallet::public_key_from_base64(address.as_str())
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
+    let server = data.read();
+    let mut records = server
+        .ledger_cloned
+        .get_owned_utxos(&key)
+        .map_err(error::ErrorServiceUnavailable)?
+        .into_iter()
+        .filter(|(_, (utxo, _))| is_confidential(&utxo.0.record))
+        .map(|(txo_sid, (utxo, owner_memo))| PrivateRecord {
+            txo_sid,
+            record: utxo.0.record,
+            owner_memo,
+        })
+        .collect::<Vec<_>>();
+    records.sort_by_key(|r| r.txo_sid);
+
+    paging.respond(*version, records, |records| records)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        ledger::data_model::AssetTypeCode,
+        rand_chacha::ChaChaRng,
+        rand_core::SeedableRng,
+        zei::{
+            noah_algebra::ristretto::PedersenCommitmentRistretto,
+            noah_api::xfr::{
+                asset_record::{build_blind_asset_record, AssetRecordType},
+                structs::AssetRecordTemplate,
+            },
+            XfrKeyPair,
+        },
+    };
+
+    #[test]
+    fn open_private_record() {
+        let mut prng = ChaChaRng::from_entropy();
+        let kp = XfrKeyPair::generate(&mut prng);
+        let code = AssetTypeCode::gen_random();
+        let record = |art| {
+            let template = AssetRecordTemplate::with_no_asset_tracing(
+                7,
+                code.val,
+                art,
+                kp.get_pk_ref().into_noah(),
+            );
+            let (ba, _, memo) = build_blind_asset_record(
+                &mut prng,
+                &PedersenCommitmentRistretto::default(),
+                &template,
+                vec![],
+            );
+            (BlindAssetRecord::from_noah(&ba), memo)
+        };
+
+        let (plain, _) =
+            record(AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType);
+        assert!(!is_confidential(&plain));
+
+        let (blind, memo) =
+            record(AssetRecordType::ConfidentialAmount_ConfidentialAssetType);
+        assert!(is_confidential(&blind));
+        let private = PrivateRecord {
+            txo_sid: TxoSID(1),
+            record: blind,
+            owner_memo: memo.map(|m| zei::OwnerMemo::from_noah(&m).unwrap()),
+        };
+        assert_eq!(private.open(&kp).unwrap(), (code, 7));
+        assert!(private
+            .open(&XfrKeyPair::generate(&mut ChaChaRng::from_entropy()))
+            .is_err());
+    }
+}
@@ -0,0 +1,171 @@
+//!
+//! Read-replica mode of the query server,
+//! follows the blocks of a primary node instead of running consensus
+//!
+//! The txns are replayed without the system operations of the begin and end
+//! of the blocks, so the staking state, the coinbase history and the supplies
+//! derived from them are not followed: their routes answer 503, see `check_staking`.
+//!
+
+use {
+    super::{server::BLOCK_CREATED, BlockTxns, QueryServerRoutes, MAX_BLOCK_RANGE},
+    actix_web::error,
+    finutils::api::NetworkRoute,
+    lazy_static::lazy_static,
+    ledger::{
+        data_model::TxnEffect,
+        store::{api_cache, LedgerState},
+    },
+    parking_lot::RwLock,
+    ruc::*,
+    std::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        thread,
+        time::Duration,
+    },
+    tracing::{error, info},
+};
+
+/// Wait between two polls of a primary that has no new blocks
+const POLL_ITV: Duration = Duration::from_secs(2);
+
+/// Timeout of one fetch from the primary
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+lazy_static! {
+    /// Whether the query server is a replica, set before serving
+    pub(crate) static ref IS_REPLICA: AtomicBool = AtomicBool::new(false);
+    /// Whether the sync with the primary has stopped, the replica is stale then
+    static ref SYNC_STOPPED: AtomicBool = AtomicBool::new(false);
+}
+
+/// Marks the sync as stopped when the sync thread exits, even by a panic
+struct SyncGuard;
+
+impl Drop for SyncGuard {
+    fn drop(&mut self) {
+        SYNC_STOPPED.store(true, Ordering::Release);
+    }
+}
+
+/// Whether the replica has stopped following the primary
+pub(crate) fn sync_stopped() -> bool {
+    SYNC_STOPPED.load(Ordering::Acquire)
+}
+
+/// Fails with 503 on a replica, for the routes of the staking state
+pub(crate) fn check_staking() -> actix_web::Result<()> {
+    if IS_REPLICA.load(Ordering::Acquire) {
+        return Err(error::ErrorServiceUnavailable(
+            "The staking state is not followed by a query replica, ask the primary.",
+        ));
+    }
+    Ok(())
+}
+
+/// Fetch the blocks of the primary from `start`, at most `MAX_BLOCK_RANGE`
+fn fetch_blocks(primary: &str, start: usize) -> Result<Vec<BlockTxns>> {
+    let url = format!(
+        "{}/v2{}/{}/{}",
+        primary.trim_end_matches('/'),
+        QueryServerRoutes::GetBlocks.route(),
+        start,
+        start + MAX_BLOCK_RANGE - 1
+    );
+    attohttpc::get(&url)
+        .timeout(FETCH_TIMEOUT)
+        .send()
+        .c(d!())?
+        .error_for_status()
+        .c(d!())?
+        .json::<Vec<BlockTxns>>()
+        .c(d!())
+}
+
+/// Re-apply one block of the primary and update the api cache,
+/// the txns have been validated by the primary, so they are trusted
+fn apply_block(ledger: &mut LedgerState, block: BlockTxns) -> Result<()> {
+    if block.block_sid != ledger.get_block_count() {
+        return Err(eg!(format!(
+            "unexpected block: {}, local block count: {}",
+            block.block_sid,
+            ledger.get_block_count()
+        )));
+    }
+
+    let mut effect = ledger.start_block().c(d!())?;
+    let mut expected = vec![];
+    for body in block.txns {
+        let txe = TxnEffect::compute_effect(body.txn).c(d!())?;
+        let tmp_sid = ledger.apply_trusted_transaction(&mut effect, txe).c(d!())?;
+        expected.push((tmp_sid, body.txn_sid));
+    }
+    let tsm = ledger.finish_block(effect).c(d!())?;
+
+    for (tmp_sid, txn_sid) in expected {
+        let (local_sid, _) = tsm.get(&tmp_sid).c(d!())?;
+        if *local_sid != txn_sid {
+            return Err(eg!(format!(
+                "diverged from the primary: txn {:?} applied as {:?}",
+                txn_sid, local_sid
+            )));
+        }
+    }
+
+    if let Some(height) = block.height {
+        ledger.set_tendermint_height(height);
+    }
+    if let Some(time) = block.time {
+        ledger.set_tendermint_time(time);
+    }
+
+    api_cache::update_api_cache(ledger).c(d!())
+}
+
+/// Keep the local ledger in sync with the primary in the background,
+/// fetch failures are retried, while a failure to apply a block stops the sync,
+/// as the local ledger can not be trusted anymore
+pub(crate) fn start_sync(ledger: Arc<RwLock<LedgerState>>, primary: String) {
+    info!("Query replica following {}", primary);
+    thread::spawn(move || {
+        let _guard = SyncGuard;
+        sync(&ledger, &primary)
+    });
+}
+
+fn sync(ledger: &RwLock<LedgerState>, primary: &str) {
+    loop {
+        let start = ledger.read().get_block_count();
+        let blocks = match fetch_blocks(primary, start) {
+            Ok(blocks) => blocks,
+            Err(e) => {
+                error!("Query replica fetch failed: {}", e.generate_log(None));
+                thread::sleep(POLL_ITV);
+                continue;
+            }
+        };
+
+        let n = blocks.len();
+        {
+            let mut ledger = ledger.write();
+            for block in blocks {
+                if let Err(e) = apply_block(&mut ledger, block) {
+                    error!("Query replica stopped: {}", e.generate_log(None));
+                    return;
+                }
+            }
+        }
+
+        if 0 < n {
+            let mut created = BLOCK_CREATED.0.lock();
+            *created = true;
+            BLOCK_CREATED.1.notify_one();
+        }
+        if n < MAX_BLOCK_RANGE {
+            thread::sleep(POLL_ITV);
+        }
+    }
+}
@@ -0,0 +1,255 @@
+//!
+//! Reward history of a delegator, from the claims of its coinbase history.
+//!
+//! The claims are summed up by epochs of a fixed number of blocks, aligned on
+//! height 0, and exported as JSON or CSV for dashboards and tax accounting.
+//!
+//! Rewards only show up when they are claimed, so the trailing APY is an
+//! estimate: the claims of the last epochs over the current bond.
+//!
+
+use {
+    super::{replica, server::QueryServer},
+    actix_web::{error, http::header, web, HttpResponse},
+    globutils::wallet,
+    ledger::{
+        data_model::XfrAddress,
+        staking::{
+            ops::mint_fra::MintKind, BlockHeight, DelegationState, BLOCK_INTERVAL,
+        },
+    },
+    parking_lot::RwLock,
+    schemars::JsonSchema,
+    serde::{Deserialize, Serialize},
+    std::{fmt::Write, sync::Arc},
+};
+
+/// Blocks of an epoch by default, about a day
+const EPOCH_SIZE: BlockHeight = 5760;
+
+/// Epochs summed up by the trailing APY, about a month by default
+const APY_EPOCHS: u64 = 30;
+
+const SECS_PER_YEAR: u128 = 365 * 24 * 3600;
+
+#[allow(missing_docs)]
+#[derive(Debug, Deserialize)]
+pub struct RewardQueryParams {
+    /// default to `EPOCH_SIZE`
+    epoch_size: Option<BlockHeight>,
+    from_height: Option<BlockHeight>,
+    /// default to the current height
+    to_height: Option<BlockHeight>,
+    /// `json` by default, or `csv`
+    format: Option<String>,
+}
+
+/// Rewards claimed in the blocks of [start_height, end_height]
+#[allow(missing_docs)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+pub struct RewardEpoch {
+    pub start_height: BlockHeight,
+    pub end_height: BlockHeight,
+    pub claim_cnt: u64,
+    pub amount: u64,
+    /// block time of the last claim, in seconds, if indexed
+    pub last_claim_time: Option<i64>,
+}
+
+/// Claimed rewards of a delegator, the epochs without claim are omitted
+#[allow(missing_docs)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct DelegationRewards {
+    pub address: String,
+    pub epoch_size: BlockHeight,
+    pub total_amount: u64,
+    /// current bond of the delegator
+    pub bond_amount: u64,
+    /// annual rate of the claims of the last `APY_EPOCHS` epochs over
+    /// `bond_amount`, as [numerator, denominator], `None` without bond
+    pub trailing_apy: Option<[u128; 2]>,
+    pub epochs: Vec<RewardEpoch>,
+}
+
+/// Sum up the (height, amount, time) of the claims, sorted by height, by epochs
+fn sum_epochs(
+    claims: impl IntoIterator<Item = (BlockHeight, u64, Option<i64>)>,
+    epoch_size: BlockHeight,
+) -> Vec<RewardEpoch> {
+    let mut epochs: Vec<RewardEpoch> = vec![];
+    for (height, amount, time) in claims {
+        let start_height = height - height % epoch_size;
+        match epochs.last_mut() {
+            Some(e) if e.start_height == start_height => {
+                e.claim_cnt += 1;
+                e.amount = e.amount.saturating_add(amount);
+                e.last_claim_time = time.or(e.last_claim_time);
+            }
+            _ => epochs.push(RewardEpoch {
+                start_height,
+                end_height: start_height + epoch_size - 1,
+                claim_cnt: 1,
+                amount,
+                last_claim_time: time,
+            }),
+        }
+    }
+    epochs
+}
+
+/// Annual rate of the claims of the `APY_EPOCHS` epochs up to the one of
+/// `to_height`, over `bond_amount`
+fn trailing_apy(
+    epochs: &[RewardEpoch],
+    to_height: BlockHeight,
+    epoch_size: BlockHeight,
+    bond_amount: u64,
+) -> Option<[u128; 2]> {
+    if 0 == bond_amount {
+        return None;
+    }
+    let end = to_height - to_height % epoch_size + epoch_size;
+    let start = end.saturating_sub(APY_EPOCHS * epoch_size);
+    let amount = epochs
+        .iter()
+        .filter(|e| start <= e.start_height && e.start_height < end)
+        .map(|e| e.amount as u128)
+        .sum::<u128>();
+    let secs = (end - start) as u128 * *BLOCK_INTERVAL as u128;
+    Some([amount * SECS_PER_YEAR, bond_amount as u128 * secs])
+}
+
+fn to_csv(rewards: &DelegationRewards) -> String {
+    let mut csv =
+        "start_height,end_height,claim_cnt,amount,last_claim_time\n".to_owned();
+    for e in rewards.epochs.iter() {
+        let time = e.last_claim_time.map(|t| t.to_string()).unwrap_or_default();
+        let _ = writeln!(
+            csv,
+            "{},{},{},{},{}",
+            e.start_height, e.end_height, e.claim_cnt, e.amount, time
+        );
+    }
+    csv
+}
+
+/// Returns the rewards claimed by a delegator, by epochs,
+/// with a trailing APY estimate, in JSON or CSV
+pub async fn get_delegation_rewards(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    address: web::Path<String>,
+    web::Query(params): web::Query<RewardQueryParams>,
+) -> actix_web::Result<HttpResponse> {
+    replica::check_staking()?;
+    let key = wallet::public_key_from_base64(address.as_str())
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+    let epoch_size = params.epoch_size.unwrap_or(EPOCH_SIZE);
+    if 0 == epoch_size {
+        return Err(error::ErrorBadRequest("epoch_size should be positive."));
+    }
+    let csv = match params.format.as_deref() {
+        None | Some("json") => false,
+        Some("csv") => true,
+        Some(_) => return Err(error::ErrorBadRequest("format should be json or csv.")),
+    };
+
+    let server = data.read();
+    let ledger = &server.ledger_cloned;
+    let staking = ledger.get_staking();
+    let from_height = params.from_height.unwrap_or(0);
+    let to_height = params.to_height.unwrap_or_else(|| staking.cur_height());
+    if from_height > to_height {
+        return Err(error::ErrorBadRequest("from_height is above to_height."));
+    }
+
+    let height_to_time = ledger.api_cache.as_ref().map(|api| &api.height_to_time);
+    let claims = server
+        .get_coinbase_entries_in_range(&XfrAddress { key }, from_height, to_height)
+        .into_iter()
+        .filter(|(_, entry)| MintKind::Claim == entry.kind)
+        .map(|(h, entry)| (h, entry.amount, height_to_time.and_then(|m| m.get(&h))));
+    let epochs = sum_epochs(claims, epoch_size);
+
+    let bond_amount = staking
+        .delegation_get(&key)
+        .filter(|d| DelegationState::Bond == d.state)
+        .map(|d| d.amount())
+        .unwrap_or(0);
+
+    let rewards = DelegationRewards {
+        address: address.into_inner(),
+        epoch_size,
+        total_amount: epochs.iter().map(|e| e.amount).sum(),
+        bond_amount,
+        trailing_apy: trailing_apy(&epochs, to_height, epoch_size, bond_amount),
+        epochs,
+    };
+
+    if csv {
+        Ok(HttpResponse::Ok()
+            .content_type("text/csv")
+            .header(
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"delegation_rewards.csv\"",
+            )
+            .body(to_csv(&rewards)))
+    } else {
+        Ok(HttpResponse::Ok().json(rewards))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sum_claims_by_epochs() {
+        let claims = vec![
+            (5, 10, Some(1)),
+            (9, 20, None),
+            (10, 5, Some(3)),
+            (35, 1, None),
+        ];
+        let epochs = sum_epochs(claims, 10);
+        assert_eq!(
+            epochs
+                .iter()
+                .map(|e| (e.start_height, e.end_height, e.claim_cnt, e.amount))
+                .collect::<Vec<_>>(),
+            vec![(0, 9, 2, 30), (10, 19, 1, 5), (30, 39, 1, 1)]
+        );
+        assert_eq!(epochs[0].last_claim_time, Some(1));
+        assert_eq!(epochs[2].last_claim_time, None);
+
+        let rewards = DelegationRewards {
+            address: String::new(),
+            epoch_size: 10,
+            total_amount: 36,
+            bond_amount: 0,
+            trailing_apy: None,
+            epochs,
+        };
+        assert_eq!(
+            to_csv(&rewards),
+            "start_height,end_height,claim_cnt,amount,last_claim_time\n\
+             0,9,2,30,1\n10,19,1,5,3\n30,39,1,1,\n"
+        );
+    }
+
+    #[test]
+    fn trailing_apy_of_claims() {
+        let epochs = sum_epochs(vec![(0, 1000, None), (5, 100, None)], 10);
+        assert_eq!(trailing_apy(&epochs, 5, 10, 0), None);
+
+        // the last epochs, up to the one of `to_height`
+        assert_eq!(
+            trailing_apy(&epochs, 5, 10, 1_000_000),
+            Some([
+                1100 * SECS_PER_YEAR,
+                1_000_000 * 10 * *BLOCK_INTERVAL as u128
+            ])
+        );
+        let far = APY_EPOCHS * 10 + 5;
+        assert_eq!(trailing_apy(&epochs, far, 10, 1).unwrap()[0], 0);
+    }
+}
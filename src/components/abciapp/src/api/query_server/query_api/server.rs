@@ -3,25 +3,49 @@
 //!
 
 use {
+    super::replica,
+    crate::abci::IS_EXITING,
     globutils::HashOf,
     lazy_static::lazy_static,
     ledger::{
         data_model::{
-            ATxoSID, AssetTypeCode, DefineAsset, IssuerPublicKey, StateCommitmentData,
-            Transaction, TxOutput, TxnIDHash, TxnSID, TxoSID, XfrAddress,
+            ATxoSID, AssetTypeCode, DefineAsset, FinalizedTransaction, IssuerPublicKey,
+            StateCommitmentData, Transaction, TxOutput, TxnIDHash, TxnSID, TxoSID,
+            XfrAddress,
+        },
+        staking::{ops::mint_fra::MintEntry, BlockHeight, BLOCK_INTERVAL},
+        store::{
+            api_cache::{
+                AddressFilter, AddressStats, ApiCache, LOST_DATA_PROGRESS,
+                SEARCH_PREFIX_LEN,
+            },
+            fbnc::Mapx,
+            LedgerState,
         },
-        staking::{ops::mint_fra::MintEntry, BlockHeight},
-        store::LedgerState,
     },
     parking_lot::{Condvar, Mutex, RwLock},
     ruc::*,
-    std::{collections::HashSet, sync::Arc},
+    std::{
+        collections::HashSet,
+        sync::{atomic::Ordering, Arc},
+        time::{Duration, SystemTime, UNIX_EPOCH},
+    },
     zei::{
         noah_api::anon_xfr::structs::{AxfrOwnerMemo, Commitment, MTLeafInfo},
         OwnerMemo,
     },
 };
 
+/// Max number of blocks the cache may lag behind the ledger while still ready
+const MAX_READY_LAG: u64 = 2;
+
+/// Max number of block intervals since the last block while still ready,
+/// the blocks replayed by a catching up node are older than that
+const MAX_READY_BLOCK_AGE: u64 = 8;
+
+/// Time to wait for the live ledger busy with a commit, to compare the heights
+const READY_LOCK_TIMEOUT: Duration = Duration::from_millis(500);
+
 lazy_static! {
     /// the query_server will be notified every time
     /// a block is added to the ledgerState to update the data
@@ -142,6 +166,28 @@ impl QueryServer {
         Ok((0, vec![]))
     }
 
+    /// Returns the coinbase entries of a given address in [from, to], by height
+    pub fn get_coinbase_entries_in_range(
+        &self,
+        address: &XfrAddress,
+        from: BlockHeight,
+        to: BlockHeight,
+    ) -> Vec<(BlockHeight, MintEntry)> {
+        let mut entries = self
+            .ledger_cloned
+            .api_cache
+            .as_ref()
+            .and_then(|api| api.coinbase_oper_hist.get(address))
+            .map(|hist| {
+                hist.iter()
+                    .filter(|(h, _)| from <= *h && *h <= to)
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        entries.sort_unstable_by_key(|(h, _)| *h);
+        entries
+    }
+
     /// Returns a list of claim transactions of a given ledger address
     pub fn get_claim_transactions(
         &self,
@@ -227,6 +273,24 @@ impl QueryServer {
             .map(|d| d.iter().map(|(k, _)| k).collect())
     }
 
+    /// Returns the height of the block containing a given txn_sid.
+    #[inline(always)]
+    pub fn get_transaction_height(&self, txn_sid: TxnSID) -> Option<BlockHeight> {
+        self.ledger_cloned
+            .api_cache
+            .as_ref()
+            .and_then(|api| api.txn_sid_to_height.get(&txn_sid))
+    }
+
+    /// Returns the time of the block at a given height, in seconds.
+    #[inline(always)]
+    pub fn get_block_time(&self, height: BlockHeight) -> Option<i64> {
+        self.ledger_cloned
+            .api_cache
+            .as_ref()
+            .and_then(|api| api.height_to_time.get(&height))
+    }
+
     /// Returns the set of transfer transactions that are associated with a given asset.
     /// The asset type must be nonconfidential.
     #[inline(always)]
@@ -287,6 +351,125 @@ impl QueryServer {
             .get(&txn_hash)
     }
 
+    /// Seconds since the time of the last cached block
+    pub fn last_block_age(&self) -> u64 {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let last = u64::try_from(self.ledger_cloned.get_tendermint_time()).unwrap_or(0);
+        now.saturating_sub(last)
+    }
+
+    /// Whether the chain is caught up with, judging by the age of the last block
+    pub fn is_caught_up(&self) -> bool {
+        self.last_block_age() <= MAX_READY_BLOCK_AGE * *BLOCK_INTERVAL
+    }
+
+    /// Whether the cached ledger has caught up with the chain and the live one,
+    /// the live ledger busy for more than `READY_LOCK_TIMEOUT` counts as lagging.
+    pub fn is_ready(&self) -> bool {
+        if IS_EXITING.load(Ordering::Acquire)
+            || replica::sync_stopped()
+            || self.ledger_cloned.api_cache.is_none()
+            || !LOST_DATA_PROGRESS.read().is_done()
+            || !self.is_caught_up()
+        {
+            return false;
+        }
+        self.ledger
+            .try_read_for(READY_LOCK_TIMEOUT)
+            .map_or(false, |l| {
+                l.get_block_commit_count() <= self.get_commits() + MAX_READY_LAG
+            })
+    }
+
+    /// Returns the txn hashes starting with `prefix`, at least
+    /// `SEARCH_PREFIX_LEN` chars are required.
+    pub fn search_txn_hashes(&self, prefix: &str, limit: usize) -> Vec<String> {
+        self.search_prefix(prefix, limit, |api| &api.txn_hash_prefixes)
+    }
+
+    /// Returns the base64 asset codes starting with `prefix`, at least
+    /// `SEARCH_PREFIX_LEN` chars are required.
+    pub fn search_asset_codes(&self, prefix: &str, limit: usize) -> Vec<String> {
+        self.search_prefix(prefix, limit, |api| &api.asset_code_prefixes)
+    }
+
+    fn search_prefix(
+        &self,
+        prefix: &str,
+        limit: usize,
+        index: impl Fn(&ApiCache) -> &Mapx<String, Vec<String>>,
+    ) -> Vec<String> {
+        if prefix.len() < SEARCH_PREFIX_LEN
+            || !prefix.is_char_boundary(SEARCH_PREFIX_LEN)
+        {
+            return vec![];
+        }
+        self.ledger_cloned
+            .api_cache
+            .as_ref()
+            .and_then(|api| index(api).get(&prefix[..SEARCH_PREFIX_LEN].to_owned()))
+            .map(|keys| {
+                keys.into_iter()
+                    .filter(|k| k.starts_with(prefix))
+                    .take(limit)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Returns the number of transactions related to a given address.
+    #[inline(always)]
+    pub fn get_related_transaction_count(&self, address: &XfrAddress) -> Option<usize> {
+        self.ledger_cloned
+            .api_cache
+            .as_ref()
+            .and_then(|api| api.related_transactions.get(address))
+            .map(|d| d.len())
+    }
+
+    /// Returns the txns containing an operation of a given kind.
+    #[inline(always)]
+    pub fn get_txns_by_op_type(&self, op_type: &str) -> Option<Vec<TxnSID>> {
+        self.ledger_cloned
+            .api_cache
+            .as_ref()
+            .and_then(|api| api.txns_by_op_type.get(&op_type.to_owned()))
+            .map(|d| d.iter().map(|(k, _)| k).collect())
+    }
+
+    /// Returns the owners of the utxos of a given nonconfidential asset type.
+    #[inline(always)]
+    pub fn get_asset_holders(
+        &self,
+        code: &AssetTypeCode,
+    ) -> Option<HashSet<XfrAddress>> {
+        self.ledger_cloned
+            .api_cache
+            .as_ref()
+            .and_then(|api| api.asset_holders.get(code))
+    }
+
+    /// Returns the cumulative transfer statistics of a given address.
+    #[inline(always)]
+    pub fn get_address_stats(&self, address: &XfrAddress) -> Option<AddressStats> {
+        self.ledger_cloned
+            .api_cache
+            .as_ref()
+            .and_then(|api| api.address_stats.get(address))
+    }
+
+    /// Returns the sid of the txn spending a given txo and its height,
+    /// `None` if the txo is unspent or unknown.
+    #[inline(always)]
+    pub fn get_spent_utxo(&self, txo_sid: TxoSID) -> Option<(TxnSID, BlockHeight)> {
+        self.ledger_cloned
+            .api_cache
+            .as_ref()
+            .and_then(|api| api.spent_utxos.get(&txo_sid))
+    }
+
     /// Returns most recent commits at query_server side.
     #[inline(always)]
     pub fn get_commits(&self) -> u64 {
@@ -379,6 +562,36 @@ impl QueryServer {
             .and_then(|api| api.height_to_max_atxo.get(&height).unwrap_or(None))
     }
 
+    /// Returns the finalized transactions of the blocks between start and end,
+    /// include start and end, blocks out of range are skipped.
+    pub fn get_blocks(
+        &self,
+        start: usize,
+        end: usize,
+    ) -> Vec<(usize, Vec<FinalizedTransaction>)> {
+        let end = min!(end, self.ledger_cloned.get_block_count().saturating_sub(1));
+        (start..=end)
+            .filter_map(|idx| self.ledger_cloned.blocks.get(idx).map(|b| (idx, b.txns)))
+            .collect()
+    }
+
+    /// Returns the address filters of the ledger blocks in [start, end],
+    /// blocks indexed before the filters were introduced have none
+    pub fn get_address_filters(
+        &self,
+        start: usize,
+        end: usize,
+    ) -> Vec<(usize, AddressFilter)> {
+        let filters = match self.ledger_cloned.api_cache.as_ref() {
+            Some(api) => &api.address_filters,
+            None => return vec![],
+        };
+        let end = min!(end, self.ledger_cloned.get_block_count().saturating_sub(1));
+        (start..=end)
+            .filter_map(|idx| filters.get(&(idx as u64)).map(|f| (idx, f)))
+            .collect()
+    }
+
     /// retrieve block reward rate at specified block height
     #[inline(always)]
     pub fn query_block_rewards_rate(&self, height: &BlockHeight) -> Option<[u128; 2]> {
@@ -5,31 +5,48 @@
 use {
     super::{
         server::{QueryServer, BLOCK_CREATED},
-        QueryApi,
+        webhook, QueryApi, QueryApiConfig,
     },
+    lazy_static::lazy_static,
     ledger::store::LedgerState,
-    parking_lot::RwLock,
+    parking_lot::{Mutex, RwLock},
     ruc::*,
     std::{sync::Arc, thread},
 };
 
+lazy_static! {
+    /// handle of the running query api, used for graceful shutdown
+    static ref QUERY_API: Mutex<Option<QueryApi>> = Mutex::new(None);
+}
+
 pub(crate) fn start_query_server(
     ledger: Arc<RwLock<LedgerState>>,
     addrs: &[(&str, u16)],
+    cfg: &QueryApiConfig,
 ) -> Result<Arc<RwLock<QueryServer>>> {
     let qs = Arc::new(RwLock::new(QueryServer::new(ledger)));
     let qs1 = Arc::clone(&qs);
     let qs2 = Arc::clone(&qs);
 
-    QueryApi::create(qs1, addrs).c(d!()).map(|_| {
+    QueryApi::create(qs1, addrs, cfg).c(d!()).map(|api| {
+        *QUERY_API.lock() = Some(api);
+        webhook::start_delivery_workers(cfg);
         thread::spawn(move || loop {
             let mut created = BLOCK_CREATED.0.lock();
             if !*created {
                 BLOCK_CREATED.1.wait(&mut created);
             }
             qs2.write().update();
+            webhook::notify(&qs2.read());
             *created = false;
         });
         qs
     })
 }
+
+/// Drain and stop the query api if it is running
+pub fn stop_query_server() {
+    if let Some(api) = QUERY_API.lock().take() {
+        api.shutdown();
+    }
+}
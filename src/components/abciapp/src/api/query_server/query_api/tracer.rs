@@ -0,0 +1,286 @@
+//!
+//! Transfers of a traced asset along with their tracer memos, for its tracers only.
+//!
+//! A tracer proves it holds the tracer key of a tracing policy by a challenge:
+//! `/tracing_challenge` encrypts a random nonce to the `lock_info_enc_key` of
+//! each policy of the asset, and `/traced_transfers` only serves the transfers
+//! traced by the policy whose nonce is sent back decrypted. A challenge can be
+//! answered once, within `CHALLENGE_TTL`.
+//!
+
+use {
+    super::{server::QueryServer, MAX_BLOCK_RANGE},
+    actix_web::{error, web},
+    lazy_static::lazy_static,
+    ledger::data_model::{AssetTypeCode, Operation, TxnSID},
+    parking_lot::{Mutex, RwLock},
+    rand_chacha::ChaChaRng,
+    rand_core::{RngCore, SeedableRng},
+    ruc::*,
+    schemars::JsonSchema,
+    serde::{Deserialize, Serialize},
+    std::{
+        collections::HashMap,
+        sync::Arc,
+        time::{Duration, Instant},
+    },
+    zei::{
+        noah_api::xfr::structs::TracingPolicy,
+        noah_crypto::hybrid_encryption::{hybrid_encrypt_x25519, Ctext, XPublicKey},
+        XfrBody,
+    },
+};
+
+/// Time to answer a challenge
+const CHALLENGE_TTL: Duration = Duration::from_secs(60);
+
+/// Max count of the pending challenges
+const MAX_CHALLENGES: usize = 4096;
+
+const NONCE_LEN: usize = 32;
+
+struct PendingChallenge {
+    code: AssetTypeCode,
+    /// one nonce per tracing policy, in the order of the policies
+    nonces: Vec<[u8; NONCE_LEN]>,
+    expires: Instant,
+}
+
+lazy_static! {
+    static ref CHALLENGES: Mutex<HashMap<u64, PendingChallenge>> =
+        Mutex::new(HashMap::new());
+}
+
+/// A nonce encrypted to the tracer key of a tracing policy
+#[allow(missing_docs)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct EncryptedNonce {
+    /// `lock_info_enc_key` of the policy
+    #[schemars(with = "serde_json::Value")]
+    pub enc_key: XPublicKey,
+    #[schemars(with = "serde_json::Value")]
+    pub ctext: Ctext,
+}
+
+/// Challenge of the tracers of an asset: decrypt the nonce of your policy
+/// with its `lock_info_dec_key`, then send it back in a `TracingQuery`
+#[allow(missing_docs)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct TracingChallenge {
+    pub id: u64,
+    pub nonces: Vec<EncryptedNonce>,
+}
+
+/// Answer of a `TracingChallenge`, along with the block range to scan,
+/// include start and end
+#[allow(missing_docs)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct TracingQuery {
+    pub challenge: u64,
+    /// the decrypted nonce in hex
+    pub nonce: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// A transfer carrying tracer memos of the queried asset,
+/// open it with the tracer key, e.g. by `trace_assets`
+#[allow(missing_docs)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct TracedTransfer {
+    pub txn_sid: TxnSID,
+    /// index of the operation in the txn
+    pub op_idx: usize,
+    #[schemars(with = "serde_json::Value")]
+    pub xfr: XfrBody,
+}
+
+fn new_challenge(
+    code: AssetTypeCode,
+    enc_keys: &[XPublicKey],
+) -> Result<TracingChallenge> {
+    let now = Instant::now();
+    let mut challenges = CHALLENGES.lock();
+    challenges.retain(|_, c| now < c.expires);
+    if challenges.len() >= MAX_CHALLENGES {
+        return Err(eg!("Too many pending challenges."));
+    }
+
+    let mut prng = ChaChaRng::from_entropy();
+    let mut nonces = vec![];
+    let mut encrypted = vec![];
+    for enc_key in enc_keys {
+        let mut nonce = [0; NONCE_LEN];
+        prng.fill_bytes(&mut nonce);
+        encrypted.push(EncryptedNonce {
+            enc_key: enc_key.clone(),
+            ctext: hybrid_encrypt_x25519(&mut prng, enc_key, &nonce),
+        });
+        nonces.push(nonce);
+    }
+
+    let id = prng.next_u64();
+    challenges.insert(
+        id,
+        PendingChallenge {
+            code,
+            nonces,
+            expires: now + CHALLENGE_TTL,
+        },
+    );
+    Ok(TracingChallenge {
+        id,
+        nonces: encrypted,
+    })
+}
+
+/// Consume a challenge, whatever the answer,
+/// returns the index of the policy whose nonce is `nonce`
+fn answer_challenge(code: &AssetTypeCode, id: u64, nonce: &[u8]) -> Option<usize> {
+    let challenge = CHALLENGES.lock().remove(&id)?;
+    if challenge.code != *code || challenge.expires <= Instant::now() {
+        return None;
+    }
+    challenge.nonces.iter().position(|n| n[..] == *nonce)
+}
+
+/// Tracing policies of an asset
+fn tracing_policies(
+    server: &QueryServer,
+    asset_token: &str,
+) -> actix_web::Result<(AssetTypeCode, Vec<TracingPolicy>)> {
+    let code = AssetTypeCode::new_from_base64(asset_token)
+        .c(d!())
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+    let asset = server
+        .ledger_cloned
+        .get_asset_type(&code)
+        .ok_or_else(|| error::ErrorNotFound("Specified asset does not exist."))?;
+    let policies = asset
+        .properties
+        .asset_rules
+        .tracing_policies
+        .get_policies()
+        .to_vec();
+    Ok((code, policies))
+}
+
+/// Issue a challenge to the tracers of an asset, see `TracingChallenge`
+pub async fn new_tracing_challenge(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    info: web::Path<String>,
+) -> actix_web::Result<web::Json<TracingChallenge>> {
+    let (code, policies) = tracing_policies(&data.read(), &info)?;
+    if policies.is_empty() {
+        return Err(error::ErrorNotFound("The asset is not traced."));
+    }
+
+    let enc_keys = policies
+        .iter()
+        .map(|p| p.enc_keys.lock_info_enc_key.clone())
+        .collect::<Vec<_>>();
+    new_challenge(code, &enc_keys)
+        .map(web::Json)
+        .map_err(|e| error::ErrorServiceUnavailable(e.to_string()))
+}
+
+/// Returns the transfers in a block range whose tracer memos are encrypted
+/// to the tracing policy proven by the challenge, limit 100 blocks
+pub async fn get_traced_transfers(
+    data: web::Data<Arc<RwLock<QueryServer>>>,
+    info: web::Path<String>,
+    web::Json(query): web::Json<TracingQuery>,
+) -> actix_web::Result<web::Json<Vec<TracedTransfer>>> {
+    if query.end < query.start || query.end - query.start >= MAX_BLOCK_RANGE {
+        return Err(error::ErrorBadRequest(format!("Limit {MAX_BLOCK_RANGE}")));
+    }
+    let nonce = hex::decode(&query.nonce)
+        .c(d!())
+        .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+
+    let server = data.read();
+    let (code, policies) = tracing_policies(&server, &info)?;
+    let enc_keys = answer_challenge(&code, query.challenge, &nonce)
+        .and_then(|idx| policies.get(idx))
+        .map(|p| p.enc_keys.clone())
+        .ok_or_else(|| error::ErrorForbidden("Invalid or expired challenge."))?;
+
+    let traced = server
+        .get_blocks(query.start, query.end)
+        .into_iter()
+        .flat_map(|(_, txns)| txns)
+        .flat_map(|ftx| {
+            let txn_sid = ftx.tx_id;
+            ftx.txn.body.operations.into_iter().enumerate().filter_map(
+                move |(op_idx, op)| match op {
+                    Operation::TransferAsset(i) => Some(TracedTransfer {
+                        txn_sid,
+                        op_idx,
+                        xfr: *i.body.transfer,
+                    }),
+                    _ => None,
+                },
+            )
+        })
+        .filter(|t| {
+            t.xfr
+                .asset_tracing_memos
+                .iter()
+                .flatten()
+                .any(|m| m.enc_key == enc_keys)
+        })
+        .collect();
+
+    Ok(web::Json(traced))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        zei::{
+            noah_api::xfr::structs::AssetTracerKeyPair,
+            noah_crypto::hybrid_encryption::hybrid_decrypt_with_x25519_secret_key,
+        },
+    };
+
+    #[test]
+    fn tracing_challenge() {
+        let mut prng = ChaChaRng::from_entropy();
+        let tracers = (0..2)
+            .map(|_| AssetTracerKeyPair::generate(&mut prng))
+            .collect::<Vec<_>>();
+        let enc_keys = tracers
+            .iter()
+            .map(|t| t.enc_key.lock_info_enc_key.clone())
+            .collect::<Vec<_>>();
+        let code = AssetTypeCode::gen_random();
+
+        // each tracer only opens its own nonce
+        let challenge = new_challenge(code, &enc_keys).unwrap();
+        let nonce = hybrid_decrypt_with_x25519_secret_key(
+            &challenge.nonces[1].ctext,
+            &tracers[1].dec_key.lock_info_dec_key,
+        );
+        assert_eq!(answer_challenge(&code, challenge.id, &nonce), Some(1));
+        // answered once only
+        assert_eq!(answer_challenge(&code, challenge.id, &nonce), None);
+
+        // a wrong answer burns the challenge
+        let challenge = new_challenge(code, &enc_keys).unwrap();
+        let nonce = hybrid_decrypt_with_x25519_secret_key(
+            &challenge.nonces[0].ctext,
+            &tracers[1].dec_key.lock_info_dec_key,
+        );
+        assert_eq!(answer_challenge(&code, challenge.id, &nonce), None);
+
+        // bound to its asset
+        let challenge = new_challenge(code, &enc_keys).unwrap();
+        let nonce = hybrid_decrypt_with_x25519_secret_key(
+            &challenge.nonces[0].ctext,
+            &tracers[0].dec_key.lock_info_dec_key,
+        );
+        let other = AssetTypeCode::gen_random();
+        assert_eq!(answer_challenge(&other, challenge.id, &nonce), None);
+    }
+}
@@ -0,0 +1,618 @@
+//!
+//! Webhooks of the query server,
+//! registered hooks are persisted and receive JSON events of new blocks
+//!
+//! Hooks are managed through the admin routes, see `QueryApiConfig::check_admin`,
+//! and may only target the hosts of the local network if the operator allows it
+//! with `QUERY_API_WEBHOOK_ALLOW_PRIVATE`. Each hook has its own delivery worker,
+//! so a slow one does not delay the others.
+//!
+//! The events are POSTed to the very address checked, the host is not resolved
+//! a second time, and the redirects are not followed: a 3xx fails the delivery.
+//!
+
+use {
+    super::{server::QueryServer, QueryApiConfig},
+    actix_web::{error, web, HttpRequest},
+    globutils::wallet,
+    hmac::{Hmac, Mac},
+    lazy_static::lazy_static,
+    ledger::{
+        data_model::{Operation, TxnSID},
+        store::{
+            api_cache::get_related_addresses,
+            fbnc::{new_mapx, Mapx},
+        },
+    },
+    parking_lot::{Mutex, RwLock},
+    ruc::*,
+    schemars::JsonSchema,
+    serde::{Deserialize, Serialize},
+    sha2::Sha256,
+    std::{
+        collections::{HashMap, HashSet},
+        io::{Read, Write},
+        net::{IpAddr, SocketAddr, TcpStream, ToSocketAddrs},
+        sync::{
+            mpsc::{channel, Receiver, RecvTimeoutError, Sender},
+            Arc,
+        },
+        thread,
+        time::{Duration, Instant},
+    },
+    tracing::{info, warn},
+};
+
+/// Max delivery attempts of one event
+const MAX_ATTEMPTS: u32 = 6;
+
+/// Delay before the first retry, doubled on each failure
+const BASE_BACKOFF: Duration = Duration::from_secs(2);
+
+/// Timeout of one delivery request
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Max count of the registered hooks
+const MAX_WEBHOOKS: usize = 64;
+
+/// Header carrying the hex HMAC-SHA256 of the body under the shared secret
+pub const SIGNATURE_HEADER: &str = "X-Findora-Signature";
+
+lazy_static! {
+    static ref WEBHOOKS: RwLock<Mapx<u64, Webhook>> =
+        RwLock::new(new_mapx!("query_server/webhooks"));
+    static ref WORKERS: Mutex<Option<Workers>> = Mutex::new(None);
+    static ref TLS_CONFIG: Arc<rustls::ClientConfig> = {
+        let mut config = rustls::ClientConfig::new();
+        config
+            .root_store
+            .add_server_trust_anchors(&webpki_roots::TLS_SERVER_ROOTS);
+        Arc::new(config)
+    };
+    /// count of the ledger blocks whose events have been emitted
+    static ref NOTIFIED_BLOCKS: Mutex<Option<usize>> = Mutex::new(None);
+}
+
+/// Kinds of events a webhook can subscribe to
+#[allow(missing_docs)]
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize, JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookTopic {
+    NewBlock,
+    Issuance,
+    AddressActivity,
+}
+
+/// A registered webhook
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct Webhook {
+    /// endpoint the events are POSTed to
+    pub url: String,
+    /// subscribed topics, empty for all
+    #[serde(default)]
+    pub topics: Vec<WebhookTopic>,
+    /// only `address_activity` of these addresses is sent, empty for all
+    #[serde(default)]
+    pub addresses: Vec<String>,
+    /// key of the HMAC in `SIGNATURE_HEADER`
+    pub secret: String,
+}
+
+impl Webhook {
+    fn accepts(&self, event: &WebhookEvent) -> bool {
+        let topic = event.topic();
+        if !self.topics.is_empty() && !self.topics.contains(&topic) {
+            return false;
+        }
+        match event {
+            WebhookEvent::AddressActivity { address, .. } => {
+                self.addresses.is_empty() || self.addresses.contains(address)
+            }
+            _ => true,
+        }
+    }
+}
+
+/// Events sent to the webhooks
+#[allow(missing_docs)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "topic", rename_all = "snake_case")]
+pub enum WebhookEvent {
+    NewBlock {
+        block_sid: usize,
+        txn_sids: Vec<TxnSID>,
+    },
+    Issuance {
+        block_sid: usize,
+        txn_sid: TxnSID,
+        asset_code: String,
+    },
+    AddressActivity {
+        block_sid: usize,
+        txn_sid: TxnSID,
+        address: String,
+    },
+}
+
+impl WebhookEvent {
+    #[allow(missing_docs)]
+    pub fn topic(&self) -> WebhookTopic {
+        match self {
+            WebhookEvent::NewBlock { .. } => WebhookTopic::NewBlock,
+            WebhookEvent::Issuance { .. } => WebhookTopic::Issuance,
+            WebhookEvent::AddressActivity { .. } => WebhookTopic::AddressActivity,
+        }
+    }
+}
+
+/// The delivery workers, by hook id, started on the first event of each hook
+struct Workers {
+    allow_private: bool,
+    senders: HashMap<u64, Sender<Delivery>>,
+}
+
+impl Workers {
+    fn send(&mut self, d: Delivery) {
+        let allow_private = self.allow_private;
+        let spawn = || {
+            let (tx, rx) = channel();
+            thread::spawn(move || deliver_loop(rx, allow_private));
+            tx
+        };
+        let id = d.id;
+        // the worker exits once its hook is removed
+        if let Err(e) = self.senders.entry(id).or_insert_with(spawn).send(d) {
+            let tx = spawn();
+            if tx.send(e.0).is_ok() {
+                self.senders.insert(id, tx);
+            }
+        }
+    }
+}
+
+struct Delivery {
+    id: u64,
+    body: Vec<u8>,
+    attempts: u32,
+    due: Instant,
+}
+
+/// HMAC-SHA256 of `msg` under `key`, in hex
+pub fn sign(key: &[u8], msg: &[u8]) -> String {
+    // any key length is valid for a HMAC
+    let mut mac = pnk!(Hmac::<Sha256>::new_from_slice(key).c(d!()));
+    mac.update(msg);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Whether a hook may send events to `ip`
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                // shared address space
+                || (100 == a && 64 == (b & 0xc0)))
+        }
+        IpAddr::V6(ip) => {
+            let seg = ip.segments()[0];
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                // unique local and link local
+                || 0xfc00 == (seg & 0xfe00)
+                || 0xfe80 == (seg & 0xffc0))
+                && ip.to_ipv4_mapped().map_or(true, |v4| is_public(v4.into()))
+        }
+    }
+}
+
+/// An http(s) url, split to send a request to a checked address
+struct Target {
+    tls: bool,
+    /// without the brackets of an ipv6 address
+    host: String,
+    port: u16,
+    /// path and query
+    path: String,
+}
+
+impl Target {
+    fn parse(url: &str) -> Result<Self> {
+        let (tls, rest) = if let Some(rest) = url.strip_prefix("https://") {
+            (true, rest)
+        } else if let Some(rest) = url.strip_prefix("http://") {
+            (false, rest)
+        } else {
+            return Err(eg!("Invalid url."));
+        };
+        let rest = rest.split('#').next().unwrap_or_default();
+        let (authority, path) = match rest.find(|c| matches!(c, '/' | '?')) {
+            Some(i) if rest[i..].starts_with('?') => {
+                (&rest[..i], format!("/{}", &rest[i..]))
+            }
+            Some(i) => (&rest[..i], rest[i..].to_owned()),
+            None => (rest, "/".to_owned()),
+        };
+        let host_port = authority.rsplit('@').next().unwrap_or_default();
+        let (host, port) = match host_port.strip_prefix('[') {
+            Some(v6) => {
+                let (host, port) = v6.split_once(']').c(d!("Invalid url."))?;
+                (host, port.strip_prefix(':'))
+            }
+            None => match host_port.split_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (host_port, None),
+            },
+        };
+        if host.is_empty() {
+            return Err(eg!("Invalid url."));
+        }
+        let port = match port {
+            Some(p) => p.parse::<u16>().c(d!("Invalid url."))?,
+            None => alt!(tls, 443, 80),
+        };
+        Ok(Target {
+            tls,
+            host: host.to_owned(),
+            port,
+            path,
+        })
+    }
+
+    /// The address to send the events to, checked as `check_target`
+    fn resolve(&self, allow_private: bool) -> Result<SocketAddr> {
+        let addrs = (self.host.as_str(), self.port)
+            .to_socket_addrs()
+            .c(d!(format!("Unresolved host: {}", self.host)))?
+            .collect::<Vec<_>>();
+        if !allow_private && addrs.iter().any(|a| !is_public(a.ip())) {
+            return Err(eg!("Private or loopback target."));
+        }
+        addrs.first().copied().c(d!("Unresolved host."))
+    }
+
+    fn host_header(&self) -> String {
+        let host = alt!(
+            self.host.contains(':'),
+            format!("[{}]", self.host),
+            self.host.clone()
+        );
+        if self.port == alt!(self.tls, 443, 80) {
+            host
+        } else {
+            format!("{}:{}", host, self.port)
+        }
+    }
+}
+
+/// Check that `url` is an http(s) url whose host resolves only to public
+/// addresses, unless `allow_private`, returns the address to send to
+fn check_target(url: &str, allow_private: bool) -> Result<SocketAddr> {
+    Target::parse(url).c(d!())?.resolve(allow_private).c(d!())
+}
+
+/// Write `request` and read the status code of the response
+fn exchange(stream: &mut (impl Read + Write), request: &[u8]) -> Result<u16> {
+    stream.write_all(request).c(d!())?;
+    stream.flush().c(d!())?;
+
+    // the status line only, e.g. `HTTP/1.1 200 OK`
+    let mut line = vec![];
+    let mut byte = [0u8; 1];
+    while !line.ends_with(b"\r\n") {
+        if line.len() > 1024 || 0 == stream.read(&mut byte).c(d!())? {
+            return Err(eg!("Invalid response."));
+        }
+        line.push(byte[0]);
+    }
+    String::from_utf8_lossy(&line)
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse::<u16>().ok())
+        .c(d!("Invalid response."))
+}
+
+/// POST `body` to `url`, signed by `secret`, on the address checked
+fn post(url: &str, secret: &str, body: &[u8], allow_private: bool) -> Result<()> {
+    let target = Target::parse(url).c(d!())?;
+    let addr = target.resolve(allow_private).c(d!())?;
+
+    let mut request = format!(
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\n\
+         {}: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        target.path,
+        target.host_header(),
+        SIGNATURE_HEADER,
+        sign(secret.as_bytes(), body),
+        body.len()
+    )
+    .into_bytes();
+    request.extend_from_slice(body);
+
+    let mut tcp = TcpStream::connect_timeout(&addr, DELIVERY_TIMEOUT).c(d!())?;
+    tcp.set_read_timeout(Some(DELIVERY_TIMEOUT)).c(d!())?;
+    tcp.set_write_timeout(Some(DELIVERY_TIMEOUT)).c(d!())?;
+    let status = if target.tls {
+        let name = webpki::DNSNameRef::try_from_ascii_str(&target.host)
+            .map_err(|e| eg!(format!("{}: {:?}", target.host, e)))?;
+        let session = rustls::ClientSession::new(&TLS_CONFIG, name);
+        exchange(&mut rustls::StreamOwned::new(session, tcp), &request).c(d!())?
+    } else {
+        exchange(&mut tcp, &request).c(d!())?
+    };
+
+    if (200..300).contains(&status) {
+        Ok(())
+    } else {
+        // a redirect could lead anywhere, past the checks of the target
+        Err(eg!(format!("status {status}")))
+    }
+}
+
+/// Start delivering the events, the ones emitted before this are dropped
+pub(crate) fn start_delivery_workers(cfg: &QueryApiConfig) {
+    *WORKERS.lock() = Some(Workers {
+        allow_private: cfg.webhook_allow_private,
+        senders: HashMap::new(),
+    });
+}
+
+/// Deliver the events of one hook, in order
+fn deliver_loop(rx: Receiver<Delivery>, allow_private: bool) {
+    let mut pending: Vec<Delivery> = vec![];
+    loop {
+        let timeout = pending
+            .iter()
+            .map(|d| d.due.saturating_duration_since(Instant::now()))
+            .min()
+            .unwrap_or_else(|| Duration::from_secs(3600));
+        match rx.recv_timeout(timeout) {
+            Ok(d) => pending.push(d),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+
+        let now = Instant::now();
+        let (due, rest) = pending.into_iter().partition(|d| d.due <= now);
+        pending = rest;
+        for mut d in due {
+            if deliver(&d, allow_private).is_ok() {
+                continue;
+            }
+            d.attempts += 1;
+            if d.attempts < MAX_ATTEMPTS {
+                d.due = now + BASE_BACKOFF * 2u32.pow(d.attempts - 1);
+                pending.push(d);
+            } else {
+                warn!(
+                    "webhook {}: event dropped after {} attempts",
+                    d.id, d.attempts
+                );
+            }
+        }
+    }
+}
+
+fn deliver(d: &Delivery, allow_private: bool) -> Result<()> {
+    // the hook may have been removed while the event was waiting
+    let hook = match WEBHOOKS.read().get(&d.id) {
+        Some(h) => h,
+        None => return Ok(()),
+    };
+    // the host may resolve to another address since the registration
+    post(&hook.url, &hook.secret, &d.body, allow_private)
+        .c(d!(format!("webhook {}", d.id)))
+}
+
+/// Events of the ledger blocks in [start, end]
+fn collect_events(server: &QueryServer, start: usize, end: usize) -> Vec<WebhookEvent> {
+    let mut events = vec![];
+    for (block_sid, txns) in server.get_blocks(start, end) {
+        events.push(WebhookEvent::NewBlock {
+            block_sid,
+            txn_sids: txns.iter().map(|ftx| ftx.tx_id).collect(),
+        });
+        for ftx in txns.iter() {
+            let mut codes = HashSet::new();
+            let addresses = get_related_addresses(&ftx.txn, |op| {
+                if let Operation::IssueAsset(i) = op {
+                    codes.insert(i.body.code.to_base64());
+                }
+            });
+            events.extend(codes.into_iter().map(|asset_code| WebhookEvent::Issuance {
+                block_sid,
+                txn_sid: ftx.tx_id,
+                asset_code,
+            }));
+            events.extend(addresses.into_iter().map(|a| {
+                WebhookEvent::AddressActivity {
+                    block_sid,
+                    txn_sid: ftx.tx_id,
+                    address: wallet::public_key_to_base64(&a.key),
+                }
+            }));
+        }
+    }
+    events
+}
+
+/// Queue the events of the blocks committed since the last call,
+/// called after the query server has been updated.
+pub(crate) fn notify(server: &QueryServer) {
+    let mut workers = WORKERS.lock();
+    let workers = match workers.as_mut() {
+        Some(w) => w,
+        None => return,
+    };
+
+    let count = server.ledger_cloned.get_block_count();
+    let mut notified = NOTIFIED_BLOCKS.lock();
+    // blocks existing at startup are not replayed
+    let start = notified.unwrap_or(count);
+    *notified = Some(count);
+    if start >= count {
+        return;
+    }
+
+    let hooks = WEBHOOKS.read().iter().collect::<Vec<_>>();
+    if hooks.is_empty() {
+        return;
+    }
+
+    let now = Instant::now();
+    for event in collect_events(server, start, count - 1) {
+        let body = pnk!(serde_json::to_vec(&event));
+        for (id, hook) in hooks.iter().filter(|(_, h)| h.accepts(&event)) {
+            workers.send(Delivery {
+                id: *id,
+                body: body.clone(),
+                attempts: 0,
+                due: now,
+            });
+        }
+    }
+}
+
+/// Result of a webhook registration
+#[allow(missing_docs)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct WebhookId {
+    pub id: u64,
+}
+
+/// Register a webhook, returns its id
+pub async fn register_webhook(
+    req: HttpRequest,
+    _data: web::Data<Arc<RwLock<QueryServer>>>,
+    cfg: web::Data<QueryApiConfig>,
+    web::Json(hook): web::Json<Webhook>,
+) -> actix_web::Result<web::Json<WebhookId>> {
+    cfg.check_admin(&req)?;
+
+    let (url, allow_private) = (hook.url.clone(), cfg.webhook_allow_private);
+    web::block(move || {
+        check_target(&url, allow_private)
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(error::ErrorBadRequest)?;
+    if hook.secret.is_empty() {
+        return Err(error::ErrorBadRequest("Empty secret."));
+    }
+    for addr in hook.addresses.iter() {
+        wallet::public_key_from_base64(addr)
+            .c(d!())
+            .map_err(|e| error::ErrorBadRequest(e.to_string()))?;
+    }
+
+    let mut hooks = WEBHOOKS.write();
+    if hooks.len() >= MAX_WEBHOOKS {
+        return Err(error::ErrorBadRequest("Too many webhooks."));
+    }
+    let id = loop {
+        let id = rand::random::<u64>();
+        if !hooks.contains_key(&id) {
+            break id;
+        }
+    };
+    info!("webhook {} registered: {}", id, hook.url);
+    hooks.insert(id, hook);
+
+    Ok(web::Json(WebhookId { id }))
+}
+
+/// Remove a webhook, along with its delivery worker
+pub async fn remove_webhook(
+    req: HttpRequest,
+    _data: web::Data<Arc<RwLock<QueryServer>>>,
+    cfg: web::Data<QueryApiConfig>,
+    info: web::Path<u64>,
+) -> actix_web::Result<web::Json<bool>> {
+    cfg.check_admin(&req)?;
+
+    WEBHOOKS
+        .write()
+        .remove(&info)
+        .ok_or_else(|| error::ErrorNotFound("Specified webhook does not exist."))?;
+    if let Some(workers) = WORKERS.lock().as_mut() {
+        workers.senders.remove(&info);
+    }
+    Ok(web::Json(true))
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::net::TcpListener};
+
+    #[test]
+    fn hmac_sha256() {
+        // RFC 4231, test case 2
+        assert_eq!(
+            sign(b"Jefe", b"what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn private_targets() {
+        for url in [
+            "http://127.0.0.1:8080/hook",
+            "http://10.1.2.3/",
+            "https://user@192.168.0.1",
+            "http://169.254.169.254/latest",
+            "https://[::1]:8443/",
+            "http://[::ffff:127.0.0.1]/",
+            "http://localhost/",
+        ] {
+            assert!(check_target(url, false).is_err(), "{url}");
+            assert!(check_target(url, true).is_ok(), "{url}");
+        }
+        assert!(check_target("http://93.184.216.34:8080/hook", false).is_ok());
+        assert!(check_target("ftp://93.184.216.34/", true).is_err());
+        assert!(check_target("http:///hook", true).is_err());
+    }
+
+    #[test]
+    fn redirect_not_followed() {
+        let hook = TcpListener::bind("127.0.0.1:0").unwrap();
+        let hook_addr = hook.local_addr().unwrap();
+        let private = TcpListener::bind("127.0.0.1:0").unwrap();
+        private.set_nonblocking(true).unwrap();
+
+        let location = private.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut conn, _) = hook.accept().unwrap();
+            let mut request = vec![];
+            let mut buf = [0u8; 1024];
+            while !request.ends_with(b"{}") {
+                let n = conn.read(&mut buf).unwrap();
+                assert!(0 < n);
+                request.extend_from_slice(&buf[..n]);
+            }
+            let request = String::from_utf8(request).unwrap();
+            assert!(request.starts_with("POST /hook?a=1 HTTP/1.1\r\n"));
+            assert!(request.contains(&format!("{SIGNATURE_HEADER}: ")));
+            write!(
+                conn,
+                "HTTP/1.1 307 Temporary Redirect\r\nLocation: http://{location}/\r\n\
+                 Content-Length: 0\r\n\r\n"
+            )
+            .unwrap();
+        });
+
+        let url = format!("http://{hook_addr}/hook?a=1");
+        let e = post(&url, "secret", b"{}", true).unwrap_err();
+        assert!(e.to_string().contains("307"), "{e}");
+        server.join().unwrap();
+        // the redirect target was never reached
+        assert!(private.accept().is_err());
+    }
+}
@@ -4,6 +4,7 @@
 
 use {
     super::{SubmissionServer, TxnForward, TxnHandle},
+    crate::api::unix_socket::UnixSocket,
     actix_cors::Cors,
     actix_web::{error, middleware, web, App, HttpServer},
     finutils::api::NetworkRoute,
@@ -110,10 +111,11 @@ impl SubmissionApi {
         submission_server: Arc<RwLock<SubmissionServer<RNG, TF>>>,
         host: &str,
         port: u16,
+        unix_socket: Option<&UnixSocket>,
     ) -> Result<SubmissionApi> {
         let _ = actix_rt::System::new("findora API");
 
-        HttpServer::new(move || {
+        let mut hdr = HttpServer::new(move || {
             App::new()
                 .wrap(middleware::Logger::default())
                 .wrap(Cors::permissive().supports_credentials())
@@ -131,8 +133,13 @@ impl SubmissionApi {
                 )
         })
         .bind(&format!("{host}:{port}"))
-        .c(d!())?
-        .run();
+        .c(d!())?;
+        if let Some(sock) = unix_socket {
+            hdr = hdr
+                .listen_uds(sock.bind().c(d!())?)
+                .c(d!(sock.path.clone()))?;
+        }
+        hdr.run();
 
         info!("Submission server started");
 
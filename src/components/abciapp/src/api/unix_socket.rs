@@ -0,0 +1,124 @@
+//!
+//! # Unix domain sockets of the apis
+//!
+//! Co-located services, such as an explorer backend or an indexer, can reach
+//! the apis through a socket file instead of a TCP port, who may connect is
+//! controlled by the permissions of the file, see `--query-unix-socket-mode`
+//! and `--submission-unix-socket-mode`.
+//!
+//! The apis are served in plain http on the socket, even if TLS is configured.
+//!
+
+use {
+    ruc::*,
+    std::{
+        fs,
+        os::unix::{
+            fs::{FileTypeExt, PermissionsExt},
+            net::UnixListener,
+        },
+        path::Path,
+    },
+};
+
+/// Permissions of the socket file by default, the owner and its group
+pub const DEFAULT_MODE: u32 = 0o660;
+
+/// A socket file to serve an api on, along with its TCP addresses
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnixSocket {
+    /// path of the socket file
+    pub path: String,
+    /// permissions of the socket file
+    pub mode: u32,
+}
+
+impl UnixSocket {
+    /// A socket file at `path` with `mode`, `DEFAULT_MODE` if `None`,
+    /// none if the path is not set
+    pub fn new(path: Option<&str>, mode: Option<u32>) -> Option<Self> {
+        path.map(|path| UnixSocket {
+            path: path.to_owned(),
+            mode: mode.unwrap_or(DEFAULT_MODE),
+        })
+    }
+
+    /// Remove the socket file left by a previous run, binding fails otherwise,
+    /// anything else than a socket at `path` is kept and an error returned
+    pub fn remove_stale(&self) -> Result<()> {
+        match fs::symlink_metadata(&self.path) {
+            Ok(meta) if meta.file_type().is_socket() => {
+                fs::remove_file(&self.path).c(d!(self.path.clone()))
+            }
+            Ok(_) => Err(eg!(format!("{} exists and is not a socket", self.path))),
+            Err(_) => Ok(()),
+        }
+    }
+
+    /// Bind the socket file, replacing a stale one.
+    ///
+    /// It is bound in a private dir next to `path` then moved to `path` with
+    /// its permissions set, so it can never be connected to with the
+    /// permissions of the umask.
+    pub fn bind(&self) -> Result<UnixListener> {
+        self.remove_stale().c(d!())?;
+
+        let path = Path::new(&self.path);
+        let parent = path
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or_else(|| Path::new("."));
+        // created with 0700, and removed once moved out of
+        let dir = tempfile::Builder::new()
+            .prefix(".sock")
+            .tempdir_in(parent)
+            .c(d!(self.path.clone()))?;
+        let tmp = dir.path().join("s");
+
+        let listener = UnixListener::bind(&tmp).c(d!(self.path.clone()))?;
+        fs::set_permissions(&tmp, fs::Permissions::from_mode(self.mode))
+            .c(d!(self.path.clone()))?;
+        fs::rename(&tmp, path).c(d!(self.path.clone()))?;
+        Ok(listener)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::os::unix::net::UnixStream};
+
+    #[test]
+    fn bind_socket() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("query.sock");
+        let sock = UnixSocket::new(Some(path.to_str().unwrap()), Some(0o600)).unwrap();
+
+        let listener = sock.bind().unwrap();
+        let meta = fs::symlink_metadata(&path).unwrap();
+        assert!(meta.file_type().is_socket());
+        assert_eq!(meta.permissions().mode() & 0o777, 0o600);
+        UnixStream::connect(&path).unwrap();
+        listener.accept().unwrap();
+        // the private dir is gone
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        // a stale socket is replaced
+        drop(listener);
+        drop(sock.bind().unwrap());
+
+        sock.remove_stale().unwrap();
+        assert!(!path.exists());
+
+        // never remove a regular file
+        fs::write(&path, b"").unwrap();
+        assert!(sock.remove_stale().is_err());
+        assert!(sock.bind().is_err());
+        assert!(path.exists());
+
+        assert_eq!(UnixSocket::new(None, Some(0o600)), None);
+        assert_eq!(
+            UnixSocket::new(Some("a.sock"), None).unwrap().mode,
+            DEFAULT_MODE
+        );
+    }
+}
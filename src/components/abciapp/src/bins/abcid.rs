@@ -3,7 +3,7 @@
 //!
 
 use {
-    abciapp::abci,
+    abciapp::{abci, api::query_server::query_api::service::stop_query_server},
     ruc::*,
     std::{
         sync::{atomic::Ordering, mpsc::channel},
@@ -27,6 +27,7 @@ fn main() {
     pnk!(ctrlc::set_handler(move || {
         println!("Waiting to exit.");
         abci::IS_EXITING.store(true, Ordering::SeqCst);
+        stop_query_server();
         while !abci::IN_SAFE_ITV.load(Ordering::SeqCst) {
             sleep_ms!(10);
         }
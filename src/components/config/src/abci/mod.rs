@@ -120,6 +120,21 @@ pub struct CheckPointConfig {
     #[serde(default = "def_fix_check_replay")]
     pub fix_check_replay: u64,
 
+    // Count the issuances of a registered asset type under its own code,
+    // the one the `max_units` checks look up.
+    #[serde(default = "def_fix_issuance_cap_height")]
+    pub fix_issuance_cap_height: u64,
+
+    // Enforce the height and time locks of the transfer outputs,
+    // the outputs carrying a lock are rejected before.
+    #[serde(default = "def_output_lock_height")]
+    pub output_lock_height: u64,
+
+    // Enforce the co-signatures of the threshold locks,
+    // the outputs carrying one are rejected before.
+    #[serde(default = "def_threshold_lock_height")]
+    pub threshold_lock_height: u64,
+
     #[serde(default = "def_fns_registry")]
     pub fns_registry: String,
 
@@ -161,6 +176,18 @@ fn def_fix_check_replay() -> u64 {
     DEFAULT_CHECKPOINT_CONFIG.fix_check_replay
 }
 
+fn def_fix_issuance_cap_height() -> u64 {
+    DEFAULT_CHECKPOINT_CONFIG.fix_issuance_cap_height
+}
+
+fn def_output_lock_height() -> u64 {
+    DEFAULT_CHECKPOINT_CONFIG.output_lock_height
+}
+
+fn def_threshold_lock_height() -> u64 {
+    DEFAULT_CHECKPOINT_CONFIG.threshold_lock_height
+}
+
 fn def_check_signatures_num() -> i64 {
     DEFAULT_CHECKPOINT_CONFIG.check_signatures_num
 }
@@ -270,6 +297,9 @@ lazy_static! {
         prism_bridge_address: "0x5f9552fEd754F20B636C996DaDB32806554Bb995".to_owned(),
         remove_fake_staking_hash: 0,
         fix_check_replay: 0,
+        fix_issuance_cap_height: 0,
+        output_lock_height: 0,
+        threshold_lock_height: 0,
         fns_registry: "".to_owned(),
         lowlevel_data_min: 0,
         lowlevel_data_max: 0,
@@ -319,6 +349,9 @@ lazy_static! {
         prism_bridge_address: "0x4672372fDB139B7295Fc59b55b43EC5fF2761A0b".to_owned(),
         remove_fake_staking_hash: 4004430,
         fix_check_replay: 4004430,
+        fix_issuance_cap_height: 5000_0000,
+        output_lock_height: 5000_0000,
+        threshold_lock_height: 5000_0000,
         fns_registry: "0x57e8782c2f77B99823EeA48aCE3Eb7635F0B35F9".to_owned(),
         lowlevel_data_min: 3971239,
         lowlevel_data_max: 4004430,
@@ -784,6 +817,51 @@ pub mod global_cfg {
         pub tendermint_node_self_addr: Option<String>,
         pub tendermint_node_key_config_path: Option<String>,
         pub ledger_dir: String,
+        /// snapshot file loaded into an empty api cache on startup
+        pub api_cache_snapshot: Option<String>,
+        /// where the api cache snapshots are dumped to
+        pub api_cache_snapshot_dir: String,
+        /// query api url of a primary node, serve as its read replica if set
+        pub query_replica_of: Option<String>,
+        /// drop the owner memos and txo indexes of spent txos from the api cache
+        pub api_cache_prune_spent: bool,
+        /// keep the coinbase history of the last N block heights only
+        pub api_cache_coinbase_hist_blocks: Option<u64>,
+        /// the api cache indexes to keep, ',' separated, default to all
+        pub api_cache_indexes: Option<String>,
+        /// key-value backend of the flat api cache maps, `fbnc` or `rocksdb`
+        pub api_cache_backend: Option<String>,
+        /// cross-check the api cache against the ledger and exit
+        pub api_cache_verify: bool,
+        /// fix the divergences found by `api_cache_verify`
+        pub api_cache_repair: bool,
+        /// write a snapshot of the ledger state into this dir and exit
+        pub ledger_snapshot_dir: Option<String>,
+        /// restore the ledger state from this snapshot archive and exit
+        pub ledger_snapshot_restore: Option<String>,
+        /// replay this block export stream on top of the ledger, checking
+        /// the state commitment of every block, and exit
+        pub ledger_replay: Option<String>,
+        /// audit the replay protection of the committed txns and exit
+        pub ledger_audit_replay: bool,
+        /// audit the replay protection of the txns of this block export stream
+        /// and exit
+        pub ledger_audit_replay_stream: Option<String>,
+        /// where the blocks and spent utxos older than `ledger_hot_blocks` are moved to
+        pub ledger_cold_dir: Option<String>,
+        /// number of recent blocks kept in the main ledger storage
+        pub ledger_hot_blocks: Option<u64>,
+        /// number of recent versions whose utxo bitmap is kept,
+        /// to serve the utxo statuses at these versions
+        pub ledger_utxo_history: Option<u64>,
+        /// socket file the query api is served on along with its TCP ports
+        pub query_unix_socket: Option<String>,
+        /// permissions of `query_unix_socket`, in octal
+        pub query_unix_socket_mode: Option<u32>,
+        /// socket file the submission api is served on along with its TCP port
+        pub submission_unix_socket: Option<String>,
+        /// permissions of `submission_unix_socket`, in octal
+        pub submission_unix_socket_mode: Option<u32>,
         #[cfg(target_os = "linux")]
         pub btmcfg: BtmCfg,
         pub checkpoint: CheckPointConfig,
@@ -822,6 +900,27 @@ pub mod global_cfg {
             .arg_from_usage("--tendermint-node-key-config-path=[Path] 'such as: ${HOME}/.tendermint/config/priv_validator_key.json'")
             .arg_from_usage("-d, --ledger-dir=[Path]")
             .arg_from_usage("--checkpoint-file=[Path]")
+            .arg_from_usage("--api-cache-snapshot=[Path] 'load the query server indexes from a snapshot on startup'")
+            .arg_from_usage("--query-replica-of=[URL] 'serve the query api as a read replica of a primary node, such as: http://primary:8668'")
+            .arg_from_usage("--api-cache-snapshot-dir=[Path] 'where the query server snapshots are dumped to, default to ${LEDGER_DIR}/api_cache_snapshots'")
+            .arg_from_usage("--api-cache-prune-spent 'drop the owner memos and txo indexes of spent txos from the query server'")
+            .arg_from_usage("--api-cache-coinbase-hist-blocks=[Blocks] 'keep the coinbase history of the last N blocks only, default to all'")
+            .arg_from_usage("--api-cache-indexes=[Indexes] 'comma separated query server indexes to keep: owner_memos, coinbase_hist, issuances, txn_hashes, explorer, staking_hist, default to all'")
+            .arg_from_usage("--api-cache-backend=[Backend] 'key-value backend of the query server indexes: fbnc or rocksdb, default to fbnc'")
+            .arg_from_usage("--verify-api-cache 'cross-check the query server indexes against the ledger, then exit'")
+            .arg_from_usage("--repair-api-cache 'like --verify-api-cache, and fix the divergences from the ledger'")
+            .arg_from_usage("--ledger-snapshot=[Dir] 'write a content-addressed snapshot of the ledger state into Dir, then exit'")
+            .arg_from_usage("--restore-ledger-snapshot=[Archive] 'restore the ledger state from a snapshot archive into an empty ledger dir, then exit'")
+            .arg_from_usage("--ledger-replay=[File] 'replay a block export stream on top of the ledger, checking the state commitment of every block, then exit'")
+            .arg_from_usage("--ledger-audit-replay 'report the duplicate no-replay tokens and out-of-order issuance sequence numbers of the committed txns, then exit'")
+            .arg_from_usage("--ledger-audit-replay-stream=[File] 'same as --ledger-audit-replay, for the txns of a block export stream'")
+            .arg_from_usage("--ledger-cold-dir=[Dir] 'where the old blocks and spent utxos are moved to, required by --ledger-hot-blocks'")
+            .arg_from_usage("--ledger-hot-blocks=[Blocks] 'keep the last N blocks only in the main ledger storage, move the older ones to the cold dir'")
+            .arg_from_usage("--ledger-utxo-history=[Versions] 'keep the utxo bitmaps of the last N state commitment versions, to prove past utxo statuses'")
+            .arg_from_usage("--query-unix-socket=[Path] 'also serve the query api on a unix socket, in plain http'")
+            .arg_from_usage("--query-unix-socket-mode=[Mode] 'octal permissions of the query api socket, default to 660'")
+            .arg_from_usage("--submission-unix-socket=[Path] 'also serve the submission api on a unix socket, in plain http'")
+            .arg_from_usage("--submission-unix-socket-mode=[Mode] 'octal permissions of the submission api socket, default to 660'")
             .arg_from_usage("--enable-snapshot 'global switch for enabling snapshot functions'")
             .arg_from_usage("--snapshot-list 'list all available snapshots in the form of block height'")
             .arg_from_usage("--snapshot-target=[TargetPath] 'a data volume containing both ledger data and tendermint data'")
@@ -952,6 +1051,85 @@ pub mod global_cfg {
             .unwrap_or_else(|| "8546".to_owned())
             .parse::<u16>()
             .c(d!())?;
+        let acs = m
+            .value_of("api-cache-snapshot")
+            .map(|v| v.to_owned())
+            .or_else(|| env::var("API_CACHE_SNAPSHOT").ok());
+        let acsd = m
+            .value_of("api-cache-snapshot-dir")
+            .map(|v| v.to_owned())
+            .or_else(|| env::var("API_CACHE_SNAPSHOT_DIR").ok())
+            .unwrap_or_else(|| format!("{}/api_cache_snapshots", &ld));
+        let qro = m
+            .value_of("query-replica-of")
+            .map(|v| v.to_owned())
+            .or_else(|| env::var("QUERY_REPLICA_OF").ok());
+        let acps = m.is_present("api-cache-prune-spent")
+            || env::var("API_CACHE_PRUNE_SPENT").is_ok();
+        let acchb = m
+            .value_of("api-cache-coinbase-hist-blocks")
+            .map(|v| v.to_owned())
+            .or_else(|| env::var("API_CACHE_COINBASE_HIST_BLOCKS").ok())
+            .map(|v| v.parse::<u64>().c(d!()))
+            .transpose()?;
+        let aci = m
+            .value_of("api-cache-indexes")
+            .map(|v| v.to_owned())
+            .or_else(|| env::var("API_CACHE_INDEXES").ok());
+        let acb = m
+            .value_of("api-cache-backend")
+            .map(|v| v.to_owned())
+            .or_else(|| env::var("API_CACHE_BACKEND").ok());
+        let acr = m.is_present("repair-api-cache");
+        let acv = acr || m.is_present("verify-api-cache");
+        let lsd = m.value_of("ledger-snapshot").map(|v| v.to_owned());
+        let lsr = m.value_of("restore-ledger-snapshot").map(|v| v.to_owned());
+        let lrp = m.value_of("ledger-replay").map(|v| v.to_owned());
+        let lar = m.is_present("ledger-audit-replay");
+        let lars = m
+            .value_of("ledger-audit-replay-stream")
+            .map(|v| v.to_owned());
+        let lcd = m
+            .value_of("ledger-cold-dir")
+            .map(|v| v.to_owned())
+            .or_else(|| env::var("LEDGER_COLD_DIR").ok());
+        let lhb = m
+            .value_of("ledger-hot-blocks")
+            .map(|v| v.to_owned())
+            .or_else(|| env::var("LEDGER_HOT_BLOCKS").ok())
+            .map(|v| v.parse::<u64>().c(d!()))
+            .transpose()?;
+        if lhb.is_some() && lcd.is_none() {
+            return Err(eg!("--ledger-hot-blocks requires --ledger-cold-dir"));
+        }
+        let luh = m
+            .value_of("ledger-utxo-history")
+            .map(|v| v.to_owned())
+            .or_else(|| env::var("LEDGER_UTXO_HISTORY").ok())
+            .map(|v| v.parse::<u64>().c(d!()))
+            .transpose()?;
+        let qus = m
+            .value_of("query-unix-socket")
+            .map(|v| v.to_owned())
+            .or_else(|| env::var("QUERY_API_UNIX_SOCKET").ok())
+            .filter(|v| !v.is_empty());
+        let qusm = m
+            .value_of("query-unix-socket-mode")
+            .map(|v| v.to_owned())
+            .or_else(|| env::var("QUERY_API_UNIX_SOCKET_MODE").ok())
+            .map(|v| u32::from_str_radix(&v, 8).c(d!(v)))
+            .transpose()?;
+        let sus = m
+            .value_of("submission-unix-socket")
+            .map(|v| v.to_owned())
+            .or_else(|| env::var("SUBMISSION_API_UNIX_SOCKET").ok())
+            .filter(|v| !v.is_empty());
+        let susm = m
+            .value_of("submission-unix-socket-mode")
+            .map(|v| v.to_owned())
+            .or_else(|| env::var("SUBMISSION_API_UNIX_SOCKET_MODE").ok())
+            .map(|v| u32::from_str_radix(&v, 8).c(d!(v)))
+            .transpose()?;
         let checkpoint_path = m
             .value_of("checkpoint-file")
             .map(|v| v.to_owned())
@@ -976,6 +1154,27 @@ pub mod global_cfg {
             tendermint_node_self_addr: tnsa,
             tendermint_node_key_config_path: tnkcp,
             ledger_dir: ld,
+            api_cache_snapshot: acs,
+            api_cache_snapshot_dir: acsd,
+            query_replica_of: qro,
+            api_cache_prune_spent: acps,
+            api_cache_coinbase_hist_blocks: acchb,
+            api_cache_indexes: aci,
+            api_cache_backend: acb,
+            api_cache_verify: acv,
+            api_cache_repair: acr,
+            ledger_snapshot_dir: lsd,
+            ledger_snapshot_restore: lsr,
+            ledger_replay: lrp,
+            ledger_audit_replay: lar,
+            ledger_audit_replay_stream: lars,
+            ledger_cold_dir: lcd,
+            ledger_hot_blocks: lhb,
+            ledger_utxo_history: luh,
+            query_unix_socket: qus,
+            query_unix_socket_mode: qusm,
+            submission_unix_socket: sus,
+            submission_unix_socket_mode: susm,
             #[cfg(target_os = "linux")]
             btmcfg: parse_btmcfg(&m).c(d!())?,
             checkpoint: CheckPointConfig::from_file(&checkpoint_path).unwrap(),
@@ -17,6 +17,10 @@ impl CheckNonce {
     pub fn new(nonce: U256) -> Self {
         CheckNonce(nonce)
     }
+
+    pub fn nonce(&self) -> U256 {
+        self.0
+    }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
@@ -26,6 +30,10 @@ impl CheckFee {
     pub fn new(fee: Option<U256>) -> Self {
         CheckFee(fee)
     }
+
+    pub fn fee(&self) -> Option<U256> {
+        self.0
+    }
 }
 
 /// Unchecked transaction type as expected by this application.
@@ -12,6 +12,9 @@ use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use sha3::{Digest, Keccak256};
 use std::str::FromStr;
 
+/// The bip44 path of the first ethereum account, "m/44'/coin'/account'/change/address".
+pub const DEFAULT_DERIVATION_PATH: &str = "m/44'/60'/0'/0/0";
+
 /// A secret seed (which is bytewise essentially equivalent to a SecretKey).
 ///
 /// We need it as a different type because `Seed` is expected to be AsRef<[u8]>.
@@ -318,13 +321,28 @@ impl SecpPair {
         phrase: &str,
         password: Option<&str>,
     ) -> ruc::Result<(SecpPair, Seed)> {
-        let mnemonic = Mnemonic::from_phrase_in(Language::English, phrase)
-            .map_err(|_| eg!("InvalidPhrase"))?;
+        Self::from_phrase_in(
+            Language::English,
+            phrase,
+            password,
+            DEFAULT_DERIVATION_PATH,
+        )
+    }
+
+    /// Generate key pair from given recovery phrase of `lang` and password,
+    /// along the bip32 derivation `path`.
+    pub fn from_phrase_in(
+        lang: Language,
+        phrase: &str,
+        password: Option<&str>,
+        path: &str,
+    ) -> ruc::Result<(SecpPair, Seed)> {
+        let mnemonic =
+            Mnemonic::from_phrase_in(lang, phrase).map_err(|_| eg!("InvalidPhrase"))?;
         let bs = mnemonic.to_seed(password.unwrap_or(""));
         let ext = XPrv::derive_from_path(
             bs,
-            &DerivationPath::from_str("m/44'/60'/0'/0/0")
-                .map_err(|_| eg!("InvalidDerivationPath"))?,
+            &DerivationPath::from_str(path).map_err(|_| eg!("InvalidDerivationPath"))?,
         )
         .map_err(|_| eg!("Failed to ExtendedPrivateKey"))?;
         let mut seed = Seed::default();
@@ -3,10 +3,16 @@
 //!
 
 use {
-    ledger::staking::{
-        self, StakerMemo, TendermintAddr, MAX_POWER_PERCENT_PER_VALIDATOR,
+    ledger::{
+        data_model::{AssetTypeCode, TxoSID},
+        staking::{self, StakerMemo, TendermintAddr, MAX_POWER_PERCENT_PER_VALIDATOR},
     },
+    ruc::*,
     serde::{Deserialize, Serialize},
+    zei::{
+        noah_api::xfr::asset_record::open_blind_asset_record, BlindAssetRecord,
+        OwnerMemo, XfrKeyPair,
+    },
 };
 
 /// A list of basic validator information of current height
@@ -185,6 +191,36 @@ impl DelegationInfo {
     }
 }
 
+/// An unspent txo whose amount or asset type is confidential,
+/// opened by its owner on the client side, the key never leaves the client
+#[allow(missing_docs)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PrivateRecord {
+    pub txo_sid: TxoSID,
+    pub record: BlindAssetRecord,
+    pub owner_memo: Option<OwnerMemo>,
+}
+
+impl PrivateRecord {
+    /// (asset code, amount) of the record, as decrypted with the owner key
+    pub fn open(&self, kp: &XfrKeyPair) -> Result<(AssetTypeCode, u64)> {
+        open_blind_asset_record(
+            &self.record.into_noah(),
+            &self.owner_memo.clone().map(|m| m.into_noah()),
+            &kp.into_noah(),
+        )
+        .c(d!())
+        .map(|oar| {
+            (
+                AssetTypeCode {
+                    val: oar.asset_type,
+                },
+                oar.amount,
+            )
+        })
+    }
+}
+
 #[allow(missing_docs)]
 pub trait NetworkRoute {
     fn route(&self) -> String;
@@ -251,6 +251,7 @@ mod issue {
                         id: None,
                         record: BlindAssetRecord::from_noah(&ba),
                         lien: None,
+                        lock: None,
                     },
                     None,
                 )
@@ -6,7 +6,7 @@ use std::ops::Div;
 
 use {
     crate::{
-        api::{DelegationInfo, ValidatorDetail},
+        api::{DelegationInfo, PrivateRecord, ValidatorDetail},
         common::get_serv_addr,
         txn_builder::{TransactionBuilder, TransferOperationBuilder},
     },
@@ -621,6 +621,32 @@ pub fn get_asset_all(kp: &XfrKeyPair) -> Result<BTreeMap<AssetTypeCode, u64>> {
     Ok(set)
 }
 
+/// Balances of the confidential utxos of a findora keypair,
+/// the owner memos are opened locally, the key is never sent
+pub fn get_private_balances(kp: &XfrKeyPair) -> Result<BTreeMap<AssetTypeCode, u64>> {
+    let url = format!(
+        "{}:8667/private_records/{}",
+        get_serv_addr().c(d!())?,
+        wallet::public_key_to_base64(kp.get_pk_ref())
+    );
+    let records = attohttpc::get(url)
+        .send()
+        .c(d!())?
+        .error_for_status()
+        .c(d!())?
+        .bytes()
+        .c(d!())
+        .and_then(|b| serde_json::from_slice::<Vec<PrivateRecord>>(&b).c(d!()))?;
+
+    let mut balances = BTreeMap::new();
+    for record in records.iter() {
+        let (code, amount) = record.open(kp).c(d!())?;
+        let total = balances.entry(code).or_insert(0u64);
+        *total = total.saturating_add(amount);
+    }
+    Ok(balances)
+}
+
 #[allow(missing_docs)]
 pub fn get_owned_utxos(
     addr: &XfrPublicKey,
@@ -16,10 +16,11 @@ use {
             get_abar_commitment, AbarConvNote, AbarToBarOps, AnonTransferOps,
             AssetRules, AssetTypeCode, BarAnonConvNote, BarToAbarOps, ConfidentialMemo,
             DefineAsset, DefineAssetBody, IndexedSignature, IssueAsset, IssueAssetBody,
-            IssuerKeyPair, IssuerPublicKey, Memo, NoReplayToken, Operation, Transaction,
-            TransactionBody, TransferAsset, TransferAssetBody, TransferType, TxOutput,
-            TxoRef, TxoSID, UpdateMemo, UpdateMemoBody, ASSET_TYPE_FRA,
-            BAR_TO_ABAR_TX_FEE_MIN, BLACK_HOLE_PUBKEY, FEE_CALCULATING_FUNC, TX_FEE_MIN,
+            IssuerKeyPair, IssuerPublicKey, Memo, NoReplayToken, Operation, OutputLock,
+            SignatureRules, Transaction, TransactionBody, TransferAsset,
+            TransferAssetBody, TransferType, TxOutput, TxoRef, TxoSID, UpdateMemo,
+            UpdateMemoBody, ASSET_TYPE_FRA, BAR_TO_ABAR_TX_FEE_MIN, BLACK_HOLE_PUBKEY,
+            FEE_CALCULATING_FUNC, TX_FEE_MIN,
         },
         staking::{
             is_valid_tendermint_addr,
@@ -43,7 +44,7 @@ use {
     serde::{Deserialize, Serialize},
     sha2::Sha512,
     std::{
-        cmp::Ordering,
+        cmp::{Ordering, Reverse},
         collections::{BTreeMap, HashMap, HashSet},
     },
     tendermint::PrivateKey,
@@ -75,7 +76,7 @@ use {
                 },
                 structs::{
                     AssetRecord, AssetRecordTemplate, AssetType, OpenAssetRecord,
-                    TracingPolicies, TracingPolicy,
+                    TracingPolicies, TracingPolicy, XfrAmount, XfrAssetType,
                 },
                 XfrNotePolicies,
             },
@@ -129,6 +130,180 @@ impl FeeInputs {
     }
 }
 
+/// The FRA fee a transaction has to pay, as required by
+/// [check_fee](ledger::data_model::Transaction::check_fee) by default
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeSchedule {
+    /// fee of a transaction
+    pub base: u64,
+    /// fee of a transaction with a `BarToAbar` operation
+    pub bar_to_abar: u64,
+}
+
+impl Default for FeeSchedule {
+    fn default() -> Self {
+        FeeSchedule {
+            base: TX_FEE_MIN,
+            bar_to_abar: BAR_TO_ABAR_TX_FEE_MIN,
+        }
+    }
+}
+
+impl FeeSchedule {
+    /// The fee of the operations of `txn`,
+    /// 0 if one of them exempts it from paying any.
+    pub fn fee_of(&self, txn: &Transaction) -> u64 {
+        let exempt = txn.is_coinbase_tx()
+            || txn.body.operations.iter().any(|op| match op {
+                Operation::DefineAsset(x) => x.body.asset.code.val == ASSET_TYPE_FRA,
+                Operation::IssueAsset(x) => x.body.code.val == ASSET_TYPE_FRA,
+                Operation::TransferAnonAsset(_)
+                | Operation::BarToAbar(_)
+                | Operation::AbarToBar(_)
+                | Operation::UpdateValidator(_) => true,
+                _ => false,
+            });
+        let bar_to_abar = txn
+            .body
+            .operations
+            .iter()
+            .any(|op| matches!(op, Operation::BarToAbar(_)));
+
+        if exempt {
+            0
+        } else if bar_to_abar {
+            self.bar_to_abar
+        } else {
+            self.base
+        }
+    }
+
+    /// The fee still to be paid by `txn`, 0 if it is exempt
+    /// or one of its outputs already pays the whole fee.
+    ///
+    /// The fee must be paid by a single output,
+    /// the ones paying a part of it are not summed up.
+    pub fn fee_due(&self, txn: &Transaction) -> u64 {
+        let fee = self.fee_of(txn);
+        let black_hole = XfrPublicKey::from_noah(&BLACK_HOLE_PUBKEY);
+        let paid = txn
+            .body
+            .operations
+            .iter()
+            .filter_map(|op| match op {
+                Operation::TransferAsset(x) => Some(x.body.outputs.iter()),
+                _ => None,
+            })
+            .flatten()
+            .any(|o| {
+                o.record.public_key == black_hole
+                    && matches!(
+                        o.record.asset_type,
+                        XfrAssetType::NonConfidential(ty) if ty == ASSET_TYPE_FRA
+                    )
+                    && matches!(
+                        o.record.amount,
+                        XfrAmount::NonConfidential(am) if am >= fee
+                    )
+            });
+        alt!(paid, 0, fee)
+    }
+}
+
+/// Max length in bytes of an asset memo accepted by `TransactionBuilder::validate`,
+/// the memos being kept by every node
+pub const MAX_MEMO_LEN: usize = 4096;
+
+/// A problem found by `TransactionBuilder::validate`
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Violation {
+    /// index of the faulty operation, None for the transaction as a whole
+    pub op_idx: Option<usize>,
+    #[allow(missing_docs)]
+    pub kind: ViolationKind,
+}
+
+/// The kinds of `Violation`
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ViolationKind {
+    /// The nonconfidential inputs and outputs of a transfer
+    /// do not add up to the same amount of an asset
+    Unbalanced {
+        #[allow(missing_docs)]
+        asset_type: AssetTypeCode,
+        #[allow(missing_docs)]
+        inputs: u128,
+        #[allow(missing_docs)]
+        outputs: u128,
+    },
+    /// An issuance whose sequence number does not exceed the one
+    /// of a previous issuance of the same asset in the transaction
+    SequenceNumber {
+        #[allow(missing_docs)]
+        code: AssetTypeCode,
+        #[allow(missing_docs)]
+        seq_num: u64,
+        #[allow(missing_docs)]
+        previous: u64,
+    },
+    /// A signature of an operation which does not verify
+    InvalidSignature {
+        #[allow(missing_docs)]
+        signer: XfrPublicKey,
+    },
+    /// A transfer input whose owner has not signed the transfer
+    UnsignedInput {
+        #[allow(missing_docs)]
+        input_idx: usize,
+        #[allow(missing_docs)]
+        owner: XfrPublicKey,
+    },
+    /// A required signer of the transaction who has not signed it,
+    /// see `TransactionBuilder::missing_signers`
+    MissingSigner {
+        #[allow(missing_docs)]
+        signer: XfrPublicKey,
+    },
+    /// An asset memo longer than `MAX_MEMO_LEN`
+    MemoTooLong {
+        #[allow(missing_docs)]
+        len: usize,
+    },
+}
+
+/// Version of the encoding of `TransactionBuilder::to_portable_bytes`, to bump
+/// along with a step of `migrate_portable` on any change of the serialized builder
+pub const PORTABLE_BUILDER_VERSION: u32 = 1;
+
+/// The versioned envelope of a portable builder
+#[derive(Serialize, Deserialize)]
+struct PortableBuilder {
+    version: u32,
+    payload: serde_json::Value,
+}
+
+/// Migrate the payload of a portable builder of `version`
+/// to the layout of `PORTABLE_BUILDER_VERSION`, one version at a time
+fn migrate_portable(
+    version: u32,
+    mut payload: serde_json::Value,
+) -> Result<serde_json::Value> {
+    if version > PORTABLE_BUILDER_VERSION {
+        return Err(eg!(format!(
+            "unsupported builder version {version}, the latest known is {PORTABLE_BUILDER_VERSION}"
+        )));
+    }
+    for v in version..PORTABLE_BUILDER_VERSION {
+        payload = match v {
+            // the raw JSON of the builder, saved before the envelope
+            // and of the same layout as version 1
+            0 => payload,
+            _ => return Err(eg!(format!("no migration from builder version {v}"))),
+        };
+    }
+    Ok(payload)
+}
+
 /// An simple builder for findora transaction
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TransactionBuilder {
@@ -142,6 +317,11 @@ pub struct TransactionBuilder {
     abar_ar_cache: Vec<AbarToArPreNote>,
     #[serde(skip)]
     abar_abar_cache: Vec<AXfrPreNote>,
+    /// the randomness of the helpers derives from it if set, see `from_seq_id_with_seed`
+    #[serde(skip)]
+    prng: Option<ChaChaRng>,
+    #[serde(skip)]
+    no_blinding: bool,
 }
 
 impl TransactionBuilder {
@@ -196,7 +376,7 @@ impl TransactionBuilder {
         &mut self,
         kp: &XfrKeyPair,
     ) -> Result<&mut TransactionBuilder> {
-        let mut opb = TransferOperationBuilder::default();
+        let mut opb = self.transfer_op_builder();
         let outputs = self.get_relative_outputs();
 
         let mut am = TX_FEE_MIN;
@@ -261,7 +441,7 @@ impl TransactionBuilder {
         fee: u64,
     ) -> Result<&mut TransactionBuilder> {
         let mut kps = vec![];
-        let mut opb = TransferOperationBuilder::default();
+        let mut opb = self.transfer_op_builder();
 
         let mut am = fee;
         for i in inputs.inner.into_iter() {
@@ -310,6 +490,110 @@ impl TransactionBuilder {
         .map(move |op| self.add_operation(op))
     }
 
+    /// As the last operation of any transaction,
+    /// add the fee `schedule` requires for the operations added so far.
+    ///
+    /// The fee is paid by the least wasteful of the FRA records of `inputs`,
+    /// nothing is added if the transaction needs no more fee.
+    pub fn add_fee_auto(
+        &mut self,
+        schedule: &FeeSchedule,
+        inputs: FeeInputs,
+    ) -> Result<&mut TransactionBuilder> {
+        let fee = schedule.fee_due(&self.txn);
+        if 0 == fee {
+            return Ok(self);
+        }
+
+        let candidates = inputs
+            .inner
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, i)| {
+                open_blind_asset_record(
+                    &i.ar.record.into_noah(),
+                    &i.om.clone().map(|o| o.into_noah()),
+                    &i.kp.into_noah(),
+                )
+                .ok()
+                .filter(|oar| ASSET_TYPE_FRA == oar.asset_type && 0 < oar.amount)
+                .map(|oar| (idx, oar.amount))
+            })
+            .collect::<Vec<_>>();
+        let amounts = candidates.iter().map(|(_, am)| *am).collect::<Vec<_>>();
+        let picked = select_coins(&amounts, fee)
+            .c(d!(format!("insufficient FRA inputs for a fee of {}", fee)))?
+            .into_iter()
+            .map(|i| candidates[i].0)
+            .collect::<HashSet<_>>();
+
+        let inner = inputs
+            .inner
+            .into_iter()
+            .enumerate()
+            .filter(|(idx, _)| picked.contains(idx))
+            .map(|(_, i)| i)
+            .collect();
+        self.add_fee_custom(FeeInputs { inner }, fee)
+    }
+
+    /// Run the checks the ledger would do without its state:
+    /// balance of the nonconfidential transfers, sequence numbers of the issuances,
+    /// signatures and memo lengths; the transaction is valid if none is violated.
+    ///
+    /// The confidential transfers are left to the ledger,
+    /// their balance being proven rather than visible.
+    pub fn validate(&self) -> Vec<Violation> {
+        let mut violations = vec![];
+        let mut seq_nums: HashMap<AssetTypeCode, u64> = HashMap::new();
+        let mut push = |op_idx, kind| violations.push(Violation { op_idx, kind });
+
+        for (idx, op) in self.txn.body.operations.iter().enumerate() {
+            match op {
+                Operation::TransferAsset(x) => {
+                    for kind in transfer_violations(x) {
+                        push(Some(idx), kind);
+                    }
+                }
+                Operation::IssueAsset(x) => {
+                    let code = x.body.code;
+                    let seq_num = x.body.seq_num;
+                    if let Some(previous) = seq_nums.insert(code, seq_num) {
+                        if seq_num <= previous {
+                            push(
+                                Some(idx),
+                                ViolationKind::SequenceNumber {
+                                    code,
+                                    seq_num,
+                                    previous,
+                                },
+                            );
+                        }
+                    }
+                }
+                Operation::DefineAsset(x) => {
+                    let len = x.body.asset.memo.0.len();
+                    if len > MAX_MEMO_LEN {
+                        push(Some(idx), ViolationKind::MemoTooLong { len });
+                    }
+                }
+                Operation::UpdateMemo(x) => {
+                    let len = x.body.new_memo.0.len();
+                    if len > MAX_MEMO_LEN {
+                        push(Some(idx), ViolationKind::MemoTooLong { len });
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for signer in self.missing_signers() {
+            push(None, ViolationKind::MissingSigner { signer });
+        }
+
+        violations
+    }
+
     /// SEE [check_fee](ledger::data_model::Transaction::check_fee)
     #[inline(always)]
     pub fn check_fee(&self) -> bool {
@@ -337,6 +621,45 @@ impl TransactionBuilder {
             abar_bar_cache: vec![],
             abar_ar_cache: vec![],
             no_replay_token,
+            prng: None,
+            no_blinding: false,
+        }
+    }
+
+    /// Create a instance from seq_id, all the randomness of which derives from `seed`:
+    /// the same calls on two instances of the same seed build the same transaction,
+    /// byte-for-byte.
+    pub fn from_seq_id_with_seed(seq_id: u64, seed: [u8; 32]) -> Self {
+        let mut prng = ChaChaRng::from_seed(seed);
+        let no_replay_token = NoReplayToken::new(&mut prng, seq_id);
+        TransactionBuilder {
+            no_replay_token,
+            prng: Some(prng),
+            ..Self::from_seq_id(seq_id)
+        }
+    }
+
+    /// Testing mode, the records built from now on are nonconfidential,
+    /// whatever the confidentiality asked for, only built with the
+    /// `no_blinding` feature so that no release can leak the amounts
+    #[cfg(any(test, feature = "no_blinding"))]
+    pub fn no_blinding(&mut self) -> &mut Self {
+        self.no_blinding = true;
+        self
+    }
+
+    /// The rng of a helper, derived from the seed of the builder if any
+    fn prng(&mut self) -> ChaChaRng {
+        derive_prng(&mut self.prng)
+    }
+
+    /// A `TransferOperationBuilder` inheriting the rng and the blinding mode
+    /// of the builder, as `TransferOperationBuilder::default()` otherwise
+    fn transfer_op_builder(&mut self) -> TransferOperationBuilder {
+        TransferOperationBuilder {
+            prng: self.prng.is_some().then(|| self.prng()),
+            no_blinding: self.no_blinding,
+            ..Default::default()
         }
     }
 
@@ -356,11 +679,11 @@ impl TransactionBuilder {
         amount: u64,
         confidentiality_flags: AssetRecordType,
     ) -> Result<&mut Self> {
-        let mut prng = ChaChaRng::from_entropy();
+        let mut prng = self.prng();
         let ar = AssetRecordTemplate::with_no_asset_tracing(
             amount,
             token_code.val,
-            confidentiality_flags,
+            blinding(confidentiality_flags, self.no_blinding),
             key_pair.get_pk().into_noah(),
         );
 
@@ -376,6 +699,7 @@ impl TransactionBuilder {
                     id: None,
                     record: BlindAssetRecord::from_noah(&ba),
                     lien: None,
+                    lock: None,
                 },
                 owner_memo.map(|om| OwnerMemo::from_noah(&om).unwrap()),
             )],
@@ -383,9 +707,22 @@ impl TransactionBuilder {
         .c(d!())
     }
 
-    #[allow(missing_docs)]
-    pub fn transaction(&self) -> &Transaction {
-        &self.txn
+    /// The transaction, refused until every required signer has signed,
+    /// see `missing_signers`
+    pub fn transaction(&self) -> Result<&Transaction> {
+        let missing = self.missing_signers();
+        if missing.is_empty() {
+            Ok(&self.txn)
+        } else {
+            Err(eg!(format!(
+                "missing signatures of: {}",
+                missing
+                    .iter()
+                    .map(wallet::public_key_to_base64)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )))
+        }
     }
 
     #[allow(missing_docs)]
@@ -401,7 +738,7 @@ impl TransactionBuilder {
 
     /// Build a transaction from various pre-notes of operations
     pub fn build(&mut self) -> Result<()> {
-        let mut prng = ChaChaRng::from_entropy();
+        let mut prng = self.prng();
 
         // hasher txn. (IMPORTANT! KEEP THE same order)
         let mut hasher = Sha512::new();
@@ -603,6 +940,189 @@ impl TransactionBuilder {
         Ok(self)
     }
 
+    /// Issue `token_code` to many recipients at once,
+    /// `outputs` being the (amount, recipient, confidentiality) of each one.
+    ///
+    /// As issued records belong to the issuer, one record per output is issued
+    /// and spent as is by a single transfer to the recipients, in the order
+    /// of `outputs`. The issued records carry no owner memo, the issuer opens
+    /// them with its own key; only the confidential outputs of the transfer do.
+    pub fn add_operation_issue_asset_batch(
+        &mut self,
+        key_pair: &XfrKeyPair,
+        token_code: &AssetTypeCode,
+        seq_num: u64,
+        outputs: &[(u64, XfrPublicKey, AssetRecordType)],
+    ) -> Result<&mut Self> {
+        if outputs.is_empty() {
+            return Err(eg!("no output to issue"));
+        }
+
+        let mut prng = self.prng();
+        let pc_gens = PedersenCommitmentRistretto::default();
+        let mut records = Vec::with_capacity(outputs.len());
+        let mut opb = self.transfer_op_builder();
+        for (i, (amount, recipient, flags)) in outputs.iter().enumerate() {
+            let template = AssetRecordTemplate::with_no_asset_tracing(
+                *amount,
+                token_code.val,
+                blinding(*flags, self.no_blinding),
+                key_pair.get_pk().into_noah(),
+            );
+            let (ba, _, owner_memo) =
+                build_blind_asset_record(&mut prng, &pc_gens, &template, vec![]);
+            let oar = open_blind_asset_record(&ba, &owner_memo, &key_pair.into_noah())
+                .c(d!())?;
+            records.push((
+                TxOutput {
+                    id: None,
+                    record: BlindAssetRecord::from_noah(&ba),
+                    lien: None,
+                    lock: None,
+                },
+                None,
+            ));
+
+            // the issued records are the last outputs of the txn,
+            // relative refs count backwards from the most recent one
+            let offset = (outputs.len() - 1 - i) as u64;
+            opb.add_input(TxoRef::Relative(offset), oar, None, None, *amount)
+                .c(d!())?
+                .add_output(
+                    &AssetRecordTemplate::with_no_asset_tracing(
+                        *amount,
+                        token_code.val,
+                        *flags,
+                        recipient.into_noah(),
+                    ),
+                    None,
+                    None,
+                    None,
+                )
+                .c(d!())?;
+        }
+
+        let xfr = opb
+            .create(TransferType::Standard)
+            .c(d!())?
+            .sign(key_pair)
+            .c(d!())?
+            .transaction()
+            .c(d!())?;
+        self.add_operation_issue_asset(key_pair, token_code, seq_num, &records)
+            .c(d!())?;
+        self.add_operation(xfr);
+        Ok(self)
+    }
+
+    /// Send the records `received` from the transfer `xfr` back to its sender,
+    /// the owner of all its inputs, by a transfer signed by `kp`, their owner.
+    ///
+    /// Each record is returned whole and as confidential as it was received,
+    /// the fee is left to the caller, see `add_fee_auto`.
+    pub fn add_operation_return_to_sender(
+        &mut self,
+        kp: &XfrKeyPair,
+        xfr: &TransferAsset,
+        received: Vec<(TxoRef, OpenAssetRecord)>,
+    ) -> Result<&mut Self> {
+        let mut senders: Vec<XfrPublicKey> = vec![];
+        for pk in xfr.get_owner_addresses() {
+            if !senders.contains(&pk) {
+                senders.push(pk);
+            }
+        }
+        let sender = match senders.as_slice() {
+            [sender] => *sender,
+            [] => return Err(eg!("the transfer has no input")),
+            _ => return Err(eg!("the transfer has several senders")),
+        };
+        if received.is_empty() {
+            return Err(eg!("no record to return"));
+        }
+
+        let mut opb = self.transfer_op_builder();
+        for (txo_sid, oar) in received {
+            if *oar.get_pub_key() != kp.get_pk().into_noah() {
+                return Err(eg!("a record to return is not owned by the key pair"));
+            }
+            let template = AssetRecordTemplate::with_no_asset_tracing(
+                *oar.get_amount(),
+                *oar.get_asset_type(),
+                oar.get_record_type(),
+                sender.into_noah(),
+            );
+            let amount = *oar.get_amount();
+            opb.add_input(txo_sid, oar, None, None, amount)
+                .c(d!())?
+                .add_output(&template, None, None, None)
+                .c(d!())?;
+        }
+
+        let op = opb
+            .create(TransferType::Standard)
+            .c(d!())?
+            .sign(kp)
+            .c(d!())?
+            .transaction()
+            .c(d!())?;
+        Ok(self.add_operation(op))
+    }
+
+    /// Re-emit the `records` of `kp` as records of `record_type`, e.g. revealing
+    /// the amounts of confidential deposits or hiding the ones of public records:
+    /// one output to `kp` per asset type, of the total amount of its records.
+    ///
+    /// The owner memos and the proofs are made by the transfer,
+    /// the fee is left to the caller, see `add_fee_auto`.
+    pub fn add_operation_convert_confidentiality(
+        &mut self,
+        kp: &XfrKeyPair,
+        records: Vec<(TxoRef, OpenAssetRecord)>,
+        record_type: AssetRecordType,
+    ) -> Result<&mut Self> {
+        if records
+            .iter()
+            .all(|(_, oar)| oar.get_record_type() == record_type)
+        {
+            return Err(eg!("no record to convert"));
+        }
+
+        let mut opb = self.transfer_op_builder();
+        let mut totals: BTreeMap<AssetTypeCode, u64> = BTreeMap::new();
+        for (txo_sid, oar) in records {
+            if *oar.get_pub_key() != kp.get_pk().into_noah() {
+                return Err(eg!("a record to convert is not owned by the key pair"));
+            }
+            let amount = *oar.get_amount();
+            let total = totals
+                .entry(AssetTypeCode {
+                    val: *oar.get_asset_type(),
+                })
+                .or_default();
+            *total = total.checked_add(amount).c(d!("overflow"))?;
+            opb.add_input(txo_sid, oar, None, None, amount).c(d!())?;
+        }
+        for (code, amount) in totals {
+            let template = AssetRecordTemplate::with_no_asset_tracing(
+                amount,
+                code.val,
+                record_type,
+                kp.get_pk().into_noah(),
+            );
+            opb.add_output(&template, None, None, None).c(d!())?;
+        }
+
+        let op = opb
+            .create(TransferType::Standard)
+            .c(d!())?
+            .sign(kp)
+            .c(d!())?
+            .transaction()
+            .c(d!())?;
+        Ok(self.add_operation(op))
+    }
+
     /// Add asset transfer operation to builder and return modified builder
     #[allow(clippy::too_many_arguments)]
     pub fn add_operation_transfer_asset(
@@ -615,7 +1135,7 @@ impl TransactionBuilder {
         output_records: &[AssetRecord],
         _output_identity_commitments: Vec<Option<ACCommitment>>,
     ) -> Result<&mut Self> {
-        let mut prng = ChaChaRng::from_entropy();
+        let mut prng = self.prng();
         let mut input_asset_records = vec![];
         for (oar, tracing_policy) in
             input_records.iter().zip(input_tracing_policies.iter())
@@ -725,7 +1245,7 @@ impl TransactionBuilder {
         bar_pub_key: &XfrPublicKey,
         asset_record_type: AssetRecordType,
     ) -> Result<&mut Self> {
-        let mut prng = ChaChaRng::from_entropy();
+        let mut prng = self.prng();
         match asset_record_type {
             AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType => {
                 let note = init_abar_to_ar_note(
@@ -789,7 +1309,7 @@ impl TransactionBuilder {
         outputs: &[OpenAnonAssetRecord],
         input_keypair: &XfrKeyPair,
     ) -> Result<(&mut Self, AXfrPreNote, Vec<OpenAnonAssetRecord>)> {
-        let mut prng = ChaChaRng::from_entropy();
+        let mut prng = self.prng();
 
         let mut vec_outputs = outputs.to_vec();
         let mut vec_changes = vec![];
@@ -1102,6 +1622,64 @@ impl TransactionBuilder {
         self
     }
 
+    /// The builder in a versioned envelope, `{version, payload}`,
+    /// to be loaded by `from_portable_bytes` of this release or a later one
+    pub fn to_portable_bytes(&self) -> Result<Vec<u8>> {
+        let payload = serde_json::to_value(self).c(d!())?;
+        serde_json::to_vec(&PortableBuilder {
+            version: PORTABLE_BUILDER_VERSION,
+            payload,
+        })
+        .c(d!())
+    }
+
+    /// Load a builder saved by `to_portable_bytes`, migrating the older versions,
+    /// the raw JSON of a builder saved before the envelope included
+    pub fn from_portable_bytes(bytes: &[u8]) -> Result<Self> {
+        let value = serde_json::from_slice::<serde_json::Value>(bytes).c(d!())?;
+        let envelope = if value.get("version").is_some() {
+            serde_json::from_value::<PortableBuilder>(value).c(d!())?
+        } else {
+            PortableBuilder {
+                version: 0,
+                payload: value,
+            }
+        };
+        migrate_portable(envelope.version, envelope.payload)
+            .c(d!())
+            .and_then(|payload| serde_json::from_value(payload).c(d!()))
+    }
+
+    /// The payload to be signed by each signer of a multi-party transaction,
+    /// the JSON encoding of its body, to be exported once the operations are all
+    /// added and built, see `sign_payload`
+    pub fn signing_payload(&self) -> Vec<u8> {
+        // Unwrap is safe because the body is guaranteed to be serializable.
+        serde_json::to_vec(&self.txn.body).unwrap()
+    }
+
+    /// Check and add the signature of a signer, made by `sign_payload`,
+    /// the signatures may be added in any order, and more than once
+    pub fn add_detached_signature(
+        &mut self,
+        pk: &XfrPublicKey,
+        sig: SignatureOf<TransactionBody>,
+    ) -> Result<&mut Self> {
+        self.txn.check_signature(pk, &sig).c(d!())?;
+        if self.txn.signatures.is_empty() {
+            self.txn.pubkey_sign_map.insert(*pk, sig);
+        } else if self.txn.check_has_signature(pk).is_err() {
+            // the map is not checked once the txn has any plain signature
+            self.txn.signatures.push(sig);
+        }
+        Ok(self)
+    }
+
+    /// The required signers of the transaction without a signature yet
+    pub fn missing_signers(&self) -> Vec<XfrPublicKey> {
+        self.txn.missing_signers()
+    }
+
     #[allow(missing_docs)]
     pub fn serialize(&self) -> Vec<u8> {
         // Unwrap is safe beacuse the underlying transaction is guaranteed to be serializable.
@@ -1116,6 +1694,100 @@ impl TransactionBuilder {
     }
 }
 
+/// Sign a payload exported by `TransactionBuilder::signing_payload`,
+/// the signature is then added by `TransactionBuilder::add_detached_signature`
+pub fn sign_payload(
+    kp: &XfrKeyPair,
+    payload: &[u8],
+) -> Result<SignatureOf<TransactionBody>> {
+    serde_json::from_slice::<TransactionBody>(payload)
+        .c(d!())
+        .map(|body| SignatureOf::new(kp, &body))
+}
+
+/// The violations of a transfer checked by `TransactionBuilder::validate`
+fn transfer_violations(x: &TransferAsset) -> Vec<ViolationKind> {
+    let mut violations = vec![];
+
+    let mut signers = HashSet::new();
+    for sig in x.body_signatures.iter() {
+        if x.body.verify_body_signature(sig) {
+            signers.insert(sig.address.key.to_bytes());
+        } else {
+            violations.push(ViolationKind::InvalidSignature {
+                signer: sig.address.key,
+            });
+        }
+    }
+    if TransferType::Standard == x.body.transfer_type {
+        for (input_idx, record) in x.body.transfer.inputs.iter().enumerate() {
+            if !signers.contains(&record.public_key.to_bytes()) {
+                violations.push(ViolationKind::UnsignedInput {
+                    input_idx,
+                    owner: record.public_key,
+                });
+            }
+        }
+    }
+
+    let nonconfidential =
+        |record: &BlindAssetRecord| match (&record.asset_type, &record.amount) {
+            (XfrAssetType::NonConfidential(ty), XfrAmount::NonConfidential(am)) => {
+                Some((*ty, *am))
+            }
+            _ => None,
+        };
+    let all_nonconfidential = x
+        .body
+        .transfer
+        .inputs
+        .iter()
+        .chain(x.body.transfer.outputs.iter())
+        .all(|record| nonconfidential(record).is_some());
+    if all_nonconfidential {
+        let mut totals: BTreeMap<AssetTypeCode, (u128, u128)> = BTreeMap::new();
+        for record in x.body.transfer.inputs.iter() {
+            if let Some((ty, am)) = nonconfidential(record) {
+                totals.entry(AssetTypeCode { val: ty }).or_default().0 += am as u128;
+            }
+        }
+        for record in x.body.transfer.outputs.iter() {
+            if let Some((ty, am)) = nonconfidential(record) {
+                totals.entry(AssetTypeCode { val: ty }).or_default().1 += am as u128;
+            }
+        }
+        for (asset_type, (inputs, outputs)) in totals {
+            if inputs != outputs {
+                violations.push(ViolationKind::Unbalanced {
+                    asset_type,
+                    inputs,
+                    outputs,
+                });
+            }
+        }
+    }
+
+    violations
+}
+
+/// A new rng, derived from the seeded `prng` of a builder if any, from entropy otherwise
+fn derive_prng(prng: &mut Option<ChaChaRng>) -> ChaChaRng {
+    prng.as_mut().map_or_else(ChaChaRng::from_entropy, |prng| {
+        let mut seed = [0u8; 32];
+        prng.fill_bytes(&mut seed);
+        ChaChaRng::from_seed(seed)
+    })
+}
+
+/// The record type of `flags`, nonconfidential in the no blinding testing mode
+fn blinding(flags: AssetRecordType, no_blinding: bool) -> AssetRecordType {
+    alt!(
+        no_blinding,
+        AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+        flags
+    )
+}
+
 /// Generates an asset record from an asset record template using optional identity proof.
 /// Returns the asset record, amount blinds, and type blind.
 pub(crate) fn build_record_and_get_blinds<R: CryptoRng + RngCore>(
@@ -1232,38 +1904,200 @@ fn gen_bar_conv_note(
     }
 }
 
-/// TransferOperationBuilder constructs transfer operations using the factory pattern
-/// Inputs and outputs are added iteratively before being signed by all input record owners
-#[derive(Clone, Serialize, Deserialize, Default)]
-pub struct TransferOperationBuilder {
-    input_sids: Vec<TxoRef>,
-    spend_amounts: Vec<u64>, // Amount of each input record to spend, the rest will be refunded if user calls balance
-    input_records: Vec<AssetRecord>,
-    inputs_tracing_policies: Vec<TracingPolicies>,
-    input_identity_commitments: Vec<Option<ACCommitment>>,
-    output_records: Vec<AssetRecord>,
-    outputs_tracing_policies: Vec<TracingPolicies>,
-    output_identity_commitments: Vec<Option<ACCommitment>>,
-    transfer: Option<TransferAsset>,
-    transfer_type: TransferType,
-    auto_refund: bool,
-}
+/// Max count of the subsets explored by `select_coins` for an exact match
+const MAX_COIN_SELECTION_TRIES: usize = 100_000;
+
+/// Least-waste coin selection, returns the indexes of the `amounts` to spend
+/// for `target`, None if they do not add up to it.
+///
+/// The waste is the change left over, then the count of inputs:
+/// an exact match is searched first, then the smallest amount covering the
+/// target alone is weighed against the largest amounts covering it together.
+fn select_coins(amounts: &[u64], target: u64) -> Option<Vec<usize>> {
+    let total = amounts.iter().map(|a| *a as u128).sum::<u128>();
+    if 0 == target || total < target as u128 {
+        return None;
+    }
 
-impl TransferOperationBuilder {
-    #[allow(missing_docs)]
-    pub fn new() -> Self {
-        Self {
-            auto_refund: true,
-            ..Default::default()
-        }
+    let mut desc = (0..amounts.len()).collect::<Vec<_>>();
+    desc.sort_by_key(|i| Reverse(amounts[*i]));
+
+    // sums of the amounts from each position of `desc` on, to prune the search
+    let mut rest = vec![0u128; desc.len() + 1];
+    for k in (0..desc.len()).rev() {
+        rest[k] = rest[k + 1] + amounts[desc[k]] as u128;
+    }
+    let mut exact = vec![];
+    if search_exact_coins(amounts, &desc, &rest, 0, target as u128, &mut exact, &mut 0) {
+        return Some(exact);
     }
 
-    /// set auto_refund, will be checked when calling `create`
-    pub fn auto_refund(&mut self, auto_refund: bool) -> &mut Self {
+    let sum = |sel: &[usize]| sel.iter().map(|i| amounts[*i] as u128).sum::<u128>();
+
+    let single = desc
+        .iter()
+        .rev()
+        .find(|i| amounts[**i] >= target)
+        .map(|i| vec![*i]);
+
+    let mut largest = vec![];
+    for i in desc.iter() {
+        if sum(&largest) >= target as u128 {
+            break;
+        }
+        largest.push(*i);
+    }
+    // drop the ones not needed, from the smallest
+    for pos in (0..largest.len()).rev() {
+        if sum(&largest) - amounts[largest[pos]] as u128 >= target as u128 {
+            largest.remove(pos);
+        }
+    }
+
+    [single, Some(largest)]
+        .into_iter()
+        .flatten()
+        .min_by_key(|sel| (sum(sel) - target as u128, sel.len()))
+}
+
+/// Depth-first search of a subset of the amounts summing up to `left`,
+/// over the indexes of `desc` from `k` on
+fn search_exact_coins(
+    amounts: &[u64],
+    desc: &[usize],
+    rest: &[u128],
+    k: usize,
+    left: u128,
+    selected: &mut Vec<usize>,
+    tries: &mut usize,
+) -> bool {
+    if 0 == left {
+        return true;
+    }
+    if k == desc.len() || rest[k] < left || *tries >= MAX_COIN_SELECTION_TRIES {
+        return false;
+    }
+    *tries += 1;
+
+    let amount = amounts[desc[k]] as u128;
+    if amount <= left {
+        selected.push(desc[k]);
+        if search_exact_coins(amounts, desc, rest, k + 1, left - amount, selected, tries)
+        {
+            return true;
+        }
+        selected.pop();
+    }
+    search_exact_coins(amounts, desc, rest, k + 1, left, selected, tries)
+}
+
+/// A N-of-M threshold address, for escrows.
+///
+/// The records sent to it are owned by the key of its custodian, who alone
+/// can read their confidential amounts and sign the transfers spending them,
+/// and are locked by the ledger until such a transfer also carries
+/// the co-signatures of N signers, see `OutputLock::Threshold`.
+/// The custodian may be one of the signers.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ThresholdAddress {
+    /// the custodian, owner of the records
+    pub owner: XfrPublicKey,
+    /// the signers, each of weight 1, sorted
+    pub rules: SignatureRules,
+}
+
+impl ThresholdAddress {
+    /// The address of `threshold` signatures out of the ones of `signers`,
+    /// held by `owner`
+    pub fn new(
+        owner: XfrPublicKey,
+        threshold: u64,
+        signers: &[XfrPublicKey],
+    ) -> Result<Self> {
+        let mut keys = signers.to_vec();
+        keys.sort_by_key(|pk| pk.to_bytes());
+        keys.dedup();
+        if 0 == threshold || threshold > keys.len() as u64 {
+            return Err(eg!(format!(
+                "invalid threshold {threshold} of {} signers",
+                keys.len()
+            )));
+        }
+        Ok(ThresholdAddress {
+            owner,
+            rules: SignatureRules {
+                threshold,
+                weights: keys.into_iter().map(|pk| (pk, 1)).collect(),
+            },
+        })
+    }
+}
+
+/// TransferOperationBuilder constructs transfer operations using the factory pattern
+/// Inputs and outputs are added iteratively before being signed by all input record owners
+#[derive(Clone, Serialize, Deserialize, Default)]
+pub struct TransferOperationBuilder {
+    input_sids: Vec<TxoRef>,
+    spend_amounts: Vec<u64>, // Amount of each input record to spend, the rest will be refunded if user calls balance
+    input_records: Vec<AssetRecord>,
+    inputs_tracing_policies: Vec<TracingPolicies>,
+    input_identity_commitments: Vec<Option<ACCommitment>>,
+    output_records: Vec<AssetRecord>,
+    outputs_tracing_policies: Vec<TracingPolicies>,
+    output_identity_commitments: Vec<Option<ACCommitment>>,
+    transfer: Option<TransferAsset>,
+    transfer_type: TransferType,
+    auto_refund: bool,
+    /// output index => lock, set on the outputs by `create`
+    #[serde(default)]
+    output_locks: BTreeMap<usize, OutputLock>,
+    #[serde(skip)]
+    prng: Option<ChaChaRng>,
+    #[serde(skip)]
+    no_blinding: bool,
+    /// transfer co-signature rules of the assets, see `add_asset_rules`
+    #[serde(default)]
+    cosig_rules: Vec<(AssetTypeCode, SignatureRules)>,
+    /// input index => co-signature rules of its threshold address
+    #[serde(default)]
+    input_cosig_rules: BTreeMap<usize, SignatureRules>,
+}
+
+impl TransferOperationBuilder {
+    #[allow(missing_docs)]
+    pub fn new() -> Self {
+        Self {
+            auto_refund: true,
+            ..Default::default()
+        }
+    }
+
+    /// set auto_refund, will be checked when calling `create`
+    pub fn auto_refund(&mut self, auto_refund: bool) -> &mut Self {
         self.auto_refund = auto_refund;
         self
     }
 
+    /// Draw all the randomness of the operation from `seed`,
+    /// see `TransactionBuilder::from_seq_id_with_seed`
+    pub fn with_seed(&mut self, seed: [u8; 32]) -> &mut Self {
+        self.prng = Some(ChaChaRng::from_seed(seed));
+        self
+    }
+
+    /// Testing mode, the outputs added from now on are nonconfidential,
+    /// whatever the confidentiality of their templates, see
+    /// `TransactionBuilder::no_blinding`
+    #[cfg(any(test, feature = "no_blinding"))]
+    pub fn no_blinding(&mut self) -> &mut Self {
+        self.no_blinding = true;
+        self
+    }
+
+    fn prng(&mut self) -> ChaChaRng {
+        derive_prng(&mut self.prng)
+    }
+
     /// TxoRef is the location of the input on the ledger and the amount is how much of the record
     /// should be spent in the transfer. See tests for example usage.
     pub fn add_input(
@@ -1283,7 +2117,7 @@ impl TransferOperationBuilder {
 
         let asset_record =
             AssetRecord::from_open_asset_record_with_asset_tracing_but_no_identity(
-                &mut ChaChaRng::from_entropy(),
+                &mut self.prng(),
                 open_ar,
                 policies.clone(),
             )
@@ -1296,6 +2130,69 @@ impl TransferOperationBuilder {
         Ok(self)
     }
 
+    /// Pick the inputs spending `target_amount` out of `oars` by least-waste
+    /// coin selection, and add them along with an output of the change,
+    /// if any, to the owner of the last one picked.
+    ///
+    /// The records must be of one asset type, the selected ones are spent
+    /// entirely, so the outputs of `target_amount` are left to the caller.
+    pub fn add_inputs_auto(
+        &mut self,
+        oars: Vec<(TxoRef, OpenAssetRecord)>,
+        target_amount: u64,
+    ) -> Result<&mut Self> {
+        if self.transfer.is_some() {
+            return Err(eg!(
+                ("Cannot mutate a transfer that has been signed".to_string())
+            ));
+        }
+
+        let asset_type = oars
+            .first()
+            .map(|(_, oar)| *oar.get_asset_type())
+            .c(d!("no input record"))?;
+        if oars
+            .iter()
+            .any(|(_, oar)| *oar.get_asset_type() != asset_type)
+        {
+            return Err(eg!("input records of different asset types"));
+        }
+
+        let amounts = oars
+            .iter()
+            .map(|(_, oar)| *oar.get_amount())
+            .collect::<Vec<_>>();
+        let selected = select_coins(&amounts, target_amount)
+            .c(d!(format!("insufficient balance for {target_amount}")))?;
+        let change = selected
+            .iter()
+            .try_fold(0u64, |acc, i| acc.checked_add(amounts[*i]))
+            .and_then(|total| total.checked_sub(target_amount))
+            .c(d!("overflow"))?;
+
+        let mut oars = oars.into_iter().map(Some).collect::<Vec<_>>();
+        let mut change_to = None;
+        for i in selected {
+            let (txo_sid, oar) = oars[i].take().c(d!())?;
+            let amount = *oar.get_amount();
+            change_to = Some((*oar.get_pub_key(), oar.get_record_type()));
+            self.add_input(txo_sid, oar, None, None, amount).c(d!())?;
+        }
+
+        if 0 < change {
+            let (owner, record_type) = change_to.c(d!())?;
+            let template = AssetRecordTemplate::with_no_asset_tracing(
+                change,
+                asset_type,
+                record_type,
+                owner,
+            );
+            self.add_output(&template, None, None, None).c(d!())?;
+        }
+
+        Ok(self)
+    }
+
     #[allow(missing_docs)]
     pub fn add_output(
         &mut self,
@@ -1304,27 +2201,29 @@ impl TransferOperationBuilder {
         identity_commitment: Option<ACCommitment>,
         credential_record: Option<(&ACUserSecretKey, &Credential, &ACCommitmentKey)>,
     ) -> Result<&mut Self> {
-        let prng = &mut ChaChaRng::from_entropy();
+        let prng = &mut self.prng();
         if self.transfer.is_some() {
             return Err(eg!(
                 ("Cannot mutate a transfer that has been signed".to_string())
             ));
         }
         let policies = tracing_policies.unwrap_or_default();
+        let mut template = asset_record_template.clone();
+        template.asset_record_type =
+            blinding(template.asset_record_type, self.no_blinding);
         let ar = if let Some((user_secret_key, credential, commitment_key)) =
             credential_record
         {
             AssetRecord::from_template_with_identity_tracing(
                 prng,
-                asset_record_template,
+                &template,
                 user_secret_key,
                 credential,
                 commitment_key,
             )
             .c(d!())?
         } else {
-            AssetRecord::from_template_no_identity_tracing(prng, asset_record_template)
-                .c(d!())?
+            AssetRecord::from_template_no_identity_tracing(prng, &template).c(d!())?
         };
         self.output_records.push(ar);
         self.outputs_tracing_policies.push(policies);
@@ -1332,6 +2231,66 @@ impl TransferOperationBuilder {
         Ok(self)
     }
 
+    /// Add an output the ledger keeps locked
+    /// until it has committed the block of `unlock_height`
+    pub fn add_output_with_lock(
+        &mut self,
+        asset_record_template: &AssetRecordTemplate,
+        unlock_height: u64,
+    ) -> Result<&mut Self> {
+        self.add_locked_output(asset_record_template, OutputLock::Height(unlock_height))
+    }
+
+    /// Add an output the ledger keeps locked
+    /// until it has committed a block of `unlock_time` or later, in seconds
+    pub fn add_output_with_time_lock(
+        &mut self,
+        asset_record_template: &AssetRecordTemplate,
+        unlock_time: i64,
+    ) -> Result<&mut Self> {
+        self.add_locked_output(asset_record_template, OutputLock::Time(unlock_time))
+    }
+
+    /// Add an output to a threshold address, the owner of the template
+    /// is replaced by the custodian of the address
+    pub fn add_output_to_threshold(
+        &mut self,
+        asset_record_template: &AssetRecordTemplate,
+        address: &ThresholdAddress,
+    ) -> Result<&mut Self> {
+        let mut template = asset_record_template.clone();
+        template.public_key = address.owner.into_noah();
+        self.add_locked_output(&template, OutputLock::Threshold(address.rules.clone()))
+    }
+
+    /// Add an input held by a threshold address, to be signed by the custodian
+    /// of the address and co-signed by enough of its signers, see `create_cosignature`
+    pub fn add_threshold_input(
+        &mut self,
+        txo_sid: TxoRef,
+        open_ar: OpenAssetRecord,
+        address: &ThresholdAddress,
+        amount: u64,
+    ) -> Result<&mut Self> {
+        let idx = self.input_records.len();
+        self.add_input(txo_sid, open_ar, None, None, amount)
+            .c(d!())?;
+        self.input_cosig_rules.insert(idx, address.rules.clone());
+        Ok(self)
+    }
+
+    fn add_locked_output(
+        &mut self,
+        asset_record_template: &AssetRecordTemplate,
+        lock: OutputLock,
+    ) -> Result<&mut Self> {
+        let idx = self.output_records.len();
+        self.add_output(asset_record_template, None, None, None)
+            .c(d!())?;
+        self.output_locks.insert(idx, lock);
+        Ok(self)
+    }
+
     /// Adds output to the records, and stores the asset amount blinds and type blind in the blinds parameter passed in.
     pub fn add_output_and_store_blinds<R: CryptoRng + RngCore>(
         &mut self,
@@ -1420,7 +2379,7 @@ impl TransferOperationBuilder {
     /// Ensures that outputs and inputs are balanced by adding remainder outputs for leftover asset
     /// amounts
     pub fn balance(&mut self, rt: Option<AssetRecordType>) -> Result<&mut Self> {
-        let mut prng = ChaChaRng::from_entropy();
+        let mut prng = self.prng();
         if self.transfer.is_some() {
             return Err(eg!(
                 ("Cannot mutate a transfer that has been signed".to_string())
@@ -1447,8 +2406,10 @@ impl TransferOperationBuilder {
                 }
                 Ordering::Less => {
                     let asset_type = *ar.open_asset_record.get_asset_type();
-                    let record_type =
-                        rt.unwrap_or_else(|| ar.open_asset_record.get_record_type());
+                    let record_type = blinding(
+                        rt.unwrap_or_else(|| ar.open_asset_record.get_record_type()),
+                        self.no_blinding,
+                    );
                     let recipient = *ar.open_asset_record.get_pub_key();
                     let ar_template = AssetRecordTemplate::with_asset_tracing(
                         amt - spend_amount,
@@ -1499,7 +2460,7 @@ impl TransferOperationBuilder {
             self.check_balance().c(d!())?;
         }
 
-        let mut prng = ChaChaRng::from_entropy();
+        let mut prng = self.prng();
         let num_inputs = self.input_records.len();
         let num_outputs = self.output_records.len();
         let xfr_policies = XfrNotePolicies::new(
@@ -1508,7 +2469,7 @@ impl TransferOperationBuilder {
             self.outputs_tracing_policies.clone(),
             vec![None; num_outputs],
         );
-        let body = TransferAssetBody::new(
+        let mut body = TransferAssetBody::new(
             &mut prng,
             self.input_sids.clone(),
             &self.input_records,
@@ -1518,6 +2479,9 @@ impl TransferOperationBuilder {
             transfer_type,
         )
         .c(d!())?;
+        for (idx, lock) in self.output_locks.iter() {
+            body.outputs.get_mut(*idx).c(d!())?.lock = Some(lock.clone());
+        }
         self.transfer = Some(TransferAsset::new(body).c(d!())?);
         Ok(self)
     }
@@ -1568,6 +2532,70 @@ impl TransferOperationBuilder {
         Ok(self)
     }
 
+    /// Load the rules of the asset `code`: once signed by the owners,
+    /// its inputs must be co-signed up to the threshold
+    /// of its transfer co-signature rules, if any, see `validate_signatures`
+    pub fn add_asset_rules(
+        &mut self,
+        code: AssetTypeCode,
+        rules: &AssetRules,
+    ) -> &mut Self {
+        self.cosig_rules.retain(|(c, _)| *c != code);
+        if let Some(sig_rules) = rules.transfer_multisig_rules.as_ref() {
+            self.cosig_rules.push((code, sig_rules.clone()));
+        }
+        self
+    }
+
+    /// The co-signature of the input `input_idx` by a co-signer of its asset,
+    /// to attach with `attach_signature`
+    pub fn create_cosignature(
+        &self,
+        keypair: &XfrKeyPair,
+        input_idx: usize,
+    ) -> Result<IndexedSignature<TransferAssetBody>> {
+        let trn = self.transfer.as_ref().c(d!(no_transfer_err!()))?;
+        if input_idx >= trn.body.transfer.inputs.len() {
+            return Err(eg!(format!("no input of index {input_idx}")));
+        }
+        Ok(trn.body.compute_body_signature(keypair, Some(input_idx)))
+    }
+
+    /// The inputs whose valid co-signatures do not reach the threshold
+    /// of the rules of their threshold address or of their asset,
+    /// with the co-signers who have not signed them
+    pub fn missing_cosigners(&self) -> Result<Vec<(usize, Vec<XfrPublicKey>)>> {
+        let trn = self.transfer.as_ref().c(d!(no_transfer_err!()))?;
+        let mut missing = vec![];
+        for (idx, ar) in self.input_records.iter().enumerate() {
+            let rules = match self.input_cosig_rules.get(&idx).or_else(|| {
+                self.cosig_rules
+                    .iter()
+                    .find(|(code, _)| code.val == ar.open_asset_record.asset_type)
+                    .map(|(_, rules)| rules)
+            }) {
+                Some(rules) => rules,
+                None => continue,
+            };
+            let signed = trn
+                .body_signatures
+                .iter()
+                .filter(|sig| Some(idx) == sig.input_idx && sig.verify(&trn.body))
+                .map(|sig| sig.address.key.to_bytes())
+                .collect::<HashSet<_>>();
+            if rules.check_signature_set(&signed).is_err() {
+                let unsigned = rules
+                    .weights
+                    .iter()
+                    .filter(|(key, _)| !signed.contains(&key.to_bytes()))
+                    .map(|(key, _)| *key)
+                    .collect();
+                missing.push((idx, unsigned));
+            }
+        }
+        Ok(missing)
+    }
+
     /// Return the transaction operation
     pub fn transaction(&self) -> Result<Operation> {
         if self.transfer.is_none() {
@@ -1596,8 +2624,170 @@ impl TransferOperationBuilder {
                 return Err(eg!(("Not all signatures present")));
             }
         }
+
+        let missing = self.missing_cosigners().c(d!())?;
+        if !missing.is_empty() {
+            return Err(eg!(format!(
+                "missing co-signatures of: {}",
+                missing
+                    .iter()
+                    .map(|(idx, keys)| format!(
+                        "input {}: {}",
+                        idx,
+                        keys.iter()
+                            .map(wallet::public_key_to_base64)
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ))
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            )));
+        }
+        Ok(self)
+    }
+}
+
+/// One side of a swap, a party paying an amount of one asset type to the other one
+#[derive(Clone, Serialize, Deserialize)]
+struct SwapLeg {
+    payer: XfrPublicKey,
+    recipient: XfrPublicKey,
+    asset_type: AssetType,
+}
+
+/// SwapBuilder composes an atomic swap of two asset types between two parties:
+/// a single transfer where the inputs of each party pay the other one,
+/// only valid once signed by both of them
+///
+/// The transfer is signed by each party, then put in a transaction by
+/// `into_transaction_builder`, which has to be signed by each party as well.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SwapBuilder {
+    op: TransferOperationBuilder,
+    legs: Vec<SwapLeg>,
+}
+
+impl Default for SwapBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SwapBuilder {
+    #[allow(missing_docs)]
+    pub fn new() -> Self {
+        SwapBuilder {
+            op: TransferOperationBuilder::new(),
+            legs: vec![],
+        }
+    }
+
+    /// Add the side of a party, paying `amount` out of `oars` to `recipient`,
+    /// the records must be owned by the party and be of one asset type,
+    /// the change goes back to the party
+    pub fn add_leg(
+        &mut self,
+        oars: Vec<(TxoRef, OpenAssetRecord)>,
+        amount: u64,
+        recipient: &XfrPublicKey,
+    ) -> Result<&mut Self> {
+        if 2 <= self.legs.len() {
+            return Err(eg!("a swap has two legs"));
+        }
+        let (payer, asset_type, record_type) = oars
+            .first()
+            .map(|(_, oar)| {
+                (
+                    *oar.get_pub_key(),
+                    *oar.get_asset_type(),
+                    oar.get_record_type(),
+                )
+            })
+            .c(d!("no input record"))?;
+        if oars.iter().any(|(_, oar)| *oar.get_pub_key() != payer) {
+            return Err(eg!("input records of different owners"));
+        }
+
+        let template = AssetRecordTemplate::with_no_asset_tracing(
+            amount,
+            asset_type,
+            record_type,
+            recipient.into_noah(),
+        );
+        self.op
+            .add_inputs_auto(oars, amount)
+            .c(d!())?
+            .add_output(&template, None, None, None)
+            .c(d!())?;
+        self.legs.push(SwapLeg {
+            payer: XfrPublicKey::from_noah(&payer),
+            recipient: *recipient,
+            asset_type,
+        });
+        Ok(self)
+    }
+
+    /// Check that the two legs pay each other, in two asset types,
+    /// and create the transfer to be signed
+    pub fn create(&mut self) -> Result<&mut Self> {
+        let (a, b) = match self.legs.as_slice() {
+            [a, b] => (a, b),
+            _ => return Err(eg!("a swap needs two legs")),
+        };
+        if a.payer == b.payer {
+            return Err(eg!("a swap is between two parties"));
+        }
+        if a.payer != b.recipient || b.payer != a.recipient {
+            return Err(eg!("the legs of a swap must pay each other"));
+        }
+        if a.asset_type == b.asset_type {
+            return Err(eg!("a swap is between two asset types"));
+        }
+
+        self.op.create(TransferType::Standard).c(d!())?;
+        Ok(self)
+    }
+
+    /// Sign the transfer with the key of a party
+    pub fn sign(&mut self, kp: &XfrKeyPair) -> Result<&mut Self> {
+        self.op.sign(kp).c(d!())?;
+        Ok(self)
+    }
+
+    /// The signature of the transfer by a party, for the other one to attach
+    pub fn create_input_signature(
+        &self,
+        kp: &XfrKeyPair,
+    ) -> Result<IndexedSignature<TransferAssetBody>> {
+        self.op.create_input_signature(kp).c(d!())
+    }
+
+    /// Attach the signature of the transfer by the other party
+    pub fn attach_signature(
+        &mut self,
+        sig: IndexedSignature<TransferAssetBody>,
+    ) -> Result<&mut Self> {
+        self.op.attach_signature(sig).c(d!())?;
         Ok(self)
     }
+
+    /// A transaction builder with the swap, once both parties have signed the transfer,
+    /// its transaction is refused until both of them have signed it too,
+    /// see `TransactionBuilder::add_detached_signature`
+    pub fn into_transaction_builder(
+        mut self,
+        seq_id: u64,
+    ) -> Result<TransactionBuilder> {
+        let op = self
+            .op
+            .validate_signatures()
+            .c(d!())?
+            .transaction()
+            .c(d!())?;
+        let mut builder = TransactionBuilder::from_seq_id(seq_id);
+        builder.add_operation(op);
+        Ok(builder)
+    }
 }
 
 /// AnonTransferOperationBuilder builders anon transfer operation using the factory pattern.
@@ -2244,6 +3434,949 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_select_coins() {
+        // an exact match
+        assert_eq!(select_coins(&[5, 30, 7, 3], 10), Some(vec![2, 3]));
+        // one record with less change than the largest ones
+        assert_eq!(select_coins(&[100, 60, 45], 50), Some(vec![1]));
+        // the largest ones, then the unneeded dropped
+        assert_eq!(select_coins(&[40, 30, 1, 25], 67).map(|s| s.len()), Some(2));
+        assert!(select_coins(&[1, 2], 4).is_none());
+        assert!(select_coins(&[1, 2], 0).is_none());
+    }
+
+    #[test]
+    fn test_add_inputs_auto() {
+        pnk!(test_add_inputs_auto_inner());
+    }
+
+    fn test_add_inputs_auto_inner() -> Result<()> {
+        let mut prng = ChaChaRng::from_entropy();
+        let pc_gens = PedersenCommitmentRistretto::default();
+        let code = AssetTypeCode::gen_random();
+        let alice = XfrKeyPair::generate(&mut prng);
+        let bob = XfrKeyPair::generate(&mut prng);
+
+        let oars = [40, 30, 25]
+            .iter()
+            .enumerate()
+            .map(|(i, amount)| {
+                let template = AssetRecordTemplate::with_no_asset_tracing(
+                    *amount,
+                    code.val,
+                    NonConfidentialAmount_NonConfidentialAssetType,
+                    alice.get_pk().into_noah(),
+                );
+                let (ba, _, memo) =
+                    build_blind_asset_record(&mut prng, &pc_gens, &template, vec![]);
+                open_blind_asset_record(&ba, &memo, &alice.into_noah())
+                    .c(d!())
+                    .map(|oar| (TxoRef::Absolute(TxoSID(i as u64)), oar))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let output_template = AssetRecordTemplate::with_no_asset_tracing(
+            60,
+            code.val,
+            NonConfidentialAmount_NonConfidentialAssetType,
+            bob.get_pk().into_noah(),
+        );
+        let mut builder = TransferOperationBuilder::new();
+        builder
+            .add_inputs_auto(oars.clone(), 60)
+            .c(d!())?
+            .add_output(&output_template, None, None, None)
+            .c(d!())?;
+        assert_eq!(builder.input_sids.len(), 2);
+        // 10 of change back to alice
+        assert_eq!(builder.output_records[0].open_asset_record.amount, 10);
+        builder
+            .create(TransferType::Standard)
+            .c(d!())?
+            .sign(&alice)
+            .c(d!())?
+            .validate_signatures()
+            .c(d!())?;
+
+        assert!(TransferOperationBuilder::new()
+            .add_inputs_auto(oars.clone(), 100)
+            .is_err());
+
+        // vesting: 20 locked until height 100, the change of 5 unlocked
+        let mut builder = TransferOperationBuilder::new();
+        builder
+            .add_inputs_auto(oars, 20)
+            .c(d!())?
+            .add_output_with_lock(
+                &AssetRecordTemplate::with_no_asset_tracing(
+                    20,
+                    code.val,
+                    NonConfidentialAmount_NonConfidentialAssetType,
+                    bob.get_pk().into_noah(),
+                ),
+                100,
+            )
+            .c(d!())?
+            .create(TransferType::Standard)
+            .c(d!())?;
+        let outputs = &builder.transfer.as_ref().c(d!())?.body.outputs;
+        assert_eq!(outputs[0].lock, None);
+        assert_eq!(outputs[1].lock, Some(OutputLock::Height(100)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_swap_builder() {
+        pnk!(test_swap_builder_inner());
+    }
+
+    fn test_swap_builder_inner() -> Result<()> {
+        let mut prng = ChaChaRng::from_entropy();
+        let pc_gens = PedersenCommitmentRistretto::default();
+        let alice = XfrKeyPair::generate(&mut prng);
+        let bob = XfrKeyPair::generate(&mut prng);
+        let (code_x, code_y) =
+            (AssetTypeCode::gen_random(), AssetTypeCode::gen_random());
+
+        let mut open = |sid: u64, amount: u64, code: &AssetTypeCode, kp: &XfrKeyPair| {
+            let template = AssetRecordTemplate::with_no_asset_tracing(
+                amount,
+                code.val,
+                NonConfidentialAmount_NonConfidentialAssetType,
+                kp.get_pk().into_noah(),
+            );
+            let (ba, _, memo) =
+                build_blind_asset_record(&mut prng, &pc_gens, &template, vec![]);
+            open_blind_asset_record(&ba, &memo, &kp.into_noah())
+                .c(d!())
+                .map(|oar| (TxoRef::Absolute(TxoSID(sid)), oar))
+        };
+        let alice_x = vec![open(1, 100, &code_x, &alice)?];
+        let bob_y = vec![open(2, 15, &code_y, &bob)?, open(3, 5, &code_y, &bob)?];
+
+        // both legs of the same party
+        assert!(SwapBuilder::new()
+            .add_leg(alice_x.clone(), 60, &bob.get_pk())
+            .c(d!())?
+            .add_leg(alice_x.clone(), 10, &bob.get_pk())
+            .c(d!())?
+            .create()
+            .is_err());
+
+        let mut swap = SwapBuilder::new();
+        swap.add_leg(alice_x, 60, &bob.get_pk())
+            .c(d!())?
+            .add_leg(bob_y, 20, &alice.get_pk())
+            .c(d!())?
+            .create()
+            .c(d!())?;
+
+        // bob signs on his side
+        let sig = swap.create_input_signature(&bob).c(d!())?;
+        assert!(swap.clone().into_transaction_builder(1).is_err());
+        swap.sign(&alice).c(d!())?.attach_signature(sig).c(d!())?;
+
+        let mut builder = swap.into_transaction_builder(1).c(d!())?;
+        assert_eq!(builder.missing_signers().len(), 2);
+        assert!(builder.transaction().is_err());
+        builder.sign_to_map(&alice).sign_to_map(&bob);
+        let txn = builder.transaction().c(d!())?.clone();
+        txn.check_tx().c(d!())?;
+        let effect = TxnEffect::compute_effect(txn).c(d!())?;
+        // 40 of change to alice, 60 to bob, 20 to alice
+        assert_eq!(effect.txos.len(), 3);
+        assert_eq!(effect.input_txos.len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_detached_signatures() {
+        pnk!(test_detached_signatures_inner());
+    }
+
+    fn test_detached_signatures_inner() -> Result<()> {
+        let mut prng = ChaChaRng::from_entropy();
+        let alice = XfrKeyPair::generate(&mut prng);
+        let bob = XfrKeyPair::generate(&mut prng);
+
+        let mut builder = TransactionBuilder::from_seq_id(1);
+        builder
+            .add_operation_create_asset(&alice, None, AssetRules::default(), "")
+            .c(d!())?
+            .add_operation_create_asset(&bob, None, AssetRules::default(), "")
+            .c(d!())?
+            .build()
+            .c(d!())?;
+        assert_eq!(
+            builder.missing_signers(),
+            vec![alice.get_pk(), bob.get_pk()]
+        );
+        assert!(builder.transaction().is_err());
+
+        let payload = builder.signing_payload();
+        let sig_bob = sign_payload(&bob, &payload).c(d!())?;
+        let sig_alice = sign_payload(&alice, &payload).c(d!())?;
+
+        // a signature is checked against its signer
+        assert!(builder
+            .add_detached_signature(&alice.get_pk(), sig_bob.clone())
+            .is_err());
+
+        builder
+            .add_detached_signature(&bob.get_pk(), sig_bob.clone())
+            .c(d!())?
+            .add_detached_signature(&bob.get_pk(), sig_bob)
+            .c(d!())?;
+        assert_eq!(builder.missing_signers(), vec![alice.get_pk()]);
+        assert!(builder.transaction().is_err());
+
+        builder
+            .add_detached_signature(&alice.get_pk(), sig_alice)
+            .c(d!())?;
+        assert!(builder.missing_signers().is_empty());
+        builder.transaction().c(d!())?.check_tx().c(d!())
+    }
+
+    #[test]
+    fn test_issue_asset_batch() {
+        pnk!(test_issue_asset_batch_inner());
+    }
+
+    fn test_issue_asset_batch_inner() -> Result<()> {
+        let mut prng = ChaChaRng::from_entropy();
+        let code = AssetTypeCode::gen_random();
+        let issuer = XfrKeyPair::generate(&mut prng);
+        let bob = XfrKeyPair::generate(&mut prng);
+        let carol = XfrKeyPair::generate(&mut prng);
+
+        let outputs = [
+            (
+                10,
+                bob.get_pk(),
+                NonConfidentialAmount_NonConfidentialAssetType,
+            ),
+            (
+                20,
+                carol.get_pk(),
+                AssetRecordType::ConfidentialAmount_ConfidentialAssetType,
+            ),
+            (
+                30,
+                bob.get_pk(),
+                NonConfidentialAmount_NonConfidentialAssetType,
+            ),
+        ];
+        let mut builder = TransactionBuilder::from_seq_id(1);
+        builder
+            .add_operation_issue_asset_batch(&issuer, &code, 1, &outputs)
+            .c(d!())?;
+        assert!(builder
+            .add_operation_issue_asset_batch(&issuer, &code, 2, &[])
+            .is_err());
+
+        let txn = builder.get_transaction().clone();
+        let effect = TxnEffect::compute_effect(txn.clone()).c(d!())?;
+        assert_eq!(effect.internally_spent_txos.len(), outputs.len());
+
+        let xfr = match &txn.body.operations[1] {
+            Operation::TransferAsset(x) => x,
+            _ => return Err(eg!("not a transfer")),
+        };
+        for (i, ((amount, recipient, _), (record, memo))) in outputs
+            .iter()
+            .zip(
+                xfr.body
+                    .outputs
+                    .iter()
+                    .zip(xfr.body.transfer.owners_memos.iter()),
+            )
+            .enumerate()
+        {
+            assert_eq!(record.record.public_key, *recipient);
+            let kp = alt!(1 == i, &carol, &bob);
+            let oar = open_blind_asset_record(
+                &record.record.into_noah(),
+                &memo.clone().map(|m| m.into_noah()),
+                &kp.into_noah(),
+            )
+            .c(d!())?;
+            assert_eq!(oar.amount, *amount);
+            assert_eq!(oar.asset_type, code.val);
+            assert_eq!(memo.is_some(), 1 == i);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_seeded_builder() {
+        pnk!(test_seeded_builder_inner());
+    }
+
+    fn test_seeded_builder_inner() -> Result<()> {
+        let mut prng = ChaChaRng::from_entropy();
+        let code = AssetTypeCode::gen_random();
+        let issuer = XfrKeyPair::generate(&mut prng);
+        let bob = XfrKeyPair::generate(&mut prng);
+
+        let build = |seed: [u8; 32], no_blinding: bool| -> Result<Transaction> {
+            let mut builder = TransactionBuilder::from_seq_id_with_seed(1, seed);
+            if no_blinding {
+                builder.no_blinding();
+            }
+            builder
+                .add_basic_issue_asset(
+                    &issuer,
+                    &code,
+                    1,
+                    100,
+                    AssetRecordType::ConfidentialAmount_ConfidentialAssetType,
+                )
+                .c(d!())?
+                .add_operation_issue_asset_batch(
+                    &issuer,
+                    &code,
+                    2,
+                    &[(
+                        10,
+                        bob.get_pk(),
+                        AssetRecordType::ConfidentialAmount_ConfidentialAssetType,
+                    )],
+                )
+                .c(d!())?;
+            Ok(builder.take_transaction())
+        };
+
+        let txn = build([7; 32], false)?;
+        let bytes = serde_json::to_vec(&txn).c(d!())?;
+        assert_eq!(bytes, serde_json::to_vec(&build([7; 32], false)?).c(d!())?);
+        assert_ne!(bytes, serde_json::to_vec(&build([8; 32], false)?).c(d!())?);
+        assert!(txn
+            .get_outputs_ref(true)
+            .iter()
+            .all(|o| !matches!(o.record.amount, XfrAmount::NonConfidential(_))));
+
+        let txn = build([7; 32], true)?;
+        assert!(txn.get_outputs_ref(true).iter().all(|o| matches!(
+            (&o.record.amount, &o.record.asset_type),
+            (
+                XfrAmount::NonConfidential(_),
+                XfrAssetType::NonConfidential(_)
+            )
+        )));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cosignature_rules() {
+        pnk!(test_cosignature_rules_inner());
+    }
+
+    fn test_cosignature_rules_inner() -> Result<()> {
+        let mut prng = ChaChaRng::from_entropy();
+        let pc_gens = PedersenCommitmentRistretto::default();
+        let code = AssetTypeCode::gen_random();
+        let alice = XfrKeyPair::generate(&mut prng);
+        let bob = XfrKeyPair::generate(&mut prng);
+        let cosigners = (0..3)
+            .map(|_| XfrKeyPair::generate(&mut prng))
+            .collect::<Vec<_>>();
+
+        let mut rules = AssetRules::default();
+        rules.set_transfer_multisig_rules(Some(SignatureRules {
+            threshold: 2,
+            weights: cosigners.iter().map(|kp| (kp.get_pk(), 1)).collect(),
+        }));
+
+        let template = AssetRecordTemplate::with_no_asset_tracing(
+            100,
+            code.val,
+            NonConfidentialAmount_NonConfidentialAssetType,
+            alice.get_pk().into_noah(),
+        );
+        let (ba, _, memo) =
+            build_blind_asset_record(&mut prng, &pc_gens, &template, vec![]);
+        let oar = open_blind_asset_record(&ba, &memo, &alice.into_noah()).c(d!())?;
+
+        let mut op = TransferOperationBuilder::new();
+        op.add_asset_rules(code, &rules)
+            .add_input(TxoRef::Absolute(TxoSID(0)), oar, None, None, 100)
+            .c(d!())?
+            .add_output(
+                &AssetRecordTemplate::with_no_asset_tracing(
+                    100,
+                    code.val,
+                    NonConfidentialAmount_NonConfidentialAssetType,
+                    bob.get_pk().into_noah(),
+                ),
+                None,
+                None,
+                None,
+            )
+            .c(d!())?
+            .create(TransferType::Standard)
+            .c(d!())?
+            .sign(&alice)
+            .c(d!())?;
+        assert_eq!(op.missing_cosigners().c(d!())?.len(), 1);
+        assert!(op.create_cosignature(&cosigners[0], 1).is_err());
+
+        let sig = op.create_cosignature(&cosigners[0], 0).c(d!())?;
+        op.attach_signature(sig).c(d!())?;
+        let missing = op.missing_cosigners().c(d!())?;
+        assert_eq!(missing[0].0, 0);
+        assert_eq!(
+            missing[0].1,
+            vec![cosigners[1].get_pk(), cosigners[2].get_pk()]
+        );
+        assert!(op.validate_signatures().is_err());
+
+        let sig = op.create_cosignature(&cosigners[2], 0).c(d!())?;
+        op.attach_signature(sig).c(d!())?;
+        assert!(op.missing_cosigners().c(d!())?.is_empty());
+        op.validate_signatures().c(d!())?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate() {
+        pnk!(test_validate_inner());
+    }
+
+    fn test_validate_inner() -> Result<()> {
+        let mut prng = ChaChaRng::from_entropy();
+        let pc_gens = PedersenCommitmentRistretto::default();
+        let code = AssetTypeCode::gen_random();
+        let issuer = XfrKeyPair::generate(&mut prng);
+        let alice = XfrKeyPair::generate(&mut prng);
+        let bob = XfrKeyPair::generate(&mut prng);
+
+        let template = AssetRecordTemplate::with_no_asset_tracing(
+            100,
+            code.val,
+            NonConfidentialAmount_NonConfidentialAssetType,
+            alice.get_pk().into_noah(),
+        );
+        let (ba, _, memo) =
+            build_blind_asset_record(&mut prng, &pc_gens, &template, vec![]);
+        let oar = open_blind_asset_record(&ba, &memo, &alice.into_noah()).c(d!())?;
+        let op = TransferOperationBuilder::new()
+            .add_input(TxoRef::Absolute(TxoSID(0)), oar, None, None, 100)
+            .c(d!())?
+            .add_output(
+                &AssetRecordTemplate::with_no_asset_tracing(
+                    100,
+                    code.val,
+                    NonConfidentialAmount_NonConfidentialAssetType,
+                    bob.get_pk().into_noah(),
+                ),
+                None,
+                None,
+                None,
+            )
+            .c(d!())?
+            .create(TransferType::Standard)
+            .c(d!())?
+            .transaction()
+            .c(d!())?;
+        let mut xfr = match op {
+            Operation::TransferAsset(x) => x,
+            _ => return Err(eg!("not a transfer")),
+        };
+        xfr.body.transfer.outputs[0].amount = XfrAmount::NonConfidential(90);
+
+        let mut builder = TransactionBuilder::from_seq_id(1);
+        builder
+            .add_operation(Operation::TransferAsset(xfr))
+            .add_basic_issue_asset(
+                &issuer,
+                &code,
+                2,
+                10,
+                NonConfidentialAmount_NonConfidentialAssetType,
+            )
+            .c(d!())?
+            .add_basic_issue_asset(
+                &issuer,
+                &code,
+                2,
+                10,
+                NonConfidentialAmount_NonConfidentialAssetType,
+            )
+            .c(d!())?
+            .add_operation_update_memo(&issuer, code, &"m".repeat(MAX_MEMO_LEN + 1));
+
+        let violations = builder.validate();
+        let expected = [
+            (
+                Some(0),
+                ViolationKind::UnsignedInput {
+                    input_idx: 0,
+                    owner: alice.get_pk(),
+                },
+            ),
+            (
+                Some(0),
+                ViolationKind::Unbalanced {
+                    asset_type: code,
+                    inputs: 100,
+                    outputs: 90,
+                },
+            ),
+            (
+                Some(2),
+                ViolationKind::SequenceNumber {
+                    code,
+                    seq_num: 2,
+                    previous: 2,
+                },
+            ),
+            (
+                Some(3),
+                ViolationKind::MemoTooLong {
+                    len: MAX_MEMO_LEN + 1,
+                },
+            ),
+            (
+                None,
+                ViolationKind::MissingSigner {
+                    signer: alice.get_pk(),
+                },
+            ),
+            (
+                None,
+                ViolationKind::MissingSigner {
+                    signer: issuer.get_pk(),
+                },
+            ),
+        ];
+        assert_eq!(
+            violations,
+            expected
+                .into_iter()
+                .map(|(op_idx, kind)| Violation { op_idx, kind })
+                .collect::<Vec<_>>()
+        );
+
+        builder.sign_to_map(&alice).sign_to_map(&issuer);
+        assert_eq!(builder.validate().len(), 4);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_portable_bytes() {
+        pnk!(test_portable_bytes_inner());
+    }
+
+    fn test_portable_bytes_inner() -> Result<()> {
+        let issuer = XfrKeyPair::generate(&mut ChaChaRng::from_entropy());
+        let mut builder = TransactionBuilder::from_seq_id(1);
+        builder
+            .add_basic_issue_asset(
+                &issuer,
+                &AssetTypeCode::gen_random(),
+                1,
+                10,
+                NonConfidentialAmount_NonConfidentialAssetType,
+            )
+            .c(d!())?
+            .sign(&issuer);
+        let raw = serde_json::to_vec(&builder).c(d!())?;
+
+        let bytes = builder.to_portable_bytes().c(d!())?;
+        let envelope = serde_json::from_slice::<serde_json::Value>(&bytes).c(d!())?;
+        assert_eq!(envelope["version"], PORTABLE_BUILDER_VERSION);
+        let loaded = TransactionBuilder::from_portable_bytes(&bytes).c(d!())?;
+        assert_eq!(serde_json::to_vec(&loaded).c(d!())?, raw);
+
+        // saved before the envelope
+        let loaded = TransactionBuilder::from_portable_bytes(&raw).c(d!())?;
+        assert_eq!(serde_json::to_vec(&loaded).c(d!())?, raw);
+
+        let newer = serde_json::to_vec(&PortableBuilder {
+            version: PORTABLE_BUILDER_VERSION + 1,
+            payload: serde_json::from_slice(&raw).c(d!())?,
+        })
+        .c(d!())?;
+        assert!(TransactionBuilder::from_portable_bytes(&newer).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_return_to_sender() {
+        pnk!(test_return_to_sender_inner());
+    }
+
+    fn test_return_to_sender_inner() -> Result<()> {
+        let mut prng = ChaChaRng::from_entropy();
+        let pc_gens = PedersenCommitmentRistretto::default();
+        let code = AssetTypeCode::gen_random();
+        let alice = XfrKeyPair::generate(&mut prng);
+        let bob = XfrKeyPair::generate(&mut prng);
+
+        let template = AssetRecordTemplate::with_no_asset_tracing(
+            100,
+            code.val,
+            AssetRecordType::ConfidentialAmount_NonConfidentialAssetType,
+            alice.get_pk().into_noah(),
+        );
+        let (ba, _, memo) =
+            build_blind_asset_record(&mut prng, &pc_gens, &template, vec![]);
+        let oar = open_blind_asset_record(&ba, &memo, &alice.into_noah()).c(d!())?;
+        let to_bob = |amount| {
+            AssetRecordTemplate::with_no_asset_tracing(
+                amount,
+                code.val,
+                AssetRecordType::ConfidentialAmount_NonConfidentialAssetType,
+                bob.get_pk().into_noah(),
+            )
+        };
+        let op = TransferOperationBuilder::new()
+            .add_input(TxoRef::Absolute(TxoSID(0)), oar, None, None, 100)
+            .c(d!())?
+            .add_output(&to_bob(60), None, None, None)
+            .c(d!())?
+            .add_output(&to_bob(40), None, None, None)
+            .c(d!())?
+            .create(TransferType::Standard)
+            .c(d!())?
+            .sign(&alice)
+            .c(d!())?
+            .transaction()
+            .c(d!())?;
+        let xfr = match op {
+            Operation::TransferAsset(x) => x,
+            _ => return Err(eg!("not a transfer")),
+        };
+
+        let received = xfr
+            .body
+            .transfer
+            .outputs
+            .iter()
+            .zip(xfr.body.transfer.owners_memos.iter())
+            .enumerate()
+            .map(|(i, (record, memo))| {
+                open_blind_asset_record(
+                    &record.into_noah(),
+                    &memo.clone().map(|m| m.into_noah()),
+                    &bob.into_noah(),
+                )
+                .c(d!())
+                .map(|oar| (TxoRef::Absolute(TxoSID(1 + i as u64)), oar))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut builder = TransactionBuilder::from_seq_id(1);
+        assert!(builder
+            .add_operation_return_to_sender(&alice, &xfr, received.clone())
+            .is_err());
+        builder
+            .add_operation_return_to_sender(&bob, &xfr, received)
+            .c(d!())?;
+
+        let back = match &builder.get_transaction().body.operations[0] {
+            Operation::TransferAsset(x) => x,
+            _ => return Err(eg!("not a transfer")),
+        };
+        let mut amounts = back
+            .body
+            .transfer
+            .outputs
+            .iter()
+            .zip(back.body.transfer.owners_memos.iter())
+            .map(|(record, memo)| {
+                assert_eq!(record.public_key, alice.get_pk());
+                open_blind_asset_record(
+                    &record.into_noah(),
+                    &memo.clone().map(|m| m.into_noah()),
+                    &alice.into_noah(),
+                )
+                .c(d!())
+                .map(|oar| oar.amount)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        amounts.sort_unstable();
+        assert_eq!(amounts, vec![40, 60]);
+        assert!(builder.sign_to_map(&bob).validate().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_convert_confidentiality() {
+        pnk!(test_convert_confidentiality_inner());
+    }
+
+    fn test_convert_confidentiality_inner() -> Result<()> {
+        let mut prng = ChaChaRng::from_entropy();
+        let pc_gens = PedersenCommitmentRistretto::default();
+        let code = AssetTypeCode::gen_random();
+        let alice = XfrKeyPair::generate(&mut prng);
+
+        let mut records = |amounts: &[u64], record_type| {
+            amounts
+                .iter()
+                .enumerate()
+                .map(|(i, amount)| {
+                    let template = AssetRecordTemplate::with_no_asset_tracing(
+                        *amount,
+                        code.val,
+                        record_type,
+                        alice.get_pk().into_noah(),
+                    );
+                    let (ba, _, memo) =
+                        build_blind_asset_record(&mut prng, &pc_gens, &template, vec![]);
+                    open_blind_asset_record(&ba, &memo, &alice.into_noah())
+                        .c(d!())
+                        .map(|oar| (TxoRef::Absolute(TxoSID(i as u64)), oar))
+                })
+                .collect::<Result<Vec<_>>>()
+        };
+        let output = |builder: &TransactionBuilder| match &builder
+            .get_transaction()
+            .body
+            .operations[0]
+        {
+            Operation::TransferAsset(x) => {
+                assert_eq!(x.body.transfer.outputs.len(), 1);
+                open_blind_asset_record(
+                    &x.body.transfer.outputs[0].into_noah(),
+                    &x.body.transfer.owners_memos[0]
+                        .clone()
+                        .map(|m| m.into_noah()),
+                    &alice.into_noah(),
+                )
+                .c(d!())
+            }
+            _ => Err(eg!("not a transfer")),
+        };
+
+        // reveal
+        let confidential = records(
+            &[30, 70],
+            AssetRecordType::ConfidentialAmount_ConfidentialAssetType,
+        )?;
+        let mut builder = TransactionBuilder::from_seq_id(1);
+        builder
+            .add_operation_convert_confidentiality(
+                &alice,
+                confidential,
+                NonConfidentialAmount_NonConfidentialAssetType,
+            )
+            .c(d!())?;
+        let oar = output(&builder)?;
+        assert_eq!(oar.amount, 100);
+        assert_eq!(
+            oar.get_record_type(),
+            NonConfidentialAmount_NonConfidentialAssetType
+        );
+
+        // hide
+        let public = records(&[50], NonConfidentialAmount_NonConfidentialAssetType)?;
+        let mut builder = TransactionBuilder::from_seq_id(2);
+        assert!(builder
+            .add_operation_convert_confidentiality(
+                &alice,
+                public.clone(),
+                NonConfidentialAmount_NonConfidentialAssetType,
+            )
+            .is_err());
+        builder
+            .add_operation_convert_confidentiality(
+                &alice,
+                public,
+                AssetRecordType::ConfidentialAmount_NonConfidentialAssetType,
+            )
+            .c(d!())?;
+        let oar = output(&builder)?;
+        assert_eq!(oar.amount, 50);
+        assert_eq!(
+            oar.get_record_type(),
+            AssetRecordType::ConfidentialAmount_NonConfidentialAssetType
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_threshold_address() {
+        pnk!(test_threshold_address_inner());
+    }
+
+    fn test_threshold_address_inner() -> Result<()> {
+        let mut prng = ChaChaRng::from_entropy();
+        let pc_gens = PedersenCommitmentRistretto::default();
+        let code = AssetTypeCode::gen_random();
+        let alice = XfrKeyPair::generate(&mut prng);
+        let signers = (0..3)
+            .map(|_| XfrKeyPair::generate(&mut prng))
+            .collect::<Vec<_>>();
+        let keys = signers.iter().map(|kp| kp.get_pk()).collect::<Vec<_>>();
+
+        let owner = &signers[0];
+        assert!(ThresholdAddress::new(owner.get_pk(), 0, &keys).is_err());
+        assert!(ThresholdAddress::new(owner.get_pk(), 4, &keys).is_err());
+        let address = ThresholdAddress::new(owner.get_pk(), 2, &keys).c(d!())?;
+        let reversed = keys.iter().rev().copied().collect::<Vec<_>>();
+        assert_eq!(
+            address,
+            ThresholdAddress::new(owner.get_pk(), 2, &reversed).c(d!())?
+        );
+
+        // to the escrow
+        let template = |amount, pk: &XfrPublicKey| {
+            AssetRecordTemplate::with_no_asset_tracing(
+                amount,
+                code.val,
+                NonConfidentialAmount_NonConfidentialAssetType,
+                pk.into_noah(),
+            )
+        };
+        let (ba, _, memo) = build_blind_asset_record(
+            &mut prng,
+            &pc_gens,
+            &template(100, &alice.get_pk()),
+            vec![],
+        );
+        let oar = open_blind_asset_record(&ba, &memo, &alice.into_noah()).c(d!())?;
+        let mut op = TransferOperationBuilder::new();
+        op.add_input(TxoRef::Absolute(TxoSID(0)), oar, None, None, 100)
+            .c(d!())?
+            .add_output_to_threshold(&template(100, &alice.get_pk()), &address)
+            .c(d!())?
+            .create(TransferType::Standard)
+            .c(d!())?;
+        let xfr = match op.transaction().c(d!())? {
+            Operation::TransferAsset(x) => x,
+            _ => return Err(eg!("not a transfer")),
+        };
+        let escrowed = &xfr.body.outputs[0];
+        assert_eq!(escrowed.record.public_key, owner.get_pk());
+        assert_eq!(
+            escrowed.lock,
+            Some(OutputLock::Threshold(address.rules.clone()))
+        );
+
+        // out of the escrow
+        let oar = open_blind_asset_record(
+            &escrowed.record.into_noah(),
+            &None,
+            &owner.into_noah(),
+        )
+        .c(d!())?;
+        let mut op = TransferOperationBuilder::new();
+        op.add_threshold_input(TxoRef::Absolute(TxoSID(1)), oar, &address, 100)
+            .c(d!())?
+            .add_output(&template(100, &alice.get_pk()), None, None, None)
+            .c(d!())?
+            .create(TransferType::Standard)
+            .c(d!())?
+            .sign(owner)
+            .c(d!())?;
+        let sig = op.create_cosignature(&signers[1], 0).c(d!())?;
+        op.attach_signature(sig).c(d!())?;
+        assert!(op.validate_signatures().is_err());
+        let sig = op.create_cosignature(&signers[2], 0).c(d!())?;
+        op.attach_signature(sig).c(d!())?;
+        op.validate_signatures().c(d!())?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_fee_auto() {
+        pnk!(test_add_fee_auto_inner());
+    }
+
+    fn test_add_fee_auto_inner() -> Result<()> {
+        let mut prng = ChaChaRng::from_entropy();
+        let pc_gens = PedersenCommitmentRistretto::default();
+        let alice = XfrKeyPair::generate(&mut prng);
+
+        let mut fee_inputs = |amounts: &[u64]| {
+            let mut fi = FeeInputs::new();
+            for (i, amount) in amounts.iter().enumerate() {
+                let template = AssetRecordTemplate::with_no_asset_tracing(
+                    *amount,
+                    ASSET_TYPE_FRA,
+                    NonConfidentialAmount_NonConfidentialAssetType,
+                    alice.get_pk().into_noah(),
+                );
+                let (ba, _, _) =
+                    build_blind_asset_record(&mut prng, &pc_gens, &template, vec![]);
+                fi.append(
+                    *amount,
+                    TxoRef::Absolute(TxoSID(i as u64)),
+                    TxOutput {
+                        id: None,
+                        record: BlindAssetRecord::from_noah(&ba),
+                        lien: None,
+                        lock: None,
+                    },
+                    None,
+                    alice.get_sk().into_keypair(),
+                );
+            }
+            fi
+        };
+        let fee_op = |builder: &TransactionBuilder, idx: usize| match &builder
+            .get_transaction()
+            .body
+            .operations[idx]
+        {
+            Operation::TransferAsset(x) => {
+                Ok((x.body.inputs.len(), x.body.outputs.len()))
+            }
+            _ => Err(eg!("not a transfer")),
+        };
+
+        let schedule = FeeSchedule::default();
+        let mut builder = TransactionBuilder::from_seq_id(1);
+        assert_eq!(schedule.fee_due(builder.get_transaction()), TX_FEE_MIN);
+
+        // the exact input is picked, no change
+        builder
+            .add_fee_auto(
+                &schedule,
+                fee_inputs(&[3 * TX_FEE_MIN, TX_FEE_MIN, TX_FEE_MIN / 2]),
+            )
+            .c(d!())?;
+        assert!(builder.check_fee());
+        assert_eq!(fee_op(&builder, 0)?, (1, 1));
+        assert_eq!(schedule.fee_due(builder.get_transaction()), 0);
+
+        // already paid
+        builder
+            .add_fee_auto(&schedule, fee_inputs(&[TX_FEE_MIN]))
+            .c(d!())?;
+        assert_eq!(builder.get_transaction().body.operations.len(), 1);
+
+        // a higher fee is not covered by the smaller fee output
+        let schedule = FeeSchedule {
+            base: 2 * TX_FEE_MIN,
+            ..Default::default()
+        };
+        assert_eq!(schedule.fee_due(builder.get_transaction()), 2 * TX_FEE_MIN);
+        builder
+            .add_fee_auto(&schedule, fee_inputs(&[TX_FEE_MIN / 2, 3 * TX_FEE_MIN]))
+            .c(d!())?;
+        assert_eq!(fee_op(&builder, 1)?, (1, 2));
+        assert_eq!(schedule.fee_due(builder.get_transaction()), 0);
+
+        let mut builder = TransactionBuilder::from_seq_id(2);
+        assert!(builder
+            .add_fee_auto(&schedule, fee_inputs(&[TX_FEE_MIN, TX_FEE_MIN / 2]))
+            .is_err());
+
+        Ok(())
+    }
+
     #[test]
     fn test_check_fee_with_ledger() {
         let mut ledger = LedgerState::tmp_ledger();
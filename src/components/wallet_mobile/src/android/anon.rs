@@ -0,0 +1,251 @@
+//!
+//! Anonymous transfers, see `crate::rust::anon`.
+//!
+//! An ABAR is given as the JSON of an `AnonInput`:
+//! `{"commitment": <base58>, "owner_memo": <AxfrOwnerMemo>, "mt_leaf_info": <MTLeafInfo>}`,
+//! and a receiver as a bech32 address or a base64 public key.
+//!
+
+use super::exception::{CatchPanicImpl, ThrowExceptionImpl};
+use super::{handle, input};
+use crate::rust::anon::{self, AnonInput, AnonTransferOperationBuilder};
+use jni::objects::{JClass, JString};
+use jni::sys::{jint, jlong, jstring};
+use jni::JNIEnv;
+use ruc::*;
+
+fn parse_input(env: JNIEnv, input: JString) -> Result<AnonInput> {
+    input::json(env, input, "ABAR")
+}
+
+#[no_mangle]
+/// # Safety
+///
+/// Opens the ABAR `input` with `keypair`, returns the JSON `OpenedAbar`.
+pub unsafe extern "system" fn Java_com_findora_JniApi_openAbar(
+    env: JNIEnv,
+    _: JClass,
+    input: JString,
+    keypair: jlong,
+) -> jstring {
+    let opened = jni_try!(env, {
+        let input = parse_input(env, input)?;
+        let keypair = handle::keypair(keypair)?;
+        let opened = anon::open_abar(&input, &keypair)?;
+        serde_json::to_string(&opened).c(d!())
+    });
+    let output = throw_exception!(env, env.new_string(opened));
+    **output
+}
+
+#[no_mangle]
+/// # Safety
+///
+/// The base58 nullifier of the ABAR `input` of `keypair`, which needs its Merkle proof.
+pub unsafe extern "system" fn Java_com_findora_JniApi_genNullifierHash(
+    env: JNIEnv,
+    _: JClass,
+    input: JString,
+    keypair: jlong,
+) -> jstring {
+    let hash = jni_try!(env, {
+        let input = parse_input(env, input)?;
+        let keypair = handle::keypair(keypair)?;
+        anon::gen_nullifier_hash(&input, &keypair)
+    });
+    let output = throw_exception!(env, env.new_string(hash));
+    **output
+}
+
+#[no_mangle]
+/// Fee smaller than this value will be denied, for a transaction converting records to ABARs.
+pub extern "system" fn Java_com_findora_JniApi_fraGetMinimalFeeForBarToAbar(
+    _env: JNIEnv,
+    _: JClass,
+) -> jlong {
+    anon::fra_get_minimal_fee_for_bar_to_abar() as jlong
+}
+
+#[no_mangle]
+/// Anon fee for a given number of inputs & outputs
+pub extern "system" fn Java_com_findora_JniApi_getAnonFee(
+    _env: JNIEnv,
+    _: JClass,
+    n_inputs: jint,
+    n_outputs: jint,
+) -> jint {
+    anon::get_anon_fee(n_inputs as u32, n_outputs as u32) as jint
+}
+
+#[no_mangle]
+/// # Safety
+///
+/// Create a new anonymous transfer builder.
+pub unsafe extern "system" fn Java_com_findora_JniApi_anonTransferOperationBuilderNew(
+    _env: JNIEnv,
+    _: JClass,
+    seq_id: jlong,
+) -> jlong {
+    handle::insert(AnonTransferOperationBuilder::new(seq_id as u64))
+}
+
+#[no_mangle]
+/// # Safety
+///
+/// Spend the ABAR `input` of `keypair`, which needs its Merkle proof.
+pub unsafe extern "system" fn Java_com_findora_JniApi_anonTransferOperationBuilderAddInput(
+    env: JNIEnv,
+    _: JClass,
+    builder: jlong,
+    input: JString,
+    keypair: jlong,
+) -> jlong {
+    let builder = jni_try!(env, {
+        let builder = handle::get::<AnonTransferOperationBuilder>(builder)?;
+        let input = parse_input(env, input)?;
+        let keypair = handle::keypair(keypair)?;
+        builder.add_input(&input, &keypair)
+    });
+    handle::insert(builder)
+}
+
+#[no_mangle]
+/// # Safety
+///
+/// Send `amount` of the base64 `asset_type` to `to`, a bech32 address or a base64 public key.
+pub unsafe extern "system" fn Java_com_findora_JniApi_anonTransferOperationBuilderAddOutput(
+    env: JNIEnv,
+    _: JClass,
+    builder: jlong,
+    amount: JString,
+    asset_type: JString,
+    to: JString,
+) -> jlong {
+    let builder = jni_try!(env, {
+        let builder = handle::get::<AnonTransferOperationBuilder>(builder)?;
+        let amount = input::amount(env, amount, "amount")?;
+        let asset_type = input::asset_code(env, asset_type, "asset type")?;
+        let to = input::public_key(env, to, "receiver")?;
+        builder.add_output(amount, &asset_type.to_base64(), &to)
+    });
+    handle::insert(builder)
+}
+
+#[no_mangle]
+/// # Safety
+///
+/// The key of the inputs, which generates the nullifiers and receives the remainders.
+pub unsafe extern "system" fn Java_com_findora_JniApi_anonTransferOperationBuilderAddKeypair(
+    env: JNIEnv,
+    _: JClass,
+    builder: jlong,
+    keypair: jlong,
+) -> jlong {
+    let builder = jni_try!(env, {
+        let builder = handle::get::<AnonTransferOperationBuilder>(builder)?;
+        let keypair = handle::keypair(keypair)?;
+        Ok(builder.add_keypair(&keypair))
+    });
+    handle::insert(builder)
+}
+
+#[no_mangle]
+/// # Safety
+///
+/// The FRA missing from the inputs to pay the fee of the transfer, as a decimal string.
+pub unsafe extern "system" fn Java_com_findora_JniApi_anonTransferOperationBuilderGetExpectedFee(
+    env: JNIEnv,
+    _: JClass,
+    builder: jlong,
+) -> jstring {
+    let fee = jni_try!(env, {
+        let builder = handle::get::<AnonTransferOperationBuilder>(builder)?;
+        builder.get_expected_fee()
+    });
+    let output = throw_exception!(env, env.new_string(fee.to_string()));
+    **output
+}
+
+#[no_mangle]
+/// # Safety
+///
+/// The fee of the transfer with its remainders, as a decimal string.
+pub unsafe extern "system" fn Java_com_findora_JniApi_anonTransferOperationBuilderGetTotalFeeEstimate(
+    env: JNIEnv,
+    _: JClass,
+    builder: jlong,
+) -> jstring {
+    let fee = jni_try!(env, {
+        let builder = handle::get::<AnonTransferOperationBuilder>(builder)?;
+        builder.get_total_fee_estimate()
+    });
+    let output = throw_exception!(env, env.new_string(fee.to_string()));
+    **output
+}
+
+#[no_mangle]
+/// # Safety
+///
+/// Generates the proof of the transfer, returns the built builder.
+/// This takes a while, it should not be called from the main thread.
+pub unsafe extern "system" fn Java_com_findora_JniApi_anonTransferOperationBuilderBuild(
+    env: JNIEnv,
+    _: JClass,
+    builder: jlong,
+) -> jlong {
+    let builder = jni_try!(env, {
+        handle::get::<AnonTransferOperationBuilder>(builder)?.build()
+    });
+    handle::insert(builder)
+}
+
+#[no_mangle]
+/// # Safety
+///
+/// The base58 commitments of the outputs, remainders included, as a JSON array.
+pub unsafe extern "system" fn Java_com_findora_JniApi_anonTransferOperationBuilderGetCommitments(
+    env: JNIEnv,
+    _: JClass,
+    builder: jlong,
+) -> jstring {
+    let commitments = jni_try!(env, {
+        let commitments =
+            handle::with(builder, |builder: &AnonTransferOperationBuilder| {
+                builder.get_commitments()
+            })?;
+        serde_json::to_string(&commitments).c(d!())
+    });
+    let output = throw_exception!(env, env.new_string(commitments));
+    **output
+}
+
+#[no_mangle]
+/// # Safety
+///
+/// The serialized transaction of the transfer, once built.
+pub unsafe extern "system" fn Java_com_findora_JniApi_anonTransferOperationBuilderTransaction(
+    env: JNIEnv,
+    _: JClass,
+    builder: jlong,
+) -> jstring {
+    let tx = jni_try!(env, {
+        handle::with(builder, |builder: &AnonTransferOperationBuilder| {
+            builder.transaction()
+        })?
+    });
+    let output = throw_exception!(env, env.new_string(tx));
+    **output
+}
+
+#[no_mangle]
+/// # Safety
+///
+/// Releases an anonymous transfer builder, `builder` is invalid after this call.
+pub unsafe extern "system" fn Java_com_findora_JniApi_anonTransferOperationBuilderFree(
+    env: JNIEnv,
+    _: JClass,
+    builder: jlong,
+) {
+    let _ =
+        ThrowExceptionImpl(env, handle::remove::<AnonTransferOperationBuilder>(builder));
+}
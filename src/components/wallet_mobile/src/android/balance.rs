@@ -0,0 +1,67 @@
+//!
+//! Opening of the records of an account in batch, see `crate::rust::balance`.
+//!
+//! The progress is reported to a `com.findora.Progress`, on the calling thread:
+//!
+//! ```java
+//! public interface Progress {
+//!     // false cancels the batch
+//!     boolean onProgress(int done, int total);
+//! }
+//! ```
+//!
+
+use super::exception::{CatchPanicImpl, ThrowExceptionImpl};
+use super::{handle, input};
+use crate::rust::balance::{open_records, RecordToOpen, DEFAULT_CHUNK_SIZE};
+use jni::objects::{JClass, JObject, JString, JValue};
+use jni::sys::{jint, jlong, jstring};
+use jni::JNIEnv;
+use ruc::*;
+
+#[no_mangle]
+/// # Safety
+///
+/// Open the records of `keypair`, a JSON array of the `OwnedUtxo`s of the query server
+/// or of `{"record": ..., "owner_memo": ...}`, and sum their balances.
+/// Returns the JSON `OpenedRecords`.
+///
+/// `chunk_size` records are opened between two calls to `progress`,
+/// `DEFAULT_CHUNK_SIZE` if not positive; `progress` may be null.
+/// This may take a while, it should not be called from the main thread.
+pub unsafe extern "system" fn Java_com_findora_JniApi_openRecords(
+    env: JNIEnv,
+    _: JClass,
+    keypair: jlong,
+    records: JString,
+    chunk_size: jint,
+    progress: JObject,
+) -> jstring {
+    let opened = jni_try!(env, {
+        let keypair = handle::keypair(keypair)?;
+        let records = input::json::<Vec<RecordToOpen>>(env, records, "records")?;
+        let chunk_size = if chunk_size > 0 {
+            chunk_size as usize
+        } else {
+            DEFAULT_CHUNK_SIZE
+        };
+        let mut report = |done: usize, total: usize| {
+            if progress.is_null() {
+                return true;
+            }
+            env.call_method(
+                progress,
+                "onProgress",
+                "(II)Z",
+                &[JValue::Int(done as jint), JValue::Int(total as jint)],
+            )
+            .and_then(|res| res.z())
+            // an exception of the callback cancels the batch
+            .unwrap_or(false)
+        };
+        let opened = open_records(&keypair, &records, chunk_size, &mut report)?;
+        serde_json::to_string(&opened).c(d!())
+    });
+    let output = throw_exception!(env, env.new_string(opened));
+    **output
+}
@@ -1,10 +1,16 @@
-use crate::rust::types;
+use super::exception::ThrowExceptionImpl;
+use super::handle;
+use crate::rust::session::Secret;
 use jni::objects::JClass;
 use jni::sys::{jbyteArray, jlong};
 use jni::JNIEnv;
 use rand_chacha::ChaChaRng;
 use rand_core::SeedableRng;
-use zei::noah_api::{keys::KeyPair as RawXfrKeyPair, xfr::structs::ASSET_TYPE_LENGTH};
+use ruc::{d, RucResult};
+use zei::{
+    noah_api::{keys::KeyPair as RawXfrKeyPair, xfr::structs::ASSET_TYPE_LENGTH},
+    XfrKeyPair,
+};
 
 #[no_mangle]
 pub unsafe extern "system" fn Java_com_findora_JniApi_xfrKeyPairNew(
@@ -16,15 +22,16 @@ pub unsafe extern "system" fn Java_com_findora_JniApi_xfrKeyPairNew(
     let mut buf = [0u8; ASSET_TYPE_LENGTH];
     buf.copy_from_slice(input.as_ref());
     let mut prng = ChaChaRng::from_seed(buf);
-    let val = types::XfrKeyPair::from(RawXfrKeyPair::generate_ed25519(&mut prng));
-    Box::into_raw(Box::new(val)) as jlong
+    let raw = RawXfrKeyPair::generate_ed25519(&mut prng);
+    let keypair = throw_exception!(env, XfrKeyPair::from_noah(&raw).c(d!()));
+    handle::insert_keypair(&keypair)
 }
 
 #[no_mangle]
 pub unsafe extern "system" fn Java_com_findora_JniApi_xfrKeyPairDestroy(
-    _env: JNIEnv,
+    env: JNIEnv,
     _class: JClass,
     xfr_keypair_ptr: jlong,
 ) {
-    let _boxed_key = Box::from_raw(xfr_keypair_ptr as *mut types::XfrKeyPair);
+    let _ = ThrowExceptionImpl(env, handle::remove::<Secret>(xfr_keypair_ptr));
 }
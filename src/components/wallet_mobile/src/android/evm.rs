@@ -1,10 +1,36 @@
-use crate::rust::account::{get_serialized_address, EVMTransactionBuilder};
+use crate::rust::account::{
+    evm_address_from_secret, get_serialized_address, restore_evm_secret_from_mnemonic,
+    EVMFee, EVMTransactionBuilder,
+};
 use jni::objects::{JClass, JString};
 use jni::sys::{jlong, jstring};
 use jni::JNIEnv;
+use ruc::{d, eg, RucResult};
 use zei::{noah_api::keys::PublicKey, XfrPublicKey};
 
-use super::{jStringToString, parseU64};
+use super::exception::{CatchPanicImpl, ThrowExceptionImpl};
+use super::{handle, input};
+
+/// Read the arguments shared by the EVM transfer constructors.
+unsafe fn transfer_args(
+    env: JNIEnv,
+    recipient: jlong,
+    amount: JString,
+    sk: JString,
+    nonce: JString,
+) -> ruc::Result<(XfrPublicKey, u64, String, fp_types::U256)> {
+    let nonce = input::nonce(env, nonce)?;
+    let amount = input::amount(env, amount, "amount")?;
+    let sk = input::string(env, sk, "secret key")?;
+
+    if 0 == recipient {
+        return Err(eg!("null recipient"));
+    }
+    let recipient = *(recipient as *mut PublicKey);
+    let recipient = XfrPublicKey::from_noah(&recipient).c(d!())?;
+
+    Ok((recipient, amount, sk, nonce))
+}
 
 #[no_mangle]
 /// # Safety
@@ -13,6 +39,7 @@ use super::{jStringToString, parseU64};
 /// @param {u64} amount - Transfer amount.
 /// @param {string} sk - Ethereum wallet private key.
 /// @param {U256} nonce - Transaction nonce for sender.
+/// @throws Will throw a `FindoraException` if an argument is invalid.
 pub unsafe extern "system" fn Java_com_findora_JniApi_transferToUtxoFromAccount(
     env: JNIEnv,
     _: JClass,
@@ -21,35 +48,280 @@ pub unsafe extern "system" fn Java_com_findora_JniApi_transferToUtxoFromAccount(
     sk: JString,
     nonce: JString,
 ) -> jstring {
-    let nonce = serde_json::from_str(&jStringToString(env, nonce)).unwrap();
+    let ser_tx = jni_try!(env, {
+        let (recipient, amount, sk, nonce) =
+            transfer_args(env, recipient, amount, sk, nonce)?;
+        EVMTransactionBuilder::new_transfer_to_utxo_from_account(
+            recipient, amount, sk, nonce,
+        )
+    });
 
-    let amount = parseU64(env, amount);
+    let output = throw_exception!(env, env.new_string(ser_tx));
+    **output
+}
 
-    let sk = jStringToString(env, sk);
+#[no_mangle]
+/// # Safety
+/// Construct an EVM Transaction builder that transfer account balance to UTXO.
+/// @param {XfrPublicKey} recipient - UTXO Asset receiver.
+/// @param {u64} amount - Transfer amount.
+/// @param {string} sk - Ethereum wallet private key.
+/// @param {U256} nonce - Transaction nonce for sender.
+/// @throws Will throw a `FindoraException` if an argument is invalid.
+///
+/// The returned builder must be released with `evmTransactionBuilderFree`.
+pub unsafe extern "system" fn Java_com_findora_JniApi_transferFromAccountEvmTransactionBuilder(
+    env: JNIEnv,
+    _: JClass,
+    recipient: jlong,
+    amount: JString,
+    sk: JString,
+    nonce: JString,
+) -> jlong {
+    let builder = jni_try!(env, {
+        let (recipient, amount, sk, nonce) =
+            transfer_args(env, recipient, amount, sk, nonce)?;
+        EVMTransactionBuilder::new_transfer_from_account(recipient, amount, sk, nonce)
+    });
 
-    let recipient = *(recipient as *mut PublicKey);
+    handle::insert(builder)
+}
+
+#[no_mangle]
+/// # Safety
+/// Construct an EVM Transaction builder that transfer account balance to UTXO,
+/// paying a custom fee.
+/// @param {XfrPublicKey} recipient - UTXO Asset receiver.
+/// @param {u64} amount - Transfer amount.
+/// @param {string} sk - Ethereum wallet private key.
+/// @param {U256} nonce - Transaction nonce for sender.
+/// @param {string} fee - JSON-serialized `EVMFee`, e.g.
+/// `{"Legacy":{"gas_limit":21000,"gas_price":"0x2540be400"}}` or
+/// `{"Eip1559":{"gas_limit":21000,"max_fee_per_gas":"0x2540be400",
+/// "max_priority_fee_per_gas":"0x3b9aca00","base_fee":"0x2540be400"}}`.
+/// @throws Will throw a `FindoraException` if an argument is invalid.
+///
+/// The returned builder must be released with `evmTransactionBuilderFree`.
+pub unsafe extern "system" fn Java_com_findora_JniApi_transferFromAccountEvmTransactionBuilderWithFee(
+    env: JNIEnv,
+    _: JClass,
+    recipient: jlong,
+    amount: JString,
+    sk: JString,
+    nonce: JString,
+    fee: JString,
+) -> jlong {
+    let builder = jni_try!(env, {
+        let (recipient, amount, sk, nonce) =
+            transfer_args(env, recipient, amount, sk, nonce)?;
+        let fee = input::json::<EVMFee>(env, fee, "fee")?;
+        EVMTransactionBuilder::new_transfer_from_account_with_fee(
+            recipient, amount, sk, nonce, &fee,
+        )
+    });
+
+    handle::insert(builder)
+}
+
+#[no_mangle]
+/// # Safety
+/// Construct an EVM Transaction builder that transfer ERC-20 tokens.
+/// @param {string} contract - Hex-encoded address of the token contract.
+/// @param {string} to - Hex-encoded address of the receiver.
+/// @param {string} amount - Decimal amount of the token, in its smallest unit.
+/// @param {string} sk - Ethereum wallet private key.
+/// @param {U256} nonce - Transaction nonce for sender.
+/// @param {u64} chain_id - Chain id of the Findora EVM.
+/// @param {string} fee - JSON-serialized `EVMFee`, not `Minimal`.
+/// @throws Will throw a `FindoraException` if an argument is invalid.
+///
+/// The returned builder must be released with `evmTransactionBuilderFree`.
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "system" fn Java_com_findora_JniApi_erc20TransferEvmTransactionBuilder(
+    env: JNIEnv,
+    _: JClass,
+    contract: JString,
+    to: JString,
+    amount: JString,
+    sk: JString,
+    nonce: JString,
+    chain_id: jlong,
+    fee: JString,
+) -> jlong {
+    let builder = jni_try!(env, {
+        let contract = input::evm_address(env, contract, "contract")?;
+        let to = input::evm_address(env, to, "recipient")?;
+        let amount = input::amount_u256(env, amount, "amount")?;
+        let sk = input::string(env, sk, "secret key")?;
+        let nonce = input::nonce(env, nonce)?;
+        let fee = input::json::<EVMFee>(env, fee, "fee")?;
+        EVMTransactionBuilder::new_erc20_transfer(
+            contract,
+            to,
+            amount,
+            sk,
+            nonce,
+            chain_id as u64,
+            &fee,
+        )
+    });
 
-    let ser_tx = EVMTransactionBuilder::new_transfer_to_utxo_from_account(
-        XfrPublicKey::from_noah(&recipient).unwrap(),
-        amount,
-        sk,
-        nonce,
-    )
-    .unwrap();
+    handle::insert(builder)
+}
+
+#[no_mangle]
+/// # Safety
+/// Construct an EVM Transaction builder that transfer UTXO assets to account balance.
+/// @param {XfrKeyPair} keypair - Owner of the UTXO assets.
+/// @param {u64} seq_id - Unique sequence ID to prevent replay attacks.
+/// @param {string} transfer_op - JSON-serialized transfer operation sending `amount` to the
+/// staking black hole address.
+/// @param {u64} amount - Transfer amount.
+/// @param {string} address - Receiving ed25519 or ecdsa address, the address of `keypair` if empty.
+/// @param {string} asset - Base64 code of the asset, FRA if empty.
+/// @param {string} lowlevel_data - Hex-encoded data of the evm call, none if empty.
+/// @throws Will throw a `FindoraException` if an argument is invalid.
+///
+/// The returned builder must be released with `evmTransactionBuilderFree`.
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "system" fn Java_com_findora_JniApi_transferToAccountEvmTransactionBuilder(
+    env: JNIEnv,
+    _: JClass,
+    keypair: jlong,
+    seq_id: jlong,
+    transfer_op: JString,
+    amount: JString,
+    address: JString,
+    asset: JString,
+    lowlevel_data: JString,
+) -> jlong {
+    let builder = jni_try!(env, {
+        let keypair = handle::keypair(keypair)?;
+        let transfer_op = input::string(env, transfer_op, "transfer operation")?;
+        let amount = input::amount(env, amount, "amount")?;
+        let address = input::opt_string(env, address, "address")?;
+        let asset = input::opt_string(env, asset, "asset")?
+            .map(|_| input::asset_code(env, asset, "asset"))
+            .transpose()?;
+        let lowlevel_data = input::opt_string(env, lowlevel_data, "lowlevel data")?
+            .map(|_| input::hex_bytes(env, lowlevel_data, "lowlevel data"))
+            .transpose()?;
+
+        EVMTransactionBuilder::new_transfer_to_account(
+            &keypair,
+            seq_id as u64,
+            transfer_op,
+            amount,
+            address,
+            asset,
+            lowlevel_data,
+        )
+    });
+
+    handle::insert(builder)
+}
+
+#[no_mangle]
+/// # Safety
+/// Restore the hex-encoded ethereum private key from a mnemonic
+/// along a bip32 derivation path, eg. "m/44'/60'/0'/0/0".
+pub unsafe extern "system" fn Java_com_findora_JniApi_restoreEvmSecretFromMnemonic(
+    env: JNIEnv,
+    _: JClass,
+    phrase: JString,
+    lang: JString,
+    path: JString,
+) -> jstring {
+    let sk = jni_try!(env, {
+        let phrase = input::string(env, phrase, "phrase")?;
+        let lang = input::string(env, lang, "language")?;
+        let path = input::string(env, path, "derivation path")?;
+        restore_evm_secret_from_mnemonic(&phrase, &lang, &path)
+    });
+
+    let output = throw_exception!(env, env.new_string(sk));
+    **output
+}
+
+#[no_mangle]
+/// # Safety
+/// The `0x` prefixed ethereum address of a hex-encoded private key.
+pub unsafe extern "system" fn Java_com_findora_JniApi_evmAddressFromSecret(
+    env: JNIEnv,
+    _: JClass,
+    sk: JString,
+) -> jstring {
+    let address = jni_try!(env, {
+        let sk = input::string(env, sk, "secret key")?;
+        evm_address_from_secret(&sk)
+    });
 
-    **env
-        .new_string(ser_tx)
-        .expect("Couldn't create java String!")
+    let output = throw_exception!(env, env.new_string(address));
+    **output
+}
+
+#[no_mangle]
+/// # Safety
+/// Preview of the transaction of an EVM Transaction builder as JSON,
+/// for the user to confirm what will be signed.
+pub unsafe extern "system" fn Java_com_findora_JniApi_evmTransactionBuilderDescribe(
+    env: JNIEnv,
+    _: JClass,
+    builder: jlong,
+) -> jstring {
+    let description = jni_try!(env, {
+        let description = handle::with(builder, |builder: &EVMTransactionBuilder| {
+            builder.describe()
+        })?;
+        serde_json::to_string(&description).c(d!())
+    });
+
+    let output = throw_exception!(env, env.new_string(description));
+    **output
+}
+
+#[no_mangle]
+/// # Safety
+/// Serialize the transaction of an EVM Transaction builder as a base64 string.
+pub unsafe extern "system" fn Java_com_findora_JniApi_evmTransactionBuilderSerializedTransactionBase64(
+    env: JNIEnv,
+    _: JClass,
+    builder: jlong,
+) -> jstring {
+    let ser_tx = jni_try!(env, {
+        handle::with(builder, |builder: &EVMTransactionBuilder| {
+            builder.serialized_transaction_base64()
+        })
+    });
+
+    let output = throw_exception!(env, env.new_string(ser_tx));
+    **output
+}
+
+#[no_mangle]
+/// # Safety
+/// Release an EVM Transaction builder, `builder` is invalid after this call.
+pub unsafe extern "system" fn Java_com_findora_JniApi_evmTransactionBuilderFree(
+    env: JNIEnv,
+    _: JClass,
+    builder: jlong,
+) {
+    let _ = ThrowExceptionImpl(env, handle::remove::<EVMTransactionBuilder>(builder));
 }
 
 #[no_mangle]
 /// Serialize ethereum address used to abci query nonce.
+/// @throws Will throw a `FindoraException` if the address is invalid.
 pub extern "system" fn Java_com_findora_JniApi_getSerializedAddress(
     env: JNIEnv,
     _: JClass,
     address: JString,
 ) -> jstring {
-    let addr = jStringToString(env, address);
-    let data = get_serialized_address(&addr).unwrap();
-    **env.new_string(data).expect("Couldn't create java String!")
+    let data = jni_try!(env, {
+        let addr = input::string(env, address, "address")?;
+        get_serialized_address(&addr)
+    });
+
+    let output = throw_exception!(env, env.new_string(data));
+    **output
 }
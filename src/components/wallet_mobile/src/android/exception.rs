@@ -3,6 +3,11 @@
 use core::fmt::Display;
 use jni::sys::jobject;
 use jni::JNIEnv;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+/// Class of the exceptions thrown to java for the errors of this library,
+/// `java/lang/Exception` is thrown instead if it can not be found.
+pub(super) const EXCEPTION_CLASS: &str = "com/findora/FindoraException";
 
 /// throw exception and return null if res is an Err(..), this behave like `?` but return null pointer.
 macro_rules! throw_exception {
@@ -14,6 +19,14 @@ macro_rules! throw_exception {
     };
 }
 
+/// Run the block as `CatchPanicImpl` then `throw_exception`,
+/// the block evaluates to a `ruc::Result`.
+macro_rules! jni_try {
+    ($env:expr, $body: block) => {
+        throw_exception!($env, CatchPanicImpl(|| $body))
+    };
+}
+
 ///Throw exception if result it's an Err(..) and return Err(null).
 pub(super) fn ThrowExceptionImpl<T, E>(
     env: JNIEnv,
@@ -32,8 +45,12 @@ where
                 env.exception_clear().unwrap();
             }
 
-            env.throw_new("java/lang/Exception", format!("{}", e))
-                .unwrap();
+            let msg = format!("{}", e);
+            if env.throw_new(EXCEPTION_CLASS, &msg).is_err() {
+                // the `NoClassDefFoundError` raised by the lookup is pending
+                let _ = env.exception_clear();
+                env.throw_new("java/lang/Exception", msg).unwrap();
+            }
 
             let null = core::ptr::null_mut() as jobject;
 
@@ -41,3 +58,28 @@ where
         }
     }
 }
+
+/// Run `f`, turning a panic into an error so it can be thrown to java
+/// instead of unwinding across the FFI boundary.
+///
+/// Panics are only caught when built with `panic = "unwind"`,
+/// with `panic = "abort"` they still abort the process.
+/// Nothing touched by `f` is used after it panicked, so it is asserted unwind safe.
+pub(super) fn CatchPanicImpl<T, F>(f: F) -> ruc::Result<T>
+where
+    F: FnOnce() -> ruc::Result<T>,
+{
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(res) => res,
+        Err(e) => {
+            let msg = if let Some(s) = e.downcast_ref::<&str>() {
+                (*s).to_owned()
+            } else if let Some(s) = e.downcast_ref::<String>() {
+                s.clone()
+            } else {
+                "unknown panic".to_owned()
+            };
+            Err(ruc::eg!(format!("panicked: {}", msg)))
+        }
+    }
+}
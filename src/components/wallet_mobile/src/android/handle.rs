@@ -0,0 +1,109 @@
+//!
+//! Opaque handles given to java in place of raw pointers,
+//! for the key pairs and the builders.
+//!
+//! The key pairs and mnemonics are held by the `SecretSession` of the process,
+//! the table only has their `Secret`.
+//!
+//! A handle is an id in a process wide table, so a released or forged handle
+//! is reported as an error instead of being dereferenced.
+//! `0` is never allocated and stands for null.
+//!
+
+use crate::rust::session::{Secret, SecretSession};
+use jni::sys::jlong;
+use ruc::*;
+use std::{
+    any::{type_name, Any},
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, MutexGuard,
+    },
+};
+use zei::XfrKeyPair;
+
+static NEXT_HANDLE: AtomicU64 = AtomicU64::new(1);
+
+static TABLE: Mutex<BTreeMap<u64, Box<dyn Any + Send>>> = Mutex::new(BTreeMap::new());
+
+fn table() -> MutexGuard<'static, BTreeMap<u64, Box<dyn Any + Send>>> {
+    // the table stays consistent even if a holder panicked
+    TABLE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Move `obj` into the table, returns its handle.
+pub(super) fn insert<T: Any + Send>(obj: T) -> jlong {
+    let id = NEXT_HANDLE.fetch_add(1, Ordering::Relaxed);
+    table().insert(id, Box::new(obj));
+    id as jlong
+}
+
+/// Run `f` on the object of `handle`, which must be a `T`.
+pub(super) fn with<T: Any + Send, R>(
+    handle: jlong,
+    f: impl FnOnce(&T) -> R,
+) -> Result<R> {
+    let table = table();
+    let obj = table
+        .get(&(handle as u64))
+        .c(d!(format!("invalid or released handle: {}", handle)))?;
+    let obj = obj.downcast_ref::<T>().c(d!(format!(
+        "handle {} is not a {}",
+        handle,
+        type_name::<T>()
+    )))?;
+    Ok(f(obj))
+}
+
+/// Run `f` on the object of `handle` mutably, which must be a `T`.
+pub(super) fn with_mut<T: Any + Send, R>(
+    handle: jlong,
+    f: impl FnOnce(&mut T) -> R,
+) -> Result<R> {
+    let mut table = table();
+    let obj = table
+        .get_mut(&(handle as u64))
+        .c(d!(format!("invalid or released handle: {}", handle)))?;
+    let obj = obj.downcast_mut::<T>().c(d!(format!(
+        "handle {} is not a {}",
+        handle,
+        type_name::<T>()
+    )))?;
+    Ok(f(obj))
+}
+
+/// A copy of the object of `handle`, which must be a `T`.
+pub(super) fn get<T: Any + Send + Clone>(handle: jlong) -> Result<T> {
+    with(handle, T::clone)
+}
+
+/// Keep a copy of `keypair` in the secret session, returns its handle.
+pub(super) fn insert_keypair(keypair: &XfrKeyPair) -> jlong {
+    insert(SecretSession::global().add_keypair(keypair))
+}
+
+/// The key pair of `handle`.
+pub(super) fn keypair(handle: jlong) -> Result<XfrKeyPair> {
+    with(handle, Secret::keypair)?.c(d!())
+}
+
+/// Take the object of `handle` out of the table, the handle is invalid afterwards.
+///
+/// A handle of another type is left untouched.
+pub(super) fn remove<T: Any + Send>(handle: jlong) -> Result<T> {
+    let mut table = table();
+    let id = handle as u64;
+    match table.get(&id) {
+        None => Err(eg!(format!("invalid or released handle: {}", handle))),
+        Some(obj) if !obj.is::<T>() => Err(eg!(format!(
+            "handle {} is not a {}",
+            handle,
+            type_name::<T>()
+        ))),
+        Some(_) => {
+            let obj = table.remove(&id).unwrap();
+            Ok(*obj.downcast::<T>().unwrap())
+        }
+    }
+}
@@ -0,0 +1,107 @@
+//!
+//! Parsing of the string parameters received from java.
+//!
+//! Every parser names the parameter in its error, so an invalid input is
+//! thrown to java as a `FindoraException` "invalid <name>", with the reason,
+//! instead of a panic or a default value.
+//!
+
+use crate::rust::account::parse_evm_address;
+use crate::rust::address::{parse_public_key, parse_validator_address};
+use fp_types::{H160, U256};
+use jni::objects::JString;
+use jni::JNIEnv;
+use ledger::{data_model::AssetTypeCode, staking::TendermintAddr};
+use ruc::*;
+use serde::de::DeserializeOwned;
+use zei::XfrPublicKey;
+
+fn invalid(name: &str) -> String {
+    format!("invalid {}", name)
+}
+
+/// A string, which must not be null.
+pub(super) fn string(env: JNIEnv, s: JString, name: &str) -> Result<String> {
+    if s.is_null() {
+        return Err(eg!(format!("{}: null", invalid(name))));
+    }
+    env.get_string(s).map(Into::into).c(d!(invalid(name)))
+}
+
+/// A string which may be omitted, null and empty are `None`.
+pub(super) fn opt_string(env: JNIEnv, s: JString, name: &str) -> Result<Option<String>> {
+    if s.is_null() {
+        return Ok(None);
+    }
+    Ok(Some(string(env, s, name)?).filter(|s| !s.is_empty()))
+}
+
+fn decimal(env: JNIEnv, s: JString, name: &str) -> Result<String> {
+    let s = string(env, s, name)?.trim().to_owned();
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(eg!(format!(
+            "{}: `{}` is not a decimal amount",
+            invalid(name),
+            s
+        )));
+    }
+    Ok(s)
+}
+
+/// An amount in decimal, java has no unsigned 64 bits integer.
+pub(super) fn amount(env: JNIEnv, s: JString, name: &str) -> Result<u64> {
+    decimal(env, s, name)?.parse().c(d!(invalid(name)))
+}
+
+/// A 256 bits amount in decimal, of an ERC20 token.
+pub(super) fn amount_u256(env: JNIEnv, s: JString, name: &str) -> Result<U256> {
+    U256::from_dec_str(&decimal(env, s, name)?)
+        .map_err(|e| eg!(format!("{}: {:?}", invalid(name), e)))
+}
+
+/// A JSON value.
+pub(super) fn json<T: DeserializeOwned>(
+    env: JNIEnv,
+    s: JString,
+    name: &str,
+) -> Result<T> {
+    serde_json::from_str(&string(env, s, name)?).c(d!(invalid(name)))
+}
+
+/// An EVM nonce, in decimal or as its JSON.
+pub(super) fn nonce(env: JNIEnv, s: JString) -> Result<U256> {
+    let s = string(env, s, "nonce")?;
+    let s = s.trim();
+    if !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()) {
+        U256::from_dec_str(s).map_err(|e| eg!(format!("{}: {:?}", invalid("nonce"), e)))
+    } else {
+        serde_json::from_str(s).c(d!(invalid("nonce")))
+    }
+}
+
+/// A public key, as a bech32 `fra1...` address or in base64.
+pub(super) fn public_key(env: JNIEnv, s: JString, name: &str) -> Result<XfrPublicKey> {
+    parse_public_key(&string(env, s, name)?).c(d!(invalid(name)))
+}
+
+/// A `0x` EVM address.
+pub(super) fn evm_address(env: JNIEnv, s: JString, name: &str) -> Result<H160> {
+    parse_evm_address(string(env, s, name)?.trim()).c(d!(invalid(name)))
+}
+
+/// The tendermint address of a validator.
+pub(super) fn validator(env: JNIEnv, s: JString, name: &str) -> Result<TendermintAddr> {
+    parse_validator_address(&string(env, s, name)?).c(d!(invalid(name)))
+}
+
+/// An asset code, in base64.
+pub(super) fn asset_code(env: JNIEnv, s: JString, name: &str) -> Result<AssetTypeCode> {
+    AssetTypeCode::new_from_base64(string(env, s, name)?.trim()).c(d!(invalid(name)))
+}
+
+/// Bytes in hex, with or without `0x`.
+pub(super) fn hex_bytes(env: JNIEnv, s: JString, name: &str) -> Result<Vec<u8>> {
+    let s = string(env, s, name)?;
+    let s = s.trim();
+    hex::decode(s.strip_prefix("0x").unwrap_or(s)).c(d!(invalid(name)))
+}
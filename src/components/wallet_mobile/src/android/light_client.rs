@@ -0,0 +1,84 @@
+//!
+//! The verification of the ledger state and utxos, see `crate::rust::light_client`.
+//!
+//! The nodes are given as a JSON array of urls, the results are returned as
+//! JSON `VerifiedState` and `VerifiedUtxo`. The functions block on the network,
+//! they must not be called from the main thread.
+//!
+
+use super::exception::{CatchPanicImpl, ThrowExceptionImpl};
+use super::{handle, input};
+use crate::rust::light_client::LightClient;
+use jni::objects::{JClass, JString};
+use jni::sys::{jlong, jstring};
+use jni::JNIEnv;
+use ruc::*;
+use std::sync::Arc;
+
+/// A client, shared as the handle table must not be locked during a request.
+type SharedLightClient = Arc<LightClient>;
+
+#[no_mangle]
+/// # Safety
+///
+/// A client of the query servers of `nodes`, a JSON array of two urls at least.
+/// The returned client must be released with `lightClientFree`.
+pub unsafe extern "system" fn Java_com_findora_JniApi_lightClientNew(
+    env: JNIEnv,
+    _: JClass,
+    nodes: JString,
+) -> jlong {
+    let client = jni_try!(env, {
+        let nodes = input::json::<Vec<String>>(env, nodes, "nodes")?;
+        LightClient::new(nodes).map(Arc::new)
+    });
+    handle::insert::<SharedLightClient>(client)
+}
+
+#[no_mangle]
+/// # Safety
+///
+/// The state commitment at the highest block height reached by all the nodes.
+pub unsafe extern "system" fn Java_com_findora_JniApi_lightClientState(
+    env: JNIEnv,
+    _: JClass,
+    client: jlong,
+) -> jstring {
+    let state = jni_try!(env, {
+        let client = handle::get::<SharedLightClient>(client)?;
+        serde_json::to_string(&client.state()?).c(d!())
+    });
+    let output = throw_exception!(env, env.new_string(state));
+    **output
+}
+
+#[no_mangle]
+/// # Safety
+///
+/// The utxo `sid`, if its proof holds against a state of all the nodes.
+/// Throws if the utxo is spent or its proof is invalid.
+pub unsafe extern "system" fn Java_com_findora_JniApi_lightClientUtxo(
+    env: JNIEnv,
+    _: JClass,
+    client: jlong,
+    sid: jlong,
+) -> jstring {
+    let utxo = jni_try!(env, {
+        let client = handle::get::<SharedLightClient>(client)?;
+        serde_json::to_string(&client.utxo(sid as u64)?).c(d!())
+    });
+    let output = throw_exception!(env, env.new_string(utxo));
+    **output
+}
+
+#[no_mangle]
+/// # Safety
+///
+/// Releases a client, `client` is invalid after this call.
+pub unsafe extern "system" fn Java_com_findora_JniApi_lightClientFree(
+    env: JNIEnv,
+    _: JClass,
+    client: jlong,
+) {
+    let _ = ThrowExceptionImpl(env, handle::remove::<SharedLightClient>(client));
+}
@@ -1,19 +1,32 @@
 #[macro_use]
 mod exception;
 
+mod anon;
+mod balance;
 mod constructor;
 mod evm;
+mod handle;
+mod input;
+mod light_client;
+mod network;
+mod queue;
+mod session;
+mod signer;
 mod transfer;
 mod tx_builder;
 
+use crate::rust::address;
+use crate::rust::logger;
 use crate::rust::types;
 use crate::rust::*;
-use exception::ThrowExceptionImpl;
+use exception::{CatchPanicImpl, ThrowExceptionImpl};
 use jni::objects::{JClass, JString};
 use jni::sys::{jboolean, jbyteArray, jint, jlong, jstring};
 use jni::JNIEnv;
 use ledger::data_model::AssetTypeCode;
+use ruc::{d, RucResult};
 use zei::{noah_api::xfr::structs::ASSET_TYPE_LENGTH, XfrKeyPair, XfrPublicKey};
+use zeroize::Zeroizing;
 
 #[no_mangle]
 /// Returns the git commit hash and commit date of the commit this library was built against.
@@ -32,6 +45,34 @@ pub extern "system" fn Java_com_findora_JniApi_buildId(
     **output
 }
 
+#[no_mangle]
+/// Logs the events of the library to logcat, with the tag `findora`, up to `level`:
+/// one of `off`, `error`, `warn`, `info`, `debug` or `trace`.
+/// Can be called again to change the level.
+pub extern "system" fn Java_com_findora_JniApi_setLogLevel(
+    env: JNIEnv,
+    _: JClass,
+    level: JString,
+) {
+    let _ = ThrowExceptionImpl(
+        env,
+        CatchPanicImpl(|| {
+            let level = input::string(env, level, "level")?;
+            logger::set_log_level(&level)
+        }),
+    );
+}
+
+#[no_mangle]
+/// Returns the level set by `setLogLevel`, `off` before.
+pub extern "system" fn Java_com_findora_JniApi_getLogLevel(
+    env: JNIEnv,
+    _: JClass,
+) -> jstring {
+    let output = throw_exception!(env, env.new_string(logger::log_level()));
+    **output
+}
+
 #[no_mangle]
 /// Generates asset type as a Base64 string from a JSON-serialized JavaScript value.
 pub extern "system" fn Java_com_findora_JniApi_assetTypeFromValue(
@@ -65,18 +106,37 @@ pub extern "system" fn Java_com_findora_JniApi_verifyAuthenticatedTxn(
     state_commitment: JString,
     authenticated_txn: JString,
 ) -> jboolean {
-    let state_commitment: String = env
-        .get_string(state_commitment)
-        .expect("Couldn't get java string!")
-        .into();
-
-    let authenticated_txn: String = env
-        .get_string(authenticated_txn)
-        .expect("Couldn't get java string!")
-        .into();
-
-    rs_verify_authenticated_txn(state_commitment, authenticated_txn).unwrap_or(false)
-        as jboolean
+    let verified = jni_try!(env, {
+        let state_commitment = input::string(env, state_commitment, "state commitment")?;
+        let authenticated_txn = input::string(env, authenticated_txn, "transaction")?;
+        Ok(
+            rs_verify_authenticated_txn(state_commitment, authenticated_txn)
+                .unwrap_or(false),
+        )
+    });
+    verified as jboolean
+}
+
+#[no_mangle]
+/// Given a serialized state commitment and utxo, returns true if the utxo is unspent and its
+/// transaction correctly hashes up to the state commitment, false otherwise.
+/// @param {string} state_commitment - String representing the state commitment.
+/// @param {string} authenticated_utxo - String representing the utxo, as returned by `utxo_sid`.
+pub extern "system" fn Java_com_findora_JniApi_verifyAuthenticatedUtxo(
+    env: JNIEnv,
+    _: JClass,
+    state_commitment: JString,
+    authenticated_utxo: JString,
+) -> jboolean {
+    let verified = jni_try!(env, {
+        let state_commitment = input::string(env, state_commitment, "state commitment")?;
+        let authenticated_utxo = input::string(env, authenticated_utxo, "utxo")?;
+        Ok(
+            rs_verify_authenticated_utxo(state_commitment, authenticated_utxo)
+                .unwrap_or(false),
+        )
+    });
+    verified as jboolean
 }
 
 #[no_mangle]
@@ -89,11 +149,10 @@ pub extern "system" fn Java_com_findora_JniApi_generateMnemonicCustom(
     words_len: jint,
     lang: JString,
 ) -> jstring {
-    let lang: String = env
-        .get_string(lang)
-        .expect("Couldn't get java string!")
-        .into();
-    let mnemonic = rs_generate_mnemonic_custom(words_len as u8, lang.as_str()).unwrap();
+    let mnemonic = jni_try!(env, {
+        let lang = input::string(env, lang, "language")?;
+        rs_generate_mnemonic_custom(words_len as u8, &lang)
+    });
     let output = env
         .new_string(mnemonic)
         .expect("Couldn't create java string!");
@@ -108,10 +167,7 @@ pub extern "system" fn Java_com_findora_JniApi_decryptionPbkdf2Aes256gcm(
     password: JString,
 ) -> jstring {
     let enc_key_pair = env.convert_byte_array(enc_key_pair).unwrap();
-    let password: String = env
-        .get_string(password)
-        .expect("Couldn't get java string!")
-        .into();
+    let password = throw_exception!(env, input::string(env, password, "password"));
     let plaintext = decryption_pbkdf2_aes256gcm(enc_key_pair, password);
     let output = env
         .new_string(plaintext)
@@ -126,14 +182,12 @@ pub extern "system" fn Java_com_findora_JniApi_encryptionPbkdf2Aes256gcm(
     key_pair: JString,
     password: JString,
 ) -> jbyteArray {
-    let key_pair: String = env
-        .get_string(key_pair)
-        .expect("Couldn't get java string!")
-        .into();
-    let password: String = env
-        .get_string(password)
-        .expect("Couldn't get java string!")
-        .into();
+    let (key_pair, password) = jni_try!(env, {
+        Ok((
+            input::string(env, key_pair, "key pair")?,
+            input::string(env, password, "password")?,
+        ))
+    });
 
     let res = encryption_pbkdf2_aes256gcm(key_pair, password);
     env.byte_array_from_slice(res.as_slice()).unwrap()
@@ -147,12 +201,11 @@ pub extern "system" fn Java_com_findora_JniApi_keypairFromStr(
     _: JClass,
     text: JString,
 ) -> jlong {
-    let text: String = env
-        .get_string(text)
-        .expect("Couldn't get java string!")
-        .into();
-    let val = types::XfrKeyPair::from(keypair_from_str(text).into_noah().unwrap());
-    Box::into_raw(Box::new(val)) as jlong
+    let keypair = jni_try!(env, {
+        let bytes = Zeroizing::new(input::hex_bytes(env, text, "key pair")?);
+        XfrKeyPair::noah_from_bytes(&bytes).c(d!())
+    });
+    handle::insert_keypair(&keypair)
 }
 
 #[no_mangle]
@@ -179,8 +232,8 @@ pub unsafe extern "system" fn Java_com_findora_JniApi_getPubKeyStr(
     _: JClass,
     xfr_keypair_ptr: jlong,
 ) -> jstring {
-    let key = &*(xfr_keypair_ptr as *mut types::XfrKeyPair);
-    let pubkey = get_pub_key_str(&XfrKeyPair::from_noah(key).unwrap());
+    let key = throw_exception!(env, handle::keypair(xfr_keypair_ptr));
+    let pubkey = get_pub_key_str(&key);
     let output = env
         .new_string(pubkey)
         .expect("Couldn't create java string!");
@@ -196,8 +249,8 @@ pub unsafe extern "system" fn Java_com_findora_JniApi_getPrivKeyStr(
     _: JClass,
     xfr_keypair_ptr: jlong,
 ) -> jstring {
-    let key = &*(xfr_keypair_ptr as *mut types::XfrKeyPair);
-    let prikey = get_priv_key_str(&XfrKeyPair::from_noah(key).unwrap());
+    let key = throw_exception!(env, handle::keypair(xfr_keypair_ptr));
+    let prikey = get_priv_key_str(&key);
     let output = env
         .new_string(prikey)
         .expect("Couldn't create java string!");
@@ -214,19 +267,59 @@ pub extern "system" fn Java_com_findora_JniApi_restoreKeypairFromMnemonicDefault
     _: JClass,
     phrase: JString,
 ) -> jlong {
-    let phrase: String = env
-        .get_string(phrase)
-        .expect("Couldn't get java string!")
-        .into();
+    let phrase = throw_exception!(env, input::string(env, phrase, "phrase"));
+    let phrase = Zeroizing::new(phrase);
     if let Ok(keypair) = rs_restore_keypair_from_mnemonic_default(phrase.as_str()) {
-        Box::into_raw(Box::new(types::XfrKeyPair::from(
-            keypair.into_noah().unwrap(),
-        ))) as jlong
+        handle::insert_keypair(&keypair)
     } else {
         ::std::ptr::null_mut::<()>() as jlong
     }
 }
 
+#[no_mangle]
+/// # Safety
+///
+/// Restore the XfrKeyPair from a mnemonic along a derivation path,
+/// "m/44'/coin'/account'/change/address" or "m/49'/coin'/account'/change/address".
+/// @throws Will throw a `FindoraException` if the phrase or the path is invalid.
+pub unsafe extern "system" fn Java_com_findora_JniApi_restoreKeypairFromMnemonicPath(
+    env: JNIEnv,
+    _: JClass,
+    phrase: JString,
+    lang: JString,
+    path: JString,
+) -> jlong {
+    let keypair = jni_try!(env, {
+        let phrase = Zeroizing::new(input::string(env, phrase, "phrase")?);
+        let lang = input::string(env, lang, "language")?;
+        let path = input::string(env, path, "derivation path")?;
+        rs_restore_keypair_from_mnemonic_path(&phrase, &lang, &path)
+    });
+    handle::insert_keypair(&keypair)
+}
+
+#[no_mangle]
+/// # Safety
+///
+/// Validate a user-entered mnemonic, returns a JSON `MnemonicCheck`
+/// with the suggested replacements of its misspelled words.
+/// - @param `lang`: acceptable value are one of [ "en", "zh", "zh_traditional", "fr", "it", "ko", "sp", "jp" ]
+pub unsafe extern "system" fn Java_com_findora_JniApi_validateMnemonic(
+    env: JNIEnv,
+    _: JClass,
+    phrase: JString,
+    lang: JString,
+) -> jstring {
+    let check = jni_try!(env, {
+        let phrase = input::string(env, phrase, "phrase")?;
+        let lang = input::string(env, lang, "language")?;
+        let check = rs_validate_mnemonic(&phrase, &lang)?;
+        serde_json::to_string(&check).c(d!())
+    });
+    let output = throw_exception!(env, env.new_string(check));
+    **output
+}
+
 #[no_mangle]
 /// # Safety
 ///
@@ -237,8 +330,8 @@ pub unsafe extern "system" fn Java_com_findora_JniApi_keypairToStr(
     _: JClass,
     xfr_keypair_ptr: jlong,
 ) -> jstring {
-    let key = &*(xfr_keypair_ptr as *mut types::XfrKeyPair);
-    let res = keypair_to_str(&XfrKeyPair::from_noah(key).unwrap());
+    let key = throw_exception!(env, handle::keypair(xfr_keypair_ptr));
+    let res = keypair_to_str(&key);
     let output = env.new_string(res).expect("Couldn't create java string!");
     **output
 }
@@ -249,14 +342,9 @@ pub extern "system" fn Java_com_findora_JniApi_createKeypairFromSecret(
     _: JClass,
     sk_str: JString,
 ) -> jlong {
-    let sk: String = env
-        .get_string(sk_str)
-        .expect("Couldn't get java string!")
-        .into();
+    let sk = throw_exception!(env, input::string(env, sk_str, "secret key"));
     if let Some(keypair) = create_keypair_from_secret(sk) {
-        Box::into_raw(Box::new(types::XfrKeyPair::from(
-            keypair.into_noah().unwrap(),
-        ))) as jlong
+        handle::insert_keypair(&keypair)
     } else {
         ::std::ptr::null_mut::<()>() as jlong
     }
@@ -266,12 +354,12 @@ pub extern "system" fn Java_com_findora_JniApi_createKeypairFromSecret(
 /// # Safety
 ///
 pub unsafe extern "system" fn Java_com_findora_JniApi_getPkFromKeypair(
-    _env: JNIEnv,
+    env: JNIEnv,
     _: JClass,
     xfr_keypair_ptr: jlong,
 ) -> jlong {
-    let kp = &*(xfr_keypair_ptr as *mut types::XfrKeyPair);
-    let pk = get_pk_from_keypair(&XfrKeyPair::from_noah(kp).unwrap());
+    let kp = throw_exception!(env, handle::keypair(xfr_keypair_ptr));
+    let pk = get_pk_from_keypair(&kp);
     Box::into_raw(Box::new(types::XfrPublicKey::from(pk.into_noah().unwrap()))) as jlong
 }
 
@@ -281,10 +369,7 @@ pub extern "system" fn Java_com_findora_JniApi_newKeypair(
     _env: JNIEnv,
     _: JClass,
 ) -> jlong {
-    let keypair = new_keypair();
-    Box::into_raw(Box::new(types::XfrKeyPair::from(
-        keypair.into_noah().unwrap(),
-    ))) as jlong
+    handle::insert_keypair(&new_keypair())
 }
 
 #[no_mangle]
@@ -293,12 +378,10 @@ pub extern "system" fn Java_com_findora_JniApi_bech32ToBase64(
     _: JClass,
     pk: JString,
 ) -> jstring {
-    let pk: String = env
-        .get_string(pk)
-        .expect("Couldn't get java string!")
-        .into();
-
-    let bs = throw_exception!(env, rs_bech32_to_base64(pk.as_str()));
+    let bs = jni_try!(env, {
+        let pk = input::string(env, pk, "address")?;
+        rs_bech32_to_base64(pk.trim())
+    });
     let output = env.new_string(bs).expect("Couldn't create java string!");
     **output
 }
@@ -309,16 +392,47 @@ pub extern "system" fn Java_com_findora_JniApi_base64ToBech32(
     _: JClass,
     pk: JString,
 ) -> jstring {
-    let pk: String = env
-        .get_string(pk)
-        .expect("Couldn't get java string!")
-        .into();
-
-    let bs = throw_exception!(env, rs_base64_to_bech32(pk.as_str()));
+    let bs = jni_try!(env, {
+        let pk = input::string(env, pk, "public key")?;
+        rs_base64_to_bech32(pk.trim())
+    });
     let output = env.new_string(bs).expect("Couldn't create java string!");
     **output
 }
 
+#[no_mangle]
+/// Parses an address entered by the user: a bech32 `fra1...` address, a base64 public key
+/// or a `0x` EVM address, checked against its EIP-55 checksum if in mixed case.
+/// Returns the JSON `ParsedAddress`, with the canonical encodings of the address.
+pub extern "system" fn Java_com_findora_JniApi_parseAddress(
+    env: JNIEnv,
+    _: JClass,
+    address: JString,
+) -> jstring {
+    let parsed = jni_try!(env, {
+        let address = input::string(env, address, "address")?;
+        let parsed = address::parse_address(&address)?;
+        serde_json::to_string(&parsed).c(d!())
+    });
+    let output = throw_exception!(env, env.new_string(parsed));
+    **output
+}
+
+#[no_mangle]
+/// Returns the EIP-55 checksummed form of a `0x` EVM address.
+pub extern "system" fn Java_com_findora_JniApi_evmChecksumAddress(
+    env: JNIEnv,
+    _: JClass,
+    address: JString,
+) -> jstring {
+    let address = jni_try!(env, {
+        let address = input::string(env, address, "address")?;
+        address::evm_checksum_address(&address)
+    });
+    let output = throw_exception!(env, env.new_string(address));
+    **output
+}
+
 #[no_mangle]
 /// # Safety
 ///
@@ -344,14 +458,10 @@ pub unsafe extern "system" fn Java_com_findora_JniApi_openClientAssetRecord(
         let memo = &*(owner_memo_ptr as *mut OwnerMemo);
         Some(memo.clone())
     };
-    let keypair = &*(keypair_ptr as *mut types::XfrKeyPair);
+    let keypair = throw_exception!(env, handle::keypair(keypair_ptr));
     let oar = throw_exception!(
         env,
-        rs_open_client_asset_record(
-            record,
-            owner_memo,
-            &XfrKeyPair::from_noah(keypair).unwrap()
-        )
+        rs_open_client_asset_record(record, owner_memo, &keypair)
     );
     Box::into_raw(Box::new(types::OpenAssetRecord::from(oar))) as jlong
 }
@@ -411,15 +521,3 @@ pub extern "system" fn Java_com_findora_JniApi_openClientAssetRecordGetPubKey(
     let record = unsafe { &*(record_ptr as *mut types::OpenAssetRecord) };
     Box::into_raw(Box::new(types::XfrPublicKey::from(*record.get_pub_key()))) as jlong
 }
-
-pub(super) fn jStringToString(env: JNIEnv, s: JString) -> String {
-    env.get_string(s)
-        .expect("Couldn't create rust String!")
-        .into()
-}
-
-pub(super) fn parseU64(env: JNIEnv, amount: JString) -> u64 {
-    jStringToString(env, amount)
-        .parse()
-        .expect("Parse u64 error.")
-}
@@ -0,0 +1,278 @@
+//!
+//! Requests to a node, see `crate::rust::network`.
+//!
+//! The requests run on a thread of their own, their outcome is given to
+//! a `com.findora.Callback`:
+//!
+//! ```java
+//! public interface Callback {
+//!     void onSuccess(String result);
+//!     void onError(String error);
+//! }
+//! ```
+//!
+//! The callbacks are called from that thread, not from the calling one.
+//!
+//! A client may send its requests through a `com.findora.HttpTransport`,
+//! eg. an OkHttp client with a `CertificatePinner`:
+//!
+//! ```java
+//! public interface HttpTransport {
+//!     // the body of the response, throws for a non 2xx status;
+//!     // `body` is the JSON body of a POST, null for a GET
+//!     byte[] send(String method, String url, String body);
+//! }
+//! ```
+//!
+
+use super::exception::{CatchPanicImpl, ThrowExceptionImpl};
+use super::{handle, input};
+use crate::rust::fee::{DraftTx, FeeEstimator};
+use crate::rust::network::{
+    DefaultTransport, Endpoints, HttpRequest, HttpTransport, NetworkClient,
+};
+use jni::objects::{GlobalRef, JClass, JObject, JString, JValue};
+use jni::sys::jlong;
+use jni::{JNIEnv, JavaVM};
+use ruc::*;
+use std::{sync::Arc, thread};
+
+struct JniTransport {
+    vm: JavaVM,
+    transport: GlobalRef,
+}
+
+impl HttpTransport for JniTransport {
+    fn send(&self, req: &HttpRequest) -> Result<Vec<u8>> {
+        let env = self.vm.attach_current_thread().c(d!())?;
+        let method = env.new_string(req.method.as_str()).c(d!())?;
+        let url = env.new_string(&req.url).c(d!())?;
+        let body = match req.body.as_ref() {
+            Some(body) => JObject::from(env.new_string(body).c(d!())?),
+            None => JObject::null(),
+        };
+        let res = env
+            .call_method(
+                self.transport.as_obj(),
+                "send",
+                "(Ljava/lang/String;Ljava/lang/String;Ljava/lang/String;)[B",
+                &[
+                    JValue::Object(JObject::from(method)),
+                    JValue::Object(JObject::from(url)),
+                    JValue::Object(body),
+                ],
+            )
+            .and_then(|res| res.l());
+        let res = match res {
+            Ok(res) => res,
+            Err(e) => {
+                let msg = take_exception(&env).unwrap_or_else(|| e.to_string());
+                return Err(eg!(format!("{}: {}", req.url, msg)));
+            }
+        };
+        if res.is_null() {
+            return Err(eg!(format!("{}: transport returned null", req.url)));
+        }
+        env.convert_byte_array(res.into_raw()).c(d!())
+    }
+}
+
+/// Clear the pending java exception, returns its description.
+fn take_exception(env: &JNIEnv) -> Option<String> {
+    let e = env.exception_occurred().ok()?;
+    if e.is_null() {
+        return None;
+    }
+    env.exception_clear().ok()?;
+    let msg = env
+        .call_method(e, "toString", "()Ljava/lang/String;", &[])
+        .and_then(|msg| msg.l())
+        .ok()?;
+    env.get_string(JString::from(msg)).ok().map(Into::into)
+}
+
+/// `transport`, or the default one if null.
+fn new_transport(env: JNIEnv, transport: JObject) -> Result<Arc<dyn HttpTransport>> {
+    if transport.is_null() {
+        return Ok(Arc::new(DefaultTransport::default()));
+    }
+    Ok(Arc::new(JniTransport {
+        vm: env.get_java_vm().c(d!())?,
+        transport: env.new_global_ref(transport).c(d!())?,
+    }))
+}
+
+/// Run `f` on a new thread, then give its outcome to `callback`.
+fn spawn<F>(env: JNIEnv, callback: JObject, f: F) -> Result<()>
+where
+    F: FnOnce() -> Result<String> + Send + 'static,
+{
+    if callback.is_null() {
+        return Err(eg!("null callback"));
+    }
+    let vm = env.get_java_vm().c(d!())?;
+    let callback = env.new_global_ref(callback).c(d!())?;
+    thread::Builder::new()
+        .name("findora-network".to_owned())
+        .spawn(move || {
+            if let Err(e) = complete(&vm, &callback, f()) {
+                println!("{:?}", e);
+            }
+        })
+        .c(d!())
+        .map(|_| ())
+}
+
+fn complete(vm: &JavaVM, callback: &GlobalRef, res: Result<String>) -> Result<()> {
+    let env = vm.attach_current_thread().c(d!())?;
+    let (name, msg) = match res {
+        Ok(res) => ("onSuccess", res),
+        Err(e) => ("onError", e.to_string()),
+    };
+    let msg = env.new_string(msg).c(d!())?;
+    let res = env.call_method(
+        callback.as_obj(),
+        name,
+        "(Ljava/lang/String;)V",
+        &[JValue::Object(JObject::from(msg))],
+    );
+    if res.is_err() {
+        // nothing may catch an exception of this thread
+        let msg = take_exception(&env);
+        return Err(eg!(format!("callback failed in {}: {:?}", name, msg)));
+    }
+    Ok(())
+}
+
+#[no_mangle]
+/// # Safety
+///
+/// A client of the servers at `query` and `submission`, eg.
+/// `https://prod-mainnet.prod.findora.org:8668` and `...:8669`.
+/// The requests are sent through `transport`, if not null.
+/// The returned client must be released with `networkClientFree`.
+pub unsafe extern "system" fn Java_com_findora_JniApi_networkClientNew(
+    env: JNIEnv,
+    _: JClass,
+    query: JString,
+    submission: JString,
+    transport: JObject,
+) -> jlong {
+    let client = jni_try!(env, {
+        let query = input::string(env, query, "query server")?;
+        let submission = input::string(env, submission, "submission server")?;
+        Ok(NetworkClient::new(
+            Endpoints::new(&query, &submission),
+            new_transport(env, transport)?,
+        ))
+    });
+    handle::insert(client)
+}
+
+#[no_mangle]
+/// # Safety
+///
+/// Submit a serialized transaction, the callback is given its handle.
+pub unsafe extern "system" fn Java_com_findora_JniApi_networkSubmitTransaction(
+    env: JNIEnv,
+    _: JClass,
+    client: jlong,
+    tx: JString,
+    callback: JObject,
+) {
+    let res = CatchPanicImpl(|| {
+        let client = handle::get::<NetworkClient>(client)?;
+        let tx = input::string(env, tx, "transaction")?;
+        spawn(env, callback, move || client.submit_transaction(&tx))
+    });
+    let _ = ThrowExceptionImpl(env, res);
+}
+
+#[no_mangle]
+/// # Safety
+///
+/// Query the utxos of `address`, bech32 or base64,
+/// the callback is given a JSON array of `OwnedUtxo`.
+pub unsafe extern "system" fn Java_com_findora_JniApi_networkOwnedUtxos(
+    env: JNIEnv,
+    _: JClass,
+    client: jlong,
+    address: JString,
+    callback: JObject,
+) {
+    let res = CatchPanicImpl(|| {
+        let client = handle::get::<NetworkClient>(client)?;
+        let owner = input::public_key(env, address, "address")?;
+        spawn(env, callback, move || {
+            client
+                .owned_utxos(&owner)
+                .and_then(|utxos| serde_json::to_string(&utxos).c(d!()))
+        })
+    });
+    let _ = ThrowExceptionImpl(env, res);
+}
+
+#[no_mangle]
+/// # Safety
+///
+/// Query the owner memos of the utxos `sids`, a JSON array,
+/// the callback is given a JSON array of the memos, null for none.
+pub unsafe extern "system" fn Java_com_findora_JniApi_networkOwnerMemos(
+    env: JNIEnv,
+    _: JClass,
+    client: jlong,
+    sids: JString,
+    callback: JObject,
+) {
+    let res = CatchPanicImpl(|| {
+        let client = handle::get::<NetworkClient>(client)?;
+        let sids = input::json::<Vec<u64>>(env, sids, "sids")?;
+        spawn(env, callback, move || {
+            client
+                .owner_memos(&sids)
+                .and_then(|memos| serde_json::to_string(&memos).c(d!()))
+        })
+    });
+    let _ = ThrowExceptionImpl(env, res);
+}
+
+#[no_mangle]
+/// # Safety
+///
+/// Estimate the fee of `draft`, a JSON `DraftTx`, with the web3 endpoint `web3`
+/// of the node, eg. `https://prod-mainnet.prod.findora.org:8545`.
+/// The requests are sent through `transport`, if not null.
+/// The callback is given the JSON `FeeEstimate`.
+pub unsafe extern "system" fn Java_com_findora_JniApi_estimateFee(
+    env: JNIEnv,
+    _: JClass,
+    web3: JString,
+    draft: JString,
+    transport: JObject,
+    callback: JObject,
+) {
+    let res = CatchPanicImpl(|| {
+        let web3 = input::string(env, web3, "web3 endpoint")?;
+        let draft = input::json::<DraftTx>(env, draft, "draft")?;
+        let estimator = FeeEstimator::new(&web3, new_transport(env, transport)?);
+        spawn(env, callback, move || {
+            estimator
+                .estimate(&draft)
+                .and_then(|fee| serde_json::to_string(&fee).c(d!()))
+        })
+    });
+    let _ = ThrowExceptionImpl(env, res);
+}
+
+#[no_mangle]
+/// # Safety
+///
+/// Releases a client, `client` is invalid after this call.
+/// The pending requests still complete.
+pub unsafe extern "system" fn Java_com_findora_JniApi_networkClientFree(
+    env: JNIEnv,
+    _: JClass,
+    client: jlong,
+) {
+    let _ = ThrowExceptionImpl(env, handle::remove::<NetworkClient>(client));
+}
@@ -0,0 +1,161 @@
+//!
+//! The pending transaction queue, see `crate::rust::queue`.
+//!
+//! The statuses are returned as JSON arrays of `QueuedTx`.
+//!
+
+use super::exception::{CatchPanicImpl, ThrowExceptionImpl};
+use super::{handle, input};
+use crate::rust::queue::{unix_now, TxQueue};
+use jni::objects::{JClass, JString};
+use jni::sys::{jboolean, jlong, jstring, JNI_TRUE};
+use jni::JNIEnv;
+use ruc::*;
+
+#[no_mangle]
+/// # Safety
+///
+/// Open the queue saved at `path`, in the storage of the app.
+/// The returned queue must be released with `txQueueFree`.
+pub unsafe extern "system" fn Java_com_findora_JniApi_txQueueOpen(
+    env: JNIEnv,
+    _: JClass,
+    path: JString,
+) -> jlong {
+    let queue = jni_try!(env, {
+        let path = input::string(env, path, "path")?;
+        TxQueue::open(path)
+    });
+    handle::insert(queue)
+}
+
+#[no_mangle]
+/// # Safety
+///
+/// Queue a serialized transaction, returns its id.
+pub unsafe extern "system" fn Java_com_findora_JniApi_txQueueEnqueue(
+    env: JNIEnv,
+    _: JClass,
+    queue: jlong,
+    tx: JString,
+) -> jlong {
+    let id = jni_try!(env, {
+        let tx = input::string(env, tx, "transaction")?;
+        handle::with_mut(queue, |queue: &mut TxQueue| queue.enqueue(tx, unix_now()))?
+    });
+    id as jlong
+}
+
+#[no_mangle]
+/// # Safety
+///
+/// Report the connectivity of the app,
+/// the pending transactions are due as soon as it is back online.
+pub unsafe extern "system" fn Java_com_findora_JniApi_txQueueSetOnline(
+    env: JNIEnv,
+    _: JClass,
+    queue: jlong,
+    online: jboolean,
+) {
+    let _ = ThrowExceptionImpl(
+        env,
+        handle::with_mut(queue, |queue: &mut TxQueue| {
+            queue.set_online(online == JNI_TRUE, unix_now())
+        })
+        .and_then(|res| res),
+    );
+}
+
+#[no_mangle]
+/// # Safety
+///
+/// The transactions to submit now, each one must be reported by `txQueueReport`.
+pub unsafe extern "system" fn Java_com_findora_JniApi_txQueueDue(
+    env: JNIEnv,
+    _: JClass,
+    queue: jlong,
+) -> jstring {
+    let due = jni_try!(env, {
+        let due = handle::with_mut(queue, |queue: &mut TxQueue| queue.due(unix_now()))??;
+        serde_json::to_string(&due).c(d!())
+    });
+    let output = throw_exception!(env, env.new_string(due));
+    **output
+}
+
+#[no_mangle]
+/// # Safety
+///
+/// Record the outcome of the submission of the transaction `id`:
+/// its hash on success, or the error with a null `hash`.
+/// Returns the JSON status of the transaction.
+pub unsafe extern "system" fn Java_com_findora_JniApi_txQueueReport(
+    env: JNIEnv,
+    _: JClass,
+    queue: jlong,
+    id: jlong,
+    hash: JString,
+    error: JString,
+) -> jstring {
+    let status = jni_try!(env, {
+        let outcome = if hash.is_null() {
+            Err(input::string(env, error, "error")?)
+        } else {
+            Ok(input::string(env, hash, "hash")?)
+        };
+        let status = handle::with_mut(queue, |queue: &mut TxQueue| {
+            queue.report(id as u64, outcome, unix_now())
+        })??;
+        serde_json::to_string(&status).c(d!())
+    });
+    let output = throw_exception!(env, env.new_string(status));
+    **output
+}
+
+#[no_mangle]
+/// # Safety
+///
+/// All the queued transactions with their status, the oldest first.
+pub unsafe extern "system" fn Java_com_findora_JniApi_txQueueStatuses(
+    env: JNIEnv,
+    _: JClass,
+    queue: jlong,
+) -> jstring {
+    let statuses = jni_try!(env, {
+        handle::with(queue, |queue: &TxQueue| {
+            serde_json::to_string(queue.statuses())
+        })?
+        .c(d!())
+    });
+    let output = throw_exception!(env, env.new_string(statuses));
+    **output
+}
+
+#[no_mangle]
+/// # Safety
+///
+/// Drop the transaction `id` from the queue.
+pub unsafe extern "system" fn Java_com_findora_JniApi_txQueueRemove(
+    env: JNIEnv,
+    _: JClass,
+    queue: jlong,
+    id: jlong,
+) {
+    let _ = ThrowExceptionImpl(
+        env,
+        handle::with_mut(queue, |queue: &mut TxQueue| queue.remove(id as u64))
+            .and_then(|res| res),
+    );
+}
+
+#[no_mangle]
+/// # Safety
+///
+/// Releases a queue, `queue` is invalid after this call.
+pub unsafe extern "system" fn Java_com_findora_JniApi_txQueueFree(
+    env: JNIEnv,
+    _: JClass,
+    queue: jlong,
+) {
+    let _ = ThrowExceptionImpl(env, handle::remove::<TxQueue>(queue));
+}
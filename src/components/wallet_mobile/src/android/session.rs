@@ -0,0 +1,89 @@
+//!
+//! The secrets held for java, see `crate::rust::session`.
+//!
+//! The key pair handles are `Secret`s too, `secretSessionWipeAll` wipes them
+//! along with the mnemonics, and any later use of their handles throws.
+//!
+
+use super::exception::{CatchPanicImpl, ThrowExceptionImpl};
+use super::{handle, input};
+use crate::rust::rs_restore_keypair_from_mnemonic_path;
+use crate::rust::session::{Secret, SecretSession};
+use jni::objects::{JClass, JString};
+use jni::sys::{jint, jlong};
+use jni::JNIEnv;
+use ruc::*;
+
+#[no_mangle]
+/// # Safety
+///
+/// Keep a mnemonic, so java can drop its own copy.
+/// The returned handle must be released with `secretSessionRelease`.
+pub unsafe extern "system" fn Java_com_findora_JniApi_secretSessionAddMnemonic(
+    env: JNIEnv,
+    _: JClass,
+    phrase: JString,
+) -> jlong {
+    let phrase = throw_exception!(env, input::string(env, phrase, "phrase"));
+    handle::insert(SecretSession::global().add_mnemonic(phrase))
+}
+
+#[no_mangle]
+/// # Safety
+///
+/// Restore the XfrKeyPair from a kept mnemonic along a derivation path,
+/// see `restoreKeypairFromMnemonicPath`.
+/// @throws Will throw a `FindoraException` if the mnemonic has been wiped.
+pub unsafe extern "system" fn Java_com_findora_JniApi_secretSessionRestoreKeypair(
+    env: JNIEnv,
+    _: JClass,
+    mnemonic: jlong,
+    lang: JString,
+    path: JString,
+) -> jlong {
+    let keypair = jni_try!(env, {
+        let lang = input::string(env, lang, "language")?;
+        let path = input::string(env, path, "derivation path")?;
+        handle::with(mnemonic, |mnemonic: &Secret| {
+            mnemonic.with_mnemonic(|phrase| {
+                rs_restore_keypair_from_mnemonic_path(phrase, &lang, &path)
+            })
+        })?
+    });
+    handle::insert_keypair(&keypair)
+}
+
+#[no_mangle]
+/// # Safety
+///
+/// Release a kept mnemonic, or a key pair.
+pub unsafe extern "system" fn Java_com_findora_JniApi_secretSessionRelease(
+    env: JNIEnv,
+    _: JClass,
+    secret: jlong,
+) {
+    let _ = ThrowExceptionImpl(env, handle::remove::<Secret>(secret).map(drop));
+}
+
+#[no_mangle]
+/// # Safety
+///
+/// Zero all the key pairs and mnemonics, to call when the app is backgrounded.
+/// Returns how many were wiped, their handles must still be released.
+pub unsafe extern "system" fn Java_com_findora_JniApi_secretSessionWipeAll(
+    _env: JNIEnv,
+    _: JClass,
+) -> jint {
+    SecretSession::global().wipe_all() as jint
+}
+
+#[no_mangle]
+/// # Safety
+///
+/// The number of key pairs and mnemonics held, neither released nor wiped.
+pub unsafe extern "system" fn Java_com_findora_JniApi_secretSessionOutstanding(
+    _env: JNIEnv,
+    _: JClass,
+) -> jint {
+    SecretSession::global().outstanding() as jint
+}
@@ -0,0 +1,107 @@
+//!
+//! Signers implemented in java, eg. over the Android Keystore/StrongBox.
+//!
+//! A signer is an object of the interface `com.findora.Signer`:
+//!
+//! ```java
+//! public interface Signer {
+//!     // the 32 bytes ed25519 public key
+//!     byte[] publicKey();
+//!     // the 64 bytes ed25519 signature of `msg`
+//!     byte[] sign(byte[] msg);
+//! }
+//! ```
+//!
+//! A signer is registered by `signerRegister`, the returned handle is passed
+//! to the `*SignWith` functions in place of a key pair.
+//!
+
+use super::exception::{CatchPanicImpl, ThrowExceptionImpl};
+use super::handle;
+use crate::rust::signer::SigningProvider;
+use jni::objects::{GlobalRef, JClass, JObject, JValue};
+use jni::sys::jlong;
+use jni::{JNIEnv, JavaVM};
+use ruc::*;
+use std::sync::Arc;
+use zei::{noah_algebra::serialization::NoahFromToBytes, XfrPublicKey, XfrSignature};
+
+/// A java signer, shared as the handle table must not be locked during a call to java.
+pub(super) type SharedJniSigner = Arc<JniSigner>;
+
+pub(super) struct JniSigner {
+    vm: JavaVM,
+    signer: GlobalRef,
+}
+
+impl JniSigner {
+    pub(super) fn new(vm: JavaVM, signer: GlobalRef) -> SharedJniSigner {
+        Arc::new(JniSigner { vm, signer })
+    }
+
+    /// Call a method of the signer returning a `byte[]`.
+    fn call(&self, name: &str, sig: &str, msg: Option<&[u8]>) -> Result<Vec<u8>> {
+        let env = self.vm.attach_current_thread().c(d!())?;
+        let args = match msg {
+            Some(msg) => {
+                vec![JValue::Object(JObject::from(
+                    env.byte_array_from_slice(msg).c(d!())?,
+                ))]
+            }
+            None => vec![],
+        };
+        let res = env
+            .call_method(self.signer.as_obj(), name, sig, &args)
+            .and_then(|res| res.l())
+            .c(d!(format!("signer failed in {}", name)))?;
+        if res.is_null() {
+            return Err(eg!(format!("signer returned null in {}", name)));
+        }
+        env.convert_byte_array(res.into_raw()).c(d!())
+    }
+}
+
+impl SigningProvider for JniSigner {
+    fn public_key(&self) -> Result<XfrPublicKey> {
+        let bytes = self.call("publicKey", "()[B", None).c(d!())?;
+        XfrPublicKey::noah_from_bytes(&bytes).map_err(|e| eg!(format!("{:?}", e)))
+    }
+
+    fn sign(&self, msg: &[u8]) -> Result<XfrSignature> {
+        let bytes = self.call("sign", "([B)[B", Some(msg)).c(d!())?;
+        XfrSignature::noah_from_bytes(&bytes).map_err(|e| eg!(format!("{:?}", e)))
+    }
+}
+
+#[no_mangle]
+/// # Safety
+///
+/// Register a `com.findora.Signer`, returns its handle.
+/// The signer is kept alive until `signerFree`.
+pub unsafe extern "system" fn Java_com_findora_JniApi_signerRegister(
+    env: JNIEnv,
+    _: JClass,
+    signer: JObject,
+) -> jlong {
+    let signer = jni_try!(env, {
+        if signer.is_null() {
+            return Err(eg!("null signer"));
+        }
+        let vm = env.get_java_vm().c(d!())?;
+        let signer = env.new_global_ref(signer).c(d!())?;
+        Ok(JniSigner::new(vm, signer))
+    });
+    handle::insert(signer)
+}
+
+#[no_mangle]
+/// # Safety
+///
+/// Releases a signer, `signer` is invalid after this call.
+pub unsafe extern "system" fn Java_com_findora_JniApi_signerFree(
+    env: JNIEnv,
+    _: JClass,
+    signer: jlong,
+) {
+    let _ = ThrowExceptionImpl(env, handle::remove::<SharedJniSigner>(signer));
+}
@@ -3,15 +3,15 @@ use jni::objects::{JClass, JString};
 use jni::sys::{jboolean, jint, jlong, jstring, jvalue, JNI_TRUE};
 use jni::JNIEnv;
 use ledger::data_model::AssetType as PlatformAssetType;
+use ruc::{d, RucResult};
 use zei::{
-    noah_api::{
-        keys::{KeyPair, PublicKey},
-        xfr::structs::OwnerMemo as NoahOwnerMemo,
-    },
-    XfrKeyPair, XfrPublicKey,
+    noah_api::{keys::PublicKey, xfr::structs::OwnerMemo as NoahOwnerMemo},
+    XfrPublicKey,
 };
 
-use super::{jStringToString, parseU64};
+use super::exception::{CatchPanicImpl, ThrowExceptionImpl};
+use super::signer::SharedJniSigner;
+use super::{handle, input};
 
 #[no_mangle]
 /// # Safety
@@ -41,14 +41,12 @@ pub unsafe extern "system" fn Java_com_findora_JniApi_assetTypeFromJson(
     _: JClass,
     asset_type_json: JString,
 ) -> jlong {
-    let asset_type_json: String = env
-        .get_string(asset_type_json)
-        .expect("Couldn't get java string!")
-        .into();
-
-    let asset_type: PlatformAssetType =
-        serde_json::from_str(asset_type_json.as_str()).unwrap();
-    Box::into_raw(Box::new(AssetType::from_json(asset_type).unwrap())) as jlong
+    let asset_type = jni_try!(env, {
+        let asset_type: PlatformAssetType =
+            input::json(env, asset_type_json, "asset type")?;
+        AssetType::from_json(asset_type).c(d!())
+    });
+    Box::into_raw(Box::new(asset_type)) as jlong
 }
 
 #[no_mangle]
@@ -75,12 +73,10 @@ pub unsafe extern "system" fn Java_com_findora_JniApi_publicKeyFromBase64(
     _: JClass,
     pk: JString,
 ) -> jlong {
-    let pk: String = env
-        .get_string(pk)
-        .expect("Couldn't get java string!")
-        .into();
-
-    let key = rs_public_key_from_base64(pk.as_str()).unwrap();
+    let key = jni_try!(env, {
+        let pk = input::string(env, pk, "public key")?;
+        rs_public_key_from_base64(pk.trim())
+    });
     Box::into_raw(Box::new(key)) as jlong
 }
 
@@ -148,14 +144,11 @@ pub unsafe extern "system" fn Java_com_findora_JniApi_clientAssetRecordFromJson(
     _: JClass,
     val: JString,
 ) -> jlong {
-    let val: String = env
-        .get_string(val)
-        .expect("Couldn't get java string!")
-        .into();
-
-    Box::into_raw(Box::new(
-        ClientAssetRecord::from_json(val.as_str()).unwrap(),
-    )) as jlong
+    let record = jni_try!(env, {
+        let val = input::string(env, val, "asset record")?;
+        ClientAssetRecord::from_json(&val).c(d!())
+    });
+    Box::into_raw(Box::new(record)) as jlong
 }
 
 #[no_mangle]
@@ -189,13 +182,11 @@ pub unsafe extern "system" fn Java_com_findora_JniApi_ownerMemoFromJson(
     _: JClass,
     val: JString,
 ) -> jlong {
-    let val: String = env
-        .get_string(val)
-        .expect("Couldn't get java string!")
-        .into();
-
-    let noah_owner_memo: NoahOwnerMemo = serde_json::from_str(val.as_str()).unwrap();
-    Box::into_raw(Box::new(OwnerMemo::from_json(noah_owner_memo).unwrap())) as jlong
+    let owner_memo = jni_try!(env, {
+        let noah_owner_memo: NoahOwnerMemo = input::json(env, val, "owner memo")?;
+        OwnerMemo::from_json(noah_owner_memo).c(d!())
+    });
+    Box::into_raw(Box::new(owner_memo)) as jlong
 }
 
 #[no_mangle]
@@ -216,7 +207,7 @@ pub extern "system" fn Java_com_findora_JniApi_transferOperationBuilderNew(
     _env: JNIEnv,
     _: JClass,
 ) -> jlong {
-    Box::into_raw(Box::new(TransferOperationBuilder::new())) as jlong
+    handle::insert(TransferOperationBuilder::new())
 }
 
 #[no_mangle]
@@ -227,7 +218,8 @@ pub unsafe extern "system" fn Java_com_findora_JniApi_transferOperationBuilderDe
     _: JClass,
     builder: jlong,
 ) -> jstring {
-    let builder = &mut *(builder as *mut TransferOperationBuilder);
+    let builder =
+        throw_exception!(env, handle::get::<TransferOperationBuilder>(builder));
     let output = env
         .new_string(builder.debug())
         .expect("Couldn't create java string!");
@@ -268,7 +260,8 @@ pub unsafe extern "system" fn Java_com_findora_JniApi_transferOperationBuilderAd
     key_ptr: jlong,
     amount: JString,
 ) -> jlong {
-    let builder = &*(builder as *mut TransferOperationBuilder);
+    let builder =
+        throw_exception!(env, handle::get::<TransferOperationBuilder>(builder));
     let txo_ref = *(txo_ref_ptr as *mut TxoRef);
     let asset_record = &*(asset_record_ptr as *mut ClientAssetRecord);
     let owner_memo = if 0 == owner_memo_ptr {
@@ -278,21 +271,20 @@ pub unsafe extern "system" fn Java_com_findora_JniApi_transferOperationBuilderAd
         Some(memo.clone())
     };
     let tracing_policies = &*(tracing_policies_ptr as *mut TracingPolicies);
-    let key = &*(key_ptr as *mut KeyPair);
-    let amount = parseU64(env, amount);
+    let key = throw_exception!(env, handle::keypair(key_ptr));
 
-    let builder = builder
-        .clone()
-        .add_input_with_tracing(
+    let builder = jni_try!(env, {
+        let amount = input::amount(env, amount, "amount")?;
+        builder.add_input_with_tracing(
             txo_ref,
             asset_record.clone(),
             owner_memo,
             tracing_policies,
-            &XfrKeyPair::from_noah(key).unwrap(),
+            &key,
             amount,
         )
-        .unwrap();
-    Box::into_raw(Box::new(builder)) as jlong
+    });
+    handle::insert(builder)
 }
 
 #[no_mangle]
@@ -324,7 +316,8 @@ pub unsafe extern "system" fn Java_com_findora_JniApi_transferOperationBuilderAd
     key_ptr: jlong,
     amount: JString,
 ) -> jlong {
-    let builder = &*(builder as *mut TransferOperationBuilder);
+    let builder =
+        throw_exception!(env, handle::get::<TransferOperationBuilder>(builder));
     let txo_ref = *(txo_ref_ptr as *mut TxoRef);
     let asset_record = &*(asset_record_ptr as *mut ClientAssetRecord);
     let owner_memo = if 0 == owner_memo_ptr {
@@ -333,20 +326,13 @@ pub unsafe extern "system" fn Java_com_findora_JniApi_transferOperationBuilderAd
         let memo = &*(owner_memo_ptr as *mut OwnerMemo);
         Some(memo.clone())
     };
-    let key = &*(key_ptr as *mut KeyPair);
-    let amount = parseU64(env, amount);
+    let key = throw_exception!(env, handle::keypair(key_ptr));
 
-    let builder = builder
-        .clone()
-        .add_input_no_tracing(
-            txo_ref,
-            asset_record,
-            owner_memo,
-            &XfrKeyPair::from_noah(key).unwrap(),
-            amount,
-        )
-        .unwrap();
-    Box::into_raw(Box::new(builder)) as jlong
+    let builder = jni_try!(env, {
+        let amount = input::amount(env, amount, "amount")?;
+        builder.add_input_no_tracing(txo_ref, asset_record, owner_memo, &key, amount)
+    });
+    handle::insert(builder)
 }
 
 #[no_mangle]
@@ -378,24 +364,24 @@ pub unsafe extern "system" fn Java_com_findora_JniApi_transferOperationBuilderAd
     conf_amount: jboolean,
     conf_type: jboolean,
 ) -> jlong {
-    let builder = &*(builder as *mut TransferOperationBuilder);
+    let builder =
+        throw_exception!(env, handle::get::<TransferOperationBuilder>(builder));
     let tracing_policies = &*(tracing_policies_ptr as *mut TracingPolicies);
     let recipient = &*(recipient as *mut PublicKey);
-    let amount = parseU64(env, amount);
-    let code = jStringToString(env, code);
 
-    let builder = builder
-        .clone()
-        .add_output_with_tracing(
+    let builder = jni_try!(env, {
+        let amount = input::amount(env, amount, "amount")?;
+        let code = input::asset_code(env, code, "asset code")?;
+        builder.add_output_with_tracing(
             amount,
-            &XfrPublicKey::from_noah(recipient).unwrap(),
+            &XfrPublicKey::from_noah(recipient).c(d!())?,
             tracing_policies,
-            code,
+            code.to_base64(),
             conf_amount == JNI_TRUE,
             conf_type == JNI_TRUE,
         )
-        .unwrap();
-    Box::into_raw(Box::new(builder)) as jlong
+    });
+    handle::insert(builder)
 }
 
 #[no_mangle]
@@ -423,22 +409,22 @@ pub unsafe extern "system" fn Java_com_findora_JniApi_transferOperationBuilderAd
     conf_amount: jboolean,
     conf_type: jboolean,
 ) -> jlong {
-    let builder = &*(builder as *mut TransferOperationBuilder);
+    let builder =
+        throw_exception!(env, handle::get::<TransferOperationBuilder>(builder));
     let recipient = &*(recipient as *mut PublicKey);
-    let amount = parseU64(env, amount);
-    let code = jStringToString(env, code);
 
-    let builder = builder
-        .clone()
-        .add_output_no_tracing(
+    let builder = jni_try!(env, {
+        let amount = input::amount(env, amount, "amount")?;
+        let code = input::asset_code(env, code, "asset code")?;
+        builder.add_output_no_tracing(
             amount,
-            &XfrPublicKey::from_noah(recipient).unwrap(),
-            code,
+            &XfrPublicKey::from_noah(recipient).c(d!())?,
+            code.to_base64(),
             conf_amount == JNI_TRUE,
             conf_type == JNI_TRUE,
         )
-        .unwrap();
-    Box::into_raw(Box::new(builder)) as jlong
+    });
+    handle::insert(builder)
 }
 
 #[no_mangle]
@@ -455,7 +441,8 @@ pub unsafe extern "system" fn Java_com_findora_JniApi_transferOperationBuilderAd
     key_ptr: jlong,
     amount: JString,
 ) -> jlong {
-    let builder = &*(builder as *mut TransferOperationBuilder);
+    let builder =
+        throw_exception!(env, handle::get::<TransferOperationBuilder>(builder));
     let txo_ref = *(txo_ref_ptr as *mut TxoRef);
     let asset_record = &*(asset_record_ptr as *mut ClientAssetRecord);
     let owner_memo = if 0 == owner_memo_ptr {
@@ -470,20 +457,20 @@ pub unsafe extern "system" fn Java_com_findora_JniApi_transferOperationBuilderAd
         let policies = &*(tracing_policies_ptr as *mut TracingPolicies);
         Some(policies)
     };
-    let key = &*(key_ptr as *mut KeyPair);
+    let key = throw_exception!(env, handle::keypair(key_ptr));
 
-    let builder = builder
-        .clone()
-        .add_input(
+    let builder = jni_try!(env, {
+        let amount = input::amount(env, amount, "amount")?;
+        builder.add_input(
             txo_ref,
             asset_record,
             owner_memo,
             tracing_policies,
-            &XfrKeyPair::from_noah(key).unwrap(),
-            parseU64(env, amount),
+            &key,
+            amount,
         )
-        .unwrap();
-    Box::into_raw(Box::new(builder)) as jlong
+    });
+    handle::insert(builder)
 }
 
 #[no_mangle]
@@ -500,7 +487,8 @@ pub unsafe extern "system" fn Java_com_findora_JniApi_transferOperationBuilderAd
     conf_amount: jboolean,
     conf_type: jboolean,
 ) -> jlong {
-    let builder = &*(builder as *mut TransferOperationBuilder);
+    let builder =
+        throw_exception!(env, handle::get::<TransferOperationBuilder>(builder));
     let tracing_policies = if 0 == tracing_policies_ptr {
         None
     } else {
@@ -508,23 +496,20 @@ pub unsafe extern "system" fn Java_com_findora_JniApi_transferOperationBuilderAd
         Some(policies)
     };
     let recipient = &*(recipient as *mut PublicKey);
-    let code: String = env
-        .get_string(code)
-        .expect("Couldn't get java string!")
-        .into();
-
-    let builder = builder
-        .clone()
-        .add_output(
-            parseU64(env, amount),
-            &XfrPublicKey::from_noah(&recipient).unwrap(),
+
+    let builder = jni_try!(env, {
+        let amount = input::amount(env, amount, "amount")?;
+        let code = input::asset_code(env, code, "asset code")?;
+        builder.add_output(
+            amount,
+            &XfrPublicKey::from_noah(recipient).c(d!())?,
             tracing_policies,
-            code,
+            code.to_base64(),
             conf_amount == JNI_TRUE,
             conf_type == JNI_TRUE,
         )
-        .unwrap();
-    Box::into_raw(Box::new(builder)) as jlong
+    });
+    handle::insert(builder)
 }
 
 #[no_mangle]
@@ -534,12 +519,13 @@ pub unsafe extern "system" fn Java_com_findora_JniApi_transferOperationBuilderAd
 /// @throws Will throw an error if the transaction cannot be balanced.
 /// @returns {TransferOperationBuilder}
 pub unsafe extern "system" fn Java_com_findora_JniApi_transferOperationBuilderBalance(
-    _env: JNIEnv,
+    env: JNIEnv,
     _: JClass,
     builder: jlong,
 ) -> jlong {
-    let builder = &*(builder as *mut TransferOperationBuilder);
-    Box::into_raw(Box::new(builder.clone().balance(None).unwrap())) as jlong
+    let builder =
+        throw_exception!(env, handle::get::<TransferOperationBuilder>(builder));
+    handle::insert(builder.balance(None).unwrap())
 }
 
 #[no_mangle]
@@ -549,12 +535,35 @@ pub unsafe extern "system" fn Java_com_findora_JniApi_transferOperationBuilderBa
 /// @throws Will throw an error if not all record owners have signed the transaction.
 /// @returns {TransferOperationBuilder}
 pub unsafe extern "system" fn Java_com_findora_JniApi_transferOperationBuilderCreate(
-    _env: JNIEnv,
+    env: JNIEnv,
     _: JClass,
     builder: jlong,
 ) -> jlong {
-    let builder = &*(builder as *mut TransferOperationBuilder);
-    Box::into_raw(Box::new(builder.clone().create().unwrap())) as jlong
+    let builder =
+        throw_exception!(env, handle::get::<TransferOperationBuilder>(builder));
+    handle::insert(builder.create().unwrap())
+}
+
+#[no_mangle]
+/// # Safety
+/// Wraps around TransferOperationBuilder to add a signature to the operation,
+/// made by a signer registered by `signerRegister`.
+///
+/// The signing key never enters the library.
+///
+/// @returns {TransferOperationBuilder}
+pub unsafe extern "system" fn Java_com_findora_JniApi_transferOperationBuilderSignWith(
+    env: JNIEnv,
+    _: JClass,
+    builder: jlong,
+    signer: jlong,
+) -> jlong {
+    let builder = jni_try!(env, {
+        let builder = handle::get::<TransferOperationBuilder>(builder)?;
+        let signer = handle::get::<SharedJniSigner>(signer)?;
+        builder.sign_with(&*signer)
+    });
+    handle::insert(builder)
 }
 
 #[no_mangle]
@@ -567,20 +576,16 @@ pub unsafe extern "system" fn Java_com_findora_JniApi_transferOperationBuilderCr
 /// @param {XfrKeyPair} kp
 /// @returns {TransferOperationBuilder}
 pub unsafe extern "system" fn Java_com_findora_JniApi_transferOperationBuilderSign(
-    _env: JNIEnv,
+    env: JNIEnv,
     _: JClass,
     builder: jlong,
     key_ptr: jlong,
 ) -> jlong {
-    let builder = &*(builder as *mut TransferOperationBuilder);
-    let key = &*(key_ptr as *mut KeyPair);
-
-    Box::into_raw(Box::new(
-        builder
-            .clone()
-            .sign(&XfrKeyPair::from_noah(&key).unwrap())
-            .unwrap(),
-    )) as jlong
+    let builder =
+        throw_exception!(env, handle::get::<TransferOperationBuilder>(builder));
+    let key = throw_exception!(env, handle::keypair(key_ptr));
+
+    handle::insert(builder.sign(&key).unwrap())
 }
 
 #[no_mangle]
@@ -591,7 +596,8 @@ pub unsafe extern "system" fn Java_com_findora_JniApi_transferOperationBuilderBu
     _: JClass,
     builder: jlong,
 ) -> jstring {
-    let builder = &*(builder as *mut TransferOperationBuilder);
+    let builder =
+        throw_exception!(env, handle::get::<TransferOperationBuilder>(builder));
     let output = env
         .new_string(builder.builder())
         .expect("Couldn't create java string!");
@@ -606,13 +612,25 @@ pub unsafe extern "system" fn Java_com_findora_JniApi_transferOperationBuilderTr
     _: JClass,
     builder: jlong,
 ) -> jstring {
-    let builder = &*(builder as *mut TransferOperationBuilder);
+    let builder =
+        throw_exception!(env, handle::get::<TransferOperationBuilder>(builder));
     let output = env
         .new_string(builder.transaction().unwrap())
         .expect("Couldn't create java string!");
     **output
 }
 
+#[no_mangle]
+/// # Safety
+/// Releases a transfer operation builder, `builder` is invalid after this call.
+pub unsafe extern "system" fn Java_com_findora_JniApi_transferOperationBuilderFree(
+    env: JNIEnv,
+    _: JClass,
+    builder: jlong,
+) {
+    let _ = ThrowExceptionImpl(env, handle::remove::<TransferOperationBuilder>(builder));
+}
+
 #[no_mangle]
 /// # Safety
 /// Fee smaller than this value will be denied.
@@ -1,26 +1,25 @@
-use super::parseU64;
+use super::exception::{CatchPanicImpl, ThrowExceptionImpl};
+use super::signer::SharedJniSigner;
+use super::{handle, input};
+use crate::rust::anon;
 use crate::rust::*;
 use jni::objects::{JClass, JString};
 use jni::sys::{jboolean, jint, jlong, jstring, JNI_TRUE};
 use jni::JNIEnv;
-use ledger::data_model::AssetTypeCode;
-use zei::XfrKeyPair;
+use ruc::{d, RucResult};
 #[no_mangle]
 /// # Safety
 /// @param kp: owner's KeyPair
 pub unsafe extern "system" fn Java_com_findora_JniApi_transactionBuilderAddFeeRelativeAuto(
-    _env: JNIEnv,
+    env: JNIEnv,
     _: JClass,
     builder: jlong,
     kp: jlong,
 ) -> jlong {
-    let builder = &*(builder as *mut TransactionBuilder);
-    let kp = &*(kp as *mut KeyPair);
-    let builder = builder
-        .clone()
-        .add_fee_relative_auto(XfrKeyPair::from_noah(kp).unwrap())
-        .unwrap();
-    Box::into_raw(Box::new(builder)) as jlong
+    let builder = throw_exception!(env, handle::get::<TransactionBuilder>(builder));
+    let kp = throw_exception!(env, handle::keypair(kp));
+    let builder = builder.add_fee_relative_auto(kp).unwrap();
+    handle::insert(builder)
 }
 
 // /// Use this func to get the necessary infomations for generating `Relative Inputs`
@@ -47,15 +46,15 @@ pub unsafe extern "system" fn Java_com_findora_JniApi_transactionBuilderAddFeeRe
 /// As the last operation of any transaction,
 /// add a static fee to the transaction.
 pub unsafe extern "system" fn Java_com_findora_JniApi_transactionBuilderAddFee(
-    _env: JNIEnv,
+    env: JNIEnv,
     _: JClass,
     builder: jlong,
     inputs: jlong,
 ) -> jlong {
-    let builder = &*(builder as *mut TransactionBuilder);
+    let builder = throw_exception!(env, handle::get::<TransactionBuilder>(builder));
     let inputs = &*(inputs as *mut FeeInputs);
-    let builder = builder.clone().add_fee(inputs.clone()).unwrap();
-    Box::into_raw(Box::new(builder)) as jlong
+    let builder = builder.add_fee(inputs.clone()).unwrap();
+    handle::insert(builder)
 }
 
 #[no_mangle]
@@ -64,11 +63,11 @@ pub unsafe extern "system" fn Java_com_findora_JniApi_transactionBuilderAddFee(
 ///
 /// SEE [check_fee](ledger::data_model::Transaction::check_fee)
 pub unsafe extern "system" fn Java_com_findora_JniApi_transactionBuilderCheckFee(
-    _env: JNIEnv,
+    env: JNIEnv,
     _: JClass,
     builder: jlong,
 ) -> jboolean {
-    let builder = &*(builder as *mut TransactionBuilder);
+    let builder = throw_exception!(env, handle::get::<TransactionBuilder>(builder));
     builder.check_fee() as jboolean
 }
 
@@ -81,7 +80,7 @@ pub unsafe extern "system" fn Java_com_findora_JniApi_transactionBuilderNew(
     _: JClass,
     seq_id: jint,
 ) -> jlong {
-    Box::into_raw(Box::new(TransactionBuilder::new(seq_id as u64))) as jlong
+    handle::insert(TransactionBuilder::new(seq_id as u64))
 }
 
 #[no_mangle]
@@ -109,27 +108,24 @@ pub unsafe extern "system" fn Java_com_findora_JniApi_transactionBuilderAddOpera
     token_code: JString,
     asset_rules: jlong,
 ) -> jlong {
-    let builder = &*(builder as *mut TransactionBuilder);
-    let key_pair = &*(key_pair as *mut KeyPair);
-    let memo: String = env
-        .get_string(memo)
-        .expect("Couldn't get java string!")
-        .into();
-    let token_code: String = env
-        .get_string(token_code)
-        .expect("Couldn't get java string!")
-        .into();
+    let builder = throw_exception!(env, handle::get::<TransactionBuilder>(builder));
+    let key_pair = throw_exception!(env, handle::keypair(key_pair));
     let asset_rules = &*(asset_rules as *mut AssetRules);
-    let builder = builder
-        .clone()
-        .add_operation_create_asset(
-            &XfrKeyPair::from_noah(key_pair).unwrap(),
+    let builder = jni_try!(env, {
+        let memo = input::string(env, memo, "memo")?;
+        let token_code = input::opt_string(env, token_code, "token code")?
+            .map(|_| input::asset_code(env, token_code, "token code"))
+            .transpose()?
+            .map(|code| code.to_base64())
+            .unwrap_or_default();
+        builder.add_operation_create_asset(
+            &key_pair,
             memo,
             token_code,
             asset_rules.clone(),
         )
-        .unwrap();
-    Box::into_raw(Box::new(builder)) as jlong
+    });
+    handle::insert(builder)
 }
 
 #[no_mangle]
@@ -154,23 +150,20 @@ pub unsafe extern "system" fn Java_com_findora_JniApi_transactionBuilderAddBasic
     amount: JString,
     conf_amount: jboolean,
 ) -> jlong {
-    let builder = &*(builder as *mut TransactionBuilder);
-    let key_pair = &*(key_pair as *mut KeyPair);
-    let code: String = env
-        .get_string(code)
-        .expect("Couldn't get java string!")
-        .into();
-    let builder = builder
-        .clone()
-        .add_basic_issue_asset(
-            &XfrKeyPair::from_noah(key_pair).unwrap(),
-            code,
+    let builder = throw_exception!(env, handle::get::<TransactionBuilder>(builder));
+    let key_pair = throw_exception!(env, handle::keypair(key_pair));
+    let builder = jni_try!(env, {
+        let code = input::asset_code(env, code, "asset code")?;
+        let amount = input::amount(env, amount, "amount")?;
+        builder.add_basic_issue_asset(
+            &key_pair,
+            code.to_base64(),
             seq_num as u64,
-            parseU64(env, amount),
+            amount,
             conf_amount == JNI_TRUE,
         )
-        .unwrap();
-    Box::into_raw(Box::new(builder)) as jlong
+    });
+    handle::insert(builder)
 }
 
 #[no_mangle]
@@ -191,25 +184,14 @@ pub unsafe extern "system" fn Java_com_findora_JniApi_transactionBuilderAddOpera
     code: JString,
     new_memo: JString,
 ) -> jlong {
-    let builder = &*(builder as *mut TransactionBuilder);
-    let auth_key_pair = &*(auth_key_pair as *mut KeyPair);
-    let code: String = env
-        .get_string(code)
-        .expect("Couldn't get java string!")
-        .into();
-    let new_memo: String = env
-        .get_string(new_memo)
-        .expect("Couldn't get java string!")
-        .into();
-    let builder = builder
-        .clone()
-        .add_operation_update_memo(
-            &XfrKeyPair::from_noah(auth_key_pair).unwrap(),
-            code,
-            new_memo,
-        )
-        .unwrap();
-    Box::into_raw(Box::new(builder)) as jlong
+    let builder = throw_exception!(env, handle::get::<TransactionBuilder>(builder));
+    let auth_key_pair = throw_exception!(env, handle::keypair(auth_key_pair));
+    let builder = jni_try!(env, {
+        let code = input::asset_code(env, code, "asset code")?;
+        let new_memo = input::string(env, new_memo, "memo")?;
+        builder.add_operation_update_memo(&auth_key_pair, code.to_base64(), new_memo)
+    });
+    handle::insert(builder)
 }
 
 #[no_mangle]
@@ -223,40 +205,29 @@ pub unsafe extern "system" fn Java_com_findora_JniApi_transactionBuilderAddOpera
     amount: JString,
     validator: JString,
 ) -> jlong {
-    let builder = &*(builder as *mut TransactionBuilder);
-    let keypair = &*(keypair as *mut KeyPair);
-    let validator: String = env
-        .get_string(validator)
-        .expect("Couldn't get java string!")
-        .into();
-
-    let builder = builder
-        .clone()
-        .add_operation_delegate(
-            &XfrKeyPair::from_noah(keypair).unwrap(),
-            parseU64(env, amount),
-            validator,
-        )
-        .unwrap();
-    Box::into_raw(Box::new(builder)) as jlong
+    let builder = throw_exception!(env, handle::get::<TransactionBuilder>(builder));
+    let keypair = throw_exception!(env, handle::keypair(keypair));
+    let builder = jni_try!(env, {
+        let amount = input::amount(env, amount, "amount")?;
+        let validator = input::validator(env, validator, "validator")?;
+        builder.add_operation_delegate(&keypair, amount, validator)
+    });
+    handle::insert(builder)
 }
 
 #[no_mangle]
 /// # Safety
 ///
 pub unsafe extern "system" fn Java_com_findora_JniApi_transactionBuilderAddOperationUndelegate(
-    _env: JNIEnv,
+    env: JNIEnv,
     _: JClass,
     builder: jlong,
     keypair: jlong,
 ) -> jlong {
-    let builder = &*(builder as *mut TransactionBuilder);
-    let keypair = &*(keypair as *mut KeyPair);
-    let builder = builder
-        .clone()
-        .add_operation_undelegate(&XfrKeyPair::from_noah(keypair).unwrap())
-        .unwrap();
-    Box::into_raw(Box::new(builder)) as jlong
+    let builder = throw_exception!(env, handle::get::<TransactionBuilder>(builder));
+    let keypair = throw_exception!(env, handle::keypair(keypair));
+    let builder = throw_exception!(env, builder.add_operation_undelegate(&keypair));
+    handle::insert(builder)
 }
 
 #[no_mangle]
@@ -270,22 +241,15 @@ pub unsafe extern "system" fn Java_com_findora_JniApi_transactionBuilderAddOpera
     am: JString,
     validator: JString,
 ) -> jlong {
-    let builder = &*(builder as *mut TransactionBuilder);
-    let keypair = &*(keypair as *mut KeyPair);
+    let builder = throw_exception!(env, handle::get::<TransactionBuilder>(builder));
+    let keypair = throw_exception!(env, handle::keypair(keypair));
 
-    let validator: String = env
-        .get_string(validator)
-        .expect("Couldn't get java string!")
-        .into();
-    let builder = builder
-        .clone()
-        .add_operation_undelegate_partially(
-            &XfrKeyPair::from_noah(keypair).unwrap(),
-            parseU64(env, am),
-            validator,
-        )
-        .unwrap();
-    Box::into_raw(Box::new(builder)) as jlong
+    let builder = jni_try!(env, {
+        let am = input::amount(env, am, "amount")?;
+        let validator = input::validator(env, validator, "validator")?;
+        builder.add_operation_undelegate_partially(&keypair, am, validator)
+    });
+    handle::insert(builder)
 }
 
 #[no_mangle]
@@ -298,19 +262,13 @@ pub unsafe extern "system" fn Java_com_findora_JniApi_transactionBuilderAddOpera
     td_addr: JString,
     keypair: jlong,
 ) -> jlong {
-    let builder = &*(builder as *mut TransactionBuilder);
-    let td_addr: String = env
-        .get_string(td_addr)
-        .expect("Couldn't get java string!")
-        .into();
-    let addr = td_addr.strip_prefix("0x").unwrap_or(&td_addr);
-    let td_address = hex::decode(addr).expect("addr format error!");
-    let keypair = &*(keypair as *mut XfrKeyPair);
-    let builder = builder
-        .clone()
-        .add_operation_claim(td_address, keypair)
-        .unwrap();
-    Box::into_raw(Box::new(builder)) as jlong
+    let builder = throw_exception!(env, handle::get::<TransactionBuilder>(builder));
+    let keypair = throw_exception!(env, handle::keypair(keypair));
+    let builder = jni_try!(env, {
+        let td_address = input::hex_bytes(env, td_addr, "validator")?;
+        builder.add_operation_claim(td_address, &keypair)
+    });
+    handle::insert(builder)
 }
 
 #[no_mangle]
@@ -324,19 +282,38 @@ pub unsafe extern "system" fn Java_com_findora_JniApi_transactionBuilderAddOpera
     keypair: jlong,
     am: JString,
 ) -> jlong {
-    let builder = &*(builder as *mut TransactionBuilder);
-    let keypair = &*(keypair as *mut KeyPair);
-    let td_addr: String = env
-        .get_string(td_addr)
-        .expect("Couldn't get java string!")
-        .into();
-    let addr = td_addr.strip_prefix("0x").unwrap_or(&td_addr);
-    let td_address = hex::decode(addr).expect("addr format error!");
-    let builder = builder
-        .clone()
-        .add_operation_claim_custom(td_address, keypair, parseU64(env, am))
-        .unwrap();
-    Box::into_raw(Box::new(builder)) as jlong
+    let builder = throw_exception!(env, handle::get::<TransactionBuilder>(builder));
+    let keypair = throw_exception!(env, handle::keypair(keypair));
+    let builder = jni_try!(env, {
+        let td_address = input::hex_bytes(env, td_addr, "validator")?;
+        let am = input::amount(env, am, "amount")?;
+        builder.add_operation_claim_custom(td_address, &keypair, am)
+    });
+    handle::insert(builder)
+}
+
+#[no_mangle]
+/// # Safety
+///
+/// Claims the rewards of `keypair` from `validator`, a tendermint address:
+/// `am` FRA units of them, or all of them if `am` is "0".
+/// Throws if the validator address is invalid.
+pub unsafe extern "system" fn Java_com_findora_JniApi_transactionBuilderAddOperationClaimRewards(
+    env: JNIEnv,
+    _: JClass,
+    builder: jlong,
+    keypair: jlong,
+    validator: JString,
+    am: JString,
+) -> jlong {
+    let builder = jni_try!(env, {
+        let builder = handle::get::<TransactionBuilder>(builder)?;
+        let keypair = handle::keypair(keypair)?;
+        let validator = input::validator(env, validator, "validator")?;
+        let am = input::amount(env, am, "amount")?;
+        builder.add_operation_claim_rewards(&keypair, &validator, am)
+    });
+    handle::insert(builder)
 }
 
 #[no_mangle]
@@ -352,13 +329,12 @@ pub unsafe extern "system" fn Java_com_findora_JniApi_transactionBuilderAddTrans
     builder: jlong,
     op: JString,
 ) -> jlong {
-    let builder = &*(builder as *mut TransactionBuilder);
-    let op: String = env
-        .get_string(op)
-        .expect("Couldn't get java string!")
-        .into();
-    let builder = builder.clone().add_transfer_operation(op).unwrap();
-    Box::into_raw(Box::new(builder)) as jlong
+    let builder = throw_exception!(env, handle::get::<TransactionBuilder>(builder));
+    let builder = jni_try!(env, {
+        let op = input::string(env, op, "transfer operation")?;
+        builder.add_transfer_operation(op).c(d!())
+    });
+    handle::insert(builder)
 }
 
 #[no_mangle]
@@ -379,63 +355,161 @@ pub unsafe extern "system" fn Java_com_findora_JniApi_transactionBuilderAddOpera
     asset: JString,
     lowlevel_data: JString,
 ) -> jlong {
-    let builder = &*(builder as *mut TransactionBuilder);
-    let addr: String = env
-        .get_string(address)
-        .expect("Couldn't get java string!")
-        .into();
+    let builder = throw_exception!(env, handle::get::<TransactionBuilder>(builder));
+    let fra_kp = throw_exception!(env, handle::keypair(keypair));
 
-    let fra_kp = &*(keypair as *mut KeyPair);
-
-    let asset_str: String = env
-        .get_string(asset)
-        .expect("Couldn't get java string!")
-        .into();
-    let asset = if asset_str.is_empty() {
-        None
-    } else {
-        Some(AssetTypeCode::new_from_base64(&asset_str).unwrap())
-    };
-
-    let lowlevel_data_str: String = env
-        .get_string(lowlevel_data)
-        .expect("Couldn't get java string!")
-        .into();
-    let lowlevel_data = if lowlevel_data_str.is_empty() {
-        None
-    } else {
-        Some(hex::decode(lowlevel_data_str).unwrap())
-    };
-
-    let builder = builder
-        .clone()
-        .add_transfer_to_account_operation(
-            parseU64(env, amount),
+    let builder = jni_try!(env, {
+        let amount = input::amount(env, amount, "amount")?;
+        let addr = input::string(env, address, "address")?;
+        let asset = input::opt_string(env, asset, "asset")?
+            .map(|_| input::asset_code(env, asset, "asset"))
+            .transpose()?;
+        let lowlevel_data = input::opt_string(env, lowlevel_data, "lowlevel data")?
+            .map(|_| input::hex_bytes(env, lowlevel_data, "lowlevel data"))
+            .transpose()?;
+        builder.add_transfer_to_account_operation(
+            amount,
             Some(addr),
-            &XfrKeyPair::from_noah(fra_kp).unwrap(),
+            &fra_kp,
             asset,
             lowlevel_data,
         )
-        .unwrap();
-    Box::into_raw(Box::new(builder)) as jlong
+    });
+    handle::insert(builder)
+}
+
+#[no_mangle]
+/// # Safety
+/// As the last operation of BarToAbar transaction,
+/// add a static fee to the transaction.
+pub unsafe extern "system" fn Java_com_findora_JniApi_transactionBuilderAddFeeBarToAbar(
+    env: JNIEnv,
+    _: JClass,
+    builder: jlong,
+    inputs: jlong,
+) -> jlong {
+    let builder = jni_try!(env, {
+        let builder = handle::get::<TransactionBuilder>(builder)?;
+        let inputs = &*(inputs as *mut FeeInputs);
+        builder.add_fee_bar_to_abar(inputs.clone())
+    });
+    handle::insert(builder)
+}
+
+#[no_mangle]
+/// # Safety
+/// Adds an operation converting a record to an ABAR.
+/// @param {string} seed - hex of 32 random bytes, generating the proof.
+/// @param {XfrKeyPair} auth_key_pair - owner of the record.
+/// @param {string} abar_pubkey - receiver of the ABAR, bech32 or base64.
+/// @param {long} txo_sid - sid of the record.
+/// @param {string} input_record - JSON `ClientAssetRecord` of the record.
+/// @param {string} owner_memo - JSON owner memo of the record, null for none.
+pub unsafe extern "system" fn Java_com_findora_JniApi_transactionBuilderAddOperationBarToAbar(
+    env: JNIEnv,
+    _: JClass,
+    builder: jlong,
+    seed: JString,
+    auth_key_pair: jlong,
+    abar_pubkey: JString,
+    txo_sid: jlong,
+    input_record: JString,
+    owner_memo: JString,
+) -> jlong {
+    let builder = jni_try!(env, {
+        let builder = handle::get::<TransactionBuilder>(builder)?;
+        let auth_key_pair = handle::keypair(auth_key_pair)?;
+        let seed = input::string(env, seed, "seed")?;
+        let abar_pubkey = input::public_key(env, abar_pubkey, "ABAR public key")?;
+        let input_record = input::string(env, input_record, "input record")?;
+        let input_record = ClientAssetRecord::from_json(&input_record)?;
+        let owner_memo = if owner_memo.is_null() {
+            None
+        } else {
+            Some(OwnerMemo::from_json(input::json(
+                env,
+                owner_memo,
+                "owner memo",
+            )?)?)
+        };
+        builder.add_operation_bar_to_abar(
+            &seed,
+            &auth_key_pair,
+            &abar_pubkey,
+            txo_sid as u64,
+            &input_record,
+            owner_memo,
+        )
+    });
+    handle::insert(builder)
+}
+
+#[no_mangle]
+/// # Safety
+/// Adds an operation converting an ABAR to a record.
+/// @param {string} input - JSON `AnonInput` of the ABAR, with its Merkle proof.
+/// @param {XfrKeyPair} from_keypair - owner of the ABAR.
+/// @param {string} recipient - receiver of the record, bech32 or base64.
+/// @param {boolean} conf_amount - whether the amount of the record is confidential.
+/// @param {boolean} conf_type - whether the asset type of the record is confidential.
+pub unsafe extern "system" fn Java_com_findora_JniApi_transactionBuilderAddOperationAbarToBar(
+    env: JNIEnv,
+    _: JClass,
+    builder: jlong,
+    input: JString,
+    from_keypair: jlong,
+    recipient: JString,
+    conf_amount: jboolean,
+    conf_type: jboolean,
+) -> jlong {
+    let builder = jni_try!(env, {
+        let builder = handle::get::<TransactionBuilder>(builder)?;
+        let from_keypair = handle::keypair(from_keypair)?;
+        let input = input::json::<anon::AnonInput>(env, input, "ABAR")?;
+        let recipient = input::public_key(env, recipient, "recipient")?;
+        builder.add_operation_abar_to_bar(
+            &input,
+            &from_keypair,
+            &recipient,
+            conf_amount == JNI_TRUE,
+            conf_type == JNI_TRUE,
+        )
+    });
+    handle::insert(builder)
+}
+
+#[no_mangle]
+/// # Safety
+///
+/// The base58 commitments of the ABARs created by the transaction, as a JSON array.
+pub unsafe extern "system" fn Java_com_findora_JniApi_transactionBuilderGetCommitments(
+    env: JNIEnv,
+    _: JClass,
+    builder: jlong,
+) -> jstring {
+    let commitments = jni_try!(env, {
+        let commitments = handle::with(builder, |builder: &TransactionBuilder| {
+            builder.get_commitments()
+        })?;
+        serde_json::to_string(&commitments).c(d!())
+    });
+    let output = throw_exception!(env, env.new_string(commitments));
+    **output
 }
 
 #[no_mangle]
 /// # Safety
 ///
 pub unsafe extern "system" fn Java_com_findora_JniApi_transactionBuilderSign(
-    _env: JNIEnv,
+    env: JNIEnv,
     _: JClass,
     builder: jlong,
     kp: jlong,
 ) -> jlong {
-    let builder = &*(builder as *mut TransactionBuilder);
-    let kp = &*(kp as *mut KeyPair);
-    let builder = builder
-        .clone()
-        .sign(&XfrKeyPair::from_noah(kp).unwrap())
-        .unwrap();
-    Box::into_raw(Box::new(builder)) as jlong
+    let builder = throw_exception!(env, handle::get::<TransactionBuilder>(builder));
+    let kp = throw_exception!(env, handle::keypair(kp));
+    let builder = builder.sign(&kp).unwrap();
+    handle::insert(builder)
 }
 
 #[no_mangle]
@@ -447,13 +521,50 @@ pub unsafe extern "system" fn Java_com_findora_JniApi_transactionBuilderTransact
     _: JClass,
     builder: jlong,
 ) -> jstring {
-    let builder = &mut *(builder as *mut TransactionBuilder);
-    let output = env
-        .new_string(builder.transaction())
-        .expect("Couldn't create java string!");
+    let tx = throw_exception!(
+        env,
+        handle::with_mut(builder, |builder: &mut TransactionBuilder| {
+            builder.transaction()
+        })
+    );
+    let output = env.new_string(tx).expect("Couldn't create java string!");
+    **output
+}
+
+#[no_mangle]
+/// # Safety
+///
+/// Saves the builder in a versioned format, loadable by later releases.
+pub unsafe extern "system" fn Java_com_findora_JniApi_transactionBuilderToPortable(
+    env: JNIEnv,
+    _: JClass,
+    builder: jlong,
+) -> jstring {
+    let builder = throw_exception!(env, handle::get::<TransactionBuilder>(builder));
+    let portable = jni_try!(env, {
+        let bytes = builder.to_portable_bytes()?;
+        String::from_utf8(bytes).c(d!())
+    });
+    let output = throw_exception!(env, env.new_string(portable));
     **output
 }
 
+#[no_mangle]
+/// # Safety
+///
+/// Loads a builder saved by `transactionBuilderToPortable`.
+pub unsafe extern "system" fn Java_com_findora_JniApi_transactionBuilderFromPortable(
+    env: JNIEnv,
+    _: JClass,
+    portable: JString,
+) -> jlong {
+    let builder = jni_try!(env, {
+        let portable = input::string(env, portable, "portable builder")?;
+        TransactionBuilder::from_portable_bytes(portable.as_bytes())
+    });
+    handle::insert(builder)
+}
+
 #[no_mangle]
 /// # Safety
 ///
@@ -463,25 +574,44 @@ pub unsafe extern "system" fn Java_com_findora_JniApi_transactionBuilderTransact
     _: JClass,
     builder: jlong,
 ) -> jstring {
-    let builder = &*(builder as *mut TransactionBuilder);
+    let builder = throw_exception!(env, handle::get::<TransactionBuilder>(builder));
     let output = env
         .new_string(builder.transaction_handle())
         .expect("Couldn't create java string!");
     **output
 }
 
+#[no_mangle]
+/// # Safety
+///
+/// Preview of the operations, amounts, recipients and fee of the transaction as JSON,
+/// for the user to confirm what will be signed.
+pub unsafe extern "system" fn Java_com_findora_JniApi_transactionBuilderDescribe(
+    env: JNIEnv,
+    _: JClass,
+    builder: jlong,
+) -> jstring {
+    let description = throw_exception!(
+        env,
+        handle::with(builder, |builder: &TransactionBuilder| builder.describe())
+    );
+    let description = throw_exception!(env, serde_json::to_string(&description));
+    let output = throw_exception!(env, env.new_string(description));
+    **output
+}
+
 #[no_mangle]
 /// # Safety
 ///
 /// Fetches a client record from a transaction.
 /// @param {number} idx - Record to fetch. Records are added to the transaction builder sequentially.
 pub unsafe extern "system" fn Java_com_findora_JniApi_transactionBuilderGetOwnerRecord(
-    _env: JNIEnv,
+    env: JNIEnv,
     _: JClass,
     builder: jlong,
     idx: jint,
 ) -> jlong {
-    let builder = &*(builder as *mut TransactionBuilder);
+    let builder = throw_exception!(env, handle::get::<TransactionBuilder>(builder));
     Box::into_raw(Box::new(builder.get_owner_record(idx as usize))) as jlong
 }
 
@@ -491,11 +621,42 @@ pub unsafe extern "system" fn Java_com_findora_JniApi_transactionBuilderGetOwner
 /// Fetches an owner memo from a transaction
 /// @param {number} idx - Owner memo to fetch. Owner memos are added to the transaction builder sequentially.
 pub unsafe extern "system" fn Java_com_findora_JniApi_transactionBuilderGetOwnerMemo(
-    _env: JNIEnv,
+    env: JNIEnv,
     _: JClass,
     builder: jlong,
     idx: jint,
 ) -> jlong {
-    let builder = &*(builder as *mut TransactionBuilder);
+    let builder = throw_exception!(env, handle::get::<TransactionBuilder>(builder));
     Box::into_raw(Box::new(builder.get_owner_memo(idx as usize))) as jlong
 }
+
+#[no_mangle]
+/// # Safety
+///
+/// Sign the transaction with a signer registered by `signerRegister`,
+/// the signing key never enters the library.
+pub unsafe extern "system" fn Java_com_findora_JniApi_transactionBuilderSignWith(
+    env: JNIEnv,
+    _: JClass,
+    builder: jlong,
+    signer: jlong,
+) -> jlong {
+    let builder = jni_try!(env, {
+        let builder = handle::get::<TransactionBuilder>(builder)?;
+        let signer = handle::get::<SharedJniSigner>(signer)?;
+        builder.sign_with(&*signer)
+    });
+    handle::insert(builder)
+}
+
+#[no_mangle]
+/// # Safety
+///
+/// Releases a transaction builder, `builder` is invalid after this call.
+pub unsafe extern "system" fn Java_com_findora_JniApi_transactionBuilderFree(
+    env: JNIEnv,
+    _: JClass,
+    builder: jlong,
+) {
+    let _ = ThrowExceptionImpl(env, handle::remove::<TransactionBuilder>(builder));
+}
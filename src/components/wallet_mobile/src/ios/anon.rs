@@ -0,0 +1,164 @@
+//!
+//! Anonymous transfers, see `crate::rust::anon`.
+//!
+//! An ABAR is given as the JSON of an `AnonInput`:
+//! `{"commitment": <base58>, "owner_memo": <AxfrOwnerMemo>, "mt_leaf_info": <MTLeafInfo>}`,
+//! and a receiver as a bech32 address or a base64 public key.
+//! The returned strings must be released with `findora_ffi_string_free`,
+//! the builders with `findora_ffi_anon_transfer_operation_builder_free`.
+//!
+
+use crate::rust::anon::{self, AnonInput, AnonTransferOperationBuilder};
+use crate::rust::{c_char_to_string, string_to_c_char};
+use ruc::*;
+use std::os::raw::c_char;
+use zei::XfrKeyPair;
+
+fn parse_input(input: *const c_char) -> Result<AnonInput> {
+    serde_json::from_str(&c_char_to_string(input)).c(d!())
+}
+
+fn string_or_null(s: Result<String>) -> *mut c_char {
+    match s {
+        Ok(s) => string_to_c_char(s),
+        Err(e) => {
+            println!("{:?}", e);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+fn builder_or_null(
+    builder: Result<AnonTransferOperationBuilder>,
+) -> *mut AnonTransferOperationBuilder {
+    match builder {
+        Ok(builder) => Box::into_raw(Box::new(builder)),
+        Err(e) => {
+            println!("{:?}", e);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Opens the ABAR `input` with `keypair`, returns the JSON `OpenedAbar`, null on error.
+#[no_mangle]
+pub extern "C" fn findora_ffi_open_abar(
+    input: *const c_char,
+    keypair: &XfrKeyPair,
+) -> *mut c_char {
+    string_or_null(
+        parse_input(input)
+            .and_then(|input| anon::open_abar(&input, keypair))
+            .and_then(|opened| serde_json::to_string(&opened).c(d!())),
+    )
+}
+
+/// The base58 nullifier of the ABAR `input` of `keypair`, which needs its Merkle proof.
+#[no_mangle]
+pub extern "C" fn findora_ffi_gen_nullifier_hash(
+    input: *const c_char,
+    keypair: &XfrKeyPair,
+) -> *mut c_char {
+    string_or_null(
+        parse_input(input).and_then(|input| anon::gen_nullifier_hash(&input, keypair)),
+    )
+}
+
+/// Fee smaller than this value will be denied, for a transaction converting records to ABARs.
+#[no_mangle]
+pub extern "C" fn findora_ffi_fra_get_minimal_fee_for_bar_to_abar() -> u64 {
+    anon::fra_get_minimal_fee_for_bar_to_abar()
+}
+
+/// Anon fee for a given number of inputs & outputs
+#[no_mangle]
+pub extern "C" fn findora_ffi_get_anon_fee(n_inputs: u32, n_outputs: u32) -> u32 {
+    anon::get_anon_fee(n_inputs, n_outputs)
+}
+
+/// Create a new anonymous transfer builder.
+#[no_mangle]
+pub extern "C" fn findora_ffi_anon_transfer_operation_builder_new(
+    seq_id: u64,
+) -> *mut AnonTransferOperationBuilder {
+    Box::into_raw(Box::new(AnonTransferOperationBuilder::new(seq_id)))
+}
+
+/// Spend the ABAR `input` of `keypair`, which needs its Merkle proof.
+#[no_mangle]
+pub extern "C" fn findora_ffi_anon_transfer_operation_builder_add_input(
+    builder: &AnonTransferOperationBuilder,
+    input: *const c_char,
+    keypair: &XfrKeyPair,
+) -> *mut AnonTransferOperationBuilder {
+    builder_or_null(
+        parse_input(input).and_then(|input| builder.clone().add_input(&input, keypair)),
+    )
+}
+
+/// Send `amount` of the base64 `asset_type` to `to`.
+#[no_mangle]
+pub extern "C" fn findora_ffi_anon_transfer_operation_builder_add_output(
+    builder: &AnonTransferOperationBuilder,
+    amount: u64,
+    asset_type: *const c_char,
+    to: *const c_char,
+) -> *mut AnonTransferOperationBuilder {
+    builder_or_null(
+        anon::anon_public_key_from_str(&c_char_to_string(to)).and_then(|to| {
+            builder
+                .clone()
+                .add_output(amount, &c_char_to_string(asset_type), &to)
+        }),
+    )
+}
+
+/// The key of the inputs, which generates the nullifiers and receives the remainders.
+#[no_mangle]
+pub extern "C" fn findora_ffi_anon_transfer_operation_builder_add_keypair(
+    builder: &AnonTransferOperationBuilder,
+    keypair: &XfrKeyPair,
+) -> *mut AnonTransferOperationBuilder {
+    Box::into_raw(Box::new(builder.clone().add_keypair(keypair)))
+}
+
+/// The FRA missing from the inputs to pay the fee of the transfer, as a decimal string.
+#[no_mangle]
+pub extern "C" fn findora_ffi_anon_transfer_operation_builder_get_expected_fee(
+    builder: &AnonTransferOperationBuilder,
+) -> *mut c_char {
+    string_or_null(builder.get_expected_fee().map(|fee| fee.to_string()))
+}
+
+/// The fee of the transfer with its remainders, as a decimal string.
+#[no_mangle]
+pub extern "C" fn findora_ffi_anon_transfer_operation_builder_get_total_fee_estimate(
+    builder: &AnonTransferOperationBuilder,
+) -> *mut c_char {
+    string_or_null(builder.get_total_fee_estimate().map(|fee| fee.to_string()))
+}
+
+/// Generates the proof of the transfer, returns the built builder.
+/// This takes a while, it should not be called from the main thread.
+#[no_mangle]
+pub extern "C" fn findora_ffi_anon_transfer_operation_builder_build(
+    builder: &AnonTransferOperationBuilder,
+) -> *mut AnonTransferOperationBuilder {
+    builder_or_null(builder.clone().build())
+}
+
+/// The base58 commitments of the outputs, remainders included, as a JSON array.
+#[no_mangle]
+pub extern "C" fn findora_ffi_anon_transfer_operation_builder_get_commitments(
+    builder: &AnonTransferOperationBuilder,
+) -> *mut c_char {
+    string_or_null(serde_json::to_string(&builder.get_commitments()).c(d!()))
+}
+
+/// The serialized transaction of the transfer, once built.
+#[no_mangle]
+pub extern "C" fn findora_ffi_anon_transfer_operation_builder_transaction(
+    builder: &AnonTransferOperationBuilder,
+) -> *mut c_char {
+    string_or_null(builder.transaction())
+}
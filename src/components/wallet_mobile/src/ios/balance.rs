@@ -0,0 +1,54 @@
+//!
+//! Opening of the records of an account in batch, see `crate::rust::balance`.
+//!
+
+use crate::rust::{
+    balance::{open_records, RecordToOpen, DEFAULT_CHUNK_SIZE},
+    c_char_to_string, string_to_c_char, types,
+};
+use ruc::*;
+use std::os::raw::{c_char, c_void};
+
+/// Progress of a batch, given the count of the opened records, their total
+/// and the `ctx` of the call. Returning false cancels the batch.
+pub type ProgressCallback =
+    extern "C" fn(ctx: *mut c_void, done: u64, total: u64) -> bool;
+
+#[no_mangle]
+/// # Safety
+///
+/// Open the records of `keypair`, a JSON array of the `OwnedUtxo`s of the query server
+/// or of `{"record": ..., "owner_memo": ...}`, and sum their balances.
+/// Returns the JSON `OpenedRecords`, null on error,
+/// it must be released with `findora_ffi_string_free`.
+///
+/// `chunk_size` records are opened between two calls to `progress`,
+/// `DEFAULT_CHUNK_SIZE` if 0; `progress` may be null.
+pub unsafe extern "C" fn findora_ffi_open_records(
+    keypair: *const types::XfrKeyPair,
+    records: *const c_char,
+    chunk_size: u32,
+    progress: Option<ProgressCallback>,
+    ctx: *mut c_void,
+) -> *mut c_char {
+    let chunk_size = if chunk_size > 0 {
+        chunk_size as usize
+    } else {
+        DEFAULT_CHUNK_SIZE
+    };
+    let mut report = |done: usize, total: usize| match progress {
+        Some(progress) => progress(ctx, done as u64, total as u64),
+        None => true,
+    };
+    match serde_json::from_str::<Vec<RecordToOpen>>(&c_char_to_string(records))
+        .c(d!())
+        .and_then(|records| open_records(&**keypair, &records, chunk_size, &mut report))
+        .and_then(|opened| serde_json::to_string(&opened).c(d!()))
+    {
+        Ok(opened) => string_to_c_char(opened),
+        Err(e) => {
+            println!("{:?}", e);
+            core::ptr::null_mut()
+        }
+    }
+}
@@ -1,8 +1,11 @@
+use ledger::data_model::AssetTypeCode;
 use std::os::raw::c_char;
-use zei::XfrPublicKey;
+use zei::{XfrKeyPair, XfrPublicKey};
 
 use crate::rust::{
-    self, account::EVMTransactionBuilder, c_char_to_string, string_to_c_char,
+    self,
+    account::{EVMFee, EVMTransactionBuilder},
+    c_char_to_string, string_to_c_char,
 };
 
 use super::parse_u64;
@@ -48,6 +51,228 @@ pub extern "C" fn findora_ffi_transfer_to_utxo_from_account(
     }
 }
 
+#[no_mangle]
+/// Construct an EVM Transaction builder that transfer account balance to UTXO.
+/// @param {XfrPublicKey} recipient - UTXO Asset receiver.
+/// @param {u64} amount - Transfer amount.
+/// @param {string} sk - Ethereum wallet private key.
+/// @param {U256} nonce - Transaction nonce for sender.
+///
+/// The returned builder is owned by the caller and must be released
+/// with `findora_ffi_evm_transaction_builder_free`, null is returned on error.
+pub extern "C" fn findora_ffi_transfer_from_account_evm_transaction_builder(
+    recipient: &XfrPublicKey,
+    amount: *const c_char,
+    sk: *const c_char,
+    nonce: *const c_char,
+) -> *mut EVMTransactionBuilder {
+    let nonce: U256 = {
+        let nonce_str = c_char_to_string(nonce);
+        match serde_json::from_str(&nonce_str) {
+            Ok(n) => n,
+            Err(e) => {
+                println!("{:?}", e);
+                return core::ptr::null_mut();
+            }
+        }
+    };
+
+    let sk = c_char_to_string(sk);
+
+    match EVMTransactionBuilder::new_transfer_from_account(
+        *recipient,
+        parse_u64(amount),
+        sk,
+        nonce,
+    ) {
+        Ok(builder) => builder.into_ptr(),
+        Err(e) => {
+            println!("{:?}", e);
+            core::ptr::null_mut()
+        }
+    }
+}
+
+#[no_mangle]
+/// Construct an EVM Transaction builder that transfer account balance to UTXO,
+/// paying a custom fee.
+/// @param {XfrPublicKey} recipient - UTXO Asset receiver.
+/// @param {u64} amount - Transfer amount.
+/// @param {string} sk - Ethereum wallet private key.
+/// @param {U256} nonce - Transaction nonce for sender.
+/// @param {string} fee - JSON-serialized `EVMFee`, e.g.
+/// `{"Legacy":{"gas_limit":21000,"gas_price":"0x2540be400"}}` or
+/// `{"Eip1559":{"gas_limit":21000,"max_fee_per_gas":"0x2540be400",
+/// "max_priority_fee_per_gas":"0x3b9aca00","base_fee":"0x2540be400"}}`.
+///
+/// The returned builder is owned by the caller and must be released
+/// with `findora_ffi_evm_transaction_builder_free`, null is returned on error.
+pub extern "C" fn findora_ffi_transfer_from_account_evm_transaction_builder_with_fee(
+    recipient: &XfrPublicKey,
+    amount: *const c_char,
+    sk: *const c_char,
+    nonce: *const c_char,
+    fee: *const c_char,
+) -> *mut EVMTransactionBuilder {
+    let nonce: U256 = match serde_json::from_str(&c_char_to_string(nonce)) {
+        Ok(n) => n,
+        Err(e) => {
+            println!("{:?}", e);
+            return core::ptr::null_mut();
+        }
+    };
+    let fee: EVMFee = match serde_json::from_str(&c_char_to_string(fee)) {
+        Ok(f) => f,
+        Err(e) => {
+            println!("{:?}", e);
+            return core::ptr::null_mut();
+        }
+    };
+
+    match EVMTransactionBuilder::new_transfer_from_account_with_fee(
+        *recipient,
+        parse_u64(amount),
+        c_char_to_string(sk),
+        nonce,
+        &fee,
+    ) {
+        Ok(builder) => builder.into_ptr(),
+        Err(e) => {
+            println!("{:?}", e);
+            core::ptr::null_mut()
+        }
+    }
+}
+
+#[no_mangle]
+/// Construct an EVM Transaction builder that transfer UTXO assets to account balance.
+/// @param {XfrKeyPair} keypair - Owner of the UTXO assets.
+/// @param {u64} seq_id - Unique sequence ID to prevent replay attacks.
+/// @param {string} transfer_op - JSON-serialized transfer operation sending `amount` to the
+/// staking black hole address.
+/// @param {u64} amount - Transfer amount.
+/// @param {string} address - Receiving ed25519 or ecdsa address, the address of `keypair` if empty.
+/// @param {string} asset - Base64 code of the asset, FRA if empty.
+/// @param {string} lowlevel_data - Hex-encoded data of the evm call, none if empty.
+///
+/// The returned builder is owned by the caller and must be released
+/// with `findora_ffi_evm_transaction_builder_free`, null is returned on error.
+pub extern "C" fn findora_ffi_transfer_to_account_evm_transaction_builder(
+    keypair: &XfrKeyPair,
+    seq_id: u64,
+    transfer_op: *const c_char,
+    amount: *const c_char,
+    address: *const c_char,
+    asset: *const c_char,
+    lowlevel_data: *const c_char,
+) -> *mut EVMTransactionBuilder {
+    let address = Some(c_char_to_string(address)).filter(|s| !s.is_empty());
+    let asset = match Some(c_char_to_string(asset))
+        .filter(|s| !s.is_empty())
+        .map(|s| AssetTypeCode::new_from_base64(&s))
+        .transpose()
+    {
+        Ok(a) => a,
+        Err(e) => {
+            println!("{:?}", e);
+            return core::ptr::null_mut();
+        }
+    };
+    let lowlevel_data = match Some(c_char_to_string(lowlevel_data))
+        .filter(|s| !s.is_empty())
+        .map(hex::decode)
+        .transpose()
+    {
+        Ok(d) => d,
+        Err(e) => {
+            println!("{:?}", e);
+            return core::ptr::null_mut();
+        }
+    };
+
+    match EVMTransactionBuilder::new_transfer_to_account(
+        keypair,
+        seq_id,
+        c_char_to_string(transfer_op),
+        parse_u64(amount),
+        address,
+        asset,
+        lowlevel_data,
+    ) {
+        Ok(builder) => builder.into_ptr(),
+        Err(e) => {
+            println!("{:?}", e);
+            core::ptr::null_mut()
+        }
+    }
+}
+
+#[no_mangle]
+/// Serialize the transaction of an EVM Transaction builder as a base64 string.
+///
+/// The builder is borrowed and stays owned by the caller,
+/// the returned string must be released with `findora_ffi_string_free`.
+pub extern "C" fn findora_ffi_evm_transaction_builder_serialized_transaction_base64(
+    builder: &EVMTransactionBuilder,
+) -> *mut c_char {
+    string_to_c_char(builder.serialized_transaction_base64())
+}
+
+#[no_mangle]
+/// Restore the hex-encoded ethereum private key from a mnemonic
+/// along a bip32 derivation path, eg. "m/44'/60'/0'/0/0".
+///
+/// The returned string must be released with `findora_ffi_string_free`.
+pub extern "C" fn findora_ffi_restore_evm_secret_from_mnemonic(
+    phrase: *const c_char,
+    lang: *const c_char,
+    path: *const c_char,
+) -> *mut c_char {
+    match rust::account::restore_evm_secret_from_mnemonic(
+        &c_char_to_string(phrase),
+        &c_char_to_string(lang),
+        &c_char_to_string(path),
+    ) {
+        Ok(sk) => string_to_c_char(sk),
+        Err(e) => {
+            println!("{:?}", e);
+            core::ptr::null_mut()
+        }
+    }
+}
+
+#[no_mangle]
+/// The `0x` prefixed ethereum address of a hex-encoded private key.
+///
+/// The returned string must be released with `findora_ffi_string_free`.
+pub extern "C" fn findora_ffi_evm_address_from_secret(sk: *const c_char) -> *mut c_char {
+    match rust::account::evm_address_from_secret(&c_char_to_string(sk)) {
+        Ok(address) => string_to_c_char(address),
+        Err(e) => {
+            println!("{:?}", e);
+            core::ptr::null_mut()
+        }
+    }
+}
+
+#[no_mangle]
+/// Preview of the transaction of an EVM Transaction builder as JSON,
+/// for the user to confirm what will be signed.
+///
+/// The builder is borrowed and stays owned by the caller,
+/// the returned string must be released with `findora_ffi_string_free`.
+pub extern "C" fn findora_ffi_evm_transaction_builder_describe(
+    builder: &EVMTransactionBuilder,
+) -> *mut c_char {
+    match serde_json::to_string(&builder.describe()) {
+        Ok(description) => string_to_c_char(description),
+        Err(e) => {
+            println!("{:?}", e);
+            core::ptr::null_mut()
+        }
+    }
+}
+
 #[no_mangle]
 /// Serialize ethereum address used to abci query nonce.
 pub extern "C" fn get_serialized_address(address: *const c_char) -> *const c_char {
@@ -1,6 +1,10 @@
+use crate::rust::fee::{DraftTx, FeeEstimator};
+use crate::rust::network::DefaultTransport;
 use crate::rust::types;
 use crate::rust::*;
+use ruc::{d, RucResult};
 use std::os::raw::c_char;
+use std::sync::Arc;
 
 use super::parse_u64;
 
@@ -10,6 +14,34 @@ pub extern "C" fn findora_ffi_fra_get_minimal_fee() -> u64 {
     fra_get_minimal_fee()
 }
 
+#[no_mangle]
+/// Estimate the fee of `draft`, a JSON `DraftTx`, with the web3 endpoint `web3`
+/// of the node, eg. `https://prod-mainnet.prod.findora.org:8545`.
+///
+/// Returns the JSON `FeeEstimate`, null on error,
+/// it must be released with `findora_ffi_string_free`.
+/// This blocks on the network, it must not be called from the main thread.
+pub extern "C" fn findora_ffi_estimate_fee(
+    web3: *const c_char,
+    draft: *const c_char,
+) -> *mut c_char {
+    let estimator = FeeEstimator::new(
+        &c_char_to_string(web3),
+        Arc::new(DefaultTransport::default()),
+    );
+    match serde_json::from_str::<DraftTx>(&c_char_to_string(draft))
+        .c(d!())
+        .and_then(|draft| estimator.estimate(&draft))
+        .and_then(|fee| serde_json::to_string(&fee).c(d!()))
+    {
+        Ok(fee) => string_to_c_char(fee),
+        Err(e) => {
+            println!("{:?}", e);
+            core::ptr::null_mut()
+        }
+    }
+}
+
 #[no_mangle]
 /// The destination for fee to be transfered to.
 pub extern "C" fn findora_ffi_fra_get_dest_pubkey() -> *mut types::XfrPublicKey {
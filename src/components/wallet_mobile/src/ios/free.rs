@@ -1,5 +1,11 @@
+use crate::rust::account::EVMTransactionBuilder;
+use crate::rust::anon::AnonTransferOperationBuilder;
+use crate::rust::light_client::LightClient;
+use crate::rust::queue::TxQueue;
 use crate::rust::types;
 use crate::rust::*;
+use std::ffi::CString;
+use std::os::raw::c_char;
 
 #[no_mangle]
 /// # Safety
@@ -20,3 +26,64 @@ pub unsafe extern "C" fn findora_ffi_fee_inputs_free(ptr: *mut FeeInputs) {
     }
     let _ = Box::from_raw(ptr);
 }
+
+#[no_mangle]
+/// # Safety
+///
+/// `ptr` must come from a `findora_ffi_*_evm_transaction_builder` constructor,
+/// it is invalid after this call.
+pub unsafe extern "C" fn findora_ffi_evm_transaction_builder_free(
+    ptr: *mut EVMTransactionBuilder,
+) {
+    if ptr.is_null() {
+        return;
+    }
+    let _ = EVMTransactionBuilder::from_ptr(ptr);
+}
+
+#[no_mangle]
+/// # Safety
+///
+/// `ptr` must be a string returned by this library, it is invalid after this call.
+pub unsafe extern "C" fn findora_ffi_string_free(ptr: *mut c_char) {
+    if ptr.is_null() {
+        return;
+    }
+    let _ = CString::from_raw(ptr);
+}
+
+#[no_mangle]
+/// # Safety
+///
+/// `ptr` must come from `findora_ffi_tx_queue_open`, it is invalid after this call.
+pub unsafe extern "C" fn findora_ffi_tx_queue_free(ptr: *mut TxQueue) {
+    if ptr.is_null() {
+        return;
+    }
+    let _ = Box::from_raw(ptr);
+}
+
+#[no_mangle]
+/// # Safety
+///
+/// `ptr` must come from `findora_ffi_light_client_new`, it is invalid after this call.
+pub unsafe extern "C" fn findora_ffi_light_client_free(ptr: *mut LightClient) {
+    if ptr.is_null() {
+        return;
+    }
+    let _ = Box::from_raw(ptr);
+}
+
+#[no_mangle]
+/// # Safety
+///
+/// `ptr` must come from a `findora_ffi_anon_transfer_operation_builder_*` function,
+/// it is invalid after this call.
+pub unsafe extern "C" fn findora_ffi_anon_transfer_operation_builder_free(
+    ptr: *mut AnonTransferOperationBuilder,
+) {
+    if ptr.is_null() {
+        return;
+    }
+    let _ = Box::from_raw(ptr);
+}
@@ -0,0 +1,67 @@
+//!
+//! The verification of the ledger state and utxos, see `crate::rust::light_client`.
+//!
+//! The nodes are given as a JSON array of urls, the results are returned as
+//! JSON `VerifiedState` and `VerifiedUtxo`,
+//! the returned strings must be released with `findora_ffi_string_free`.
+//! The functions block on the network, they must not be called from the main thread.
+//!
+
+use crate::rust::{c_char_to_string, light_client::LightClient, string_to_c_char};
+use ruc::*;
+use std::os::raw::c_char;
+
+#[no_mangle]
+/// A client of the query servers of `nodes`, a JSON array of two urls at least.
+///
+/// The returned client must be released with `findora_ffi_light_client_free`,
+/// null is returned on error.
+pub extern "C" fn findora_ffi_light_client_new(
+    nodes: *const c_char,
+) -> *mut LightClient {
+    match serde_json::from_str::<Vec<String>>(&c_char_to_string(nodes))
+        .c(d!())
+        .and_then(LightClient::new)
+    {
+        Ok(client) => Box::into_raw(Box::new(client)),
+        Err(e) => {
+            println!("{:?}", e);
+            core::ptr::null_mut()
+        }
+    }
+}
+
+#[no_mangle]
+/// The state commitment at the highest block height reached by all the nodes.
+pub extern "C" fn findora_ffi_light_client_state(client: &LightClient) -> *mut c_char {
+    match client
+        .state()
+        .and_then(|state| serde_json::to_string(&state).c(d!()))
+    {
+        Ok(state) => string_to_c_char(state),
+        Err(e) => {
+            println!("{:?}", e);
+            core::ptr::null_mut()
+        }
+    }
+}
+
+#[no_mangle]
+/// The utxo `sid`, if its proof holds against a state of all the nodes.
+///
+/// Null is returned if the utxo is spent or its proof is invalid.
+pub extern "C" fn findora_ffi_light_client_utxo(
+    client: &LightClient,
+    sid: u64,
+) -> *mut c_char {
+    match client
+        .utxo(sid)
+        .and_then(|utxo| serde_json::to_string(&utxo).c(d!()))
+    {
+        Ok(utxo) => string_to_c_char(utxo),
+        Err(e) => {
+            println!("{:?}", e);
+            core::ptr::null_mut()
+        }
+    }
+}
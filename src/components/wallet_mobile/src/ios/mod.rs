@@ -1,15 +1,22 @@
+pub mod anon;
 pub mod asset_rules;
+pub mod balance;
 pub mod evm;
 pub mod fee;
 pub mod free;
+pub mod light_client;
+pub mod queue;
 pub mod tx_builder;
 pub mod tx_op_builder;
 
+use crate::rust::address;
+use crate::rust::logger;
 use crate::rust::types;
 use crate::rust::*;
 use ledger::data_model::{AssetType as PlatformAssetType, AssetTypeCode};
 use rand_chacha::ChaChaRng;
 use rand_core::SeedableRng;
+use ruc::{d, RucResult};
 use std::ffi::{CStr, CString};
 use std::os::raw::c_char;
 use std::ptr;
@@ -21,6 +28,26 @@ pub extern "C" fn findora_ffi_build_id() -> *mut c_char {
     string_to_c_char(build_id())
 }
 
+#[no_mangle]
+/// Logs the events of the library to os_log, in the category `findora`, up to `level`:
+/// one of `off`, `error`, `warn`, `info`, `debug` or `trace`.
+/// Can be called again to change the level, returns false if `level` is unknown.
+pub extern "C" fn findora_ffi_set_log_level(level: *const c_char) -> bool {
+    match logger::set_log_level(&c_char_to_string(level)) {
+        Ok(()) => true,
+        Err(e) => {
+            println!("{:?}", e);
+            false
+        }
+    }
+}
+
+#[no_mangle]
+/// Returns the level set by `findora_ffi_set_log_level`, `off` before.
+pub extern "C" fn findora_ffi_get_log_level() -> *mut c_char {
+    string_to_c_char(logger::log_level())
+}
+
 #[no_mangle]
 pub extern "C" fn findora_ffi_random_asset_type() -> *mut c_char {
     string_to_c_char(random_asset_type())
@@ -58,6 +85,20 @@ pub extern "C" fn findora_ffi_verify_authenticated_txn(
     rs_verify_authenticated_txn(state_commitment, authenticated_txn).unwrap_or(false)
 }
 
+#[no_mangle]
+/// Given a serialized state commitment and utxo, returns true if the utxo is unspent and its
+/// transaction correctly hashes up to the state commitment, false otherwise.
+/// @param {string} state_commitment - String representing the state commitment.
+/// @param {string} authenticated_utxo - String representing the utxo, as returned by `utxo_sid`.
+pub extern "C" fn findora_ffi_verify_authenticated_utxo(
+    state_commitment: *const c_char,
+    authenticated_utxo: *const c_char,
+) -> bool {
+    let state_commitment = c_char_to_string(state_commitment);
+    let authenticated_utxo = c_char_to_string(authenticated_utxo);
+    rs_verify_authenticated_utxo(state_commitment, authenticated_utxo).unwrap_or(false)
+}
+
 #[no_mangle]
 pub extern "C" fn findora_ffi_get_null_pk() -> *mut types::XfrPublicKey {
     let pk = get_null_pk().into();
@@ -224,6 +265,53 @@ pub unsafe extern "C" fn findora_ffi_restore_keypair_from_mnemonic_default(
     }
 }
 
+#[no_mangle]
+/// # Safety
+///
+/// Restore the XfrKeyPair from a mnemonic along a derivation path,
+/// "m/44'/coin'/account'/change/address" or "m/49'/coin'/account'/change/address".
+pub unsafe extern "C" fn findora_ffi_restore_keypair_from_mnemonic_path(
+    phrase: *const c_char,
+    lang: *const c_char,
+    path: *const c_char,
+) -> *mut types::XfrKeyPair {
+    match rs_restore_keypair_from_mnemonic_path(
+        c_char_to_string(phrase).as_str(),
+        c_char_to_string(lang).as_str(),
+        c_char_to_string(path).as_str(),
+    ) {
+        Ok(info) => Box::into_raw(Box::new(types::XfrKeyPair::from(info))),
+        Err(e) => {
+            println!("{:?}", e);
+            ptr::null_mut()
+        }
+    }
+}
+
+#[no_mangle]
+/// Validate a user-entered mnemonic, returns a JSON `MnemonicCheck`
+/// with the suggested replacements of its misspelled words.
+/// - @param `lang`: acceptable value are one of [ "en", "zh", "zh_traditional", "fr", "it", "ko", "sp", "jp" ]
+///
+/// The returned string must be released with `findora_ffi_string_free`.
+pub extern "C" fn findora_ffi_validate_mnemonic(
+    phrase: *const c_char,
+    lang: *const c_char,
+) -> *mut c_char {
+    match rs_validate_mnemonic(
+        c_char_to_string(phrase).as_str(),
+        c_char_to_string(lang).as_str(),
+    )
+    .and_then(|check| serde_json::to_string(&check).c(d!()))
+    {
+        Ok(check) => string_to_c_char(check),
+        Err(e) => {
+            println!("{:?}", e);
+            ptr::null_mut()
+        }
+    }
+}
+
 #[no_mangle]
 /// # Safety
 ///
@@ -297,6 +385,37 @@ pub unsafe extern "C" fn findora_ffi_base64_to_bech32(pk: *const c_char) -> *mut
     }
 }
 
+#[no_mangle]
+/// Parses an address entered by the user: a bech32 `fra1...` address, a base64 public key
+/// or a `0x` EVM address, checked against its EIP-55 checksum if in mixed case.
+/// Returns the JSON `ParsedAddress`, null if the address is invalid,
+/// it must be released with `findora_ffi_string_free`.
+pub extern "C" fn findora_ffi_parse_address(address: *const c_char) -> *mut c_char {
+    match address::parse_address(&c_char_to_string(address))
+        .and_then(|parsed| serde_json::to_string(&parsed).c(d!()))
+    {
+        Ok(parsed) => string_to_c_char(parsed),
+        Err(e) => {
+            println!("{:?}", e);
+            ptr::null_mut()
+        }
+    }
+}
+
+#[no_mangle]
+/// Returns the EIP-55 checksummed form of a `0x` EVM address, null if it is invalid.
+pub extern "C" fn findora_ffi_evm_checksum_address(
+    address: *const c_char,
+) -> *mut c_char {
+    match address::evm_checksum_address(&c_char_to_string(address)) {
+        Ok(address) => string_to_c_char(address),
+        Err(e) => {
+            println!("{:?}", e);
+            ptr::null_mut()
+        }
+    }
+}
+
 #[no_mangle]
 /// # Safety
 /// Builds an asset type from a JSON-encoded JavaScript value.
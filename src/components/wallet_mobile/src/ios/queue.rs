@@ -0,0 +1,120 @@
+//!
+//! The pending transaction queue, see `crate::rust::queue`.
+//!
+//! The statuses are returned as JSON arrays of `QueuedTx`,
+//! the returned strings must be released with `findora_ffi_string_free`.
+//!
+
+use crate::rust::{
+    c_char_to_string,
+    queue::{unix_now, TxQueue},
+    string_to_c_char,
+};
+use ruc::*;
+use std::os::raw::c_char;
+
+#[no_mangle]
+/// Open the queue saved at `path`, in the storage of the app.
+///
+/// The returned queue must be released with `findora_ffi_tx_queue_free`,
+/// null is returned on error.
+pub extern "C" fn findora_ffi_tx_queue_open(path: *const c_char) -> *mut TxQueue {
+    match TxQueue::open(c_char_to_string(path)) {
+        Ok(queue) => Box::into_raw(Box::new(queue)),
+        Err(e) => {
+            println!("{:?}", e);
+            core::ptr::null_mut()
+        }
+    }
+}
+
+#[no_mangle]
+/// Queue a serialized transaction, returns its id, or -1 on error.
+pub extern "C" fn findora_ffi_tx_queue_enqueue(
+    queue: &mut TxQueue,
+    tx: *const c_char,
+) -> i64 {
+    match queue.enqueue(c_char_to_string(tx), unix_now()) {
+        Ok(id) => id as i64,
+        Err(e) => {
+            println!("{:?}", e);
+            -1
+        }
+    }
+}
+
+#[no_mangle]
+/// Report the connectivity of the app,
+/// the pending transactions are due as soon as it is back online.
+pub extern "C" fn findora_ffi_tx_queue_set_online(
+    queue: &mut TxQueue,
+    online: bool,
+) -> bool {
+    queue
+        .set_online(online, unix_now())
+        .map_err(|e| println!("{:?}", e))
+        .is_ok()
+}
+
+#[no_mangle]
+/// The transactions to submit now,
+/// each one must be reported by `findora_ffi_tx_queue_report`.
+pub extern "C" fn findora_ffi_tx_queue_due(queue: &mut TxQueue) -> *mut c_char {
+    match queue
+        .due(unix_now())
+        .and_then(|due| serde_json::to_string(&due).c(d!()))
+    {
+        Ok(due) => string_to_c_char(due),
+        Err(e) => {
+            println!("{:?}", e);
+            core::ptr::null_mut()
+        }
+    }
+}
+
+#[no_mangle]
+/// Record the outcome of the submission of the transaction `id`:
+/// its hash on success, or the error with a null `hash`.
+/// Returns the JSON status of the transaction.
+pub extern "C" fn findora_ffi_tx_queue_report(
+    queue: &mut TxQueue,
+    id: u64,
+    hash: *const c_char,
+    error: *const c_char,
+) -> *mut c_char {
+    let outcome = if !hash.is_null() {
+        Ok(c_char_to_string(hash))
+    } else if !error.is_null() {
+        Err(c_char_to_string(error))
+    } else {
+        Err("unknown error".to_owned())
+    };
+    match queue
+        .report(id, outcome, unix_now())
+        .and_then(|status| serde_json::to_string(&status).c(d!()))
+    {
+        Ok(status) => string_to_c_char(status),
+        Err(e) => {
+            println!("{:?}", e);
+            core::ptr::null_mut()
+        }
+    }
+}
+
+#[no_mangle]
+/// All the queued transactions with their status, the oldest first.
+pub extern "C" fn findora_ffi_tx_queue_statuses(queue: &TxQueue) -> *mut c_char {
+    match serde_json::to_string(queue.statuses()) {
+        Ok(statuses) => string_to_c_char(statuses),
+        Err(e) => {
+            println!("{:?}", e);
+            core::ptr::null_mut()
+        }
+    }
+}
+
+#[no_mangle]
+/// Drop the transaction `id` from the queue.
+pub extern "C" fn findora_ffi_tx_queue_remove(queue: &mut TxQueue, id: u64) -> bool {
+    queue.remove(id).map_err(|e| println!("{:?}", e)).is_ok()
+}
@@ -1,9 +1,10 @@
 use super::parse_u64;
 use crate::rust::{
-    c_char_to_string, string_to_c_char, AssetRules, ClientAssetRecord, FeeInputs,
+    anon, c_char_to_string, string_to_c_char, AssetRules, ClientAssetRecord, FeeInputs,
     OwnerMemo, TransactionBuilder,
 };
 use ledger::data_model::AssetTypeCode;
+use ruc::{d, RucResult};
 use std::os::raw::c_char;
 use zei::XfrKeyPair;
 
@@ -45,6 +46,20 @@ pub extern "C" fn findora_ffi_transaction_builder_add_fee(
     }
 }
 
+/// As the last operation of BarToAbar transaction,
+/// add a static fee to the transaction.
+#[no_mangle]
+pub extern "C" fn findora_ffi_transaction_builder_add_fee_bar_to_abar(
+    builder: &TransactionBuilder,
+    inputs: &FeeInputs,
+) -> *mut TransactionBuilder {
+    if let Ok(info) = builder.clone().add_fee_bar_to_abar(inputs.clone()) {
+        Box::into_raw(Box::new(info))
+    } else {
+        std::ptr::null_mut()
+    }
+}
+
 /// A simple fee checker for mainnet v1.0.
 ///
 /// SEE [check_fee](ledger::data_model::Transaction::check_fee)
@@ -244,6 +259,29 @@ pub extern "C" fn findora_ffi_transaction_builder_add_operation_claim_custom(
     }
 }
 
+#[no_mangle]
+/// Claims the rewards of `keypair` from `validator`, a tendermint address:
+/// `am` FRA units of them, or all of them if `am` is "0". Null on error.
+pub extern "C" fn findora_ffi_transaction_builder_add_operation_claim_rewards(
+    builder: &TransactionBuilder,
+    keypair: &XfrKeyPair,
+    validator: *const c_char,
+    am: *const c_char,
+) -> *mut TransactionBuilder {
+    let am = parse_u64(am);
+    match builder.clone().add_operation_claim_rewards(
+        keypair,
+        &c_char_to_string(validator),
+        am,
+    ) {
+        Ok(builder) => Box::into_raw(Box::new(builder)),
+        Err(e) => {
+            println!("{:?}", e);
+            std::ptr::null_mut()
+        }
+    }
+}
+
 /// Adds a serialized transfer asset operation to a transaction builder instance.
 /// @param {string} op - a JSON-serialized transfer operation.
 /// @see {@link module:Findora-Wasm~TransferOperationBuilder} for details on constructing a transfer operation.
@@ -320,6 +358,94 @@ pub extern "C" fn findora_ffi_transaction_builder_sign(
     }
 }
 
+#[no_mangle]
+/// # Safety
+///
+/// Adds an operation converting the record `txo_sid` of `auth_key_pair` to an ABAR
+/// of `abar_pubkey`, a bech32 address or a base64 public key.
+/// `seed` is the hex of 32 random bytes generating the proof, `owner_memo` may be null.
+pub unsafe extern "C" fn findora_ffi_transaction_builder_add_operation_bar_to_abar(
+    builder: &TransactionBuilder,
+    seed: *const c_char,
+    auth_key_pair: &XfrKeyPair,
+    abar_pubkey: *const c_char,
+    txo_sid: u64,
+    input_record: *const ClientAssetRecord,
+    owner_memo: *const OwnerMemo,
+) -> *mut TransactionBuilder {
+    let memo = if owner_memo.is_null() {
+        None
+    } else {
+        Some((*owner_memo).clone())
+    };
+    match anon::anon_public_key_from_str(&c_char_to_string(abar_pubkey)).and_then(
+        |abar_pubkey| {
+            builder.clone().add_operation_bar_to_abar(
+                &c_char_to_string(seed),
+                auth_key_pair,
+                &abar_pubkey,
+                txo_sid,
+                &*input_record,
+                memo,
+            )
+        },
+    ) {
+        Ok(info) => Box::into_raw(Box::new(info)),
+        Err(e) => {
+            println!("{:?}", e);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Adds an operation converting the ABAR `input`, the JSON of an `AnonInput`
+/// with its Merkle proof, to a record of `recipient`, a bech32 address or a base64 public key.
+#[no_mangle]
+pub extern "C" fn findora_ffi_transaction_builder_add_operation_abar_to_bar(
+    builder: &TransactionBuilder,
+    input: *const c_char,
+    from_keypair: &XfrKeyPair,
+    recipient: *const c_char,
+    conf_amount: bool,
+    conf_type: bool,
+) -> *mut TransactionBuilder {
+    match serde_json::from_str::<anon::AnonInput>(&c_char_to_string(input))
+        .c(d!())
+        .and_then(|input| {
+            let recipient =
+                anon::anon_public_key_from_str(&c_char_to_string(recipient))?;
+            builder.clone().add_operation_abar_to_bar(
+                &input,
+                from_keypair,
+                &recipient,
+                conf_amount,
+                conf_type,
+            )
+        }) {
+        Ok(info) => Box::into_raw(Box::new(info)),
+        Err(e) => {
+            println!("{:?}", e);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// The base58 commitments of the ABARs created by the transaction, as a JSON array.
+///
+/// The returned string must be released with `findora_ffi_string_free`.
+#[no_mangle]
+pub extern "C" fn findora_ffi_transaction_builder_get_commitments(
+    builder: &TransactionBuilder,
+) -> *mut c_char {
+    match serde_json::to_string(&builder.get_commitments()) {
+        Ok(commitments) => string_to_c_char(commitments),
+        Err(e) => {
+            println!("{:?}", e);
+            std::ptr::null_mut()
+        }
+    }
+}
+
 /// Extracts the serialized form of a transaction.
 #[no_mangle]
 pub extern "C" fn findora_ffi_transaction_builder_transaction(
@@ -328,6 +454,49 @@ pub extern "C" fn findora_ffi_transaction_builder_transaction(
     string_to_c_char(builder.transaction())
 }
 
+/// Preview of the operations, amounts, recipients and fee of the transaction as JSON,
+/// for the user to confirm what will be signed.
+///
+/// The returned string must be released with `findora_ffi_string_free`.
+#[no_mangle]
+pub extern "C" fn findora_ffi_transaction_builder_describe(
+    builder: &TransactionBuilder,
+) -> *mut c_char {
+    match serde_json::to_string(&builder.describe()) {
+        Ok(description) => string_to_c_char(description),
+        Err(e) => {
+            println!("{:?}", e);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Saves the builder in a versioned format, loadable by later releases.
+#[no_mangle]
+pub extern "C" fn findora_ffi_transaction_builder_to_portable(
+    builder: &TransactionBuilder,
+) -> *mut c_char {
+    builder
+        .to_portable_bytes()
+        .ok()
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+        .map_or(std::ptr::null_mut(), string_to_c_char)
+}
+
+/// Loads a builder saved by `findora_ffi_transaction_builder_to_portable`.
+#[no_mangle]
+pub extern "C" fn findora_ffi_transaction_builder_from_portable(
+    portable: *const c_char,
+) -> *mut TransactionBuilder {
+    if let Ok(info) =
+        TransactionBuilder::from_portable_bytes(c_char_to_string(portable).as_bytes())
+    {
+        Box::into_raw(Box::new(info))
+    } else {
+        std::ptr::null_mut()
+    }
+}
+
 /// Calculates transaction handle.
 #[no_mangle]
 pub extern "C" fn findora_ffi_transaction_builder_transaction_handle(
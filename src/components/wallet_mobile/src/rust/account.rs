@@ -1,18 +1,34 @@
 use core::str::FromStr;
-use ledger::data_model::{AssetTypeCode, ASSET_TYPE_FRA};
-use ruc::{d, Result, RucResult};
-use zei::{XfrKeyPair, XfrPublicKey};
+use ledger::data_model::{
+    AssetTypeCode, Operation, Transaction, ASSET_TYPE_FRA, BLACK_HOLE_PUBKEY_STAKING,
+};
+use ruc::{d, eg, Result, RucResult};
+use serde::{Deserialize, Serialize};
+use zei::{
+    noah_api::xfr::structs::{XfrAmount, XfrAssetType},
+    XfrKeyPair, XfrPublicKey,
+};
 
+use super::crypto::mnemonic_language;
+use super::describe::{
+    describe_transaction, h160_to_string, OperationDescription, OutputDescription,
+    TxDescription,
+};
 use super::transaction::TransactionBuilder;
 
+use ethereum::{
+    LegacyTransactionMessage, TransactionAction, TransactionSignature,
+    TransactionV0 as EthereumTransaction,
+};
 use fp_types::{
     actions::{
+        ethereum::Action as EthereumAction,
         xhub::{Action as XhubAction, NonConfidentialOutput, NonConfidentialTransfer},
         Action,
     },
     assemble::{CheckFee, CheckNonce, SignedExtra, UncheckedTransaction},
     crypto::{Address, MultiSignature, MultiSigner},
-    U256,
+    H160, H256, U256,
 };
 
 use fp_utils::{ecdsa::SecpPair, tx::EvmRawTxWrapper};
@@ -70,21 +86,152 @@ pub struct EVMTransactionBuilder {
 
 pub enum EVMTransactionKind {
     Unchecked(UncheckedTransaction<SignedExtra>),
+    /// utxo transaction converting utxo assets to account balance
+    Native(Transaction),
+}
+
+/// Fee of an account transaction, burned from the balance of the sender.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EVMFee {
+    /// the minimal fee of the chain
+    #[default]
+    Minimal,
+    /// `gas_limit * gas_price`
+    Legacy { gas_limit: u64, gas_price: U256 },
+    /// `gas_limit * min(max_fee_per_gas, base_fee + max_priority_fee_per_gas)`,
+    /// `base_fee` is the one of the latest block, see `eth_feeHistory`.
+    Eip1559 {
+        gas_limit: u64,
+        max_fee_per_gas: U256,
+        max_priority_fee_per_gas: U256,
+        base_fee: U256,
+    },
+}
+
+impl EVMFee {
+    /// The gas limit and the effective gas price, `None` for the minimal fee.
+    pub fn gas(&self) -> Result<Option<(u64, U256)>> {
+        let (gas_limit, gas_price) = match self {
+            EVMFee::Minimal => return Ok(None),
+            EVMFee::Legacy {
+                gas_limit,
+                gas_price,
+            } => (*gas_limit, *gas_price),
+            EVMFee::Eip1559 {
+                gas_limit,
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+                base_fee,
+            } => {
+                if max_priority_fee_per_gas > max_fee_per_gas {
+                    return Err(eg!("max priority fee per gas above max fee per gas"));
+                }
+                if max_fee_per_gas < base_fee {
+                    return Err(eg!(
+                        "max fee per gas below the base fee, the transaction would be stuck"
+                    ));
+                }
+                let price = base_fee
+                    .checked_add(*max_priority_fee_per_gas)
+                    .map_or(*max_fee_per_gas, |p| p.min(*max_fee_per_gas));
+                (*gas_limit, price)
+            }
+        };
+        if 0 == gas_limit {
+            return Err(eg!("zero gas limit"));
+        }
+        Ok(Some((gas_limit, gas_price)))
+    }
+
+    /// The fee to pay, `None` for the minimal one.
+    pub fn amount(&self) -> Result<Option<U256>> {
+        match self.gas().c(d!())? {
+            None => Ok(None),
+            Some((gas_limit, gas_price)) => gas_price
+                .checked_mul(U256::from(gas_limit))
+                .c(d!("fee overflow"))
+                .map(Some),
+        }
+    }
+}
+
+/// Selector of `transfer(address,uint256)`
+const ERC20_TRANSFER_SELECTOR: [u8; 4] = [0xa9, 0x05, 0x9c, 0xbb];
+
+/// ABI encoded call of `transfer(to, amount)` of an ERC-20 contract.
+pub fn erc20_transfer_input(to: H160, amount: U256) -> Vec<u8> {
+    let mut input = Vec::with_capacity(4 + 2 * 32);
+    input.extend_from_slice(&ERC20_TRANSFER_SELECTOR);
+    // an address is left padded to a 32 bytes word
+    input.extend_from_slice(&[0u8; 12]);
+    input.extend_from_slice(to.as_bytes());
+    let mut word = [0u8; 32];
+    amount.to_big_endian(&mut word);
+    input.extend_from_slice(&word);
+    input
+}
+
+/// The receiver and amount of an ERC-20 `transfer` call, see `erc20_transfer_input`.
+pub fn erc20_transfer_from_input(input: &[u8]) -> Option<(H160, U256)> {
+    if input.len() != 4 + 2 * 32
+        || input[..4] != ERC20_TRANSFER_SELECTOR
+        || input[4..16].iter().any(|b| *b != 0)
+    {
+        return None;
+    }
+    Some((
+        H160::from_slice(&input[16..36]),
+        U256::from_big_endian(&input[36..]),
+    ))
+}
+
+/// Parse a hex-encoded EVM address, with or without the `0x` prefix.
+pub fn parse_evm_address(address: &str) -> Result<H160> {
+    let bytes = hex::decode(address.strip_prefix("0x").unwrap_or(address)).c(d!())?;
+    if bytes.len() != H160::len_bytes() {
+        return Err(eg!("invalid evm address length"));
+    }
+    Ok(H160::from_slice(&bytes))
+}
+
+fn secp_pair_from_hex(sk: &str) -> Result<SecpPair> {
+    let seed = hex::decode(sk.strip_prefix("0x").unwrap_or(sk)).c(d!())?;
+    if seed.len() != 32 {
+        return Err(eg!("invalid ethereum private key length"));
+    }
+    let mut s = [0u8; 32];
+    s.copy_from_slice(&seed);
+    Ok(SecpPair::from_seed(&s))
 }
 
 impl EVMTransactionBuilder {
     /// transfer to uxto assets from account(ed25519 or ecdsa address) balance.
+    pub fn new_transfer_from_account(
+        recipient: XfrPublicKey,
+        amount: u64,
+        sk: String,
+        nonce: U256,
+    ) -> Result<Self> {
+        Self::new_transfer_from_account_with_fee(
+            recipient,
+            amount,
+            sk,
+            nonce,
+            &EVMFee::default(),
+        )
+    }
 
-    pub fn new_transfer_to_utxo_from_account(
+    /// transfer to uxto assets from account(ed25519 or ecdsa address) balance,
+    /// paying `fee`.
+    pub fn new_transfer_from_account_with_fee(
         recipient: XfrPublicKey,
         amount: u64,
         sk: String,
         nonce: U256,
-    ) -> Result<String> {
-        let seed = hex::decode(sk).c(d!())?;
-        let mut s = [0u8; 32];
-        s.copy_from_slice(&seed);
-        let kp = SecpPair::from_seed(&s);
+        fee: &EVMFee,
+    ) -> Result<Self> {
+        let fee = fee.amount().c(d!())?;
+        let kp = secp_pair_from_hex(&sk).c(d!())?;
 
         let output = NonConfidentialOutput {
             target: recipient,
@@ -100,27 +247,225 @@ impl EVMTransactionBuilder {
             },
         ));
 
-        let extra = (CheckNonce::new(nonce), CheckFee::new(None));
+        let extra = (CheckNonce::new(nonce), CheckFee::new(fee));
         let msg = serde_json::to_vec(&(action.clone(), extra.clone())).c(d!())?;
         let signature = MultiSignature::from(kp.sign(&msg));
         let signer = Address::from(kp.address());
 
         let tx = UncheckedTransaction::new_signed(action, signer, signature, extra);
-        let res = serde_json::to_string(&tx).c(d!())?;
+        Ok(EVMTransactionBuilder {
+            tx: EVMTransactionKind::Unchecked(tx),
+        })
+    }
 
-        let tx_with_tag = EvmRawTxWrapper::wrap(res.as_bytes());
+    /// transfer `amount` of the ERC-20 token of `contract` to `to`,
+    /// as an ethereum transaction signed by `sk` for `chain_id`.
+    ///
+    /// `fee` can not be `EVMFee::Minimal`, an ethereum transaction has its own gas.
+    pub fn new_erc20_transfer(
+        contract: H160,
+        to: H160,
+        amount: U256,
+        sk: String,
+        nonce: U256,
+        chain_id: u64,
+        fee: &EVMFee,
+    ) -> Result<Self> {
+        let kp = secp_pair_from_hex(&sk).c(d!())?;
+        let (gas_limit, gas_price) =
+            fee.gas().c(d!())?.c(d!("gas limit and price required"))?;
+
+        let msg = LegacyTransactionMessage {
+            nonce,
+            gas_price,
+            gas_limit: U256::from(gas_limit),
+            action: TransactionAction::Call(contract),
+            value: U256::zero(),
+            input: erc20_transfer_input(to, amount),
+            chain_id: Some(chain_id),
+        };
+        let sig = kp.sign_prehashed(msg.hash().as_fixed_bytes()).0;
+        // EIP-155
+        let v = sig[64] as u64 % 2 + chain_id * 2 + 35;
+        let signature = TransactionSignature::new(
+            v,
+            H256::from_slice(&sig[0..32]),
+            H256::from_slice(&sig[32..64]),
+        )
+        .c(d!("invalid signature"))?;
+
+        let tx = EthereumTransaction {
+            nonce: msg.nonce,
+            gas_price: msg.gas_price,
+            gas_limit: msg.gas_limit,
+            action: msg.action,
+            value: msg.value,
+            input: msg.input,
+            signature,
+        };
+        let action = Action::Ethereum(EthereumAction::Transact(tx));
+        Ok(EVMTransactionBuilder {
+            tx: EVMTransactionKind::Unchecked(UncheckedTransaction::new_unsigned(
+                action,
+            )),
+        })
+    }
+
+    /// transfer utxo assets to account(ed25519 or ecdsa address) balance.
+    ///
+    /// `transfer_op` is a serialized transfer operation of `keypair`
+    /// sending `amount` of the asset to `BLACK_HOLE_PUBKEY_STAKING`,
+    /// see `TransferOperationBuilder`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_transfer_to_account(
+        keypair: &XfrKeyPair,
+        seq_id: u64,
+        transfer_op: String,
+        amount: u64,
+        address: Option<String>,
+        asset: Option<AssetTypeCode>,
+        lowlevel_data: Option<Vec<u8>>,
+    ) -> Result<Self> {
+        let op = serde_json::from_str::<Operation>(&transfer_op).c(d!())?;
+        let expected_asset = asset.map(|a| a.val).unwrap_or(ASSET_TYPE_FRA);
+        let converted = match &op {
+            Operation::TransferAsset(t) => t
+                .body
+                .outputs
+                .iter()
+                .filter(|o| {
+                    o.record.public_key
+                        == XfrPublicKey::from_noah(&BLACK_HOLE_PUBKEY_STAKING)
+                        && o.record.asset_type
+                            == XfrAssetType::NonConfidential(expected_asset)
+                })
+                .map(|o| match o.record.amount {
+                    XfrAmount::NonConfidential(am) => am,
+                    XfrAmount::Confidential(_) => 0,
+                })
+                .sum::<u64>(),
+            _ => return Err(eg!("not a transfer operation")),
+        };
+        if converted != amount {
+            return Err(eg!(format!(
+                "the transfer operation burns {} instead of {}",
+                converted, amount
+            )));
+        }
+
+        let mut builder = TransactionBuilder::new(seq_id)
+            .add_transfer_operation(transfer_op)
+            .c(d!())?
+            .add_transfer_to_account_operation(
+                amount,
+                address,
+                keypair,
+                asset,
+                lowlevel_data,
+            )
+            .c(d!())?
+            .sign(keypair)
+            .c(d!())?;
+        let tx = builder
+            .get_builder_mut()
+            .build_and_take_transaction()
+            .c(d!())?;
+
+        Ok(EVMTransactionBuilder {
+            tx: EVMTransactionKind::Native(tx),
+        })
+    }
+
+    /// transfer to uxto assets from account(ed25519 or ecdsa address) balance,
+    /// returns the tagged transaction.
+    pub fn new_transfer_to_utxo_from_account(
+        recipient: XfrPublicKey,
+        amount: u64,
+        sk: String,
+        nonce: U256,
+    ) -> Result<String> {
+        let builder =
+            Self::new_transfer_from_account(recipient, amount, sk, nonce).c(d!())?;
+        let tx_with_tag = builder.serialized_transaction().c(d!())?;
         String::from_utf8(tx_with_tag).c(d!())
     }
 
-    pub fn serialized_transaction_base64(&self) -> String {
-        let txn = match &self.tx {
+    /// The evm transactions are tagged by `EvmRawTxWrapper`,
+    /// the utxo ones are submitted as they are.
+    fn serialized_transaction(&self) -> Result<Vec<u8>> {
+        match &self.tx {
             EVMTransactionKind::Unchecked(tx_unchecked) => {
-                serde_json::to_vec(tx_unchecked).unwrap()
+                let txn = serde_json::to_vec(tx_unchecked).c(d!())?;
+                Ok(EvmRawTxWrapper::wrap(&txn))
             }
-        };
+            EVMTransactionKind::Native(tx) => serde_json::to_vec(tx).c(d!()),
+        }
+    }
 
-        let txn_with_tag = EvmRawTxWrapper::wrap(&txn);
-        base64::encode(txn_with_tag)
+    pub fn serialized_transaction_base64(&self) -> String {
+        base64::encode(self.serialized_transaction().unwrap())
+    }
+
+    /// Preview of the transaction, for the user to confirm what will be signed.
+    pub fn describe(&self) -> TxDescription {
+        let tx = match &self.tx {
+            EVMTransactionKind::Unchecked(tx) => tx,
+            EVMTransactionKind::Native(tx) => return describe_transaction(tx),
+        };
+        match (&tx.function, &tx.signature) {
+            (
+                Action::XHub(XhubAction::NonConfidentialTransfer(t)),
+                Some((signer, _, (nonce, fee))),
+            ) => TxDescription {
+                operations: vec![OperationDescription::TransferToUtxo {
+                    signer: signer.to_string(),
+                    nonce: nonce.nonce().to_string(),
+                    outputs: t
+                        .outputs
+                        .iter()
+                        .map(|o| OutputDescription::new(&o.target, o.asset, o.amount))
+                        .collect(),
+                }],
+                fee: fee.fee().map(|f| f.to_string()),
+            },
+            (Action::Ethereum(EthereumAction::Transact(t)), _) => {
+                let op = match (&t.action, erc20_transfer_from_input(&t.input)) {
+                    (TransactionAction::Call(contract), Some((to, amount))) => {
+                        OperationDescription::Erc20Transfer {
+                            contract: h160_to_string(contract),
+                            to: h160_to_string(&to),
+                            amount: amount.to_string(),
+                            nonce: t.nonce.to_string(),
+                        }
+                    }
+                    (action, _) => OperationDescription::EthereumCall {
+                        to: match action {
+                            TransactionAction::Call(to) => Some(h160_to_string(to)),
+                            TransactionAction::Create => None,
+                        },
+                        value: t.value.to_string(),
+                        nonce: t.nonce.to_string(),
+                        input: hex::encode(&t.input),
+                    },
+                };
+                TxDescription {
+                    operations: vec![op],
+                    fee: Some(t.gas_limit.saturating_mul(t.gas_price).to_string()),
+                }
+            }
+            (action, _) => TxDescription {
+                operations: vec![OperationDescription::Other {
+                    name: match action {
+                        Action::Ethereum(_) => "Ethereum",
+                        Action::Evm(_) => "Evm",
+                        Action::XHub(_) => "XHub",
+                        Action::Template(_) => "Template",
+                    }
+                    .to_owned(),
+                }],
+                fee: None,
+            },
+        }
     }
 
     pub fn into_ptr(self) -> *mut Self {
@@ -134,6 +479,26 @@ impl EVMTransactionBuilder {
     }
 }
 
+/// Restore the ethereum private key from a mnemonic along a bip32 derivation path,
+/// `fp_utils::ecdsa::DEFAULT_DERIVATION_PATH` for the first account of most wallets.
+///
+/// The key is hex-encoded, as the `sk` of the constructors of `EVMTransactionBuilder`.
+pub fn restore_evm_secret_from_mnemonic(
+    phrase: &str,
+    lang: &str,
+    path: &str,
+) -> Result<String> {
+    let lang = mnemonic_language(lang).c(d!())?;
+    let (kp, _) = SecpPair::from_phrase_in(lang, phrase, None, path).c(d!())?;
+    Ok(hex::encode(kp.seed()))
+}
+
+/// The `0x` prefixed ethereum address of a hex-encoded private key.
+pub fn evm_address_from_secret(sk: &str) -> Result<String> {
+    let kp = secp_pair_from_hex(sk).c(d!())?;
+    Ok(h160_to_string(&kp.address()))
+}
+
 /// Serialize ethereum address used to abci query nonce.
 pub fn get_serialized_address(address: &str) -> Result<String> {
     let ms = MultiSigner::from_str(address)?;
@@ -0,0 +1,132 @@
+//!
+//! Parsing of the addresses entered by a user, shared by every target
+//! so they accept and reject the same inputs.
+//!
+//! An address is either a utxo public key, as a bech32 `fra1...` address or
+//! in base64, or an EVM account, as `0x` and 40 hex digits. A utxo key and an
+//! EVM account are distinct accounts, so one never converts to the other.
+//!
+//! A validator is given by its tendermint address, 40 hex digits.
+//!
+
+use fp_utils::hashing::keccak_256;
+use globutils::wallet;
+use ledger::staking::{is_valid_tendermint_addr, TendermintAddr};
+use ruc::*;
+use serde::{Deserialize, Serialize};
+use zei::XfrPublicKey;
+
+/// Human readable part of a bech32 wallet address.
+pub const BECH32_PREFIX: &str = "fra1";
+
+/// Format of an address.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AddressFormat {
+    /// bech32 `fra1...` public key
+    Bech32,
+    /// base64 public key
+    Base64,
+    /// `0x` EVM address
+    Evm,
+}
+
+/// A valid address, in the canonical encodings of its format.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParsedAddress {
+    pub format: AddressFormat,
+    /// bech32 address of a public key, `None` for an EVM address
+    pub bech32: Option<String>,
+    /// base64 public key, `None` for an EVM address
+    pub base64: Option<String>,
+    /// checksummed EVM address, `None` for a public key
+    pub evm: Option<String>,
+}
+
+/// Parse `address` in any of the formats, after trimming its whitespaces.
+pub fn parse_address(address: &str) -> Result<ParsedAddress> {
+    let address = address.trim();
+    if address.starts_with("0x") || address.starts_with("0X") {
+        return Ok(ParsedAddress {
+            format: AddressFormat::Evm,
+            bech32: None,
+            base64: None,
+            evm: Some(evm_checksum_address(address).c(d!())?),
+        });
+    }
+    let format = if address.starts_with(BECH32_PREFIX) {
+        AddressFormat::Bech32
+    } else {
+        AddressFormat::Base64
+    };
+    let pk = parse_public_key(address).c(d!())?;
+    Ok(ParsedAddress {
+        format,
+        bech32: Some(wallet::public_key_to_bech32(&pk)),
+        base64: Some(wallet::public_key_to_base64(&pk)),
+        evm: None,
+    })
+}
+
+/// The public key of a bech32 `fra1...` address or of a base64 public key.
+pub fn parse_public_key(address: &str) -> Result<XfrPublicKey> {
+    let address = address.trim();
+    if address.starts_with(BECH32_PREFIX) {
+        wallet::public_key_from_bech32(address).c(d!("invalid bech32 address"))
+    } else {
+        wallet::public_key_from_base64(address).c(d!("invalid address"))
+    }
+}
+
+/// The EIP-55 checksummed form of the EVM `address`.
+///
+/// An address in a single case is taken as is, a mixed case one must match
+/// its checksum, so a typo is caught.
+pub fn evm_checksum_address(address: &str) -> Result<String> {
+    let address = address.trim();
+    let hex = address
+        .strip_prefix("0x")
+        .or_else(|| address.strip_prefix("0X"))
+        .c(d!("an evm address starts with 0x"))?;
+    if hex.len() != 40 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(eg!("an evm address has 40 hex digits"));
+    }
+    let lower = hex.to_ascii_lowercase();
+    let hash = keccak_256(lower.as_bytes());
+    let checksummed = lower
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            let nibble = (hash[i / 2] >> (if i % 2 == 0 { 4 } else { 0 })) & 0x0f;
+            if nibble >= 8 {
+                c.to_ascii_uppercase()
+            } else {
+                c
+            }
+        })
+        .collect::<String>();
+
+    let mixed_case = hex.chars().any(|c| c.is_ascii_lowercase())
+        && hex.chars().any(|c| c.is_ascii_uppercase());
+    if mixed_case && hex != checksummed {
+        return Err(eg!("invalid evm address checksum"));
+    }
+    Ok(format!("0x{}", checksummed))
+}
+
+/// The tendermint address of a validator, in the uppercase hex of the ledger.
+///
+/// The `0x` prefix and the case of the hex digits are optional.
+pub fn parse_validator_address(address: &str) -> Result<TendermintAddr> {
+    let address = address.trim();
+    let address = address
+        .strip_prefix("0x")
+        .or_else(|| address.strip_prefix("0X"))
+        .unwrap_or(address)
+        .to_ascii_uppercase();
+    if !address.chars().all(|c| c.is_ascii_hexdigit())
+        || !is_valid_tendermint_addr(&address)
+    {
+        return Err(eg!("invalid validator address"));
+    }
+    Ok(address)
+}
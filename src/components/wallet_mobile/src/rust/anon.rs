@@ -0,0 +1,273 @@
+//!
+//! Anonymous transfers: the anonymous records (ABARs) of an account,
+//! their nullifiers, and the builder of a transfer between them.
+//!
+//! An ABAR is given by its commitment, in base58, and is opened with its
+//! `AxfrOwnerMemo`, from the `get_abar_memo/{atxo_sid}` route of the query server.
+//! Spending it also needs its Merkle proof, from the `get_abar_proof/{atxo_sid}` route.
+//!
+//! The conversions of a record to an ABAR and back are operations of the
+//! `TransactionBuilder`, see `add_operation_bar_to_abar` and `add_operation_abar_to_bar`.
+//!
+
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use super::address::parse_public_key;
+use finutils::txn_builder::AnonTransferOperationBuilder as PlatformAnonTransferOperationBuilder;
+use globutils::wallet;
+use ledger::data_model::{AssetTypeCode, BAR_TO_ABAR_TX_FEE_MIN};
+use rand_chacha::ChaChaRng;
+use rand_core::SeedableRng;
+use ruc::*;
+use serde::{Deserialize, Serialize};
+use zei::{
+    noah_api::anon_xfr::{
+        nullify,
+        structs::{
+            AnonAssetRecord, AxfrOwnerMemo, Commitment, MTLeafInfo, OpenAnonAssetRecord,
+            OpenAnonAssetRecordBuilder,
+        },
+    },
+    XfrKeyPair, XfrPublicKey,
+};
+
+/// An ABAR to open or to spend.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AnonInput {
+    /// base58 commitment of the ABAR
+    pub commitment: String,
+    pub owner_memo: AxfrOwnerMemo,
+    /// needed to spend the ABAR, not to open it
+    #[serde(default)]
+    pub mt_leaf_info: Option<MTLeafInfo>,
+}
+
+/// An opened ABAR.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OpenedAbar {
+    pub commitment: String,
+    /// base64 asset type
+    pub asset_type: String,
+    pub amount: u64,
+}
+
+/// Fee smaller than this value will be denied, for a transaction converting records to ABARs.
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub fn fra_get_minimal_fee_for_bar_to_abar() -> u64 {
+    BAR_TO_ABAR_TX_FEE_MIN
+}
+
+/// Anon fee for a given number of inputs & outputs
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub fn get_anon_fee(n_inputs: u32, n_outputs: u32) -> u32 {
+    PlatformAnonTransferOperationBuilder::get_anon_fee(n_inputs, n_outputs)
+}
+
+/// The public key receiving an ABAR, from its bech32 address or base64 encoding.
+pub fn anon_public_key_from_str(key: &str) -> Result<XfrPublicKey> {
+    parse_public_key(key).c(d!())
+}
+
+/// The commitment of a base58 string.
+pub fn commitment_from_base58(commitment: &str) -> Result<Commitment> {
+    wallet::commitment_from_base58(commitment.trim()).c(d!())
+}
+
+impl AnonInput {
+    /// The ABAR opened with `keypair`, with its Merkle proof if any.
+    pub fn open(&self, keypair: &XfrKeyPair) -> Result<OpenAnonAssetRecord> {
+        let abar = AnonAssetRecord {
+            commitment: commitment_from_base58(&self.commitment).c(d!())?,
+        };
+        let builder = OpenAnonAssetRecordBuilder::from_abar(
+            &abar,
+            self.owner_memo.clone(),
+            &keypair.into_noah(),
+        )
+        .c(d!())?;
+        match self.mt_leaf_info.clone() {
+            Some(info) => builder.mt_leaf_info(info).build().c(d!()),
+            None => builder.build().c(d!()),
+        }
+    }
+
+    /// The ABAR opened with `keypair`, to spend it.
+    pub fn open_to_spend(&self, keypair: &XfrKeyPair) -> Result<OpenAnonAssetRecord> {
+        if self.mt_leaf_info.is_none() {
+            return Err(eg!(format!("no merkle proof of {}", self.commitment)));
+        }
+        self.open(keypair).c(d!())
+    }
+}
+
+/// Opens `input` with `keypair`, fails if it is owned by another key.
+pub fn open_abar(input: &AnonInput, keypair: &XfrKeyPair) -> Result<OpenedAbar> {
+    let oabar = input.open(keypair).c(d!())?;
+    Ok(OpenedAbar {
+        commitment: input.commitment.clone(),
+        asset_type: AssetTypeCode {
+            val: oabar.get_asset_type(),
+        }
+        .to_base64(),
+        amount: oabar.get_amount(),
+    })
+}
+
+/// The base58 nullifier of `input`, which is published when it is spent,
+/// so a wallet can tell its spent ABARs.
+pub fn gen_nullifier_hash(input: &AnonInput, keypair: &XfrKeyPair) -> Result<String> {
+    let oabar = input.open_to_spend(keypair).c(d!())?;
+    let uid = input
+        .mt_leaf_info
+        .as_ref()
+        .map(|info| info.uid)
+        .unwrap_or_default();
+    let n = nullify(
+        &keypair.into_noah(),
+        oabar.get_amount(),
+        oabar.get_asset_type().as_scalar(),
+        uid,
+    )
+    .c(d!())?;
+    Ok(wallet::nullifier_to_base58(&n.0))
+}
+
+#[derive(Clone)]
+/// Structure that enables clients to construct transfers between ABARs.
+///
+/// The inputs and outputs are kept until `build`, which generates the proof,
+/// the remainders going back to the key given to `add_keypair`.
+pub struct AnonTransferOperationBuilder {
+    seq_id: u64,
+    inputs: Vec<OpenAnonAssetRecord>,
+    outputs: Vec<OpenAnonAssetRecord>,
+    keypair: Option<XfrKeyPair>,
+    commitments: Vec<Commitment>,
+    transaction: Option<String>,
+}
+
+impl AnonTransferOperationBuilder {
+    /// Create a new anonymous transfer builder.
+    pub fn new(seq_id: u64) -> Self {
+        AnonTransferOperationBuilder {
+            seq_id,
+            inputs: vec![],
+            outputs: vec![],
+            keypair: None,
+            commitments: vec![],
+            transaction: None,
+        }
+    }
+
+    /// Spend `input`, owned by `keypair`, which needs its Merkle proof.
+    pub fn add_input(
+        mut self,
+        input: &AnonInput,
+        keypair: &XfrKeyPair,
+    ) -> Result<AnonTransferOperationBuilder> {
+        self.check_not_built().c(d!())?;
+        let oabar = input.open_to_spend(keypair).c(d!())?;
+        self.inputs.push(oabar);
+        // checks the count of the inputs
+        self.platform_builder().c(d!())?;
+        Ok(self)
+    }
+
+    /// Send `amount` of the base64 `asset_type` to `to`.
+    pub fn add_output(
+        mut self,
+        amount: u64,
+        asset_type: &str,
+        to: &XfrPublicKey,
+    ) -> Result<AnonTransferOperationBuilder> {
+        self.check_not_built().c(d!())?;
+        let code = AssetTypeCode::new_from_base64(asset_type).c(d!())?;
+        let mut prng = ChaChaRng::from_entropy();
+        let oabar = OpenAnonAssetRecordBuilder::new()
+            .amount(amount)
+            .asset_type(code.val)
+            .pub_key(&to.into_noah())
+            .finalize(&mut prng)
+            .c(d!())?
+            .build()
+            .c(d!())?;
+        self.outputs.push(oabar);
+        // checks the count of the outputs
+        self.platform_builder().c(d!())?;
+        Ok(self)
+    }
+
+    /// The key of the inputs, which generates the nullifiers and receives the remainders.
+    pub fn add_keypair(mut self, keypair: &XfrKeyPair) -> AnonTransferOperationBuilder {
+        self.keypair = Some(keypair.clone());
+        self
+    }
+
+    /// The FRA missing from the inputs to pay the fee of the transfer.
+    pub fn get_expected_fee(&self) -> Result<u64> {
+        self.platform_builder()
+            .c(d!())?
+            .extra_fee_estimation()
+            .c(d!())
+    }
+
+    /// The fee of the transfer, with its remainders.
+    pub fn get_total_fee_estimate(&self) -> Result<u64> {
+        self.platform_builder()
+            .c(d!())?
+            .get_total_fee_estimation()
+            .c(d!())
+    }
+
+    /// Generates the proof of the transfer, it can not be changed afterwards.
+    pub fn build(mut self) -> Result<AnonTransferOperationBuilder> {
+        self.check_not_built().c(d!())?;
+        tracing::debug!(
+            inputs = self.inputs.len(),
+            outputs = self.outputs.len(),
+            "build anon transfer"
+        );
+        let mut builder = self.platform_builder().c(d!())?;
+        builder.build().c(d!("error in txn_builder: build"))?;
+        builder.build_txn().c(d!())?;
+        self.commitments = builder.get_commitments();
+        self.transaction = Some(builder.serialize_str().c(d!())?);
+        Ok(self)
+    }
+
+    /// The base58 commitments of the outputs, remainders included, once built.
+    pub fn get_commitments(&self) -> Vec<String> {
+        self.commitments
+            .iter()
+            .map(wallet::commitment_to_base58)
+            .collect()
+    }
+
+    /// The serialized transaction of the transfer, once built.
+    pub fn transaction(&self) -> Result<String> {
+        self.transaction.clone().c(d!("Anon transfer not built"))
+    }
+
+    fn check_not_built(&self) -> Result<()> {
+        if self.transaction.is_some() {
+            return Err(eg!("Anon transfer already built"));
+        }
+        Ok(())
+    }
+
+    fn platform_builder(&self) -> Result<PlatformAnonTransferOperationBuilder> {
+        let mut builder =
+            PlatformAnonTransferOperationBuilder::new_from_seq_id(self.seq_id);
+        for input in self.inputs.iter() {
+            builder.add_input(input.clone()).c(d!())?;
+        }
+        for output in self.outputs.iter() {
+            builder.add_output(output.clone()).c(d!())?;
+        }
+        if let Some(keypair) = self.keypair.as_ref() {
+            builder.add_keypair(keypair.clone());
+        }
+        Ok(builder)
+    }
+}
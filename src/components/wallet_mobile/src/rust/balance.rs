@@ -0,0 +1,155 @@
+//!
+//! Opening of the records of an account in batch, to show its balances.
+//!
+//! Opening a confidential record costs a decryption, so the records are
+//! opened by chunks and the progress reported after each one: an account
+//! with thousands of utxos can show a progress bar, and be cancelled.
+//!
+//! A record that can not be opened, eg. owned by another key, is reported
+//! with its error and left out of the balances, the batch goes on.
+//!
+
+use ledger::data_model::{AssetTypeCode, Utxo};
+use ruc::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use zei::{
+    noah_api::xfr::{
+        asset_record::open_blind_asset_record as open_bar,
+        structs::{XfrAmount, XfrAssetType},
+    },
+    BlindAssetRecord, OwnerMemo, XfrKeyPair,
+};
+
+/// Count of the records opened between two progress reports, by default.
+pub const DEFAULT_CHUNK_SIZE: usize = 256;
+
+/// A record to open with its memo, which is `None` for a non-confidential one.
+///
+/// It is deserialized from an `OwnedUtxo` of the query server, or from
+/// `{"record": <BlindAssetRecord>, "owner_memo": <OwnerMemo>}`.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(from = "RecordInput")]
+pub struct RecordToOpen {
+    pub sid: Option<u64>,
+    pub record: BlindAssetRecord,
+    pub owner_memo: Option<OwnerMemo>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RecordInput {
+    Utxo {
+        sid: u64,
+        utxo: Utxo,
+        owner_memo: Option<OwnerMemo>,
+    },
+    Record {
+        #[serde(default)]
+        sid: Option<u64>,
+        record: BlindAssetRecord,
+        owner_memo: Option<OwnerMemo>,
+    },
+}
+
+impl From<RecordInput> for RecordToOpen {
+    fn from(input: RecordInput) -> Self {
+        match input {
+            RecordInput::Utxo {
+                sid,
+                utxo,
+                owner_memo,
+            } => RecordToOpen {
+                sid: Some(sid),
+                record: utxo.0.record,
+                owner_memo,
+            },
+            RecordInput::Record {
+                sid,
+                record,
+                owner_memo,
+            } => RecordToOpen {
+                sid,
+                record,
+                owner_memo,
+            },
+        }
+    }
+}
+
+/// An opened record, `asset_type` and `amount` are `None` if it failed with `error`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OpenedRecord {
+    pub sid: Option<u64>,
+    pub asset_type: Option<String>,
+    pub amount: Option<String>,
+    /// if its amount or asset type was confidential
+    pub confidential: bool,
+    pub error: Option<String>,
+}
+
+/// The opened records, in their order, and the balance of each asset type.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OpenedRecords {
+    pub records: Vec<OpenedRecord>,
+    /// base64 asset type to decimal amount
+    pub balances: BTreeMap<String, String>,
+}
+
+/// Open `records` with `keypair`, `chunk_size` at a time.
+///
+/// `progress` is given the count of the opened records and their total after
+/// each chunk, the batch is cancelled with an error if it returns `false`.
+pub fn open_records(
+    keypair: &XfrKeyPair,
+    records: &[RecordToOpen],
+    chunk_size: usize,
+    progress: &mut dyn FnMut(usize, usize) -> bool,
+) -> Result<OpenedRecords> {
+    let keypair = keypair.into_noah();
+    let total = records.len();
+    let mut opened = Vec::with_capacity(total);
+    let mut balances = BTreeMap::<String, u128>::new();
+
+    for chunk in records.chunks(chunk_size.max(1)) {
+        for r in chunk {
+            let confidential = matches!(r.record.amount, XfrAmount::Confidential(_))
+                || matches!(r.record.asset_type, XfrAssetType::Confidential(_));
+            match open_bar(&r.record.into_noah(), &r.owner_memo, &keypair) {
+                Ok(oar) => {
+                    let asset_type = AssetTypeCode {
+                        val: oar.asset_type,
+                    }
+                    .to_base64();
+                    *balances.entry(asset_type.clone()).or_default() +=
+                        oar.amount as u128;
+                    opened.push(OpenedRecord {
+                        sid: r.sid,
+                        asset_type: Some(asset_type),
+                        amount: Some(oar.amount.to_string()),
+                        confidential,
+                        error: None,
+                    });
+                }
+                Err(e) => opened.push(OpenedRecord {
+                    sid: r.sid,
+                    asset_type: None,
+                    amount: None,
+                    confidential,
+                    error: Some(format!("{:?}", e)),
+                }),
+            }
+        }
+        if !progress(opened.len(), total) {
+            return Err(eg!(format!("cancelled at {}/{}", opened.len(), total)));
+        }
+    }
+
+    Ok(OpenedRecords {
+        records: opened,
+        balances: balances
+            .into_iter()
+            .map(|(asset, amount)| (asset, amount.to_string()))
+            .collect(),
+    })
+}
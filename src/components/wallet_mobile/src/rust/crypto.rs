@@ -4,6 +4,7 @@ use wasm_bindgen::prelude::*;
 use super::data_model::*;
 use aes_gcm::aead::{generic_array::GenericArray, Aead, KeyInit};
 use aes_gcm::Aes256Gcm;
+use bip0039::{Language, Mnemonic};
 use credentials::{
     credential_commit, credential_issuer_key_gen, credential_open_commitment,
     credential_reveal, credential_sign, credential_user_key_gen, credential_verify,
@@ -20,7 +21,8 @@ use ledger::{
 use rand_chacha::ChaChaRng;
 use rand_core::SeedableRng;
 use ring::pbkdf2;
-use ruc::{d, Result, RucResult};
+use ruc::{d, eg, Result, RucResult};
+use serde::{Deserialize, Serialize};
 use std::num::NonZeroU32;
 use std::str;
 use zei::{
@@ -458,6 +460,164 @@ pub fn rs_restore_keypair_from_mnemonic_bip49(
     wallet::restore_keypair_from_mnemonic_bip49(phrase, lang, &path.into())
 }
 
+/// Restore the XfrKeyPair from a mnemonic along a derivation path,
+/// "m/44'/coin'/account'/change/address" or "m/49'/coin'/account'/change/address".
+pub fn rs_restore_keypair_from_mnemonic_path(
+    phrase: &str,
+    lang: &str,
+    path: &str,
+) -> Result<XfrKeyPair> {
+    let (purpose, path) = parse_bip_path(path).c(d!())?;
+    match purpose {
+        44 => rs_restore_keypair_from_mnemonic_bip44(phrase, lang, &path),
+        49 => rs_restore_keypair_from_mnemonic_bip49(phrase, lang, &path),
+        _ => Err(eg!(format!("unsupported purpose: {}", purpose))),
+    }
+}
+
+/// Split "m/purpose'/coin'/account'/change/address" into the purpose and a `BipPath`.
+fn parse_bip_path(path: &str) -> Result<(u32, BipPath)> {
+    let levels = path
+        .strip_prefix("m/")
+        .c(d!("a derivation path starts with 'm/'"))?
+        .split('/')
+        .enumerate()
+        .map(|(i, level)| {
+            // the first three levels are hardened, the last two are not
+            let idx = match (i < 3, level.strip_suffix('\'')) {
+                (true, Some(idx)) | (false, None) => idx,
+                _ => return Err(eg!(format!("invalid derivation level: {}", level))),
+            };
+            idx.parse::<u32>().c(d!())
+        })
+        .collect::<Result<Vec<_>>>()?;
+    match levels[..] {
+        [purpose, coin, account, change, address] => {
+            Ok((purpose, BipPath::new(coin, account, change, address)))
+        }
+        _ => Err(eg!("a derivation path has 5 levels")),
+    }
+}
+
+/// Language of a mnemonic, in the codes of `rs_generate_mnemonic_custom`.
+pub(crate) fn mnemonic_language(lang: &str) -> Result<Language> {
+    match lang {
+        "en" => Ok(Language::English),
+        "zh" => Ok(Language::SimplifiedChinese),
+        "zh_traditional" => Ok(Language::TraditionalChinese),
+        "fr" => Ok(Language::French),
+        "it" => Ok(Language::Italian),
+        "ko" => Ok(Language::Korean),
+        "sp" => Ok(Language::Spanish),
+        "jp" => Ok(Language::Japanese),
+        _ => Err(eg!(format!("unsupported language: {}", lang))),
+    }
+}
+
+/// Result of the validation of a user-entered mnemonic.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MnemonicCheck {
+    /// the phrase can restore a key pair
+    pub valid: bool,
+    /// the words not in the word list of the language, in the order of the phrase
+    pub unknown_words: Vec<UnknownWord>,
+    /// why a phrase with no unknown word is invalid, eg. a wrong word count or checksum
+    pub error: Option<String>,
+}
+
+/// A word of a mnemonic missing from the word list.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnknownWord {
+    /// position of the word in the phrase, from 0
+    pub index: usize,
+    pub word: String,
+    /// the closest words of the word list, the best first
+    pub suggestions: Vec<String>,
+}
+
+/// Count of the suggestions of an unknown word.
+const MNEMONIC_SUGGESTIONS: usize = 5;
+
+/// Validate a user-entered mnemonic, suggesting replacements for its misspelled words.
+/// - @param `lang`: acceptable value are one of [ "en", "zh", "zh_traditional", "fr", "it", "ko", "sp", "jp" ]
+pub fn rs_validate_mnemonic(phrase: &str, lang: &str) -> Result<MnemonicCheck> {
+    let lang = mnemonic_language(lang).c(d!())?;
+    let words = lang.word_list();
+
+    let unknown_words = phrase
+        .split_whitespace()
+        .enumerate()
+        .filter(|(_, word)| !words.contains(word))
+        .map(|(index, word)| UnknownWord {
+            index,
+            word: word.to_owned(),
+            suggestions: mnemonic_suggestions(words, word),
+        })
+        .collect::<Vec<_>>();
+    if !unknown_words.is_empty() {
+        return Ok(MnemonicCheck {
+            valid: false,
+            unknown_words,
+            error: None,
+        });
+    }
+
+    let phrase = phrase.split_whitespace().collect::<Vec<_>>().join(" ");
+    Ok(match Mnemonic::from_phrase_in(lang, phrase.as_str()) {
+        Ok(_) => MnemonicCheck {
+            valid: true,
+            ..Default::default()
+        },
+        Err(e) => MnemonicCheck {
+            valid: false,
+            error: Some(e.to_string()),
+            ..Default::default()
+        },
+    })
+}
+
+/// The words of `words` starting with `word`, then the ones with the fewest edits.
+fn mnemonic_suggestions(words: &[&str], word: &str) -> Vec<String> {
+    let mut candidates = words
+        .iter()
+        .map(|w| {
+            let dist = if w.starts_with(word) {
+                0
+            } else {
+                edit_distance(w, word)
+            };
+            (dist, *w)
+        })
+        .filter(|(dist, _)| *dist <= 2)
+        .collect::<Vec<_>>();
+    candidates.sort();
+    candidates
+        .into_iter()
+        .take(MNEMONIC_SUGGESTIONS)
+        .map(|(_, w)| w.to_owned())
+        .collect()
+}
+
+/// Levenshtein distance of two words.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut row = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cur = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                prev
+            } else {
+                1 + prev.min(row[j]).min(cur)
+            };
+            prev = cur;
+        }
+    }
+    row[b.len()]
+}
+
 /// ID of FRA, in `String` format.
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
 pub fn fra_get_asset_code() -> String {
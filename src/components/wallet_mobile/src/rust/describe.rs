@@ -0,0 +1,160 @@
+//!
+//! Human readable previews of the transactions of the builders,
+//! shown by a wallet on the confirmation screen before signing.
+//!
+//! Amounts are decimal strings in the smallest unit of their asset,
+//! as the account ones do not fit in a javascript number.
+//!
+
+use super::crypto::public_key_to_bech32;
+use fp_types::{crypto::MultiSigner, H160};
+use ledger::data_model::{
+    AssetTypeCode, Operation, Transaction, ASSET_TYPE_FRA, BLACK_HOLE_PUBKEY,
+};
+use serde::{Deserialize, Serialize};
+use zei::{
+    noah_api::xfr::structs::{AssetType, XfrAmount, XfrAssetType},
+    BlindAssetRecord, XfrPublicKey,
+};
+
+/// Preview of a transaction.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TxDescription {
+    pub operations: Vec<OperationDescription>,
+    /// FRA burned as fee, for an ethereum transaction the most its gas can cost,
+    /// `None` for the minimal fee of an account transaction.
+    pub fee: Option<String>,
+}
+
+/// Preview of an operation of a transaction.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+pub enum OperationDescription {
+    /// utxo transfer, the fee outputs are counted in `TxDescription::fee`
+    TransferAsset { outputs: Vec<OutputDescription> },
+    /// utxo assets converted to the balance of `receiver`
+    ConvertAccount {
+        receiver: String,
+        asset: String,
+        amount: String,
+    },
+    /// account balance transferred to utxo outputs
+    TransferToUtxo {
+        signer: String,
+        nonce: String,
+        outputs: Vec<OutputDescription>,
+    },
+    /// transfer of the ERC-20 token of `contract`
+    Erc20Transfer {
+        contract: String,
+        to: String,
+        amount: String,
+        nonce: String,
+    },
+    /// any other ethereum transaction, `to` is `None` for a contract creation
+    EthereumCall {
+        to: Option<String>,
+        value: String,
+        nonce: String,
+        input: String,
+    },
+    /// an operation with no preview, see `Operation::KINDS`
+    Other { name: String },
+}
+
+/// Preview of an output, `asset` and `amount` are `None` if confidential.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutputDescription {
+    pub recipient: String,
+    pub asset: Option<String>,
+    pub amount: Option<String>,
+}
+
+impl OutputDescription {
+    pub(crate) fn new(recipient: &XfrPublicKey, asset: AssetType, amount: u64) -> Self {
+        OutputDescription {
+            recipient: public_key_to_bech32(recipient),
+            asset: Some(AssetTypeCode { val: asset }.to_base64()),
+            amount: Some(amount.to_string()),
+        }
+    }
+
+    fn from_record(record: &BlindAssetRecord) -> Self {
+        OutputDescription {
+            recipient: public_key_to_bech32(&record.public_key),
+            asset: match record.asset_type {
+                XfrAssetType::NonConfidential(at) => {
+                    Some(AssetTypeCode { val: at }.to_base64())
+                }
+                XfrAssetType::Confidential(_) => None,
+            },
+            amount: match record.amount {
+                XfrAmount::NonConfidential(am) => Some(am.to_string()),
+                XfrAmount::Confidential(_) => None,
+            },
+        }
+    }
+}
+
+/// Display form of an account address, bech32 or hex.
+pub(crate) fn multi_signer_to_string(signer: &MultiSigner) -> String {
+    match signer {
+        MultiSigner::Xfr(pk) => public_key_to_bech32(pk),
+        MultiSigner::Ethereum(address) => h160_to_string(address),
+    }
+}
+
+/// `0x` prefixed hex form of an EVM address.
+pub(crate) fn h160_to_string(address: &H160) -> String {
+    format!("{:?}", address)
+}
+
+/// Preview of a utxo transaction, its fee is the FRA sent to `BLACK_HOLE_PUBKEY`.
+pub fn describe_transaction(tx: &Transaction) -> TxDescription {
+    let fee_key = XfrPublicKey::from_noah(&BLACK_HOLE_PUBKEY);
+    let mut fee = 0u64;
+
+    let operations = tx
+        .body
+        .operations
+        .iter()
+        .map(|op| match op {
+            Operation::TransferAsset(t) => {
+                let outputs = t
+                    .body
+                    .outputs
+                    .iter()
+                    .filter(|o| {
+                        let is_fee = o.record.public_key == fee_key
+                            && o.record.asset_type
+                                == XfrAssetType::NonConfidential(ASSET_TYPE_FRA);
+                        if let (true, XfrAmount::NonConfidential(am)) =
+                            (is_fee, o.record.amount)
+                        {
+                            fee = fee.saturating_add(am);
+                        }
+                        !is_fee
+                    })
+                    .map(|o| OutputDescription::from_record(&o.record))
+                    .collect();
+                OperationDescription::TransferAsset { outputs }
+            }
+            Operation::ConvertAccount(c) => OperationDescription::ConvertAccount {
+                receiver: multi_signer_to_string(&c.receiver),
+                asset: AssetTypeCode {
+                    val: c.asset_type.unwrap_or(ASSET_TYPE_FRA),
+                }
+                .to_base64(),
+                amount: c.value.to_string(),
+            },
+            op => OperationDescription::Other {
+                name: op.kind().to_owned(),
+            },
+        })
+        .collect();
+
+    TxDescription {
+        operations,
+        fee: Some(fee.to_string()),
+    }
+}
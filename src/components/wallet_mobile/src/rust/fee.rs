@@ -0,0 +1,272 @@
+//!
+//! Estimation of the fee of a draft transaction, shown before signing.
+//!
+//! The fee of a utxo or account transaction is a constant of the chain,
+//! the one of an ethereum transaction is its gas cost, estimated with the
+//! web3 endpoint of the node, eg. `https://prod-mainnet.prod.findora.org:8545`.
+//!
+//! The estimated fees are in the smallest FRA unit of the utxos, 6 decimals,
+//! rounded up.
+//!
+
+use super::account::{erc20_transfer_input, parse_evm_address, EVMFee};
+use super::network::{HttpMethod, HttpRequest, HttpTransport};
+use fp_types::U256;
+use ledger::data_model::{Operation, Transaction, BAR_TO_ABAR_TX_FEE_MIN, TX_FEE_MIN};
+use ruc::*;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+/// Minimal fee of an account transaction, in wei, see `StableTxFee` of the baseapp.
+pub const ACCOUNT_MIN_FEE_WEI: u64 = 10_000_000_000_000_000;
+
+/// Wei in the smallest FRA unit of the utxos.
+const WEI_PER_UNIT: u64 = 1_000_000_000_000;
+
+/// Margin added to the estimated gas, in percent,
+/// as the state may change before the transaction is executed.
+const GAS_LIMIT_MARGIN: u64 = 20;
+
+/// A transaction to estimate the fee of.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+pub enum DraftTx {
+    /// a utxo transaction, as serialized by `TransactionBuilder::transaction`
+    Utxo { tx: String },
+    /// an account transaction, see `EVMTransactionBuilder::new_transfer_from_account`
+    Account,
+    /// an ethereum call, as given to `eth_estimateGas`, `value` and `data` are hex
+    Ethereum {
+        from: String,
+        to: Option<String>,
+        #[serde(default)]
+        value: Option<String>,
+        #[serde(default)]
+        data: Option<String>,
+    },
+    /// an ERC-20 transfer, see `EVMTransactionBuilder::new_erc20_transfer`,
+    /// `amount` is decimal
+    Erc20Transfer {
+        from: String,
+        contract: String,
+        to: String,
+        amount: String,
+    },
+}
+
+/// Gas prices of the chain, `base_fee` is `None` without EIP-1559.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GasPrices {
+    pub gas_price: U256,
+    pub base_fee: Option<U256>,
+}
+
+/// Estimated fee of a transaction.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeEstimate {
+    /// the fee, in the smallest FRA unit
+    pub fee: String,
+    /// the fee to give the `EVMTransactionBuilder`, `None` for a utxo transaction
+    pub evm_fee: Option<EVMFee>,
+}
+
+/// The minimal fee of a utxo transaction, as checked by `Transaction::check_fee`.
+pub fn utxo_min_fee(tx: &Transaction) -> u64 {
+    if tx
+        .body
+        .operations
+        .iter()
+        .any(|op| matches!(op, Operation::BarToAbar(_)))
+    {
+        BAR_TO_ABAR_TX_FEE_MIN
+    } else {
+        TX_FEE_MIN
+    }
+}
+
+impl DraftTx {
+    /// The fee of a draft needing no gas estimation, `None` for an ethereum one.
+    pub fn fixed_fee(&self) -> Result<Option<FeeEstimate>> {
+        match self {
+            DraftTx::Utxo { tx } => {
+                let tx = serde_json::from_str::<Transaction>(tx).c(d!())?;
+                Ok(Some(FeeEstimate {
+                    fee: utxo_min_fee(&tx).to_string(),
+                    evm_fee: None,
+                }))
+            }
+            DraftTx::Account => Ok(Some(FeeEstimate {
+                fee: wei_to_units(U256::from(ACCOUNT_MIN_FEE_WEI)).to_string(),
+                evm_fee: Some(EVMFee::Minimal),
+            })),
+            _ => Ok(None),
+        }
+    }
+
+    /// The call object of `eth_estimateGas`, for an ethereum draft.
+    pub fn call(&self) -> Result<Value> {
+        let (from, to, value, data) = match self {
+            DraftTx::Ethereum {
+                from,
+                to,
+                value,
+                data,
+            } => (from.clone(), to.clone(), value.clone(), data.clone()),
+            DraftTx::Erc20Transfer {
+                from,
+                contract,
+                to,
+                amount,
+            } => {
+                let to = parse_evm_address(to).c(d!())?;
+                let amount =
+                    U256::from_dec_str(amount).map_err(|e| eg!(format!("{:?}", e)))?;
+                let data = erc20_transfer_input(to, amount);
+                (
+                    from.clone(),
+                    Some(contract.clone()),
+                    None,
+                    Some(format!("0x{}", hex::encode(data))),
+                )
+            }
+            _ => return Err(eg!("not an ethereum transaction")),
+        };
+        let mut call = json!({ "from": from });
+        if let Some(to) = to {
+            call["to"] = json!(to);
+        }
+        if let Some(value) = value {
+            call["value"] = json!(value);
+        }
+        if let Some(data) = data {
+            call["data"] = json!(data);
+        }
+        Ok(call)
+    }
+}
+
+/// A JSON-RPC request to the web3 endpoint `web3`.
+pub fn rpc_request(web3: &str, method: &str, params: Value) -> HttpRequest {
+    HttpRequest {
+        method: HttpMethod::Post,
+        url: web3.trim().trim_end_matches('/').to_owned(),
+        body: Some(
+            json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": method,
+                "params": params,
+            })
+            .to_string(),
+        ),
+    }
+}
+
+/// The result of a JSON-RPC response.
+pub fn parse_rpc_response<T: DeserializeOwned>(resp: &[u8]) -> Result<T> {
+    let mut resp = serde_json::from_slice::<Value>(resp).c(d!())?;
+    if let Some(e) = resp.get("error") {
+        return Err(eg!(format!("rpc error: {}", e)));
+    }
+    serde_json::from_value(resp["result"].take()).c(d!())
+}
+
+/// The base fee of the next block, from the result of `eth_feeHistory`.
+pub fn parse_base_fee(fee_history: &Value) -> Option<U256> {
+    fee_history["baseFeePerGas"]
+        .as_array()
+        .and_then(|fees| fees.last())
+        .and_then(|fee| serde_json::from_value(fee.clone()).ok())
+}
+
+/// The fee of an ethereum transaction of `estimated_gas`, at `prices`.
+pub fn gas_fee(estimated_gas: U256, prices: &GasPrices) -> Result<FeeEstimate> {
+    if estimated_gas > U256::from(u64::MAX / (100 + GAS_LIMIT_MARGIN)) {
+        return Err(eg!("estimated gas overflow"));
+    }
+    let gas_limit = estimated_gas.as_u64() * (100 + GAS_LIMIT_MARGIN) / 100;
+    let evm_fee = match prices.base_fee {
+        Some(base_fee) => {
+            let priority = prices.gas_price.saturating_sub(base_fee);
+            EVMFee::Eip1559 {
+                gas_limit,
+                // room for the base fee to double
+                max_fee_per_gas: base_fee
+                    .saturating_mul(U256::from(2u64))
+                    .saturating_add(priority),
+                max_priority_fee_per_gas: priority,
+                base_fee,
+            }
+        }
+        None => EVMFee::Legacy {
+            gas_limit,
+            gas_price: prices.gas_price,
+        },
+    };
+    let fee = evm_fee.amount().c(d!())?.c(d!())?;
+    Ok(FeeEstimate {
+        fee: wei_to_units(fee).to_string(),
+        evm_fee: Some(evm_fee),
+    })
+}
+
+/// Wei converted to the smallest FRA unit, rounded up.
+fn wei_to_units(wei: U256) -> U256 {
+    let unit = U256::from(WEI_PER_UNIT);
+    let (units, rem) = wei.div_mod(unit);
+    if rem.is_zero() {
+        units
+    } else {
+        units.saturating_add(U256::one())
+    }
+}
+
+/// Estimates the fees with the web3 endpoint of a node, through `transport`.
+#[derive(Clone)]
+pub struct FeeEstimator {
+    web3: String,
+    transport: Arc<dyn HttpTransport>,
+}
+
+impl FeeEstimator {
+    pub fn new(web3: &str, transport: Arc<dyn HttpTransport>) -> Self {
+        FeeEstimator {
+            web3: web3.to_owned(),
+            transport,
+        }
+    }
+
+    /// The fee of `draft`, at the current gas prices.
+    pub fn estimate(&self, draft: &DraftTx) -> Result<FeeEstimate> {
+        if let Some(fee) = draft.fixed_fee().c(d!())? {
+            return Ok(fee);
+        }
+        let gas = self
+            .rpc::<U256>("eth_estimateGas", json!([draft.call().c(d!())?]))
+            .c(d!())?;
+        gas_fee(gas, &self.gas_prices().c(d!())?).c(d!())
+    }
+
+    /// The current gas prices of the chain.
+    pub fn gas_prices(&self) -> Result<GasPrices> {
+        let gas_price = self.rpc::<U256>("eth_gasPrice", json!([])).c(d!())?;
+        // a node without EIP-1559 fails or returns no base fee
+        let base_fee = self
+            .rpc::<Value>("eth_feeHistory", json!(["0x1", "latest", []]))
+            .ok()
+            .and_then(|history| parse_base_fee(&history));
+        Ok(GasPrices {
+            gas_price,
+            base_fee,
+        })
+    }
+
+    fn rpc<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T> {
+        let resp = self
+            .transport
+            .send(&rpc_request(&self.web3, method, params))
+            .c(d!())?;
+        parse_rpc_response(&resp).c(d!(method.to_owned()))
+    }
+}
@@ -0,0 +1,183 @@
+//!
+//! Verification of the ledger state and of the utxos against the query servers
+//! of several nodes, so a wallet does not trust a single endpoint blindly.
+//!
+//! A state commitment is accepted once all the configured nodes, two at least,
+//! report it for the same block height, a utxo once its inclusion and unspent
+//! proofs hash up to such a commitment. The proofs are checked locally.
+//!
+//! The nodes are the base urls of their query servers, eg.
+//! `https://prod-mainnet.prod.findora.org:8668`.
+//!
+
+use globutils::HashOf;
+use ledger::data_model::{AuthenticatedUtxo, StateCommitmentData, Utxo};
+use ruc::*;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::time::Duration;
+
+/// Timeout of a request to a node.
+const TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Most blocks committed while a utxo is fetched, before giving up on its height.
+const MAX_LAG: u64 = 16;
+
+/// Fewest distinct nodes to cross-check, a single node would be trusted blindly.
+const MIN_NODES: usize = 2;
+
+/// A state commitment reported by all the nodes for `height`.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct VerifiedState {
+    pub height: u64,
+    pub commitment: HashOf<Option<StateCommitmentData>>,
+}
+
+/// An unspent utxo, proven against `state`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct VerifiedUtxo {
+    pub sid: u64,
+    pub utxo: Utxo,
+    pub state: VerifiedState,
+}
+
+/// A client of the query servers of `nodes`.
+pub struct LightClient {
+    nodes: Vec<String>,
+}
+
+impl LightClient {
+    /// A client of `nodes`, at least `MIN_NODES` distinct ones are required.
+    pub fn new(nodes: Vec<String>) -> Result<Self> {
+        let mut distinct: Vec<String> = vec![];
+        for node in nodes {
+            let node = node.trim().trim_end_matches('/').to_owned();
+            if !node.is_empty() && !distinct.contains(&node) {
+                distinct.push(node);
+            }
+        }
+        if distinct.len() < MIN_NODES {
+            return Err(eg!(format!(
+                "{} distinct nodes configured, {} at least are required",
+                distinct.len(),
+                MIN_NODES
+            )));
+        }
+        Ok(LightClient { nodes: distinct })
+    }
+
+    /// The base urls of the nodes.
+    pub fn nodes(&self) -> &[String] {
+        &self.nodes
+    }
+
+    /// The state commitment at the highest block height reached by all the nodes.
+    pub fn state(&self) -> Result<VerifiedState> {
+        let mut height = u64::MAX;
+        for node in self.nodes.iter() {
+            height = height.min(self.height_of(node).c(d!())?);
+        }
+        if height == 0 {
+            return Err(eg!("no block committed"));
+        }
+
+        let node = &self.nodes[0];
+        let commitment = self
+            .commitment_at(node, height)
+            .c(d!())?
+            .c(d!(format!("{}: no state commitment at {}", node, height)))?;
+        self.endorse(height, &commitment).c(d!())?;
+        Ok(VerifiedState { height, commitment })
+    }
+
+    /// The utxo `sid`, if its proof holds against a state of all the nodes.
+    ///
+    /// Fails if the utxo is spent, or does not exist.
+    pub fn utxo(&self, sid: u64) -> Result<VerifiedUtxo> {
+        let node = &self.nodes[0];
+        let before = self.height_of(node).c(d!())?;
+        let au: AuthenticatedUtxo =
+            get(&format!("{}/utxo_sid/{}", node, sid)).c(d!())?;
+        let after = self.height_of(node).c(d!())?;
+
+        let commitment = au.state_commitment_data.compute_commitment();
+        if !au.is_valid(commitment.clone()) {
+            return Err(eg!(format!("{}: invalid proof of utxo {}", node, sid)));
+        }
+
+        // the proof is against the state of `node` when it answered
+        if after.saturating_sub(before) > MAX_LAG {
+            return Err(eg!(format!("{}: too many new blocks, retry", node)));
+        }
+        let mut height = None;
+        for h in before.max(1)..=after {
+            if self.commitment_at(node, h).c(d!())?.as_ref() == Some(&commitment) {
+                height = Some(h);
+                break;
+            }
+        }
+        let height = height.c(d!(format!(
+            "{}: unknown state of the proof of {}",
+            node, sid
+        )))?;
+
+        self.endorse(height, &commitment).c(d!())?;
+        Ok(VerifiedUtxo {
+            sid,
+            utxo: au.utxo,
+            state: VerifiedState { height, commitment },
+        })
+    }
+
+    /// Check that all the nodes report `commitment` at `height`.
+    fn endorse(
+        &self,
+        height: u64,
+        commitment: &HashOf<Option<StateCommitmentData>>,
+    ) -> Result<()> {
+        for node in self.nodes.iter() {
+            match self.commitment_at(node, height).c(d!())? {
+                Some(c) if &c == commitment => {}
+                Some(_) => {
+                    return Err(eg!(format!(
+                        "{}: another state commitment at {}",
+                        node, height
+                    )));
+                }
+                None => {
+                    return Err(eg!(format!("{}: height {} not reached", node, height)));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The latest block height of `node`.
+    fn height_of(&self, node: &str) -> Result<u64> {
+        get::<(HashOf<Option<StateCommitmentData>>, u64, String)>(&format!(
+            "{}/global_state",
+            node
+        ))
+        .c(d!())
+        .map(|(_, height, _)| height)
+    }
+
+    /// The state commitment of `node` at `height`, `None` if not reached.
+    fn commitment_at(
+        &self,
+        node: &str,
+        height: u64,
+    ) -> Result<Option<HashOf<Option<StateCommitmentData>>>> {
+        get(&format!("{}/global_state_version/{}", node, height)).c(d!())
+    }
+}
+
+fn get<T: DeserializeOwned>(url: &str) -> Result<T> {
+    attohttpc::get(url)
+        .timeout(TIMEOUT)
+        .send()
+        .c(d!(url.to_owned()))?
+        .error_for_status()
+        .c(d!(url.to_owned()))?
+        .json::<T>()
+        .c(d!(url.to_owned()))
+}
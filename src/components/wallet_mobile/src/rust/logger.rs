@@ -0,0 +1,205 @@
+//!
+//! Forwards the `tracing` events of the library, and of the findora crates it
+//! builds on, to the log of the host: logcat on android, os_log on iOS, the
+//! console in a browser and stderr elsewhere.
+//!
+//! Nothing is logged until the host calls `set_log_level`, which can be called
+//! again at any time to change the level.
+//!
+
+use ruc::*;
+use std::fmt::{self, Write};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Once;
+use tracing::{
+    callsite,
+    field::{Field, Visit},
+    level_filters::LevelFilter,
+    span, Event, Metadata, Subscriber,
+};
+
+/// Tag of the library in the host log.
+pub const LOG_TAG: &str = "findora";
+
+/// The levels, from the least to the most verbose, `MAX_LEVEL` is an index.
+const LEVELS: [LevelFilter; 6] = [
+    LevelFilter::OFF,
+    LevelFilter::ERROR,
+    LevelFilter::WARN,
+    LevelFilter::INFO,
+    LevelFilter::DEBUG,
+    LevelFilter::TRACE,
+];
+
+static MAX_LEVEL: AtomicU8 = AtomicU8::new(0);
+static INSTALL: Once = Once::new();
+
+fn max_level() -> LevelFilter {
+    LEVELS[MAX_LEVEL.load(Ordering::Relaxed) as usize]
+}
+
+/// Logs the events up to `level`, one of `off`, `error`, `warn`, `info`,
+/// `debug` or `trace`.
+pub fn set_log_level(level: &str) -> Result<()> {
+    let level = level
+        .trim()
+        .parse::<LevelFilter>()
+        .c(d!("unknown log level"))?;
+
+    INSTALL.call_once(|| {
+        // fails if the application has installed its own subscriber
+        let _ = tracing::subscriber::set_global_default(HostLogger);
+    });
+    if !tracing::dispatcher::get_default(|d| d.is::<HostLogger>()) {
+        return Err(eg!("another tracing subscriber is installed"));
+    }
+
+    let idx = LEVELS.iter().position(|l| *l == level).unwrap_or(0);
+    MAX_LEVEL.store(idx as u8, Ordering::Relaxed);
+    // the callsites cache whether they are enabled
+    callsite::rebuild_interest_cache();
+    Ok(())
+}
+
+/// The level set by `set_log_level`, `off` before.
+pub fn log_level() -> String {
+    max_level().to_string().to_lowercase()
+}
+
+/// Writes the events to the host log, spans are not recorded.
+struct HostLogger;
+
+impl Subscriber for HostLogger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.is_event() && *metadata.level() <= max_level()
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        Some(max_level())
+    }
+
+    fn new_span(&self, _: &span::Attributes<'_>) -> span::Id {
+        span::Id::from_u64(1)
+    }
+
+    fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+
+    fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut line = Line::default();
+        event.record(&mut line);
+        let meta = event.metadata();
+        host::write(
+            meta.level(),
+            &format!("[{}] {}{}", meta.target(), line.message, line.fields),
+        );
+    }
+
+    fn enter(&self, _: &span::Id) {}
+
+    fn exit(&self, _: &span::Id) {}
+}
+
+/// The message of an event, followed by its other fields as `name=value`.
+#[derive(Default)]
+struct Line {
+    message: String,
+    fields: String,
+}
+
+impl Visit for Line {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{:?}", value);
+        } else {
+            let _ = write!(self.fields, " {}={:?}", field.name(), value);
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            let _ = write!(self.fields, " {}={}", field.name(), value);
+        }
+    }
+}
+
+#[cfg(target_os = "android")]
+mod host {
+    use super::LOG_TAG;
+    use std::ffi::CString;
+    use std::os::raw::{c_char, c_int};
+    use tracing::Level;
+
+    #[link(name = "log")]
+    extern "C" {
+        fn __android_log_write(
+            prio: c_int,
+            tag: *const c_char,
+            text: *const c_char,
+        ) -> c_int;
+    }
+
+    pub(super) fn write(level: &Level, line: &str) {
+        // priorities of android/log.h
+        let prio = match *level {
+            Level::ERROR => 6,
+            Level::WARN => 5,
+            Level::INFO => 4,
+            Level::DEBUG => 3,
+            _ => 2,
+        };
+        let tag = CString::new(LOG_TAG).unwrap();
+        let text = CString::new(line.replace('\0', "")).unwrap();
+        unsafe {
+            __android_log_write(prio, tag.as_ptr(), text.as_ptr());
+        }
+    }
+}
+
+#[cfg(target_os = "ios")]
+mod host {
+    use super::LOG_TAG;
+    use oslog::{Level as OsLevel, OsLog};
+    use tracing::Level;
+
+    pub(super) fn write(level: &Level, line: &str) {
+        let level = match *level {
+            Level::ERROR => OsLevel::Error,
+            Level::WARN => OsLevel::Default,
+            Level::INFO => OsLevel::Info,
+            _ => OsLevel::Debug,
+        };
+        OsLog::new("org.findora.wallet", LOG_TAG).with_level(level, line);
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod host {
+    use tracing::Level;
+    use wasm_bindgen::JsValue;
+    use web_sys::console;
+
+    pub(super) fn write(level: &Level, line: &str) {
+        let line = JsValue::from_str(line);
+        match *level {
+            Level::ERROR => console::error_1(&line),
+            Level::WARN => console::warn_1(&line),
+            Level::INFO => console::info_1(&line),
+            Level::DEBUG => console::debug_1(&line),
+            _ => console::log_1(&line),
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+mod host {
+    use super::LOG_TAG;
+    use tracing::Level;
+
+    pub(super) fn write(level: &Level, line: &str) {
+        eprintln!("{} {:>5} {}", LOG_TAG, level, line);
+    }
+}
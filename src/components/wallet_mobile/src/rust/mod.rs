@@ -2,8 +2,20 @@
 use wasm_bindgen::prelude::*;
 
 pub mod account;
+pub mod address;
+pub mod anon;
+pub mod balance;
 mod crypto;
 mod data_model;
+mod describe;
+pub mod fee;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod light_client;
+pub mod logger;
+pub mod network;
+pub mod queue;
+pub mod session;
+pub mod signer;
 #[cfg(test)]
 mod tests;
 pub mod transaction;
@@ -12,6 +24,7 @@ mod util;
 
 pub use crypto::*;
 pub use data_model::*;
+pub use describe::*;
 pub use transaction::*;
 pub use util::*;
 
@@ -0,0 +1,226 @@
+//!
+//! Requests to the submission and query servers of a node.
+//!
+//! The requests are built and their responses parsed here, the I/O is done
+//! by an `HttpTransport`: the host app may provide its own one, eg. over an
+//! HTTP stack pinning the certificates of the node, the default one is
+//! `attohttpc` with the system roots. The wasm bindings use `fetch` instead.
+//!
+
+use super::crypto::public_key_to_base64;
+use ledger::data_model::{TxoSID, Utxo};
+use ruc::*;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, sync::Arc};
+use zei::{OwnerMemo, XfrPublicKey};
+
+/// Default port of the query server.
+pub const QUERY_PORT: u16 = 8668;
+
+/// Default port of the submission server.
+pub const SUBMISSION_PORT: u16 = 8669;
+
+/// Base urls of the servers of a node.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Endpoints {
+    pub query: String,
+    pub submission: String,
+}
+
+/// HTTP method of a request.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HttpMethod {
+    Get,
+    Post,
+}
+
+impl HttpMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HttpMethod::Get => "GET",
+            HttpMethod::Post => "POST",
+        }
+    }
+}
+
+/// A request to a node, the body of a `Post` is JSON.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HttpRequest {
+    pub method: HttpMethod,
+    pub url: String,
+    pub body: Option<String>,
+}
+
+/// An utxo of an address, with the memo to open it if confidential.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct OwnedUtxo {
+    pub sid: u64,
+    pub utxo: Utxo,
+    pub owner_memo: Option<OwnerMemo>,
+}
+
+impl Endpoints {
+    /// The servers of `host` on their default ports, eg. `https://prod-mainnet.prod.findora.org`.
+    pub fn from_host(host: &str) -> Self {
+        let host = host.trim().trim_end_matches('/');
+        Endpoints {
+            query: format!("{}:{}", host, QUERY_PORT),
+            submission: format!("{}:{}", host, SUBMISSION_PORT),
+        }
+    }
+
+    /// Servers at custom urls, eg. behind a reverse proxy.
+    pub fn new(query: &str, submission: &str) -> Self {
+        Endpoints {
+            query: query.trim().trim_end_matches('/').to_owned(),
+            submission: submission.trim().trim_end_matches('/').to_owned(),
+        }
+    }
+
+    /// Submit a serialized transaction, see `parse_txn_handle`.
+    pub fn submit_transaction(&self, tx: &str) -> HttpRequest {
+        HttpRequest {
+            method: HttpMethod::Post,
+            url: format!("{}/submit_transaction", self.submission),
+            body: Some(tx.to_owned()),
+        }
+    }
+
+    /// Query the utxos of `owner`, see `parse_owned_utxos`.
+    pub fn owned_utxos(&self, owner: &XfrPublicKey) -> HttpRequest {
+        HttpRequest {
+            method: HttpMethod::Get,
+            url: format!("{}/owned_utxos/{}", self.query, public_key_to_base64(owner)),
+            body: None,
+        }
+    }
+
+    /// Query the owner memos of the utxos `sids`, see `parse_owner_memos`.
+    pub fn owner_memos(&self, sids: &[u64]) -> HttpRequest {
+        let sids = sids
+            .iter()
+            .map(|sid| sid.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        HttpRequest {
+            method: HttpMethod::Get,
+            url: format!("{}/get_owner_memo_batch/{}", self.query, sids),
+            body: None,
+        }
+    }
+}
+
+/// The handle of a submitted transaction, to query its status.
+pub fn parse_txn_handle(resp: &[u8]) -> Result<String> {
+    serde_json::from_slice(resp).c(d!())
+}
+
+/// The utxos of an address, the oldest first.
+pub fn parse_owned_utxos(resp: &[u8]) -> Result<Vec<OwnedUtxo>> {
+    serde_json::from_slice::<BTreeMap<TxoSID, (Utxo, Option<OwnerMemo>)>>(resp)
+        .c(d!())
+        .map(|utxos| {
+            utxos
+                .into_iter()
+                .map(|(sid, (utxo, owner_memo))| OwnedUtxo {
+                    sid: sid.0,
+                    utxo,
+                    owner_memo,
+                })
+                .collect()
+        })
+}
+
+/// The owner memos, in the order of the requested utxos.
+pub fn parse_owner_memos(resp: &[u8]) -> Result<Vec<Option<OwnerMemo>>> {
+    serde_json::from_slice(resp).c(d!())
+}
+
+/// Sends the requests to the nodes, blocking until their response.
+///
+/// This is the hook for TLS pinning: a transport of the host app can verify
+/// the certificates of the node before sending anything.
+pub trait HttpTransport: Send + Sync {
+    /// The body of the response to `req`, an error for a non 2xx status.
+    fn send(&self, req: &HttpRequest) -> Result<Vec<u8>>;
+}
+
+/// The default transport, trusting the system roots.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct DefaultTransport {
+    pub timeout: std::time::Duration,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Default for DefaultTransport {
+    fn default() -> Self {
+        DefaultTransport {
+            timeout: std::time::Duration::from_secs(30),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl HttpTransport for DefaultTransport {
+    fn send(&self, req: &HttpRequest) -> Result<Vec<u8>> {
+        let resp = match req.method {
+            HttpMethod::Get => attohttpc::get(&req.url).timeout(self.timeout).send(),
+            HttpMethod::Post => attohttpc::post(&req.url)
+                .timeout(self.timeout)
+                .header(attohttpc::header::CONTENT_TYPE, "application/json")
+                .bytes(req.body.as_deref().unwrap_or_default().as_bytes())
+                .send(),
+        };
+        resp.c(d!(req.url.clone()))?
+            .error_for_status()
+            .c(d!(req.url.clone()))?
+            .bytes()
+            .c(d!(req.url.clone()))
+    }
+}
+
+/// A client of a node, through `transport`.
+#[derive(Clone)]
+pub struct NetworkClient {
+    pub endpoints: Endpoints,
+    transport: Arc<dyn HttpTransport>,
+}
+
+impl NetworkClient {
+    pub fn new(endpoints: Endpoints, transport: Arc<dyn HttpTransport>) -> Self {
+        NetworkClient {
+            endpoints,
+            transport,
+        }
+    }
+
+    /// Submit a serialized transaction, returns its handle.
+    pub fn submit_transaction(&self, tx: &str) -> Result<String> {
+        let resp = self
+            .transport
+            .send(&self.endpoints.submit_transaction(tx))
+            .c(d!())?;
+        parse_txn_handle(&resp).c(d!())
+    }
+
+    /// The utxos of `owner`, the oldest first.
+    pub fn owned_utxos(&self, owner: &XfrPublicKey) -> Result<Vec<OwnedUtxo>> {
+        let resp = self
+            .transport
+            .send(&self.endpoints.owned_utxos(owner))
+            .c(d!())?;
+        parse_owned_utxos(&resp).c(d!())
+    }
+
+    /// The owner memos of the utxos `sids`, in their order.
+    pub fn owner_memos(&self, sids: &[u64]) -> Result<Vec<Option<OwnerMemo>>> {
+        if sids.is_empty() {
+            return Ok(vec![]);
+        }
+        let resp = self
+            .transport
+            .send(&self.endpoints.owner_memos(sids))
+            .c(d!())?;
+        parse_owner_memos(&resp).c(d!())
+    }
+}
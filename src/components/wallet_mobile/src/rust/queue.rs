@@ -0,0 +1,233 @@
+//!
+//! A persistent queue of the built but unsubmitted transactions.
+//!
+//! The queue does no I/O but its own file, the host app submits the transactions:
+//! - `set_online` is called by the connectivity callbacks of the app,
+//! - `due` returns the transactions to submit now,
+//! - `report` records the outcome of each submission,
+//!   a failed one is retried with an exponential backoff.
+//!
+//! The queue is saved to the storage path of the app after each change,
+//! an interrupted submission is retried once the queue is reopened.
+//!
+
+use ruc::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Delay before the first retry, in seconds.
+const BACKOFF_BASE: u64 = 5;
+
+/// Longest delay between two retries, in seconds.
+const BACKOFF_MAX: u64 = 3600;
+
+/// Count of the failed submissions after which a transaction is given up.
+pub const MAX_ATTEMPTS: u32 = 10;
+
+/// Submission status of a queued transaction.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status")]
+pub enum TxStatus {
+    /// waiting for its submission, at `next_attempt` or once online
+    Pending {
+        attempts: u32,
+        next_attempt: u64,
+        last_error: Option<String>,
+    },
+    /// returned by `due`, waiting for its `report`
+    Submitting { attempts: u32 },
+    /// accepted by the network
+    Submitted { hash: String },
+    /// given up after `MAX_ATTEMPTS` failures
+    Failed { attempts: u32, error: String },
+}
+
+/// A queued transaction.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueuedTx {
+    pub id: u64,
+    /// the serialized transaction, as submitted
+    pub tx: String,
+    /// unix time of its enqueuing, in seconds
+    pub created_at: u64,
+    #[serde(flatten)]
+    pub status: TxStatus,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct QueueData {
+    next_id: u64,
+    txs: Vec<QueuedTx>,
+}
+
+/// The pending transaction queue, saved at `path`.
+pub struct TxQueue {
+    path: PathBuf,
+    online: bool,
+    data: QueueData,
+}
+
+impl TxQueue {
+    /// Open the queue saved at `path`, an empty queue if there is no such file.
+    ///
+    /// The queue is online until `set_online` reports otherwise.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut data = if path.exists() {
+            serde_json::from_slice::<QueueData>(&fs::read(&path).c(d!())?).c(d!())?
+        } else {
+            QueueData::default()
+        };
+
+        // the outcome of an interrupted submission is unknown, submit it again
+        for tx in data.txs.iter_mut() {
+            if let TxStatus::Submitting { attempts } = tx.status {
+                tx.status = TxStatus::Pending {
+                    attempts,
+                    next_attempt: 0,
+                    last_error: None,
+                };
+            }
+        }
+
+        Ok(TxQueue {
+            path,
+            online: true,
+            data,
+        })
+    }
+
+    /// Queue a serialized transaction, returns its id.
+    pub fn enqueue(&mut self, tx: String, now: u64) -> Result<u64> {
+        let id = self.data.next_id;
+        self.data.next_id += 1;
+        self.data.txs.push(QueuedTx {
+            id,
+            tx,
+            created_at: now,
+            status: TxStatus::Pending {
+                attempts: 0,
+                next_attempt: now,
+                last_error: None,
+            },
+        });
+        self.save().c(d!()).map(|_| id)
+    }
+
+    /// Report the connectivity of the app,
+    /// the pending transactions are due as soon as it is back online.
+    pub fn set_online(&mut self, online: bool, now: u64) -> Result<()> {
+        let back_online = online && !self.online;
+        self.online = online;
+        if !back_online {
+            return Ok(());
+        }
+        for tx in self.data.txs.iter_mut() {
+            if let TxStatus::Pending { next_attempt, .. } = &mut tx.status {
+                *next_attempt = (*next_attempt).min(now);
+            }
+        }
+        self.save().c(d!())
+    }
+
+    /// The transactions to submit now, none while offline.
+    ///
+    /// They are `Submitting` until their outcome is given to `report`.
+    pub fn due(&mut self, now: u64) -> Result<Vec<QueuedTx>> {
+        if !self.online {
+            return Ok(vec![]);
+        }
+        let mut due = vec![];
+        for tx in self.data.txs.iter_mut() {
+            if let TxStatus::Pending {
+                attempts,
+                next_attempt,
+                ..
+            } = tx.status
+            {
+                if next_attempt <= now {
+                    tx.status = TxStatus::Submitting { attempts };
+                    due.push(tx.clone());
+                }
+            }
+        }
+        if !due.is_empty() {
+            self.save().c(d!())?;
+        }
+        Ok(due)
+    }
+
+    /// Record the outcome of the submission of the transaction `id`,
+    /// its hash on success, the error otherwise.
+    pub fn report(
+        &mut self,
+        id: u64,
+        outcome: std::result::Result<String, String>,
+        now: u64,
+    ) -> Result<TxStatus> {
+        let tx = self
+            .data
+            .txs
+            .iter_mut()
+            .find(|tx| tx.id == id)
+            .c(d!(format!("no queued transaction: {}", id)))?;
+        let attempts = match tx.status {
+            TxStatus::Submitting { attempts } => attempts + 1,
+            _ => return Err(eg!(format!("transaction {} is not submitting", id))),
+        };
+
+        tx.status = match outcome {
+            Ok(hash) => TxStatus::Submitted { hash },
+            Err(error) if attempts >= MAX_ATTEMPTS => {
+                TxStatus::Failed { attempts, error }
+            }
+            Err(error) => TxStatus::Pending {
+                attempts,
+                next_attempt: now.saturating_add(backoff(attempts)),
+                last_error: Some(error),
+            },
+        };
+        let status = tx.status.clone();
+        self.save().c(d!()).map(|_| status)
+    }
+
+    /// All the queued transactions, the oldest first.
+    pub fn statuses(&self) -> &[QueuedTx] {
+        &self.data.txs
+    }
+
+    /// Drop the transaction `id` from the queue, eg. once its status is shown.
+    pub fn remove(&mut self, id: u64) -> Result<()> {
+        let len = self.data.txs.len();
+        self.data.txs.retain(|tx| tx.id != id);
+        if self.data.txs.len() == len {
+            return Err(eg!(format!("no queued transaction: {}", id)));
+        }
+        self.save().c(d!())
+    }
+
+    /// Save to a temporary file first, so a crash never leaves a truncated queue.
+    fn save(&self) -> Result<()> {
+        let tmp = self.path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_vec(&self.data).c(d!())?).c(d!())?;
+        fs::rename(&tmp, &self.path).c(d!())
+    }
+}
+
+/// Delay before the retry following the `attempts`-th failure, in seconds.
+fn backoff(attempts: u32) -> u64 {
+    BACKOFF_BASE
+        .saturating_mul(1 << attempts.saturating_sub(1).min(16))
+        .min(BACKOFF_MAX)
+}
+
+/// The current unix time, in seconds.
+pub fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
@@ -0,0 +1,132 @@
+//!
+//! The key pairs and mnemonics kept by the library on behalf of the host.
+//!
+//! A secret is held serialized in a container zeroed when dropped, and a key
+//! pair is decoded only for the duration of a call. Each secret is owned by a
+//! `Secret`, released when it is dropped, and the host can wipe all of them at
+//! once with `wipe_all` when the app is sent to the background. The
+//! outstanding `Secret`s then report their secret as wiped.
+//!
+//! The decoded copies, and the strings the host passed in, are not covered.
+//!
+
+use ruc::*;
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, MutexGuard,
+    },
+};
+use zei::{noah_algebra::serialization::NoahFromToBytes, XfrKeyPair};
+use zeroize::Zeroizing;
+
+enum Material {
+    KeyPair(Zeroizing<Vec<u8>>),
+    Mnemonic(Zeroizing<String>),
+}
+
+/// A set of secrets, with the ids of the `Secret`s owning them.
+pub struct SecretSession {
+    next_id: AtomicU64,
+    secrets: Mutex<BTreeMap<u64, Material>>,
+}
+
+static SESSION: SecretSession = SecretSession::new();
+
+impl Default for SecretSession {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SecretSession {
+    pub const fn new() -> Self {
+        SecretSession {
+            next_id: AtomicU64::new(1),
+            secrets: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// The session of the process, the one the bindings use.
+    pub fn global() -> &'static SecretSession {
+        &SESSION
+    }
+
+    fn secrets(&self) -> MutexGuard<BTreeMap<u64, Material>> {
+        self.secrets.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn add(&'static self, material: Material) -> Secret {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.secrets().insert(id, material);
+        Secret { session: self, id }
+    }
+
+    /// Keep a copy of `keypair`.
+    pub fn add_keypair(&'static self, keypair: &XfrKeyPair) -> Secret {
+        self.add(Material::KeyPair(Zeroizing::new(keypair.noah_to_bytes())))
+    }
+
+    /// Keep the mnemonic `phrase`, which is zeroed when released.
+    pub fn add_mnemonic(&'static self, phrase: String) -> Secret {
+        self.add(Material::Mnemonic(Zeroizing::new(phrase)))
+    }
+
+    /// The number of secrets held, those not released nor wiped.
+    pub fn outstanding(&self) -> usize {
+        self.secrets().len()
+    }
+
+    /// Zero and drop all the secrets, returns how many there were.
+    pub fn wipe_all(&self) -> usize {
+        let wiped = std::mem::take(&mut *self.secrets());
+        let n = wiped.len();
+        drop(wiped);
+        if n > 0 {
+            tracing::debug!("wiped {} secrets", n);
+        }
+        n
+    }
+}
+
+/// The handle of a secret of a `SecretSession`, which is released on drop.
+pub struct Secret {
+    session: &'static SecretSession,
+    id: u64,
+}
+
+impl Secret {
+    fn with<R>(&self, f: impl FnOnce(&Material) -> Result<R>) -> Result<R> {
+        let secrets = self.session.secrets();
+        let material = secrets.get(&self.id).c(d!("the secret has been wiped"))?;
+        f(material)
+    }
+
+    /// A copy of the key pair, dropped by the caller once used.
+    pub fn keypair(&self) -> Result<XfrKeyPair> {
+        self.with(|material| match material {
+            Material::KeyPair(bytes) => XfrKeyPair::noah_from_bytes(bytes).c(d!()),
+            Material::Mnemonic(_) => Err(eg!("the secret is not a key pair")),
+        })
+    }
+
+    /// Run `f` on the mnemonic phrase, without copying it.
+    pub fn with_mnemonic<R>(&self, f: impl FnOnce(&str) -> Result<R>) -> Result<R> {
+        self.with(|material| match material {
+            Material::Mnemonic(phrase) => f(phrase),
+            Material::KeyPair(_) => Err(eg!("the secret is not a mnemonic")),
+        })
+    }
+
+    /// Whether the secret is still held, it is not after `wipe_all`.
+    pub fn is_held(&self) -> bool {
+        self.session.secrets().contains_key(&self.id)
+    }
+}
+
+impl Drop for Secret {
+    fn drop(&mut self) {
+        self.session.secrets().remove(&self.id);
+    }
+}
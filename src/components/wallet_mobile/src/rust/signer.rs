@@ -0,0 +1,52 @@
+//!
+//! Signing with keys held outside of this library.
+//!
+//! A `SigningProvider` signs the payloads of the builders with its own key,
+//! eg. a key of the Android Keystore/StrongBox, so the secret key never
+//! enters the rust memory. The builders sign through it by `sign_with`.
+//!
+//! Only the ed25519 keys of the utxo transactions are supported,
+//! the `EVMTransactionBuilder` still signs with a secp256k1 private key.
+//!
+
+use globutils::{Serialized, SignatureOf};
+use ruc::{d, eg, Result, RucResult};
+use serde::{de::DeserializeOwned, Serialize};
+use zei::{XfrKeyPair, XfrPublicKey, XfrSignature};
+
+/// A signer of the transactions, the secret key may be out of reach.
+pub trait SigningProvider {
+    /// The public key of the signing key.
+    fn public_key(&self) -> Result<XfrPublicKey>;
+
+    /// Sign `msg` with the signing key, a plain ed25519 signature.
+    fn sign(&self, msg: &[u8]) -> Result<XfrSignature>;
+}
+
+impl SigningProvider for XfrKeyPair {
+    fn public_key(&self) -> Result<XfrPublicKey> {
+        Ok(self.get_pk())
+    }
+
+    fn sign(&self, msg: &[u8]) -> Result<XfrSignature> {
+        self.get_sk_ref()
+            .sign(msg)
+            .map_err(|e| eg!(format!("{:?}", e)))
+    }
+}
+
+/// The signature of `to_sign` by `signer`, as made by `SignatureOf::new`.
+pub(crate) fn sign_of<T>(
+    signer: &dyn SigningProvider,
+    to_sign: &T,
+) -> Result<SignatureOf<T>>
+where
+    T: Serialize + DeserializeOwned,
+{
+    let msg = Serialized::new(to_sign);
+    let sig = signer.sign(msg.as_ref()).c(d!())?;
+    // a `SignatureOf` is serialized as the bare signature
+    serde_json::to_value(sig)
+        .and_then(serde_json::from_value)
+        .c(d!())
+}
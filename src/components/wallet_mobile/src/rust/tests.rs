@@ -93,3 +93,883 @@ fn test_asset_rules_from_str_empty_str_max_units() {
         serde_json::from_str::<PlatformAssetRules>(&actual_serialized_json).unwrap();
     assert_eq!(res.max_units, None);
 }
+
+#[test]
+fn t_evm_transaction_builder() {
+    use account::EVMTransactionBuilder;
+    let kp = new_keypair();
+    let sk = "11".repeat(32);
+
+    let builder = EVMTransactionBuilder::new_transfer_from_account(
+        kp.get_pk(),
+        100,
+        sk.clone(),
+        1.into(),
+    )
+    .unwrap();
+    let tx = EVMTransactionBuilder::new_transfer_to_utxo_from_account(
+        kp.get_pk(),
+        100,
+        sk,
+        1.into(),
+    )
+    .unwrap();
+    assert_eq!(builder.serialized_transaction_base64(), base64::encode(tx));
+
+    assert!(EVMTransactionBuilder::new_transfer_from_account(
+        kp.get_pk(),
+        100,
+        "11".to_owned(),
+        1.into(),
+    )
+    .is_err());
+}
+
+#[test]
+fn t_evm_fee() {
+    use account::EVMFee;
+    use fp_types::U256;
+
+    assert_eq!(EVMFee::Minimal.amount().unwrap(), None);
+
+    let legacy: EVMFee = serde_json::from_str(
+        r#"{"Legacy":{"gas_limit":21000,"gas_price":"0x2540be400"}}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        legacy.amount().unwrap(),
+        Some(U256::from(21000u64) * U256::from(10_000_000_000u64))
+    );
+
+    let eip1559 = |max_fee: u64, priority: u64, base_fee: u64| EVMFee::Eip1559 {
+        gas_limit: 10,
+        max_fee_per_gas: max_fee.into(),
+        max_priority_fee_per_gas: priority.into(),
+        base_fee: base_fee.into(),
+    };
+    // base fee plus priority fee
+    assert_eq!(eip1559(100, 5, 50).amount().unwrap(), Some(550.into()));
+    // capped by the max fee
+    assert_eq!(eip1559(100, 60, 50).amount().unwrap(), Some(1000.into()));
+    // stuck below the base fee
+    assert!(eip1559(40, 5, 50).amount().is_err());
+    assert!(eip1559(100, 200, 50).amount().is_err());
+
+    let zero_gas = EVMFee::Legacy {
+        gas_limit: 0,
+        gas_price: 1.into(),
+    };
+    assert!(zero_gas.amount().is_err());
+}
+
+#[test]
+fn t_erc20_transfer() {
+    use account::{
+        erc20_transfer_input, parse_evm_address, EVMFee, EVMTransactionBuilder,
+    };
+    use fp_types::U256;
+
+    let to = parse_evm_address("0x00000000000000000000000000000000000000ff").unwrap();
+    assert_eq!(
+        parse_evm_address("00000000000000000000000000000000000000ff").unwrap(),
+        to
+    );
+    assert!(parse_evm_address("0x00ff").is_err());
+
+    let input = erc20_transfer_input(to, U256::from(1000u64));
+    assert_eq!(input.len(), 68);
+    assert_eq!(input[..4], [0xa9, 0x05, 0x9c, 0xbb]);
+    assert!(input[4..35].iter().all(|b| *b == 0));
+    assert_eq!(input[35], 0xff);
+    assert_eq!(input[66..], [0x03, 0xe8]);
+
+    let sk = "0x".to_owned() + &"11".repeat(32);
+    let fee = EVMFee::Legacy {
+        gas_limit: 60000,
+        gas_price: 10_000_000_000u64.into(),
+    };
+    let builder = EVMTransactionBuilder::new_erc20_transfer(
+        to,
+        to,
+        1000u64.into(),
+        sk.clone(),
+        0u64.into(),
+        2152,
+        &fee,
+    )
+    .unwrap();
+    assert!(!builder.serialized_transaction_base64().is_empty());
+
+    // the gas limit and price are part of the signed ethereum txn
+    assert!(EVMTransactionBuilder::new_erc20_transfer(
+        to,
+        to,
+        1000u64.into(),
+        sk,
+        0u64.into(),
+        2152,
+        &EVMFee::Minimal,
+    )
+    .is_err());
+}
+
+#[test]
+fn t_describe() {
+    use account::{parse_evm_address, EVMFee, EVMTransactionBuilder};
+
+    let builder = TransactionBuilder::new(1);
+    let description = builder.describe();
+    assert!(description.operations.is_empty());
+    assert_eq!(description.fee.as_deref(), Some("0"));
+
+    let kp = new_keypair();
+    let sk = "11".repeat(32);
+    let builder = EVMTransactionBuilder::new_transfer_from_account(
+        kp.get_pk(),
+        100,
+        sk.clone(),
+        1.into(),
+    )
+    .unwrap();
+    let description = builder.describe();
+    assert_eq!(description.fee, None);
+    match &description.operations[..] {
+        [OperationDescription::TransferToUtxo { nonce, outputs, .. }] => {
+            assert_eq!(nonce, "1");
+            assert_eq!(outputs.len(), 1);
+            assert_eq!(outputs[0].recipient, public_key_to_bech32(kp.get_pk_ref()));
+            assert_eq!(outputs[0].amount.as_deref(), Some("100"));
+        }
+        ops => panic!("unexpected operations: {:?}", ops),
+    }
+
+    let contract =
+        parse_evm_address("0x00000000000000000000000000000000000000ee").unwrap();
+    let to = parse_evm_address("0x00000000000000000000000000000000000000ff").unwrap();
+    let fee = EVMFee::Legacy {
+        gas_limit: 60000,
+        gas_price: 10.into(),
+    };
+    let builder = EVMTransactionBuilder::new_erc20_transfer(
+        contract,
+        to,
+        1000u64.into(),
+        sk,
+        2.into(),
+        2152,
+        &fee,
+    )
+    .unwrap();
+    let description = builder.describe();
+    assert_eq!(description.fee.as_deref(), Some("600000"));
+    assert_eq!(
+        description.operations,
+        vec![OperationDescription::Erc20Transfer {
+            contract: "0x00000000000000000000000000000000000000ee".to_owned(),
+            to: "0x00000000000000000000000000000000000000ff".to_owned(),
+            amount: "1000".to_owned(),
+            nonce: "2".to_owned(),
+        }]
+    );
+}
+
+#[test]
+fn t_mnemonic() {
+    let phrase = rs_generate_mnemonic_custom(24, "en").unwrap();
+    let check = rs_validate_mnemonic(&phrase, "en").unwrap();
+    assert!(check.valid);
+    assert!(check.unknown_words.is_empty());
+
+    let mut words = phrase.split_whitespace().collect::<Vec<_>>();
+    words[3] = "abandn";
+    let check = rs_validate_mnemonic(&words.join(" "), "en").unwrap();
+    assert!(!check.valid);
+    assert_eq!(check.unknown_words.len(), 1);
+    assert_eq!(check.unknown_words[0].index, 3);
+    assert!(check.unknown_words[0]
+        .suggestions
+        .contains(&"abandon".to_owned()));
+
+    // a wrong word count is not an unknown word
+    let check =
+        rs_validate_mnemonic(&words[..4].join(" ").replace("abandn", "abandon"), "en")
+            .unwrap();
+    assert!(!check.valid);
+    assert!(check.unknown_words.is_empty());
+    assert!(check.error.is_some());
+    assert!(rs_validate_mnemonic(&phrase, "xx").is_err());
+
+    let kp = rs_restore_keypair_from_mnemonic_default(&phrase).unwrap();
+    let restored =
+        rs_restore_keypair_from_mnemonic_path(&phrase, "en", "m/44'/917'/0'/0/0")
+            .unwrap();
+    assert_eq!(kp.get_pk(), restored.get_pk());
+    let other =
+        rs_restore_keypair_from_mnemonic_path(&phrase, "en", "m/44'/917'/1'/0/0")
+            .unwrap();
+    assert_ne!(kp.get_pk(), other.get_pk());
+    assert!(
+        rs_restore_keypair_from_mnemonic_path(&phrase, "en", "m/44'/917'/0'/0").is_err()
+    );
+    assert!(
+        rs_restore_keypair_from_mnemonic_path(&phrase, "en", "m/44/917'/0'/0/0")
+            .is_err()
+    );
+    assert!(
+        rs_restore_keypair_from_mnemonic_path(&phrase, "en", "m/84'/917'/0'/0/0")
+            .is_err()
+    );
+
+    let sk = account::restore_evm_secret_from_mnemonic(
+        &phrase,
+        "en",
+        fp_utils::ecdsa::DEFAULT_DERIVATION_PATH,
+    )
+    .unwrap();
+    let (expected, _) = fp_utils::ecdsa::SecpPair::from_phrase(&phrase, None).unwrap();
+    assert_eq!(sk, hex::encode(expected.seed()));
+    assert_eq!(
+        account::evm_address_from_secret(&sk).unwrap(),
+        format!("{:?}", expected.address())
+    );
+}
+
+#[test]
+fn t_sign_with() {
+    use zei::{XfrKeyPair, XfrPublicKey, XfrSignature};
+
+    let kp = new_keypair();
+    let builder = TransactionBuilder::new(1);
+
+    let signed = builder.clone().sign(&kp).unwrap();
+    let signed_with = builder.clone().sign_with(&kp).unwrap();
+    assert_eq!(
+        serde_json::to_string(&signed.get_builder().get_transaction().signatures)
+            .unwrap(),
+        serde_json::to_string(&signed_with.get_builder().get_transaction().signatures)
+            .unwrap()
+    );
+
+    // a signer whose public key does not match its signatures is rejected
+    struct WrongKey(XfrKeyPair, XfrKeyPair);
+    impl signer::SigningProvider for WrongKey {
+        fn public_key(&self) -> ruc::Result<XfrPublicKey> {
+            Ok(self.0.get_pk())
+        }
+        fn sign(&self, msg: &[u8]) -> ruc::Result<XfrSignature> {
+            signer::SigningProvider::sign(&self.1, msg)
+        }
+    }
+    assert!(builder.sign_with(&WrongKey(kp, new_keypair())).is_err());
+}
+
+#[test]
+fn t_tx_queue() {
+    use queue::{TxQueue, TxStatus, MAX_ATTEMPTS};
+
+    let path = std::env::temp_dir().join(format!(
+        "wallet_mobile_tx_queue_{}.json",
+        rand::random::<u64>()
+    ));
+    let mut q = TxQueue::open(&path).unwrap();
+    let a = q.enqueue("tx_a".to_owned(), 100).unwrap();
+    let b = q.enqueue("tx_b".to_owned(), 100).unwrap();
+
+    // nothing is due while offline
+    q.set_online(false, 100).unwrap();
+    assert!(q.due(100).unwrap().is_empty());
+    q.set_online(true, 100).unwrap();
+    let due = q.due(100).unwrap();
+    assert_eq!(due.iter().map(|tx| tx.id).collect::<Vec<_>>(), vec![a, b]);
+    assert!(q.due(100).unwrap().is_empty());
+
+    assert_eq!(
+        q.report(a, Ok("hash_a".to_owned()), 101).unwrap(),
+        TxStatus::Submitted {
+            hash: "hash_a".to_owned()
+        }
+    );
+    // a failure is retried later, with a growing delay
+    let next = match q.report(b, Err("offline".to_owned()), 101).unwrap() {
+        TxStatus::Pending { next_attempt, .. } => next_attempt,
+        s => panic!("unexpected status: {:?}", s),
+    };
+    assert!(next > 101);
+    assert!(q.due(next - 1).unwrap().is_empty());
+    assert_eq!(q.due(next).unwrap().len(), 1);
+    let later = match q.report(b, Err("offline".to_owned()), next).unwrap() {
+        TxStatus::Pending { next_attempt, .. } => next_attempt,
+        s => panic!("unexpected status: {:?}", s),
+    };
+    assert!(later - next > next - 101);
+
+    // back online, the pending ones are due at once
+    q.set_online(false, next).unwrap();
+    q.set_online(true, next).unwrap();
+    assert_eq!(q.due(next).unwrap().len(), 1);
+
+    // an interrupted submission is pending once reopened
+    let mut q = TxQueue::open(&path).unwrap();
+    assert_eq!(q.statuses().len(), 2);
+    assert_eq!(q.due(next).unwrap().len(), 1);
+
+    let mut now = next;
+    for _ in 3..MAX_ATTEMPTS {
+        assert!(matches!(
+            q.report(b, Err("offline".to_owned()), now).unwrap(),
+            TxStatus::Pending { .. }
+        ));
+        now += 1 << 20;
+        assert_eq!(q.due(now).unwrap().len(), 1);
+    }
+    assert!(matches!(
+        q.report(b, Err("rejected".to_owned()), now).unwrap(),
+        TxStatus::Failed { .. }
+    ));
+    assert!(q.due(u64::MAX).unwrap().is_empty());
+
+    q.remove(a).unwrap();
+    assert!(q.remove(a).is_err());
+    assert_eq!(q.statuses().len(), 1);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn t_light_client() {
+    use super::light_client::LightClient;
+
+    assert!(LightClient::new(vec![]).is_err());
+    assert!(LightClient::new(vec![" ".to_owned()]).is_err());
+    // a single node, even repeated, is not cross-checked
+    assert!(LightClient::new(vec!["http://127.0.0.1:8668".to_owned()]).is_err());
+    assert!(LightClient::new(vec![
+        "http://127.0.0.1:8668".to_owned(),
+        "http://127.0.0.1:8668/".to_owned(),
+    ])
+    .is_err());
+    let client = LightClient::new(vec![
+        "http://127.0.0.1:8668/".to_owned(),
+        "https://prod-mainnet.prod.findora.org:8668".to_owned(),
+        "http://127.0.0.1:8668".to_owned(),
+    ])
+    .unwrap();
+    assert_eq!(
+        client.nodes(),
+        [
+            "http://127.0.0.1:8668",
+            "https://prod-mainnet.prod.findora.org:8668"
+        ]
+    );
+
+    assert!(rs_verify_authenticated_utxo("\"\"".to_owned(), "{}".to_owned()).is_err());
+}
+
+#[test]
+fn t_network_client() {
+    use super::network::{
+        Endpoints, HttpMethod, HttpRequest, HttpTransport, NetworkClient,
+    };
+    use std::sync::{Arc, Mutex};
+
+    struct MockTransport(Mutex<Vec<HttpRequest>>);
+
+    impl HttpTransport for MockTransport {
+        fn send(&self, req: &HttpRequest) -> ruc::Result<Vec<u8>> {
+            self.0.lock().unwrap().push(req.clone());
+            let resp = if req.url.contains("/submit_transaction") {
+                "\"handle\""
+            } else if req.url.contains("/owned_utxos/") {
+                "{}"
+            } else {
+                "[null,null]"
+            };
+            Ok(resp.as_bytes().to_vec())
+        }
+    }
+
+    let endpoints = Endpoints::from_host("https://prod-mainnet.prod.findora.org/");
+    assert_eq!(
+        endpoints,
+        Endpoints::new(
+            "https://prod-mainnet.prod.findora.org:8668",
+            "https://prod-mainnet.prod.findora.org:8669/"
+        )
+    );
+
+    let transport = Arc::new(MockTransport(Mutex::new(vec![])));
+    let client = NetworkClient::new(endpoints, transport.clone());
+    assert_eq!(client.submit_transaction("{}").unwrap(), "handle");
+    let kp = new_keypair();
+    assert!(client.owned_utxos(kp.get_pk_ref()).unwrap().is_empty());
+    assert_eq!(client.owner_memos(&[1, 2]).unwrap().len(), 2);
+    assert!(client.owner_memos(&[]).unwrap().is_empty());
+
+    let reqs = transport.0.lock().unwrap();
+    assert_eq!(reqs.len(), 3);
+    assert_eq!(reqs[0].method, HttpMethod::Post);
+    assert_eq!(
+        reqs[0].url,
+        "https://prod-mainnet.prod.findora.org:8669/submit_transaction"
+    );
+    assert_eq!(reqs[0].body.as_deref(), Some("{}"));
+    assert_eq!(
+        reqs[1].url,
+        format!(
+            "https://prod-mainnet.prod.findora.org:8668/owned_utxos/{}",
+            public_key_to_base64(kp.get_pk_ref())
+        )
+    );
+    assert_eq!(
+        reqs[2].url,
+        "https://prod-mainnet.prod.findora.org:8668/get_owner_memo_batch/1,2"
+    );
+}
+
+#[test]
+fn t_open_records() {
+    use balance::{open_records, RecordToOpen};
+    use ledger::data_model::AssetTypeCode;
+    use rand_chacha::ChaChaRng;
+    use rand_core::SeedableRng;
+    use zei::{
+        noah_algebra::ristretto::PedersenCommitmentRistretto,
+        noah_api::xfr::{
+            asset_record::{build_blind_asset_record, AssetRecordType},
+            structs::AssetRecordTemplate,
+        },
+        BlindAssetRecord,
+    };
+
+    let mut prng = ChaChaRng::from_seed([0u8; 32]);
+    let pc_gens = PedersenCommitmentRistretto::default();
+    let kp = new_keypair();
+    let other = new_keypair();
+    let code = AssetTypeCode::gen_random();
+
+    let records = [
+        (
+            10,
+            AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+            &kp,
+        ),
+        (
+            20,
+            AssetRecordType::ConfidentialAmount_ConfidentialAssetType,
+            &kp,
+        ),
+        (
+            30,
+            AssetRecordType::ConfidentialAmount_NonConfidentialAssetType,
+            &other,
+        ),
+        (
+            40,
+            AssetRecordType::ConfidentialAmount_NonConfidentialAssetType,
+            &kp,
+        ),
+    ]
+    .iter()
+    .enumerate()
+    .map(|(sid, (amount, record_type, owner))| {
+        let template = AssetRecordTemplate::with_no_asset_tracing(
+            *amount,
+            code.val,
+            *record_type,
+            owner.get_pk().into_noah(),
+        );
+        let (record, _, owner_memo) =
+            build_blind_asset_record(&mut prng, &pc_gens, &template, vec![]);
+        RecordToOpen {
+            sid: Some(sid as u64),
+            record: BlindAssetRecord::from_noah(&record),
+            owner_memo,
+        }
+    })
+    .collect::<Vec<_>>();
+
+    // round trip as the bindings get them
+    let records: Vec<RecordToOpen> =
+        serde_json::from_str(&serde_json::to_string(&records).unwrap()).unwrap();
+
+    let mut reports = vec![];
+    let opened = open_records(&kp, &records, 3, &mut |done, total| {
+        reports.push((done, total));
+        true
+    })
+    .unwrap();
+    assert_eq!(reports, [(3, 4), (4, 4)]);
+
+    assert_eq!(opened.records.len(), 4);
+    assert_eq!(opened.records[0].amount.as_deref(), Some("10"));
+    assert!(!opened.records[0].confidential);
+    assert_eq!(opened.records[1].amount.as_deref(), Some("20"));
+    assert!(opened.records[1].confidential);
+    assert!(opened.records[2].error.is_some());
+    assert_eq!(opened.records[3].sid, Some(3));
+    assert_eq!(opened.balances.len(), 1);
+    assert_eq!(opened.balances[&code.to_base64()], "70");
+
+    assert!(open_records(&kp, &records, 2, &mut |_, _| false).is_err());
+}
+
+#[test]
+fn t_estimate_fee() {
+    use super::network::{HttpRequest, HttpTransport};
+    use account::EVMFee;
+    use fee::{DraftTx, FeeEstimator};
+    use fp_types::U256;
+    use std::sync::Arc;
+
+    struct MockNode {
+        base_fee: bool,
+    }
+
+    impl HttpTransport for MockNode {
+        fn send(&self, req: &HttpRequest) -> ruc::Result<Vec<u8>> {
+            let body: serde_json::Value =
+                serde_json::from_str(req.body.as_deref().unwrap()).unwrap();
+            let result = match body["method"].as_str().unwrap() {
+                "eth_estimateGas" => {
+                    assert_eq!(body["params"][0]["from"], "0x01");
+                    serde_json::json!("0x61a8") // 25000
+                }
+                // 10 gwei
+                "eth_gasPrice" => serde_json::json!("0x2540be400"),
+                "eth_feeHistory" if self.base_fee => {
+                    // 4 gwei
+                    serde_json::json!({ "baseFeePerGas": ["0x1", "0xee6b2800"] })
+                }
+                _ => {
+                    return Ok(
+                        br#"{"jsonrpc":"2.0","id":1,"error":{"message":"no"}}"#.to_vec()
+                    )
+                }
+            };
+            Ok(
+                serde_json::json!({ "jsonrpc": "2.0", "id": 1, "result": result })
+                    .to_string()
+                    .into_bytes(),
+            )
+        }
+    }
+
+    let legacy = FeeEstimator::new(
+        "http://127.0.0.1:8545",
+        Arc::new(MockNode { base_fee: false }),
+    );
+    let eip1559 = FeeEstimator::new(
+        "http://127.0.0.1:8545",
+        Arc::new(MockNode { base_fee: true }),
+    );
+
+    let utxo = DraftTx::Utxo {
+        tx: TransactionBuilder::new(1).transaction(),
+    };
+    let fee = legacy.estimate(&utxo).unwrap();
+    assert_eq!(fee.fee, fra_get_minimal_fee().to_string());
+    assert_eq!(fee.evm_fee, None);
+
+    let fee = legacy.estimate(&DraftTx::Account).unwrap();
+    assert_eq!(fee.fee, fra_get_minimal_fee().to_string());
+    assert_eq!(fee.evm_fee, Some(EVMFee::Minimal));
+
+    let draft = DraftTx::Erc20Transfer {
+        from: "0x01".to_owned(),
+        contract: format!("0x{}", "22".repeat(20)),
+        to: format!("0x{}", "33".repeat(20)),
+        amount: "1000".to_owned(),
+    };
+    let call = draft.call().unwrap();
+    assert!(call["data"].as_str().unwrap().starts_with("0xa9059cbb"));
+
+    // 30000 gas with the margin, at 10 gwei: 0.0003 FRA
+    let fee = legacy.estimate(&draft).unwrap();
+    assert_eq!(fee.fee, "300");
+    assert_eq!(
+        fee.evm_fee,
+        Some(EVMFee::Legacy {
+            gas_limit: 30000,
+            gas_price: U256::from(10_000_000_000u64),
+        })
+    );
+
+    let fee = eip1559.estimate(&draft).unwrap();
+    assert_eq!(fee.fee, "300");
+    assert_eq!(
+        fee.evm_fee,
+        Some(EVMFee::Eip1559 {
+            gas_limit: 30000,
+            max_fee_per_gas: U256::from(14_000_000_000u64),
+            max_priority_fee_per_gas: U256::from(6_000_000_000u64),
+            base_fee: U256::from(4_000_000_000u64),
+        })
+    );
+}
+
+#[test]
+fn t_anon() {
+    use anon::{
+        anon_public_key_from_str, gen_nullifier_hash, open_abar, AnonInput,
+        AnonTransferOperationBuilder,
+    };
+    use ledger::data_model::{AssetTypeCode, Operation, TxOutput};
+    use rand_chacha::ChaChaRng;
+    use rand_core::SeedableRng;
+    use zei::{
+        noah_algebra::ristretto::PedersenCommitmentRistretto,
+        noah_api::xfr::{
+            asset_record::{build_blind_asset_record, AssetRecordType},
+            structs::AssetRecordTemplate,
+        },
+        BlindAssetRecord,
+    };
+
+    let mut prng = ChaChaRng::from_seed([0u8; 32]);
+    let kp = new_keypair();
+    let other = new_keypair();
+    let code = AssetTypeCode::gen_random();
+
+    let template = AssetRecordTemplate::with_no_asset_tracing(
+        100,
+        code.val,
+        AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+        kp.get_pk().into_noah(),
+    );
+    let (record, _, _) = build_blind_asset_record(
+        &mut prng,
+        &PedersenCommitmentRistretto::default(),
+        &template,
+        vec![],
+    );
+    let record = ClientAssetRecord {
+        txo: TxOutput {
+            id: None,
+            record: BlindAssetRecord::from_noah(&record),
+            lien: None,
+            lock: None,
+        },
+    };
+
+    assert_eq!(
+        anon_public_key_from_str(&public_key_to_bech32(&kp.get_pk())).unwrap(),
+        kp.get_pk()
+    );
+    assert_eq!(
+        anon_public_key_from_str(&public_key_to_base64(&kp.get_pk())).unwrap(),
+        kp.get_pk()
+    );
+
+    // the record of another key can not be converted
+    assert!(TransactionBuilder::new(1)
+        .add_operation_bar_to_abar(
+            &hex::encode([7u8; 32]),
+            &other,
+            &kp.get_pk(),
+            1,
+            &record,
+            None,
+        )
+        .is_err());
+
+    let builder = TransactionBuilder::new(1)
+        .add_operation_bar_to_abar(
+            &hex::encode([7u8; 32]),
+            &kp,
+            &kp.get_pk(),
+            1,
+            &record,
+            None,
+        )
+        .unwrap();
+    let commitments = builder.get_commitments();
+    assert_eq!(commitments.len(), 1);
+
+    let owner_memo = match &builder.get_builder().get_transaction().body.operations[0] {
+        Operation::BarToAbar(op) => op.axfr_memo(),
+        _ => panic!("not a BarToAbar"),
+    };
+    let input = AnonInput {
+        commitment: commitments[0].clone(),
+        owner_memo,
+        mt_leaf_info: None,
+    };
+
+    let opened = open_abar(&input, &kp).unwrap();
+    assert_eq!(opened.commitment, commitments[0]);
+    assert_eq!(opened.asset_type, code.to_base64());
+    assert_eq!(opened.amount, 100);
+    assert!(open_abar(&input, &other).is_err());
+
+    // spending needs the merkle proof of the ABAR
+    assert!(gen_nullifier_hash(&input, &kp).is_err());
+    assert!(AnonTransferOperationBuilder::new(1)
+        .add_input(&input, &kp)
+        .is_err());
+
+    let transfer = AnonTransferOperationBuilder::new(1)
+        .add_output(50, &code.to_base64(), &other.get_pk())
+        .unwrap();
+    assert!(transfer.transaction().is_err());
+    assert!(transfer.get_commitments().is_empty());
+}
+
+#[test]
+fn t_address() {
+    use address::{
+        evm_checksum_address, parse_address, parse_public_key, AddressFormat,
+    };
+
+    let kp = new_keypair();
+    let bech32 = public_key_to_bech32(&kp.get_pk());
+    let base64 = public_key_to_base64(&kp.get_pk());
+
+    for input in [bech32.clone(), base64.clone(), format!(" {}\n", bech32)] {
+        let parsed = parse_address(&input).unwrap();
+        assert_eq!(parsed.bech32.as_deref(), Some(bech32.as_str()));
+        assert_eq!(parsed.base64.as_deref(), Some(base64.as_str()));
+        assert_eq!(parsed.evm, None);
+    }
+    assert_eq!(
+        parse_address(&bech32).unwrap().format,
+        AddressFormat::Bech32
+    );
+    assert_eq!(
+        parse_address(&base64).unwrap().format,
+        AddressFormat::Base64
+    );
+    assert!(parse_address(&bech32[..bech32.len() - 1]).is_err());
+    assert!(parse_address("fra1").is_err());
+    assert!(parse_address("").is_err());
+    for input in [&bech32, &base64] {
+        assert_eq!(parse_public_key(input).unwrap(), kp.get_pk());
+    }
+    assert!(parse_public_key(&base64[1..]).is_err());
+
+    // EIP-55 test vectors
+    for addr in [
+        "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+        "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+        "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+        "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+    ] {
+        assert_eq!(evm_checksum_address(addr).unwrap(), addr);
+        assert_eq!(evm_checksum_address(&addr.to_lowercase()).unwrap(), addr);
+        let parsed = parse_address(&addr.to_uppercase().replace("0X", "0x")).unwrap();
+        assert_eq!(parsed.format, AddressFormat::Evm);
+        assert_eq!(parsed.evm.as_deref(), Some(addr));
+        assert_eq!(parsed.bech32, None);
+    }
+    // a typo in a checksummed address
+    assert!(evm_checksum_address("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD").is_err());
+    assert!(evm_checksum_address("0x5aaeb6053f3e94c9b9a09f33669435e7ef1beae").is_err());
+    assert!(evm_checksum_address("0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaeg").is_err());
+    assert!(evm_checksum_address("5aaeb6053f3e94c9b9a09f33669435e7ef1beaed").is_err());
+}
+
+#[test]
+fn t_logger() {
+    use logger::{log_level, set_log_level};
+
+    assert!(set_log_level("verbose").is_err());
+    assert!(set_log_level("6").is_err());
+
+    set_log_level(" DEBUG ").unwrap();
+    assert_eq!(log_level(), "debug");
+    assert!(tracing::enabled!(tracing::Level::DEBUG));
+    assert!(!tracing::enabled!(tracing::Level::TRACE));
+    tracing::debug!(seq_id = 1, "logged to stderr");
+
+    set_log_level("off").unwrap();
+    assert_eq!(log_level(), "off");
+    assert!(!tracing::enabled!(tracing::Level::ERROR));
+}
+
+#[test]
+fn t_staking() {
+    use address::parse_validator_address;
+    use ledger::data_model::Operation;
+
+    let validator = "A50D65F2F63F65D845A7C5CBB989FF94D6688F38";
+    assert_eq!(parse_validator_address(validator).unwrap(), validator);
+    assert_eq!(
+        parse_validator_address(&format!(" 0x{} ", validator.to_lowercase())).unwrap(),
+        validator
+    );
+    assert!(parse_validator_address(&validator[1..]).is_err());
+    assert!(
+        parse_validator_address("G50D65F2F63F65D845A7C5CBB989FF94D6688F38").is_err()
+    );
+    assert!(parse_validator_address("").is_err());
+
+    let kp = new_keypair();
+    assert!(TransactionBuilder::new(1)
+        .add_operation_delegate(&kp, 100, "fra1validator".to_owned())
+        .is_err());
+    assert!(TransactionBuilder::new(1)
+        .add_operation_claim_rewards(&kp, &validator[2..], 0)
+        .is_err());
+
+    let builder = TransactionBuilder::new(1)
+        .add_operation_delegate(&kp, 100, validator.to_lowercase())
+        .unwrap()
+        .add_operation_claim_rewards(&kp, validator, 0)
+        .unwrap()
+        .add_operation_claim_rewards(&kp, validator, 7)
+        .unwrap()
+        .add_operation_undelegate(&kp)
+        .unwrap();
+    let ops = &builder.get_builder().get_transaction().body.operations;
+    assert_eq!(ops.len(), 4);
+    match &ops[0] {
+        Operation::Delegation(op) => {
+            let op = serde_json::to_value(op).unwrap();
+            assert_eq!(op["body"]["validator"], validator);
+        }
+        _ => panic!("not a Delegation"),
+    }
+    for (op, am) in ops[1..3].iter().zip([None, Some(7)]) {
+        match op {
+            Operation::Claim(op) => {
+                assert_eq!(op.td_addr, Some(hex::decode(validator).unwrap()));
+                let op = serde_json::to_value(op).unwrap();
+                assert_eq!(op["body"]["amount"], serde_json::json!(am));
+            }
+            _ => panic!("not a Claim"),
+        }
+    }
+    assert!(matches!(ops[3], Operation::UnDelegation(_)));
+}
+
+#[test]
+fn t_session() {
+    use session::SecretSession;
+
+    static SESSION: SecretSession = SecretSession::new();
+
+    let kp = new_keypair();
+    let phrase = generate_mnemonic_default();
+    let secret_kp = SESSION.add_keypair(&kp);
+    let secret_phrase = SESSION.add_mnemonic(phrase.clone());
+    assert_eq!(SESSION.outstanding(), 2);
+
+    assert_eq!(secret_kp.keypair().unwrap().get_pk(), kp.get_pk());
+    assert!(secret_kp.with_mnemonic(|_| Ok(())).is_err());
+    secret_phrase
+        .with_mnemonic(|p| {
+            assert_eq!(p, phrase);
+            Ok(())
+        })
+        .unwrap();
+    assert!(secret_phrase.keypair().is_err());
+
+    drop(secret_phrase);
+    assert_eq!(SESSION.outstanding(), 1);
+
+    let other = SESSION.add_keypair(&new_keypair());
+    assert_eq!(SESSION.wipe_all(), 2);
+    assert_eq!(SESSION.outstanding(), 0);
+    assert!(!secret_kp.is_held());
+    assert!(secret_kp.keypair().is_err());
+    drop(other);
+    assert_eq!(SESSION.wipe_all(), 0);
+}
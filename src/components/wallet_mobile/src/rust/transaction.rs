@@ -1,17 +1,22 @@
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
+use super::address::parse_validator_address;
+use super::anon::AnonInput;
 use super::data_model::*;
+use super::describe::{describe_transaction, TxDescription};
+use super::signer::{sign_of, SigningProvider};
 use finutils::txn_builder::{
     FeeInput as PlatformFeeInput, FeeInputs as PlatformFeeInputs,
     TransactionBuilder as PlatformTransactionBuilder,
     TransferOperationBuilder as PlatformTransferOperationBuilder,
 };
-use globutils::HashOf;
+use globutils::{wallet, HashOf};
+use hex::FromHex;
 use ledger::{
     data_model::{
-        gen_random_keypair, AssetTypeCode, AuthenticatedTransaction, Operation,
-        TransferType, TxOutput,
+        gen_random_keypair, AssetTypeCode, AuthenticatedTransaction, AuthenticatedUtxo,
+        IndexedSignature, Operation, TransferType, TxOutput, TxoSID, XfrAddress,
     },
     staking::{td_addr_to_bytes, PartialUnDelegation, TendermintAddr},
 };
@@ -19,7 +24,10 @@ use ruc::{d, eg, err::RucResult as NewRucResult, Result as RucResult};
 use serde_json::Result;
 use zei::{
     noah_api::xfr::{
-        asset_record::{open_blind_asset_record as open_bar, AssetRecordType},
+        asset_record::{
+            open_blind_asset_record as open_bar, AssetRecordType,
+            AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+        },
         structs::AssetRecordTemplate,
     },
     OwnerMemo as NoahOwnerMemo, XfrKeyPair, XfrPublicKey,
@@ -37,6 +45,18 @@ pub fn rs_verify_authenticated_txn(
     Ok(authenticated_txn.is_valid(state_commitment))
 }
 
+/// Given a serialized state commitment and utxo, returns true if the utxo is unspent and its
+/// transaction correctly hashes up to the state commitment, false otherwise.
+pub fn rs_verify_authenticated_utxo(
+    state_commitment: String,
+    authenticated_utxo: String,
+) -> Result<bool> {
+    let authenticated_utxo =
+        serde_json::from_str::<AuthenticatedUtxo>(&authenticated_utxo)?;
+    let state_commitment = serde_json::from_str::<HashOf<_>>(&state_commitment)?;
+    Ok(authenticated_utxo.is_valid(state_commitment))
+}
+
 #[derive(Clone)]
 struct FeeInput {
     // Amount
@@ -150,6 +170,7 @@ impl TransactionBuilder {
                         id: None,
                         record: new.0,
                         lien: None,
+                        lock: None,
                     },
                 });
                 base
@@ -159,7 +180,30 @@ impl TransactionBuilder {
     /// As the last operation of any transaction,
     /// add a static fee to the transaction.
     pub fn add_fee(mut self, inputs: FeeInputs) -> RucResult<TransactionBuilder> {
-        self.transaction_builder.add_fee(inputs.into())?;
+        tracing::debug!(inputs = inputs.inner.len(), "add_fee");
+        self.transaction_builder
+            .add_fee(inputs.into())
+            .map_err(|e| {
+                tracing::warn!("add_fee failed: {}", e);
+                e
+            })?;
+        Ok(self)
+    }
+
+    /// As the last operation of BarToAbar transaction,
+    /// add a static fee to the transaction.
+    pub fn add_fee_bar_to_abar(
+        mut self,
+        inputs: FeeInputs,
+    ) -> RucResult<TransactionBuilder> {
+        tracing::debug!(inputs = inputs.inner.len(), "add_fee_bar_to_abar");
+        self.transaction_builder
+            .add_fee_bar_to_abar(inputs.into())
+            .map_err(|e| {
+                tracing::warn!("add_fee_bar_to_abar failed: {}", e);
+                e
+            })
+            .c(d!())?;
         Ok(self)
     }
 
@@ -262,13 +306,16 @@ impl TransactionBuilder {
         Ok(self)
     }
 
-    #[allow(missing_docs)]
+    /// Delegates `amount` FRA of `keypair` to `validator`, a tendermint address.
+    ///
+    /// The transfer of `amount` to the staking black hole must be added with it.
     pub fn add_operation_delegate(
         mut self,
         keypair: &XfrKeyPair,
         amount: u64,
         validator: TendermintAddr,
     ) -> RucResult<TransactionBuilder> {
+        let validator = parse_validator_address(&validator).c(d!())?;
         self.get_builder_mut()
             .add_operation_delegation(keypair, amount, validator);
         Ok(self)
@@ -291,6 +338,7 @@ impl TransactionBuilder {
         am: u64,
         target_validator: TendermintAddr,
     ) -> RucResult<TransactionBuilder> {
+        let target_validator = parse_validator_address(&target_validator).c(d!())?;
         let middle_pk = gen_random_keypair().get_pk();
         self.get_builder_mut().add_operation_undelegation(
             keypair,
@@ -329,6 +377,24 @@ impl TransactionBuilder {
         Ok(self)
     }
 
+    /// Claims the staking rewards of `keypair` from `validator`, a tendermint address:
+    /// `am` of them, or all of them if `am` is zero.
+    pub fn add_operation_claim_rewards(
+        mut self,
+        keypair: &XfrKeyPair,
+        validator: &str,
+        am: u64,
+    ) -> RucResult<TransactionBuilder> {
+        let validator = parse_validator_address(validator).c(d!())?;
+        let td_addr = td_addr_to_bytes(&validator).c(d!())?;
+        self.get_builder_mut().add_operation_claim(
+            Some(td_addr),
+            keypair,
+            if am == 0 { None } else { Some(am) },
+        );
+        Ok(self)
+    }
+
     /// Adds a serialized transfer asset operation to a transaction builder instance.
     pub fn add_transfer_operation(mut self, op: String) -> Result<TransactionBuilder> {
         let op = serde_json::from_str::<Operation>(&op)?;
@@ -336,20 +402,129 @@ impl TransactionBuilder {
         Ok(self)
     }
 
+    /// Adds an operation converting the record `txo_sid` of `auth_key_pair` to an ABAR
+    /// of `abar_pubkey`, its proof being generated from the hex `seed` of 32 bytes.
+    pub fn add_operation_bar_to_abar(
+        mut self,
+        seed: &str,
+        auth_key_pair: &XfrKeyPair,
+        abar_pubkey: &XfrPublicKey,
+        txo_sid: u64,
+        input_record: &ClientAssetRecord,
+        owner_memo: Option<OwnerMemo>,
+    ) -> RucResult<TransactionBuilder> {
+        let oar = open_bar(
+            &input_record.get_bar_ref().into_noah(),
+            &owner_memo.map(|memo| memo.get_memo_ref().clone()),
+            &auth_key_pair.into_noah(),
+        )
+        .c(d!("Could not open asset record"))?;
+        let is_bar_transparent =
+            oar.get_record_type() == NonConfidentialAmount_NonConfidentialAssetType;
+        let seed = <[u8; 32]>::from_hex(seed.trim()).c(d!("invalid seed"))?;
+
+        self.get_builder_mut()
+            .add_operation_bar_to_abar(
+                seed,
+                auth_key_pair,
+                abar_pubkey,
+                TxoSID(txo_sid),
+                &oar,
+                is_bar_transparent,
+            )
+            .c(d!())?;
+        Ok(self)
+    }
+
+    /// Adds an operation converting the ABAR `input` of `from_keypair` to a record of `recipient`.
+    pub fn add_operation_abar_to_bar(
+        mut self,
+        input: &AnonInput,
+        from_keypair: &XfrKeyPair,
+        recipient: &XfrPublicKey,
+        conf_amount: bool,
+        conf_type: bool,
+    ) -> RucResult<TransactionBuilder> {
+        let oabar = input.open_to_spend(from_keypair).c(d!())?;
+        self.get_builder_mut()
+            .add_operation_abar_to_bar(
+                &oabar,
+                from_keypair,
+                recipient,
+                AssetRecordType::from_flags(conf_amount, conf_type),
+            )
+            .c(d!())?;
+        Ok(self)
+    }
+
+    /// The base58 commitments of the ABARs created by the transaction, in its order.
+    pub fn get_commitments(&self) -> Vec<String> {
+        self.get_builder()
+            .get_transaction()
+            .body
+            .operations
+            .iter()
+            .filter_map(|op| match op {
+                Operation::BarToAbar(op) => {
+                    Some(wallet::commitment_to_base58(&op.output_record().commitment))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
     pub fn sign(mut self, kp: &XfrKeyPair) -> Result<TransactionBuilder> {
         self.get_builder_mut().sign(kp);
         Ok(self)
     }
 
+    /// Sign the transaction as `sign` does, with the key of `signer`.
+    pub fn sign_with(
+        mut self,
+        signer: &dyn SigningProvider,
+    ) -> RucResult<TransactionBuilder> {
+        let pk = signer.public_key().c(d!())?;
+        let sig = sign_of(signer, &self.get_builder().get_transaction().body).c(d!())?;
+        self.get_builder_mut().add_signature(&pk, sig).c(d!())?;
+        Ok(self)
+    }
+
     /// Extracts the serialized form of a transaction.
     pub fn transaction(&mut self) -> String {
+        tracing::debug!(
+            operations = self.get_builder().get_transaction().body.operations.len(),
+            "build transaction"
+        );
         self.get_builder_mut().build().unwrap();
         self.get_builder().serialize_str()
     }
 
     /// Calculates transaction handle.
     pub fn transaction_handle(&self) -> String {
-        self.get_builder().transaction().handle()
+        self.get_builder().get_transaction().handle()
+    }
+
+    /// Preview of the operations, amounts, recipients and fee of the transaction,
+    /// for the user to confirm what will be signed.
+    pub fn describe(&self) -> TxDescription {
+        describe_transaction(self.get_builder().get_transaction())
+    }
+
+    /// Saves the builder in a versioned format, loadable by later releases.
+    pub fn to_portable_bytes(&self) -> RucResult<Vec<u8>> {
+        self.get_builder().to_portable_bytes()
+    }
+
+    /// Loads a builder saved by `to_portable_bytes`, or by an older release.
+    pub fn from_portable_bytes(bytes: &[u8]) -> RucResult<TransactionBuilder> {
+        let transaction_builder = PlatformTransactionBuilder::from_portable_bytes(bytes)
+            .map_err(|e| {
+                tracing::warn!(len = bytes.len(), "invalid portable builder: {}", e);
+                e
+            })?;
+        Ok(TransactionBuilder {
+            transaction_builder,
+        })
     }
 
     /// Fetches a client record from a transaction.
@@ -552,6 +727,27 @@ impl TransferOperationBuilder {
         Ok(self)
     }
 
+    /// Sign the transfer as `sign` does, with the key of `signer`.
+    pub fn sign_with(
+        mut self,
+        signer: &dyn SigningProvider,
+    ) -> RucResult<TransferOperationBuilder> {
+        let body = match self.get_builder().transaction().c(d!())? {
+            Operation::TransferAsset(t) => t.body,
+            _ => return Err(eg!("not a transfer operation")),
+        };
+        let key = signer.public_key().c(d!())?;
+        let signature = sign_of(signer, &(body, None)).c(d!())?;
+        self.get_builder_mut()
+            .attach_signature(IndexedSignature {
+                address: XfrAddress { key },
+                signature,
+                input_idx: None,
+            })
+            .c(d!())?;
+        Ok(self)
+    }
+
     pub fn builder(&self) -> String {
         serde_json::to_string(self.get_builder()).unwrap()
     }
@@ -0,0 +1,127 @@
+//!
+//! Anonymous transfers, see `crate::rust::anon`.
+//!
+//! An ABAR is given as an `AnonInput`:
+//! `{commitment: <base58>, owner_memo: <AxfrOwnerMemo>, mt_leaf_info: <MTLeafInfo>}`.
+//!
+
+use crate::rust::anon::{
+    self, AnonInput, AnonTransferOperationBuilder as AnonTxOpBuilder,
+};
+use crate::rust::error_to_jsvalue;
+use ruc::{d, err::RucResult};
+use wasm_bindgen::prelude::*;
+use zei::{XfrKeyPair, XfrPublicKey};
+
+fn parse_input(input: &JsValue) -> Result<AnonInput, JsValue> {
+    input.into_serde().c(d!()).map_err(error_to_jsvalue)
+}
+
+#[wasm_bindgen]
+/// Opens an ABAR with the key pair of its owner.
+/// @param {JsValue} input - the `AnonInput` of the ABAR.
+/// @param {XfrKeyPair} keypair - owner of the ABAR.
+/// @returns {JsValue} the `OpenedAbar`, `{commitment, asset_type, amount}`.
+/// @throws Will throw an error if the ABAR fails to open.
+pub fn open_abar(input: &JsValue, keypair: &XfrKeyPair) -> Result<JsValue, JsValue> {
+    let input = parse_input(input)?;
+    anon::open_abar(&input, keypair)
+        .and_then(|opened| JsValue::from_serde(&opened).c(d!()))
+        .map_err(error_to_jsvalue)
+}
+
+#[wasm_bindgen]
+/// Generate nullifier hash of an ABAR, published when it is spent.
+/// @param {JsValue} input - the `AnonInput` of the ABAR, with its Merkle proof.
+/// @param {XfrKeyPair} keypair - owner of the ABAR.
+/// @throws Will throw an error if the ABAR fails to open.
+pub fn gen_nullifier_hash(
+    input: &JsValue,
+    keypair: &XfrKeyPair,
+) -> Result<String, JsValue> {
+    let input = parse_input(input)?;
+    anon::gen_nullifier_hash(&input, keypair).map_err(error_to_jsvalue)
+}
+
+#[wasm_bindgen]
+/// Structure that enables clients to construct transfers between ABARs.
+pub struct AnonTransferOperationBuilder(AnonTxOpBuilder);
+
+#[wasm_bindgen]
+impl AnonTransferOperationBuilder {
+    /// new is a constructor for AnonTransferOperationBuilder
+    pub fn new(seq_id: u64) -> Self {
+        AnonTransferOperationBuilder(AnonTxOpBuilder::new(seq_id))
+    }
+
+    /// add_input is used to add a new input source for Anon Transfer
+    /// @param {JsValue} input - the `AnonInput` of the ABAR, with its Merkle proof.
+    /// @param {XfrKeyPair} keypair - owner of the ABAR.
+    /// @throws Will throw an error if abar fails to open, input fails to get added to Operation
+    pub fn add_input(
+        self,
+        input: &JsValue,
+        keypair: &XfrKeyPair,
+    ) -> Result<AnonTransferOperationBuilder, JsValue> {
+        let input = parse_input(input)?;
+        let builder = self
+            .0
+            .add_input(&input, keypair)
+            .map_err(error_to_jsvalue)?;
+        Ok(AnonTransferOperationBuilder(builder))
+    }
+
+    /// add_output is used to add a output to the Anon Transfer
+    /// @param {BigInt} amount - amount to be sent to the receiver
+    /// @param {string} asset_type - base64 asset type
+    /// @param {XfrPublicKey} to - receiver
+    /// @throws error if ABAR fails to be built
+    pub fn add_output(
+        self,
+        amount: u64,
+        asset_type: String,
+        to: &XfrPublicKey,
+    ) -> Result<AnonTransferOperationBuilder, JsValue> {
+        let builder = self
+            .0
+            .add_output(amount, &asset_type, to)
+            .map_err(error_to_jsvalue)?;
+        Ok(AnonTransferOperationBuilder(builder))
+    }
+
+    /// add_keypair is used to add the sender's keypair for the nullifier generation
+    pub fn add_keypair(self, keypair: &XfrKeyPair) -> AnonTransferOperationBuilder {
+        AnonTransferOperationBuilder(self.0.add_keypair(keypair))
+    }
+
+    /// get_expected_fee is used to gather extra FRA that needs to be spent to make the transaction
+    /// have enough fees.
+    pub fn get_expected_fee(&self) -> Result<u64, JsValue> {
+        self.0.get_expected_fee().map_err(error_to_jsvalue)
+    }
+
+    /// get_total_fee_estimate
+    pub fn get_total_fee_estimate(&self) -> Result<u64, JsValue> {
+        self.0.get_total_fee_estimate().map_err(error_to_jsvalue)
+    }
+
+    /// build is used to build proof the Transfer Operation
+    pub fn build(self) -> Result<AnonTransferOperationBuilder, JsValue> {
+        let builder = self.0.build().map_err(error_to_jsvalue)?;
+        Ok(AnonTransferOperationBuilder(builder))
+    }
+
+    /// get_commitments returns the base58 commitments of the outputs, remainders included
+    pub fn get_commitments(&self) -> Vec<JsValue> {
+        self.0
+            .get_commitments()
+            .into_iter()
+            .map(JsValue::from)
+            .collect()
+    }
+
+    /// transaction returns the prepared Anon Transfer Operation
+    pub fn transaction(&self) -> Result<String, JsValue> {
+        self.0.transaction().map_err(error_to_jsvalue)
+    }
+}
@@ -1,13 +1,37 @@
+mod anon;
+mod network;
+
+use crate::rust::account;
+use crate::rust::address;
+use crate::rust::anon::AnonInput;
+use crate::rust::balance;
+use crate::rust::logger;
 use crate::rust::TransactionBuilder as TxBuilder;
 use crate::rust::TransferOperationBuilder as TxOpBuilder;
 use crate::rust::*;
 use credentials::{
     CredIssuerPublicKey, CredIssuerSecretKey, CredUserPublicKey, CredUserSecretKey,
 };
+use ledger::data_model::AssetTypeCode;
 use ruc::{d, err::RucResult};
 use wasm_bindgen::prelude::*;
 use zei::{noah_api::xfr::structs::ASSET_TYPE_LENGTH, XfrKeyPair, XfrPublicKey};
 
+#[wasm_bindgen]
+/// Logs the events of the library to the console up to `level`:
+/// one of `off`, `error`, `warn`, `info`, `debug` or `trace`.
+/// Can be called again to change the level.
+/// @throws Will throw an error if the level is unknown.
+pub fn set_log_level(level: &str) -> Result<(), JsValue> {
+    logger::set_log_level(level).map_err(error_to_jsvalue)
+}
+
+#[wasm_bindgen]
+/// Returns the level set by `set_log_level`, `off` before.
+pub fn get_log_level() -> String {
+    logger::log_level()
+}
+
 #[wasm_bindgen]
 /// Generates asset type as a Base64 string from a JSON-serialized JavaScript value.
 pub fn asset_type_from_jsvalue(val: &JsValue) -> Result<String, JsValue> {
@@ -34,6 +58,21 @@ pub fn verify_authenticated_txn(
         .map_err(error_to_jsvalue)
 }
 
+#[wasm_bindgen]
+/// Given a serialized state commitment and utxo, returns true if the utxo is unspent and its
+/// transaction correctly hashes up to the state commitment, false otherwise.
+/// @param {string} state_commitment - String representing the state commitment.
+/// @param {string} authenticated_utxo - String representing the utxo, as returned by `utxo_sid`.
+/// @throws Will throw an error if the state commitment or the utxo fails to deserialize.
+pub fn verify_authenticated_utxo(
+    state_commitment: String,
+    authenticated_utxo: String,
+) -> Result<bool, JsValue> {
+    rs_verify_authenticated_utxo(state_commitment, authenticated_utxo)
+        .c(d!())
+        .map_err(error_to_jsvalue)
+}
+
 #[wasm_bindgen]
 /// Structure that allows users to construct arbitrary transactions.
 pub struct TransactionBuilder(TxBuilder);
@@ -79,6 +118,20 @@ impl TransactionBuilder {
         Ok(TransactionBuilder(builder))
     }
 
+    /// As the last operation of BarToAbar transaction,
+    /// add a static fee to the transaction.
+    pub fn add_fee_bar_to_abar(
+        self,
+        inputs: FeeInputs,
+    ) -> Result<TransactionBuilder, JsValue> {
+        let builder = self
+            .0
+            .add_fee_bar_to_abar(inputs)
+            .c(d!())
+            .map_err(error_to_jsvalue)?;
+        Ok(TransactionBuilder(builder))
+    }
+
     /// A simple fee checker for mainnet v1.0.
     ///
     /// SEE [check_fee](ledger::data_model::Transaction::check_fee)
@@ -201,6 +254,56 @@ impl TransactionBuilder {
         Ok(TransactionBuilder(builder))
     }
 
+    /// Delegates FRA to a validator.
+    /// The transfer of `amount` to the staking black hole must be added with it.
+    /// @param {XfrKeyPair} keypair - delegator.
+    /// @param {BigInt} amount - FRA units to delegate.
+    /// @param {string} validator - tendermint address of the validator, 40 hex digits.
+    /// @throws Will throw an error if the validator address is invalid.
+    pub fn add_operation_delegate(
+        self,
+        keypair: &XfrKeyPair,
+        amount: u64,
+        validator: String,
+    ) -> Result<TransactionBuilder, JsValue> {
+        let builder = self
+            .0
+            .add_operation_delegate(keypair, amount, validator)
+            .map_err(error_to_jsvalue)?;
+        Ok(TransactionBuilder(builder))
+    }
+
+    /// Undelegates all the FRA delegated by `keypair`.
+    /// @param {XfrKeyPair} keypair - delegator.
+    pub fn add_operation_undelegate(
+        self,
+        keypair: &XfrKeyPair,
+    ) -> Result<TransactionBuilder, JsValue> {
+        let builder = self
+            .0
+            .add_operation_undelegate(keypair)
+            .map_err(error_to_jsvalue)?;
+        Ok(TransactionBuilder(builder))
+    }
+
+    /// Claims staking rewards from a validator.
+    /// @param {XfrKeyPair} keypair - delegator.
+    /// @param {string} validator - tendermint address of the validator, 40 hex digits.
+    /// @param {BigInt} am - FRA units to claim, all the rewards if zero.
+    /// @throws Will throw an error if the validator address is invalid.
+    pub fn add_operation_claim_rewards(
+        self,
+        keypair: &XfrKeyPair,
+        validator: String,
+        am: u64,
+    ) -> Result<TransactionBuilder, JsValue> {
+        let builder = self
+            .0
+            .add_operation_claim_rewards(keypair, &validator, am)
+            .map_err(error_to_jsvalue)?;
+        Ok(TransactionBuilder(builder))
+    }
+
     /// Adds a serialized transfer asset operation to a transaction builder instance.
     /// @param {string} op - a JSON-serialized transfer operation.
     /// @see {@link module:Findora-Wasm~TransferOperationBuilder} for details on constructing a transfer operation.
@@ -218,6 +321,79 @@ impl TransactionBuilder {
         Ok(TransactionBuilder(builder))
     }
 
+    /// Adds an operation converting a record to an ABAR.
+    ///
+    /// @param {string} seed - hex of 32 random bytes, generating the proof.
+    /// @param {XfrKeyPair} auth_key_pair - owner of the record.
+    /// @param {XfrPublicKey} abar_pubkey - receiver of the ABAR.
+    /// @param {BigInt} txo_sid - sid of the record.
+    /// @param {ClientAssetRecord} input_record - the record.
+    /// @param {OwnerMemo} owner_memo - its owner memo, if confidential.
+    /// @throws Will throw an error if the record fails to open with `auth_key_pair`.
+    pub fn add_operation_bar_to_abar(
+        self,
+        seed: String,
+        auth_key_pair: &XfrKeyPair,
+        abar_pubkey: &XfrPublicKey,
+        txo_sid: u64,
+        input_record: &ClientAssetRecord,
+        owner_memo: Option<OwnerMemo>,
+    ) -> Result<TransactionBuilder, JsValue> {
+        let builder = self
+            .0
+            .add_operation_bar_to_abar(
+                &seed,
+                auth_key_pair,
+                abar_pubkey,
+                txo_sid,
+                input_record,
+                owner_memo,
+            )
+            .c(d!())
+            .map_err(error_to_jsvalue)?;
+        Ok(TransactionBuilder(builder))
+    }
+
+    /// Adds an operation converting an ABAR to a record.
+    ///
+    /// @param {JsValue} input - the `AnonInput` of the ABAR, with its Merkle proof.
+    /// @param {XfrKeyPair} from_keypair - owner of the ABAR.
+    /// @param {XfrPublicKey} recipient - receiver of the record.
+    /// @param {boolean} conf_amount - whether the amount of the record is confidential.
+    /// @param {boolean} conf_type - whether the asset type of the record is confidential.
+    /// @throws Will throw an error if `input` fails to deserialize or to open.
+    pub fn add_operation_abar_to_bar(
+        self,
+        input: &JsValue,
+        from_keypair: &XfrKeyPair,
+        recipient: &XfrPublicKey,
+        conf_amount: bool,
+        conf_type: bool,
+    ) -> Result<TransactionBuilder, JsValue> {
+        let input: AnonInput = input.into_serde().c(d!()).map_err(error_to_jsvalue)?;
+        let builder = self
+            .0
+            .add_operation_abar_to_bar(
+                &input,
+                from_keypair,
+                recipient,
+                conf_amount,
+                conf_type,
+            )
+            .c(d!())
+            .map_err(error_to_jsvalue)?;
+        Ok(TransactionBuilder(builder))
+    }
+
+    /// The base58 commitments of the ABARs created by the transaction, in its order.
+    pub fn get_commitments(&self) -> Vec<JsValue> {
+        self.0
+            .get_commitments()
+            .into_iter()
+            .map(JsValue::from)
+            .collect()
+    }
+
     pub fn sign(self, kp: &XfrKeyPair) -> Result<TransactionBuilder, JsValue> {
         let builder = self.0.sign(kp).c(d!()).map_err(error_to_jsvalue)?;
         Ok(TransactionBuilder(builder))
@@ -233,6 +409,14 @@ impl TransactionBuilder {
         self.0.transaction_handle()
     }
 
+    /// Preview of the operations, amounts, recipients and fee of the transaction,
+    /// for the user to confirm what will be signed.
+    pub fn describe(&self) -> Result<JsValue, JsValue> {
+        JsValue::from_serde(&self.0.describe())
+            .c(d!())
+            .map_err(error_to_jsvalue)
+    }
+
     /// Fetches a client record from a transaction.
     /// @param {number} idx - Record to fetch. Records are added to the transaction builder sequentially.
     pub fn get_owner_record(&self, idx: usize) -> ClientAssetRecord {
@@ -448,6 +632,45 @@ pub fn open_client_asset_record(
         .and_then(|oa| JsValue::from_serde(&oa).c(d!()).map_err(error_to_jsvalue))
 }
 
+#[wasm_bindgen]
+/// Opens the records of an account and sums their balances, `chunk_size` at a time.
+///
+/// @param {XfrKeyPair} keypair - Keypair of the owner.
+/// @param {JsValue} records - Array of the `OwnedUtxo`s of the query server, or of `{record, owner_memo}`.
+/// @param {number} chunk_size - Count of the records opened between two calls to `progress`, 0 for the default.
+/// @param {Function} progress - Optional `(done, total) => boolean`, returning false cancels the batch.
+/// @returns {JsValue} The `OpenedRecords`, a record that can not be opened is reported with its `error`.
+/// @throws Will throw an error if `records` fails to deserialize, or the batch is cancelled.
+pub fn open_records(
+    keypair: &XfrKeyPair,
+    records: &JsValue,
+    chunk_size: u32,
+    progress: Option<js_sys::Function>,
+) -> Result<JsValue, JsValue> {
+    let records: Vec<balance::RecordToOpen> =
+        records.into_serde().c(d!()).map_err(error_to_jsvalue)?;
+    let chunk_size = if chunk_size > 0 {
+        chunk_size as usize
+    } else {
+        balance::DEFAULT_CHUNK_SIZE
+    };
+    let mut report = |done: usize, total: usize| match progress.as_ref() {
+        Some(progress) => progress
+            .call2(
+                &JsValue::NULL,
+                &JsValue::from(done as u32),
+                &JsValue::from(total as u32),
+            )
+            .map(|res| res != JsValue::FALSE)
+            .unwrap_or(false),
+        None => true,
+    };
+    balance::open_records(keypair, &records, chunk_size, &mut report)
+        .c(d!())
+        .and_then(|opened| JsValue::from_serde(&opened).c(d!()))
+        .map_err(error_to_jsvalue)
+}
+
 #[wasm_bindgen]
 /// Converts a base64 encoded public key string to a public key.
 pub fn public_key_from_base64(pk: &str) -> Result<XfrPublicKey, JsValue> {
@@ -633,6 +856,24 @@ pub fn base64_to_bech32(pk: &str) -> Result<String, JsValue> {
     rs_base64_to_bech32(pk).c(d!()).map_err(error_to_jsvalue)
 }
 
+#[wasm_bindgen]
+/// Parses an address entered by the user: a bech32 `fra1...` address, a base64 public key
+/// or a `0x` EVM address, checked against its EIP-55 checksum if in mixed case.
+/// @returns {JsValue} the `ParsedAddress`, `{format, bech32, base64, evm}`.
+/// @throws Will throw an error if the address is invalid.
+pub fn parse_address(address: &str) -> Result<JsValue, JsValue> {
+    address::parse_address(address)
+        .and_then(|parsed| JsValue::from_serde(&parsed).c(d!()))
+        .map_err(error_to_jsvalue)
+}
+
+#[wasm_bindgen]
+/// Returns the EIP-55 checksummed form of a `0x` EVM address.
+/// @throws Will throw an error if the address is invalid.
+pub fn evm_checksum_address(address: &str) -> Result<String, JsValue> {
+    address::evm_checksum_address(address).map_err(error_to_jsvalue)
+}
+
 /// Generate mnemonic with custom length and language.
 /// - @param `wordslen`: acceptable value are one of [ 12, 15, 18, 21, 24 ]
 /// - @param `lang`: acceptable value are one of [ "en", "zh", "zh_traditional", "fr", "it", "ko", "sp", "jp" ]
@@ -679,3 +920,194 @@ pub fn restore_keypair_from_mnemonic_bip49(
         .c(d!())
         .map_err(error_to_jsvalue)
 }
+
+/// Restore the XfrKeyPair from a mnemonic along a derivation path,
+/// "m/44'/coin'/account'/change/address" or "m/49'/coin'/account'/change/address".
+#[wasm_bindgen]
+pub fn restore_keypair_from_mnemonic_path(
+    phrase: &str,
+    lang: &str,
+    path: &str,
+) -> Result<XfrKeyPair, JsValue> {
+    rs_restore_keypair_from_mnemonic_path(phrase, lang, path)
+        .c(d!())
+        .map_err(error_to_jsvalue)
+}
+
+/// Validate a user-entered mnemonic, returns a `MnemonicCheck` object
+/// with the suggested replacements of its misspelled words.
+/// - @param `lang`: acceptable value are one of [ "en", "zh", "zh_traditional", "fr", "it", "ko", "sp", "jp" ]
+#[wasm_bindgen]
+pub fn validate_mnemonic(phrase: &str, lang: &str) -> Result<JsValue, JsValue> {
+    rs_validate_mnemonic(phrase, lang)
+        .and_then(|check| JsValue::from_serde(&check).c(d!()))
+        .map_err(error_to_jsvalue)
+}
+
+/// Restore the hex-encoded ethereum private key from a mnemonic
+/// along a bip32 derivation path, eg. "m/44'/60'/0'/0/0".
+#[wasm_bindgen]
+pub fn restore_evm_secret_from_mnemonic(
+    phrase: &str,
+    lang: &str,
+    path: &str,
+) -> Result<String, JsValue> {
+    account::restore_evm_secret_from_mnemonic(phrase, lang, path)
+        .c(d!())
+        .map_err(error_to_jsvalue)
+}
+
+/// The `0x` prefixed ethereum address of a hex-encoded private key.
+#[wasm_bindgen]
+pub fn evm_address_from_secret(sk: &str) -> Result<String, JsValue> {
+    account::evm_address_from_secret(sk)
+        .c(d!())
+        .map_err(error_to_jsvalue)
+}
+
+#[wasm_bindgen]
+/// Structure that constructs the transactions moving balances between UTXOs and accounts.
+pub struct EVMTransactionBuilder(account::EVMTransactionBuilder);
+
+#[wasm_bindgen]
+impl EVMTransactionBuilder {
+    /// Construct an EVM Transaction that transfer account balance to UTXO.
+    /// @param {XfrPublicKey} recipient - UTXO Asset receiver.
+    /// @param {u64} amount - Transfer amount.
+    /// @param {string} sk - Ethereum wallet private key.
+    /// @param {u64} nonce - Transaction nonce for sender.
+    pub fn new_transfer_from_account(
+        recipient: &XfrPublicKey,
+        amount: u64,
+        sk: String,
+        nonce: u64,
+    ) -> Result<EVMTransactionBuilder, JsValue> {
+        account::EVMTransactionBuilder::new_transfer_from_account(
+            *recipient,
+            amount,
+            sk,
+            nonce.into(),
+        )
+        .c(d!())
+        .map(EVMTransactionBuilder)
+        .map_err(error_to_jsvalue)
+    }
+
+    /// Construct an EVM Transaction that transfer account balance to UTXO,
+    /// paying a custom fee.
+    /// @param {XfrPublicKey} recipient - UTXO Asset receiver.
+    /// @param {u64} amount - Transfer amount.
+    /// @param {string} sk - Ethereum wallet private key.
+    /// @param {u64} nonce - Transaction nonce for sender.
+    /// @param {any} fee - `EVMFee` object, e.g.
+    /// `{Legacy: {gas_limit: 21000, gas_price: "0x2540be400"}}`.
+    pub fn new_transfer_from_account_with_fee(
+        recipient: &XfrPublicKey,
+        amount: u64,
+        sk: String,
+        nonce: u64,
+        fee: &JsValue,
+    ) -> Result<EVMTransactionBuilder, JsValue> {
+        let fee: account::EVMFee = fee.into_serde().c(d!()).map_err(error_to_jsvalue)?;
+        account::EVMTransactionBuilder::new_transfer_from_account_with_fee(
+            *recipient,
+            amount,
+            sk,
+            nonce.into(),
+            &fee,
+        )
+        .c(d!())
+        .map(EVMTransactionBuilder)
+        .map_err(error_to_jsvalue)
+    }
+
+    /// Construct an EVM Transaction that transfer ERC-20 tokens.
+    /// @param {string} contract - Hex-encoded address of the token contract.
+    /// @param {string} to - Hex-encoded address of the receiver.
+    /// @param {string} amount - Decimal amount of the token, in its smallest unit.
+    /// @param {string} sk - Ethereum wallet private key.
+    /// @param {u64} nonce - Transaction nonce for sender.
+    /// @param {u64} chain_id - Chain id of the Findora EVM.
+    /// @param {any} fee - `EVMFee` object, not `Minimal`.
+    pub fn new_erc20_transfer(
+        contract: &str,
+        to: &str,
+        amount: &str,
+        sk: String,
+        nonce: u64,
+        chain_id: u64,
+        fee: &JsValue,
+    ) -> Result<EVMTransactionBuilder, JsValue> {
+        let contract = account::parse_evm_address(contract)
+            .c(d!())
+            .map_err(error_to_jsvalue)?;
+        let to = account::parse_evm_address(to)
+            .c(d!())
+            .map_err(error_to_jsvalue)?;
+        let amount = fp_types::U256::from_dec_str(amount)
+            .map_err(|e| error_to_jsvalue(format!("{:?}", e)))?;
+        let fee: account::EVMFee = fee.into_serde().c(d!()).map_err(error_to_jsvalue)?;
+        account::EVMTransactionBuilder::new_erc20_transfer(
+            contract,
+            to,
+            amount,
+            sk,
+            nonce.into(),
+            chain_id,
+            &fee,
+        )
+        .c(d!())
+        .map(EVMTransactionBuilder)
+        .map_err(error_to_jsvalue)
+    }
+
+    /// Construct a Transaction that transfer UTXO assets to account balance.
+    /// @param {XfrKeyPair} keypair - Owner of the UTXO assets.
+    /// @param {u64} seq_id - Unique sequence ID to prevent replay attacks.
+    /// @param {string} transfer_op - JSON-serialized transfer operation sending `amount` to the
+    /// staking black hole address.
+    /// @param {u64} amount - Transfer amount.
+    /// @param {string | undefined} address - Receiving ed25519 or ecdsa address, the address of `keypair` if undefined.
+    /// @param {string | undefined} asset - Base64 code of the asset, FRA if undefined.
+    /// @param {Uint8Array | undefined} lowlevel_data - Data of the evm call.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_transfer_to_account(
+        keypair: &XfrKeyPair,
+        seq_id: u64,
+        transfer_op: String,
+        amount: u64,
+        address: Option<String>,
+        asset: Option<String>,
+        lowlevel_data: Option<Vec<u8>>,
+    ) -> Result<EVMTransactionBuilder, JsValue> {
+        let asset = asset
+            .map(|a| AssetTypeCode::new_from_base64(&a))
+            .transpose()
+            .c(d!())
+            .map_err(error_to_jsvalue)?;
+        account::EVMTransactionBuilder::new_transfer_to_account(
+            keypair,
+            seq_id,
+            transfer_op,
+            amount,
+            address,
+            asset,
+            lowlevel_data,
+        )
+        .c(d!())
+        .map(EVMTransactionBuilder)
+        .map_err(error_to_jsvalue)
+    }
+
+    /// The transaction, serialized as a base64 string.
+    pub fn serialized_transaction_base64(&self) -> String {
+        self.0.serialized_transaction_base64()
+    }
+
+    /// Preview of the transaction, for the user to confirm what will be signed.
+    pub fn describe(&self) -> Result<JsValue, JsValue> {
+        JsValue::from_serde(&self.0.describe())
+            .c(d!())
+            .map_err(error_to_jsvalue)
+    }
+}
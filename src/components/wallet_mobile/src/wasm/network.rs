@@ -0,0 +1,185 @@
+//!
+//! Requests to a node, see `crate::rust::network`.
+//!
+//! The requests are sent by `fetch`, or by a transport of the host app,
+//! eg. a native HTTP client pinning the certificates of the node.
+//! The fee estimation is here too, as it queries the web3 endpoint of the node.
+//!
+
+use crate::rust::fee::{self, DraftTx, GasPrices};
+use crate::rust::network::{self, Endpoints, HttpMethod, HttpRequest};
+use crate::rust::{error_to_jsvalue, rs_public_key_from_bech32};
+use fp_types::U256;
+use js_sys::{Function, Promise, Uint8Array};
+use ruc::{d, err::RucResult};
+use serde_json::{json, Value};
+use wasm_bindgen::{prelude::*, JsCast};
+use wasm_bindgen_futures::{future_to_promise, JsFuture};
+use web_sys::{Request, RequestInit, Response};
+
+#[wasm_bindgen]
+/// A client of the submission and query servers of a node.
+pub struct NetworkClient {
+    endpoints: Endpoints,
+    transport: Option<Function>,
+}
+
+#[wasm_bindgen]
+impl NetworkClient {
+    /// @param {string} query - url of the query server, eg. `https://prod-mainnet.prod.findora.org:8668`.
+    /// @param {string} submission - url of the submission server, eg. `https://prod-mainnet.prod.findora.org:8669`.
+    /// @param {Function} transport - optional `(method, url, body) => Promise<Uint8Array>` sending
+    /// the requests in place of `fetch`, `body` is null for a GET. It rejects for a non 2xx status.
+    pub fn new(query: String, submission: String, transport: Option<Function>) -> Self {
+        NetworkClient {
+            endpoints: Endpoints::new(&query, &submission),
+            transport,
+        }
+    }
+
+    /// Submits a serialized transaction.
+    /// @returns {Promise<string>} the handle of the transaction.
+    pub fn submit_transaction(&self, tx: String) -> Promise {
+        let req = self.endpoints.submit_transaction(&tx);
+        let transport = self.transport.clone();
+        future_to_promise(async move {
+            let resp = send(transport, req).await?;
+            network::parse_txn_handle(&resp)
+                .map(JsValue::from)
+                .map_err(error_to_jsvalue)
+        })
+    }
+
+    /// Queries the utxos of the bech32 `address`.
+    /// @returns {Promise<Array>} the `OwnedUtxo`s of the address, the oldest first.
+    /// @throws Will throw an error if the address is invalid.
+    pub fn owned_utxos(&self, address: String) -> Result<Promise, JsValue> {
+        let owner = rs_public_key_from_bech32(&address)
+            .c(d!())
+            .map_err(error_to_jsvalue)?;
+        let req = self.endpoints.owned_utxos(&owner);
+        let transport = self.transport.clone();
+        Ok(future_to_promise(async move {
+            let resp = send(transport, req).await?;
+            network::parse_owned_utxos(&resp)
+                .and_then(|utxos| JsValue::from_serde(&utxos).c(d!()))
+                .map_err(error_to_jsvalue)
+        }))
+    }
+
+    /// Queries the owner memos of the utxos `sids`, an array of numbers.
+    /// @returns {Promise<Array>} the memos, in the order of `sids`, null for none.
+    /// @throws Will throw an error if `sids` is not an array of numbers.
+    pub fn owner_memos(&self, sids: &JsValue) -> Result<Promise, JsValue> {
+        let sids: Vec<u64> = sids.into_serde().c(d!()).map_err(error_to_jsvalue)?;
+        if sids.is_empty() {
+            return Ok(Promise::resolve(&js_sys::Array::new()));
+        }
+        let req = self.endpoints.owner_memos(&sids);
+        let transport = self.transport.clone();
+        Ok(future_to_promise(async move {
+            let resp = send(transport, req).await?;
+            network::parse_owner_memos(&resp)
+                .and_then(|memos| JsValue::from_serde(&memos).c(d!()))
+                .map_err(error_to_jsvalue)
+        }))
+    }
+}
+
+#[wasm_bindgen]
+/// Estimates the fee of a draft transaction, before signing it.
+///
+/// @param {string} web3 - url of the web3 endpoint of the node, eg. `https://prod-mainnet.prod.findora.org:8545`.
+/// @param {JsValue} draft - The `DraftTx`, eg. `{kind: "Erc20Transfer", from, contract, to, amount}`.
+/// @param {Function} transport - optional transport, as given to `NetworkClient.new`.
+/// @returns {Promise<Object>} the `FeeEstimate`, its `fee` in the smallest FRA unit.
+/// @throws Will throw an error if `draft` fails to deserialize.
+pub fn estimate_fee(
+    web3: String,
+    draft: &JsValue,
+    transport: Option<Function>,
+) -> Result<Promise, JsValue> {
+    let draft: DraftTx = draft.into_serde().c(d!()).map_err(error_to_jsvalue)?;
+    if let Some(fee) = draft.fixed_fee().map_err(error_to_jsvalue)? {
+        let fee = JsValue::from_serde(&fee)
+            .c(d!())
+            .map_err(error_to_jsvalue)?;
+        return Ok(Promise::resolve(&fee));
+    }
+    let call = draft.call().map_err(error_to_jsvalue)?;
+    Ok(future_to_promise(async move {
+        let rpc = |method: &str, params: Value| fee::rpc_request(&web3, method, params);
+
+        let resp =
+            send(transport.clone(), rpc("eth_estimateGas", json!([call]))).await?;
+        let gas: U256 = fee::parse_rpc_response(&resp).map_err(error_to_jsvalue)?;
+        let resp = send(transport.clone(), rpc("eth_gasPrice", json!([]))).await?;
+        let gas_price: U256 =
+            fee::parse_rpc_response(&resp).map_err(error_to_jsvalue)?;
+        // a node without EIP-1559 fails or returns no base fee
+        let base_fee = match send(
+            transport,
+            rpc("eth_feeHistory", json!(["0x1", "latest", []])),
+        )
+        .await
+        {
+            Ok(resp) => fee::parse_rpc_response::<Value>(&resp)
+                .ok()
+                .and_then(|history| fee::parse_base_fee(&history)),
+            Err(_) => None,
+        };
+
+        fee::gas_fee(
+            gas,
+            &GasPrices {
+                gas_price,
+                base_fee,
+            },
+        )
+        .and_then(|fee| JsValue::from_serde(&fee).c(d!()))
+        .map_err(error_to_jsvalue)
+    }))
+}
+
+/// The body of the response to `req`.
+async fn send(
+    transport: Option<Function>,
+    req: HttpRequest,
+) -> Result<Vec<u8>, JsValue> {
+    let body = match transport {
+        Some(transport) => {
+            let body = req.body.map(JsValue::from).unwrap_or(JsValue::NULL);
+            let resp = transport.call3(
+                &JsValue::NULL,
+                &JsValue::from(req.method.as_str()),
+                &JsValue::from(req.url.as_str()),
+                &body,
+            )?;
+            JsFuture::from(Promise::resolve(&resp)).await?
+        }
+        None => {
+            let mut init = RequestInit::new();
+            init.method(req.method.as_str());
+            if let Some(body) = req.body.as_ref() {
+                init.body(Some(&JsValue::from(body.as_str())));
+            }
+            let request = Request::new_with_str_and_init(&req.url, &init)?;
+            if req.method == HttpMethod::Post {
+                request.headers().set("Content-Type", "application/json")?;
+            }
+            let window = web_sys::window().ok_or_else(|| JsValue::from("no window"))?;
+            let resp: Response = JsFuture::from(window.fetch_with_request(&request))
+                .await?
+                .dyn_into()?;
+            if !resp.ok() {
+                return Err(JsValue::from(format!(
+                    "{}: status {}",
+                    req.url,
+                    resp.status()
+                )));
+            }
+            JsFuture::from(resp.array_buffer()?).await?
+        }
+    };
+    Ok(Uint8Array::new(&body).to_vec())
+}
@@ -305,6 +305,7 @@ impl TransactionBuilder {
                             id: None,
                             record: new.0,
                             lien: None,
+                            lock: None,
                         },
                     }
                     .to_json()
@@ -843,7 +844,7 @@ impl TransactionBuilder {
 
     /// Calculates transaction handle.
     pub fn transaction_handle(&self) -> String {
-        self.get_builder().transaction().handle()
+        self.get_builder().get_transaction().handle()
     }
 
     /// Fetches a client record from a transaction.
@@ -321,6 +321,7 @@ impl TxnEffect {
                     id: None,
                     record: output.record.clone(),
                     lien: None,
+                    lock: None,
                 })
             {
                 return Err(eg!());
@@ -542,6 +543,10 @@ impl TxnEffect {
                             {
                                 return Err(eg!());
                             }
+                            // a lock is only released by the ledger, see `check_txn_effects`
+                            if txo.lock.is_some() {
+                                return Err(eg!("locked output spent in its own txn"));
+                            }
                             self.internally_spent_txos.push(txo.clone());
                         }
                     }
@@ -559,6 +564,7 @@ impl TxnEffect {
                             id: None,
                             record: record.clone(),
                             lien: lien.cloned(),
+                            lock: None,
                         },
                     );
                 }
@@ -567,7 +573,14 @@ impl TxnEffect {
 
         self.txos.reserve(trn.body.transfer.outputs.len());
         let mut conf_transfer = false;
-        for (out, lien) in trn.body.transfer.outputs.iter().zip(lien_outputs) {
+        for ((out, lien), output) in trn
+            .body
+            .transfer
+            .outputs
+            .iter()
+            .zip(lien_outputs)
+            .zip(trn.body.outputs.iter())
+        {
             if let XfrAssetType::Confidential(_) = out.asset_type {
                 conf_transfer = true;
             }
@@ -579,6 +592,7 @@ impl TxnEffect {
                 id: None,
                 record: out.clone(),
                 lien: lien.cloned(),
+                lock: output.lock.clone(),
             }));
             *txo_count += 1;
         }
@@ -638,6 +652,7 @@ impl TxnEffect {
                 id: None,
                 record: bar_to_abar.input_record(),
                 lien: None,
+                lock: None,
             },
         );
         // push new ABAR created
@@ -660,6 +675,7 @@ impl TxnEffect {
             id: None,
             record: abar_to_bar.note.get_output(),
             lien: None,
+            lock: None,
         }));
 
         Ok(())
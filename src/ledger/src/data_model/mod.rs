@@ -38,6 +38,7 @@ use {
     rand_chacha::{rand_core, ChaChaRng},
     rand_core::{CryptoRng, RngCore, SeedableRng},
     ruc::*,
+    schemars::JsonSchema,
     serde::{de::Visitor, Deserialize, Deserializer, Serialize, Serializer},
     std::{
         collections::{HashMap, HashSet},
@@ -788,6 +789,7 @@ pub struct CredentialProof {
     Deserialize,
     Eq,
     Hash,
+    JsonSchema,
     PartialEq,
     Serialize,
     Ord,
@@ -819,6 +821,7 @@ pub type TxoSIDList = Vec<TxoSID>;
     Deserialize,
     Eq,
     Hash,
+    JsonSchema,
     PartialEq,
     Serialize,
     Ord,
@@ -839,6 +842,7 @@ pub struct OutputPosition(pub usize);
     Default,
     Deserialize,
     Hash,
+    JsonSchema,
     PartialEq,
     Eq,
     PartialOrd,
@@ -886,6 +890,39 @@ pub struct TxOutput {
     #[serde(default)]
     #[serde(skip_serializing_if = "is_default")]
     pub lien: Option<HashOf<Vec<TxOutput>>>,
+    /// the output can not be spent until the lock is released,
+    /// rejected before `CheckPointConfig::output_lock_height`
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_default")]
+    pub lock: Option<OutputLock>,
+}
+
+/// A lock on a transfer output, enforced by the ledger:
+/// the output can not be spent before the last committed block
+/// has reached the height or the time of the lock,
+/// or without the co-signatures required by its threshold
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum OutputLock {
+    /// tendermint height
+    Height(u64),
+    /// tendermint block time, in seconds
+    Time(i64),
+    /// co-signers of the input spending the output, see `IndexedSignature`,
+    /// rejected before `CheckPointConfig::threshold_lock_height`
+    Threshold(SignatureRules),
+}
+
+impl OutputLock {
+    /// Whether the lock is released after the block of `height` and `time`,
+    /// always for a threshold, released by the co-signatures of each spending
+    #[inline(always)]
+    pub fn is_released(&self, height: u64, time: i64) -> bool {
+        match self {
+            OutputLock::Height(h) => *h <= height,
+            OutputLock::Time(t) => *t <= time,
+            OutputLock::Threshold(_) => true,
+        }
+    }
 }
 
 #[allow(missing_docs)]
@@ -1026,6 +1063,7 @@ impl TransferAssetBody {
                 id: None,
                 record: rec.clone(),
                 lien: None,
+                lock: None,
             })
             .collect();
         Ok(TransferAssetBody {
@@ -1697,6 +1735,50 @@ pub enum Operation {
 }
 
 impl Operation {
+    /// Names of all the operation kinds
+    pub const KINDS: [&'static str; 17] = [
+        "TransferAsset",
+        "IssueAsset",
+        "DefineAsset",
+        "UpdateMemo",
+        "UpdateStaker",
+        "Delegation",
+        "UnDelegation",
+        "Claim",
+        "UpdateValidator",
+        "Governance",
+        "FraDistribution",
+        "MintFra",
+        "ConvertAccount",
+        "BarToAbar",
+        "AbarToBar",
+        "TransferAnonAsset",
+        "ReplaceStaker",
+    ];
+
+    /// Name of the operation kind, one of `Operation::KINDS`
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Operation::TransferAsset(_) => "TransferAsset",
+            Operation::IssueAsset(_) => "IssueAsset",
+            Operation::DefineAsset(_) => "DefineAsset",
+            Operation::UpdateMemo(_) => "UpdateMemo",
+            Operation::UpdateStaker(_) => "UpdateStaker",
+            Operation::Delegation(_) => "Delegation",
+            Operation::UnDelegation(_) => "UnDelegation",
+            Operation::Claim(_) => "Claim",
+            Operation::UpdateValidator(_) => "UpdateValidator",
+            Operation::Governance(_) => "Governance",
+            Operation::FraDistribution(_) => "FraDistribution",
+            Operation::MintFra(_) => "MintFra",
+            Operation::ConvertAccount(_) => "ConvertAccount",
+            Operation::BarToAbar(_) => "BarToAbar",
+            Operation::AbarToBar(_) => "AbarToBar",
+            Operation::TransferAnonAsset(_) => "TransferAnonAsset",
+            Operation::ReplaceStaker(_) => "ReplaceStaker",
+        }
+    }
+
     /// get serialized bytes for signature and prove.
     pub fn digest(&self) -> Vec<u8> {
         match self {
@@ -2173,6 +2255,32 @@ impl Transaction {
             })
     }
 
+    /// Nonconfidential FRA sent to `BLACK_HOLE_PUBKEY` by the transfers,
+    /// the fee as seen by `check_fee`, explicit burns included
+    pub fn fee_paid(&self) -> u64 {
+        let black_hole = XfrPublicKey::from_noah(&BLACK_HOLE_PUBKEY);
+        self.body
+            .operations
+            .iter()
+            .filter_map(|op| match op {
+                Operation::TransferAsset(x) => Some(x.body.outputs.iter()),
+                _ => None,
+            })
+            .flatten()
+            .filter(|o| {
+                o.record.public_key == black_hole
+                    && matches!(
+                        o.record.asset_type,
+                        XfrAssetType::NonConfidential(ty) if ty == ASSET_TYPE_FRA
+                    )
+            })
+            .filter_map(|o| match o.record.amount {
+                XfrAmount::NonConfidential(am) => Some(am),
+                _ => None,
+            })
+            .fold(0u64, |acc, am| acc.saturating_add(am))
+    }
+
     /// findora hash
     #[inline(always)]
     pub fn hash(&self, id: TxnSID) -> HashOf<(TxnSID, Transaction)> {
@@ -2340,61 +2448,35 @@ impl Transaction {
         }
     }
 
-    /// NOTE: This method is used to verify the signature in the transaction,
-    /// when the user constructs the transaction not only needs to sign each `operation`,
-    /// but also needs to sign the whole transaction, otherwise it will not be passed here
-    #[allow(missing_docs)]
-    #[inline(always)]
-    pub fn check_tx(&self) -> Result<()> {
-        let select_check = |tx: &Transaction, pk: &XfrPublicKey| -> Result<()> {
-            if tx.signatures.is_empty() {
-                tx.check_has_signature_from_map(pk)
-            } else {
-                tx.check_has_signature(pk)
+    /// The keys whose signatures are required by `check_tx`,
+    /// in the order of the operations, without duplicates
+    pub fn required_signers(&self) -> Vec<XfrPublicKey> {
+        let mut signers: Vec<XfrPublicKey> = vec![];
+        let mut add = |pk: XfrPublicKey| {
+            if !signers.contains(&pk) {
+                signers.push(pk);
             }
         };
 
         for operation in self.body.operations.iter() {
             match operation {
                 Operation::TransferAsset(o) => {
-                    for pk in o.get_owner_addresses().iter() {
-                        select_check(self, pk).c(d!())?;
-                    }
-                }
-                Operation::IssueAsset(o) => {
-                    select_check(self, &o.pubkey.key).c(d!())?;
-                }
-                Operation::DefineAsset(o) => {
-                    select_check(self, &o.pubkey.key).c(d!())?;
-                }
-                Operation::UpdateMemo(o) => {
-                    select_check(self, &o.pubkey).c(d!())?;
-                }
-                Operation::UpdateStaker(o) => {
-                    select_check(self, &o.pubkey).c(d!())?;
-                }
-                Operation::Delegation(o) => {
-                    select_check(self, &o.pubkey).c(d!())?;
-                }
-                Operation::UnDelegation(o) => {
-                    select_check(self, &o.pubkey).c(d!())?;
-                }
-                Operation::Claim(o) => {
-                    select_check(self, &o.pubkey).c(d!())?;
+                    o.get_owner_addresses().into_iter().for_each(&mut add);
                 }
+                Operation::IssueAsset(o) => add(o.pubkey.key),
+                Operation::DefineAsset(o) => add(o.pubkey.key),
+                Operation::UpdateMemo(o) => add(o.pubkey),
+                Operation::UpdateStaker(o) => add(o.pubkey),
+                Operation::Delegation(o) => add(o.pubkey),
+                Operation::UnDelegation(o) => add(o.pubkey),
+                Operation::Claim(o) => add(o.pubkey),
                 Operation::UpdateValidator(_) => {}
                 Operation::Governance(_) => {}
                 Operation::FraDistribution(_) => {}
                 Operation::MintFra(_) => {}
-                Operation::ConvertAccount(o) => {
-                    select_check(self, &o.signer).c(d!())?;
-                }
+                Operation::ConvertAccount(o) => add(o.signer),
                 Operation::ReplaceStaker(o) => {
-                    if !o.get_related_pubkeys().is_empty() {
-                        for pk in o.get_related_pubkeys() {
-                            select_check(self, &pk).c(d!())?;
-                        }
-                    }
+                    o.get_related_pubkeys().into_iter().for_each(&mut add);
                 }
                 Operation::BarToAbar(_) => {}
                 Operation::AbarToBar(_) => {}
@@ -2402,6 +2484,37 @@ impl Transaction {
             }
         }
 
+        signers
+    }
+
+    /// Whether `public_key` has signed, in `signatures` if there is any,
+    /// or else in `pubkey_sign_map`
+    fn has_signed(&self, public_key: &XfrPublicKey) -> Result<()> {
+        if self.signatures.is_empty() {
+            self.check_has_signature_from_map(public_key)
+        } else {
+            self.check_has_signature(public_key)
+        }
+    }
+
+    /// The required signers without a valid signature yet, see `required_signers`
+    pub fn missing_signers(&self) -> Vec<XfrPublicKey> {
+        self.required_signers()
+            .into_iter()
+            .filter(|pk| self.has_signed(pk).is_err())
+            .collect()
+    }
+
+    /// NOTE: This method is used to verify the signature in the transaction,
+    /// when the user constructs the transaction not only needs to sign each `operation`,
+    /// but also needs to sign the whole transaction, otherwise it will not be passed here
+    #[allow(missing_docs)]
+    #[inline(always)]
+    pub fn check_tx(&self) -> Result<()> {
+        for pk in self.required_signers() {
+            self.has_signed(&pk).c(d!())?;
+        }
+
         Ok(())
     }
 }
@@ -299,6 +299,7 @@ fn gen_fee_operation(
                     public_key: dest_pubkey,
                 },
                 lien: None,
+                lock: None,
             }],
             lien_assignments: Vec::new(),
             transfer: Box::new(XfrBody {
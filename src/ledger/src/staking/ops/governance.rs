@@ -113,6 +113,31 @@ impl Data {
             custom_percent,
         }
     }
+
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn kind(&self) -> &ByzantineKind {
+        &self.kind
+    }
+
+    /// The validator to penalize.
+    #[inline(always)]
+    pub fn byzantine_id(&self) -> &XfrPublicKey {
+        &self.byzantine_id
+    }
+
+    #[inline(always)]
+    #[allow(missing_docs)]
+    pub fn custom_percent(&self) -> Option<[u64; 2]> {
+        self.custom_percent
+    }
+
+    /// The penalty applied, the custom one or the one of the rule of its kind.
+    #[inline(always)]
+    pub fn penalty_percent(&self) -> Option<[u64; 2]> {
+        self.custom_percent
+            .or_else(|| RULES.get(&self.kind).map(|r| r.gen_penalty_percent()))
+    }
 }
 
 /// Kinds of byzantine behavior and corresponding punishment mechanism.
@@ -87,6 +87,7 @@ impl MintEntry {
             id: None,
             record: BlindAssetRecord::from_noah(&ba),
             lien: None,
+            lock: None,
         };
 
         MintEntry {
@@ -4,27 +4,130 @@
 use {
     crate::{
         data_model::{
-            ATxoSID, AssetTypeCode, AssetTypePrefix, DefineAsset, IssueAsset,
-            IssuerPublicKey, Operation, StateCommitmentData, Transaction, TxOutput,
-            TxnIDHash, TxnSID, TxoSID, XfrAddress,
+            ATxoSID, AssetTypeCode, AssetTypePrefix, DefineAsset, FinalizedTransaction,
+            IssueAsset, IssuerPublicKey, Operation, StateCommitmentData, Transaction,
+            TxOutput, TxnIDHash, TxnSID, TxoRef, TxoSID, XfrAddress,
         },
         staking::{
-            ops::mint_fra::MintEntry, Amount, BlockHeight, DelegationRwdDetail,
-            CHAN_D_AMOUNT_HIST, CHAN_GLOB_RATE_HIST, CHAN_V_SELF_D_HIST, KEEP_HIST,
+            ops::{
+                governance::{Data as GovernanceData, GovernanceOps},
+                mint_fra::MintEntry,
+            },
+            Amount, BlockHeight, DelegationRwdDetail, Staking, CHAN_D_AMOUNT_HIST,
+            CHAN_GLOB_RATE_HIST, CHAN_V_SELF_D_HIST, KEEP_HIST,
+        },
+        store::{
+            kv::{self, ApiCacheBackend, ApiMap, ApiMapx, ApiMapxnk, KvState, RocksMap},
+            LedgerState,
         },
-        store::LedgerState,
     },
     config::abci::global_cfg::CFG,
+    cryptohash::sha256,
     fbnc::{new_mapx, new_mapxnk, Mapx, Mapxnk},
     globutils::{wallet, HashOf},
+    lazy_static::lazy_static,
+    parking_lot::RwLock,
+    rayon::prelude::*,
     ruc::*,
+    schemars::JsonSchema,
     serde::{Deserialize, Serialize},
-    std::collections::HashSet,
-    zei::{noah_api::anon_xfr::structs::AxfrOwnerMemo, OwnerMemo, XfrPublicKey},
+    std::{
+        collections::{BTreeMap, BTreeSet, HashSet},
+        fs,
+        io::{ErrorKind, Write},
+        ops::DerefMut,
+        path::Path,
+        thread,
+    },
+    zei::{
+        noah_api::anon_xfr::structs::AxfrOwnerMemo, BlindAssetRecord, OwnerMemo,
+        XfrPublicKey,
+    },
 };
 
 type Issuances = Vec<(TxOutput, Option<OwnerMemo>)>;
 
+/// Length of the keys of the search prefix indexes
+pub const SEARCH_PREFIX_LEN: usize = 4;
+
+/// Blocks between two samples of `ApiCache::staking_signed_cnt_hist`
+pub const UPTIME_SAMPLE_ITV: BlockHeight = 100;
+
+/// Cumulative transfer statistics of an address
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+pub struct AddressStats {
+    /// height of the first txn related to the address
+    pub first_seen_height: BlockHeight,
+    /// number of txns related to the address
+    pub txn_count: u64,
+    /// base64 asset code => nonconfidential amount received, change included
+    pub received: BTreeMap<String, u64>,
+    /// base64 asset code => nonconfidential amount sent, change included
+    pub sent: BTreeMap<String, u64>,
+}
+
+/// A governance operation of the ledger, a penalty of a byzantine validator
+#[allow(missing_docs)]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct GovernanceProposal {
+    /// sequence number of the proposal, in the ledger order
+    pub id: u64,
+    pub txn_sid: TxnSID,
+    pub txn_hash: String,
+    pub height: BlockHeight,
+    /// the co-signers of the operation
+    pub proposers: Vec<XfrPublicKey>,
+    pub payload: GovernanceData,
+}
+
+/// Bits of an `AddressFilter` per address, for about 1% of false positives
+const FILTER_BITS_PER_ADDRESS: usize = 10;
+
+/// Bloom filter of the addresses involved in a block.
+///
+/// The `hash_count` positions of an address are `(h1 + i * h2) % bits.len() * 8`,
+/// with h1 and h2 the first two little-endian u64 of the sha256 of its key bytes,
+/// bit `n` being bit `n % 8` of byte `n / 8`.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct AddressFilter {
+    /// the filter bits, at least 8 bytes
+    pub bits: Vec<u8>,
+    /// number of bits set per address
+    pub hash_count: u32,
+}
+
+impl AddressFilter {
+    /// Filter of a set of addresses
+    pub fn new<'a>(addresses: impl ExactSizeIterator<Item = &'a XfrAddress>) -> Self {
+        let len = ((addresses.len() * FILTER_BITS_PER_ADDRESS + 7) / 8).max(8);
+        let mut filter = AddressFilter {
+            bits: vec![0; len],
+            hash_count: 7,
+        };
+        for address in addresses {
+            for n in filter.positions(address) {
+                filter.bits[n / 8] |= 1 << (n % 8);
+            }
+        }
+        filter
+    }
+
+    /// Whether the address may be involved, false positives are possible
+    pub fn contains(&self, address: &XfrAddress) -> bool {
+        self.positions(address)
+            .all(|n| 0 != self.bits[n / 8] & (1 << (n % 8)))
+    }
+
+    fn positions(&self, address: &XfrAddress) -> impl Iterator<Item = usize> {
+        let h = sha256::hash(&address.key.to_bytes()).0;
+        let h1 = u64::from_le_bytes(pnk!(h[..8].try_into()));
+        let h2 = u64::from_le_bytes(pnk!(h[8..16].try_into()));
+        let m = self.bits.len() as u64 * 8;
+        (0..self.hash_count as u64)
+            .map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % m) as usize)
+    }
+}
+
 /// Used in APIs
 #[derive(Clone, Deserialize, Serialize)]
 pub struct ApiCache {
@@ -33,6 +136,8 @@ pub struct ApiCache {
     pub related_transactions: Mapx<XfrAddress, Mapxnk<TxnSID, bool>>,
     /// Set of transfer transactions related to an asset code
     pub related_transfers: Mapx<AssetTypeCode, Mapxnk<TxnSID, bool>>,
+    /// txns containing an operation of a kind, see `Operation::kind`
+    pub txns_by_op_type: Mapx<String, Mapxnk<TxnSID, bool>>,
     /// List of claim transactions related to a ledger address
     pub claim_hist_txns: Mapx<XfrAddress, Mapxnk<TxnSID, bool>>,
     /// Payments from coinbase
@@ -44,21 +149,39 @@ pub struct ApiCache {
     /// issuance mapped by token code
     pub token_code_issuances: Mapx<AssetTypeCode, Issuances>,
     /// used in confidential tx
-    pub owner_memos: Mapxnk<TxoSID, OwnerMemo>,
+    pub owner_memos: ApiMapxnk<TxoSID, OwnerMemo>,
     /// used in anonymous tx
     pub abar_memos: Mapx<ATxoSID, AxfrOwnerMemo>,
     /// ownship of txo
-    pub utxos_to_map_index: Mapxnk<TxoSID, XfrAddress>,
+    pub utxos_to_map_index: ApiMapxnk<TxoSID, XfrAddress>,
     /// txo(spent, unspent) to authenticated txn (sid, hash)
-    pub txo_to_txnid: Mapxnk<TxoSID, TxnIDHash>,
+    pub txo_to_txnid: ApiMapxnk<TxoSID, TxnIDHash>,
     /// atxo to authenticated txn (sid, hash)
-    pub atxo_to_txnid: Mapx<ATxoSID, TxnIDHash>,
+    pub atxo_to_txnid: ApiMapx<ATxoSID, TxnIDHash>,
     /// txn sid to txn hash
-    pub txn_sid_to_hash: Mapxnk<TxnSID, String>,
+    pub txn_sid_to_hash: ApiMapxnk<TxnSID, String>,
     /// txn hash to txn sid
-    pub txn_hash_to_sid: Mapx<String, TxnSID>,
+    pub txn_hash_to_sid: ApiMapx<String, TxnSID>,
+    /// txn sid to the height of the block containing it
+    pub txn_sid_to_height: ApiMapxnk<TxnSID, BlockHeight>,
+    /// block height to block time, in seconds
+    pub height_to_time: Mapxnk<BlockHeight, i64>,
+    /// cumulative transfer statistics of addresses
+    pub address_stats: Mapx<XfrAddress, AddressStats>,
+    /// owners of the utxos of a nonconfidential asset type
+    pub asset_holders: Mapx<AssetTypeCode, HashSet<XfrAddress>>,
+    /// spent txo to the (sid, height) of the txn spending it
+    pub spent_utxos: ApiMapxnk<TxoSID, (TxnSID, BlockHeight)>,
+    /// ledger block index to the filter of the addresses it involves
+    pub address_filters: Mapxnk<u64, AddressFilter>,
+    /// first `SEARCH_PREFIX_LEN` chars of txn hashes to the full hashes
+    pub txn_hash_prefixes: Mapx<String, Vec<String>>,
+    /// first `SEARCH_PREFIX_LEN` chars of base64 asset codes to the full codes
+    pub asset_code_prefixes: Mapx<String, Vec<String>>,
     /// max (latest) atxo sid at block height
     pub height_to_max_atxo: Mapxnk<BlockHeight, Option<usize>>,
+    /// block height to the next sids after it, see `rollback_to_height`
+    pub height_to_next_sids: Mapxnk<BlockHeight, NextSids>,
     /// global rate history
     pub staking_global_rate_hist: Mapxnk<BlockHeight, [u128; 2]>,
     /// - self-delegation amount history
@@ -71,11 +194,54 @@ pub struct ApiCache {
     /// rewards history, used on some pulic nodes, such as fullnode
     pub staking_delegation_rwd_hist:
         Mapx<XfrPublicKey, Mapxnk<BlockHeight, DelegationRwdDetail>>,
+    /// - number of blocks signed by a validator, see `Validator::signed_cnt`
+    /// - sampled every `UPTIME_SAMPLE_ITV` blocks, while in the validator set
+    pub staking_signed_cnt_hist: Mapx<XfrPublicKey, Mapxnk<BlockHeight, u64>>,
+    /// governance proposals by id
+    pub governance_proposals: Mapxnk<u64, GovernanceProposal>,
     /// there are no transactions lost before last_sid
     pub last_sid: Mapx<String, u64>,
     /// State commitment history.
     /// The BitDigest at index i is the state commitment of the ledger at block height  i + 1.
     pub state_commitment_version: Option<HashOf<Option<StateCommitmentData>>>,
+    /// file of the `WalEntry` of the block being indexed, if logged
+    #[serde(skip)]
+    pub(crate) wal_path: Option<String>,
+}
+
+/// A block being indexed into the api cache, logged before the indexing
+/// and cleared once the cache is flushed, so that an interrupted indexing
+/// is replayed on the next startup
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct WalEntry {
+    /// index of the block in the ledger
+    pub block_idx: usize,
+    pub height: BlockHeight,
+    pub time: i64,
+}
+
+/// Sids of the ledger after the block at a height, the entries indexed
+/// above that height are the ones with greater or equal sids
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct NextSids {
+    pub block_count: u64,
+    pub txn: TxnSID,
+    pub txo: TxoSID,
+    pub atxo: ATxoSID,
+}
+
+impl NextSids {
+    /// Next sids of the ledger as is
+    pub fn of_ledger(ledger: &LedgerState) -> Self {
+        NextSids {
+            block_count: ledger.get_block_count() as u64,
+            txn: ledger.get_next_txn(),
+            txo: ledger.get_next_txo(),
+            atxo: ledger.status.next_atxo,
+        }
+    }
 }
 
 impl ApiCache {
@@ -88,6 +254,7 @@ impl ApiCache {
             related_transfers: new_mapx!(
                 format!("api_cache/{prefix}related_transfers",)
             ),
+            txns_by_op_type: new_mapx!(format!("api_cache/{prefix}txns_by_op_type",)),
             claim_hist_txns: new_mapx!(format!("api_cache/{prefix}claim_hist_txns",)),
             coinbase_oper_hist: new_mapx!(format!(
                 "api_cache/{prefix}coinbase_oper_hist",
@@ -97,21 +264,50 @@ impl ApiCache {
             token_code_issuances: new_mapx!(format!(
                 "api_cache/{prefix}token_code_issuances",
             )),
-            owner_memos: new_mapxnk!(format!("api_cache/{prefix}owner_memos",)),
+            owner_memos: ApiMap::Fbnc(new_mapxnk!(format!(
+                "api_cache/{prefix}owner_memos",
+            ))),
             abar_memos: new_mapx!(format!("api_cache/{prefix}abar_memos",)),
-            utxos_to_map_index: new_mapxnk!(format!(
+            utxos_to_map_index: ApiMap::Fbnc(new_mapxnk!(format!(
                 "api_cache/{prefix}utxos_to_map_index",
+            ))),
+            txo_to_txnid: ApiMap::Fbnc(new_mapxnk!(format!(
+                "api_cache/{prefix}txo_to_txnid",
+            ))),
+            atxo_to_txnid: ApiMap::Fbnc(new_mapx!(format!(
+                "api_cache/{prefix}atxo_to_txnid",
+            ))),
+            txn_sid_to_hash: ApiMap::Fbnc(new_mapxnk!(format!(
+                "api_cache/{prefix}txn_sid_to_hash",
+            ))),
+            txn_hash_to_sid: ApiMap::Fbnc(new_mapx!(format!(
+                "api_cache/{prefix}txn_hash_to_sid",
+            ))),
+            txn_sid_to_height: ApiMap::Fbnc(new_mapxnk!(format!(
+                "api_cache/{prefix}txn_sid_to_height",
+            ))),
+            height_to_time: new_mapxnk!(format!("api_cache/{prefix}height_to_time",)),
+            address_stats: new_mapx!(format!("api_cache/{prefix}address_stats",)),
+            asset_holders: new_mapx!(format!("api_cache/{prefix}asset_holders",)),
+            spent_utxos: ApiMap::Fbnc(new_mapxnk!(format!(
+                "api_cache/{prefix}spent_utxos",
+            ))),
+            address_filters: new_mapxnk!(format!("api_cache/{prefix}address_filters",)),
+            txn_hash_prefixes: new_mapx!(
+                format!("api_cache/{prefix}txn_hash_prefixes",)
+            ),
+            asset_code_prefixes: new_mapx!(format!(
+                "api_cache/{prefix}asset_code_prefixes",
             )),
-            txo_to_txnid: new_mapxnk!(format!("api_cache/{prefix}txo_to_txnid",)),
-            atxo_to_txnid: new_mapx!(format!("api_cache/{prefix}atxo_to_txnid",)),
-            txn_sid_to_hash: new_mapxnk!(format!("api_cache/{prefix}txn_sid_to_hash",)),
-            txn_hash_to_sid: new_mapx!(format!("api_cache/{prefix}txn_hash_to_sid",)),
             staking_global_rate_hist: new_mapxnk!(format!(
                 "api_cache/{prefix}staking_global_rate_hist",
             )),
             height_to_max_atxo: new_mapxnk!(format!(
                 "api_cache/{prefix}height_to_max_atxo",
             )),
+            height_to_next_sids: new_mapxnk!(format!(
+                "api_cache/{prefix}height_to_next_sids",
+            )),
             staking_self_delegation_hist: new_mapx!(format!(
                 "api_cache/{prefix}staking_self_delegation_hist",
             )),
@@ -121,9 +317,103 @@ impl ApiCache {
             staking_delegation_rwd_hist: new_mapx!(format!(
                 "api_cache/{prefix}staking_delegation_rwd_hist",
             )),
+            staking_signed_cnt_hist: new_mapx!(format!(
+                "api_cache/{prefix}staking_signed_cnt_hist",
+            )),
+            governance_proposals: new_mapxnk!(format!(
+                "api_cache/{prefix}governance_proposals",
+            )),
             last_sid: new_mapx!(format!("api_cache/{prefix}last_sid",)),
             state_commitment_version: None,
+            wal_path: None,
+        }
+    }
+
+    /// Keep the flat txo and txn maps in a RocksDB at `path` if `backend` says so,
+    /// the other maps stay in fbnc: the nested ones, and the ones relying on
+    /// fbnc specific operations or small enough not to matter
+    pub(crate) fn with_backend(
+        mut self,
+        backend: ApiCacheBackend,
+        path: &str,
+    ) -> Result<Self> {
+        if ApiCacheBackend::Fbnc == backend {
+            return Ok(self);
+        }
+
+        let state = kv::open_kv_state(path).c(d!())?;
+        let prefix = self.prefix.clone();
+        macro_rules! rocks {
+            ($field: ident) => {
+                self.$field = ApiMap::RocksDB(RocksMap::new(
+                    state.clone(),
+                    path,
+                    &format!("{}{}", prefix, stringify!($field)),
+                ));
+            };
+        }
+        rocks!(owner_memos);
+        rocks!(utxos_to_map_index);
+        rocks!(txo_to_txnid);
+        rocks!(atxo_to_txnid);
+        rocks!(txn_sid_to_hash);
+        rocks!(txn_hash_to_sid);
+        rocks!(txn_sid_to_height);
+        rocks!(spent_utxos);
+
+        Ok(self)
+    }
+
+    /// State of the maps kept in RocksDB, see `with_backend`
+    pub(crate) fn kv_state(&self) -> Option<&KvState> {
+        self.owner_memos.kv_state()
+    }
+
+    /// Log the indexing of a block into `path`, see `WalEntry`
+    pub(crate) fn with_wal(mut self, path: String) -> Self {
+        self.wal_path = Some(path);
+        self
+    }
+
+    /// Entry of the block whose indexing was interrupted, if any
+    pub fn pending_wal_entry(&self) -> Result<Option<WalEntry>> {
+        let path = match self.wal_path.as_ref() {
+            Some(p) => p,
+            None => return Ok(None),
+        };
+        match fs::read(path) {
+            Ok(b) => serde_json::from_slice(&b).c(d!()).map(Some),
+            Err(e) if ErrorKind::NotFound == e.kind() => Ok(None),
+            Err(e) => Err(eg!(e)),
+        }
+    }
+
+    /// Durably log an entry, replacing the previous one
+    pub(crate) fn log_wal_entry(&self, entry: &WalEntry) -> Result<()> {
+        if let Some(path) = self.wal_path.as_ref() {
+            let tmp = format!("{path}.tmp");
+            let mut f = fs::File::create(&tmp).c(d!())?;
+            f.write_all(&serde_json::to_vec(entry).c(d!())?).c(d!())?;
+            f.sync_all().c(d!())?;
+            fs::rename(&tmp, path).c(d!())?;
+        }
+        Ok(())
+    }
+
+    /// Flush the cache to disk, then drop the logged entry
+    pub(crate) fn clear_wal(&self) -> Result<()> {
+        if let Some(state) = self.kv_state() {
+            kv::commit_kv_state(state).c(d!())?;
+        }
+        if let Some(path) = self.wal_path.as_ref() {
+            fbnc::flush_data();
+            if let Err(e) = fs::remove_file(path) {
+                if ErrorKind::NotFound != e.kind() {
+                    return Err(eg!(e));
+                }
+            }
         }
+        Ok(())
     }
 
     /// Add created asset
@@ -138,6 +428,8 @@ impl ApiCache {
             cur_height,
         );
 
+        Self::add_search_prefix(&mut self.asset_code_prefixes, code.to_base64());
+
         let prefix = self.prefix.clone();
         let issuer = creation.pubkey;
         let mut tmp = creation.clone();
@@ -154,6 +446,235 @@ impl ApiCache {
             .insert(code, tmp);
     }
 
+    /// Index `key` under its first `SEARCH_PREFIX_LEN` chars
+    pub fn add_search_prefix(index: &mut Mapx<String, Vec<String>>, key: String) {
+        if key.len() < SEARCH_PREFIX_LEN {
+            return;
+        }
+        #[allow(unused_mut)]
+        let mut keys = index
+            .entry(key[..SEARCH_PREFIX_LEN].to_owned())
+            .or_insert_with(Vec::new);
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+
+    /// Account a new txn in the statistics of its related addresses,
+    /// must be called once per txn
+    pub fn add_address_stats(&mut self, txn: &Transaction, height: BlockHeight) {
+        let flows = get_record_flows(txn);
+
+        for address in get_related_addresses(txn, |_| {}) {
+            #[allow(unused_mut)]
+            let mut stats =
+                self.address_stats
+                    .entry(address)
+                    .or_insert_with(|| AddressStats {
+                        first_seen_height: height,
+                        ..Default::default()
+                    });
+            stats.txn_count += 1;
+        }
+
+        for (r, received) in flows {
+            let (asset_type, amount) =
+                match (r.asset_type.get_asset_type(), r.amount.get_amount()) {
+                    (Some(t), Some(a)) => (t, a),
+                    _ => continue,
+                };
+            let code = AssetTypeCode { val: asset_type }.to_base64();
+            #[allow(unused_mut)]
+            let mut stats = self
+                .address_stats
+                .entry(XfrAddress { key: r.public_key })
+                .or_insert_with(|| AddressStats {
+                    first_seen_height: height,
+                    ..Default::default()
+                });
+            let total = if received {
+                stats.received.entry(code).or_insert(0)
+            } else {
+                stats.sent.entry(code).or_insert(0)
+            };
+            *total = total.saturating_add(amount);
+        }
+    }
+
+    /// Update the holders of the nonconfidential assets received and sent
+    /// in a block, senders are dropped once they own no utxo of the asset
+    pub fn update_asset_holders(
+        &mut self,
+        ledger: &LedgerState,
+        received: Vec<(AssetTypeCode, XfrAddress)>,
+        sent: Vec<(AssetTypeCode, XfrAddress)>,
+    ) -> Result<()> {
+        for (code, holder) in received {
+            #[allow(unused_mut)]
+            let mut holders =
+                self.asset_holders.entry(code).or_insert_with(HashSet::new);
+            holders.insert(holder);
+        }
+
+        for (code, sender) in sent.into_iter().collect::<HashSet<_>>() {
+            let holds = ledger.get_owned_utxos(&sender.key).c(d!())?.values().any(
+                |(utxo, _)| utxo.0.record.asset_type.get_asset_type() == Some(code.val),
+            );
+            if !holds {
+                if let Some(mut holders) = self.asset_holders.get_mut(&code) {
+                    holders.deref_mut().remove(&sender);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Record the txos consumed by a txn as spent by it
+    pub fn add_spent_utxos(
+        &mut self,
+        txn: &Transaction,
+        txn_sid: TxnSID,
+        height: BlockHeight,
+    ) {
+        for sid in get_spent_txos(txn) {
+            self.spent_utxos.insert(sid, (txn_sid, height));
+        }
+    }
+
+    /// Drop the owner memo and the indexes of a spent txo,
+    /// returns whether anything was dropped
+    pub fn prune_txo(&mut self, sid: TxoSID) -> bool {
+        let memo = self.owner_memos.remove(&sid).is_some();
+        let owner = self.utxos_to_map_index.remove(&sid).is_some();
+        let txn = self.txo_to_txnid.remove(&sid).is_some();
+        memo || owner || txn
+    }
+
+    /// Drop the coinbase history below `min_height`,
+    /// returns the number of dropped entries
+    pub fn prune_coinbase_hist(&mut self, min_height: BlockHeight) -> u64 {
+        let mut n = 0;
+        for (_, mut hist) in self.coinbase_oper_hist.iter() {
+            let old = hist
+                .iter()
+                .map(|(h, _)| h)
+                .filter(|h| *h < min_height)
+                .collect::<Vec<_>>();
+            for h in old {
+                hist.remove(&h);
+                n += 1;
+            }
+        }
+        n
+    }
+
+    /// Drop the entries indexed above `height`, so that the txns rolled back
+    /// by consensus are not served, returns the number of dropped entries.
+    ///
+    /// The cumulative indexes (address stats, asset holders, created assets,
+    /// issuances and asset code prefixes) are not tagged and left as is.
+    pub fn rollback_to_height(&mut self, height: BlockHeight) -> Result<u64> {
+        let next = self
+            .height_to_next_sids
+            .get(&height)
+            .c(d!(format!("height {} is not indexed", height)))?;
+        Ok(self.rollback_to(height, next))
+    }
+
+    pub(crate) fn rollback_to(&mut self, height: BlockHeight, next: NextSids) -> u64 {
+        // drop the entries of a map matching a condition, returns their number
+        macro_rules! drop_where {
+            ($m: expr, |$k: pat_param, $v: pat_param| $cond: expr) => {{
+                let keys = $m
+                    .iter()
+                    .filter(|($k, $v)| $cond)
+                    .map(|(k, _)| k)
+                    .collect::<Vec<_>>();
+                for k in keys.iter() {
+                    $m.remove(k);
+                }
+                keys.len() as u64
+            }};
+        }
+        macro_rules! drop_nested_where {
+            ($m: expr, |$k: pat_param, $v: pat_param| $cond: expr) => {{
+                let mut n = 0;
+                for (_, mut sub) in $m.iter() {
+                    n += drop_where!(sub, |$k, $v| $cond);
+                }
+                n
+            }};
+        }
+
+        let hashes = self
+            .txn_sid_to_hash
+            .iter()
+            .filter(|(sid, _)| *sid >= next.txn)
+            .map(|(_, hash)| hash)
+            .collect::<Vec<_>>();
+        for hash in hashes {
+            self.txn_hash_to_sid.remove(&hash);
+            let prefix = hash.get(..SEARCH_PREFIX_LEN).unwrap_or_default().to_owned();
+            if let Some(mut keys) = self.txn_hash_prefixes.get(&prefix) {
+                keys.retain(|k| *k != hash);
+                if keys.is_empty() {
+                    self.txn_hash_prefixes.remove(&prefix);
+                } else {
+                    self.txn_hash_prefixes.insert(prefix, keys);
+                }
+            }
+        }
+
+        let mut n = drop_where!(self.txn_sid_to_hash, |sid, _| *sid >= next.txn);
+        n += drop_where!(self.txn_hash_to_sid, |_, sid| *sid >= next.txn);
+        n += drop_where!(self.txn_sid_to_height, |sid, _| *sid >= next.txn);
+        n += drop_nested_where!(self.related_transactions, |sid, _| *sid >= next.txn);
+        n += drop_nested_where!(self.related_transfers, |sid, _| *sid >= next.txn);
+        n += drop_nested_where!(self.txns_by_op_type, |sid, _| *sid >= next.txn);
+        n += drop_nested_where!(self.claim_hist_txns, |sid, _| *sid >= next.txn);
+
+        n += drop_where!(self.owner_memos, |sid, _| *sid >= next.txo);
+        n += drop_where!(self.utxos_to_map_index, |sid, _| *sid >= next.txo);
+        n += drop_where!(self.txo_to_txnid, |sid, _| *sid >= next.txo);
+        // the txos spent by the dropped txns are unspent again
+        n += drop_where!(self.spent_utxos, |sid, (txn, _)| *sid >= next.txo
+            || *txn >= next.txn);
+        n += drop_where!(self.abar_memos, |sid, _| *sid >= next.atxo);
+        n += drop_where!(self.atxo_to_txnid, |sid, _| *sid >= next.atxo);
+        n += drop_where!(self.address_filters, |idx, _| *idx >= next.block_count);
+
+        n += drop_where!(self.height_to_time, |h, _| *h > height);
+        n += drop_where!(self.height_to_max_atxo, |h, _| *h > height);
+        n += drop_where!(self.height_to_next_sids, |h, _| *h > height);
+        n += drop_nested_where!(self.coinbase_oper_hist, |h, _| *h > height);
+        n += drop_where!(self.staking_global_rate_hist, |h, _| *h > height);
+        n += drop_nested_where!(self.staking_self_delegation_hist, |h, _| *h > height);
+        n += drop_nested_where!(self.staking_delegation_amount_hist, |h, _| *h > height);
+        n += drop_nested_where!(self.staking_delegation_rwd_hist, |h, _| *h > height);
+        n += drop_nested_where!(self.staking_signed_cnt_hist, |h, _| *h > height);
+        // the ids stay contiguous, the dropped proposals being the last ones
+        n += drop_where!(self.governance_proposals, |_, p| p.txn_sid >= next.txn);
+
+        // so that `check_lost_data` checks the sids again from there
+        for (key, next_sid) in [
+            ("last_txn_sid", next.txn.0 as u64),
+            ("last_txo_sid", next.txo.0),
+        ] {
+            let key = key.to_owned();
+            match (self.last_sid.get(&key), next_sid.checked_sub(1)) {
+                (Some(last), Some(max)) if max < last => {
+                    self.last_sid.insert(key, max);
+                }
+                (Some(_), None) => {
+                    self.last_sid.remove(&key);
+                }
+                _ => {}
+            }
+        }
+
+        n
+    }
+
     /// Cache issuance records
     pub fn cache_issuance(&mut self, issuance: &IssueAsset) {
         let new_records = issuance.body.records.to_vec();
@@ -175,10 +696,61 @@ impl ApiCache {
         save_issuance!(token_issuances, token_code);
     }
 
+    /// Sample the signed block counts of the current validators
+    pub fn add_signed_cnt_samples(&mut self, staking: &Staking, height: BlockHeight) {
+        let vd = match staking.validator_get_current() {
+            Some(vd) => vd,
+            None => return,
+        };
+        let prefix = self.prefix.clone();
+        for v in vd.body.values() {
+            self.staking_signed_cnt_hist
+                .entry(v.id)
+                .or_insert_with(|| {
+                    new_mapxnk!(format!(
+                        "api_cache/{}staking_signed_cnt_hist/{}",
+                        prefix,
+                        wallet::public_key_to_base64(&v.id)
+                    ))
+                })
+                .insert(height, v.signed_cnt);
+        }
+    }
+
+    /// Index a governance operation as the next proposal
+    pub fn add_governance_proposal(
+        &mut self,
+        op: &GovernanceOps,
+        txn_sid: TxnSID,
+        txn_hash: String,
+        height: BlockHeight,
+    ) {
+        let id = self.governance_proposals.len() as u64;
+        self.governance_proposals.insert(
+            id,
+            GovernanceProposal {
+                id,
+                txn_sid,
+                txn_hash,
+                height,
+                proposers: op.cosigs.keys().copied().collect(),
+                payload: op.data.clone(),
+            },
+        );
+    }
+
     /// Cache history style data
     ///
     /// Note: This function's data will migrate to findora scanner.
     pub fn cache_hist_data(&mut self) {
+        // the channels are drained anyway
+        if !API_CACHE_INDEXES.staking_hist {
+            CHAN_GLOB_RATE_HIST.1.lock().try_iter().for_each(drop);
+            CHAN_V_SELF_D_HIST.1.lock().try_iter().for_each(drop);
+            CHAN_D_AMOUNT_HIST.1.lock().try_iter().for_each(drop);
+            return;
+        }
+
         CHAN_GLOB_RATE_HIST.1.lock().try_iter().for_each(|(h, r)| {
             self.staking_global_rate_hist.insert(h, r);
         });
@@ -343,149 +915,810 @@ pub fn get_transferred_nonconfidential_assets(
     transferred_assets
 }
 
-/// check the lost data
-pub fn check_lost_data(ledger: &mut LedgerState) -> Result<()> {
-    // check the lost txn sids
-    let cur_txn_sid = ledger.get_next_txn().0;
-    let api_cache_opt = ledger.api_cache.as_mut();
-    let mut last_txn_sid: usize = 0;
-    if let Some(api_cache) = api_cache_opt {
-        if let Some(sid) = api_cache.last_sid.get(&"last_txn_sid".to_string()) {
-            last_txn_sid = sid as usize;
-        };
-    } else {
-        return Ok(());
+/// Records moved by a txn, `true` for the received ones,
+/// the inputs of transfers are the sent ones
+pub fn get_record_flows(txn: &Transaction) -> Vec<(&BlindAssetRecord, bool)> {
+    let mut flows = vec![];
+    for op in txn.body.operations.iter() {
+        match op {
+            Operation::TransferAsset(i) => {
+                let xfr = &i.body.transfer;
+                flows.extend(xfr.inputs.iter().map(|r| (r, false)));
+                flows.extend(xfr.outputs.iter().map(|r| (r, true)));
+            }
+            Operation::IssueAsset(i) => {
+                flows.extend(i.body.records.iter().map(|(o, _)| (&o.record, true)));
+            }
+            Operation::MintFra(i) => {
+                flows.extend(i.entries.iter().map(|me| (&me.utxo.record, true)));
+            }
+            _ => {}
+        }
     }
+    flows
+}
 
-    if last_txn_sid < cur_txn_sid {
-        for index in last_txn_sid..cur_txn_sid {
-            if !ledger
-                .api_cache
-                .as_mut()
-                .unwrap()
-                .txn_sid_to_hash
-                .contains_key(&TxnSID(index))
-            {
-                let ftx = ledger.get_transaction_light(TxnSID(index)).c(d!())?;
-                let hash = ftx.txn.hash_tm().hex().to_uppercase();
-
-                ledger
-                    .api_cache
-                    .as_mut()
-                    .unwrap()
-                    .txn_sid_to_hash
-                    .insert(TxnSID(index), hash.clone());
-
-                ledger
-                    .api_cache
-                    .as_mut()
-                    .unwrap()
-                    .txn_hash_to_sid
-                    .insert(hash.clone(), TxnSID(index));
+/// Ledger txos consumed by a txn
+pub fn get_spent_txos(txn: &Transaction) -> Vec<TxoSID> {
+    let mut spent = vec![];
+    for op in txn.body.operations.iter() {
+        match op {
+            Operation::TransferAsset(i) => {
+                spent.extend(i.body.inputs.iter().filter_map(|r| match r {
+                    TxoRef::Absolute(sid) => Some(*sid),
+                    // outputs of the same txn, never in the ledger
+                    TxoRef::Relative(_) => None,
+                }))
             }
+            Operation::BarToAbar(i) => spent.push(i.txo_sid),
+            _ => {}
+        }
+    }
+    spent
+}
 
-            // update the last txn sid
-            ledger
-                .api_cache
-                .as_mut()
-                .unwrap()
-                .last_sid
-                .insert("last_txn_sid".to_string(), index as u64);
+/// Interval in block heights of the automatic prune of the coinbase history
+pub const COINBASE_PRUNE_ITV: BlockHeight = 100;
+
+/// Retention policy of the api cache, everything is kept by default
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct PruningPolicy {
+    /// drop the owner memos and txo indexes of spent txos
+    #[serde(default)]
+    pub prune_spent_txos: bool,
+    /// keep the coinbase history of the last N block heights only
+    #[serde(default)]
+    pub coinbase_hist_blocks: Option<u64>,
+}
+
+impl PruningPolicy {
+    /// Policy set by `--api-cache-prune-spent`
+    /// and `--api-cache-coinbase-hist-blocks`
+    pub fn from_cfg() -> Self {
+        PruningPolicy {
+            prune_spent_txos: CFG.api_cache_prune_spent,
+            coinbase_hist_blocks: CFG.api_cache_coinbase_hist_blocks,
         }
     }
+}
 
-    // check the lost memos
-    let cur_txo_sid = ledger.get_next_txo().0;
-    let last_txo_sid_opt = ledger
+/// Names of the `ApiCacheIndexes`, as accepted by `--api-cache-indexes`
+pub const API_CACHE_INDEX_NAMES: [&str; 6] = [
+    "owner_memos",
+    "coinbase_hist",
+    "issuances",
+    "txn_hashes",
+    "explorer",
+    "staking_hist",
+];
+
+/// Groups of api cache indexes kept when `KEEP_HIST` is set, all by default,
+/// the txo ownership and the heights and times of txns are always kept
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ApiCacheIndexes {
+    /// owner memos of txos and abars, needed by wallets
+    pub owner_memos: bool,
+    /// coinbase and claim histories of addresses
+    pub coinbase_hist: bool,
+    /// created assets and their issuances
+    pub issuances: bool,
+    /// txn sid <=> hash maps and the creating txn of txos
+    pub txn_hashes: bool,
+    /// related txns and transfers, txns by operation type, address stats,
+    /// asset holders, spent utxos and address filters
+    pub explorer: bool,
+    /// staking rates, delegation amounts, rewards and signed blocks by height,
+    /// and governance proposals
+    pub staking_hist: bool,
+}
+
+impl Default for ApiCacheIndexes {
+    fn default() -> Self {
+        ApiCacheIndexes {
+            owner_memos: true,
+            coinbase_hist: true,
+            issuances: true,
+            txn_hashes: true,
+            explorer: true,
+            staking_hist: true,
+        }
+    }
+}
+
+impl ApiCacheIndexes {
+    /// Parse a ',' separated list of `API_CACHE_INDEX_NAMES`, or "all"
+    pub fn parse(names: &str) -> Result<Self> {
+        let names = names
+            .split(',')
+            .map(|n| n.trim())
+            .filter(|n| !n.is_empty())
+            .collect::<HashSet<_>>();
+        if names.contains("all") {
+            return Ok(Self::default());
+        }
+        if let Some(n) = names.iter().find(|n| !API_CACHE_INDEX_NAMES.contains(n)) {
+            return Err(eg!(format!(
+                "unknown api cache index: {}, expected: all, {}",
+                n,
+                API_CACHE_INDEX_NAMES.join(", ")
+            )));
+        }
+        Ok(ApiCacheIndexes {
+            owner_memos: names.contains("owner_memos"),
+            coinbase_hist: names.contains("coinbase_hist"),
+            issuances: names.contains("issuances"),
+            txn_hashes: names.contains("txn_hashes"),
+            explorer: names.contains("explorer"),
+            staking_hist: names.contains("staking_hist"),
+        })
+    }
+
+    /// Indexes set by `--api-cache-indexes`
+    pub fn from_cfg() -> Result<Self> {
+        CFG.api_cache_indexes
+            .as_deref()
+            .map_or(Ok(Self::default()), Self::parse)
+    }
+}
+
+lazy_static! {
+    /// the api cache indexes kept by this node
+    pub static ref API_CACHE_INDEXES: ApiCacheIndexes =
+        pnk!(ApiCacheIndexes::from_cfg());
+}
+
+/// Number of entries dropped by `prune_api_cache`
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct PruneStats {
+    pub spent_txos: u64,
+    pub coinbase_entries: u64,
+}
+
+/// Enforce a retention policy on the whole api cache
+pub fn prune_api_cache(
+    ledger: &mut LedgerState,
+    policy: &PruningPolicy,
+) -> Result<PruneStats> {
+    let mut api_cache = ledger.api_cache.take().c(d!("api cache is disabled"))?;
+    let mut stats = PruneStats::default();
+
+    if policy.prune_spent_txos {
+        let spent = api_cache
+            .utxos_to_map_index
+            .iter()
+            .map(|(sid, _)| sid)
+            .chain(api_cache.owner_memos.iter().map(|(sid, _)| sid))
+            .filter(|sid| ledger.get_utxo_light(*sid).is_none())
+            .collect::<HashSet<_>>();
+        for sid in spent {
+            if api_cache.prune_txo(sid) {
+                stats.spent_txos += 1;
+            }
+        }
+    }
+
+    if let Some(n) = policy.coinbase_hist_blocks {
+        let min_height = ledger.status.td_commit_height.saturating_sub(n);
+        stats.coinbase_entries = api_cache.prune_coinbase_hist(min_height);
+    }
+
+    ledger.api_cache = Some(api_cache);
+    Ok(stats)
+}
+
+/// Version of the `ApiCacheSnapshot` format
+pub const SNAPSHOT_VERSION: u32 = 8;
+
+/// Plain copy of all the indexes of an `ApiCache`,
+/// used to bootstrap a query server without replaying the whole ledger
+#[allow(missing_docs)]
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct ApiCacheSnapshot {
+    pub version: u32,
+    /// ledger block count when the snapshot was taken
+    pub block_count: usize,
+    pub related_transactions: Vec<(XfrAddress, Vec<(TxnSID, bool)>)>,
+    pub related_transfers: Vec<(AssetTypeCode, Vec<(TxnSID, bool)>)>,
+    pub txns_by_op_type: Vec<(String, Vec<(TxnSID, bool)>)>,
+    pub claim_hist_txns: Vec<(XfrAddress, Vec<(TxnSID, bool)>)>,
+    pub coinbase_oper_hist: Vec<(XfrAddress, Vec<(BlockHeight, MintEntry)>)>,
+    pub created_assets: Vec<(IssuerPublicKey, Vec<(AssetTypeCode, DefineAsset)>)>,
+    pub issuances: Vec<(IssuerPublicKey, Issuances)>,
+    pub token_code_issuances: Vec<(AssetTypeCode, Issuances)>,
+    pub owner_memos: Vec<(TxoSID, OwnerMemo)>,
+    pub abar_memos: Vec<(ATxoSID, AxfrOwnerMemo)>,
+    pub utxos_to_map_index: Vec<(TxoSID, XfrAddress)>,
+    pub txo_to_txnid: Vec<(TxoSID, TxnIDHash)>,
+    pub atxo_to_txnid: Vec<(ATxoSID, TxnIDHash)>,
+    pub txn_sid_to_hash: Vec<(TxnSID, String)>,
+    pub txn_hash_to_sid: Vec<(String, TxnSID)>,
+    pub txn_sid_to_height: Vec<(TxnSID, BlockHeight)>,
+    pub height_to_time: Vec<(BlockHeight, i64)>,
+    pub address_stats: Vec<(XfrAddress, AddressStats)>,
+    pub asset_holders: Vec<(AssetTypeCode, HashSet<XfrAddress>)>,
+    pub spent_utxos: Vec<(TxoSID, (TxnSID, BlockHeight))>,
+    pub address_filters: Vec<(u64, AddressFilter)>,
+    pub txn_hash_prefixes: Vec<(String, Vec<String>)>,
+    pub asset_code_prefixes: Vec<(String, Vec<String>)>,
+    pub height_to_max_atxo: Vec<(BlockHeight, Option<usize>)>,
+    pub height_to_next_sids: Vec<(BlockHeight, NextSids)>,
+    pub staking_global_rate_hist: Vec<(BlockHeight, [u128; 2])>,
+    pub staking_self_delegation_hist: Vec<(XfrPublicKey, Vec<(BlockHeight, Amount)>)>,
+    pub staking_delegation_amount_hist: Vec<(XfrPublicKey, Vec<(BlockHeight, Amount)>)>,
+    pub staking_delegation_rwd_hist:
+        Vec<(XfrPublicKey, Vec<(BlockHeight, DelegationRwdDetail)>)>,
+    pub staking_signed_cnt_hist: Vec<(XfrPublicKey, Vec<(BlockHeight, u64)>)>,
+    pub governance_proposals: Vec<(u64, GovernanceProposal)>,
+    pub last_sid: Vec<(String, u64)>,
+    pub state_commitment_version: Option<HashOf<Option<StateCommitmentData>>>,
+}
+
+impl ApiCache {
+    /// Copy all the indexes out of the fbnc maps
+    pub fn export_snapshot(&self, block_count: usize) -> ApiCacheSnapshot {
+        macro_rules! flat {
+            ($m: expr) => {
+                $m.iter().collect()
+            };
+        }
+        macro_rules! nested {
+            ($m: expr) => {
+                $m.iter().map(|(k, v)| (k, v.iter().collect())).collect()
+            };
+        }
+
+        ApiCacheSnapshot {
+            version: SNAPSHOT_VERSION,
+            block_count,
+            related_transactions: nested!(self.related_transactions),
+            related_transfers: nested!(self.related_transfers),
+            txns_by_op_type: nested!(self.txns_by_op_type),
+            claim_hist_txns: nested!(self.claim_hist_txns),
+            coinbase_oper_hist: nested!(self.coinbase_oper_hist),
+            created_assets: nested!(self.created_assets),
+            issuances: flat!(self.issuances),
+            token_code_issuances: flat!(self.token_code_issuances),
+            owner_memos: flat!(self.owner_memos),
+            abar_memos: flat!(self.abar_memos),
+            utxos_to_map_index: flat!(self.utxos_to_map_index),
+            txo_to_txnid: flat!(self.txo_to_txnid),
+            atxo_to_txnid: flat!(self.atxo_to_txnid),
+            txn_sid_to_hash: flat!(self.txn_sid_to_hash),
+            txn_hash_to_sid: flat!(self.txn_hash_to_sid),
+            txn_sid_to_height: flat!(self.txn_sid_to_height),
+            height_to_time: flat!(self.height_to_time),
+            address_stats: flat!(self.address_stats),
+            asset_holders: flat!(self.asset_holders),
+            spent_utxos: flat!(self.spent_utxos),
+            address_filters: flat!(self.address_filters),
+            txn_hash_prefixes: flat!(self.txn_hash_prefixes),
+            asset_code_prefixes: flat!(self.asset_code_prefixes),
+            height_to_max_atxo: flat!(self.height_to_max_atxo),
+            height_to_next_sids: flat!(self.height_to_next_sids),
+            staking_global_rate_hist: flat!(self.staking_global_rate_hist),
+            staking_self_delegation_hist: nested!(self.staking_self_delegation_hist),
+            staking_delegation_amount_hist: nested!(self.staking_delegation_amount_hist),
+            staking_delegation_rwd_hist: nested!(self.staking_delegation_rwd_hist),
+            staking_signed_cnt_hist: nested!(self.staking_signed_cnt_hist),
+            governance_proposals: flat!(self.governance_proposals),
+            last_sid: flat!(self.last_sid),
+            state_commitment_version: self.state_commitment_version.clone(),
+        }
+    }
+
+    /// Write a snapshot into this cache, which is expected to be empty
+    pub fn import_snapshot(&mut self, snapshot: ApiCacheSnapshot) -> Result<()> {
+        if snapshot.version != SNAPSHOT_VERSION {
+            return Err(eg!(format!(
+                "unsupported snapshot version: {}",
+                snapshot.version
+            )));
+        }
+        if 0 < self.txn_sid_to_hash.len() {
+            return Err(eg!("the api cache is not empty"));
+        }
+
+        let prefix = self.prefix.clone();
+
+        macro_rules! flat {
+            ($field: ident) => {
+                for (k, v) in snapshot.$field {
+                    self.$field.insert(k, v);
+                }
+            };
+        }
+        macro_rules! nested {
+            ($field: ident, $path: expr) => {
+                for (k, entries) in snapshot.$field {
+                    let mut sub = new_mapxnk!($path(&k));
+                    for (k2, v2) in entries {
+                        sub.insert(k2, v2);
+                    }
+                    self.$field.insert(k, sub);
+                }
+            };
+        }
+
+        nested!(related_transactions, |k: &XfrAddress| format!(
+            "api_cache/{}related_transactions/{}",
+            prefix,
+            k.to_base64()
+        ));
+        nested!(related_transfers, |k: &AssetTypeCode| format!(
+            "api_cache/{}related_transfers/{}",
+            prefix,
+            k.to_base64()
+        ));
+        nested!(txns_by_op_type, |k: &String| format!(
+            "api_cache/{}txns_by_op_type/{}",
+            prefix, k
+        ));
+        nested!(claim_hist_txns, |k: &XfrAddress| format!(
+            "api_cache/{}claim_hist_txns/{}",
+            prefix,
+            k.to_base64()
+        ));
+        nested!(coinbase_oper_hist, |k: &XfrAddress| format!(
+            "api_cache/{}coinbase_oper_hist/{}",
+            prefix,
+            k.to_base64()
+        ));
+        nested!(created_assets, |k: &IssuerPublicKey| format!(
+            "api_cache/{}created_assets/{}",
+            prefix,
+            k.to_base64()
+        ));
+        flat!(issuances);
+        flat!(token_code_issuances);
+        flat!(owner_memos);
+        flat!(abar_memos);
+        flat!(utxos_to_map_index);
+        flat!(txo_to_txnid);
+        flat!(atxo_to_txnid);
+        flat!(txn_sid_to_hash);
+        flat!(txn_hash_to_sid);
+        flat!(txn_sid_to_height);
+        flat!(height_to_time);
+        flat!(address_stats);
+        flat!(asset_holders);
+        flat!(spent_utxos);
+        flat!(address_filters);
+        flat!(txn_hash_prefixes);
+        flat!(asset_code_prefixes);
+        flat!(height_to_max_atxo);
+        flat!(height_to_next_sids);
+        flat!(staking_global_rate_hist);
+        nested!(staking_self_delegation_hist, |k: &XfrPublicKey| format!(
+            "staking_self_delegation_hist_subdata/{}",
+            wallet::public_key_to_base64(k)
+        ));
+        nested!(staking_delegation_amount_hist, |k: &XfrPublicKey| format!(
+            "staking_delegation_amount_hist_subdata/{}",
+            wallet::public_key_to_base64(k)
+        ));
+        nested!(staking_delegation_rwd_hist, |k: &XfrPublicKey| format!(
+            "staking_delegation_rwd_hist_subdata/{}",
+            wallet::public_key_to_base64(k)
+        ));
+        nested!(staking_signed_cnt_hist, |k: &XfrPublicKey| format!(
+            "api_cache/{}staking_signed_cnt_hist/{}",
+            prefix,
+            wallet::public_key_to_base64(k)
+        ));
+        flat!(governance_proposals);
+        flat!(last_sid);
+        self.state_commitment_version = snapshot.state_commitment_version;
+
+        Ok(())
+    }
+}
+
+/// Dump the api cache of a ledger into a snapshot file
+pub fn dump_snapshot(ledger: &LedgerState, path: &Path) -> Result<()> {
+    let api_cache = ledger.api_cache.as_ref().c(d!("api cache is disabled"))?;
+    let snapshot = api_cache.export_snapshot(ledger.get_block_count());
+    let bytes = bincode::serialize(&snapshot).c(d!())?;
+
+    // write aside and rename, a reader never sees a partial snapshot
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, bytes).c(d!())?;
+    fs::rename(&tmp, path).c(d!())
+}
+
+/// Load a snapshot file into the empty api cache of a ledger,
+/// the txns after the snapshot are filled in by `check_lost_data`
+pub fn load_snapshot(ledger: &mut LedgerState, path: &Path) -> Result<()> {
+    let bytes = fs::read(path).c(d!())?;
+    let snapshot = bincode::deserialize::<ApiCacheSnapshot>(&bytes).c(d!())?;
+    if snapshot.block_count > ledger.get_block_count() {
+        return Err(eg!(format!(
+            "snapshot is ahead of the ledger: {} > {}",
+            snapshot.block_count,
+            ledger.get_block_count()
+        )));
+    }
+
+    ledger
         .api_cache
         .as_mut()
-        .unwrap()
+        .c(d!("api cache is disabled"))?
+        .import_snapshot(snapshot)
+        .c(d!())
+}
+
+/// Max number of txn sids and of txo sids checked by `check_lost_data`
+/// on each commit, the rest is left to `recover_lost_data`
+pub const LOST_DATA_SIDS_PER_BLOCK: u64 = 200;
+
+/// Number of sids checked by one batch of `recover_lost_data`
+pub const LOST_DATA_BATCH: u64 = 10_000;
+
+/// Progress of the recovery of the data lost by the api cache,
+/// sids below `*_checked` are known to be cached
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct LostDataProgress {
+    pub txn_checked: u64,
+    pub txn_total: u64,
+    pub txo_checked: u64,
+    pub txo_total: u64,
+}
+
+impl LostDataProgress {
+    /// All the sids of the ledger have been checked
+    pub fn is_done(&self) -> bool {
+        self.txn_checked >= self.txn_total && self.txo_checked >= self.txo_total
+    }
+
+    /// Checked part of the sids, in [0, 1]
+    pub fn ratio(&self) -> f64 {
+        let total = self.txn_total + self.txo_total;
+        if 0 == total {
+            1.0
+        } else {
+            (self.txn_checked.min(self.txn_total) + self.txo_checked.min(self.txo_total))
+                as f64
+                / total as f64
+        }
+    }
+}
+
+lazy_static! {
+    /// latest progress of `check_lost_data`
+    pub static ref LOST_DATA_PROGRESS: RwLock<LostDataProgress> =
+        RwLock::new(LostDataProgress::default());
+}
+
+/// Check the lost data, at most `max_sids` txn sids and `max_sids` txo sids
+/// are checked per call, the position is persisted in `last_sid` once per
+/// call, so the check resumes from there on the next call or after a restart.
+pub fn check_lost_data(
+    ledger: &mut LedgerState,
+    max_sids: u64,
+) -> Result<LostDataProgress> {
+    let cur_txn_sid = ledger.get_next_txn().0 as u64;
+    let cur_txo_sid = ledger.get_next_txo().0;
+
+    let mut api_cache = if let Some(api_cache) = ledger.api_cache.take() {
+        api_cache
+    } else {
+        return Ok(LostDataProgress::default());
+    };
+    let res = check_lost_data_batch(
+        ledger,
+        &mut api_cache,
+        [cur_txn_sid, cur_txo_sid],
+        max_sids,
+    );
+    ledger.api_cache = Some(api_cache);
+
+    let progress = res.c(d!())?;
+    *LOST_DATA_PROGRESS.write() = progress;
+    Ok(progress)
+}
+
+fn check_lost_data_batch(
+    ledger: &LedgerState,
+    api_cache: &mut ApiCache,
+    [cur_txn_sid, cur_txo_sid]: [u64; 2],
+    max_sids: u64,
+) -> Result<LostDataProgress> {
+    // the last checked sid is stored
+    let txn_start = api_cache
+        .last_sid
+        .get(&"last_txn_sid".to_string())
+        .map_or(0, |sid| sid + 1);
+    let txn_end = cur_txn_sid.min(txn_start.saturating_add(max_sids));
+
+    let indexes = *API_CACHE_INDEXES;
+
+    // check the lost txn sids
+    for index in (txn_start..txn_end).filter(|_| indexes.txn_hashes) {
+        let sid = TxnSID(index as usize);
+        if !api_cache.txn_sid_to_hash.contains_key(&sid) {
+            let ftx = ledger.get_transaction_light(sid).c(d!())?;
+            let hash = ftx.txn.hash_tm().hex().to_uppercase();
+            api_cache.txn_sid_to_hash.insert(sid, hash.clone());
+            api_cache.txn_hash_to_sid.insert(hash, sid);
+        }
+    }
+    if txn_start < txn_end {
+        api_cache
+            .last_sid
+            .insert("last_txn_sid".to_string(), txn_end - 1);
+    }
+
+    let txo_start = api_cache
         .last_sid
-        .get(&"last_txo_sid".to_string());
+        .get(&"last_txo_sid".to_string())
+        .map_or(0, |sid| sid + 1);
+    let txo_end = cur_txo_sid.min(txo_start.saturating_add(max_sids));
+
+    // check the lost memos
+    for index in txo_start..txo_end {
+        if api_cache.owner_memos.contains_key(&TxoSID(index)) {
+            continue;
+        }
+        let utxo = if let Some(utxo) = ledger.get_utxo(TxoSID(index)) {
+            utxo
+        } else {
+            continue;
+        };
+        let ftx = ledger
+            .get_transaction_light(utxo.authenticated_txn.finalized_txn.tx_id)
+            .c(d!())?;
+        let tx_hash = ftx.txn.hash_tm().hex().to_uppercase();
+        let owner_memos = ftx.txn.get_owner_memos_ref();
+        let mut addresses: Vec<XfrAddress> = vec![];
+        for sid in ftx.txo_ids.iter() {
+            let key = ledger
+                .get_utxo_light(*sid)
+                .or_else(|| ledger.get_spent_utxo_light(*sid))
+                .c(d!())?
+                .utxo
+                .0
+                .record
+                .public_key;
+            addresses.push(XfrAddress { key });
+        }
+
+        for (txo_sid, (address, owner_memo)) in ftx
+            .txo_ids
+            .iter()
+            .zip(addresses.iter().zip(owner_memos.iter()))
+        {
+            if *txo_sid == TxoSID(index) {
+                api_cache.utxos_to_map_index.insert(*txo_sid, *address);
+                if let Some(memo) = owner_memo.as_ref().filter(|_| indexes.owner_memos) {
+                    api_cache.owner_memos.insert(*txo_sid, memo.clone());
+                }
+                if indexes.txn_hashes {
+                    api_cache
+                        .txo_to_txnid
+                        .insert(*txo_sid, (ftx.tx_id, tx_hash.clone()));
+                }
+            }
+        }
+    }
+    if txo_start < txo_end {
+        api_cache
+            .last_sid
+            .insert("last_txo_sid".to_string(), txo_end - 1);
+    }
+
+    Ok(LostDataProgress {
+        txn_checked: txn_end.max(txn_start),
+        txn_total: cur_txn_sid,
+        txo_checked: txo_end.max(txo_start),
+        txo_total: cur_txo_sid,
+    })
+}
+
+/// Check the lost data in batches of `LOST_DATA_BATCH` until the api cache
+/// has caught up with the ledger, the lock is released between two batches
+/// so that blocks can still be committed, meant to be run in the background.
+pub fn recover_lost_data(ledger: &RwLock<LedgerState>) -> Result<()> {
+    loop {
+        let progress = check_lost_data(&mut ledger.write(), LOST_DATA_BATCH).c(d!())?;
+        if progress.is_done() {
+            return Ok(());
+        }
+        tracing::info!(
+            "api cache recovery: {:.2}%, txn {}/{}, txo {}/{}",
+            progress.ratio() * 100.0,
+            progress.txn_checked,
+            progress.txn_total,
+            progress.txo_checked,
+            progress.txo_total
+        );
+        thread::yield_now();
+    }
+}
+
+/// Divergences between the api cache and the ledger found by `verify_api_cache`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ApiCacheReport {
+    /// txns whose `txn_sid_to_hash` entry is missing or wrong
+    pub txn_sid_to_hash: u64,
+    /// txns whose `txn_hash_to_sid` entry is missing or wrong
+    pub txn_hash_to_sid: u64,
+    /// `txn_hash_to_sid` entries of no txn of the ledger
+    pub dangling_txn_hashes: u64,
+    /// txos whose `utxos_to_map_index` entry is wrong, or missing while unspent
+    pub utxos_to_map_index: u64,
+    /// txos whose `txo_to_txnid` entry is wrong, or missing while unspent
+    pub txo_to_txnid: u64,
+    /// txo index entries of no txo of the ledger
+    pub dangling_txos: u64,
+    /// the divergences have been repaired
+    pub repaired: bool,
+}
+
+impl ApiCacheReport {
+    /// No divergence has been found
+    pub fn is_consistent(&self) -> bool {
+        ApiCacheReport {
+            repaired: self.repaired,
+            ..Default::default()
+        } == *self
+    }
+}
+
+/// Cross-check the txn and txo indexes of the api cache against the ledger,
+/// all the txns are checked instead of the ones after `last_sid` as in
+/// `check_lost_data`; the divergences are fixed from the ledger if `repair`.
+/// Spent txos missing from the txo indexes are not reported, as they may
+/// have been pruned, nor the indexes disabled by `API_CACHE_INDEXES`.
+pub fn verify_api_cache(
+    ledger: &mut LedgerState,
+    repair: bool,
+) -> Result<ApiCacheReport> {
+    let mut api_cache = ledger.api_cache.take().c(d!("api cache is disabled"))?;
+    let res = verify_indexes(ledger, &mut api_cache, repair);
+    ledger.api_cache = Some(api_cache);
+    res
+}
 
-    let mut last_txo_sid: u64 = 0;
-    if let Some(sid) = last_txo_sid_opt {
-        last_txo_sid = sid;
+fn verify_indexes(
+    ledger: &LedgerState,
+    api_cache: &mut ApiCache,
+    repair: bool,
+) -> Result<ApiCacheReport> {
+    let mut report = ApiCacheReport {
+        repaired: repair,
+        ..Default::default()
     };
+    let txn_hashes = API_CACHE_INDEXES.txn_hashes;
 
-    if last_txo_sid < cur_txo_sid {
-        for index in last_txo_sid..cur_txo_sid {
-            if !ledger
-                .api_cache
-                .as_mut()
-                .unwrap()
-                .owner_memos
-                .contains_key(&TxoSID(index))
+    for block_idx in 0..ledger.get_block_count() {
+        let block = ledger.blocks.get(block_idx).c(d!())?;
+        for ftx in block.txns.iter() {
+            let sid = ftx.tx_id;
+            let hash = ftx.txn.hash_tm().hex().to_uppercase();
+
+            if txn_hashes && api_cache.txn_sid_to_hash.get(&sid).as_ref() != Some(&hash)
             {
-                let utxo_opt = ledger.get_utxo(TxoSID(index));
-                if let Some(utxo) = utxo_opt {
-                    let ftx = ledger
-                        .get_transaction_light(
-                            utxo.authenticated_txn.finalized_txn.tx_id,
-                        )
-                        .unwrap();
-                    let tx_hash = ftx.txn.hash_tm().hex().to_uppercase();
-                    let owner_memos = ftx.txn.get_owner_memos_ref();
-                    let addresses: Vec<XfrAddress> = ftx
-                        .txo_ids
-                        .iter()
-                        .map(|sid| XfrAddress {
-                            key: ((ledger
-                                .get_utxo_light(*sid)
-                                .or_else(|| ledger.get_spent_utxo_light(*sid))
-                                .unwrap()
-                                .utxo)
-                                .0)
-                                .record
-                                .public_key,
-                        })
-                        .collect();
-
-                    for (txo_sid, (address, owner_memo)) in ftx
-                        .txo_ids
-                        .iter()
-                        .zip(addresses.iter().zip(owner_memos.iter()))
-                    {
-                        if *txo_sid == TxoSID(index) {
-                            ledger
-                                .api_cache
-                                .as_mut()
-                                .unwrap()
-                                .utxos_to_map_index
-                                .insert(*txo_sid, *address);
-
-                            if let Some(memo) = owner_memo {
-                                ledger
-                                    .api_cache
-                                    .as_mut()
-                                    .unwrap()
-                                    .owner_memos
-                                    .insert(*txo_sid, (*memo).clone());
-                            }
-
-                            ledger
-                                .api_cache
-                                .as_mut()
-                                .unwrap()
-                                .txo_to_txnid
-                                .insert(*txo_sid, (ftx.tx_id, tx_hash.clone()));
+                report.txn_sid_to_hash += 1;
+                if repair {
+                    api_cache.txn_sid_to_hash.insert(sid, hash.clone());
+                }
+            }
+            if txn_hashes && api_cache.txn_hash_to_sid.get(&hash) != Some(sid) {
+                report.txn_hash_to_sid += 1;
+                if repair {
+                    api_cache.txn_hash_to_sid.insert(hash.clone(), sid);
+                }
+            }
+
+            for txo_sid in ftx.txo_ids.iter() {
+                let (utxo, unspent) = match ledger.get_utxo_light(*txo_sid) {
+                    Some(utxo) => (utxo, true),
+                    None => (ledger.get_spent_utxo_light(*txo_sid).c(d!())?, false),
+                };
+                let owner = XfrAddress {
+                    key: utxo.utxo.0.record.public_key,
+                };
+
+                match api_cache.utxos_to_map_index.get(txo_sid) {
+                    Some(o) if o == owner => {}
+                    None if !unspent => {}
+                    _ => {
+                        report.utxos_to_map_index += 1;
+                        if repair {
+                            api_cache.utxos_to_map_index.insert(*txo_sid, owner);
+                        }
+                    }
+                }
+
+                match api_cache.txo_to_txnid.get(txo_sid) {
+                    Some((s, h)) if s == sid && h == hash => {}
+                    None if !unspent || !txn_hashes => {}
+                    _ => {
+                        report.txo_to_txnid += 1;
+                        if repair {
+                            api_cache.txo_to_txnid.insert(*txo_sid, (sid, hash.clone()));
                         }
                     }
-                } else {
-                    continue;
                 }
             }
+        }
+    }
+
+    // checked against the repaired `txn_sid_to_hash` when repairing
+    let dangling_hashes = api_cache
+        .txn_hash_to_sid
+        .iter()
+        .filter(|(hash, sid)| api_cache.txn_sid_to_hash.get(sid).as_ref() != Some(hash))
+        .map(|(hash, _)| hash)
+        .collect::<Vec<_>>();
+    report.dangling_txn_hashes = dangling_hashes.len() as u64;
+
+    let next_txo = ledger.get_next_txo();
+    let dangling_txos = api_cache
+        .utxos_to_map_index
+        .iter()
+        .map(|(sid, _)| sid)
+        .chain(api_cache.txo_to_txnid.iter().map(|(sid, _)| sid))
+        .filter(|sid| *sid >= next_txo)
+        .collect::<HashSet<_>>();
+    report.dangling_txos = dangling_txos.len() as u64;
 
-            // update the last txo sid
-            ledger
-                .api_cache
-                .as_mut()
-                .unwrap()
-                .last_sid
-                .insert("last_txo_sid".to_string(), index);
+    if repair {
+        for hash in dangling_hashes {
+            api_cache.txn_hash_to_sid.remove(&hash);
+        }
+        for sid in dangling_txos {
+            api_cache.utxos_to_map_index.remove(&sid);
+            api_cache.txo_to_txnid.remove(&sid);
         }
     }
-    Ok(())
+
+    Ok(report)
+}
+
+/// Data of a txn read from the ledger by `update_api_cache`
+struct TxnUpdate<'a> {
+    ftx: &'a FinalizedTransaction,
+    hash: String,
+    /// owners of the txos, in order
+    owners: Vec<XfrAddress>,
+    related_addresses: HashSet<XfrAddress>,
+    transferred_assets: HashSet<AssetTypeCode>,
+    op_types: BTreeSet<&'static str>,
+}
+
+impl<'a> TxnUpdate<'a> {
+    /// Returns the sid of the first txo whose owner is not found on error
+    fn new(
+        ledger: &LedgerState,
+        ftx: &'a FinalizedTransaction,
+    ) -> std::result::Result<Self, TxoSID> {
+        let mut owners = Vec::with_capacity(ftx.txo_ids.len());
+        for sid in ftx.txo_ids.iter() {
+            let utxo = ledger
+                .get_utxo_light(*sid)
+                .or_else(|| ledger.get_spent_utxo_light(*sid))
+                .ok_or(*sid)?;
+            owners.push(XfrAddress {
+                key: utxo.utxo.0.record.public_key,
+            });
+        }
+
+        let mut op_types = BTreeSet::new();
+        let related_addresses = get_related_addresses(&ftx.txn, |op| {
+            op_types.insert(op.kind());
+        });
+
+        Ok(TxnUpdate {
+            ftx,
+            hash: ftx.txn.hash_tm().hex().to_uppercase(),
+            owners,
+            related_addresses,
+            transferred_assets: get_transferred_nonconfidential_assets(&ftx.txn),
+            op_types,
+        })
+    }
 }
 
 /// update the data of QueryServer when we create a new block in ABCI
@@ -494,53 +1727,145 @@ pub fn update_api_cache(ledger: &mut LedgerState) -> Result<()> {
         return Ok(());
     }
 
-    check_lost_data(ledger)?;
+    check_lost_data(ledger, LOST_DATA_SIDS_PER_BLOCK)?;
 
-    let mut api_cache = ledger.api_cache.take().unwrap();
+    ledger.api_cache.as_mut().c(d!())?.cache_hist_data();
 
-    api_cache.cache_hist_data();
+    let entry = match ledger.get_block_count().checked_sub(1) {
+        Some(block_idx) => WalEntry {
+            block_idx,
+            height: ledger.status.td_commit_height,
+            time: ledger.status.td_commit_time,
+        },
+        None => return Ok(()),
+    };
 
-    let block = if let Some(b) = ledger.blocks.last() {
-        b
-    } else {
-        ledger.api_cache = Some(api_cache);
-        return Ok(());
+    ledger
+        .api_cache
+        .as_ref()
+        .c(d!())?
+        .log_wal_entry(&entry)
+        .c(d!())?;
+    index_block(ledger, entry).c(d!())?;
+    ledger.api_cache.as_ref().c(d!())?.clear_wal().c(d!())
+}
+
+/// Replay the indexing interrupted by a crash, called on startup
+pub fn replay_wal(ledger: &mut LedgerState) -> Result<()> {
+    let entry = match ledger.api_cache.as_ref() {
+        Some(api_cache) => api_cache.pending_wal_entry().c(d!())?,
+        None => None,
+    };
+    if let Some(entry) = entry {
+        // otherwise the block itself did not reach the disk, nothing to replay
+        if entry.block_idx < ledger.get_block_count() {
+            tracing::warn!("replaying the api cache indexing of {:?}", entry);
+            index_block(ledger, entry).c(d!())?;
+        }
+        ledger.api_cache.as_ref().c(d!())?.clear_wal().c(d!())?;
+    }
+    Ok(())
+}
+
+/// Drop the api cache entries above the height of the ledger,
+/// left by a consensus rollback, called on startup
+pub fn rollback_to_ledger(ledger: &mut LedgerState) -> Result<u64> {
+    let height = ledger.status.td_commit_height;
+    let next = NextSids::of_ledger(ledger);
+    let api_cache = match ledger.api_cache.as_mut() {
+        Some(api_cache) => api_cache,
+        None => return Ok(0),
     };
+    if !api_cache.txn_sid_to_height.contains_key(&next.txn)
+        && !api_cache.height_to_time.contains_key(&(height + 1))
+    {
+        return Ok(0);
+    }
+
+    let n = api_cache.rollback_to(height, next);
+    tracing::warn!(
+        "rolled back {} api cache entries above height {}",
+        n,
+        height
+    );
+    fbnc::flush_data();
+    Ok(n)
+}
+
+/// Index the txns of a block into the api cache
+fn index_block(ledger: &mut LedgerState, entry: WalEntry) -> Result<()> {
+    let block = ledger.blocks.get(entry.block_idx).c(d!())?;
+    let mut api_cache = ledger.api_cache.take().c(d!())?;
 
     let prefix = api_cache.prefix.clone();
 
     // Update state commitment versions
     api_cache.state_commitment_version = ledger.status.state_commitment_versions.last();
 
-    // Update ownership status
-    for (txn_sid, txo_sids, atxo_sids) in block
+    let block_height = entry.height;
+    let policy = PruningPolicy::from_cfg();
+    let indexes = *API_CACHE_INDEXES;
+    let mut spent_txos = vec![];
+    let (mut received, mut sent) = (vec![], vec![]);
+    let mut block_addresses = HashSet::new();
+
+    // The read-only part runs in parallel over the txns,
+    // the writes are then applied in one pass, in txn order
+    let updates = block
         .txns
-        .iter()
-        .map(|v| (v.tx_id, v.txo_ids.as_slice(), v.atxo_ids.as_slice()))
-    {
-        let curr_txn = ledger.get_transaction_light(txn_sid).c(d!())?.txn;
-        // get the transaction, ownership addresses, and memos associated with each transaction
-        let (addresses, owner_memos) = {
-            let mut addresses: Vec<XfrAddress> = vec![];
-            for sid in txo_sids.iter() {
-                let key = ledger
-                    .get_utxo_light(*sid)
-                    .or_else(|| ledger.get_spent_utxo_light(*sid))
-                    .c(d!())?
-                    .utxo
-                    .0
-                    .record
-                    .public_key;
-                addresses.push(XfrAddress { key });
-            }
+        .par_iter()
+        .map(|ftx| TxnUpdate::new(ledger, ftx))
+        .collect::<Vec<_>>();
 
-            let owner_memos = curr_txn.get_owner_memos_ref();
-            (addresses, owner_memos)
-        };
+    for update in updates {
+        let TxnUpdate {
+            ftx,
+            hash,
+            owners,
+            related_addresses,
+            transferred_assets,
+            op_types,
+        } = update.map_err(|sid| eg!(format!("owner of {:?} not found", sid)))?;
+        let txn_sid = ftx.tx_id;
+        let curr_txn = &ftx.txn;
 
-        let classify_op = |op: &Operation| {
+        // the last block is revisited on every commit, keep the first height seen
+        let first_seen = !api_cache.txn_sid_to_height.contains_key(&txn_sid);
+        if first_seen {
+            api_cache.txn_sid_to_height.insert(txn_sid, block_height);
+        }
+        if first_seen && indexes.txn_hashes {
+            ApiCache::add_search_prefix(&mut api_cache.txn_hash_prefixes, hash.clone());
+        }
+        if first_seen && indexes.explorer {
+            api_cache.add_address_stats(curr_txn, block_height);
+            api_cache.add_spent_utxos(curr_txn, txn_sid, block_height);
+            for (r, is_received) in get_record_flows(curr_txn) {
+                if let Some(val) = r.asset_type.get_asset_type() {
+                    let flow = (AssetTypeCode { val }, XfrAddress { key: r.public_key });
+                    alt!(is_received, received.push(flow), sent.push(flow));
+                }
+            }
+            for op_type in op_types {
+                api_cache
+                    .txns_by_op_type
+                    .entry(op_type.to_owned())
+                    .or_insert_with(|| {
+                        new_mapxnk!(format!(
+                            "api_cache/{}txns_by_op_type/{}",
+                            prefix, op_type
+                        ))
+                    })
+                    .insert(txn_sid, Default::default());
+            }
+        }
+        if policy.prune_spent_txos {
+            spent_txos.extend(get_spent_txos(curr_txn));
+        }
+
+        for op in curr_txn.body.operations.iter() {
             match op {
-                Operation::Claim(i) => {
+                Operation::Claim(i) if indexes.coinbase_hist => {
                     let key = XfrAddress {
                         key: i.get_claim_publickey(),
                     };
@@ -556,29 +1881,48 @@ pub fn update_api_cache(ledger: &mut LedgerState) -> Result<()> {
                         })
                         .set_value(txn_sid, Default::default());
                 }
-                Operation::MintFra(i) => i.entries.iter().for_each(|me| {
-                    let key = XfrAddress {
-                        key: me.utxo.record.public_key,
-                    };
-                    #[allow(unused_mut)]
-                    let mut hist =
-                        api_cache.coinbase_oper_hist.entry(key).or_insert_with(|| {
-                            new_mapxnk!(format!(
-                                "api_cache/{}coinbase_oper_hist/{}",
-                                prefix,
-                                key.to_base64()
-                            ))
-                        });
-                    hist.insert(i.height, me.clone());
-                }),
+                Operation::MintFra(i) if indexes.coinbase_hist => {
+                    i.entries.iter().for_each(|me| {
+                        let key = XfrAddress {
+                            key: me.utxo.record.public_key,
+                        };
+                        #[allow(unused_mut)]
+                        let mut hist = api_cache
+                            .coinbase_oper_hist
+                            .entry(key)
+                            .or_insert_with(|| {
+                                new_mapxnk!(format!(
+                                    "api_cache/{}coinbase_oper_hist/{}",
+                                    prefix,
+                                    key.to_base64()
+                                ))
+                            });
+                        hist.insert(i.height, me.clone());
+                    })
+                }
+                // Add created asset
+                Operation::DefineAsset(define_asset) if indexes.issuances => {
+                    api_cache.add_created_asset(define_asset, block_height);
+                }
+                Operation::IssueAsset(issue_asset) if indexes.issuances => {
+                    api_cache.cache_issuance(issue_asset);
+                }
+                Operation::Governance(i) if first_seen && indexes.staking_hist => {
+                    api_cache.add_governance_proposal(
+                        i,
+                        txn_sid,
+                        hash.clone(),
+                        block_height,
+                    );
+                }
                 _ => { /* filter more operations before this line */ }
             };
-        };
+        }
+
+        block_addresses.extend(related_addresses.iter().copied());
 
         // Update related addresses
-        // Apply classify_op for each operation in curr_txn
-        let related_addresses = get_related_addresses(&curr_txn, classify_op);
-        for address in &related_addresses {
+        for address in related_addresses.iter().filter(|_| indexes.explorer) {
             api_cache
                 .related_transactions
                 .entry(*address)
@@ -593,8 +1937,7 @@ pub fn update_api_cache(ledger: &mut LedgerState) -> Result<()> {
         }
 
         // Update transferred nonconfidential assets
-        let transferred_assets = get_transferred_nonconfidential_assets(&curr_txn);
-        for asset in &transferred_assets {
+        for asset in transferred_assets.iter().filter(|_| indexes.explorer) {
             api_cache
                 .related_transfers
                 .entry(*asset)
@@ -608,38 +1951,26 @@ pub fn update_api_cache(ledger: &mut LedgerState) -> Result<()> {
                 .insert(txn_sid, Default::default());
         }
 
-        // Add created asset
-        for op in &curr_txn.body.operations {
-            match op {
-                Operation::DefineAsset(define_asset) => {
-                    api_cache.add_created_asset(
-                        &define_asset,
-                        ledger.status.td_commit_height,
-                    );
-                }
-                Operation::IssueAsset(issue_asset) => {
-                    api_cache.cache_issuance(&issue_asset);
-                }
-                _ => {}
-            };
-        }
-
         // Add new utxos (this handles both transfers and issuances)
-        for (txo_sid, (address, owner_memo)) in txo_sids
+        if indexes.txn_hashes {
+            api_cache.txn_sid_to_hash.insert(txn_sid, hash.clone());
+            api_cache.txn_hash_to_sid.insert(hash.clone(), txn_sid);
+        }
+        let owner_memos = curr_txn.get_owner_memos_ref();
+        for (txo_sid, (address, owner_memo)) in ftx
+            .txo_ids
             .iter()
-            .zip(addresses.iter().zip(owner_memos.iter()))
+            .zip(owners.iter().zip(owner_memos.iter()))
         {
             api_cache.utxos_to_map_index.insert(*txo_sid, *address);
-            let hash = curr_txn.hash_tm().hex().to_uppercase();
-            api_cache
-                .txo_to_txnid
-                .insert(*txo_sid, (txn_sid, hash.clone()));
-            api_cache.txn_sid_to_hash.insert(txn_sid, hash.clone());
-            api_cache.txn_hash_to_sid.insert(hash.clone(), txn_sid);
-            if let Some(owner_memo) = owner_memo {
+            if indexes.txn_hashes {
                 api_cache
-                    .owner_memos
-                    .insert(*txo_sid, (*owner_memo).clone());
+                    .txo_to_txnid
+                    .insert(*txo_sid, (txn_sid, hash.clone()));
+            }
+            if let Some(owner_memo) = owner_memo.as_ref().filter(|_| indexes.owner_memos)
+            {
+                api_cache.owner_memos.insert(*txo_sid, owner_memo.clone());
             }
         }
 
@@ -651,18 +1982,49 @@ pub fn update_api_cache(ledger: &mut LedgerState) -> Result<()> {
             _ => vec![],
         });
 
-        for (a, id) in abar_memos.zip(atxo_sids) {
-            api_cache.abar_memos.insert(*id, a);
-            let hash = curr_txn.hash_tm().hex().to_uppercase();
-            api_cache.atxo_to_txnid.insert(*id, (txn_sid, hash.clone()));
+        for (a, id) in abar_memos.zip(ftx.atxo_ids.iter()) {
+            if indexes.owner_memos {
+                api_cache.abar_memos.insert(*id, a);
+            }
+            if indexes.txn_hashes {
+                api_cache.atxo_to_txnid.insert(*id, (txn_sid, hash.clone()));
+            }
+        }
+    }
+
+    if indexes.explorer {
+        api_cache
+            .update_asset_holders(ledger, received, sent)
+            .c(d!())?;
+        api_cache.address_filters.insert(
+            entry.block_idx as u64,
+            AddressFilter::new(block_addresses.iter()),
+        );
+    }
+
+    // Enforce the retention policy, after the new txos of the block are added
+    for sid in spent_txos {
+        api_cache.prune_txo(sid);
+    }
+    if let Some(n) = policy.coinbase_hist_blocks {
+        if 0 == block_height % COINBASE_PRUNE_ITV {
+            api_cache.prune_coinbase_hist(block_height.saturating_sub(n));
         }
     }
 
     // Update block height to max atxo mapping
-    let max_atxo = api_cache.abar_memos.len().checked_sub(1);
-    let block_height = ledger.status.td_commit_height;
+    let max_atxo = ledger.status.next_atxo.0.checked_sub(1).map(|n| n as usize);
     api_cache.height_to_max_atxo.insert(block_height, max_atxo);
 
+    if indexes.staking_hist && 0 == block_height % UPTIME_SAMPLE_ITV {
+        api_cache.add_signed_cnt_samples(ledger.get_staking(), block_height);
+    }
+
+    api_cache.height_to_time.insert(block_height, entry.time);
+    api_cache
+        .height_to_next_sids
+        .insert(block_height, NextSids::of_ledger(ledger));
+
     ledger.api_cache = Some(api_cache);
 
     Ok(())
@@ -0,0 +1,175 @@
+//!
+//! Replay protection audit of the committed txns, for incident response.
+//!
+//! The no-replay token of a txn is checked by `check_txn_effects` against a
+//! sliding window of the recent blocks only, and the issuance sequence numbers
+//! against the limit of the ledger status, so neither check can tell whether
+//! the history itself is consistent. This audit scans every committed txn,
+//! of the local ledger or of a block export stream, and reports:
+//!
+//! - the no-replay tokens used by more than one txn
+//! - the tokens whose seq_id is out of the window of their block
+//! - the issuances whose sequence number is not above the previous one
+//!   of the same asset type
+//!
+
+use {
+    super::{export::BlockReader, LedgerState, TRANSACTION_WINDOW_WIDTH},
+    crate::data_model::{AssetTypeCode, FinalizedTransaction, Operation, TxnSID},
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    std::{collections::HashMap, io::Read},
+};
+
+/// A txn breaking the replay protection of the ledger
+#[allow(missing_docs)]
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind")]
+pub enum Anomaly {
+    /// the no-replay token of `txn` was already used by `first_txn`
+    DuplicateToken {
+        rand: [u8; 8],
+        seq_id: u64,
+        first_txn: TxnSID,
+        txn: TxnSID,
+        block_sid: usize,
+    },
+    /// the seq_id of the token of `txn` is ahead of its block,
+    /// or more than `TRANSACTION_WINDOW_WIDTH` blocks behind it
+    SeqIdOutOfWindow {
+        seq_id: u64,
+        txn: TxnSID,
+        block_sid: usize,
+    },
+    /// `txn` issues `code` with a sequence number not above the previous one
+    IssuanceSeqNum {
+        /// base64 asset code
+        code: String,
+        seq_num: u64,
+        prev_seq_num: u64,
+        txn: TxnSID,
+        block_sid: usize,
+    },
+}
+
+/// Outcome of an audit
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ReplayAudit {
+    /// count of the blocks scanned
+    pub blocks: usize,
+    /// count of the txns of those blocks
+    pub txns: usize,
+    /// count of the `IssueAsset` operations of those txns
+    pub issuances: usize,
+    /// in block order
+    pub anomalies: Vec<Anomaly>,
+}
+
+/// Scans the blocks in order, the state kept is the first txn of each token
+/// and the last sequence number of each asset type
+#[derive(Default)]
+pub struct ReplayAuditor {
+    tokens: HashMap<([u8; 8], u64), TxnSID>,
+    seq_nums: HashMap<AssetTypeCode, u64>,
+    report: ReplayAudit,
+}
+
+impl ReplayAuditor {
+    /// Audit the txns of the next block
+    pub fn audit_block(&mut self, block_sid: usize, txns: &[FinalizedTransaction]) {
+        for ftx in txns.iter() {
+            self.audit_txn(block_sid, ftx);
+        }
+        self.report.blocks += 1;
+        self.report.txns += txns.len();
+    }
+
+    fn audit_txn(&mut self, block_sid: usize, ftx: &FinalizedTransaction) {
+        let token = ftx.txn.body.no_replay_token;
+        let (rand, seq_id) = (token.get_rand(), token.get_seq_id());
+
+        // the seq_id is checked against the count of the blocks committed before
+        let block_count = block_sid as u64;
+        if seq_id > block_count || seq_id + TRANSACTION_WINDOW_WIDTH < block_count {
+            self.report.anomalies.push(Anomaly::SeqIdOutOfWindow {
+                seq_id,
+                txn: ftx.tx_id,
+                block_sid,
+            });
+        }
+
+        if let Some(first_txn) = self.tokens.get(&(rand, seq_id)) {
+            self.report.anomalies.push(Anomaly::DuplicateToken {
+                rand,
+                seq_id,
+                first_txn: *first_txn,
+                txn: ftx.tx_id,
+                block_sid,
+            });
+        } else {
+            self.tokens.insert((rand, seq_id), ftx.tx_id);
+        }
+
+        for op in ftx.txn.body.operations.iter() {
+            if let Operation::IssueAsset(i) = op {
+                let (code, seq_num) = (i.body.code, i.body.seq_num);
+                if let Some(prev_seq_num) = self.seq_nums.get(&code) {
+                    if seq_num <= *prev_seq_num {
+                        self.report.anomalies.push(Anomaly::IssuanceSeqNum {
+                            code: code.to_base64(),
+                            seq_num,
+                            prev_seq_num: *prev_seq_num,
+                            txn: ftx.tx_id,
+                            block_sid,
+                        });
+                    }
+                }
+                let last = self.seq_nums.entry(code).or_insert(seq_num);
+                *last = seq_num.max(*last);
+                self.report.issuances += 1;
+            }
+        }
+    }
+
+    /// The report of the blocks audited so far
+    pub fn finish(self) -> ReplayAudit {
+        self.report
+    }
+}
+
+impl LedgerState {
+    /// Audit every committed block of the ledger,
+    /// `on_block` is called with the sid of each block audited.
+    pub fn audit_replay(&self, mut on_block: impl FnMut(usize)) -> Result<ReplayAudit> {
+        let mut auditor = ReplayAuditor::default();
+        for block_sid in 0..self.get_block_count() {
+            let block = self.get_finalized_block(block_sid).c(d!())?;
+            auditor.audit_block(block_sid, &block.txns);
+            on_block(block_sid);
+        }
+        Ok(auditor.finish())
+    }
+}
+
+/// Audit the blocks of an export stream, which must start at the first block,
+/// or the issuances and tokens of the skipped ones are not known.
+///
+/// `on_block` is called with the sid of each block audited.
+pub fn audit_replay_stream(
+    reader: impl Read,
+    mut on_block: impl FnMut(usize),
+) -> Result<ReplayAudit> {
+    let mut auditor = ReplayAuditor::default();
+    for block in BlockReader::new(reader).c(d!())? {
+        let block = block.c(d!())?;
+        if 0 == auditor.report.blocks && 0 != block.block_sid {
+            return Err(eg!(format!(
+                "the stream starts at block {}, not at the first one",
+                block.block_sid
+            )));
+        }
+        auditor.audit_block(block.block_sid, &block.block.txns);
+        on_block(block.block_sid);
+    }
+    Ok(auditor.finish())
+}
@@ -0,0 +1,348 @@
+//!
+//! Block export in a stable format,
+//! independent of the fbnc storage layout of the ledger.
+//!
+//! A stream is made of:
+//!
+//! - the magic bytes `EXPORT_MAGIC`
+//! - the format version, a little-endian u32, see `EXPORT_VERSION`
+//! - one `ExportHeader` frame
+//! - one `ExportedBlock` frame per block, in block order
+//!
+//! A frame is a little-endian u32 length followed by that many bytes,
+//! the JSON encoding of the item, as returned by the query APIs.
+//!
+//! The proofs of the blocks and txns are against the state commitment of the
+//! exporting ledger, carried by the header, they are checked on import.
+//!
+
+use {
+    super::{api_cache, LedgerState},
+    crate::data_model::{
+        AuthenticatedBlock, AuthenticatedTransaction, FinalizedBlock,
+        StateCommitmentData, Transaction, TxnEffect, TxnSID,
+    },
+    globutils::{HashOf, ProofOf},
+    ruc::*,
+    serde::{de::DeserializeOwned, Deserialize, Serialize},
+    std::{
+        io::{ErrorKind, Read, Write},
+        ops::Range,
+    },
+};
+
+/// Magic bytes starting an export stream
+pub const EXPORT_MAGIC: [u8; 4] = *b"FBLK";
+
+/// Version of the export format
+pub const EXPORT_VERSION: u32 = 1;
+
+/// Frames above this length are rejected on import
+const MAX_FRAME_LEN: u32 = 256 * 1024 * 1024;
+
+/// First frame of an export stream
+#[allow(missing_docs)]
+#[derive(Clone, Deserialize, Serialize)]
+pub struct ExportHeader {
+    /// sids of the exported blocks, the end excluded
+    pub start: usize,
+    pub end: usize,
+    /// state commitment of the exporting ledger, the proofs are against it
+    pub state_commitment_data: StateCommitmentData,
+    pub state_commitment: HashOf<Option<StateCommitmentData>>,
+}
+
+/// A finalized block along with its proofs
+#[allow(missing_docs)]
+#[derive(Clone, Deserialize, Serialize)]
+pub struct ExportedBlock {
+    pub block_sid: usize,
+    /// tendermint height and time of the block, if known by the api cache
+    pub height: Option<u64>,
+    pub time: Option<i64>,
+    pub block: FinalizedBlock,
+    pub block_inclusion_proof: ProofOf<Vec<Transaction>>,
+    /// in the order of `block.txns`
+    pub txn_inclusion_proofs: Vec<ProofOf<(TxnSID, Transaction)>>,
+}
+
+impl ExportedBlock {
+    /// Check the proofs of the block and its txns against a header
+    pub fn is_valid(&self, header: &ExportHeader) -> bool {
+        if self.txn_inclusion_proofs.len() != self.block.txns.len() {
+            return false;
+        }
+
+        let block = AuthenticatedBlock {
+            block: self.block.clone(),
+            block_inclusion_proof: self.block_inclusion_proof.clone(),
+            state_commitment_data: header.state_commitment_data.clone(),
+            state_commitment: header.state_commitment.clone(),
+        };
+        if !block.is_valid(header.state_commitment.clone()) {
+            return false;
+        }
+
+        self.block
+            .txns
+            .iter()
+            .zip(self.txn_inclusion_proofs.iter())
+            .all(|(ftx, proof)| {
+                AuthenticatedTransaction {
+                    finalized_txn: ftx.clone(),
+                    txn_inclusion_proof: proof.clone(),
+                    state_commitment_data: header.state_commitment_data.clone(),
+                    state_commitment: header.state_commitment.clone(),
+                }
+                .is_valid(header.state_commitment.clone())
+            })
+    }
+}
+
+fn write_frame<T: Serialize>(writer: &mut impl Write, item: &T) -> Result<()> {
+    let bytes = serde_json::to_vec(item).c(d!())?;
+    let len = u32::try_from(bytes.len()).c(d!())?;
+    writer.write_all(&len.to_le_bytes()).c(d!())?;
+    writer.write_all(&bytes).c(d!())
+}
+
+/// Read the next frame, None at the end of the stream
+fn read_frame<T: DeserializeOwned>(reader: &mut impl Read) -> Result<Option<T>> {
+    let mut len = [0; 4];
+    match reader.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(e) if ErrorKind::UnexpectedEof == e.kind() => return Ok(None),
+        Err(e) => return Err(eg!(e)),
+    }
+    let len = u32::from_le_bytes(len);
+    if len > MAX_FRAME_LEN {
+        return Err(eg!(format!("frame too long: {}", len)));
+    }
+
+    let mut bytes = vec![0; len as usize];
+    reader.read_exact(&mut bytes).c(d!())?;
+    serde_json::from_slice(&bytes).c(d!()).map(Some)
+}
+
+/// Reader of an export stream, the blocks are checked against the header
+pub struct BlockReader<R: Read> {
+    reader: R,
+    header: ExportHeader,
+    next_sid: usize,
+}
+
+impl<R: Read> BlockReader<R> {
+    /// Read the magic bytes, the version and the header of a stream
+    pub fn new(mut reader: R) -> Result<Self> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic).c(d!())?;
+        if magic != EXPORT_MAGIC {
+            return Err(eg!("not a block export stream"));
+        }
+        let mut version = [0; 4];
+        reader.read_exact(&mut version).c(d!())?;
+        let version = u32::from_le_bytes(version);
+        if version != EXPORT_VERSION {
+            return Err(eg!(format!("unsupported export version: {}", version)));
+        }
+
+        let header: ExportHeader = read_frame(&mut reader)?.c(d!("missing header"))?;
+        if header.state_commitment != header.state_commitment_data.compute_commitment() {
+            return Err(eg!("invalid state commitment"));
+        }
+
+        Ok(BlockReader {
+            reader,
+            next_sid: header.start,
+            header,
+        })
+    }
+
+    #[allow(missing_docs)]
+    pub fn header(&self) -> &ExportHeader {
+        &self.header
+    }
+
+    fn next_block(&mut self) -> Result<Option<ExportedBlock>> {
+        let block = match read_frame::<ExportedBlock>(&mut self.reader).c(d!())? {
+            Some(block) => block,
+            None if self.next_sid == self.header.end => return Ok(None),
+            None => return Err(eg!(format!("truncated at block {}", self.next_sid))),
+        };
+
+        if block.block_sid != self.next_sid || block.block_sid >= self.header.end {
+            return Err(eg!(format!(
+                "unexpected block: {}, expected: {}",
+                block.block_sid, self.next_sid
+            )));
+        }
+        if !block.is_valid(&self.header) {
+            return Err(eg!(format!("invalid proofs of block {}", block.block_sid)));
+        }
+
+        self.next_sid += 1;
+        Ok(Some(block))
+    }
+}
+
+impl<R: Read> Iterator for BlockReader<R> {
+    type Item = Result<ExportedBlock>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_block().transpose()
+    }
+}
+
+impl LedgerState {
+    /// Write the blocks of `range` into an export stream,
+    /// returns the number of blocks written
+    pub fn export_blocks(
+        &self,
+        range: Range<usize>,
+        writer: &mut impl Write,
+    ) -> Result<usize> {
+        let end = range.end.min(self.get_block_count());
+        let start = range.start.min(end);
+        let state_commitment_data = self
+            .status
+            .state_commitment_data
+            .clone()
+            .c(d!("no block committed"))?;
+
+        writer.write_all(&EXPORT_MAGIC).c(d!())?;
+        writer.write_all(&EXPORT_VERSION.to_le_bytes()).c(d!())?;
+        write_frame(
+            writer,
+            &ExportHeader {
+                start,
+                end,
+                state_commitment: state_commitment_data.compute_commitment(),
+                state_commitment_data,
+            },
+        )
+        .c(d!())?;
+
+        for block_sid in start..end {
+            let block = self.get_finalized_block(block_sid).c(d!())?;
+            let block_inclusion_proof = ProofOf::new(
+                self.block_merkle
+                    .read()
+                    .get_proof(block.merkle_id, 0)
+                    .c(d!())?,
+            );
+            let txn_inclusion_proofs = block
+                .txns
+                .iter()
+                .map(|ftx| {
+                    self.txn_merkle
+                        .read()
+                        .get_proof(ftx.merkle_id, 0)
+                        .c(d!())
+                        .map(ProofOf::new)
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            let height = self.api_cache.as_ref().and_then(|c| {
+                block
+                    .txns
+                    .first()
+                    .and_then(|ftx| c.txn_sid_to_height.get(&ftx.tx_id))
+            });
+            let time = self
+                .api_cache
+                .as_ref()
+                .and_then(|c| height.and_then(|h| c.height_to_time.get(&h)));
+
+            write_frame(
+                writer,
+                &ExportedBlock {
+                    block_sid,
+                    height,
+                    time,
+                    block,
+                    block_inclusion_proof,
+                    txn_inclusion_proofs,
+                },
+            )
+            .c(d!())?;
+        }
+
+        writer.flush().c(d!())?;
+        Ok(end - start)
+    }
+
+    /// Re-apply the blocks of an export stream on top of this ledger,
+    /// the stream must start at its block count, returns the number of blocks applied.
+    ///
+    /// The txns are applied without being checked against the ledger status,
+    /// as their proofs are checked against the commitment of the exporting ledger.
+    pub fn import_blocks(&mut self, reader: impl Read) -> Result<usize> {
+        let mut n = 0;
+        for block in BlockReader::new(reader).c(d!())? {
+            self.import_block(block.c(d!())?).c(d!())?;
+            n += 1;
+        }
+        Ok(n)
+    }
+
+    fn import_block(&mut self, block: ExportedBlock) -> Result<()> {
+        self.apply_exported_block(&block, true).c(d!())?;
+        api_cache::update_api_cache(self).c(d!())
+    }
+
+    /// Apply the txns of an exported block as a new block of this ledger,
+    /// in the order of a node committing it: the staking height is set before
+    /// the block, the tendermint height and time after it.
+    ///
+    /// The txns are checked against the ledger status unless `trusted`.
+    pub(super) fn apply_exported_block(
+        &mut self,
+        block: &ExportedBlock,
+        trusted: bool,
+    ) -> Result<()> {
+        if block.block_sid != self.get_block_count() {
+            return Err(eg!(format!(
+                "unexpected block: {}, local block count: {}",
+                block.block_sid,
+                self.get_block_count()
+            )));
+        }
+
+        if let Some(height) = block.height {
+            self.get_staking_mut().set_custom_block_height(height);
+        }
+
+        let mut effect = self.start_block().c(d!())?;
+        let mut expected = vec![];
+        for ftx in block.block.txns.iter() {
+            let txe = TxnEffect::compute_effect(ftx.txn.clone()).c(d!())?;
+            let tmp_sid = if trusted {
+                self.apply_trusted_transaction(&mut effect, txe).c(d!())?
+            } else {
+                self.apply_transaction(&mut effect, txe)
+                    .c(d!(format!("txn {:?} rejected", ftx.tx_id)))?
+            };
+            expected.push((tmp_sid, ftx.tx_id));
+        }
+        let tsm = self.finish_block(effect).c(d!())?;
+
+        for (tmp_sid, txn_sid) in expected {
+            let (local_sid, _) = tsm.get(&tmp_sid).c(d!())?;
+            if *local_sid != txn_sid {
+                return Err(eg!(format!(
+                    "diverged from the export: txn {:?} applied as {:?}",
+                    txn_sid, local_sid
+                )));
+            }
+        }
+
+        if let Some(height) = block.height {
+            self.set_tendermint_height(height);
+        }
+        if let Some(time) = block.time {
+            self.set_tendermint_time(time);
+        }
+
+        Ok(())
+    }
+}
@@ -155,6 +155,7 @@ pub fn create_issue_and_transfer_txn(
                 id: None,
                 record: BlindAssetRecord::from_noah(&ba),
                 lien: None,
+                lock: None,
             },
             None,
         )],
@@ -243,6 +244,7 @@ pub fn create_issue_and_transfer_txn_with_asset_tracing(
                 id: None,
                 record: BlindAssetRecord::from_noah(&ba),
                 lien: None,
+                lock: None,
             },
             None,
         )],
@@ -327,6 +329,7 @@ pub fn create_issuance_txn(
                 id: None,
                 record: BlindAssetRecord::from_noah(&ba),
                 lien: None,
+                lock: None,
             },
             None,
         )],
@@ -0,0 +1,486 @@
+//!
+//! Key-value backends of the flat api cache maps.
+//!
+//! The maps are kept in fbnc by default, `--api-cache-backend=rocksdb` moves
+//! the large flat ones (txo and txn indexes, owner memos, spent utxos)
+//! into a RocksDB instance of their own, see `ApiCache::with_backend`.
+//!
+//! All the maps of an api cache share one `State<RocksDB>`, each of them under
+//! a key prefix of its own. The writes are buffered in the session cache of the
+//! state and committed in one batch per block, when the api cache is flushed.
+//!
+//! The maps are not moved from one backend to the other, a node switching its
+//! backend has to rebuild its api cache, e.g. from an api cache snapshot.
+//!
+//! Like the fbnc ones, a RocksDB map is serialized as the path of its database,
+//! which is reopened, or shared if already open, on deserialization.
+//!
+
+use {
+    super::VERSION_WINDOW,
+    crate::{
+        data_model::{ATxoSID, TxnIDHash, TxnSID, TxoSID, XfrAddress},
+        staking::BlockHeight,
+    },
+    bincode::Options,
+    config::abci::global_cfg::CFG,
+    fbnc::{Mapx, Mapxnk},
+    fin_db::RocksDB,
+    lazy_static::lazy_static,
+    parking_lot::{Mutex, RwLock},
+    ruc::*,
+    serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize, Serializer},
+    std::{
+        collections::{BTreeMap, HashMap, VecDeque},
+        iter::Peekable,
+        marker::PhantomData,
+        sync::{Arc, Weak},
+    },
+    storage::{
+        db::IterOrder,
+        state::{ChainState, State},
+    },
+    zei::OwnerMemo,
+};
+
+/// The RocksDB state shared by the maps of an api cache
+pub type KvState = Arc<RwLock<State<RocksDB>>>;
+
+/// Count of the entries read from the database at once by an iterator
+const ITER_BATCH: usize = 1024;
+
+lazy_static! {
+    /// The open states by path, a database can only be opened once
+    static ref KV_STATES: Mutex<HashMap<String, Weak<RwLock<State<RocksDB>>>>> =
+        Mutex::new(HashMap::new());
+}
+
+/// Backend of the flat api cache maps
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ApiCacheBackend {
+    #[allow(missing_docs)]
+    Fbnc,
+    #[allow(missing_docs)]
+    RocksDB,
+}
+
+impl ApiCacheBackend {
+    /// Parse a backend name, `fbnc` or `rocksdb`
+    pub fn parse(name: &str) -> Result<Self> {
+        match name.trim() {
+            "fbnc" => Ok(ApiCacheBackend::Fbnc),
+            "rocksdb" => Ok(ApiCacheBackend::RocksDB),
+            n => Err(eg!(format!(
+                "unknown api cache backend: {}, expected: fbnc, rocksdb",
+                n
+            ))),
+        }
+    }
+
+    /// Backend set by `--api-cache-backend`
+    pub fn from_cfg() -> Result<Self> {
+        CFG.api_cache_backend
+            .as_deref()
+            .map_or(Ok(ApiCacheBackend::Fbnc), Self::parse)
+    }
+}
+
+/// Open or create the RocksDB state of an api cache,
+/// the one already open at `path` is shared
+pub(crate) fn open_kv_state(path: &str) -> Result<KvState> {
+    let mut states = KV_STATES.lock();
+    if let Some(state) = states.get(path).and_then(Weak::upgrade) {
+        return Ok(state);
+    }
+
+    let fdb = RocksDB::open(path).c(d!("failed to open db"))?;
+    let cs = Arc::new(RwLock::new(ChainState::new(
+        fdb,
+        "api_cache_db".to_string(),
+        VERSION_WINDOW,
+        false,
+    )));
+    let state = Arc::new(RwLock::new(State::new(cs, false)));
+    states.insert(path.to_owned(), Arc::downgrade(&state));
+    Ok(state)
+}
+
+/// Write the buffered changes of a state in one batch
+pub(crate) fn commit_kv_state(state: &KvState) -> Result<()> {
+    let mut state = state.write();
+    let height = state.chain_state().read().height().c(d!())?;
+    state.commit(height + 1).c(d!()).map(|_| ())
+}
+
+/// The operations of the api cache on a flat map
+#[allow(missing_docs)]
+pub trait KvMap<K, V> {
+    fn get(&self, key: &K) -> Option<V>;
+    fn insert(&mut self, key: K, value: V) -> Option<V>;
+    fn remove(&mut self, key: &K) -> Option<V>;
+    fn contains_key(&self, key: &K) -> bool;
+    fn iter(&self) -> Box<dyn Iterator<Item = (K, V)> + '_>;
+    fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        0 == self.len()
+    }
+}
+
+macro_rules! impl_fbnc_kv_map {
+    ($($map: ident<$k: ty, $v: ty>),+ $(,)?) => {
+        $(
+            impl KvMap<$k, $v> for $map<$k, $v> {
+                fn get(&self, key: &$k) -> Option<$v> {
+                    $map::get(self, key)
+                }
+                fn insert(&mut self, key: $k, value: $v) -> Option<$v> {
+                    $map::insert(self, key, value)
+                }
+                fn remove(&mut self, key: &$k) -> Option<$v> {
+                    $map::remove(self, key)
+                }
+                fn contains_key(&self, key: &$k) -> bool {
+                    $map::contains_key(self, key)
+                }
+                fn iter(&self) -> Box<dyn Iterator<Item = ($k, $v)> + '_> {
+                    Box::new($map::iter(self))
+                }
+                fn len(&self) -> usize {
+                    $map::len(self)
+                }
+            }
+        )+
+    };
+}
+
+impl_fbnc_kv_map!(
+    Mapxnk<TxoSID, OwnerMemo>,
+    Mapxnk<TxoSID, XfrAddress>,
+    Mapxnk<TxoSID, TxnIDHash>,
+    Mapx<ATxoSID, TxnIDHash>,
+    Mapxnk<TxnSID, String>,
+    Mapx<String, TxnSID>,
+    Mapxnk<TxnSID, BlockHeight>,
+    Mapxnk<TxoSID, (TxnSID, BlockHeight)>,
+);
+
+/// Keys are fixed-width big-endian, so that the numeric sids iterate in order
+fn key_codec() -> impl Options {
+    bincode::DefaultOptions::new()
+        .with_big_endian()
+        .with_fixint_encoding()
+}
+
+/// A flat map in a `KvState`, under the key prefix `api_cache/{name}/`,
+/// its count of entries is kept under `api_cache_len/{name}`
+pub struct RocksMap<K, V> {
+    state: KvState,
+    path: String,
+    name: String,
+    prefix: Vec<u8>,
+    len_key: Vec<u8>,
+    _pd: PhantomData<(K, V)>,
+}
+
+impl<K, V> Clone for RocksMap<K, V> {
+    fn clone(&self) -> Self {
+        RocksMap::new(Arc::clone(&self.state), &self.path, &self.name)
+    }
+}
+
+impl<K, V> RocksMap<K, V> {
+    /// The map `name` in the state opened at `path`
+    pub fn new(state: KvState, path: &str, name: &str) -> Self {
+        RocksMap {
+            state,
+            path: path.to_owned(),
+            name: name.to_owned(),
+            prefix: format!("api_cache/{name}/").into_bytes(),
+            len_key: format!("api_cache_len/{name}").into_bytes(),
+            _pd: PhantomData,
+        }
+    }
+}
+
+/// Serialized form of a `RocksMap`
+#[derive(Deserialize, Serialize)]
+struct RocksMapPath {
+    path: String,
+    name: String,
+}
+
+impl<K, V> Serialize for RocksMap<K, V> {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        RocksMapPath {
+            path: self.path.clone(),
+            name: self.name.clone(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, K, V> Deserialize<'de> for RocksMap<K, V> {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        let p = RocksMapPath::deserialize(deserializer)?;
+        let state = open_kv_state(&p.path)
+            .map_err(|e| serde::de::Error::custom(e.to_string()))?;
+        Ok(RocksMap::new(state, &p.path, &p.name))
+    }
+}
+
+impl<K, V> RocksMap<K, V>
+where
+    K: Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+{
+    fn raw_key(&self, key: &K) -> Vec<u8> {
+        let mut raw = self.prefix.clone();
+        raw.extend(pnk!(key_codec().serialize(key)));
+        raw
+    }
+
+    fn set_len(&self, len: usize) {
+        pnk!(self
+            .state
+            .write()
+            .set(&self.len_key, (len as u64).to_be_bytes().to_vec()));
+    }
+
+    /// The count of entries, counted once if the map predates the counter
+    fn stored_len(&self) -> usize {
+        let len = pnk!(self.state.read().get(&self.len_key));
+        match len {
+            Some(v) => u64::from_be_bytes(pnk!(v.as_slice().try_into())) as usize,
+            None => {
+                let mut entries = self.iter_entries();
+                let len = std::iter::from_fn(|| entries.next_raw()).count();
+                self.set_len(len);
+                len
+            }
+        }
+    }
+
+    /// The entries in key order, the buffered changes included,
+    /// read from the database by batches of `ITER_BATCH`
+    fn iter_entries(&self) -> RocksIter<'_, K, V> {
+        let mut cached = BTreeMap::new();
+        self.state.read().iterate_cache(&self.prefix, &mut cached);
+
+        // '0' follows the '/' ending the prefix
+        let mut upper = self.prefix.clone();
+        *pnk!(upper.last_mut()) = b'0';
+
+        RocksIter {
+            map: self,
+            lower: Some(self.prefix.clone()),
+            upper,
+            batch: VecDeque::new(),
+            cached: cached.into_iter().peekable(),
+        }
+    }
+}
+
+/// Iterator of a `RocksMap`, merging the entries of the database,
+/// scanned batch by batch without committing the state,
+/// with the changes buffered in its session cache
+struct RocksIter<'a, K, V> {
+    map: &'a RocksMap<K, V>,
+    /// start of the next batch, `None` once the database is scanned
+    lower: Option<Vec<u8>>,
+    upper: Vec<u8>,
+    batch: VecDeque<(Vec<u8>, Vec<u8>)>,
+    cached: Peekable<std::collections::btree_map::IntoIter<Vec<u8>, Option<Vec<u8>>>>,
+}
+
+impl<'a, K, V> RocksIter<'a, K, V> {
+    fn fill_batch(&mut self) {
+        let lower = match self.lower.take() {
+            Some(l) => l,
+            None => return,
+        };
+        let batch = &mut self.batch;
+        self.map.state.read().iterate(
+            &lower,
+            &self.upper,
+            IterOrder::Asc,
+            &mut |(k, v)| {
+                batch.push_back((k.to_vec(), v.to_vec()));
+                batch.len() >= ITER_BATCH
+            },
+        );
+        if batch.len() >= ITER_BATCH {
+            // the smallest key after the last one read
+            let mut next = pnk!(batch.back()).0.clone();
+            next.push(0);
+            self.lower = Some(next);
+        }
+    }
+
+    /// The next raw entry, a buffered change taking precedence
+    /// over the database entry of the same key
+    fn next_raw(&mut self) -> Option<(Vec<u8>, Vec<u8>)> {
+        loop {
+            if self.batch.is_empty() {
+                self.fill_batch();
+            }
+            let from_cache = match (self.batch.front(), self.cached.peek()) {
+                (None, None) => return None,
+                (Some(_), None) => false,
+                (None, Some(_)) => true,
+                (Some((k, _)), Some((ck, _))) => {
+                    if ck <= k {
+                        if ck == k {
+                            self.batch.pop_front();
+                        }
+                        true
+                    } else {
+                        false
+                    }
+                }
+            };
+            if !from_cache {
+                return self.batch.pop_front();
+            }
+            // a removed entry is buffered as `None`
+            if let (k, Some(v)) = pnk!(self.cached.next()) {
+                return Some((k, v));
+            }
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for RocksIter<'a, K, V>
+where
+    K: DeserializeOwned,
+    V: DeserializeOwned,
+{
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_raw().map(|(k, v)| {
+            let key = pnk!(key_codec().deserialize(&k[self.map.prefix.len()..]));
+            (key, pnk!(serde_json::from_slice(&v)))
+        })
+    }
+}
+
+impl<K, V> KvMap<K, V> for RocksMap<K, V>
+where
+    K: Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+{
+    fn get(&self, key: &K) -> Option<V> {
+        pnk!(self.state.read().get(&self.raw_key(key)))
+            .map(|v| pnk!(serde_json::from_slice(&v)))
+    }
+
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let old = self.get(&key);
+        if old.is_none() {
+            self.set_len(self.stored_len() + 1);
+        }
+        pnk!(self
+            .state
+            .write()
+            .set(&self.raw_key(&key), pnk!(serde_json::to_vec(&value))));
+        old
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        let old = self.get(key);
+        if old.is_some() {
+            self.set_len(self.stored_len().saturating_sub(1));
+            pnk!(self.state.write().delete(&self.raw_key(key)));
+        }
+        old
+    }
+
+    fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = (K, V)> + '_> {
+        Box::new(self.iter_entries())
+    }
+
+    fn len(&self) -> usize {
+        self.stored_len()
+    }
+}
+
+/// A flat api cache map, in fbnc or in RocksDB
+#[allow(missing_docs)]
+#[derive(Clone, Deserialize, Serialize)]
+pub enum ApiMap<M, K, V> {
+    Fbnc(M),
+    RocksDB(RocksMap<K, V>),
+}
+
+#[allow(missing_docs)]
+impl<M, K, V> ApiMap<M, K, V>
+where
+    M: KvMap<K, V>,
+    K: Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+{
+    /// The state of the map, if kept in RocksDB
+    pub(crate) fn kv_state(&self) -> Option<&KvState> {
+        match self {
+            ApiMap::Fbnc(_) => None,
+            ApiMap::RocksDB(m) => Some(&m.state),
+        }
+    }
+
+    fn inner(&self) -> &dyn KvMap<K, V> {
+        match self {
+            ApiMap::Fbnc(m) => m,
+            ApiMap::RocksDB(m) => m,
+        }
+    }
+
+    fn inner_mut(&mut self) -> &mut dyn KvMap<K, V> {
+        match self {
+            ApiMap::Fbnc(m) => m,
+            ApiMap::RocksDB(m) => m,
+        }
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.inner().get(key)
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.inner_mut().insert(key, value)
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        self.inner_mut().remove(key)
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.inner().contains_key(key)
+    }
+
+    pub fn iter(&self) -> Box<dyn Iterator<Item = (K, V)> + '_> {
+        self.inner().iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        0 == self.len()
+    }
+}
+
+/// A flat api cache map with fbnc `Mapx` as its default backend
+pub type ApiMapx<K, V> = ApiMap<Mapx<K, V>, K, V>;
+
+/// A flat api cache map with fbnc `Mapxnk` as its default backend
+pub type ApiMapxnk<K, V> = ApiMap<Mapxnk<K, V>, K, V>;
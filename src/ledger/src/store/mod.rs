@@ -3,9 +3,18 @@
 //!
 
 pub mod api_cache;
+pub mod audit;
+pub mod export;
 pub mod helpers;
+pub mod kv;
+pub mod replay;
+pub mod snapshot;
+pub mod stats;
+pub mod supply;
 mod test;
+pub mod tiering;
 pub mod utils;
+pub mod validators;
 
 pub use fbnc;
 
@@ -15,9 +24,10 @@ use {
             ATxoSID, AnonStateCommitmentData, AssetType, AssetTypeCode, AssetTypePrefix,
             AuthenticatedBlock, AuthenticatedTransaction, AuthenticatedUtxo,
             AuthenticatedUtxoStatus, BlockEffect, BlockSID, FinalizedBlock,
-            FinalizedTransaction, IssuerPublicKey, Operation, OutputPosition,
-            StateCommitmentData, Transaction, TxnEffect, TxnSID, TxnTempSID, TxoSID,
-            UnAuthenticatedUtxo, Utxo, UtxoStatus, BLACK_HOLE_PUBKEY,
+            FinalizedTransaction, IssuerPublicKey, Operation, OutputLock,
+            OutputPosition, StateCommitmentData, Transaction, TxnEffect, TxnSID,
+            TxnTempSID, TxoRef, TxoSID, UnAuthenticatedUtxo, Utxo, UtxoStatus,
+            BLACK_HOLE_PUBKEY,
         },
         staking::{
             Amount, Power, Staking, TendermintAddrRef, FF_PK_EXTRA_120_0000, FF_PK_LIST,
@@ -43,6 +53,7 @@ use {
     sha2::Sha512,
     sliding_set::SlidingSet,
     sparse_merkle_tree::{Key, SmtMap256},
+    stats::BlockStats,
     std::{
         collections::{BTreeMap, HashMap, HashSet},
         env,
@@ -56,6 +67,7 @@ use {
         state::{ChainState, State},
         store::{ImmutablePrefixedStore, PrefixedStore},
     },
+    tiering::ColdStore,
     zei::{
         noah_accumulators::merkle_tree::{
             ImmutablePersistentMerkleTree, PersistentMerkleTree, Proof, TreePath,
@@ -99,6 +111,8 @@ pub struct LedgerState {
     pub blocks: Vecx<FinalizedBlock>,
     /// <tx id> => [<block id>, <tx idx in block>]
     pub tx_to_block_location: Mapxnk<TxnSID, [usize; 2]>,
+    /// <block id> => fee and utilization stats of the block
+    pub block_stats: Mapxnk<u64, BlockStats>,
     /// cache used in APIs
     pub api_cache: Option<ApiCache>,
 
@@ -117,6 +131,10 @@ pub struct LedgerState {
     abar_state: Arc<RwLock<State<RocksDB>>>,
     // Sparse Merkle Tree to hold nullifier Set
     nullifier_set: Arc<RwLock<SmtMap256<RocksDB>>>,
+    // Backend of the blocks and spent utxos migrated out of fbnc
+    cold_store: Option<Arc<dyn ColdStore>>,
+    // Number of recent versions whose utxo bitmap is kept, none if disabled
+    utxo_history: Option<u64>,
 }
 
 impl LedgerState {
@@ -161,6 +179,21 @@ impl LedgerState {
             })
     }
 
+    /// Apply a tx already validated and committed by another node,
+    /// the checks against the ledger status are skipped,
+    /// used by query replicas following a primary node
+    pub fn apply_trusted_transaction(
+        &self,
+        block: &mut BlockEffect,
+        txe: TxnEffect,
+    ) -> Result<TxnTempSID> {
+        let tx = txe.txn.clone();
+        block.add_txn_effect(txe).c(d!()).map(|tmpid| {
+            block.staking_simulator.coinbase_check_and_pay(&tx);
+            tmpid
+        })
+    }
+
     // Update the UTXO bitmap
     fn update_utxo_map(
         &mut self,
@@ -283,6 +316,8 @@ impl LedgerState {
             self.tx_to_block_location
                 .insert(tx.tx_id, [block_idx, tx_idx]);
         });
+        self.block_stats
+            .insert(block_idx as u64, BlockStats::new(height, &tx_block));
 
         self.blocks.push(FinalizedBlock {
             txns: tx_block,
@@ -318,6 +353,7 @@ impl LedgerState {
         self.update_utxo_map(base_sid, max_sid, &block.temp_sids, &tsm)
             .c(d!())
             .and_then(|_| self.update_state(block, &tsm, backup_next_txn_sid).c(d!()))
+            .and_then(|_| self.migrate_cold_data_by_cfg().c(d!()))
             .map(|_| tsm)
     }
 
@@ -385,6 +421,18 @@ impl LedgerState {
         self.status.td_commit_height
     }
 
+    /// Set the time of the tendermint block being committed, in seconds
+    #[inline(always)]
+    pub fn set_tendermint_time(&mut self, tendermint_t: i64) {
+        self.status.td_commit_time = tendermint_t;
+    }
+
+    /// Get the time of the last committed tendermint block, in seconds
+    #[inline(always)]
+    pub fn get_tendermint_time(&self) -> i64 {
+        self.status.td_commit_time
+    }
+
     #[inline(always)]
     #[allow(missing_docs)]
     pub fn get_next_txn(&self) -> TxnSID {
@@ -463,6 +511,18 @@ impl LedgerState {
         self.status
             .state_commitment_versions
             .push(state_commitment_data.compute_commitment());
+        if let Some(n) = self.utxo_history {
+            let version = self.status.state_commitment_versions.len() as u64;
+            let utxo_map_bytes = self.utxo_map.read().serialize(0);
+            self.status
+                .utxo_map_hist
+                .insert(version, (state_commitment_data.clone(), utxo_map_bytes));
+            // also catches up with a smaller `n` than the previous runs
+            let mut stale = version.saturating_sub(n);
+            while 0 < stale && self.status.utxo_map_hist.remove(&stale).is_some() {
+                stale -= 1;
+            }
+        }
         self.status.state_commitment_data = Some(state_commitment_data);
 
         // Commit Anon tree changes here following Tendermint protocol
@@ -619,6 +679,7 @@ impl LedgerState {
 
         let blocks_path = prefix.clone() + "blocks";
         let tx_to_block_location_path = prefix.clone() + "tx_to_block_location";
+        let block_stats_path = prefix.clone() + "block_stats";
 
         let mut abar_state = LedgerState::init_abar_state(&abar_store_path).c(d!())?;
 
@@ -636,15 +697,32 @@ impl LedgerState {
             )),
             blocks: new_vecx!(&blocks_path),
             tx_to_block_location: new_mapxnk!(&tx_to_block_location_path),
+            block_stats: new_mapxnk!(&block_stats_path),
             utxo_map: Arc::new(RwLock::new(
                 LedgerState::init_utxo_map(&utxo_map_path).c(d!())?,
             )),
             block_ctx: Some(BlockEffect::default()),
-            api_cache: alt!(*KEEP_HIST, Some(ApiCache::new(&prefix)), None),
+            api_cache: alt!(
+                *KEEP_HIST,
+                Some(
+                    ApiCache::new(&prefix)
+                        .with_wal(format!("{}/{}api_cache_wal", basedir, &prefix))
+                        .with_backend(
+                            kv::ApiCacheBackend::from_cfg().c(d!())?,
+                            &format!("{}/{}api_cache_db", basedir, &prefix),
+                        )
+                        .c(d!())?
+                ),
+                None
+            ),
             abar_state: Arc::new(RwLock::new(abar_state)),
             nullifier_set: Arc::new(RwLock::new(
                 LedgerState::init_nullifier_smt(&nullifier_store_path).c(d!())?,
             )),
+            cold_store: tiering::cold_store_from_cfg()
+                .c(d!())?
+                .map(|s| Arc::new(s) as Arc<dyn ColdStore>),
+            utxo_history: CFG.ledger_utxo_history.filter(|n| 0 < *n),
         };
 
         ledger.status.refresh_data();
@@ -661,6 +739,12 @@ impl LedgerState {
         ledger.get_staking_mut().set_custom_block_height(h);
         omit!(ledger.utxo_map.write().compute_checksum());
         ledger.fast_invariant_check().c(d!())?;
+        ledger.status.init_burned_amounts();
+        if 0 < ledger.status.cold_block_count && ledger.cold_store.is_none() {
+            return Err(eg!("blocks have been migrated, a cold store is required"));
+        }
+        api_cache::replay_wal(&mut ledger).c(d!())?;
+        api_cache::rollback_to_ledger(&mut ledger).c(d!())?;
 
         flush_data();
 
@@ -917,7 +1001,7 @@ impl LedgerState {
 
     /// Get a spent utxo along with the transaction, spent status and commitment data which it belongs
     pub fn get_spent_utxo(&self, addr: TxoSID) -> Option<AuthenticatedUtxo> {
-        let utxo = self.status.get_spent_utxo(addr);
+        let utxo = self.get_spent_utxo_through(addr);
         if let Some(utxo) = utxo {
             let txn_location = self.status.txo_to_txn_location.get(&addr).unwrap();
             let authenticated_txn = self.get_transaction(txn_location.0).unwrap();
@@ -940,7 +1024,7 @@ impl LedgerState {
     /// Get a spent utxo along with the transaction which it belongs
     /// Avoid ledger query operation to reduce latency
     pub fn get_spent_utxo_light(&self, addr: TxoSID) -> Option<UnAuthenticatedUtxo> {
-        let utxo = self.status.get_spent_utxo(addr);
+        let utxo = self.get_spent_utxo_through(addr);
         if let Some(utxo) = utxo {
             let txn_location = self.status.txo_to_txn_location.get(&addr).unwrap();
             let txn = self.get_transaction_light(txn_location.0).unwrap();
@@ -1161,6 +1245,50 @@ impl LedgerState {
         }
     }
 
+    /// Keep the utxo bitmaps of the last `versions` state commitment versions,
+    /// none to stop keeping them
+    pub fn set_utxo_history(&mut self, versions: Option<u64>) {
+        self.utxo_history = versions.filter(|n| 0 < *n);
+    }
+
+    /// Get the status of a utxo at a past state commitment version, that is
+    /// a block commit count as in `get_state_commitment_at_block_height`,
+    /// only the versions kept by `--ledger-utxo-history` are served
+    pub fn get_utxo_status_at_version(
+        &self,
+        addr: TxoSID,
+        version: u64,
+    ) -> Result<AuthenticatedUtxoStatus> {
+        if self.utxo_history.is_none() {
+            return Err(eg!("utxo history is not enabled"));
+        }
+        let (state_commitment_data, utxo_map_bytes) = self
+            .status
+            .utxo_map_hist
+            .get(&version)
+            .c(d!("the utxo map of this version is not retained"))?;
+
+        let mut status = AuthenticatedUtxoStatus {
+            status: UtxoStatus::Nonexistent,
+            state_commitment: state_commitment_data.compute_commitment(),
+            state_commitment_data,
+            utxo_sid: addr,
+            utxo_map_bytes: None,
+        };
+        if addr.0 >= status.state_commitment_data.txo_count {
+            return Ok(status);
+        }
+
+        let utxo_map = SparseMap::new(&utxo_map_bytes).c(d!())?;
+        status.status = if utxo_map.query(addr.0).c(d!())? {
+            UtxoStatus::Unspent
+        } else {
+            UtxoStatus::Spent
+        };
+        status.utxo_map_bytes = Some(utxo_map_bytes);
+        Ok(status)
+    }
+
     #[inline(always)]
     #[allow(missing_docs)]
     pub fn get_staking(&self) -> &Staking {
@@ -1190,8 +1318,7 @@ impl LedgerState {
             .get(&id)
             .c(d!())
             .and_then(|[block_idx, tx_idx]| {
-                self.blocks
-                    .get(block_idx)
+                self.get_finalized_block(block_idx)
                     .c(d!())
                     .and_then(|b| b.txns.get(tx_idx).cloned().c(d!()))
             })
@@ -1199,7 +1326,7 @@ impl LedgerState {
 
     /// Query the Block by a BlockSID along with its proof data
     pub fn get_block(&self, addr: BlockSID) -> Option<AuthenticatedBlock> {
-        match self.blocks.get(addr.0) {
+        match self.get_finalized_block(addr.0).ok() {
             None => None,
             Some(finalized_block) => {
                 let block_inclusion_proof = ProofOf::new(
@@ -1280,6 +1407,11 @@ pub struct LedgerStatus {
     /// The BitDigest at index i is the state commitment of the ledger at block height  i + 1.
     #[serde(default = "default_status_state_commitment_versions")]
     state_commitment_versions: Vecx<HashOf<Option<StateCommitmentData>>>,
+    /// State commitment data and utxo bitmap of the recent versions, keyed like
+    /// `state_commitment_versions` but starting at 1, so that past utxo statuses
+    /// can be proven, see `LedgerState::set_utxo_history`
+    #[serde(default = "default_status_utxo_map_hist")]
+    utxo_map_hist: Mapxnk<u64, (StateCommitmentData, Vec<u8>)>,
     /// Anon state commitment versions
     #[serde(default = "default_status_anon_state_commitment_versions")]
     anon_state_commitment_versions: Vecx<HashOf<Option<AnonStateCommitmentData>>>,
@@ -1292,6 +1424,9 @@ pub struct LedgerStatus {
     /// Issuance amounts for assets with limits
     #[serde(default = "default_status_issuance_amounts")]
     issuance_amounts: Mapx<AssetTypeCode, u64>,
+    /// Nonconfidential amounts sent to `BLACK_HOLE_PUBKEY`, by asset type
+    #[serde(default = "default_status_burned_amounts")]
+    burned_amounts: Mapx<AssetTypeCode, u64>,
     /// Should be equal to the count of transactions
     #[serde(default = "default_status_next_txn")]
     next_txn: TxnSID,
@@ -1322,6 +1457,12 @@ pub struct LedgerStatus {
     /// tendermint commit height
     #[serde(default = "default_status_td_commit_height")]
     td_commit_height: u64,
+    /// tendermint commit time, in seconds
+    #[serde(default = "default_status_td_commit_time")]
+    td_commit_time: i64,
+    /// number of blocks migrated to the cold store, see `tiering`
+    #[serde(default = "default_status_cold_block_count")]
+    cold_block_count: usize,
 }
 
 impl LedgerStatus {
@@ -1370,6 +1511,74 @@ impl LedgerStatus {
         self.asset_types.get(code)
     }
 
+    /// Whether the issuances are counted under the code of their asset type,
+    /// see `CheckPointConfig::fix_issuance_cap_height`
+    #[inline(always)]
+    fn issuance_cap_fixed(&self) -> bool {
+        CFG.checkpoint.fix_issuance_cap_height <= self.td_commit_height
+    }
+
+    /// Whether the locks of the transfer outputs are accepted and enforced,
+    /// see `CheckPointConfig::output_lock_height`
+    #[inline(always)]
+    fn output_locks_enabled(&self) -> bool {
+        CFG.checkpoint.output_lock_height <= self.td_commit_height
+    }
+
+    /// Whether the co-signatures of the threshold locks are enforced,
+    /// see `CheckPointConfig::threshold_lock_height`
+    #[inline(always)]
+    fn threshold_locks_enabled(&self) -> bool {
+        CFG.checkpoint.threshold_lock_height <= self.td_commit_height
+    }
+
+    /// Nonconfidential amount issued of an asset type, along with the one
+    /// counted under its derived code before the issuance cap fix
+    fn get_issued_amount(&self, code: &AssetTypeCode) -> u64 {
+        let derived = AssetTypeCode::from_prefix_and_raw_asset_type_code(
+            AssetTypePrefix::UserDefined,
+            code,
+            &CFG.checkpoint,
+            self.td_commit_height,
+        );
+        let amount = self.issuance_amounts.get(code).unwrap_or(0);
+        if derived == *code {
+            amount
+        } else {
+            amount.saturating_add(self.issuance_amounts.get(&derived).unwrap_or(0))
+        }
+    }
+
+    #[inline(always)]
+    fn get_burned_amount(&self, code: &AssetTypeCode) -> u64 {
+        self.burned_amounts.get(code).unwrap_or(0)
+    }
+
+    /// Count the burns from the utxos of `BLACK_HOLE_PUBKEY`, which are never
+    /// spent, for the ledgers created before they were tracked
+    pub(crate) fn init_burned_amounts(&mut self) {
+        if 0 < self.burned_amounts.len() {
+            return;
+        }
+        let black_hole = XfrPublicKey::from_noah(&BLACK_HOLE_PUBKEY);
+        for sid in self.get_owned_utxos(&black_hole) {
+            let record = match self.utxos.get(&sid) {
+                Some(utxo) => utxo.0.record,
+                None => continue,
+            };
+            if let (Some(amount), Some(val)) = (
+                record.amount.get_amount(),
+                record.asset_type.get_asset_type(),
+            ) {
+                let mut burned = self
+                    .burned_amounts
+                    .entry(AssetTypeCode { val })
+                    .or_insert(0);
+                *burned.deref_mut() = burned.saturating_add(amount);
+            }
+        }
+    }
+
     fn fast_invariant_check(&self) -> Result<()> {
         let cnt_eq =
             self.block_commit_count == self.state_commitment_versions.len() as u64;
@@ -1420,7 +1629,10 @@ impl LedgerStatus {
             txo_to_txn_location: default_status_txo_to_txn_location(),
             ax_txo_to_txn_location: default_status_ax_txo_to_txn_location(),
             issuance_amounts: default_status_issuance_amounts(),
+            burned_amounts: default_status_burned_amounts(),
             state_commitment_versions: default_status_state_commitment_versions(),
+            utxo_map_hist: default_status_utxo_map_hist(),
+            cold_block_count: default_status_cold_block_count(),
             anon_state_commitment_versions:
                 default_status_anon_state_commitment_versions(),
             asset_types: default_status_asset_types(),
@@ -1434,6 +1646,7 @@ impl LedgerStatus {
             block_commit_count: default_status_block_commit_count(),
             staking: default_status_staking(),
             td_commit_height: default_status_td_commit_height(),
+            td_commit_time: default_status_td_commit_time(),
         })
     }
 
@@ -1475,13 +1688,42 @@ impl LedgerStatus {
             }
         }
 
+        // Before the checkpoint, the nodes not knowing the locks would accept
+        // the early spendings of the locked outputs
+        let locks_enabled = self.output_locks_enabled();
+        if !locks_enabled
+            && txn_effect
+                .txos
+                .iter()
+                .flatten()
+                .any(|txo| txo.lock.is_some())
+        {
+            return Err(eg!("Output locks are not enabled yet"));
+        }
+        let thresholds_enabled = self.threshold_locks_enabled();
+        if !thresholds_enabled
+            && txn_effect
+                .txos
+                .iter()
+                .flatten()
+                .any(|txo| matches!(txo.lock, Some(OutputLock::Threshold(_))))
+        {
+            return Err(eg!("Threshold locks are not enabled yet"));
+        }
+
         // 1. Each input must be unspent and correspond to the claimed record
         // 2. Inputs with transfer restrictions can only be owned by the asset issuer
+        // 3. Locked inputs can only be spent once their locks are released
         for (inp_sid, inp_record) in txn_effect.input_txos.iter() {
-            // (1)
+            // (1), the lock is kept by the ledger, not claimed by the input
             let inp_utxo = self.utxos.get(inp_sid).c(d!("Input must be unspent"))?;
             let record = &(inp_utxo.0);
-            if record != inp_record {
+            let matched = if locks_enabled {
+                record.record == inp_record.record && record.lien == inp_record.lien
+            } else {
+                record == inp_record
+            };
+            if !matched {
                 return Err(eg!((format!(
                     "Input must correspond to claimed record: {} != {}",
                     serde_json::to_string(&record).c(d!())?,
@@ -1508,6 +1750,43 @@ impl LedgerStatus {
                     ));
                 }
             }
+            // (3)
+            if let Some(lock) = record.lock.as_ref().filter(|_| locks_enabled) {
+                if !lock.is_released(self.td_commit_height, self.td_commit_time) {
+                    return Err(eg!(format!("Input is locked: {:?}", lock)));
+                }
+            }
+        }
+
+        // Threshold inputs must be co-signed up to the threshold of their lock,
+        // the co-signatures being verified along with the transfer
+        let operations = if thresholds_enabled {
+            txn_effect.txn.body.operations.as_slice()
+        } else {
+            &[]
+        };
+        for op in operations.iter() {
+            if let Operation::TransferAsset(xfr) = op {
+                for (idx, inp) in xfr.body.inputs.iter().enumerate() {
+                    let sid = match inp {
+                        TxoRef::Absolute(sid) => sid,
+                        TxoRef::Relative(_) => continue,
+                    };
+                    if let Some(OutputLock::Threshold(rules)) =
+                        self.utxos.get(sid).and_then(|utxo| utxo.0.lock)
+                    {
+                        let cosigners = xfr
+                            .body_signatures
+                            .iter()
+                            .filter(|sig| Some(idx) == sig.input_idx)
+                            .map(|sig| sig.address.key.to_bytes())
+                            .collect::<HashSet<_>>();
+                        rules
+                            .check_signature_set(&cosigners)
+                            .c(d!(format!("Input {idx} lacks co-signatures")))?;
+                    }
+                }
+            }
         }
 
         // Internally spend inputs with transfer restrictions can only be owned by the asset issuer
@@ -1613,7 +1892,11 @@ impl LedgerStatus {
                 .c(d!())?;
             // (1)
             if let Some(cap) = asset_type.properties.asset_rules.max_units {
-                let current_amount = self.issuance_amounts.get(code).unwrap_or(0);
+                let current_amount = if self.issuance_cap_fixed() {
+                    self.get_issued_amount(code)
+                } else {
+                    self.issuance_amounts.get(code).unwrap_or(0)
+                };
                 if current_amount.checked_add(*amount).c(d!())? > cap {
                     return Err(eg!(("Amount exceeds asset cap")));
                 }
@@ -1778,10 +2061,21 @@ impl LedgerStatus {
             asset.properties.memo = memo;
         }
 
+        // The issuances of a registered asset type are counted under its code,
+        // where the cap checks look them up
+        let cap_fixed = self.issuance_cap_fixed();
+        let issuance_code = |code: AssetTypeCode| {
+            if cap_fixed && self.asset_types.contains_key(&code) {
+                code
+            } else {
+                handle_asset_type_code(code)
+            }
+        };
+
         for (code, amount) in block.issuance_amounts.drain() {
-            let code = handle_asset_type_code(code);
+            let code = issuance_code(code);
             let mut amt = self.issuance_amounts.entry(code).or_insert(0);
-            *amt.deref_mut() += amount;
+            *amt.deref_mut() = amt.saturating_add(amount);
         }
 
         // Add new UTXOs
@@ -1792,6 +2086,7 @@ impl LedgerStatus {
         {
             let mut next_txn = self.next_txn.0;
             let mut next_txo = self.next_txo.0;
+            let black_hole = XfrPublicKey::from_noah(&BLACK_HOLE_PUBKEY);
 
             for (ix, txos) in block.temp_sids.iter().zip(block.txos.drain(..)) {
                 let txn_sid = next_txn;
@@ -1803,6 +2098,19 @@ impl LedgerStatus {
                     let txo_sid = next_txo;
                     next_txo += 1;
                     if let Some(tx_output) = txo {
+                        let record = &tx_output.record;
+                        if record.public_key == black_hole {
+                            if let (Some(amount), Some(val)) = (
+                                record.amount.get_amount(),
+                                record.asset_type.get_asset_type(),
+                            ) {
+                                let mut burned = self
+                                    .burned_amounts
+                                    .entry(AssetTypeCode { val })
+                                    .or_insert(0);
+                                *burned.deref_mut() = burned.saturating_add(amount);
+                            }
+                        }
                         self.owned_utxos
                             .entry(tx_output.record.public_key)
                             .or_insert_with(HashSet::new)
@@ -1827,7 +2135,7 @@ impl LedgerStatus {
 
         // Update issuance sequence number limits
         for (code, seq_nums) in block.new_issuance_nums.drain() {
-            let code = handle_asset_type_code(code);
+            let code = issuance_code(code);
             // One more than the greatest sequence number, or 0
             let new_max_seq_num = seq_nums.last().map(|x| x + 1).unwrap_or(0);
             self.issuance_num.insert(code, new_max_seq_num);
@@ -1946,11 +2254,23 @@ fn default_status_issuance_amounts() -> Mapx<AssetTypeCode, u64> {
     new_mapx!(SNAPSHOT_ENTRIES_DIR.to_owned() + "/issuance_amounts")
 }
 
+fn default_status_burned_amounts() -> Mapx<AssetTypeCode, u64> {
+    new_mapx!(SNAPSHOT_ENTRIES_DIR.to_owned() + "/burned_amounts")
+}
+
 fn default_status_state_commitment_versions() -> Vecx<HashOf<Option<StateCommitmentData>>>
 {
     new_vecx!(SNAPSHOT_ENTRIES_DIR.to_owned() + "/state_commitment_versions")
 }
 
+fn default_status_utxo_map_hist() -> Mapxnk<u64, (StateCommitmentData, Vec<u8>)> {
+    new_mapxnk!(SNAPSHOT_ENTRIES_DIR.to_owned() + "/utxo_map_hist")
+}
+
+fn default_status_cold_block_count() -> usize {
+    0
+}
+
 fn default_status_anon_state_commitment_versions(
 ) -> Vecx<HashOf<Option<AnonStateCommitmentData>>> {
     new_vecx!(SNAPSHOT_ENTRIES_DIR.to_owned() + "/anon_state_commitment_versions")
@@ -2000,6 +2320,10 @@ fn default_status_td_commit_height() -> u64 {
     0
 }
 
+fn default_status_td_commit_time() -> i64 {
+    0
+}
+
 fn default_status_sliding_set() -> SlidingSet<[u8; 8]> {
     SlidingSet::<[u8; 8]>::new(TRANSACTION_WINDOW_WIDTH as usize)
 }
@@ -0,0 +1,128 @@
+//!
+//! Deterministic replay of an export stream, see `export`.
+//!
+//! The txns of each exported block are checked and applied by
+//! `apply_transaction`/`finish_block`, and the resulting state commitment
+//! is compared with the one the exporting ledger recorded for the block.
+//!
+//! Replaying a stream with two builds bisects a consensus-affecting change
+//! down to the first diverging block and the commitment fields it touched.
+//!
+//! The staking changes made by the ABCI app outside of the txns (rewards,
+//! governance) are not replayed, so are not the blocks whose commitments
+//! carry a staking hash, see `CFG.checkpoint.remove_fake_staking_hash`.
+//!
+
+use {
+    super::{export::BlockReader, LedgerState},
+    crate::data_model::StateCommitmentData,
+    globutils::HashOf,
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    std::io::Read,
+};
+
+/// The first block whose local state commitment differs from the exported one
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Divergence {
+    #[allow(missing_docs)]
+    pub block_sid: usize,
+    /// tendermint height of the block, if exported
+    pub height: Option<u64>,
+    /// names of the differing fields of `StateCommitmentData`
+    pub fields: Vec<String>,
+    /// commitment of the exporting ledger
+    pub expected: HashOf<Option<StateCommitmentData>>,
+    /// commitment of the replaying ledger
+    pub actual: HashOf<Option<StateCommitmentData>>,
+}
+
+/// Outcome of a replay
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ReplayReport {
+    /// count of the blocks replayed with a matching commitment
+    pub blocks: usize,
+    /// count of the txns of those blocks
+    pub txns: usize,
+    /// the replay stops at the first divergence,
+    /// as the later commitments chain on the diverged one
+    pub divergence: Option<Divergence>,
+}
+
+/// Names of the fields of `actual` different from the ones of `expected`
+fn diff_fields(
+    expected: &StateCommitmentData,
+    actual: &StateCommitmentData,
+) -> Vec<String> {
+    [
+        ("bitmap", expected.bitmap == actual.bitmap),
+        ("block_merkle", expected.block_merkle == actual.block_merkle),
+        (
+            "txns_in_block_hash",
+            expected.txns_in_block_hash == actual.txns_in_block_hash,
+        ),
+        (
+            "previous_state_commitment",
+            expected.previous_state_commitment == actual.previous_state_commitment,
+        ),
+        (
+            "transaction_merkle_commitment",
+            expected.transaction_merkle_commitment
+                == actual.transaction_merkle_commitment,
+        ),
+        (
+            "air_commitment",
+            expected.air_commitment == actual.air_commitment,
+        ),
+        ("txo_count", expected.txo_count == actual.txo_count),
+        ("pulse_count", expected.pulse_count == actual.pulse_count),
+        ("staking", expected.staking == actual.staking),
+    ]
+    .iter()
+    .filter(|(_, eq)| !eq)
+    .map(|(name, _)| (*name).to_owned())
+    .collect()
+}
+
+impl LedgerState {
+    /// Replay the blocks of an export stream on top of this ledger,
+    /// the stream must start at its block count.
+    ///
+    /// `on_block` is called with the sid of each block replayed.
+    /// A txn rejected by the ledger is returned as an error,
+    /// a state commitment mismatch stops the replay and is reported.
+    pub fn replay_blocks(
+        &mut self,
+        reader: impl Read,
+        mut on_block: impl FnMut(usize),
+    ) -> Result<ReplayReport> {
+        let mut report = ReplayReport::default();
+        for block in BlockReader::new(reader).c(d!())? {
+            let block = block.c(d!())?;
+            self.apply_exported_block(&block, false)
+                .c(d!(format!("failed to replay block {}", block.block_sid)))?;
+
+            let actual = self
+                .status
+                .state_commitment_data
+                .clone()
+                .c(d!("no block committed"))?;
+            let expected = &block.block.state;
+            if actual != *expected {
+                report.divergence = Some(Divergence {
+                    block_sid: block.block_sid,
+                    height: block.height,
+                    fields: diff_fields(expected, &actual),
+                    expected: expected.compute_commitment(),
+                    actual: actual.compute_commitment(),
+                });
+                break;
+            }
+
+            report.blocks += 1;
+            report.txns += block.block.txns.len();
+            on_block(block.block_sid);
+        }
+        Ok(report)
+    }
+}
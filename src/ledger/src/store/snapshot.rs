@@ -0,0 +1,351 @@
+//!
+//! Snapshots of the ledger state,
+//! a new node can be restored from one instead of replaying all the blocks.
+//!
+//! An archive is named by the sha256 of its content, so it can be fetched
+//! from any untrusted source and checked before being restored.
+//! The blocks themselves are not archived, a restored ledger starts
+//! with the state of the snapshot height and an empty block history.
+//!
+
+use {
+    super::{flush_data, LedgerState},
+    crate::{
+        data_model::{
+            ATxoSID, AnonStateCommitmentData, AssetType, AssetTypeCode, OutputPosition,
+            StateCommitmentData, Transaction, TxnSID, TxoSID, Utxo,
+        },
+        staking::Staking,
+    },
+    cryptohash::sha256,
+    globutils::HashOf,
+    ruc::*,
+    serde::{Deserialize, Serialize},
+    sliding_set::SlidingSet,
+    sparse_merkle_tree::Key,
+    std::{
+        collections::HashSet,
+        fs,
+        path::{Path, PathBuf},
+    },
+    zei::{
+        noah_algebra::prelude::*,
+        noah_api::anon_xfr::structs::{AnonAssetRecord, Nullifier},
+    },
+};
+
+/// Version of the `LedgerSnapshot` format
+pub const LEDGER_SNAPSHOT_VERSION: u32 = 2;
+
+/// Extension of the archive files
+const ARCHIVE_EXT: &str = "snapshot";
+
+/// Files copied as is: the merkle logs and the utxo bitmap
+const RAW_FILES: [&str; 3] = ["block_merkle", "txn_merkle", "utxo_map"];
+
+/// Complete state of a ledger at a tendermint height:
+/// the UTXO set, the asset registry and the staking state,
+/// along with the commitments they are checked against on restore
+#[allow(missing_docs)]
+#[derive(Clone, Deserialize, Serialize)]
+pub struct LedgerSnapshot {
+    pub version: u32,
+    /// tendermint height when the snapshot was taken
+    pub height: u64,
+    /// ledger block count when the snapshot was taken
+    pub block_count: usize,
+    pub td_commit_time: i64,
+    pub utxos: Vec<(TxoSID, Utxo)>,
+    pub spent_utxos: Vec<(TxoSID, Utxo)>,
+    pub txo_to_txn_location: Vec<(TxoSID, (TxnSID, OutputPosition))>,
+    pub ax_utxos: Vec<(ATxoSID, AnonAssetRecord)>,
+    pub ax_txo_to_txn_location: Vec<(ATxoSID, (TxnSID, OutputPosition))>,
+    pub spent_abars: Vec<Nullifier>,
+    pub asset_types: Vec<(AssetTypeCode, AssetType)>,
+    pub issuance_num: Vec<(AssetTypeCode, u64)>,
+    pub issuance_amounts: Vec<(AssetTypeCode, u64)>,
+    pub state_commitment_versions: Vec<HashOf<Option<StateCommitmentData>>>,
+    pub anon_state_commitment_versions: Vec<HashOf<Option<AnonStateCommitmentData>>>,
+    pub state_commitment_data: Option<StateCommitmentData>,
+    pub anon_state_commitment_data: Option<AnonStateCommitmentData>,
+    pub next_txn: TxnSID,
+    pub next_txo: TxoSID,
+    pub next_atxo: ATxoSID,
+    pub block_commit_count: u64,
+    pub txns_in_block_hash: Option<HashOf<Vec<Transaction>>>,
+    pub sliding_set: SlidingSet<[u8; 8]>,
+    pub staking: Staking,
+    /// (name without prefix, content) of the `RAW_FILES`
+    pub raw_files: Vec<(String, Vec<u8>)>,
+}
+
+impl LedgerSnapshot {
+    /// State commitment of the snapshot height
+    pub fn state_commitment(&self) -> Option<HashOf<Option<StateCommitmentData>>> {
+        self.state_commitment_versions.last().cloned()
+    }
+}
+
+fn file_prefix(prefix: Option<&str>) -> String {
+    prefix.map(|p| format!("{p}_")).unwrap_or_default()
+}
+
+/// Files of `basedir` belonging to one of the `RAW_FILES`,
+/// the merkle logs may be split into several files
+fn raw_file_names(basedir: &str, prefix: &str) -> Result<Vec<String>> {
+    let mut names = vec![];
+    for entry in fs::read_dir(basedir).c(d!())? {
+        let name = entry.c(d!())?.file_name().to_string_lossy().into_owned();
+        if let Some(name) = name.strip_prefix(prefix) {
+            if RAW_FILES.iter().any(|f| name.starts_with(f)) {
+                names.push(name.to_owned());
+            }
+        }
+    }
+    names.sort_unstable();
+    Ok(names)
+}
+
+impl LedgerState {
+    /// Take a snapshot of the current state,
+    /// `basedir` and `prefix` are those the ledger was opened with
+    pub fn take_snapshot(
+        &mut self,
+        basedir: &str,
+        prefix: Option<&str>,
+    ) -> Result<LedgerSnapshot> {
+        self.utxo_map.write().write().c(d!())?;
+        self.txn_merkle.write().write().c(d!())?;
+        self.block_merkle.write().write().c(d!())?;
+
+        let prefix = file_prefix(prefix);
+        let raw_files = raw_file_names(basedir, &prefix)
+            .c(d!())?
+            .into_iter()
+            .map(|name| {
+                fs::read(format!("{basedir}/{prefix}{name}"))
+                    .c(d!())
+                    .map(|content| (name, content))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let s = &self.status;
+        Ok(LedgerSnapshot {
+            version: LEDGER_SNAPSHOT_VERSION,
+            height: s.td_commit_height,
+            block_count: self.get_block_count(),
+            td_commit_time: s.td_commit_time,
+            utxos: s.utxos.iter().collect(),
+            // along with the ones migrated to the cold store
+            spent_utxos: s
+                .spent_utxos
+                .iter()
+                .chain(
+                    (0..alt!(0 < s.cold_block_count, s.next_txo.0, 0))
+                        .map(TxoSID)
+                        .filter(|sid| !s.spent_utxos.contains_key(sid))
+                        .filter_map(|sid| {
+                            self.get_spent_utxo_through(sid).map(|u| (sid, u))
+                        }),
+                )
+                .collect(),
+            txo_to_txn_location: s.txo_to_txn_location.iter().collect(),
+            ax_utxos: s.ax_utxos.iter().collect(),
+            ax_txo_to_txn_location: s.ax_txo_to_txn_location.iter().collect(),
+            spent_abars: s.spent_abars.iter().map(|(n, _)| n).collect(),
+            asset_types: s.asset_types.iter().collect(),
+            issuance_num: s.issuance_num.iter().collect(),
+            issuance_amounts: s.issuance_amounts.iter().collect(),
+            state_commitment_versions: (0..s.state_commitment_versions.len())
+                .filter_map(|i| s.state_commitment_versions.get(i))
+                .collect(),
+            anon_state_commitment_versions: (0..s.anon_state_commitment_versions.len())
+                .filter_map(|i| s.anon_state_commitment_versions.get(i))
+                .collect(),
+            state_commitment_data: s.state_commitment_data.clone(),
+            anon_state_commitment_data: s.anon_state_commitment_data.clone(),
+            next_txn: s.next_txn,
+            next_txo: s.next_txo,
+            next_atxo: s.next_atxo,
+            block_commit_count: s.block_commit_count,
+            txns_in_block_hash: s.txns_in_block_hash.clone(),
+            sliding_set: s.sliding_set.clone(),
+            staking: s.staking.clone(),
+            raw_files,
+        })
+    }
+
+    /// Restore a ledger from a snapshot into `basedir`,
+    /// which must not contain a ledger yet
+    pub fn restore_snapshot(
+        basedir: &str,
+        prefix: Option<&str>,
+        snapshot: LedgerSnapshot,
+    ) -> Result<LedgerState> {
+        if snapshot.version != LEDGER_SNAPSHOT_VERSION {
+            return Err(eg!(format!(
+                "unsupported snapshot version: {}",
+                snapshot.version
+            )));
+        }
+        if snapshot.raw_files.iter().any(|(name, _)| {
+            name.contains('/') || !RAW_FILES.iter().any(|f| name.starts_with(f))
+        }) {
+            return Err(eg!("invalid file in the snapshot"));
+        }
+
+        let file_prefix = file_prefix(prefix);
+        fs::create_dir_all(basedir).c(d!())?;
+        if Path::new(&format!("{basedir}/{file_prefix}ledger_status")).exists()
+            || !raw_file_names(basedir, &file_prefix).c(d!())?.is_empty()
+        {
+            return Err(eg!(format!("a ledger already exists in {}", basedir)));
+        }
+        for (name, content) in snapshot.raw_files.iter() {
+            fs::write(format!("{basedir}/{file_prefix}{name}"), content).c(d!())?;
+        }
+
+        let mut ledger = LedgerState::new(basedir, prefix).c(d!())?;
+        if 0 < ledger.status.next_txn.0 || 0 < ledger.status.utxos.len() {
+            return Err(eg!("the ledger status is not empty"));
+        }
+
+        // the anon trees live in rocksdb, rebuild them instead of copying
+        let mut ax_utxos = snapshot.ax_utxos;
+        ax_utxos.sort_unstable_by_key(|(sid, _)| *sid);
+        for (sid, abar) in ax_utxos {
+            if ledger.add_abar(&abar).c(d!())? != sid {
+                return Err(eg!(format!("abar {:?} out of order", sid)));
+            }
+            ledger.status.owned_ax_utxos.insert(abar.commitment, sid);
+            ledger.status.ax_utxos.insert(sid, abar);
+        }
+        for n in snapshot.spent_abars {
+            let k = Key::from_bytes(n.noah_to_bytes()).c(d!())?;
+            ledger
+                .nullifier_set
+                .write()
+                .set(&k, Some(n.noah_to_bytes()))
+                .c(d!())?;
+            ledger.status.spent_abars.insert(n, ());
+        }
+        ledger.commit_anon_changes().c(d!())?;
+        ledger.commit_nullifier_changes().c(d!())?;
+
+        let s = &mut ledger.status;
+        for (sid, utxo) in snapshot.utxos {
+            s.owned_utxos
+                .entry(utxo.0.record.public_key)
+                .or_insert_with(HashSet::new)
+                .insert(sid);
+            s.utxos.insert(sid, utxo);
+        }
+        for (sid, utxo) in snapshot.spent_utxos {
+            s.spent_utxos.insert(sid, utxo);
+        }
+        for (sid, loc) in snapshot.txo_to_txn_location {
+            s.txo_to_txn_location.insert(sid, loc);
+        }
+        for (sid, loc) in snapshot.ax_txo_to_txn_location {
+            s.ax_txo_to_txn_location.insert(sid, loc);
+        }
+        for (code, at) in snapshot.asset_types {
+            s.asset_types.insert(code, at);
+        }
+        for (code, n) in snapshot.issuance_num {
+            s.issuance_num.insert(code, n);
+        }
+        for (code, n) in snapshot.issuance_amounts {
+            s.issuance_amounts.insert(code, n);
+        }
+        for v in snapshot.state_commitment_versions {
+            s.state_commitment_versions.push(v);
+        }
+        for v in snapshot.anon_state_commitment_versions {
+            s.anon_state_commitment_versions.push(v);
+        }
+        s.state_commitment_data = snapshot.state_commitment_data;
+        s.anon_state_commitment_data = snapshot.anon_state_commitment_data;
+        s.next_txn = snapshot.next_txn;
+        s.next_txo = snapshot.next_txo;
+        s.next_atxo = snapshot.next_atxo;
+        s.block_commit_count = snapshot.block_commit_count;
+        s.txns_in_block_hash = snapshot.txns_in_block_hash;
+        s.sliding_set = snapshot.sliding_set;
+        s.staking = snapshot.staking;
+        s.td_commit_height = snapshot.height;
+        s.td_commit_time = snapshot.td_commit_time;
+        // the burned outputs are among the utxos
+        s.init_burned_amounts();
+        s.refresh_data();
+
+        ledger.check_restored().c(d!())?;
+
+        let h = ledger.get_tendermint_height();
+        ledger.get_staking_mut().set_custom_block_height(h);
+
+        let path = format!("{}/{}", basedir, ledger.status.snapshot_file);
+        serde_json::to_vec(&ledger.status)
+            .c(d!())
+            .and_then(|s| fs::write(&path, s).c(d!(path)))?;
+        flush_data();
+
+        Ok(ledger)
+    }
+
+    /// Check a restored ledger against the commitments of its snapshot
+    fn check_restored(&self) -> Result<()> {
+        self.status.fast_invariant_check().c(d!())?;
+
+        if let Some(data) = self.status.state_commitment_data.as_ref() {
+            if data.bitmap != self.utxo_map.write().compute_checksum()
+                || data.block_merkle != self.block_merkle.read().get_root_hash()
+                || data.transaction_merkle_commitment
+                    != self.txn_merkle.read().get_root_hash()
+                || data.txo_count != self.status.next_txo.0
+            {
+                return Err(eg!("the restored state does not match its commitment"));
+            }
+        }
+
+        if let Some(data) = self.status.anon_state_commitment_data.as_ref() {
+            let nullifier_root_hash = self
+                .nullifier_set
+                .read()
+                .merkle_root()
+                .unwrap_or(sparse_merkle_tree::ZERO_DIGEST);
+            if data.abar_root_hash != self.get_abar_root_hash().c(d!())?
+                || data.nullifier_root_hash != nullifier_root_hash
+            {
+                return Err(eg!(
+                    "the restored anon state does not match its commitment"
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Write a snapshot into `dir`, returns the path of the archive,
+/// named by the sha256 of its content
+pub fn write_archive(snapshot: &LedgerSnapshot, dir: &str) -> Result<PathBuf> {
+    let bytes = bincode::serialize(snapshot).c(d!())?;
+    let name = hex::encode(sha256::hash(&bytes).0);
+
+    fs::create_dir_all(dir).c(d!())?;
+    let path = Path::new(dir).join(name).with_extension(ARCHIVE_EXT);
+    fs::write(&path, bytes).c(d!())?;
+    Ok(path)
+}
+
+/// Read a snapshot archive,
+/// its content must match the hash it is named by
+pub fn read_archive(path: &Path) -> Result<LedgerSnapshot> {
+    let bytes = fs::read(path).c(d!())?;
+    let name = path.file_stem().c(d!())?.to_string_lossy();
+    if name != hex::encode(sha256::hash(&bytes).0) {
+        return Err(eg!(format!("corrupted snapshot: {}", path.display())));
+    }
+    bincode::deserialize(&bytes).c(d!())
+}
@@ -0,0 +1,158 @@
+//!
+//! Per-block fee and utilization statistics,
+//! recorded along with each ledger block in `LedgerState::block_stats`.
+//!
+
+use {
+    super::LedgerState,
+    crate::{
+        data_model::{FinalizedTransaction, TX_FEE_MIN},
+        staking::BlockHeight,
+    },
+    schemars::JsonSchema,
+    serde::{Deserialize, Serialize},
+    std::collections::BTreeMap,
+};
+
+/// Aggregates of the txns of a ledger block
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+pub struct BlockStats {
+    /// tendermint height of the block
+    pub height: BlockHeight,
+    /// number of txns in the block
+    pub txn_count: u64,
+    /// FRA paid to the black hole, see `Transaction::fee_paid`
+    pub fees: u64,
+    /// size of the JSON encoded txns
+    pub bytes: u64,
+    /// operation kind => count, see `Operation::kind`
+    pub op_counts: BTreeMap<String, u64>,
+    /// fees of the txns paying one, ascending, see `get_fee_suggestion`
+    #[serde(default)]
+    pub txn_fees: Vec<u64>,
+}
+
+impl BlockStats {
+    /// Stats of the txns of a block
+    pub fn new(height: BlockHeight, txns: &[FinalizedTransaction]) -> Self {
+        let mut stats = BlockStats {
+            height,
+            txn_count: txns.len() as u64,
+            ..Default::default()
+        };
+        for ftx in txns.iter() {
+            let fee = ftx.txn.fee_paid();
+            if 0 < fee {
+                stats.txn_fees.push(fee);
+            }
+            stats.fees = stats.fees.saturating_add(fee);
+            stats.bytes += serde_json::to_vec(&ftx.txn).map_or(0, |b| b.len() as u64);
+            for op in ftx.txn.body.operations.iter() {
+                *stats.op_counts.entry(op.kind().to_owned()).or_default() += 1;
+            }
+        }
+        stats.txn_fees.sort_unstable();
+        stats
+    }
+}
+
+/// Sums of the `BlockStats` of a range of blocks
+#[allow(missing_docs)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+pub struct BlockStatsSummary {
+    /// number of blocks with stats in the range,
+    /// the ones committed before the stats were introduced have none
+    pub block_count: u64,
+    pub txn_count: u64,
+    pub fees: u64,
+    pub bytes: u64,
+    pub op_counts: BTreeMap<String, u64>,
+}
+
+impl BlockStatsSummary {
+    fn add(&mut self, stats: &BlockStats) {
+        self.block_count += 1;
+        self.txn_count += stats.txn_count;
+        self.fees = self.fees.saturating_add(stats.fees);
+        self.bytes += stats.bytes;
+        for (kind, n) in stats.op_counts.iter() {
+            *self.op_counts.entry(kind.clone()).or_default() += n;
+        }
+    }
+}
+
+/// Fee levels of the recent txns, to be used instead of a hard-coded minimum
+#[allow(missing_docs)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+pub struct FeeSuggestion {
+    /// number of blocks with stats in the window
+    pub block_count: u64,
+    /// number of txns paying a fee in those blocks
+    pub txn_count: u64,
+    /// `TX_FEE_MIN`, the fee required by `Transaction::check_fee`
+    pub min: u64,
+    /// 25th, 50th and 90th percentiles of the fees paid,
+    /// `min` if no txn paid a fee in the window
+    pub low: u64,
+    pub medium: u64,
+    pub high: u64,
+}
+
+impl FeeSuggestion {
+    /// Percentiles of the ascending fees, none below `TX_FEE_MIN`
+    pub fn new(block_count: u64, fees: &[u64]) -> Self {
+        let percentile = |p: usize| {
+            if fees.is_empty() {
+                return TX_FEE_MIN;
+            }
+            let idx = (fees.len() * p).div_ceil(100).saturating_sub(1);
+            fees[idx].max(TX_FEE_MIN)
+        };
+        FeeSuggestion {
+            block_count,
+            txn_count: fees.len() as u64,
+            min: TX_FEE_MIN,
+            low: percentile(25),
+            medium: percentile(50),
+            high: percentile(90),
+        }
+    }
+}
+
+impl LedgerState {
+    /// Stats of the ledger blocks in [start, end]
+    pub fn get_block_stats(&self, start: usize, end: usize) -> Vec<(usize, BlockStats)> {
+        let end = end.min(self.get_block_count().saturating_sub(1));
+        (start..=end)
+            .filter_map(|idx| self.block_stats.get(&(idx as u64)).map(|s| (idx, s)))
+            .collect()
+    }
+
+    /// Sums of the stats of the last `n` ledger blocks
+    pub fn get_recent_block_stats(&self, n: usize) -> BlockStatsSummary {
+        let count = self.get_block_count();
+        let mut summary = BlockStatsSummary::default();
+        if 0 < count && 0 < n {
+            for (_, stats) in self.get_block_stats(count.saturating_sub(n), count - 1) {
+                summary.add(&stats);
+            }
+        }
+        summary
+    }
+
+    /// Fee levels of the txns of the last `n` ledger blocks,
+    /// from the fees recorded in their stats at block finish
+    pub fn get_fee_suggestion(&self, n: usize) -> FeeSuggestion {
+        let count = self.get_block_count();
+        let mut block_count = 0;
+        let mut fees = vec![];
+        if 0 < count && 0 < n {
+            for (_, stats) in self.get_block_stats(count.saturating_sub(n), count - 1) {
+                block_count += 1;
+                fees.extend(stats.txn_fees);
+            }
+        }
+        fees.sort_unstable();
+        FeeSuggestion::new(block_count, &fees)
+    }
+}
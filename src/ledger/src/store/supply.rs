@@ -0,0 +1,42 @@
+//!
+//! Supply of an asset type, from the issued and burned amounts
+//! counted in the ledger status.
+//!
+
+use {
+    super::LedgerState,
+    crate::data_model::AssetTypeCode,
+    schemars::JsonSchema,
+    serde::{Deserialize, Serialize},
+};
+
+/// Nonconfidential supply of an asset type
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+pub struct AssetSupply {
+    /// base64 asset code
+    pub code: String,
+    /// amount issued by `IssueAsset` operations
+    pub issued: u64,
+    /// amount sent to `BLACK_HOLE_PUBKEY`, which can not be spent
+    pub burned: u64,
+    /// `issued - burned`
+    pub circulating: u64,
+    /// the `max_units` rule of the asset type, if any
+    pub max_units: Option<u64>,
+}
+
+impl LedgerState {
+    /// Supply of a registered asset type
+    pub fn get_asset_supply(&self, code: &AssetTypeCode) -> Option<AssetSupply> {
+        let asset_type = self.get_asset_type(code)?;
+        let issued = self.status.get_issued_amount(code);
+        let burned = self.status.get_burned_amount(code);
+        Some(AssetSupply {
+            code: code.to_base64(),
+            issued,
+            burned,
+            circulating: issued.saturating_sub(burned),
+            max_units: asset_type.properties.asset_rules.max_units,
+        })
+    }
+}
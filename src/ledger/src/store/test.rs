@@ -5,9 +5,9 @@ use {
     crate::{
         data_model::{
             get_abar_commitment, AssetRules, AssetTypeCode, IssueAsset, IssueAssetBody,
-            IssuerKeyPair, Memo, Operation, Transaction, TransferAsset,
-            TransferAssetBody, TransferType, TxOutput, TxnEffect, TxoRef, TxoSID,
-            ASSET_TYPE_FRA, BLACK_HOLE_PUBKEY, TX_FEE_MIN,
+            IssuerKeyPair, Memo, Operation, OutputLock, SignatureRules, Transaction,
+            TransferAsset, TransferAssetBody, TransferType, TxOutput, TxnEffect, TxoRef,
+            TxoSID, XfrAddress, ASSET_TYPE_FRA, BLACK_HOLE_PUBKEY, TX_FEE_MIN,
         },
         store::{helpers::create_definition_transaction, utils::fra_gen_initial_tx},
     },
@@ -123,6 +123,432 @@ fn test_asset_creation_valid() {
     assert_eq!(0, state.get_asset_type(&token_code).unwrap().units);
 }
 
+#[test]
+fn test_api_cache_txn_height_and_time() {
+    let mut prng = ChaChaRng::from_entropy();
+    let mut state = LedgerState::tmp_ledger();
+
+    let keypair = build_keys(&mut prng);
+    let code = AssetTypeCode::gen_random();
+    let seq_id = state.get_block_commit_count();
+    let tx = create_definition_transaction(
+        &code,
+        &keypair,
+        AssetRules::default(),
+        None,
+        seq_id,
+    )
+    .unwrap();
+    let effect = TxnEffect::compute_effect(tx).unwrap();
+
+    let mut block = state.start_block().unwrap();
+    let temp_sid = state.apply_transaction(&mut block, effect).unwrap();
+    let (txn_sid, _) = state
+        .finish_block(block)
+        .unwrap()
+        .remove(&temp_sid)
+        .unwrap();
+
+    state.set_tendermint_height(10);
+    state.set_tendermint_time(1_600_000_000);
+    api_cache::update_api_cache(&mut state).unwrap();
+
+    // an empty block re-visits the last finalized block
+    state.set_tendermint_height(11);
+    state.set_tendermint_time(1_600_000_016);
+    api_cache::update_api_cache(&mut state).unwrap();
+
+    let cache = state.api_cache.as_ref().unwrap();
+    assert_eq!(cache.txn_sid_to_height.get(&txn_sid), Some(10));
+    assert_eq!(cache.height_to_time.get(&10), Some(1_600_000_000));
+    assert_eq!(cache.height_to_time.get(&11), Some(1_600_000_016));
+}
+
+#[test]
+fn test_api_cache_address_stats() {
+    let mut ledger = LedgerState::tmp_ledger();
+    let issuer = XfrKeyPair::generate(&mut ledger.get_prng());
+    let alice = XfrKeyPair::generate(&mut ledger.get_prng());
+
+    let code = AssetTypeCode::gen_random();
+    let seq_id = ledger.get_block_commit_count();
+    let tx = create_definition_transaction(
+        &code,
+        &issuer,
+        AssetRules::default(),
+        None,
+        seq_id,
+    )
+    .unwrap();
+    let new_code = AssetTypeCode::from_prefix_and_raw_asset_type_code(
+        AssetTypePrefix::UserDefined,
+        &code,
+        &CFG.checkpoint,
+        ledger.get_tendermint_height(),
+    );
+
+    ledger.set_tendermint_height(10);
+    apply_transaction(&mut ledger, tx);
+    api_cache::update_api_cache(&mut ledger).unwrap();
+
+    let (tx, _) = create_issue_and_transfer_txn(
+        &mut ledger,
+        &new_code,
+        100,
+        &issuer,
+        alice.get_pk_ref(),
+        0,
+    );
+    ledger.set_tendermint_height(11);
+    apply_transaction(&mut ledger, tx);
+    api_cache::update_api_cache(&mut ledger).unwrap();
+    // the last block is revisited without new txns
+    ledger.set_tendermint_height(12);
+    api_cache::update_api_cache(&mut ledger).unwrap();
+
+    let cache = ledger.api_cache.as_ref().unwrap();
+    let code_b64 = new_code.to_base64();
+
+    let issuer_stats = cache
+        .address_stats
+        .get(&XfrAddress {
+            key: *issuer.get_pk_ref(),
+        })
+        .unwrap();
+    assert_eq!(issuer_stats.first_seen_height, 10);
+    assert_eq!(issuer_stats.txn_count, 2);
+    assert_eq!(issuer_stats.received.get(&code_b64), Some(&100));
+    assert_eq!(issuer_stats.sent.get(&code_b64), Some(&100));
+
+    let alice_stats = cache
+        .address_stats
+        .get(&XfrAddress {
+            key: *alice.get_pk_ref(),
+        })
+        .unwrap();
+    assert_eq!(alice_stats.first_seen_height, 11);
+    assert_eq!(alice_stats.txn_count, 1);
+    assert_eq!(alice_stats.received.get(&code_b64), Some(&100));
+    assert!(alice_stats.sent.is_empty());
+
+    // the issuer has sent all of its units
+    let holders = cache.asset_holders.get(&new_code).unwrap();
+    assert_eq!(
+        holders.into_iter().collect::<Vec<_>>(),
+        vec![XfrAddress {
+            key: *alice.get_pk_ref(),
+        }]
+    );
+
+    // each txn is indexed once under each of its operation kinds
+    for (op_type, count) in [("DefineAsset", 1), ("IssueAsset", 1), ("TransferAsset", 1)]
+    {
+        assert_eq!(
+            cache
+                .txns_by_op_type
+                .get(&op_type.to_owned())
+                .unwrap()
+                .len(),
+            count
+        );
+    }
+    assert!(cache
+        .txns_by_op_type
+        .get(&"Delegation".to_owned())
+        .is_none());
+}
+
+#[test]
+fn test_api_cache_spent_utxos() {
+    let mut ledger = LedgerState::tmp_ledger();
+    let issuer = XfrKeyPair::generate(&mut ledger.get_prng());
+    let alice = XfrKeyPair::generate(&mut ledger.get_prng());
+    let bob = XfrKeyPair::generate(&mut ledger.get_prng());
+
+    let code = AssetTypeCode::gen_random();
+    let seq_id = ledger.get_block_commit_count();
+    let tx = create_definition_transaction(
+        &code,
+        &issuer,
+        AssetRules::default(),
+        None,
+        seq_id,
+    )
+    .unwrap();
+    let new_code = AssetTypeCode::from_prefix_and_raw_asset_type_code(
+        AssetTypePrefix::UserDefined,
+        &code,
+        &CFG.checkpoint,
+        ledger.get_tendermint_height(),
+    );
+    apply_transaction(&mut ledger, tx);
+
+    let (tx, _) = create_issue_and_transfer_txn(
+        &mut ledger,
+        &new_code,
+        100,
+        &issuer,
+        alice.get_pk_ref(),
+        0,
+    );
+    let (_, sids) = apply_transaction(&mut ledger, tx);
+    let sid = sids[0];
+    let bar = ledger.get_utxo_light(sid).unwrap().utxo.0.record;
+
+    let template = AssetRecordTemplate::with_no_asset_tracing(
+        100,
+        new_code.val,
+        AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+        bob.get_pk().into_noah(),
+    );
+    let record = AssetRecord::from_template_no_identity_tracing(
+        &mut ledger.get_prng(),
+        &template,
+    )
+    .unwrap();
+    let mut transfer = TransferAsset::new(
+        TransferAssetBody::new(
+            &mut ledger.get_prng(),
+            vec![TxoRef::Absolute(sid)],
+            &[AssetRecord::from_open_asset_record_no_asset_tracing(
+                open_blind_asset_record(&bar.into_noah(), &None, &alice.into_noah())
+                    .unwrap(),
+            )],
+            &[record],
+            None,
+            vec![],
+            TransferType::Standard,
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    transfer.sign(&alice);
+    let seq_id = ledger.get_block_commit_count();
+    let tx = Transaction::from_operation(Operation::TransferAsset(transfer), seq_id);
+
+    let cache = ledger.api_cache.as_mut().unwrap();
+    cache.add_spent_utxos(&tx, TxnSID(7), 12);
+
+    assert_eq!(cache.spent_utxos.get(&sid), Some((TxnSID(7), 12)));
+    assert!(cache.spent_utxos.get(&TxoSID(sid.0 + 1)).is_none());
+}
+
+#[test]
+fn test_api_cache_snapshot_roundtrip() {
+    let mut prng = ChaChaRng::from_entropy();
+    let mut state = LedgerState::tmp_ledger();
+
+    let keypair = build_keys(&mut prng);
+    let code = AssetTypeCode::gen_random();
+    let seq_id = state.get_block_commit_count();
+    let tx = create_definition_transaction(
+        &code,
+        &keypair,
+        AssetRules::default(),
+        None,
+        seq_id,
+    )
+    .unwrap();
+    state.set_tendermint_height(10);
+    let (txn_sid, _) = apply_transaction(&mut state, tx);
+    api_cache::update_api_cache(&mut state).unwrap();
+
+    let snapshot = state
+        .api_cache
+        .as_ref()
+        .unwrap()
+        .export_snapshot(state.get_block_count());
+    assert_eq!(snapshot.block_count, 1);
+
+    let bytes = bincode::serialize(&snapshot).unwrap();
+    let snapshot = bincode::deserialize::<api_cache::ApiCacheSnapshot>(&bytes).unwrap();
+
+    let mut restored = ApiCache::new(&format!("{}snapshot/", rand::random::<u64>()));
+    restored.import_snapshot(snapshot.clone()).unwrap();
+
+    let cache = state.api_cache.as_ref().unwrap();
+    let address = XfrAddress {
+        key: *keypair.get_pk_ref(),
+    };
+    assert_eq!(
+        restored.txn_sid_to_hash.get(&txn_sid),
+        cache.txn_sid_to_hash.get(&txn_sid)
+    );
+    assert_eq!(restored.txn_sid_to_height.get(&txn_sid), Some(10));
+    assert_eq!(
+        restored.address_stats.get(&address),
+        cache.address_stats.get(&address)
+    );
+    assert_eq!(
+        restored
+            .related_transactions
+            .get(&address)
+            .unwrap()
+            .iter()
+            .map(|(k, _)| k)
+            .collect::<Vec<_>>(),
+        vec![txn_sid]
+    );
+
+    // only an empty cache can be restored
+    assert!(restored.import_snapshot(snapshot).is_err());
+}
+
+#[test]
+fn test_api_cache_check_lost_data_in_batches() {
+    let mut prng = ChaChaRng::from_entropy();
+    let mut state = LedgerState::tmp_ledger();
+
+    let keypair = build_keys(&mut prng);
+    let mut txn_sids = vec![];
+    for _ in 0..3 {
+        let seq_id = state.get_block_commit_count();
+        let tx = create_definition_transaction(
+            &AssetTypeCode::gen_random(),
+            &keypair,
+            AssetRules::default(),
+            None,
+            seq_id,
+        )
+        .unwrap();
+        txn_sids.push(apply_transaction(&mut state, tx).0);
+    }
+    let total = state.get_next_txn().0 as u64;
+
+    let progress = api_cache::check_lost_data(&mut state, 1).unwrap();
+    assert_eq!(progress.txn_checked, 1);
+    assert_eq!(progress.txn_total, total);
+    assert!(!progress.is_done());
+
+    // resumes from the persisted position
+    let progress = api_cache::check_lost_data(&mut state, 1).unwrap();
+    assert_eq!(progress.txn_checked, 2);
+
+    let progress = api_cache::check_lost_data(&mut state, total).unwrap();
+    assert!(progress.is_done());
+
+    let cache = state.api_cache.as_ref().unwrap();
+    for sid in txn_sids {
+        let hash = cache.txn_sid_to_hash.get(&sid).unwrap();
+        assert_eq!(cache.txn_hash_to_sid.get(&hash), Some(sid));
+    }
+}
+
+#[test]
+fn test_api_cache_prune_spent_txos() {
+    let mut ledger = LedgerState::tmp_ledger();
+    let issuer = XfrKeyPair::generate(&mut ledger.get_prng());
+    let alice = XfrKeyPair::generate(&mut ledger.get_prng());
+
+    let code = AssetTypeCode::gen_random();
+    let seq_id = ledger.get_block_commit_count();
+    let tx = create_definition_transaction(
+        &code,
+        &issuer,
+        AssetRules::default(),
+        None,
+        seq_id,
+    )
+    .unwrap();
+    let new_code = AssetTypeCode::from_prefix_and_raw_asset_type_code(
+        AssetTypePrefix::UserDefined,
+        &code,
+        &CFG.checkpoint,
+        ledger.get_tendermint_height(),
+    );
+    apply_transaction(&mut ledger, tx);
+
+    let (tx, _) = create_issue_and_transfer_txn(
+        &mut ledger,
+        &new_code,
+        100,
+        &issuer,
+        alice.get_pk_ref(),
+        0,
+    );
+    let (_, sids) = apply_transaction(&mut ledger, tx);
+    api_cache::update_api_cache(&mut ledger).unwrap();
+
+    let unspent = sids[0];
+    let spent = TxoSID(unspent.0 + 1000);
+    let owner = XfrAddress {
+        key: *alice.get_pk_ref(),
+    };
+    ledger
+        .api_cache
+        .as_mut()
+        .unwrap()
+        .utxos_to_map_index
+        .insert(spent, owner);
+
+    // nothing is dropped by the default policy
+    let stats =
+        api_cache::prune_api_cache(&mut ledger, &api_cache::PruningPolicy::default())
+            .unwrap();
+    assert_eq!(stats, api_cache::PruneStats::default());
+
+    let policy = api_cache::PruningPolicy {
+        prune_spent_txos: true,
+        coinbase_hist_blocks: Some(10),
+    };
+    let stats = api_cache::prune_api_cache(&mut ledger, &policy).unwrap();
+    assert_eq!(stats.spent_txos, 1);
+    assert_eq!(stats.coinbase_entries, 0);
+
+    let cache = ledger.api_cache.as_ref().unwrap();
+    assert!(cache.utxos_to_map_index.get(&spent).is_none());
+    assert_eq!(cache.utxos_to_map_index.get(&unspent), Some(owner));
+}
+
+#[test]
+fn test_verify_api_cache() {
+    let mut prng = ChaChaRng::from_entropy();
+    let mut state = LedgerState::tmp_ledger();
+
+    let keypair = build_keys(&mut prng);
+    let seq_id = state.get_block_commit_count();
+    let tx = create_definition_transaction(
+        &AssetTypeCode::gen_random(),
+        &keypair,
+        AssetRules::default(),
+        None,
+        seq_id,
+    )
+    .unwrap();
+    let (txn_sid, _) = apply_transaction(&mut state, tx);
+    api_cache::update_api_cache(&mut state).unwrap();
+
+    let report = api_cache::verify_api_cache(&mut state, false).unwrap();
+    assert!(report.is_consistent());
+
+    let cache = state.api_cache.as_mut().unwrap();
+    cache.txn_sid_to_hash.insert(txn_sid, "BAD".to_owned());
+    cache.txn_hash_to_sid.insert("BAD".to_owned(), txn_sid);
+    cache.utxos_to_map_index.insert(
+        TxoSID(1000),
+        XfrAddress {
+            key: *keypair.get_pk_ref(),
+        },
+    );
+
+    let report = api_cache::verify_api_cache(&mut state, false).unwrap();
+    assert_eq!(report.txn_sid_to_hash, 1);
+    assert_eq!(report.dangling_txn_hashes, 0);
+    assert_eq!(report.dangling_txos, 1);
+    assert!(!report.is_consistent());
+
+    let report = api_cache::verify_api_cache(&mut state, true).unwrap();
+    assert_eq!(report.txn_sid_to_hash, 1);
+    assert_eq!(report.dangling_txn_hashes, 1);
+    assert!(report.repaired);
+
+    let report = api_cache::verify_api_cache(&mut state, false).unwrap();
+    assert!(report.is_consistent());
+    let cache = state.api_cache.as_ref().unwrap();
+    assert!(cache.txn_hash_to_sid.get(&"BAD".to_owned()).is_none());
+    assert!(cache.utxos_to_map_index.get(&TxoSID(1000)).is_none());
+}
+
 // Change the signature to have the wrong public key
 #[test]
 fn test_asset_creation_invalid_public_key() {
@@ -152,6 +578,7 @@ fn test_asset_creation_invalid_public_key() {
 #[allow(clippy::redundant_clone)]
 fn test_asset_transfer() {
     let mut ledger = LedgerState::tmp_ledger();
+    ledger.set_utxo_history(Some(2));
 
     let code = AssetTypeCode::gen_random();
     let mut prng = ChaChaRng::from_entropy();
@@ -200,6 +627,7 @@ fn test_asset_transfer() {
                     id: None,
                     record: BlindAssetRecord::from_noah(&ba),
                     lien: None,
+                    lock: None,
                 },
                 None,
             ),
@@ -208,6 +636,7 @@ fn test_asset_transfer() {
                     id: None,
                     record: BlindAssetRecord::from_noah(&ba.clone()),
                     lien: None,
+                    lock: None,
                 },
                 None,
             ),
@@ -236,6 +665,7 @@ fn test_asset_transfer() {
     ledger.api_cache.as_mut().unwrap().state_commitment_version =
         ledger.status.state_commitment_versions.last();
     let state_commitment = ledger.get_state_commitment().0;
+    let issuance_version = ledger.get_block_commit_count();
 
     for txo_id in &txos {
         assert!(ledger.status.utxos.contains_key(&txo_id));
@@ -309,6 +739,25 @@ fn test_asset_transfer() {
     assert!(!input_bar_proof.is_valid(state_commitment));
     assert!(utxo_status.status == UtxoStatus::Spent);
 
+    // It can still be proven unspent at the issuance version
+    let issuance_commitment = ledger
+        .get_state_commitment_at_block_height(issuance_version)
+        .unwrap();
+    let utxo_status = ledger
+        .get_utxo_status_at_version(TxoSID(0), issuance_version)
+        .unwrap();
+    assert!(utxo_status.is_valid(issuance_commitment.clone()));
+    assert!(utxo_status.status == UtxoStatus::Unspent);
+    let utxo_status = ledger
+        .get_utxo_status_at_version(TxoSID(0), ledger.get_block_commit_count())
+        .unwrap();
+    assert!(utxo_status.status == UtxoStatus::Spent);
+    assert!(!utxo_status.is_valid(issuance_commitment));
+    // only the last two versions are kept
+    assert!(ledger
+        .get_utxo_status_at_version(TxoSID(0), issuance_version - 1)
+        .is_err());
+
     // Adversary will attempt to spend the same blind asset record at another index
     second_transfer.body.inputs = vec![TxoRef::Absolute(second_txo_id)];
 
@@ -399,6 +848,7 @@ fn asset_issued() {
                 id: None,
                 record: BlindAssetRecord::from_noah(&ba),
                 lien: None,
+                lock: None,
             },
             None,
         )],
@@ -825,8 +1275,73 @@ fn test_check_fee_with_ledger() {
 }
 
 #[test]
-fn test_update_anon_stores() {
-    let mut prng = ChaChaRng::from_seed([0u8; 32]);
+fn test_block_stats() {
+    let mut ledger = LedgerState::tmp_ledger();
+    let fra_owner_kp = XfrKeyPair::generate(&mut ChaChaRng::from_entropy());
+
+    let tx = fra_gen_initial_tx(&fra_owner_kp);
+    let effect = TxnEffect::compute_effect(tx).unwrap();
+    let mut block = ledger.start_block().unwrap();
+    let tmp_sid = ledger.apply_transaction(&mut block, effect).unwrap();
+    let txo_sid = ledger
+        .finish_block(block)
+        .unwrap()
+        .remove(&tmp_sid)
+        .unwrap()
+        .1[0];
+
+    let tx2 = Transaction::from_operation(
+        gen_fee_operation(&mut ledger, txo_sid, &fra_owner_kp),
+        1,
+    );
+    assert_eq!(tx2.fee_paid(), TX_FEE_MIN);
+    let effect = TxnEffect::compute_effect(tx2.clone()).unwrap();
+    let mut block = ledger.start_block().unwrap();
+    ledger.apply_transaction(&mut block, effect).unwrap();
+    ledger.finish_block(block).unwrap();
+
+    let stats = ledger.get_block_stats(1, 100);
+    assert_eq!(stats.len(), 1);
+    let (idx, stats) = &stats[0];
+    assert_eq!(*idx, 1);
+    assert_eq!(stats.txn_count, 1);
+    assert_eq!(stats.fees, TX_FEE_MIN);
+    assert_eq!(stats.bytes, serde_json::to_vec(&tx2).unwrap().len() as u64);
+    assert_eq!(stats.op_counts.get("TransferAsset"), Some(&1));
+
+    let recent = ledger.get_recent_block_stats(10);
+    assert_eq!(recent.block_count, 2);
+    assert_eq!(recent.txn_count, 2);
+    assert_eq!(recent.fees, TX_FEE_MIN);
+    assert_eq!(ledger.get_recent_block_stats(1).txn_count, 1);
+
+    assert_eq!(stats.txn_fees, vec![TX_FEE_MIN]);
+    let suggestion = ledger.get_fee_suggestion(10);
+    assert_eq!((suggestion.block_count, suggestion.txn_count), (2, 1));
+    assert_eq!(suggestion.medium, TX_FEE_MIN);
+    assert_eq!(ledger.get_fee_suggestion(0).block_count, 0);
+}
+
+#[test]
+fn test_fee_suggestion() {
+    let suggestion = stats::FeeSuggestion::new(3, &[]);
+    assert_eq!(suggestion.txn_count, 0);
+    assert_eq!(
+        (suggestion.low, suggestion.medium, suggestion.high),
+        (TX_FEE_MIN, TX_FEE_MIN, TX_FEE_MIN)
+    );
+
+    let fees = (1..=100).map(|i| i * 300).collect::<Vec<_>>();
+    let suggestion = stats::FeeSuggestion::new(3, &fees);
+    assert_eq!(suggestion.txn_count, 100);
+    // the 25th fee is below the minimum
+    assert_eq!(suggestion.low, TX_FEE_MIN);
+    assert_eq!((suggestion.medium, suggestion.high), (15_000, 27_000));
+}
+
+#[test]
+fn test_update_anon_stores() {
+    let mut prng = ChaChaRng::from_seed([0u8; 32]);
 
     let mut state = LedgerState::tmp_ledger();
 
@@ -906,3 +1421,874 @@ fn test_update_anon_stores() {
     assert_eq!(state.status.owned_ax_utxos.get(&new_com), Some(ATxoSID(0)));
     assert_eq!(state.status.owned_ax_utxos.get(&new_com2), Some(ATxoSID(1)));
 }
+
+#[test]
+fn test_ledger_snapshot_restore() {
+    let mut prng = ChaChaRng::from_entropy();
+    fbnc::clear();
+    let basedir = globutils::fresh_tmp_dir().to_string_lossy().into_owned();
+    let mut state = LedgerState::new(&basedir, Some("test")).unwrap();
+
+    let keypair = build_keys(&mut prng);
+    let code = AssetTypeCode::gen_random();
+    let seq_id = state.get_block_commit_count();
+    let tx = create_definition_transaction(
+        &code,
+        &keypair,
+        AssetRules::default(),
+        None,
+        seq_id,
+    )
+    .unwrap();
+    state.set_tendermint_height(10);
+    let (txn_sid, _) = apply_transaction(&mut state, tx);
+
+    let snapshot = state.take_snapshot(&basedir, Some("test")).unwrap();
+    assert_eq!(snapshot.height, 10);
+    assert!(!snapshot.raw_files.is_empty());
+
+    let archive_dir = globutils::fresh_tmp_dir().to_string_lossy().into_owned();
+    let path = snapshot::write_archive(&snapshot, &archive_dir).unwrap();
+    let snapshot = snapshot::read_archive(&path).unwrap();
+
+    // the content must match the name
+    let renamed = path.with_file_name(format!("{}.snapshot", "0".repeat(64)));
+    fs::copy(&path, &renamed).unwrap();
+    assert!(snapshot::read_archive(&renamed).is_err());
+
+    // a ledger can not be restored over an existing one
+    assert!(
+        LedgerState::restore_snapshot(&basedir, Some("test"), snapshot.clone()).is_err()
+    );
+
+    let commitment = state.get_state_commitment();
+    let next_txn = state.get_next_txn();
+    let next_txo = state.get_next_txo();
+
+    fbnc::clear();
+    let restore_dir = globutils::fresh_tmp_dir().to_string_lossy().into_owned();
+    let restored =
+        LedgerState::restore_snapshot(&restore_dir, Some("test"), snapshot).unwrap();
+
+    assert_eq!(restored.get_state_commitment(), commitment);
+    assert_eq!(restored.get_next_txn(), next_txn);
+    assert_eq!(restored.get_next_txo(), next_txo);
+    assert_eq!(restored.get_tendermint_height(), 10);
+    assert!(restored.get_asset_type(&code).is_some());
+    assert_eq!(next_txn, TxnSID(txn_sid.0 + 1));
+}
+
+#[test]
+fn test_api_cache_wal_replay() {
+    let mut prng = ChaChaRng::from_entropy();
+    let mut state = LedgerState::tmp_ledger();
+
+    let keypair = build_keys(&mut prng);
+    let code = AssetTypeCode::gen_random();
+    let seq_id = state.get_block_commit_count();
+    let tx = create_definition_transaction(
+        &code,
+        &keypair,
+        AssetRules::default(),
+        None,
+        seq_id,
+    )
+    .unwrap();
+    state.set_tendermint_height(10);
+    let (txn_sid, _) = apply_transaction(&mut state, tx);
+
+    // crash after the entry is logged, before the block is indexed
+    let entry = api_cache::WalEntry {
+        block_idx: state.get_block_count() - 1,
+        height: 10,
+        time: 0,
+    };
+    let cache = state.api_cache.as_ref().unwrap();
+    cache.log_wal_entry(&entry).unwrap();
+    assert_eq!(cache.pending_wal_entry().unwrap(), Some(entry));
+    assert!(cache.txn_sid_to_hash.get(&txn_sid).is_none());
+
+    api_cache::replay_wal(&mut state).unwrap();
+
+    let cache = state.api_cache.as_ref().unwrap();
+    assert!(cache.pending_wal_entry().unwrap().is_none());
+    assert!(cache.txn_sid_to_hash.get(&txn_sid).is_some());
+    assert_eq!(cache.txn_sid_to_height.get(&txn_sid), Some(10));
+
+    // nothing left to replay
+    api_cache::replay_wal(&mut state).unwrap();
+
+    // a normal update leaves no entry behind
+    api_cache::update_api_cache(&mut state).unwrap();
+    let cache = state.api_cache.as_ref().unwrap();
+    assert!(cache.pending_wal_entry().unwrap().is_none());
+}
+
+#[test]
+fn test_api_cache_address_filters() {
+    let mut prng = ChaChaRng::from_entropy();
+    let mut state = LedgerState::tmp_ledger();
+
+    let keypair = build_keys(&mut prng);
+    let other = build_keys(&mut prng);
+    let address = XfrAddress {
+        key: *keypair.get_pk_ref(),
+    };
+    let other = XfrAddress {
+        key: *other.get_pk_ref(),
+    };
+
+    let filter = api_cache::AddressFilter::new([address].iter());
+    assert_eq!(filter.bits.len(), 8);
+    assert!(filter.contains(&address));
+
+    let code = AssetTypeCode::gen_random();
+    let seq_id = state.get_block_commit_count();
+    let tx = create_definition_transaction(
+        &code,
+        &keypair,
+        AssetRules::default(),
+        None,
+        seq_id,
+    )
+    .unwrap();
+    apply_transaction(&mut state, tx);
+    api_cache::update_api_cache(&mut state).unwrap();
+
+    let block_idx = state.get_block_count() as u64 - 1;
+    let filter = state
+        .api_cache
+        .as_ref()
+        .unwrap()
+        .address_filters
+        .get(&block_idx)
+        .unwrap();
+    assert!(filter.contains(&address));
+    // a false positive is possible, but unlikely with a single address
+    assert!(!filter.contains(&other));
+}
+
+#[test]
+fn test_api_cache_indexes_parse() {
+    let all = api_cache::ApiCacheIndexes::default();
+    assert_eq!(api_cache::ApiCacheIndexes::parse("all").unwrap(), all);
+    assert_eq!(
+        api_cache::ApiCacheIndexes::parse("owner_memos, all").unwrap(),
+        all
+    );
+
+    let memos = api_cache::ApiCacheIndexes::parse("owner_memos").unwrap();
+    assert!(memos.owner_memos);
+    assert!(!memos.coinbase_hist);
+    assert!(!memos.issuances);
+    assert!(!memos.txn_hashes);
+    assert!(!memos.explorer);
+    assert!(!memos.staking_hist);
+
+    let some = api_cache::ApiCacheIndexes::parse("txn_hashes,explorer,").unwrap();
+    assert!(some.txn_hashes && some.explorer);
+    assert!(!some.owner_memos);
+
+    assert!(api_cache::ApiCacheIndexes::parse("owner_memos,blocks").is_err());
+}
+
+#[test]
+fn test_api_cache_rocksdb_backend() {
+    use super::kv::ApiCacheBackend;
+
+    assert_eq!(
+        ApiCacheBackend::parse("fbnc").unwrap(),
+        ApiCacheBackend::Fbnc
+    );
+    assert_eq!(
+        ApiCacheBackend::parse("rocksdb").unwrap(),
+        ApiCacheBackend::RocksDB
+    );
+    assert!(ApiCacheBackend::parse("sled").is_err());
+
+    let dir = format!("/tmp/api_cache_db_{}", rand::random::<u64>());
+    let mut cache = api_cache::ApiCache::new(&format!("{}kv/", rand::random::<u64>()))
+        .with_backend(ApiCacheBackend::RocksDB, &dir)
+        .unwrap();
+
+    for sid in [300, 2, 1000] {
+        cache
+            .txn_sid_to_hash
+            .insert(TxnSID(sid), format!("{:X}", sid));
+        cache
+            .txn_hash_to_sid
+            .insert(format!("{:X}", sid), TxnSID(sid));
+    }
+    // visible before the commit
+    assert_eq!(
+        cache.txn_sid_to_hash.get(&TxnSID(300)),
+        Some("12C".to_owned())
+    );
+    cache.clear_wal().unwrap();
+
+    assert_eq!(
+        cache.txn_hash_to_sid.get(&"3E8".to_owned()),
+        Some(TxnSID(1000))
+    );
+    assert_eq!(
+        cache.txn_sid_to_hash.remove(&TxnSID(2)),
+        Some("2".to_owned())
+    );
+    assert!(!cache.txn_sid_to_hash.contains_key(&TxnSID(2)));
+
+    // in numeric order, the other maps of the state left out
+    let sids = cache
+        .txn_sid_to_hash
+        .iter()
+        .map(|(sid, _)| sid)
+        .collect::<Vec<_>>();
+    assert_eq!(sids, vec![TxnSID(300), TxnSID(1000)]);
+    assert_eq!(cache.txn_hash_to_sid.len(), 3);
+    assert_eq!(cache.txn_sid_to_hash.len(), 2);
+    assert!(cache.spent_utxos.is_empty());
+
+    // the buffered changes are read along, without being committed
+    let height = || {
+        let state = cache.kv_state().unwrap().read();
+        let height = state.chain_state().read().height().unwrap();
+        height
+    };
+    let committed = height();
+    cache.txn_sid_to_hash.insert(TxnSID(5), "5".to_owned());
+    cache.txn_sid_to_hash.remove(&TxnSID(1000));
+    let entries = cache.txn_sid_to_hash.iter().collect::<Vec<_>>();
+    assert_eq!(
+        entries,
+        vec![(TxnSID(5), "5".to_owned()), (TxnSID(300), "12C".to_owned())]
+    );
+    assert_eq!(cache.txn_sid_to_hash.len(), 2);
+    assert_eq!(height(), committed);
+
+    // serialized as the path of the database, shared once reopened
+    let copy: api_cache::ApiCache =
+        serde_json::from_slice(&serde_json::to_vec(&cache).unwrap()).unwrap();
+    assert_eq!(copy.txn_sid_to_hash.get(&TxnSID(5)), Some("5".to_owned()));
+    assert!(Arc::ptr_eq(
+        copy.kv_state().unwrap(),
+        cache.kv_state().unwrap()
+    ));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_api_cache_rollback_to_height() {
+    let mut prng = ChaChaRng::from_entropy();
+    let mut state = LedgerState::tmp_ledger();
+    let keypair = build_keys(&mut prng);
+
+    let mut sids = vec![];
+    for height in [10, 11] {
+        let tx = create_definition_transaction(
+            &AssetTypeCode::gen_random(),
+            &keypair,
+            AssetRules::default(),
+            None,
+            state.get_block_commit_count(),
+        )
+        .unwrap();
+        state.set_tendermint_height(height);
+        let (txn_sid, _) = apply_transaction(&mut state, tx);
+        api_cache::update_api_cache(&mut state).unwrap();
+        sids.push(txn_sid);
+    }
+
+    let cache = state.api_cache.as_mut().unwrap();
+    let hash = cache.txn_sid_to_hash.get(&sids[1]).unwrap();
+    assert!(cache.height_to_next_sids.get(&11).is_some());
+
+    assert!(0 < cache.rollback_to_height(10).unwrap());
+    assert!(cache.txn_sid_to_hash.get(&sids[1]).is_none());
+    assert!(cache.txn_hash_to_sid.get(&hash).is_none());
+    assert!(cache.txn_sid_to_height.get(&sids[1]).is_none());
+    assert!(cache.height_to_next_sids.get(&11).is_none());
+    assert!(cache.height_to_time.get(&11).is_none());
+    // the entries up to the height are kept
+    assert!(cache.txn_sid_to_hash.get(&sids[0]).is_some());
+    assert_eq!(cache.txn_sid_to_height.get(&sids[0]), Some(10));
+
+    assert_eq!(cache.rollback_to_height(10).unwrap(), 0);
+    assert!(cache.rollback_to_height(11).is_err());
+}
+
+#[test]
+fn test_export_import_blocks() {
+    let mut prng = ChaChaRng::from_entropy();
+    let mut state = LedgerState::tmp_ledger();
+    let keypair = build_keys(&mut prng);
+
+    for height in [10, 11, 12] {
+        let tx = create_definition_transaction(
+            &AssetTypeCode::gen_random(),
+            &keypair,
+            AssetRules::default(),
+            None,
+            state.get_block_commit_count(),
+        )
+        .unwrap();
+        state.set_tendermint_height(height);
+        apply_transaction(&mut state, tx);
+        api_cache::update_api_cache(&mut state).unwrap();
+    }
+    let count = state.get_block_count();
+
+    let mut stream = vec![];
+    assert_eq!(
+        state.export_blocks(0..count + 5, &mut stream).unwrap(),
+        count
+    );
+
+    let reader = export::BlockReader::new(&stream[..]).unwrap();
+    assert_eq!((reader.header().start, reader.header().end), (0, count));
+    let blocks = reader.collect::<Result<Vec<_>>>().unwrap();
+    assert_eq!(blocks.len(), count);
+    assert_eq!(blocks[count - 1].height, Some(12));
+
+    let mut imported = LedgerState::tmp_ledger();
+    assert_eq!(imported.import_blocks(&stream[..]).unwrap(), count);
+    assert_eq!(imported.get_block_count(), count);
+    assert_eq!(imported.get_next_txn(), state.get_next_txn());
+    assert_eq!(imported.status.td_commit_height, 12);
+    for sid in 0..count {
+        assert_eq!(
+            imported.blocks.get(sid).unwrap().txns,
+            state.blocks.get(sid).unwrap().txns
+        );
+    }
+
+    // a truncated stream is rejected
+    let mut truncated = vec![];
+    state.export_blocks(0..count, &mut truncated).unwrap();
+    truncated.truncate(truncated.len() - 1);
+    assert!(export::BlockReader::new(&truncated[..])
+        .unwrap()
+        .any(|b| b.is_err()));
+
+    // so is a stream not starting at the local block count
+    let mut partial = vec![];
+    state.export_blocks(1..count, &mut partial).unwrap();
+    assert!(LedgerState::tmp_ledger()
+        .import_blocks(&partial[..])
+        .is_err());
+}
+
+#[test]
+fn test_replay_blocks() {
+    let mut prng = ChaChaRng::from_entropy();
+    let keypair = build_keys(&mut prng);
+
+    // `stage_height` commits the blocks as a node does,
+    // with the staking height set before each block
+    let build = |stage_height: bool| {
+        let mut state = LedgerState::tmp_ledger();
+        for height in [10, 11, 12] {
+            let tx = create_definition_transaction(
+                &AssetTypeCode::gen_random(),
+                &keypair,
+                AssetRules::default(),
+                None,
+                state.get_block_commit_count(),
+            )
+            .unwrap();
+            if stage_height {
+                state.get_staking_mut().set_custom_block_height(height);
+            }
+            apply_transaction(&mut state, tx);
+            state.set_tendermint_height(height);
+            api_cache::update_api_cache(&mut state).unwrap();
+        }
+        let mut stream = vec![];
+        let count = state.get_block_count();
+        state.export_blocks(0..count, &mut stream).unwrap();
+        (stream, count, state.get_state_commitment().0)
+    };
+
+    let (stream, count, commitment) = build(true);
+    let mut replayed = vec![];
+    let mut local = LedgerState::tmp_ledger();
+    let report = local
+        .replay_blocks(&stream[..], |sid| replayed.push(sid))
+        .unwrap();
+    assert!(report.divergence.is_none());
+    assert_eq!((report.blocks, report.txns), (count, count));
+    assert_eq!(replayed, (0..count).collect::<Vec<_>>());
+    assert_eq!(local.get_state_commitment().0, commitment);
+
+    // the pulse counts of the exporting ledger are off
+    let (stream, _, _) = build(false);
+    let report = LedgerState::tmp_ledger()
+        .replay_blocks(&stream[..], |_| {})
+        .unwrap();
+    assert_eq!(report.blocks, 0);
+    let divergence = report.divergence.unwrap();
+    assert_eq!((divergence.block_sid, divergence.height), (0, Some(10)));
+    assert!(divergence.fields.contains(&"pulse_count".to_owned()));
+    assert_ne!(divergence.expected, divergence.actual);
+}
+
+#[test]
+fn test_output_lock() {
+    let mut prng = ChaChaRng::from_entropy();
+    let mut ledger = LedgerState::tmp_ledger();
+    let alice = build_keys(&mut prng);
+    let bob = build_keys(&mut prng);
+    let code = AssetTypeCode::gen_random();
+    let art = AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType;
+
+    let tx = create_definition_transaction(
+        &code,
+        &alice,
+        AssetRules::default(),
+        None,
+        ledger.get_block_commit_count(),
+    )
+    .unwrap();
+    apply_transaction(&mut ledger, tx);
+    let tx = create_issuance_txn(&mut ledger, &code, 100, 0, art, &alice);
+    let (_, txos) = apply_transaction(&mut ledger, tx);
+
+    // the whole of `txo` from `from` to `to`, locked by `lock`,
+    // co-signed by `cosigners`
+    let transfer = |ledger: &mut LedgerState,
+                    txo: TxoSID,
+                    from: &XfrKeyPair,
+                    to: &XfrKeyPair,
+                    lock: Option<OutputLock>,
+                    cosigners: &[&XfrKeyPair]| {
+        let record = ledger.get_utxo_light(txo).unwrap().utxo.0.record;
+        let oar = open_blind_asset_record(&record.into_noah(), &None, &from.into_noah())
+            .unwrap();
+        let template = AssetRecordTemplate::with_no_asset_tracing(
+            100,
+            code.val,
+            art,
+            to.get_pk().into_noah(),
+        );
+        let output = AssetRecord::from_template_no_identity_tracing(
+            &mut ledger.get_prng(),
+            &template,
+        )
+        .unwrap();
+        let mut body = TransferAssetBody::new(
+            &mut ledger.get_prng(),
+            vec![TxoRef::Absolute(txo)],
+            &[AssetRecord::from_open_asset_record_no_asset_tracing(oar)],
+            &[output],
+            None,
+            vec![],
+            TransferType::Standard,
+        )
+        .unwrap();
+        body.outputs[0].lock = lock;
+        let mut transfer = TransferAsset::new(body).unwrap();
+        transfer.sign(from);
+        for kp in cosigners {
+            let sig = transfer.body.compute_body_signature(kp, Some(0));
+            transfer.attach_signature(sig).unwrap();
+        }
+        TxnEffect::compute_effect(Transaction::from_operation(
+            Operation::TransferAsset(transfer),
+            ledger.get_block_commit_count(),
+        ))
+        .unwrap()
+    };
+
+    // no lock before the checkpoint
+    let base = CFG.checkpoint.output_lock_height;
+    if 0 < base {
+        ledger.set_tendermint_height(base - 1);
+        let effect = transfer(
+            &mut ledger,
+            txos[0],
+            &alice,
+            &bob,
+            Some(OutputLock::Height(base + 8)),
+            &[],
+        );
+        let mut block = ledger.start_block().unwrap();
+        assert!(ledger.apply_transaction(&mut block, effect).is_err());
+        abort_block(block);
+    }
+
+    ledger.set_tendermint_height(base + 5);
+    let effect = transfer(
+        &mut ledger,
+        txos[0],
+        &alice,
+        &bob,
+        Some(OutputLock::Height(base + 8)),
+        &[],
+    );
+    let mut block = ledger.start_block().unwrap();
+    let temp_sid = ledger.apply_transaction(&mut block, effect).unwrap();
+    let (_, locked) = ledger
+        .finish_block(block)
+        .unwrap()
+        .remove(&temp_sid)
+        .unwrap();
+    assert_eq!(
+        ledger.get_utxo_light(locked[0]).unwrap().utxo.0.lock,
+        Some(OutputLock::Height(base + 8))
+    );
+
+    // not before the block of height 8 is committed
+    ledger.set_tendermint_height(base + 7);
+    let effect = transfer(&mut ledger, locked[0], &bob, &alice, None, &[]);
+    let mut block = ledger.start_block().unwrap();
+    assert!(ledger.apply_transaction(&mut block, effect).is_err());
+
+    ledger.set_tendermint_height(base + 8);
+    let effect = transfer(&mut ledger, locked[0], &bob, &alice, None, &[]);
+    let temp_sid = ledger.apply_transaction(&mut block, effect).unwrap();
+    let (_, unlocked) = ledger
+        .finish_block(block)
+        .unwrap()
+        .remove(&temp_sid)
+        .unwrap();
+    assert!(ledger.get_utxo_light(locked[0]).is_none());
+
+    // 2 co-signatures of 3 to spend a threshold output
+    let cosigners = (0..3).map(|_| build_keys(&mut prng)).collect::<Vec<_>>();
+    let rules = SignatureRules {
+        threshold: 2,
+        weights: cosigners.iter().map(|kp| (kp.get_pk(), 1)).collect(),
+    };
+    let threshold_height = CFG.checkpoint.threshold_lock_height;
+    if base + 8 < threshold_height {
+        let effect = transfer(
+            &mut ledger,
+            unlocked[0],
+            &alice,
+            &bob,
+            Some(OutputLock::Threshold(rules.clone())),
+            &[],
+        );
+        let mut block = ledger.start_block().unwrap();
+        assert!(ledger.apply_transaction(&mut block, effect).is_err());
+        abort_block(block);
+    }
+    ledger.set_tendermint_height(threshold_height.max(base + 8));
+    let effect = transfer(
+        &mut ledger,
+        unlocked[0],
+        &alice,
+        &bob,
+        Some(OutputLock::Threshold(rules)),
+        &[],
+    );
+    let mut block = ledger.start_block().unwrap();
+    let temp_sid = ledger.apply_transaction(&mut block, effect).unwrap();
+    let (_, locked) = ledger
+        .finish_block(block)
+        .unwrap()
+        .remove(&temp_sid)
+        .unwrap();
+
+    let effect = transfer(&mut ledger, locked[0], &bob, &alice, None, &[&cosigners[0]]);
+    let mut block = ledger.start_block().unwrap();
+    assert!(ledger.apply_transaction(&mut block, effect).is_err());
+
+    let effect = transfer(
+        &mut ledger,
+        locked[0],
+        &bob,
+        &alice,
+        None,
+        &[&cosigners[0], &cosigners[2]],
+    );
+    assert!(ledger.apply_transaction(&mut block, effect).is_ok());
+    ledger.finish_block(block).unwrap();
+    assert!(ledger.get_utxo_light(locked[0]).is_none());
+}
+
+#[test]
+fn test_migrate_cold_data() {
+    let mut prng = ChaChaRng::from_entropy();
+    let mut state = LedgerState::tmp_ledger();
+    let cold_dir = globutils::fresh_tmp_dir();
+    state.set_cold_store(Arc::new(tiering::DirStore::new(&cold_dir).unwrap()));
+    let keypair = build_keys(&mut prng);
+
+    let mut sids = vec![];
+    for _ in 0..3 {
+        let tx = create_definition_transaction(
+            &AssetTypeCode::gen_random(),
+            &keypair,
+            AssetRules::default(),
+            None,
+            state.get_block_commit_count(),
+        )
+        .unwrap();
+        sids.push(apply_transaction(&mut state, tx).0);
+    }
+    let count = state.get_block_count();
+    let hot = (0..count)
+        .map(|i| state.blocks.get(i).unwrap())
+        .collect::<Vec<_>>();
+
+    let stats = state.migrate_cold_data(1).unwrap();
+    assert_eq!(stats.blocks, count as u64 - 1);
+    assert_eq!(state.migrate_cold_data(1).unwrap().blocks, 0);
+
+    // the hot copies are stubs, the reads go through the cold store
+    assert!(state.blocks.get(0).unwrap().txns.is_empty());
+    for (i, block) in hot.iter().enumerate() {
+        assert_eq!(&state.get_finalized_block(i).unwrap(), block);
+    }
+    for sid in sids {
+        assert_eq!(state.get_transaction_light(sid).unwrap().tx_id, sid);
+    }
+
+    let commitment = state.get_state_commitment().0;
+    assert!(state.get_block(BlockSID(0)).unwrap().is_valid(commitment));
+}
+
+#[test]
+fn test_api_cache_validator_uptime() {
+    let mut prng = ChaChaRng::from_entropy();
+    let mut state = LedgerState::tmp_ledger();
+    let id = *build_keys(&mut prng).get_pk_ref();
+    let itv = api_cache::UPTIME_SAMPLE_ITV;
+
+    // out of the validator set at 4 * itv, then its count restarts
+    let mut samples = new_mapxnk!(format!(
+        "api_cache/test_validator_uptime/{}",
+        globutils::wallet::public_key_to_base64(&id)
+    ));
+    for (h, cnt) in [(1, 0), (2, 90), (3, 190), (5, 250), (6, 340), (7, 10)] {
+        samples.insert(h * itv, cnt);
+    }
+    state
+        .api_cache
+        .as_mut()
+        .unwrap()
+        .staking_signed_cnt_hist
+        .insert(id, samples);
+
+    let periods = state.get_validator_uptime(&id, 0, 10 * itv);
+    assert_eq!(
+        periods
+            .iter()
+            .map(|p| (p.start_height / itv, p.signed))
+            .collect::<Vec<_>>(),
+        vec![(1, 90), (2, 100), (5, 90)]
+    );
+    assert!(periods.iter().all(|p| p.blocks == itv));
+
+    let periods = state.get_validator_uptime(&id, 2 * itv, 5 * itv);
+    assert_eq!(periods.len(), 1);
+    assert_eq!(
+        (periods[0].start_height, periods[0].end_height),
+        (2 * itv, 3 * itv)
+    );
+
+    let other = *build_keys(&mut prng).get_pk_ref();
+    assert!(state.get_validator_uptime(&other, 0, 10 * itv).is_empty());
+
+    // no validator in a fresh ledger
+    assert!(state.get_validator_set().validators.is_empty());
+}
+
+#[test]
+fn test_api_cache_governance_proposals() {
+    use crate::{
+        data_model::NoReplayToken,
+        staking::ops::governance::{ByzantineKind, GovernanceOps},
+    };
+
+    let mut prng = ChaChaRng::from_entropy();
+    let mut state = LedgerState::tmp_ledger();
+    let proposer = build_keys(&mut prng);
+    let target = *build_keys(&mut prng).get_pk_ref();
+
+    let cache = state.api_cache.as_mut().unwrap();
+    for (sid, kind, custom) in [
+        (3, ByzantineKind::OffLine, None),
+        (5, ByzantineKind::Unknown, Some([1, 2])),
+    ] {
+        let op = GovernanceOps::new(
+            &[&proposer],
+            target,
+            kind,
+            custom,
+            NoReplayToken::new(&mut prng, sid),
+        )
+        .unwrap();
+        cache.add_governance_proposal(&op, TxnSID(sid as usize), sid.to_string(), sid);
+    }
+
+    let p = cache.governance_proposals.get(&0).unwrap();
+    assert_eq!((p.id, p.txn_sid, p.height), (0, TxnSID(3), 3));
+    assert_eq!(p.proposers, vec![*proposer.get_pk_ref()]);
+    assert_eq!(p.payload.byzantine_id(), &target);
+    assert_eq!(p.payload.penalty_percent(), Some([1, 10_000_000]));
+    let p = cache.governance_proposals.get(&1).unwrap();
+    assert_eq!(p.payload.penalty_percent(), Some([1, 2]));
+
+    // dropped along with their txns
+    let next = api_cache::NextSids {
+        block_count: 0,
+        txn: TxnSID(4),
+        txo: TxoSID(0),
+        atxo: ATxoSID(0),
+    };
+    assert_eq!(cache.rollback_to(4, next), 1);
+    assert_eq!(cache.governance_proposals.len(), 1);
+    assert!(cache.governance_proposals.get(&1).is_none());
+}
+
+#[test]
+fn test_asset_supply() {
+    let mut ledger = LedgerState::tmp_ledger();
+    let issuer = XfrKeyPair::generate(&mut ledger.get_prng());
+    let black_hole = XfrPublicKey::from_noah(&BLACK_HOLE_PUBKEY);
+
+    let code = AssetTypeCode::gen_random();
+    let seq_id = ledger.get_block_commit_count();
+    let tx = create_definition_transaction(
+        &code,
+        &issuer,
+        AssetRules::default().set_max_units(Some(100)).clone(),
+        None,
+        seq_id,
+    )
+    .unwrap();
+    let new_code = AssetTypeCode::from_prefix_and_raw_asset_type_code(
+        AssetTypePrefix::UserDefined,
+        &code,
+        &CFG.checkpoint,
+        ledger.get_tendermint_height(),
+    );
+    apply_transaction(&mut ledger, tx);
+    assert_eq!(ledger.get_asset_supply(&new_code).unwrap().issued, 0);
+    assert!(ledger
+        .get_asset_supply(&AssetTypeCode::gen_random())
+        .is_none());
+
+    let tx = create_issuance_txn(
+        &mut ledger,
+        &new_code,
+        60,
+        0,
+        AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+        &issuer,
+    );
+    apply_transaction(&mut ledger, tx);
+    let (tx, _) = create_issue_and_transfer_txn(
+        &mut ledger,
+        &new_code,
+        30,
+        &issuer,
+        &black_hole,
+        1,
+    );
+    apply_transaction(&mut ledger, tx);
+
+    let supply = ledger.get_asset_supply(&new_code).unwrap();
+    assert_eq!(
+        (supply.issued, supply.burned, supply.circulating),
+        (90, 30, 60)
+    );
+    assert_eq!(supply.max_units, Some(100));
+
+    // the burned units still count against the cap
+    let tx = create_issuance_txn(
+        &mut ledger,
+        &new_code,
+        11,
+        2,
+        AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType,
+        &issuer,
+    );
+    let effect = TxnEffect::compute_effect(tx).unwrap();
+    let mut block = ledger.start_block().unwrap();
+    assert!(ledger.apply_transaction(&mut block, effect).is_err());
+}
+
+#[test]
+fn test_audit_replay() {
+    let mut ledger = LedgerState::tmp_ledger();
+    let issuer = XfrKeyPair::generate(&mut ledger.get_prng());
+    let art = AssetRecordType::NonConfidentialAmount_NonConfidentialAssetType;
+
+    let code = AssetTypeCode::gen_random();
+    let seq_id = ledger.get_block_commit_count();
+    let tx = create_definition_transaction(
+        &code,
+        &issuer,
+        AssetRules::default(),
+        None,
+        seq_id,
+    )
+    .unwrap();
+    let new_code = AssetTypeCode::from_prefix_and_raw_asset_type_code(
+        AssetTypePrefix::UserDefined,
+        &code,
+        &CFG.checkpoint,
+        ledger.get_tendermint_height(),
+    );
+    apply_transaction(&mut ledger, tx);
+    for seq_num in [0, 1] {
+        let tx = create_issuance_txn(&mut ledger, &new_code, 10, seq_num, art, &issuer);
+        apply_transaction(&mut ledger, tx);
+    }
+
+    let count = ledger.get_block_count();
+    let mut audited = vec![];
+    let report = ledger.audit_replay(|sid| audited.push(sid)).unwrap();
+    assert!(report.anomalies.is_empty());
+    assert_eq!(
+        (report.blocks, report.txns, report.issuances),
+        (count, count, 2)
+    );
+    assert_eq!(audited, (0..count).collect::<Vec<_>>());
+
+    let mut stream = vec![];
+    ledger.export_blocks(0..count, &mut stream).unwrap();
+    let report = audit::audit_replay_stream(&stream[..], |_| {}).unwrap();
+    assert!(report.anomalies.is_empty());
+    assert_eq!(report.blocks, count);
+
+    // the stream must start at the first block
+    let mut partial = vec![];
+    ledger.export_blocks(1..count, &mut partial).unwrap();
+    assert!(audit::audit_replay_stream(&partial[..], |_| {}).is_err());
+
+    // the last issuance committed twice, then far after its seq_id
+    let last = ledger.get_finalized_block(count - 1).unwrap().txns;
+    let txn = last[0].tx_id;
+    let mut auditor = audit::ReplayAuditor::default();
+    for sid in 0..count {
+        let block = ledger.get_finalized_block(sid).unwrap();
+        auditor.audit_block(sid, &block.txns);
+    }
+    auditor.audit_block(count, &last);
+    auditor.audit_block(count + TRANSACTION_WINDOW_WIDTH as usize, &last);
+    let report = auditor.finish();
+    assert_eq!(report.anomalies.len(), 5);
+    let seq_id = last[0].txn.body.no_replay_token.get_seq_id();
+    assert!(report.anomalies.contains(&audit::Anomaly::IssuanceSeqNum {
+        code: new_code.to_base64(),
+        seq_num: 1,
+        prev_seq_num: 1,
+        txn,
+        block_sid: count,
+    }));
+    assert!(report
+        .anomalies
+        .contains(&audit::Anomaly::SeqIdOutOfWindow {
+            seq_id,
+            txn,
+            block_sid: count + TRANSACTION_WINDOW_WIDTH as usize,
+        }));
+    assert!(matches!(
+        report.anomalies[0],
+        audit::Anomaly::DuplicateToken { first_txn, block_sid, .. }
+            if first_txn == txn && block_sid == count
+    ));
+}
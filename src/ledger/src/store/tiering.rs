@@ -0,0 +1,227 @@
+//!
+//! Cold storage of the historical ledger data,
+//! the blocks and spent utxos older than `--ledger-hot-blocks` are moved
+//! out of fbnc into a `ColdStore`, and read through on queries.
+//!
+//! A migrated block is replaced in `LedgerState::blocks` by a stub without txns,
+//! its merkle id and state are kept so that the block proofs are unchanged.
+//! The hot copies are only dropped once the cold ones are written, and the reads
+//! fall back to the cold store whenever the hot copy is empty or missing,
+//! so an interrupted migration loses nothing.
+//!
+
+use {
+    super::LedgerState,
+    crate::data_model::{FinalizedBlock, Operation, TxoRef, TxoSID, Utxo},
+    config::abci::global_cfg::CFG,
+    ruc::*,
+    serde::{de::DeserializeOwned, Deserialize, Serialize},
+    std::{
+        fs,
+        io::ErrorKind,
+        path::{Path, PathBuf},
+        sync::Arc,
+    },
+};
+
+/// The cold data is migrated once every `MIGRATE_ITV` blocks
+pub const MIGRATE_ITV: usize = 100;
+
+/// A key-value backend for the cold data, e.g. a slower disk or an object storage
+pub trait ColdStore: Send + Sync {
+    /// Store a value, replacing the previous one if any
+    fn put(&self, key: &str, value: &[u8]) -> Result<()>;
+    /// Load a value, None if not found
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>>;
+}
+
+/// A `ColdStore` in a local directory, one file per value
+pub struct DirStore {
+    dir: PathBuf,
+}
+
+impl DirStore {
+    #[allow(missing_docs)]
+    pub fn new(dir: impl AsRef<Path>) -> Result<Self> {
+        fs::create_dir_all(dir.as_ref()).c(d!())?;
+        Ok(DirStore {
+            dir: dir.as_ref().to_owned(),
+        })
+    }
+}
+
+impl ColdStore for DirStore {
+    fn put(&self, key: &str, value: &[u8]) -> Result<()> {
+        let path = self.dir.join(key);
+        fs::create_dir_all(path.parent().c(d!())?).c(d!())?;
+
+        // write aside and rename, a reader never sees a partial value
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, value).c(d!())?;
+        fs::rename(&tmp, &path).c(d!())
+    }
+
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        match fs::read(self.dir.join(key)) {
+            Ok(v) => Ok(Some(v)),
+            Err(e) if ErrorKind::NotFound == e.kind() => Ok(None),
+            Err(e) => Err(eg!(e)),
+        }
+    }
+}
+
+/// Keys are grouped by thousands, so that no directory grows too large
+fn block_key(block_idx: usize) -> String {
+    format!("blocks/{}/{}", block_idx / 1000, block_idx)
+}
+
+fn spent_utxo_key(sid: TxoSID) -> String {
+    format!("spent_utxos/{}/{}", sid.0 / 1000, sid.0)
+}
+
+fn load<T: DeserializeOwned>(store: &dyn ColdStore, key: &str) -> Result<Option<T>> {
+    store
+        .get(key)
+        .c(d!())?
+        .map(|v| serde_json::from_slice(&v).c(d!()))
+        .transpose()
+}
+
+/// The utxos spent by the txns of a block,
+/// the same inputs the block effects consume
+fn spent_in(block: &FinalizedBlock) -> Vec<TxoSID> {
+    block
+        .txns
+        .iter()
+        .flat_map(|tx| tx.txn.body.operations.iter())
+        .flat_map(|op| match op {
+            Operation::TransferAsset(xfr) => xfr
+                .body
+                .inputs
+                .iter()
+                .filter_map(|i| match i {
+                    TxoRef::Absolute(sid) => Some(*sid),
+                    TxoRef::Relative(_) => None,
+                })
+                .collect(),
+            Operation::BarToAbar(b2a) => vec![b2a.txo_sid],
+            _ => vec![],
+        })
+        .collect()
+}
+
+/// What has been moved by a `migrate_cold_data`
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct MigrationStats {
+    pub blocks: u64,
+    pub spent_utxos: u64,
+}
+
+impl LedgerState {
+    /// Use a cold store other than the configured one
+    pub fn set_cold_store(&mut self, store: Arc<dyn ColdStore>) {
+        self.cold_store = Some(store);
+    }
+
+    /// Read a block, from the cold store if it has been migrated,
+    /// an empty hot block may be a stub
+    pub fn get_finalized_block(&self, block_idx: usize) -> Result<FinalizedBlock> {
+        let block = self.blocks.get(block_idx).c(d!())?;
+        if !block.txns.is_empty() {
+            return Ok(block);
+        }
+        match self.cold_store.as_deref() {
+            Some(store) => load(store, &block_key(block_idx))
+                .c(d!())
+                .map(|cold| cold.unwrap_or(block)),
+            None => Ok(block),
+        }
+    }
+
+    /// Read a spent utxo, from the cold store if it has been migrated
+    pub(crate) fn get_spent_utxo_through(&self, sid: TxoSID) -> Option<Utxo> {
+        self.status.get_spent_utxo(sid).or_else(|| {
+            // neither created yet nor still unspent
+            if sid >= self.status.next_txo || self.status.utxos.contains_key(&sid) {
+                return None;
+            }
+            let store = self.cold_store.as_deref()?;
+            pnk!(load(store, &spent_utxo_key(sid)))
+        })
+    }
+
+    /// Move the blocks older than `hot_blocks`, and the utxos spent in them,
+    /// to the cold store
+    pub fn migrate_cold_data(&mut self, hot_blocks: usize) -> Result<MigrationStats> {
+        let mut stats = MigrationStats::default();
+        let store = match self.cold_store.clone() {
+            Some(store) => store,
+            None => return Ok(stats),
+        };
+
+        // blocks below it have been migrated, saved along with the status
+        let start = self.status.cold_block_count;
+        let end = self.blocks.len().saturating_sub(hot_blocks);
+        if end <= start {
+            return Ok(stats);
+        }
+
+        let mut spent = vec![];
+        for block_idx in start..end {
+            let block = self.blocks.get(block_idx).c(d!())?;
+            // a stub left by an interrupted migration, already copied
+            if block.txns.is_empty() {
+                let key = block_key(block_idx);
+                if let Some(cold) = load::<FinalizedBlock>(&*store, &key).c(d!())? {
+                    spent.extend(spent_in(&cold));
+                    continue;
+                }
+            }
+            spent.extend(spent_in(&block));
+            store
+                .put(&block_key(block_idx), &serde_json::to_vec(&block).c(d!())?)
+                .c(d!())?;
+        }
+
+        spent.retain(|sid| self.status.spent_utxos.contains_key(sid));
+        for sid in spent.iter() {
+            let utxo = self.status.spent_utxos.get(sid).c(d!())?;
+            store
+                .put(&spent_utxo_key(*sid), &serde_json::to_vec(&utxo).c(d!())?)
+                .c(d!())?;
+        }
+
+        // the cold copies are complete, drop the hot ones
+        for block_idx in start..end {
+            let mut block = self.blocks.get_mut(block_idx).c(d!())?;
+            block.txns.clear();
+        }
+        for sid in spent.iter() {
+            self.status.spent_utxos.remove(sid);
+        }
+        self.status.cold_block_count = end;
+
+        stats.blocks = (end - start) as u64;
+        stats.spent_utxos = spent.len() as u64;
+        Ok(stats)
+    }
+
+    /// Migrate the cold data as configured, every `MIGRATE_ITV` blocks
+    pub(crate) fn migrate_cold_data_by_cfg(&mut self) -> Result<()> {
+        if let Some(n) = CFG.ledger_hot_blocks {
+            if 0 == self.blocks.len() % MIGRATE_ITV {
+                let stats = self.migrate_cold_data(n as usize).c(d!())?;
+                if 0 < stats.blocks {
+                    tracing::info!("migrated to the cold store: {:?}", stats);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The cold store configured by `--ledger-cold-dir`
+pub fn cold_store_from_cfg() -> Result<Option<DirStore>> {
+    CFG.ledger_cold_dir.as_ref().map(DirStore::new).transpose()
+}
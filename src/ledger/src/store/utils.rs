@@ -74,6 +74,7 @@ pub fn fra_gen_initial_tx(fra_owner_kp: &XfrKeyPair) -> Transaction {
                     id: None,
                     record: BlindAssetRecord::from_noah(&ba),
                     lien: None,
+                    lock: None,
                 },
                 None,
             )
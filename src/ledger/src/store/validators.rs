@@ -0,0 +1,138 @@
+//!
+//! The current validator set of the staking state, and the uptime
+//! of the validators derived from `ApiCache::staking_signed_cnt_hist`.
+//!
+
+use {
+    super::{api_cache::UPTIME_SAMPLE_ITV, LedgerState},
+    crate::staking::{
+        td_addr_to_string, Amount, BlockHeight, StakerMemo, TendermintAddr,
+    },
+    globutils::wallet,
+    schemars::JsonSchema,
+    serde::{Deserialize, Serialize},
+    zei::XfrPublicKey,
+};
+
+/// A validator of the current set
+#[allow(missing_docs)]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+pub struct ValidatorInfo {
+    /// tendermint address
+    pub addr: TendermintAddr,
+    /// base64 public key, the validator id
+    pub pubkey: String,
+    pub kind: String,
+    pub voting_power: Amount,
+    /// 1 for the most powerful validator
+    pub voting_power_rank: u64,
+    pub commission_rate: [u64; 2],
+    pub self_delegation: Amount,
+    /// delegated by the other delegators
+    pub delegated: Amount,
+    pub delegator_cnt: u64,
+    /// whether it signed the last block
+    pub is_online: bool,
+    /// number of blocks signed
+    pub signed_cnt: u64,
+    pub memo: StakerMemo,
+}
+
+/// The validator set at a height
+#[allow(missing_docs)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+pub struct ValidatorSet {
+    pub height: BlockHeight,
+    /// sum of the voting powers
+    pub global_power: Amount,
+    /// by decreasing voting power
+    pub validators: Vec<ValidatorInfo>,
+}
+
+/// Blocks signed by a validator between two samples, `(start, end]`
+#[allow(missing_docs)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+pub struct UptimePeriod {
+    pub start_height: BlockHeight,
+    pub end_height: BlockHeight,
+    pub signed: u64,
+    pub blocks: u64,
+}
+
+impl LedgerState {
+    /// The current validators, the ones without voting power included
+    pub fn get_validator_set(&self) -> ValidatorSet {
+        let staking = self.get_staking();
+        let vd = match staking.validator_get_current() {
+            Some(vd) => vd,
+            None => return ValidatorSet::default(),
+        };
+
+        let mut validators = vd
+            .body
+            .values()
+            .map(|v| {
+                let self_delegation = staking
+                    .delegation_get(&v.id)
+                    .and_then(|d| d.delegations.get(&v.id).copied())
+                    .unwrap_or(0);
+                ValidatorInfo {
+                    addr: td_addr_to_string(&v.td_addr),
+                    pubkey: wallet::public_key_to_base64(&v.id),
+                    kind: v.kind(),
+                    voting_power: v.td_power,
+                    voting_power_rank: 0,
+                    commission_rate: v.get_commission_rate(),
+                    self_delegation,
+                    delegated: v.delegators.values().sum(),
+                    delegator_cnt: v.delegators.len() as u64,
+                    is_online: v.signed_last_block,
+                    signed_cnt: v.signed_cnt,
+                    memo: v.memo.clone(),
+                }
+            })
+            .collect::<Vec<_>>();
+        validators.sort_by(|a, b| b.voting_power.cmp(&a.voting_power));
+        for (i, v) in validators.iter_mut().enumerate() {
+            v.voting_power_rank = 1 + i as u64;
+        }
+
+        ValidatorSet {
+            height: staking.cur_height(),
+            global_power: staking.validator_global_power(),
+            validators,
+        }
+    }
+
+    /// Uptime of the validator `id` between the samples in [start, end],
+    /// the periods it was out of the validator set are skipped
+    pub fn get_validator_uptime(
+        &self,
+        id: &XfrPublicKey,
+        start: BlockHeight,
+        end: BlockHeight,
+    ) -> Vec<UptimePeriod> {
+        let samples = match self
+            .api_cache
+            .as_ref()
+            .and_then(|api| api.staking_signed_cnt_hist.get(id))
+        {
+            Some(samples) => samples
+                .iter()
+                .filter(|(h, _)| start <= *h && *h <= end)
+                .collect::<Vec<_>>(),
+            None => return vec![],
+        };
+
+        samples
+            .windows(2)
+            .filter(|w| w[1].0 - w[0].0 == UPTIME_SAMPLE_ITV && w[0].1 <= w[1].1)
+            .map(|w| UptimePeriod {
+                start_height: w[0].0,
+                end_height: w[1].0,
+                signed: w[1].1 - w[0].1,
+                blocks: UPTIME_SAMPLE_ITV,
+            })
+            .collect()
+    }
+}